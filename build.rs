@@ -29,26 +29,117 @@ fn output_file_for_shader_file(shader_file_path: &Path) -> Result<PathBuf> {
     Ok(parent.join(Path::new(&output_file_name)))
 }
 
-fn needs_rebuild(shader_file_path: &Path, output_path: &Path) -> Result<bool> {
+/// The directories searched for `#include` files after the including
+/// shader's own directory.
+///
+/// Directories are provided with the CCTHW_SHADER_INCLUDE_DIRS environment
+/// variable, using the platform's path separator.
+fn include_dirs() -> Vec<PathBuf> {
+    println!("cargo:rerun-if-env-changed=CCTHW_SHADER_INCLUDE_DIRS");
+    std::env::var_os("CCTHW_SHADER_INCLUDE_DIRS")
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default()
+}
+
+/// Preprocessor definitions passed to every shader.
+///
+/// Definitions are read from the CCTHW_SHADER_DEFINES environment variable
+/// as a semicolon-separated list like `PARTICLE_COUNT=1024;USE_FOG`.
+fn shader_defines() -> Vec<String> {
+    println!("cargo:rerun-if-env-changed=CCTHW_SHADER_DEFINES");
+    std::env::var("CCTHW_SHADER_DEFINES")
+        .unwrap_or_default()
+        .split(';')
+        .map(str::trim)
+        .filter(|define| !define.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Find every file included by the shader, including nested includes.
+fn find_includes(
+    shader_file_path: &Path,
+    include_dirs: &[PathBuf],
+    includes: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let source = std::fs::read_to_string(shader_file_path)?;
+    let parent = shader_file_path.parent().unwrap_or_else(|| Path::new("."));
+    for line in source.lines() {
+        let line = line.trim();
+        if !line.starts_with("#include") {
+            continue;
+        }
+        let name = line["#include".len()..]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '<' || c == '>');
+        let resolved = std::iter::once(parent.to_path_buf())
+            .chain(include_dirs.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|path| path.exists())
+            .with_context(|| {
+                format!("Unable to resolve {name:?} in {shader_file_path:?}")
+            })?;
+        if !includes.contains(&resolved) {
+            includes.push(resolved.clone());
+            find_includes(&resolved, include_dirs, includes)?;
+        }
+    }
+    Ok(())
+}
+
+fn needs_rebuild(
+    shader_file_path: &Path,
+    includes: &[PathBuf],
+    output_path: &Path,
+) -> Result<bool> {
     if !output_path.try_exists()? {
         println!("PATH DOESNT EXIST {}", output_path.to_str().unwrap());
         return Ok(true);
     }
 
-    let shader_last_modified_time =
-        std::fs::metadata(shader_file_path)?.modified()?;
     let output_last_modified_time =
         std::fs::metadata(output_path)?.modified()?;
+    for path in std::iter::once(shader_file_path)
+        .chain(includes.iter().map(|p| p.as_path()))
+    {
+        let last_modified_time = std::fs::metadata(path)?.modified()?;
+        if last_modified_time > output_last_modified_time {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
 
-    let result = shader_last_modified_time > output_last_modified_time;
-    Ok(result)
+/// Returns true when the shader defines differ from the last build, in
+/// which case every shader must be rebuilt.
+fn defines_changed(defines: &[String]) -> Result<bool> {
+    let out_dir = std::env::var("OUT_DIR")?;
+    let stamp_path = Path::new(&out_dir).join("shader_defines");
+    let current = defines.join(";");
+    let previous = std::fs::read_to_string(&stamp_path).unwrap_or_default();
+    std::fs::write(&stamp_path, &current)?;
+    Ok(current != previous)
 }
 
-fn compile_shader(shader_file_path: &Path) -> Result<()> {
+fn compile_shader(
+    shader_file_path: &Path,
+    include_dirs: &[PathBuf],
+    defines: &[String],
+    force_rebuild: bool,
+) -> Result<()> {
     let output_path = output_file_for_shader_file(shader_file_path)?;
 
+    let mut includes = vec![];
+    find_includes(shader_file_path, include_dirs, &mut includes)?;
+    for include in &includes {
+        println!("cargo:rerun-if-changed={}", include.to_str().unwrap());
+    }
+
     let shader_path_str = shader_file_path.to_str().unwrap();
-    if !needs_rebuild(shader_file_path, &output_path).unwrap_or(true) {
+    if !force_rebuild
+        && !needs_rebuild(shader_file_path, &includes, &output_path)
+            .unwrap_or(true)
+    {
         println!(
             "cargo:warning=Skip rebuild for {} because it's up to date",
             shader_file_path.to_str().unwrap()
@@ -57,13 +148,19 @@ fn compile_shader(shader_file_path: &Path) -> Result<()> {
         return Ok(());
     }
 
-    let output = Command::new("glslc")
+    let mut command = Command::new("glslc");
+    command
         .arg(shader_file_path.to_str().unwrap())
         .arg("-o")
         .arg(&output_path)
-        .arg("--target-env=vulkan1.3")
-        .output()
-        .unwrap();
+        .arg("--target-env=vulkan1.3");
+    for include_dir in include_dirs {
+        command.arg("-I").arg(include_dir);
+    }
+    for define in defines {
+        command.arg(format!("-D{define}"));
+    }
+    let output = command.output().unwrap();
 
     if !output.status.success() {
         let stdout = String::from_utf8(output.stdout).unwrap();
@@ -91,9 +188,21 @@ fn main() -> Result<()> {
         .chain(glob::glob("./examples/**/*.comp")?)
         .chain(glob::glob("./src/**/*.frag")?)
         .chain(glob::glob("./src/**/*.vert")?)
-        .chain(glob::glob("./src/**/*.comp")?);
+        .chain(glob::glob("./src/**/*.comp")?)
+        .chain(glob::glob("./examples/**/*.rgen")?)
+        .chain(glob::glob("./examples/**/*.rmiss")?)
+        .chain(glob::glob("./examples/**/*.rchit")?)
+        .chain(glob::glob("./examples/**/*.rahit")?);
+    let include_dirs = include_dirs();
+    let defines = shader_defines();
+    let force_rebuild = defines_changed(&defines)?;
     for path_entry in all_paths {
-        compile_shader(path_entry?.as_path())?;
+        compile_shader(
+            path_entry?.as_path(),
+            &include_dirs,
+            &defines,
+            force_rebuild,
+        )?;
     }
 
     Ok(())
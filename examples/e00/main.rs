@@ -1,27 +1,27 @@
 use {
     anyhow::Result,
-    ccthw::application::{Application, GlfwWindow, State},
+    ccthw::application::{
+        Action, Application, Key, State, WindowBackend, WindowEvent,
+    },
 };
 
 struct AppLifecycleExample;
 
 impl State for AppLifecycleExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
+    fn new(_window: &mut dyn WindowBackend) -> Result<Self> {
         Ok(Self)
     }
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -31,5 +31,5 @@ impl State for AppLifecycleExample {
 }
 
 fn main() -> Result<()> {
-    Application::<AppLifecycleExample>::run()
+    Application::<AppLifecycleExample>::run_with_args()
 }
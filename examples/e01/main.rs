@@ -1,7 +1,9 @@
 use {
     anyhow::Result,
     ccthw::{
-        application::{Application, GlfwWindow, State},
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
+        },
         graphics::vulkan_api::RenderDevice,
     },
     ccthw_ash_instance::PhysicalDeviceFeatures,
@@ -13,8 +15,7 @@ struct RenderDeviceExample {
 }
 
 impl State for RenderDeviceExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             window.create_default_render_device(
                 PhysicalDeviceFeatures::default(),
@@ -30,15 +31,14 @@ impl State for RenderDeviceExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -48,5 +48,5 @@ impl State for RenderDeviceExample {
 }
 
 fn main() -> Result<()> {
-    Application::<RenderDeviceExample>::run()
+    Application::<RenderDeviceExample>::run_with_args()
 }
@@ -2,10 +2,13 @@ use {
     anyhow::Result,
     ash::vk,
     ccthw::{
-        application::{Application, GlfwWindow, State},
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
+        },
         graphics::vulkan_api::{
             raii, RenderDevice, Swapchain, SwapchainStatus,
         },
+        timing::Clock,
     },
     ccthw_ash_instance::{PhysicalDeviceFeatures, VulkanHandle},
     std::sync::Arc,
@@ -21,9 +24,7 @@ struct CreateSwapchainExample {
 }
 
 impl State for CreateSwapchainExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
-
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             // SAFE because the render device is destroyed when state is
             // dropped.
@@ -76,15 +77,14 @@ impl State for CreateSwapchainExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -92,7 +92,11 @@ impl State for CreateSwapchainExample {
         Ok(())
     }
 
-    fn update(&mut self, window: &mut GlfwWindow) -> Result<()> {
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         if self.swapchain_needs_rebuild {
             return self.rebuild_swapchain(window);
         }
@@ -107,6 +111,12 @@ impl State for CreateSwapchainExample {
             )?;
             match result {
                 SwapchainStatus::Index(index) => index,
+                SwapchainStatus::Suboptimal(index) => {
+                    // Still present the acquired image so the acquire
+                    // semaphore is waited on, then rebuild next update.
+                    self.swapchain_needs_rebuild = true;
+                    index
+                }
                 SwapchainStatus::NeedsRebuild => {
                     self.swapchain_needs_rebuild = true;
                     return Ok(());
@@ -227,7 +237,7 @@ impl CreateSwapchainExample {
 
     /// Rebuild the swapchain (typically because the current swapchain is
     /// out of date.
-    fn rebuild_swapchain(&mut self, window: &GlfwWindow) -> Result<()> {
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
         // Wait for all pending operations to complete before rebuilding the
         // swapchain.
         unsafe { self.render_device.device().device_wait_idle()? };
@@ -263,5 +273,5 @@ impl Drop for CreateSwapchainExample {
 }
 
 fn main() -> Result<()> {
-    Application::<CreateSwapchainExample>::run()
+    Application::<CreateSwapchainExample>::run_with_args()
 }
@@ -2,10 +2,13 @@ use {
     anyhow::Result,
     ash::vk,
     ccthw::{
-        application::{Application, GlfwWindow, State},
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
+        },
         graphics::vulkan_api::{
             FrameStatus, FramesInFlight, RenderDevice, Swapchain,
         },
+        timing::Clock,
     },
     ccthw_ash_instance::PhysicalDeviceFeatures,
     std::sync::Arc,
@@ -17,9 +20,7 @@ struct FramesInFlightExample {
 }
 
 impl State for FramesInFlightExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
-
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             // SAFE because the render device is destroyed when state is
             // dropped.
@@ -47,15 +48,14 @@ impl State for FramesInFlightExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -63,7 +63,11 @@ impl State for FramesInFlightExample {
         Ok(())
     }
 
-    fn update(&mut self, window: &mut GlfwWindow) -> Result<()> {
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         let frame = match self.frames_in_flight.acquire_frame()? {
             FrameStatus::FrameAcquired(frame) => frame,
             FrameStatus::SwapchainNeedsRebuild => {
@@ -116,7 +120,7 @@ impl FramesInFlightExample {
 
     /// Rebuild the swapchain (typically because the current swapchain is
     /// out of date.
-    fn rebuild_swapchain(&mut self, window: &GlfwWindow) -> Result<()> {
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
         unsafe {
             self.frames_in_flight
                 .stall_and_rebuild_swapchain(window.get_framebuffer_size())?
@@ -126,5 +130,5 @@ impl FramesInFlightExample {
 }
 
 fn main() -> Result<()> {
-    Application::<FramesInFlightExample>::run()
+    Application::<FramesInFlightExample>::run_with_args()
 }
@@ -2,25 +2,30 @@ use {
     anyhow::Result,
     ash::vk,
     ccthw::{
-        application::{Application, GlfwWindow, State},
-        graphics::vulkan_api::{
-            ColorPass, FrameStatus, FramesInFlight, RenderDevice,
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
         },
+        graphics::{
+            vulkan_api::{
+                ColorPass, FrameStatus, FramesInFlight, RenderDevice,
+                Swapchain, SwapchainChanges,
+            },
+            GraphicsError,
+        },
+        timing::Clock,
     },
     ccthw_ash_instance::PhysicalDeviceFeatures,
-    std::sync::Arc,
+    std::{cell::RefCell, rc::Rc, sync::Arc},
 };
 
 struct RenderPassExample {
     frames_in_flight: FramesInFlight,
-    color_pass: ColorPass,
+    color_pass: Rc<RefCell<ColorPass>>,
     render_device: Arc<RenderDevice>,
 }
 
 impl State for RenderPassExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
-
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             // SAFE because the render device is destroyed when state is
             // dropped.
@@ -31,7 +36,7 @@ impl State for RenderPassExample {
             window.create_default_render_device(device_features)?
         };
 
-        let frames_in_flight = unsafe {
+        let mut frames_in_flight = unsafe {
             // SAFE because the render device is destroyed when state is dropped
             FramesInFlight::new(
                 render_device.clone(),
@@ -40,9 +45,23 @@ impl State for RenderPassExample {
             )?
         };
 
-        let color_pass = unsafe {
+        let color_pass = Rc::new(RefCell::new(unsafe {
             ColorPass::new(render_device.clone(), frames_in_flight.swapchain())?
-        };
+        }));
+        frames_in_flight.on_swapchain_rebuilt({
+            let color_pass = color_pass.clone();
+            move |swapchain: &Swapchain,
+                  _changes: &SwapchainChanges|
+                  -> Result<(), GraphicsError> {
+                // SAFE because dependents are only rebuilt after every frame
+                // has finished executing.
+                unsafe {
+                    color_pass
+                        .borrow_mut()
+                        .rebuild_for_swapchain(swapchain, None)
+                }
+            }
+        });
 
         Ok(Self {
             color_pass,
@@ -53,15 +72,14 @@ impl State for RenderPassExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -69,7 +87,11 @@ impl State for RenderPassExample {
         Ok(())
     }
 
-    fn update(&mut self, window: &mut GlfwWindow) -> Result<()> {
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         let frame = match self.frames_in_flight.acquire_frame()? {
             FrameStatus::FrameAcquired(frame) => frame,
             FrameStatus::SwapchainNeedsRebuild => {
@@ -79,6 +101,7 @@ impl State for RenderPassExample {
 
         unsafe {
             self.color_pass
+                .borrow()
                 .begin_render_pass_inline(&frame, [0.5, 0.0, 0.0, 1.0]);
 
             // draw commands go here
@@ -96,22 +119,17 @@ impl State for RenderPassExample {
 
 impl RenderPassExample {
     /// Rebuild the swapchain (typically because the current swapchain is
-    /// out of date.
-    fn rebuild_swapchain(&mut self, window: &GlfwWindow) -> Result<()> {
+    /// out of date. The color pass is rebuilt by the dependent registered in
+    /// `new`.
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
         unsafe {
             self.frames_in_flight
                 .stall_and_rebuild_swapchain(window.get_framebuffer_size())?;
-
-            self.color_pass = ColorPass::new(
-                self.render_device.clone(),
-                self.frames_in_flight.swapchain(),
-            )?;
-        };
-
+        }
         Ok(())
     }
 }
 
 fn main() -> Result<()> {
-    Application::<RenderPassExample>::run()
+    Application::<RenderPassExample>::run_with_args()
 }
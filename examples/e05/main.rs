@@ -2,13 +2,20 @@ use {
     anyhow::Result,
     ash::vk,
     ccthw::{
-        application::{Application, GlfwWindow, State},
-        graphics::vulkan_api::{
-            raii, ColorPass, FrameStatus, FramesInFlight, RenderDevice,
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
         },
+        graphics::{
+            vulkan_api::{
+                raii, ColorPass, FrameStatus, FramesInFlight, RenderDevice,
+                Swapchain, SwapchainChanges,
+            },
+            GraphicsError,
+        },
+        timing::Clock,
     },
     ccthw_ash_instance::PhysicalDeviceFeatures,
-    std::sync::Arc,
+    std::{cell::RefCell, rc::Rc, sync::Arc},
 };
 
 mod pipeline;
@@ -18,16 +25,14 @@ use self::pipeline::create_pipeline;
 struct FirstTriangleExample {
     frames_in_flight: FramesInFlight,
     _descriptor_set_layout: raii::DescriptorSetLayout,
-    pipeline_layout: raii::PipelineLayout,
-    pipeline: raii::Pipeline,
-    color_pass: ColorPass,
+    pipeline_layout: Rc<raii::PipelineLayout>,
+    pipeline: Rc<RefCell<raii::Pipeline>>,
+    color_pass: Rc<RefCell<ColorPass>>,
     render_device: Arc<RenderDevice>,
 }
 
 impl State for FirstTriangleExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
-
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             // SAFE because the render device is destroyed when state is
             // dropped.
@@ -38,7 +43,7 @@ impl State for FirstTriangleExample {
             window.create_default_render_device(device_features)?
         };
 
-        let frames_in_flight = unsafe {
+        let mut frames_in_flight = unsafe {
             // SAFE because the render device is destroyed when state is dropped
             FramesInFlight::new(
                 render_device.clone(),
@@ -74,6 +79,43 @@ impl State for FirstTriangleExample {
             )?
         };
 
+        let pipeline_layout = Rc::new(pipeline_layout);
+        let color_pass = Rc::new(RefCell::new(color_pass));
+        let pipeline = Rc::new(RefCell::new(pipeline));
+        frames_in_flight.on_swapchain_rebuilt({
+            let render_device = render_device.clone();
+            let pipeline_layout = pipeline_layout.clone();
+            let color_pass = color_pass.clone();
+            let pipeline = pipeline.clone();
+            move |swapchain: &Swapchain,
+                  changes: &SwapchainChanges|
+                  -> Result<(), GraphicsError> {
+                // SAFE because dependents are only rebuilt after every frame
+                // has finished executing.
+                unsafe {
+                    let mut color_pass = color_pass.borrow_mut();
+                    color_pass.rebuild_for_swapchain(swapchain, None)?;
+
+                    // The render pass, and the pipeline built against it,
+                    // only change when the swapchain format changes.
+                    if changes.format_changed {
+                        *pipeline.borrow_mut() = create_pipeline(
+                            render_device.clone(),
+                            include_bytes!(
+                                "./shaders/static_triangle.vert.spv"
+                            ),
+                            include_bytes!(
+                                "./shaders/static_triangle.frag.spv"
+                            ),
+                            &pipeline_layout,
+                            color_pass.render_pass(),
+                        )?;
+                    }
+                }
+                Ok(())
+            }
+        });
+
         Ok(Self {
             _descriptor_set_layout: descriptor_set_layout,
             pipeline_layout,
@@ -86,15 +128,14 @@ impl State for FirstTriangleExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -102,7 +143,11 @@ impl State for FirstTriangleExample {
         Ok(())
     }
 
-    fn update(&mut self, window: &mut GlfwWindow) -> Result<()> {
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         let frame = match self.frames_in_flight.acquire_frame()? {
             FrameStatus::FrameAcquired(frame) => frame,
             FrameStatus::SwapchainNeedsRebuild => {
@@ -112,13 +157,14 @@ impl State for FirstTriangleExample {
 
         unsafe {
             self.color_pass
+                .borrow()
                 .begin_render_pass_inline(&frame, [0.2, 0.2, 0.3, 1.0]);
 
             // draw commands go here
             self.render_device.device().cmd_bind_pipeline(
                 frame.command_buffer(),
                 vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline.raw(),
+                self.pipeline.borrow().raw(),
             );
             let vk::Extent2D { width, height } =
                 self.frames_in_flight.swapchain().extent();
@@ -163,30 +209,17 @@ impl State for FirstTriangleExample {
 
 impl FirstTriangleExample {
     /// Rebuild the swapchain (typically because the current swapchain is
-    /// out of date.
-    fn rebuild_swapchain(&mut self, window: &GlfwWindow) -> Result<()> {
+    /// out of date. The color pass and pipeline are rebuilt by the
+    /// dependents registered in `new`.
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
         unsafe {
             self.frames_in_flight
                 .stall_and_rebuild_swapchain(window.get_framebuffer_size())?;
-
-            self.color_pass = ColorPass::new(
-                self.render_device.clone(),
-                self.frames_in_flight.swapchain(),
-            )?;
-
-            self.pipeline = create_pipeline(
-                self.render_device.clone(),
-                include_bytes!("./shaders/static_triangle.vert.spv"),
-                include_bytes!("./shaders/static_triangle.frag.spv"),
-                &self.pipeline_layout,
-                self.color_pass.render_pass(),
-            )?;
-        };
-
+        }
         Ok(())
     }
 }
 
 fn main() -> Result<()> {
-    Application::<FirstTriangleExample>::run()
+    Application::<FirstTriangleExample>::run_with_args()
 }
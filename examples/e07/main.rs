@@ -2,14 +2,21 @@ use {
     anyhow::Result,
     ash::vk,
     ccthw::{
-        application::{Application, GlfwWindow, State},
-        graphics::vulkan_api::{
-            raii, ColorPass, FrameStatus, FramesInFlight,
-            OneTimeSubmitCommandBuffer, RenderDevice,
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
         },
+        graphics::{
+            vulkan_api::{
+                raii, ColorPass, FrameStatus, FramesInFlight, MappedBuffer,
+                OneTimeSubmitCommandBuffer, RenderDevice, Swapchain,
+                SwapchainChanges,
+            },
+            GraphicsError,
+        },
+        timing::Clock,
     },
     ccthw_ash_instance::PhysicalDeviceFeatures,
-    std::sync::Arc,
+    std::{cell::RefCell, rc::Rc, sync::Arc},
 };
 
 mod pipeline;
@@ -32,23 +39,21 @@ struct TextureExample {
     _sampler: raii::Sampler,
 
     // Vertex resources
-    _buffer: raii::Buffer,
+    _buffer: MappedBuffer<Vertex>,
 
     // Descriptor Set bindigs
     descriptor_pool: raii::DescriptorPool,
     _descriptor_set_layout: raii::DescriptorSetLayout,
 
     // Pipeline / Per-Frame resources
-    pipeline_layout: raii::PipelineLayout,
-    pipeline: raii::Pipeline,
-    color_pass: ColorPass,
+    pipeline_layout: Rc<raii::PipelineLayout>,
+    pipeline: Rc<RefCell<raii::Pipeline>>,
+    color_pass: Rc<RefCell<ColorPass>>,
     render_device: Arc<RenderDevice>,
 }
 
 impl State for TextureExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
-
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             // SAFE because the render device is destroyed when state is
             // dropped.
@@ -59,7 +64,7 @@ impl State for TextureExample {
             window.create_default_render_device(device_features)?
         };
 
-        let frames_in_flight = unsafe {
+        let mut frames_in_flight = unsafe {
             // SAFE because the render device is destroyed when state is dropped
             FramesInFlight::new(
                 render_device.clone(),
@@ -111,21 +116,16 @@ impl State for TextureExample {
             )?
         };
 
-        let buffer = unsafe {
-            let create_info = vk::BufferCreateInfo {
-                size: (std::mem::size_of::<Vertex>() * 6) as u64,
-                usage: vk::BufferUsageFlags::STORAGE_BUFFER,
-                ..vk::BufferCreateInfo::default()
-            };
-            raii::Buffer::new(
+        let mut buffer = unsafe {
+            MappedBuffer::<Vertex>::new(
                 render_device.clone(),
-                &create_info,
+                6,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
                 vk::MemoryPropertyFlags::HOST_VISIBLE
                     | vk::MemoryPropertyFlags::HOST_COHERENT,
             )?
         };
 
-        let ptr = unsafe { buffer.allocation().map(render_device.device())? };
         let vertices = [
             // top triangle
             Vertex {
@@ -154,9 +154,7 @@ impl State for TextureExample {
                 uv: [0.0, 0.0],
             },
         ];
-        unsafe {
-            std::ptr::write_unaligned(ptr as *mut [Vertex; 6], vertices);
-        };
+        buffer.write(&vertices);
 
         let img =
             image::io::Reader::open("examples/e07/my_example_texture.png")?
@@ -378,7 +376,7 @@ impl State for TextureExample {
             let buffer_info = vk::DescriptorBufferInfo {
                 buffer: buffer.raw(),
                 offset: 0,
-                range: buffer.allocation().size_in_bytes(),
+                range: buffer.size_in_bytes(),
             };
             let image_info = vk::DescriptorImageInfo {
                 sampler: sampler.raw(),
@@ -413,6 +411,43 @@ impl State for TextureExample {
             );
         };
 
+        let pipeline_layout = Rc::new(pipeline_layout);
+        let color_pass = Rc::new(RefCell::new(color_pass));
+        let pipeline = Rc::new(RefCell::new(pipeline));
+        frames_in_flight.on_swapchain_rebuilt({
+            let render_device = render_device.clone();
+            let pipeline_layout = pipeline_layout.clone();
+            let color_pass = color_pass.clone();
+            let pipeline = pipeline.clone();
+            move |swapchain: &Swapchain,
+                  changes: &SwapchainChanges|
+                  -> Result<(), GraphicsError> {
+                // SAFE because dependents are only rebuilt after every frame
+                // has finished executing.
+                unsafe {
+                    let mut color_pass = color_pass.borrow_mut();
+                    color_pass.rebuild_for_swapchain(swapchain, None)?;
+
+                    // The render pass, and the pipeline built against it,
+                    // only change when the swapchain format changes.
+                    if changes.format_changed {
+                        *pipeline.borrow_mut() = create_pipeline(
+                            render_device.clone(),
+                            include_bytes!(
+                                "./shaders/static_triangle.vert.spv"
+                            ),
+                            include_bytes!(
+                                "./shaders/static_triangle.frag.spv"
+                            ),
+                            &pipeline_layout,
+                            color_pass.render_pass(),
+                        )?;
+                    }
+                }
+                Ok(())
+            }
+        });
+
         Ok(Self {
             _image: image,
             _image_view: image_view,
@@ -431,15 +466,14 @@ impl State for TextureExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -447,7 +481,11 @@ impl State for TextureExample {
         Ok(())
     }
 
-    fn update(&mut self, window: &mut GlfwWindow) -> Result<()> {
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         let frame = match self.frames_in_flight.acquire_frame()? {
             FrameStatus::FrameAcquired(frame) => frame,
             FrameStatus::SwapchainNeedsRebuild => {
@@ -457,13 +495,14 @@ impl State for TextureExample {
 
         unsafe {
             self.color_pass
+                .borrow()
                 .begin_render_pass_inline(&frame, [0.2, 0.2, 0.3, 1.0]);
 
             // draw commands go here
             self.render_device.device().cmd_bind_pipeline(
                 frame.command_buffer(),
                 vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline.raw(),
+                self.pipeline.borrow().raw(),
             );
             let vk::Extent2D { width, height } =
                 self.frames_in_flight.swapchain().extent();
@@ -516,30 +555,17 @@ impl State for TextureExample {
 
 impl TextureExample {
     /// Rebuild the swapchain (typically because the current swapchain is
-    /// out of date.
-    fn rebuild_swapchain(&mut self, window: &GlfwWindow) -> Result<()> {
+    /// out of date. The color pass and pipeline are rebuilt by the
+    /// dependents registered in `new`.
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
         unsafe {
             self.frames_in_flight
                 .stall_and_rebuild_swapchain(window.get_framebuffer_size())?;
-
-            self.color_pass = ColorPass::new(
-                self.render_device.clone(),
-                self.frames_in_flight.swapchain(),
-            )?;
-
-            self.pipeline = create_pipeline(
-                self.render_device.clone(),
-                include_bytes!("./shaders/static_triangle.vert.spv"),
-                include_bytes!("./shaders/static_triangle.frag.spv"),
-                &self.pipeline_layout,
-                self.color_pass.render_pass(),
-            )?;
-        };
-
+        }
         Ok(())
     }
 }
 
 fn main() -> Result<()> {
-    Application::<TextureExample>::run()
+    Application::<TextureExample>::run_with_args()
 }
@@ -2,28 +2,33 @@ use {
     anyhow::Result,
     ash::vk,
     ccthw::{
-        application::{Application, GlfwWindow, State},
-        graphics::vulkan_api::{
-            BindlessTriangles, BindlessVertex, ColorPass, FrameStatus,
-            FramesInFlight, RenderDevice, TextureLoader,
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
         },
+        graphics::{
+            vulkan_api::{
+                BindlessTriangles, BindlessVertex, ColorPass, FrameStatus,
+                FramesInFlight, RenderDevice, Swapchain, SwapchainChanges,
+                TextureLoader,
+            },
+            GraphicsError,
+        },
+        timing::Clock,
     },
     ccthw_ash_instance::PhysicalDeviceFeatures,
-    std::sync::Arc,
+    std::{cell::RefCell, rc::Rc, sync::Arc},
 };
 
 struct BindlessTrianglesExample {
     vertices: Vec<BindlessVertex>,
     frames_in_flight: FramesInFlight,
-    color_pass: ColorPass,
-    bindless_triangles: BindlessTriangles,
+    color_pass: Rc<RefCell<ColorPass>>,
+    bindless_triangles: Rc<RefCell<BindlessTriangles>>,
     render_device: Arc<RenderDevice>,
 }
 
 impl State for BindlessTrianglesExample {
-    fn new(window: &mut GlfwWindow) -> Result<Self> {
-        window.set_key_polling(true);
-
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
         let render_device = unsafe {
             // SAFE because the render device is destroyed when state is
             // dropped.
@@ -36,6 +41,9 @@ impl State for BindlessTrianglesExample {
             device_features
                 .descriptor_indexing_features_mut()
                 .shader_sampled_image_array_non_uniform_indexing = vk::TRUE;
+            device_features
+                .descriptor_indexing_features_mut()
+                .shader_storage_buffer_array_non_uniform_indexing = vk::TRUE;
             device_features
                 .descriptor_indexing_features_mut()
                 .runtime_descriptor_array = vk::TRUE;
@@ -43,7 +51,7 @@ impl State for BindlessTrianglesExample {
             window.create_default_render_device(device_features)?
         };
 
-        let frames_in_flight = unsafe {
+        let mut frames_in_flight = unsafe {
             // SAFE because the render device is destroyed when state is dropped
             FramesInFlight::new(
                 render_device.clone(),
@@ -78,6 +86,32 @@ impl State for BindlessTrianglesExample {
             )?
         };
 
+        // The vertex buffers and textures don't depend on the swapchain, so
+        // only the pipeline is rebuilt, and only when the render pass was
+        // replaced.
+        let color_pass = Rc::new(RefCell::new(color_pass));
+        let bindless_triangles = Rc::new(RefCell::new(bindless_triangles));
+        frames_in_flight.on_swapchain_rebuilt({
+            let color_pass = color_pass.clone();
+            let bindless_triangles = bindless_triangles.clone();
+            move |swapchain: &Swapchain,
+                  changes: &SwapchainChanges|
+                  -> Result<(), GraphicsError> {
+                // SAFE because dependents are only rebuilt after every frame
+                // has finished executing.
+                unsafe {
+                    let mut color_pass = color_pass.borrow_mut();
+                    color_pass.rebuild_for_swapchain(swapchain, None)?;
+                    if changes.format_changed {
+                        bindless_triangles
+                            .borrow_mut()
+                            .rebuild_pipeline(color_pass.render_pass())?;
+                    }
+                }
+                Ok(())
+            }
+        });
+
         Ok(Self {
             vertices: Vec::with_capacity(10_000),
             frames_in_flight,
@@ -89,15 +123,14 @@ impl State for BindlessTrianglesExample {
 
     fn handle_event(
         &mut self,
-        window: &mut GlfwWindow,
-        window_event: glfw::WindowEvent,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
     ) -> Result<()> {
-        use glfw::{Action, Key, WindowEvent};
         match window_event {
-            WindowEvent::Key(Key::Space, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Space, Action::Release) => {
                 window.toggle_fullscreen()?;
             }
-            WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+            WindowEvent::Key(Key::Escape, Action::Release) => {
                 window.set_should_close(true);
             }
             _ => (),
@@ -105,7 +138,11 @@ impl State for BindlessTrianglesExample {
         Ok(())
     }
 
-    fn update(&mut self, window: &mut GlfwWindow) -> Result<()> {
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         let frame = match self.frames_in_flight.acquire_frame()? {
             FrameStatus::FrameAcquired(frame) => frame,
             FrameStatus::SwapchainNeedsRebuild => {
@@ -172,12 +209,14 @@ impl State for BindlessTrianglesExample {
 
         unsafe {
             self.color_pass
+                .borrow()
                 .begin_render_pass_inline(&frame, [0.2, 0.2, 0.3, 1.0]);
 
-            self.bindless_triangles
+            let mut bindless_triangles = self.bindless_triangles.borrow_mut();
+            bindless_triangles
                 .write_vertices_for_frame(&frame, &self.vertices)?;
 
-            self.bindless_triangles.draw_vertices(
+            bindless_triangles.draw_vertices(
                 &frame,
                 self.frames_in_flight.swapchain().extent(),
             )?;
@@ -195,22 +234,17 @@ impl State for BindlessTrianglesExample {
 
 impl BindlessTrianglesExample {
     /// Rebuild the swapchain (typically because the current swapchain is
-    /// out of date.
-    fn rebuild_swapchain(&mut self, window: &GlfwWindow) -> Result<()> {
+    /// out of date. The color pass and pipeline are rebuilt by the
+    /// dependents registered in `new`.
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
         unsafe {
             self.frames_in_flight
                 .stall_and_rebuild_swapchain(window.get_framebuffer_size())?;
-
-            self.color_pass = ColorPass::new(
-                self.render_device.clone(),
-                self.frames_in_flight.swapchain(),
-            )?;
-        };
-
+        }
         Ok(())
     }
 }
 
 fn main() -> Result<()> {
-    Application::<BindlessTrianglesExample>::run()
+    Application::<BindlessTrianglesExample>::run_with_args()
 }
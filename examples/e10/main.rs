@@ -0,0 +1,322 @@
+use {
+    anyhow::Result,
+    ash::vk,
+    ccthw::{
+        application::{
+            Action, Application, Key, State, WindowBackend, WindowEvent,
+        },
+        graphics::{
+            deferred::{DeferredPass, LightingParams, LightingResolve},
+            vulkan_api::{
+                raii, FrameRecorder, FrameStatus, FramesInFlight,
+                GraphicsPipelineBuilder, RenderDevice, Swapchain,
+                SwapchainChanges,
+            },
+            GraphicsError,
+        },
+        timing::Clock,
+    },
+    ccthw_ash_instance::PhysicalDeviceFeatures,
+    std::{cell::RefCell, rc::Rc, sync::Arc},
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct CubeConstants {
+    time: f32,
+    aspect: f32,
+}
+
+struct DeferredShadingExample {
+    frame_recorder: Option<FrameRecorder>,
+    frames_in_flight: FramesInFlight,
+    lighting_resolve: Rc<RefCell<LightingResolve>>,
+    pipeline_layout: Rc<raii::PipelineLayout>,
+    pipeline: Rc<RefCell<raii::Pipeline>>,
+    deferred_pass: Rc<RefCell<DeferredPass>>,
+    render_device: Arc<RenderDevice>,
+}
+
+impl State for DeferredShadingExample {
+    fn new(window: &mut dyn WindowBackend) -> Result<Self> {
+        let render_device = unsafe {
+            // SAFE because the render device is destroyed when state is
+            // dropped.
+            let mut device_features = PhysicalDeviceFeatures::default();
+            // enable synchronization2 for queue_submit2
+            device_features.vulkan_13_features_mut().synchronization2 =
+                vk::TRUE;
+            window.create_default_render_device(device_features)?
+        };
+
+        let mut frames_in_flight = unsafe {
+            // SAFE because the render device is destroyed when state is dropped
+            FramesInFlight::new(
+                render_device.clone(),
+                window.get_framebuffer_size(),
+                3,
+            )?
+        };
+
+        let deferred_pass = unsafe {
+            DeferredPass::new(
+                render_device.clone(),
+                frames_in_flight.swapchain(),
+            )?
+        };
+
+        let pipeline_layout = unsafe {
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX,
+                    offset: 0,
+                    size: std::mem::size_of::<CubeConstants>() as u32,
+                }],
+            )?
+        };
+        let pipeline = unsafe {
+            Self::create_geometry_pipeline(
+                &render_device,
+                &pipeline_layout,
+                &deferred_pass,
+            )?
+        };
+
+        let lighting_resolve = unsafe {
+            LightingResolve::new(render_device.clone(), &deferred_pass)?
+        };
+
+        let frame_recorder = match &window.args().output {
+            Some(output) => Some(unsafe {
+                // SAFE because the recorder is finished and dropped before
+                // the render device.
+                FrameRecorder::new(
+                    render_device.clone(),
+                    &frames_in_flight,
+                    output,
+                )?
+            }),
+            None => None,
+        };
+
+        let lighting_resolve = Rc::new(RefCell::new(lighting_resolve));
+        let pipeline_layout = Rc::new(pipeline_layout);
+        let pipeline = Rc::new(RefCell::new(pipeline));
+        let deferred_pass = Rc::new(RefCell::new(deferred_pass));
+        frames_in_flight.on_swapchain_rebuilt({
+            let render_device = render_device.clone();
+            let lighting_resolve = lighting_resolve.clone();
+            let pipeline_layout = pipeline_layout.clone();
+            let pipeline = pipeline.clone();
+            let deferred_pass = deferred_pass.clone();
+            move |swapchain: &Swapchain,
+                  changes: &SwapchainChanges|
+                  -> Result<(), GraphicsError> {
+                // SAFE because dependents are only rebuilt after every frame
+                // has finished executing.
+                unsafe {
+                    let mut deferred_pass = deferred_pass.borrow_mut();
+                    let mut lighting_resolve = lighting_resolve.borrow_mut();
+                    deferred_pass.rebuild_for_swapchain(swapchain)?;
+                    lighting_resolve
+                        .rebuild_descriptors(&deferred_pass, None)?;
+
+                    // The render pass, and the pipelines built against it,
+                    // only change when the swapchain format changes.
+                    if changes.format_changed {
+                        *pipeline.borrow_mut() =
+                            Self::create_geometry_pipeline(
+                                &render_device,
+                                &pipeline_layout,
+                                &deferred_pass,
+                            )?;
+                        lighting_resolve.rebuild_pipeline(&deferred_pass)?;
+                    }
+                }
+                Ok(())
+            }
+        });
+
+        Ok(Self {
+            frame_recorder,
+            frames_in_flight,
+            lighting_resolve,
+            pipeline_layout,
+            pipeline,
+            deferred_pass,
+            render_device,
+        })
+    }
+
+    fn handle_event(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        window_event: WindowEvent,
+    ) -> Result<()> {
+        match window_event {
+            WindowEvent::Key(Key::Space, Action::Release) => {
+                window.toggle_fullscreen()?;
+            }
+            WindowEvent::Key(Key::Escape, Action::Release) => {
+                window.set_should_close(true);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        window: &mut dyn WindowBackend,
+        clock: &mut Clock,
+    ) -> Result<()> {
+        let frame = match self.frames_in_flight.acquire_frame()? {
+            FrameStatus::FrameAcquired(frame) => frame,
+            FrameStatus::SwapchainNeedsRebuild => {
+                return self.rebuild_swapchain(window);
+            }
+        };
+
+        let time = clock.time();
+        let deferred_pass = self.deferred_pass.borrow();
+        let vk::Extent2D { width, height } = deferred_pass.extent();
+        let constants = CubeConstants {
+            time,
+            aspect: width as f32 / height as f32,
+        };
+        let lighting_params = LightingParams {
+            light_direction: [time.cos(), -0.5, time.sin(), 0.0],
+            ..Default::default()
+        };
+
+        unsafe {
+            deferred_pass
+                .begin_render_pass_inline(&frame, [0.2, 0.2, 0.3, 1.0]);
+
+            // geometry subpass
+            self.render_device.device().cmd_bind_pipeline(
+                frame.command_buffer(),
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline.borrow().raw(),
+            );
+            self.render_device.device().cmd_set_viewport(
+                frame.command_buffer(),
+                0,
+                &[vk::Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    width: width as f32,
+                    height: height as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                }],
+            );
+            self.render_device.device().cmd_set_scissor(
+                frame.command_buffer(),
+                0,
+                &[vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: vk::Extent2D { width, height },
+                }],
+            );
+            self.render_device.device().cmd_push_constants(
+                frame.command_buffer(),
+                self.pipeline_layout.raw(),
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                std::slice::from_raw_parts(
+                    &constants as *const CubeConstants as *const u8,
+                    std::mem::size_of::<CubeConstants>(),
+                ),
+            );
+            self.render_device.device().cmd_draw(
+                frame.command_buffer(),
+                36,
+                1,
+                0,
+                0,
+            );
+
+            // lighting subpass
+            deferred_pass.next_subpass_inline(&frame);
+            self.lighting_resolve
+                .borrow()
+                .draw(&frame, &lighting_params);
+
+            self.render_device
+                .device()
+                .cmd_end_render_pass(frame.command_buffer());
+
+            if let Some(frame_recorder) = &mut self.frame_recorder {
+                frame_recorder
+                    .cmd_capture(&frame, self.frames_in_flight.swapchain())?;
+            }
+        }
+
+        self.frames_in_flight.present_frame(frame)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for DeferredShadingExample {
+    fn drop(&mut self) {
+        if let Some(frame_recorder) = &mut self.frame_recorder {
+            let result = unsafe {
+                // SAFE because no frame is being recorded.
+                frame_recorder.finish(&self.frames_in_flight)
+            };
+            if let Err(error) = result {
+                log::error!("Unable to write the final frames: {}", error);
+            }
+        }
+    }
+}
+
+impl DeferredShadingExample {
+    /// Create the pipeline which writes the cube into the G-buffer.
+    unsafe fn create_geometry_pipeline(
+        render_device: &Arc<RenderDevice>,
+        pipeline_layout: &raii::PipelineLayout,
+        deferred_pass: &DeferredPass,
+    ) -> Result<raii::Pipeline> {
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!("./shaders/cube.vert.spv"))
+                .fragment_shader(include_bytes!("./shaders/cube.frag.spv"))
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .depth_test(true)
+                .subpass(DeferredPass::GEOMETRY_SUBPASS)
+                .build(
+                    render_device.clone(),
+                    pipeline_layout,
+                    deferred_pass.render_pass(),
+                )?;
+        Ok(pipeline)
+    }
+
+    /// Rebuild the swapchain (typically because the current swapchain is
+    /// out of date. The deferred pass, lighting resolve, and pipeline are
+    /// rebuilt by the dependents registered in `new`.
+    fn rebuild_swapchain(&mut self, window: &dyn WindowBackend) -> Result<()> {
+        unsafe {
+            self.frames_in_flight
+                .stall_and_rebuild_swapchain(window.get_framebuffer_size())?;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    Application::<DeferredShadingExample>::run_with_args()
+}
@@ -0,0 +1,15 @@
+#version 460
+
+layout(location = 0) in vec3 albedo;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 position;
+
+layout(location = 0) out vec4 out_albedo;
+layout(location = 1) out vec4 out_normal;
+layout(location = 2) out vec4 out_position;
+
+void main() {
+    out_albedo = vec4(albedo, 1.0);
+    out_normal = vec4(normalize(normal), 1.0);
+    out_position = vec4(position, 1.0);
+}
@@ -0,0 +1,68 @@
+#version 460
+
+layout(push_constant) uniform Constants {
+    float time;
+    float aspect;
+} constants;
+
+layout(location = 0) out vec3 out_albedo;
+layout(location = 1) out vec3 out_normal;
+layout(location = 2) out vec3 out_position;
+
+const vec3 face_normals[6] = vec3[] (
+    vec3(1.0, 0.0, 0.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, -1.0)
+);
+
+const vec2 quad[6] = vec2[] (
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+mat3 rotation(float t) {
+    float cy = cos(t);
+    float sy = sin(t);
+    float cx = cos(t * 0.7);
+    float sx = sin(t * 0.7);
+    mat3 about_y = mat3(
+        cy, 0.0, -sy,
+        0.0, 1.0, 0.0,
+        sy, 0.0, cy
+    );
+    mat3 about_x = mat3(
+        1.0, 0.0, 0.0,
+        0.0, cx, sx,
+        0.0, -sx, cx
+    );
+    return about_x * about_y;
+}
+
+void main() {
+    int face = gl_VertexIndex / 6;
+    vec3 n = face_normals[face];
+    vec3 u = abs(n.y) > 0.5 ? vec3(1.0, 0.0, 0.0) : vec3(0.0, 1.0, 0.0);
+    vec3 v = cross(n, u);
+    vec2 q = quad[gl_VertexIndex % 6];
+
+    mat3 r = rotation(constants.time);
+    vec3 world = r * ((n + q.x * u + q.y * v) * 0.4);
+
+    out_albedo = abs(n) * 0.6 + vec3(0.3);
+    out_normal = r * n;
+    out_position = world;
+
+    gl_Position = vec4(
+        world.x / constants.aspect,
+        world.y,
+        world.z * 0.5 + 0.5,
+        1.0
+    );
+}
@@ -0,0 +1,209 @@
+use {
+    super::{ValidationLevel, WindowBackendKind},
+    anyhow::{bail, Context, Result},
+    std::{path::PathBuf, str::FromStr},
+};
+
+/// Common command-line flags shared by every sketch.
+///
+/// Parsed by `Application::run_with_args` and available to the sketch through
+/// `WindowBackend::args`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    /// `--width <pixels>`: the initial window width.
+    pub width: Option<u32>,
+
+    /// `--height <pixels>`: the initial window height.
+    pub height: Option<u32>,
+
+    /// `--fullscreen`: start in fullscreen mode.
+    pub fullscreen: bool,
+
+    /// `--hidden`: create the window without showing it. Used for batch
+    /// rendering and benchmarks on machines without a visible desktop.
+    pub hidden: bool,
+
+    /// `--frames <count>`: render exactly this many frames at a fixed
+    /// timestep, then exit. See `Application::run_with_args`.
+    pub frames: Option<u64>,
+
+    /// `--fps <rate>`: the virtual frame rate used with `--frames`.
+    pub fps: Option<f32>,
+
+    /// `--output <dir>`: where the sketch should write rendered frames.
+    pub output: Option<PathBuf>,
+
+    /// `--seed <number>`: a seed for the sketch's random number generators.
+    pub seed: Option<u64>,
+
+    /// `--shader <path>`: a shader for the sketch to load instead of its
+    /// built-in shader.
+    pub shader: Option<PathBuf>,
+
+    /// `--validation <level>`: the Vulkan validation level. See
+    /// `ValidationLevel::parse` for the level names.
+    pub validation: Option<ValidationLevel>,
+
+    /// `--window <backend>`: the library which creates the window, `glfw`
+    /// or `winit`. See `WindowBackendKind`.
+    pub window_backend: Option<WindowBackendKind>,
+
+    /// `--log <spec>`: which logs to keep, e.g. `info,swapchain=debug`. See
+    /// `LoggingConfig::spec`.
+    pub log: Option<String>,
+
+    /// `--log-dir <dir>`: where log files are written, or `none` to only log
+    /// to stdout.
+    pub log_dir: Option<PathBuf>,
+
+    /// `--log-json`: write log files as JSON lines.
+    pub log_json: bool,
+
+    /// `--log-no-dedup`: log every copy of a repeated message instead of
+    /// summarizing repeats at the end of each frame.
+    pub log_no_dedup: bool,
+
+    /// Any arguments which aren't common flags, in order, for the sketch to
+    /// interpret.
+    pub extra: Vec<String>,
+}
+
+impl Args {
+    /// The virtual frame rate used in batch mode when `--fps` isn't given.
+    pub const DEFAULT_FPS: f32 = 60.0;
+
+    /// The usage text printed for `--help`.
+    pub const USAGE: &'static str = "\
+Options:
+    --width <pixels>     Initial window width
+    --height <pixels>    Initial window height
+    --fullscreen         Start in fullscreen mode
+    --hidden             Create the window without showing it
+    --frames <count>     Render <count> frames at a fixed timestep, then exit
+    --fps <rate>         Frames per second of virtual time for --frames
+    --output <dir>       Directory for rendered frames
+    --seed <number>      Seed for random number generators
+    --shader <path>      Shader to load instead of the built-in shader
+    --validation <level> Vulkan validation: off, standard, sync, or gpu
+    --window <backend>   Window library: glfw, or winit with the winit feature
+    --log <spec>         Log filter, e.g. info,swapchain=debug
+    --log-dir <dir>      Directory for log files, or none for stdout only
+    --log-json           Write log files as JSON lines
+    --log-no-dedup       Log every repeated message instead of a summary
+    --help               Print this message and exit";
+
+    /// Returns true when the application renders a fixed number of frames at
+    /// a fixed timestep, rather than running interactively.
+    pub fn is_batch(&self) -> bool {
+        self.frames.is_some()
+    }
+
+    /// The time step, in seconds, between frames in batch mode.
+    pub fn batch_time_step(&self) -> f32 {
+        1.0 / self.fps.unwrap_or(Self::DEFAULT_FPS)
+    }
+
+    /// Parse the process's command-line arguments.
+    pub fn from_env() -> Result<Self> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Parse arguments, not including the program name.
+    ///
+    /// Flag values can be given as `--flag value` or `--flag=value`. `--help`
+    /// prints usage and exits the process.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_owned(), Some(value.to_owned()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String> {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("{} requires a value", flag))
+            };
+            match flag.as_str() {
+                "--width" => parsed.width = Some(parse_value(&flag, value()?)?),
+                "--height" => {
+                    parsed.height = Some(parse_value(&flag, value()?)?)
+                }
+                "--frames" => {
+                    parsed.frames = Some(parse_value(&flag, value()?)?)
+                }
+                "--fps" => parsed.fps = Some(parse_value(&flag, value()?)?),
+                "--seed" => parsed.seed = Some(parse_value(&flag, value()?)?),
+                "--output" => parsed.output = Some(value()?.into()),
+                "--shader" => parsed.shader = Some(value()?.into()),
+                "--validation" => {
+                    parsed.validation = Some(ValidationLevel::parse(&value()?)?)
+                }
+                "--window" => {
+                    parsed.window_backend =
+                        Some(WindowBackendKind::parse(&value()?)?)
+                }
+                "--log" => parsed.log = Some(value()?),
+                "--log-dir" => parsed.log_dir = Some(value()?.into()),
+                "--log-json" => {
+                    if inline_value.is_some() {
+                        bail!("--log-json does not take a value");
+                    }
+                    parsed.log_json = true;
+                }
+                "--log-no-dedup" => {
+                    if inline_value.is_some() {
+                        bail!("--log-no-dedup does not take a value");
+                    }
+                    parsed.log_no_dedup = true;
+                }
+                "--hidden" => {
+                    if inline_value.is_some() {
+                        bail!("--hidden does not take a value");
+                    }
+                    parsed.hidden = true;
+                }
+                "--fullscreen" => {
+                    if inline_value.is_some() {
+                        bail!("--fullscreen does not take a value");
+                    }
+                    parsed.fullscreen = true;
+                }
+                "--help" | "-h" => {
+                    println!("{}", Self::USAGE);
+                    std::process::exit(0);
+                }
+                _ => parsed.extra.push(arg),
+            }
+        }
+        if parsed.width == Some(0) || parsed.height == Some(0) {
+            bail!("--width and --height must be greater than 0");
+        }
+        if parsed.frames == Some(0) {
+            bail!("--frames must be greater than 0");
+        }
+        if parsed
+            .fps
+            .map(|fps| !(fps > 0.0 && fps.is_finite()))
+            .unwrap_or(false)
+        {
+            bail!("--fps must be a finite number greater than 0");
+        }
+        Ok(parsed)
+    }
+}
+
+/// Parse a flag's value, with an error which names the flag.
+fn parse_value<T>(flag: &str, value: String) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .with_context(|| format!("Invalid value for {}: {:?}", flag, value))
+}
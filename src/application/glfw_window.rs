@@ -1,30 +1,42 @@
 use {
-    crate::graphics::vulkan_api::RenderDevice,
+    super::{
+        Action, Args, Key, MouseButton, ValidationLevel, WindowBackend,
+        WindowEvent,
+    },
     anyhow::{bail, Context, Result},
     ash::{vk, vk::Handle},
-    ccthw_ash_instance::{PhysicalDeviceFeatures, VulkanInstance},
-    glfw::{ClientApiHint, WindowEvent, WindowHint, WindowMode},
-    std::sync::{mpsc::Receiver, Arc},
+    ccthw_ash_instance::VulkanInstance,
+    glfw::{ClientApiHint, WindowHint, WindowMode},
+    std::sync::mpsc::Receiver,
 };
 
 /// All resources required for running a single-windowed GLFW application which
 /// renders graphics using Vulkan.
 ///
 /// GlfwWindow derefs as a raw GLFW window handle so application state can
-/// configure the window however is convenient.
+/// configure the window however is convenient. Every kind of event is
+/// polled, so states receive all events without enabling them.
 pub struct GlfwWindow {
     window_pos: (i32, i32),
     window_size: (i32, i32),
     window_handle: glfw::Window,
+    args: Args,
+    validation_level: ValidationLevel,
 
     /// The receiver for the Window's events.
-    pub(super) event_receiver: Option<Receiver<(f64, WindowEvent)>>,
+    event_receiver: Receiver<(f64, glfw::WindowEvent)>,
 
     /// The GLFW library instance.
-    pub(super) glfw: glfw::Glfw,
+    glfw: glfw::Glfw,
 }
 
 impl GlfwWindow {
+    /// The window width used when none is requested.
+    const DEFAULT_WIDTH: u32 = 1366;
+
+    /// The window height used when none is requested.
+    const DEFAULT_HEIGHT: u32 = 768;
+
     /// Create a new GLFW window.
     ///
     /// The window starts in "windowed" mode and can be toggled into fullscreen
@@ -34,6 +46,23 @@ impl GlfwWindow {
     ///
     /// * `window_title` - The title shown on the window's top bar.
     pub fn new(window_title: impl AsRef<str>) -> Result<Self> {
+        Self::new_with_args(window_title, Args::default())
+    }
+
+    /// Create a new GLFW window configured by command-line arguments.
+    ///
+    /// The window uses the requested width and height, if any, and starts
+    /// in fullscreen mode when `--fullscreen` is given.
+    ///
+    /// # Params
+    ///
+    /// * `window_title` - The title shown on the window's top bar.
+    /// * `args` - The parsed command-line arguments. They remain available
+    ///   to the application through `args()`.
+    pub fn new_with_args(
+        window_title: impl AsRef<str>,
+        args: Args,
+    ) -> Result<Self> {
         let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS)?;
 
         if !glfw.vulkan_supported() {
@@ -42,33 +71,59 @@ impl GlfwWindow {
 
         glfw.window_hint(WindowHint::ClientApi(ClientApiHint::NoApi));
         glfw.window_hint(WindowHint::ScaleToMonitor(true));
+        glfw.window_hint(WindowHint::Visible(!args.hidden));
 
-        let (window_handle, event_receiver) = glfw
+        let (mut window_handle, event_receiver) = glfw
             .create_window(
-                1366,
-                768,
+                args.width.unwrap_or(Self::DEFAULT_WIDTH),
+                args.height.unwrap_or(Self::DEFAULT_HEIGHT),
                 window_title.as_ref(),
                 WindowMode::Windowed,
             )
             .context("Creating the GLFW Window failed!")?;
+        window_handle.set_all_polling(true);
 
-        Ok(Self {
+        let fullscreen = args.fullscreen;
+        let mut window = Self {
             window_pos: window_handle.get_pos(),
             window_size: window_handle.get_size(),
-            event_receiver: Some(event_receiver),
+            event_receiver,
             window_handle,
+            validation_level: args.validation.unwrap_or_default(),
+            args,
             glfw,
-        })
+        };
+        if fullscreen {
+            window.toggle_fullscreen()?;
+        }
+        Ok(window)
     }
+}
 
-    /// Toggle application fullscreen.
-    ///
-    /// If the window is currently windowed then swap to fullscreen using
-    /// whatever the primary monitor advertises as the primary video mode.
-    ///
-    /// If the window is currently fullscreen, then swap to windowed and
-    /// restore the window's previous size and location.
-    pub fn toggle_fullscreen(&mut self) -> Result<()> {
+impl WindowBackend for GlfwWindow {
+    fn args(&self) -> &Args {
+        &self.args
+    }
+
+    fn validation_level(&self) -> ValidationLevel {
+        self.validation_level
+    }
+
+    fn set_validation_level(&mut self, validation_level: ValidationLevel) {
+        self.validation_level = validation_level;
+    }
+
+    fn should_close(&self) -> bool {
+        self.window_handle.should_close()
+    }
+
+    fn set_should_close(&mut self, should_close: bool) {
+        self.window_handle.set_should_close(should_close);
+    }
+
+    /// Fullscreen uses whatever the primary monitor advertises as the
+    /// primary video mode.
+    fn toggle_fullscreen(&mut self) -> Result<()> {
         let is_fullscreen =
             self.window_handle.with_window_mode(|mode| match mode {
                 WindowMode::Windowed => false,
@@ -116,106 +171,42 @@ impl GlfwWindow {
         Ok(())
     }
 
-    /// Create a render device with no additional instanc extensions or layers.
-    ///
-    /// # Params
-    ///
-    /// * `features` - The physical device features required by the application.
-    ///
-    /// # Safety
-    ///
-    /// The application is responsible for synchronizing access to all Vulkan
-    /// resources and destroying the render device at exit.
-    pub unsafe fn create_default_render_device(
-        &self,
-        physical_device_features: PhysicalDeviceFeatures,
-    ) -> Result<Arc<RenderDevice>> {
-        self.create_render_device(&[], &[], physical_device_features)
+    fn get_framebuffer_size(&self) -> (i32, i32) {
+        self.window_handle.get_framebuffer_size()
     }
 
-    /// Create a render device for the application.
-    ///
-    /// # Params
-    ///
-    /// * `instance_extensions` - Any extensions to enable when creating the
-    ///   instance. Extensions for the swapchain on the current platform are
-    ///   added automatically and do not need to be provided.
-    /// * `instance_layers` - Any additional layers to provide. The khronos
-    ///   validation layer is added automatically when debug assertions are
-    ///   enabled.
-    /// * `features` - The physical device features required by the application.
-    ///
-    /// # Safety
-    ///
-    /// The application is responsible for synchronizing access to all Vulkan
-    /// resources and destroying the render device at exit.
-    pub unsafe fn create_render_device(
-        &self,
-        instance_extensions: &[String],
-        instance_layers: &[String],
-        features: PhysicalDeviceFeatures,
-    ) -> Result<Arc<RenderDevice>> {
-        let instance =
-            self.create_vulkan_instance(instance_extensions, instance_layers)?;
-
-        let surface = {
-            let mut surface_handle: u64 = 0;
-            let result =
-                vk::Result::from_raw(self.window_handle.create_window_surface(
-                    instance.ash().handle().as_raw() as usize,
-                    std::ptr::null(),
-                    &mut surface_handle,
-                ) as i32);
-            if result != vk::Result::SUCCESS {
-                bail!("Unable to create a Vulkan SurfaceKHR with GLFW!");
-            }
-            vk::SurfaceKHR::from_raw(surface_handle)
-        };
-
-        let device = RenderDevice::new(instance, features, surface)
-            .context("Unable to create the render device!")?;
+    fn get_window_size(&self) -> (i32, i32) {
+        self.window_handle.get_size()
+    }
 
-        log::debug!("{}", device);
+    fn poll_events(&mut self) -> Result<Vec<WindowEvent>> {
+        self.glfw.poll_events();
+        Ok(glfw::flush_messages(&self.event_receiver)
+            .filter_map(|(_, window_event)| convert_event(window_event))
+            .collect())
+    }
 
-        Ok(Arc::new(device))
+    fn required_instance_extensions(&self) -> Result<Vec<String>> {
+        self.glfw.get_required_instance_extensions().context(
+            "Cannot get the required instance extensions for this platform",
+        )
     }
 
-    /// Create a Vulkan instance with extensions and layers configured to
-    /// such that it can present swapchain frames to the window.
-    ///
-    /// # Params
-    ///
-    /// * `instance_extensions` - Any extensions to enable when creating the
-    ///   instance. Extensions for the swapchain on the current platform are
-    ///   added automatically and do not need to be provided.
-    /// * `instance_layers` - Any additional layers to provide. The khronos
-    ///   validation layer is added automatically when debug assertions are
-    ///   enabled.
-    ///
-    /// # Safety
-    ///
-    /// The application is responsible for synchronizing access to all Vulkan
-    /// resources and destroying the Vulkan instance at exit.
-    pub unsafe fn create_vulkan_instance(
+    unsafe fn create_surface(
         &self,
-        instance_extensions: &[String],
-        instance_layers: &[String],
-    ) -> Result<VulkanInstance> {
-        let mut all_instance_extensions =
-            self.glfw.get_required_instance_extensions().context(
-                "Cannot get the required instance extensions for this platform",
-            )?;
-        all_instance_extensions.extend_from_slice(instance_extensions);
-
-        let mut all_layers = instance_layers.to_vec();
-        if cfg!(debug_assertions) {
-            all_layers.push("VK_LAYER_KHRONOS_validation".to_owned());
-        }
-
-        unsafe {
-            VulkanInstance::new(&all_instance_extensions, &all_layers)
-                .context("Error createing the Vulkan instance!")
+        instance: &VulkanInstance,
+    ) -> Result<vk::SurfaceKHR> {
+        let mut surface_handle: u64 = 0;
+        let result =
+            vk::Result::from_raw(self.window_handle.create_window_surface(
+                instance.ash().handle().as_raw() as usize,
+                std::ptr::null(),
+                &mut surface_handle,
+            ) as i32);
+        if result != vk::Result::SUCCESS {
+            bail!("Unable to create a Vulkan SurfaceKHR with GLFW!");
         }
+        Ok(vk::SurfaceKHR::from_raw(surface_handle))
     }
 }
 
@@ -232,3 +223,111 @@ impl std::ops::DerefMut for GlfwWindow {
         &mut self.window_handle
     }
 }
+
+/// Translate a GLFW event, or None when it has no WindowEvent equivalent.
+fn convert_event(window_event: glfw::WindowEvent) -> Option<WindowEvent> {
+    let event = match window_event {
+        glfw::WindowEvent::Close => WindowEvent::Close,
+        glfw::WindowEvent::FramebufferSize(width, height) => {
+            WindowEvent::FramebufferSize(width, height)
+        }
+        glfw::WindowEvent::Focus(focused) => WindowEvent::Focus(focused),
+        glfw::WindowEvent::Key(key, _, action, _) => {
+            WindowEvent::Key(convert_key(key), convert_action(action))
+        }
+        glfw::WindowEvent::Char(character) => WindowEvent::Char(character),
+        glfw::WindowEvent::MouseButton(button, action, _) => {
+            let button = match button {
+                glfw::MouseButton::Button1 => MouseButton::Left,
+                glfw::MouseButton::Button2 => MouseButton::Right,
+                glfw::MouseButton::Button3 => MouseButton::Middle,
+                other => MouseButton::Other(other as u16),
+            };
+            WindowEvent::MouseButton(button, convert_action(action))
+        }
+        glfw::WindowEvent::CursorPos(x, y) => WindowEvent::CursorPos(x, y),
+        glfw::WindowEvent::Scroll(x, y) => WindowEvent::Scroll(x, y),
+        glfw::WindowEvent::FileDrop(paths) => WindowEvent::FileDrop(paths),
+        _ => return None,
+    };
+    Some(event)
+}
+
+fn convert_action(action: glfw::Action) -> Action {
+    match action {
+        glfw::Action::Press => Action::Press,
+        glfw::Action::Release => Action::Release,
+        glfw::Action::Repeat => Action::Repeat,
+    }
+}
+
+fn convert_key(key: glfw::Key) -> Key {
+    use glfw::Key as G;
+    match key {
+        G::A => Key::A,
+        G::B => Key::B,
+        G::C => Key::C,
+        G::D => Key::D,
+        G::E => Key::E,
+        G::F => Key::F,
+        G::G => Key::G,
+        G::H => Key::H,
+        G::I => Key::I,
+        G::J => Key::J,
+        G::K => Key::K,
+        G::L => Key::L,
+        G::M => Key::M,
+        G::N => Key::N,
+        G::O => Key::O,
+        G::P => Key::P,
+        G::Q => Key::Q,
+        G::R => Key::R,
+        G::S => Key::S,
+        G::T => Key::T,
+        G::U => Key::U,
+        G::V => Key::V,
+        G::W => Key::W,
+        G::X => Key::X,
+        G::Y => Key::Y,
+        G::Z => Key::Z,
+        G::Num0 => Key::Num0,
+        G::Num1 => Key::Num1,
+        G::Num2 => Key::Num2,
+        G::Num3 => Key::Num3,
+        G::Num4 => Key::Num4,
+        G::Num5 => Key::Num5,
+        G::Num6 => Key::Num6,
+        G::Num7 => Key::Num7,
+        G::Num8 => Key::Num8,
+        G::Num9 => Key::Num9,
+        G::F1 => Key::F1,
+        G::F2 => Key::F2,
+        G::F3 => Key::F3,
+        G::F4 => Key::F4,
+        G::F5 => Key::F5,
+        G::F6 => Key::F6,
+        G::F7 => Key::F7,
+        G::F8 => Key::F8,
+        G::F9 => Key::F9,
+        G::F10 => Key::F10,
+        G::F11 => Key::F11,
+        G::F12 => Key::F12,
+        G::Space => Key::Space,
+        G::Escape => Key::Escape,
+        G::Enter => Key::Enter,
+        G::Tab => Key::Tab,
+        G::Backspace => Key::Backspace,
+        G::Delete => Key::Delete,
+        G::Left => Key::Left,
+        G::Right => Key::Right,
+        G::Up => Key::Up,
+        G::Down => Key::Down,
+        G::LeftShift => Key::LeftShift,
+        G::RightShift => Key::RightShift,
+        G::LeftControl => Key::LeftControl,
+        G::RightControl => Key::RightControl,
+        G::LeftAlt => Key::LeftAlt,
+        G::RightAlt => Key::RightAlt,
+        _ => Key::Unknown,
+    }
+}
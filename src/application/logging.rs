@@ -1,11 +1,19 @@
 use {
+    super::Args,
     anyhow::Result,
     flexi_logger::{
+        filter::{LogLineFilter, LogLineWriter},
         DeferredNow, Duplicate, FileSpec, Logger, LoggerHandle, Record,
         WriteMode,
     },
     regex::Regex,
-    std::{fmt::Write as FmtWrite, sync::Once},
+    serde_json::json,
+    std::{
+        collections::HashMap,
+        fmt::Write as FmtWrite,
+        path::PathBuf,
+        sync::{Arc, Mutex, Once},
+    },
     textwrap::{termwidth, Options},
 };
 
@@ -20,25 +28,158 @@ static INIT: Once = Once::new();
 //::new(r"(┃)(.*)$").unwrap();
 static mut LAST_NEWLINE_DELIM_MACHER: Option<Regex> = None;
 
-/// Setup pretty console and file logging.
-pub fn setup() {
+/// Counts of messages logged during the current frame, shared with the
+/// Deduplicate filter. Only set when deduplication is enabled.
+static mut FRAME_MESSAGES: Option<Arc<Mutex<FrameMessages>>> = None;
+
+/// Subsystem names which can be used in a log spec in place of module paths.
+///
+/// For example, `info,swapchain=debug` logs everything at info and the
+/// swapchain at debug.
+pub const LOG_SUBSYSTEMS: &[(&str, &[&str])] = &[
+    ("vulkan", &["ccthw::graphics::vulkan_api"]),
+    ("swapchain", &["ccthw::graphics::vulkan_api::swapchain"]),
+    (
+        "shader",
+        &[
+            "ccthw::graphics::vulkan_api::raii::shader_module",
+            "ccthw::graphics::vulkan_api::pipeline_builder",
+        ],
+    ),
+    (
+        "assets",
+        &[
+            "ccthw::graphics::vulkan_api::texture",
+            "ccthw::graphics::sprite",
+            "ccthw::graphics::point_cloud",
+            "ccthw::params::persistence",
+        ],
+    ),
+    ("validation", &["ccthw_ash_instance"]),
+];
+
+/// How the application's logs are filtered and written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggingConfig {
+    /// A flexi_logger spec like `info,swapchain=debug`. Module paths and the
+    /// names in `LOG_SUBSYSTEMS` can be used. The `RUST_LOG` environment
+    /// variable takes precedence when set.
+    pub spec: String,
+
+    /// The directory for log files, or None to only log to stdout.
+    pub directory: Option<PathBuf>,
+
+    /// Write log files as JSON lines instead of the multiline format.
+    pub json: bool,
+
+    /// Only log the first copy of a message each frame, then report how many
+    /// times it repeated when the frame ends. Keeps per-frame messages, like
+    /// validation errors, from flooding the logs.
+    pub deduplicate: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            spec: "trace".to_owned(),
+            directory: Some(PathBuf::from("logs")),
+            json: false,
+            deduplicate: true,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// The default config, adjusted by the `--log`, `--log-dir`,
+    /// `--log-json`, and `--log-no-dedup` flags.
+    pub fn from_args(args: &Args) -> Self {
+        let mut config = Self::default();
+        if let Some(spec) = &args.log {
+            config.spec = spec.clone();
+        }
+        if let Some(directory) = &args.log_dir {
+            config.directory = match directory.to_str() {
+                Some("none") => None,
+                _ => Some(directory.clone()),
+            };
+        }
+        config.json = args.log_json;
+        config.deduplicate = !args.log_no_dedup;
+        config
+    }
+}
+
+/// Setup pretty console and file logging. Only the first call has any effect.
+pub fn setup(config: &LoggingConfig) {
     INIT.call_once(|| {
-        let handle = Logger::try_with_env_or_str("trace")
+        let spec = std::env::var("RUST_LOG")
+            .unwrap_or_else(|_| expand_subsystems(&config.spec));
+        let mut logger = Logger::try_with_str(spec)
             .unwrap()
-            .log_to_file(FileSpec::default().directory("logs"))
-            .format(multiline_format)
-            .duplicate_to_stdout(Duplicate::Warn)
-            .write_mode(WriteMode::Async)
-            .start()
-            .expect("Unable to start the logger!");
+            .format_for_stdout(multiline_format)
+            .write_mode(WriteMode::Async);
+        logger = match &config.directory {
+            Some(directory) => {
+                let mut file_spec = FileSpec::default().directory(directory);
+                if config.json {
+                    file_spec = file_spec.suffix("json");
+                }
+                logger
+                    .log_to_file(file_spec)
+                    .duplicate_to_stdout(Duplicate::Warn)
+            }
+            None => logger.log_to_stdout(),
+        };
+        logger = if config.json {
+            logger.format_for_files(json_format)
+        } else {
+            logger.format_for_files(multiline_format)
+        };
+        let frame_messages = Arc::new(Mutex::new(FrameMessages::default()));
+        if config.deduplicate {
+            logger = logger.filter(Box::new(Deduplicate {
+                frame_messages: frame_messages.clone(),
+            }));
+        }
+        let handle = logger.start().expect("Unable to start the logger!");
         let matcher = Regex::new(r"(┃)(.*)$").unwrap();
         unsafe {
             LOGGER_HANDLE = Some(handle);
-            LAST_NEWLINE_DELIM_MACHER = Some(matcher)
+            LAST_NEWLINE_DELIM_MACHER = Some(matcher);
+            if config.deduplicate {
+                FRAME_MESSAGES = Some(frame_messages);
+            }
         };
     });
 }
 
+/// Report repeated messages from the frame which just ended and start
+/// deduplicating the next frame.
+pub fn end_frame() {
+    let frame_messages = unsafe {
+        // Safe because FRAME_MESSAGES is only written once, during setup.
+        match FRAME_MESSAGES.as_ref() {
+            Some(frame_messages) => frame_messages,
+            None => return,
+        }
+    };
+    let repeated = {
+        let mut frame_messages = frame_messages.lock().unwrap();
+        std::mem::take(&mut frame_messages.counts)
+    };
+    for ((level, target, message), count) in repeated {
+        if count > 1 {
+            log::log!(
+                target: &target,
+                level,
+                "(repeated {} more times this frame) {}",
+                count - 1,
+                message
+            );
+        }
+    }
+}
+
 /// A multiline log format for flexi_logger.
 ///
 /// Logs are automatically wrapped at terminal width and prefixed with unicode
@@ -79,3 +220,83 @@ pub fn multiline_format(
 
     writeln!(w, "{formatted}")
 }
+
+/// A single-line JSON log format for flexi_logger.
+pub fn json_format(
+    w: &mut dyn std::io::Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> Result<(), std::io::Error> {
+    let line = json!({
+        "time": now.now().format("%Y-%m-%dT%H:%M:%S%.6f%:z").to_string(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "file": record.file(),
+        "line": record.line(),
+        "message": record.args().to_string(),
+    });
+    writeln!(w, "{line}")
+}
+
+/// Replace subsystem names in a log spec with their module paths.
+fn expand_subsystems(spec: &str) -> String {
+    spec.split(',')
+        .map(|directive| {
+            let (name, level) = match directive.split_once('=') {
+                Some((name, level)) => (name.trim(), Some(level)),
+                None => (directive.trim(), None),
+            };
+            let modules = LOG_SUBSYSTEMS
+                .iter()
+                .find(|(subsystem, _)| *subsystem == name)
+                .map(|(_, modules)| *modules);
+            match (modules, level) {
+                (Some(modules), Some(level)) => modules
+                    .iter()
+                    .map(|module| format!("{module}={level}"))
+                    .collect::<Vec<String>>()
+                    .join(","),
+                _ => directive.to_owned(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// How many times each message was logged during the current frame, keyed
+/// by level, target, and message text.
+#[derive(Debug, Default)]
+struct FrameMessages {
+    counts: HashMap<(log::Level, String, String), usize>,
+}
+
+/// A log filter which drops messages already logged during the current
+/// frame.
+struct Deduplicate {
+    frame_messages: Arc<Mutex<FrameMessages>>,
+}
+
+impl LogLineFilter for Deduplicate {
+    fn write(
+        &self,
+        now: &mut DeferredNow,
+        record: &Record,
+        log_line_writer: &dyn LogLineWriter,
+    ) -> std::io::Result<()> {
+        let key = (
+            record.level(),
+            record.target().to_owned(),
+            record.args().to_string(),
+        );
+        let is_first = {
+            let mut frame_messages = self.frame_messages.lock().unwrap();
+            let count = frame_messages.counts.entry(key).or_insert(0);
+            *count += 1;
+            *count == 1
+        };
+        if is_first {
+            log_line_writer.write(now, record)?;
+        }
+        Ok(())
+    }
+}
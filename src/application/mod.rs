@@ -1,30 +1,57 @@
-//! Provides structures for running a stateful single-window GLFW application.
+//! Provides structures for running a stateful single-window application.
+//!
+//! Windows are created by GLFW by default, or by winit with `--window winit`
+//! when the `winit` feature is enabled.
 
-use {anyhow::Result, glfw::WindowEvent};
+use {
+    crate::{
+        bench::{self, BenchConfig, BenchReport, SizeReport},
+        timing::Clock,
+    },
+    anyhow::Result,
+    std::time::Instant,
+};
 
+mod args;
 mod glfw_window;
 mod logging;
+mod profiling;
+mod validation;
+mod window_backend;
+mod window_event;
 
-pub use self::glfw_window::GlfwWindow;
+#[cfg(feature = "winit")]
+mod winit_window;
+
+pub use self::{
+    args::Args,
+    glfw_window::GlfwWindow,
+    logging::{LoggingConfig, LOG_SUBSYSTEMS},
+    validation::ValidationLevel,
+    window_backend::{WindowBackend, WindowBackendKind},
+    window_event::{Action, Key, MouseButton, WindowEvent},
+};
+
+#[cfg(feature = "winit")]
+pub use self::winit_window::WinitWindow;
 
 /// Application state can be any type which implements the State trait.
 ///
-/// State is created after the GLFW window is created, but is allowed to
-/// configure the window for things like resizability and event polling.
+/// State is created after the window is created and only sees it through the
+/// WindowBackend trait, so the same State runs with any backend.
 pub trait State {
     /// Create a new instance of this state.
     ///
     /// # Params
     ///
     /// * `window` - A fully constructed application window. The implementation
-    ///   can use this handle to resize the window, apply GLFW window hints,
-    ///   toggle fullscren, and construct a Vulkan instance which can present
-    ///   surfaces to the window.
-    fn new(window: &mut GlfwWindow) -> Result<Self>
+    ///   can use this handle to toggle fullscren and construct a Vulkan
+    ///   instance which can present surfaces to the window.
+    fn new(window: &mut dyn WindowBackend) -> Result<Self>
     where
         Self: Sized;
 
-    /// Handle a GLFW event and update the application state.
+    /// Handle a window event and update the application state.
     ///
     /// # Params
     ///
@@ -33,8 +60,8 @@ pub trait State {
     /// * `window_event` - The event currently being processed by the window.
     fn handle_event(
         &mut self,
-        _window: &mut GlfwWindow,
-        _window_event: glfw::WindowEvent,
+        _window: &mut dyn WindowBackend,
+        _window_event: WindowEvent,
     ) -> Result<()> {
         Ok(())
     }
@@ -48,18 +75,26 @@ pub trait State {
     ///
     /// * `window` - The fully constructed application window. The application
     ///   can exit by calling `set_should_close` on the window.
-    fn update(&mut self, _window: &mut GlfwWindow) -> Result<()> {
+    /// * `clock` - The animation clock, already advanced for this frame. The
+    ///   application can pause, step, scale, or scrub time through it.
+    fn update(
+        &mut self,
+        _window: &mut dyn WindowBackend,
+        _clock: &mut Clock,
+    ) -> Result<()> {
         Ok(())
     }
 }
 
-/// Every application is comprised of a State type and a GLFW window.
+/// Every application is comprised of a State type and a window.
 /// Applications automatically pause if they are minimized or the window is
 /// resized such that there is no drawing area.
 pub struct Application<S: State> {
     state: S,
     paused: bool,
-    window: GlfwWindow,
+    frame_count: u64,
+    clock: Clock,
+    window: Box<dyn WindowBackend>,
 }
 
 // Public API
@@ -73,7 +108,52 @@ where
     /// The window title is just the Application state struct's type name.
     pub fn run() -> Result<()> {
         let window_title = std::any::type_name::<S>();
-        Self::new(window_title)?.main_loop()
+        Self::new(window_title, Args::default())?.main_loop()
+    }
+
+    /// Create and run the Application, configured by the common command-line
+    /// flags described by `Args::USAGE`.
+    ///
+    /// The window size and fullscreen flags are applied automatically. Every
+    /// flag is available to the state through `WindowBackend::args`.
+    ///
+    /// With `--frames N` the application runs in batch mode: it updates
+    /// exactly N times, advancing the Clock by a fixed timestep
+    /// (`--fps`, 60 by default) each frame, then exits. Sketches pass
+    /// `--output` to a FrameRecorder to write each frame to disk.
+    pub fn run_with_args() -> Result<()> {
+        let args = Args::from_env()?;
+        let window_title = std::any::type_name::<S>();
+        Self::new(window_title, args)?.main_loop()
+    }
+
+    /// Benchmark the State in a hidden window.
+    ///
+    /// A new window and State are created for each size in the config. The
+    /// State is updated for the warmup frames, then measured for the
+    /// configured number of frames, advancing the Clock by a fixed timestep
+    /// like `--frames` does. The report is written to `config.output` when
+    /// one is given.
+    pub fn bench(config: &BenchConfig) -> Result<BenchReport> {
+        let window_title = std::any::type_name::<S>();
+        let mut report = BenchReport::new(window_title);
+        for &(width, height) in &config.sizes {
+            let args = Args {
+                width: Some(width),
+                height: Some(height),
+                frames: Some(config.warmup_frames + config.frames),
+                hidden: true,
+                ..Args::default()
+            };
+            let size_report =
+                Self::new(window_title, args)?.bench_loop(config)?;
+            log::info!("{:#?}", size_report);
+            report.sizes.push(size_report);
+        }
+        if let Some(output) = &config.output {
+            report.write_json(output)?;
+        }
+        Ok(report)
     }
 }
 
@@ -84,38 +164,87 @@ where
     S: Sized + State,
 {
     /// Create a new running application.
-    fn new(window_title: impl AsRef<str>) -> Result<Self> {
-        self::logging::setup();
+    fn new(window_title: impl AsRef<str>, args: Args) -> Result<Self> {
+        self::logging::setup(&LoggingConfig::from_args(&args));
+        self::profiling::setup();
+        let _span = tracing::info_span!("Application::new").entered();
+        log::debug!("{:#?}", args);
 
-        let mut window = GlfwWindow::new(window_title)?;
-
-        // Framebuffer polling is required for detecting when the app should be
-        // paused.
-        window.set_framebuffer_size_polling(true);
+        let clock = if args.is_batch() {
+            Clock::fixed_step(args.batch_time_step())
+        } else {
+            Clock::new()
+        };
+        let mut window = args
+            .window_backend
+            .unwrap_or_default()
+            .create_window(window_title, args)?;
 
         Ok(Self {
-            state: S::new(&mut window)?,
+            state: S::new(window.as_mut())?,
             paused: false,
+            frame_count: 0,
+            clock,
             window,
         })
     }
 
     /// Run the application until until the window is closed.
     fn main_loop(mut self) -> Result<()> {
-        let event_receiver = self.window.event_receiver.take().unwrap();
         while !self.window.should_close() {
-            self.window.glfw.poll_events();
-            for (_, window_event) in glfw::flush_messages(&event_receiver) {
+            let _span = tracing::info_span!("frame", frame = self.frame_count)
+                .entered();
+            for window_event in self.window.poll_events()? {
                 self.handle_event(window_event)?;
             }
             if !self.paused {
-                self.state.update(&mut self.window)?;
+                self.clock.tick();
+                let _span = tracing::info_span!("State::update").entered();
+                self.state.update(self.window.as_mut(), &mut self.clock)?;
+                self.frame_count += 1;
+            }
+            self::logging::end_frame();
+            self::profiling::end_frame();
+            if Some(self.frame_count) == self.window.args().frames {
+                self.window.set_should_close(true);
             }
         }
         Ok(())
     }
 
-    /// Handle a GLFW window event.
+    /// Update the application for the configured number of frames, measuring
+    /// each frame after the warmup.
+    fn bench_loop(mut self, config: &BenchConfig) -> Result<SizeReport> {
+        let mut frames = Vec::with_capacity(config.frames as usize);
+        for frame in 0..config.warmup_frames + config.frames {
+            let _span = tracing::info_span!("frame", frame).entered();
+            for window_event in self.window.poll_events()? {
+                self.handle_event(window_event)?;
+            }
+            self.clock.tick();
+
+            // discard anything recorded while handling events
+            bench::take_frame_counters();
+            let start = Instant::now();
+            self.state.update(self.window.as_mut(), &mut self.clock)?;
+            let cpu_time = start.elapsed();
+            let counters = bench::take_frame_counters();
+
+            self.frame_count += 1;
+            self::logging::end_frame();
+            self::profiling::end_frame();
+            if frame >= config.warmup_frames {
+                frames.push((cpu_time, counters));
+            }
+        }
+        let (width, height) = self.window.get_framebuffer_size();
+        Ok(SizeReport::from_frames(
+            (width as u32, height as u32),
+            &frames,
+        ))
+    }
+
+    /// Handle a window event.
     fn handle_event(&mut self, window_event: WindowEvent) -> Result<()> {
         match window_event {
             WindowEvent::Close => {
@@ -127,6 +256,6 @@ where
             _ => (),
         }
 
-        self.state.handle_event(&mut self.window, window_event)
+        self.state.handle_event(self.window.as_mut(), window_event)
     }
 }
@@ -0,0 +1,30 @@
+//! Optional CPU profiling.
+//!
+//! The library is instrumented with `tracing` spans around each frame,
+//! frame acquire and present, pipeline creation, and asset loading. Build
+//! with `--features tracy` to send those spans to the Tracy profiler, then
+//! connect to the running application with the Tracy UI. Without the
+//! feature the spans are never recorded and cost almost nothing.
+
+/// Install the profiler's tracing subscriber. Only the first call has any
+/// effect.
+pub fn setup() {
+    #[cfg(feature = "tracy")]
+    {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_tracy::TracyLayer::default());
+        if tracing::subscriber::set_global_default(subscriber).is_err() {
+            log::warn!("A tracing subscriber is already installed");
+        }
+    }
+}
+
+/// Mark the end of a frame on the profiler's timeline.
+pub fn end_frame() {
+    #[cfg(feature = "tracy")]
+    if let Some(client) = tracy_client::Client::running() {
+        client.frame_mark();
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{bail, Result};
+
+/// How much Vulkan validation to enable when creating the instance.
+///
+/// Everything other than `Off` enables the Khronos validation layer. The
+/// extra checks are read by the layer from the `VK_LAYER_ENABLES`
+/// environment variable, which must be set before the application starts.
+/// The application never modifies its own environment, so it only warns
+/// when the requested checks are missing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// No validation layer.
+    Off,
+
+    /// The validation layer's default checks.
+    Standard,
+
+    /// Standard checks plus synchronization validation and best-practices
+    /// warnings. Catches missing and incorrect barriers.
+    Synchronization,
+
+    /// Standard checks plus GPU-assisted validation and best-practices
+    /// warnings. Catches out-of-bounds descriptor indexing and buffer
+    /// accesses in shaders, at a significant performance cost.
+    GpuAssisted,
+}
+
+impl Default for ValidationLevel {
+    /// Standard validation when debug assertions are enabled, otherwise off.
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Standard
+        } else {
+            Self::Off
+        }
+    }
+}
+
+// Public API
+// ----------
+
+impl ValidationLevel {
+    /// Parse a level from its command-line name: `off`, `standard`, `sync`,
+    /// or `gpu`.
+    pub fn parse(name: &str) -> Result<Self> {
+        let level = match name {
+            "off" => Self::Off,
+            "standard" => Self::Standard,
+            "sync" => Self::Synchronization,
+            "gpu" => Self::GpuAssisted,
+            _ => bail!(
+                "Unknown validation level {:?}, expected off, standard, sync, \
+                 or gpu",
+                name
+            ),
+        };
+        Ok(level)
+    }
+
+    /// True when the Khronos validation layer should be enabled.
+    pub fn uses_validation_layer(&self) -> bool {
+        *self != Self::Off
+    }
+
+    /// The validation features to enable, named as they are in
+    /// `VK_LAYER_ENABLES`.
+    pub fn enabled_features(&self) -> &'static [&'static str] {
+        match self {
+            Self::Off | Self::Standard => &[],
+            Self::Synchronization => &[
+                "VK_VALIDATION_FEATURE_ENABLE_SYNCHRONIZATION_VALIDATION_EXT",
+                "VK_VALIDATION_FEATURE_ENABLE_BEST_PRACTICES_EXT",
+            ],
+            Self::GpuAssisted => &[
+                "VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_EXT",
+                "VK_VALIDATION_FEATURE_ENABLE_BEST_PRACTICES_EXT",
+            ],
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl ValidationLevel {
+    /// Warn when `VK_LAYER_ENABLES` is missing any of this level's extra
+    /// checks.
+    ///
+    /// Setting the variable at runtime would race with other threads reading
+    /// the environment, so the user has to set it when launching the
+    /// application.
+    pub(super) fn check_layer_settings(&self) {
+        let enabled = std::env::var("VK_LAYER_ENABLES").unwrap_or_default();
+        let all_enabled = self.enabled_features().iter().all(|feature| {
+            enabled.split(',').any(|enabled| enabled.trim() == *feature)
+        });
+        if all_enabled {
+            return;
+        }
+        log::warn!(
+            "Validation level {:?} needs VK_LAYER_ENABLES={}",
+            self,
+            self.enabled_features().join(",")
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use {
+    super::{Args, GlfwWindow, ValidationLevel, WindowEvent},
+    crate::graphics::vulkan_api::RenderDevice,
+    anyhow::{bail, Context, Result},
+    ash::vk,
+    ccthw_ash_instance::{PhysicalDeviceFeatures, VulkanInstance},
+    std::sync::Arc,
+};
+
+/// The windowing libraries an Application can run on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum WindowBackendKind {
+    /// GLFW, through the glfw crate. Always available.
+    #[default]
+    Glfw,
+
+    /// winit. Only available with the `winit` feature.
+    Winit,
+}
+
+/// A window which a State renders to and receives events from.
+///
+/// The Application owns the window and hands it to the State as a trait
+/// object, so a State runs unchanged on any backend. Backends only provide
+/// the platform-specific pieces: events, sizing, and the Vulkan surface.
+/// Render devices are created the same way for every backend.
+pub trait WindowBackend {
+    /// The command-line arguments the window was created with.
+    fn args(&self) -> &Args;
+
+    /// The validation level used when creating Vulkan instances.
+    fn validation_level(&self) -> ValidationLevel;
+
+    /// Set the validation level used when creating Vulkan instances.
+    ///
+    /// Defaults to `--validation` when given, otherwise to
+    /// `ValidationLevel::default()`. Only affects instances created after
+    /// the call.
+    fn set_validation_level(&mut self, validation_level: ValidationLevel);
+
+    /// Returns true once the window should close.
+    fn should_close(&self) -> bool;
+
+    /// Ask the Application to close the window and exit, or cancel a
+    /// pending close.
+    fn set_should_close(&mut self, should_close: bool);
+
+    /// Toggle between fullscreen and windowed mode, restoring the window's
+    /// previous size and location when leaving fullscreen.
+    fn toggle_fullscreen(&mut self) -> Result<()>;
+
+    /// The framebuffer's size in pixels.
+    fn get_framebuffer_size(&self) -> (i32, i32);
+
+    /// The window's size in screen coordinates, the same units as
+    /// `WindowEvent::CursorPos`. Differs from the framebuffer size on
+    /// high-DPI displays.
+    fn get_window_size(&self) -> (i32, i32);
+
+    /// Process pending native events without blocking and return them as
+    /// WindowEvents, oldest first.
+    fn poll_events(&mut self) -> Result<Vec<WindowEvent>>;
+
+    /// The instance extensions required to present to this window.
+    fn required_instance_extensions(&self) -> Result<Vec<String>>;
+
+    /// Create a Vulkan surface for the window.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the instance must have the extensions from
+    ///     `required_instance_extensions`
+    ///   - the surface must be destroyed before the instance
+    unsafe fn create_surface(
+        &self,
+        instance: &VulkanInstance,
+    ) -> Result<vk::SurfaceKHR>;
+
+    /// Create a render device with no additional instance extensions or
+    /// layers.
+    ///
+    /// # Params
+    ///
+    /// * `features` - The physical device features required by the application.
+    ///
+    /// # Safety
+    ///
+    /// The application is responsible for synchronizing access to all Vulkan
+    /// resources and destroying the render device at exit.
+    unsafe fn create_default_render_device(
+        &self,
+        physical_device_features: PhysicalDeviceFeatures,
+    ) -> Result<Arc<RenderDevice>> {
+        self.create_render_device(&[], &[], physical_device_features)
+    }
+
+    /// Create a render device for the application.
+    ///
+    /// # Params
+    ///
+    /// * `instance_extensions` - Any extensions to enable when creating the
+    ///   instance. Extensions for the swapchain on the current platform are
+    ///   added automatically and do not need to be provided.
+    /// * `instance_layers` - Any additional layers to provide. The khronos
+    ///   validation layer is added automatically unless the validation level
+    ///   is `ValidationLevel::Off`.
+    /// * `features` - The physical device features required by the application.
+    ///
+    /// # Safety
+    ///
+    /// The application is responsible for synchronizing access to all Vulkan
+    /// resources and destroying the render device at exit.
+    unsafe fn create_render_device(
+        &self,
+        instance_extensions: &[String],
+        instance_layers: &[String],
+        features: PhysicalDeviceFeatures,
+    ) -> Result<Arc<RenderDevice>> {
+        self.create_render_device_with_device_extensions(
+            instance_extensions,
+            instance_layers,
+            &[],
+            features,
+        )
+    }
+
+    /// Create a render device which enables additional device extensions.
+    ///
+    /// # Params
+    ///
+    /// * `instance_extensions` - Any extensions to enable when creating the
+    ///   instance.
+    /// * `instance_layers` - Any additional layers to provide.
+    /// * `device_extensions` - Any device extensions required by the
+    ///   application. The swapchain extension is always enabled.
+    /// * `features` - The physical device features required by the application.
+    ///
+    /// # Safety
+    ///
+    /// The application is responsible for synchronizing access to all Vulkan
+    /// resources and destroying the render device at exit.
+    unsafe fn create_render_device_with_device_extensions(
+        &self,
+        instance_extensions: &[String],
+        instance_layers: &[String],
+        device_extensions: &[String],
+        features: PhysicalDeviceFeatures,
+    ) -> Result<Arc<RenderDevice>> {
+        let instance =
+            self.create_vulkan_instance(instance_extensions, instance_layers)?;
+        let surface = self.create_surface(&instance)?;
+
+        let device = RenderDevice::new_with_device_extensions(
+            instance,
+            features,
+            surface,
+            device_extensions,
+        )
+        .context("Unable to create the render device!")?;
+
+        log::debug!("{}", device);
+
+        Ok(Arc::new(device))
+    }
+
+    /// Create a Vulkan instance with extensions and layers configured to
+    /// such that it can present swapchain frames to the window.
+    ///
+    /// # Params
+    ///
+    /// * `instance_extensions` - Any extensions to enable when creating the
+    ///   instance. Extensions for the swapchain on the current platform are
+    ///   added automatically and do not need to be provided.
+    /// * `instance_layers` - Any additional layers to provide. The khronos
+    ///   validation layer is added automatically unless the validation level
+    ///   is `ValidationLevel::Off`.
+    ///
+    /// # Safety
+    ///
+    /// The application is responsible for synchronizing access to all Vulkan
+    /// resources and destroying the Vulkan instance at exit.
+    unsafe fn create_vulkan_instance(
+        &self,
+        instance_extensions: &[String],
+        instance_layers: &[String],
+    ) -> Result<VulkanInstance> {
+        let mut all_instance_extensions =
+            self.required_instance_extensions()?;
+        all_instance_extensions.extend_from_slice(instance_extensions);
+
+        let validation_level = self.validation_level();
+        let mut all_layers = instance_layers.to_vec();
+        if validation_level.uses_validation_layer() {
+            validation_level.check_layer_settings();
+            all_layers.push("VK_LAYER_KHRONOS_validation".to_owned());
+        }
+
+        unsafe {
+            VulkanInstance::new(&all_instance_extensions, &all_layers)
+                .context("Error createing the Vulkan instance!")
+        }
+    }
+}
+
+impl WindowBackendKind {
+    /// Parse a backend from its name, as given to `--window`.
+    pub fn parse(name: &str) -> Result<Self> {
+        let kind = match name {
+            "glfw" => Self::Glfw,
+            "winit" => Self::Winit,
+            _ => bail!(
+                "Unknown window backend {:?}, expected glfw or winit",
+                name
+            ),
+        };
+        Ok(kind)
+    }
+
+    /// Create a window with this backend, configured by command-line
+    /// arguments.
+    ///
+    /// # Params
+    ///
+    /// * `window_title` - The title shown on the window's top bar.
+    /// * `args` - The parsed command-line arguments.
+    pub fn create_window(
+        &self,
+        window_title: impl AsRef<str>,
+        args: Args,
+    ) -> Result<Box<dyn WindowBackend>> {
+        match self {
+            Self::Glfw => {
+                Ok(Box::new(GlfwWindow::new_with_args(window_title, args)?))
+            }
+            #[cfg(feature = "winit")]
+            Self::Winit => Ok(Box::new(super::WinitWindow::new_with_args(
+                window_title,
+                args,
+            )?)),
+            #[cfg(not(feature = "winit"))]
+            Self::Winit => {
+                bail!("The winit window backend requires the winit feature")
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+/// A window event, the same for every WindowBackend.
+///
+/// Backends translate their native events into these, so a State's
+/// `handle_event` works no matter which backend created the window. Native
+/// events without an equivalent here are dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    /// The user asked to close the window.
+    Close,
+
+    /// The framebuffer was resized, in pixels. Either size is 0 while the
+    /// window is minimized.
+    FramebufferSize(i32, i32),
+
+    /// The window gained (true) or lost (false) input focus.
+    Focus(bool),
+
+    /// A key was pressed, released, or repeated.
+    Key(Key, Action),
+
+    /// A unicode character was typed.
+    Char(char),
+
+    /// A mouse button was pressed or released.
+    MouseButton(MouseButton, Action),
+
+    /// The cursor moved, in screen coordinates relative to the window's
+    /// top-left corner.
+    CursorPos(f64, f64),
+
+    /// The mouse wheel or touchpad scrolled, in lines.
+    Scroll(f64, f64),
+
+    /// Files were dropped onto the window.
+    FileDrop(Vec<PathBuf>),
+}
+
+/// What happened to a key or mouse button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Press,
+    Release,
+
+    /// The key was held long enough to repeat. Mouse buttons never repeat.
+    Repeat,
+}
+
+/// A mouse button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+
+    /// Any other button, numbered from 0 in the order the backend reports.
+    Other(u16),
+}
+
+/// A key, named for its position on a US keyboard layout.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Space,
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    LeftShift,
+    RightShift,
+    LeftControl,
+    RightControl,
+    LeftAlt,
+    RightAlt,
+
+    /// A key without a name here.
+    Unknown,
+}
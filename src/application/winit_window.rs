@@ -0,0 +1,351 @@
+use {
+    super::{
+        Action, Args, Key, MouseButton, ValidationLevel, WindowBackend,
+        WindowEvent,
+    },
+    anyhow::{Context, Result},
+    ash::vk,
+    ccthw_ash_instance::VulkanInstance,
+    std::{collections::HashSet, ffi::CStr},
+    winit::{
+        dpi::LogicalSize,
+        event::{ElementState, Event, MouseScrollDelta, VirtualKeyCode},
+        event_loop::{ControlFlow, EventLoop},
+        platform::run_return::EventLoopExtRunReturn,
+        window::{Fullscreen, Window, WindowBuilder},
+    },
+};
+
+/// All resources required for running a single-windowed winit application
+/// which renders graphics using Vulkan.
+///
+/// winit owns the event loop, so events are pumped with `run_return` each
+/// time the Application polls rather than handing control to winit.
+pub struct WinitWindow {
+    window: Window,
+    args: Args,
+    validation_level: ValidationLevel,
+    should_close: bool,
+
+    /// Keys which are currently held. winit reports a repeated key as
+    /// another press, so presses of held keys become `Action::Repeat`.
+    pressed_keys: HashSet<Key>,
+
+    /// The event loop which delivers the window's events.
+    event_loop: EventLoop<()>,
+}
+
+impl WinitWindow {
+    /// The window width used when none is requested.
+    const DEFAULT_WIDTH: u32 = 1366;
+
+    /// The window height used when none is requested.
+    const DEFAULT_HEIGHT: u32 = 768;
+
+    /// The number of pixels treated as one line when a touchpad reports
+    /// scrolling in pixels.
+    const PIXELS_PER_LINE: f64 = 20.0;
+
+    /// Create a new winit window.
+    ///
+    /// # Params
+    ///
+    /// * `window_title` - The title shown on the window's top bar.
+    pub fn new(window_title: impl AsRef<str>) -> Result<Self> {
+        Self::new_with_args(window_title, Args::default())
+    }
+
+    /// Create a new winit window configured by command-line arguments.
+    ///
+    /// The window uses the requested width and height, if any, and starts
+    /// in fullscreen mode when `--fullscreen` is given.
+    ///
+    /// # Params
+    ///
+    /// * `window_title` - The title shown on the window's top bar.
+    /// * `args` - The parsed command-line arguments. They remain available
+    ///   to the application through `args()`.
+    pub fn new_with_args(
+        window_title: impl AsRef<str>,
+        args: Args,
+    ) -> Result<Self> {
+        let event_loop = EventLoop::new();
+        let window = WindowBuilder::new()
+            .with_title(window_title.as_ref())
+            .with_inner_size(LogicalSize::new(
+                args.width.unwrap_or(Self::DEFAULT_WIDTH),
+                args.height.unwrap_or(Self::DEFAULT_HEIGHT),
+            ))
+            .with_visible(!args.hidden)
+            .build(&event_loop)
+            .context("Creating the winit Window failed!")?;
+
+        let fullscreen = args.fullscreen;
+        let mut window = Self {
+            window,
+            validation_level: args.validation.unwrap_or_default(),
+            args,
+            should_close: false,
+            pressed_keys: HashSet::new(),
+            event_loop,
+        };
+        if fullscreen {
+            window.toggle_fullscreen()?;
+        }
+        Ok(window)
+    }
+}
+
+impl WindowBackend for WinitWindow {
+    fn args(&self) -> &Args {
+        &self.args
+    }
+
+    fn validation_level(&self) -> ValidationLevel {
+        self.validation_level
+    }
+
+    fn set_validation_level(&mut self, validation_level: ValidationLevel) {
+        self.validation_level = validation_level;
+    }
+
+    fn should_close(&self) -> bool {
+        self.should_close
+    }
+
+    fn set_should_close(&mut self, should_close: bool) {
+        self.should_close = should_close;
+    }
+
+    /// Fullscreen is borderless on the window's current monitor, so winit
+    /// restores the window's size and location when leaving it.
+    fn toggle_fullscreen(&mut self) -> Result<()> {
+        if self.window.fullscreen().is_some() {
+            self.window.set_fullscreen(None);
+        } else {
+            self.window
+                .set_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        Ok(())
+    }
+
+    fn get_framebuffer_size(&self) -> (i32, i32) {
+        let size = self.window.inner_size();
+        (size.width as i32, size.height as i32)
+    }
+
+    fn get_window_size(&self) -> (i32, i32) {
+        let size = self
+            .window
+            .inner_size()
+            .to_logical::<f64>(self.window.scale_factor());
+        (size.width.round() as i32, size.height.round() as i32)
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<WindowEvent>> {
+        let Self {
+            window,
+            pressed_keys,
+            event_loop,
+            ..
+        } = self;
+        let mut window_events = vec![];
+        event_loop.run_return(|event, _, control_flow| {
+            *control_flow = ControlFlow::Poll;
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    if let Some(window_event) =
+                        convert_event(window, pressed_keys, event)
+                    {
+                        window_events.push(window_event);
+                    }
+                }
+                Event::MainEventsCleared => {
+                    *control_flow = ControlFlow::Exit;
+                }
+                _ => (),
+            }
+        });
+        Ok(window_events)
+    }
+
+    fn required_instance_extensions(&self) -> Result<Vec<String>> {
+        let extensions =
+            ash_window::enumerate_required_extensions(&self.window).context(
+                "Cannot get the required instance extensions for this platform",
+            )?;
+        Ok(extensions
+            .iter()
+            .map(|&name| {
+                // SAFE because ash-window returns static, nul-terminated
+                // extension names.
+                unsafe { CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect())
+    }
+
+    unsafe fn create_surface(
+        &self,
+        instance: &VulkanInstance,
+    ) -> Result<vk::SurfaceKHR> {
+        ash_window::create_surface(
+            &ash::Entry::linked(),
+            instance.ash(),
+            &self.window,
+            None,
+        )
+        .context("Unable to create a Vulkan SurfaceKHR with winit!")
+    }
+}
+
+impl std::ops::Deref for WinitWindow {
+    type Target = Window;
+
+    fn deref(&self) -> &Self::Target {
+        &self.window
+    }
+}
+
+/// Translate a winit event, or None when it has no WindowEvent equivalent.
+fn convert_event(
+    window: &Window,
+    pressed_keys: &mut HashSet<Key>,
+    window_event: winit::event::WindowEvent,
+) -> Option<WindowEvent> {
+    use winit::event::WindowEvent as W;
+    let event = match window_event {
+        W::CloseRequested => WindowEvent::Close,
+        W::Resized(size) => {
+            WindowEvent::FramebufferSize(size.width as i32, size.height as i32)
+        }
+        W::ScaleFactorChanged { new_inner_size, .. } => {
+            WindowEvent::FramebufferSize(
+                new_inner_size.width as i32,
+                new_inner_size.height as i32,
+            )
+        }
+        W::Focused(focused) => WindowEvent::Focus(focused),
+        W::KeyboardInput { input, .. } => {
+            let key = input.virtual_keycode.map_or(Key::Unknown, convert_key);
+            let action = match input.state {
+                ElementState::Pressed if !pressed_keys.insert(key) => {
+                    Action::Repeat
+                }
+                ElementState::Pressed => Action::Press,
+                ElementState::Released => {
+                    pressed_keys.remove(&key);
+                    Action::Release
+                }
+            };
+            WindowEvent::Key(key, action)
+        }
+        W::ReceivedCharacter(character) => WindowEvent::Char(character),
+        W::MouseInput { state, button, .. } => {
+            let button = match button {
+                winit::event::MouseButton::Left => MouseButton::Left,
+                winit::event::MouseButton::Right => MouseButton::Right,
+                winit::event::MouseButton::Middle => MouseButton::Middle,
+                winit::event::MouseButton::Other(index) => {
+                    MouseButton::Other(index)
+                }
+            };
+            WindowEvent::MouseButton(button, convert_state(state))
+        }
+        W::CursorMoved { position, .. } => {
+            let position = position.to_logical::<f64>(window.scale_factor());
+            WindowEvent::CursorPos(position.x, position.y)
+        }
+        W::MouseWheel { delta, .. } => match delta {
+            MouseScrollDelta::LineDelta(x, y) => {
+                WindowEvent::Scroll(x as f64, y as f64)
+            }
+            MouseScrollDelta::PixelDelta(position) => WindowEvent::Scroll(
+                position.x / WinitWindow::PIXELS_PER_LINE,
+                position.y / WinitWindow::PIXELS_PER_LINE,
+            ),
+        },
+        W::DroppedFile(path) => WindowEvent::FileDrop(vec![path]),
+        _ => return None,
+    };
+    Some(event)
+}
+
+fn convert_state(state: ElementState) -> Action {
+    match state {
+        ElementState::Pressed => Action::Press,
+        ElementState::Released => Action::Release,
+    }
+}
+
+fn convert_key(key: VirtualKeyCode) -> Key {
+    use VirtualKeyCode as V;
+    match key {
+        V::A => Key::A,
+        V::B => Key::B,
+        V::C => Key::C,
+        V::D => Key::D,
+        V::E => Key::E,
+        V::F => Key::F,
+        V::G => Key::G,
+        V::H => Key::H,
+        V::I => Key::I,
+        V::J => Key::J,
+        V::K => Key::K,
+        V::L => Key::L,
+        V::M => Key::M,
+        V::N => Key::N,
+        V::O => Key::O,
+        V::P => Key::P,
+        V::Q => Key::Q,
+        V::R => Key::R,
+        V::S => Key::S,
+        V::T => Key::T,
+        V::U => Key::U,
+        V::V => Key::V,
+        V::W => Key::W,
+        V::X => Key::X,
+        V::Y => Key::Y,
+        V::Z => Key::Z,
+        V::Key0 => Key::Num0,
+        V::Key1 => Key::Num1,
+        V::Key2 => Key::Num2,
+        V::Key3 => Key::Num3,
+        V::Key4 => Key::Num4,
+        V::Key5 => Key::Num5,
+        V::Key6 => Key::Num6,
+        V::Key7 => Key::Num7,
+        V::Key8 => Key::Num8,
+        V::Key9 => Key::Num9,
+        V::F1 => Key::F1,
+        V::F2 => Key::F2,
+        V::F3 => Key::F3,
+        V::F4 => Key::F4,
+        V::F5 => Key::F5,
+        V::F6 => Key::F6,
+        V::F7 => Key::F7,
+        V::F8 => Key::F8,
+        V::F9 => Key::F9,
+        V::F10 => Key::F10,
+        V::F11 => Key::F11,
+        V::F12 => Key::F12,
+        V::Space => Key::Space,
+        V::Escape => Key::Escape,
+        V::Return => Key::Enter,
+        V::Tab => Key::Tab,
+        V::Back => Key::Backspace,
+        V::Delete => Key::Delete,
+        V::Left => Key::Left,
+        V::Right => Key::Right,
+        V::Up => Key::Up,
+        V::Down => Key::Down,
+        V::LShift => Key::LeftShift,
+        V::RShift => Key::RightShift,
+        V::LControl => Key::LeftControl,
+        V::RControl => Key::RightControl,
+        V::LAlt => Key::LeftAlt,
+        V::RAlt => Key::RightAlt,
+        _ => Key::Unknown,
+    }
+}
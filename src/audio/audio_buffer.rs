@@ -0,0 +1,127 @@
+use {
+    super::AudioInput,
+    crate::graphics::{
+        vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The header at the start of the audio storage buffer. Must match the
+/// `AudioBands` buffer in `shaders/audio.glsl`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct AudioHeader {
+    level: f32,
+    band_count: u32,
+    pad: [u32; 2],
+}
+
+/// A storage buffer for each frame in flight which holds an AudioInput's
+/// level and band magnitudes.
+pub struct AudioBuffer {
+    band_count: usize,
+    buffers: Vec<raii::Buffer>,
+    buffer_ptrs: Vec<*mut u8>,
+}
+
+// Public API
+// ----------
+
+impl AudioBuffer {
+    /// Create buffers with room for `band_count` bands.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        band_count: usize,
+    ) -> Result<Self, GraphicsError> {
+        let size = std::mem::size_of::<AudioHeader>()
+            + band_count.max(1) * std::mem::size_of::<f32>();
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let mut buffers = vec![];
+        let mut buffer_ptrs = vec![];
+        for _ in 0..frames_in_flight.frame_count() {
+            let buffer = raii::Buffer::new(
+                render_device.clone(),
+                &vk::BufferCreateInfo {
+                    size: size as u64,
+                    usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+                    sharing_mode: vk::SharingMode::EXCLUSIVE,
+                    queue_family_index_count: 1,
+                    p_queue_family_indices: &queue_family_index,
+                    ..Default::default()
+                },
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.set_debug_name("Audio Bands");
+            let ptr = buffer.allocation().map(render_device.device())?;
+            std::ptr::write_bytes(ptr as *mut u8, 0, size);
+            buffer_ptrs.push(ptr as *mut u8);
+            buffers.push(buffer);
+        }
+        Ok(Self {
+            band_count,
+            buffers,
+            buffer_ptrs,
+        })
+    }
+
+    /// Copy the input's current level and bands into the frame's buffer.
+    ///
+    /// Bands beyond the buffer's capacity are ignored.
+    pub fn write_for_frame(&mut self, frame: &Frame, audio_input: &AudioInput) {
+        let bands = audio_input.bands();
+        let band_count = bands.len().min(self.band_count);
+        let header = AudioHeader {
+            level: audio_input.level(),
+            band_count: band_count as u32,
+            pad: [0; 2],
+        };
+        unsafe {
+            // SAFE because the buffer has room for the header and
+            // self.band_count floats, and the frame's previous commands are
+            // complete.
+            let ptr = self.buffer_ptrs[frame.frame_index()];
+            std::ptr::write(ptr as *mut AudioHeader, header);
+            std::ptr::copy_nonoverlapping(
+                bands.as_ptr(),
+                ptr.add(std::mem::size_of::<AudioHeader>()) as *mut f32,
+                band_count,
+            );
+        }
+    }
+
+    /// The descriptor info for the frame's buffer, for writing a
+    /// STORAGE_BUFFER descriptor.
+    pub fn descriptor_buffer_info(
+        &self,
+        frame: &Frame,
+    ) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffers[frame.frame_index()].raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// A descriptor set layout binding for the audio buffer.
+    pub fn descriptor_set_layout_binding(
+        binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use {
+    super::fft,
+    anyhow::{bail, Context, Result},
+    cpal::traits::{DeviceTrait, HostTrait, StreamTrait},
+    std::{
+        sync::{
+            mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+            Arc, Mutex,
+        },
+        thread::JoinHandle,
+        time::Duration,
+    },
+};
+
+/// The most recent analysis produced by the worker thread.
+#[derive(Debug, Clone, Default)]
+struct Analysis {
+    bands: Vec<f32>,
+    level: f32,
+}
+
+/// Captures an audio input device and analyzes it on a worker thread.
+///
+/// Call `update` once per frame to pull in the latest analysis and apply
+/// smoothing, then read `bands` and `level`.
+pub struct AudioInput {
+    stream: Option<cpal::Stream>,
+    worker: Option<JoinHandle<()>>,
+    latest: Arc<Mutex<Analysis>>,
+    bands: Vec<f32>,
+    level: f32,
+    sample_rate: u32,
+
+    /// How quickly, in seconds, smoothed values rise toward louder input.
+    pub attack: f32,
+
+    /// How quickly, in seconds, smoothed values fall toward quieter input.
+    pub release: f32,
+}
+
+// Public API
+// ----------
+
+impl AudioInput {
+    /// The number of samples analyzed by each FFT.
+    pub const FFT_SIZE: usize = 2048;
+
+    /// The number of new samples between each FFT.
+    const HOP_SIZE: usize = 512;
+
+    /// The lowest frequency, in hertz, covered by the bands.
+    const MIN_FREQUENCY: f32 = 20.0;
+
+    /// The highest frequency, in hertz, covered by the bands.
+    const MAX_FREQUENCY: f32 = 20_000.0;
+
+    /// Start capturing the system's default input device.
+    ///
+    /// # Params
+    ///
+    /// * `band_count` - the number of logarithmically-spaced frequency bands
+    ///   between 20Hz and 20kHz (or the Nyquist frequency if it is lower)
+    pub fn open_default(band_count: usize) -> Result<Self> {
+        let host = cpal::default_host();
+        let device = host
+            .default_input_device()
+            .context("No default audio input device is available")?;
+        let supported_config = device
+            .default_input_config()
+            .context("Unable to get the default audio input config")?;
+        let sample_format = supported_config.sample_format();
+        let config: cpal::StreamConfig = supported_config.into();
+        let sample_rate = config.sample_rate.0;
+        log::debug!(
+            "Capturing audio from {:?} with {:?}",
+            device.name().unwrap_or_default(),
+            config
+        );
+
+        let (sender, receiver) = channel();
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => {
+                Self::build_stream(&device, &config, sender, |s: f32| s)
+            }
+            cpal::SampleFormat::I16 => {
+                Self::build_stream(&device, &config, sender, |s: i16| {
+                    s as f32 / 32768.0
+                })
+            }
+            cpal::SampleFormat::U16 => {
+                Self::build_stream(&device, &config, sender, |s: u16| {
+                    (s as f32 - 32768.0) / 32768.0
+                })
+            }
+            other => bail!("Unsupported audio sample format {:?}", other),
+        }?;
+        stream.play().context("Unable to start audio capture")?;
+
+        let latest = Arc::new(Mutex::new(Analysis {
+            bands: vec![0.0; band_count],
+            level: 0.0,
+        }));
+        let worker_latest = latest.clone();
+        let worker = std::thread::Builder::new()
+            .name("audio-analysis".to_owned())
+            .spawn(move || {
+                Self::analyze(receiver, worker_latest, sample_rate, band_count)
+            })
+            .context("Unable to spawn the audio analysis thread")?;
+
+        Ok(Self {
+            stream: Some(stream),
+            worker: Some(worker),
+            latest,
+            bands: vec![0.0; band_count],
+            level: 0.0,
+            sample_rate,
+            attack: 0.01,
+            release: 0.25,
+        })
+    }
+
+    /// Pull in the latest analysis and smooth it.
+    ///
+    /// # Params
+    ///
+    /// * `dt` - the time, in seconds, since the last update
+    pub fn update(&mut self, dt: f32) {
+        let latest = match self.latest.lock() {
+            Ok(latest) => latest.clone(),
+            Err(_) => return,
+        };
+        let (attack, release) = (self.attack, self.release);
+        let smooth = |current: &mut f32, target: f32| {
+            let time_constant =
+                if target > *current { attack } else { release };
+            let amount = 1.0 - (-dt / time_constant.max(1e-4)).exp();
+            *current += (target - *current) * amount;
+        };
+        for (band, &target) in self.bands.iter_mut().zip(&latest.bands) {
+            smooth(band, target);
+        }
+        smooth(&mut self.level, latest.level);
+    }
+
+    /// The smoothed magnitude of each frequency band, from lowest to highest
+    /// frequency. A full-scale sine wave has a magnitude of roughly 1.
+    pub fn bands(&self) -> &[f32] {
+        &self.bands
+    }
+
+    /// The smoothed RMS level of the input.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// The input device's sample rate in hertz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl Drop for AudioInput {
+    fn drop(&mut self) {
+        // Dropping the stream drops the sender, which stops the worker.
+        self.stream = None;
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::warn!("The audio analysis thread panicked");
+            }
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl AudioInput {
+    /// Build an input stream which mixes every channel to mono and sends the
+    /// samples to the analysis thread.
+    fn build_stream<T: cpal::SizedSample>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        sender: Sender<Vec<f32>>,
+        to_f32: fn(T) -> f32,
+    ) -> Result<cpal::Stream> {
+        let channels = config.channels.max(1) as usize;
+        let stream = device
+            .build_input_stream(
+                config,
+                move |data: &[T], _: &cpal::InputCallbackInfo| {
+                    let mono = data
+                        .chunks(channels)
+                        .map(|frame| {
+                            frame.iter().map(|&s| to_f32(s)).sum::<f32>()
+                                / frame.len() as f32
+                        })
+                        .collect();
+                    // The analysis thread only stops when this stream is
+                    // dropped, so a send error can be ignored.
+                    let _ = sender.send(mono);
+                },
+                |error| log::error!("Audio input error: {}", error),
+                None,
+            )
+            .context("Unable to build the audio input stream")?;
+        Ok(stream)
+    }
+
+    /// The analysis thread's main loop.
+    fn analyze(
+        receiver: Receiver<Vec<f32>>,
+        latest: Arc<Mutex<Analysis>>,
+        sample_rate: u32,
+        band_count: usize,
+    ) {
+        let band_bins = Self::band_bins(sample_rate, band_count);
+        let mut window = vec![0.0; Self::FFT_SIZE];
+        let mut samples_since_fft = 0;
+        loop {
+            let samples = match receiver.recv_timeout(Duration::from_secs(1)) {
+                Ok(samples) => samples,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            // Keep the most recent FFT_SIZE samples.
+            let keep = samples.len().min(Self::FFT_SIZE);
+            window.drain(..keep);
+            window.extend_from_slice(&samples[samples.len() - keep..]);
+            samples_since_fft += samples.len();
+            if samples_since_fft < Self::HOP_SIZE {
+                continue;
+            }
+            samples_since_fft = 0;
+
+            let magnitudes = fft::magnitudes(&window);
+            let bands = band_bins
+                .iter()
+                .map(|bins| {
+                    let bins = &magnitudes[bins.clone()];
+                    bins.iter().sum::<f32>() / bins.len().max(1) as f32
+                })
+                .collect();
+            let level = (window.iter().map(|s| s * s).sum::<f32>()
+                / window.len() as f32)
+                .sqrt();
+            if let Ok(mut latest) = latest.lock() {
+                *latest = Analysis { bands, level };
+            }
+        }
+    }
+
+    /// The range of FFT bins covered by each band.
+    ///
+    /// Bands are spaced evenly in log-frequency. Every band covers at least
+    /// one bin, so low bands may overlap when there are many bands.
+    fn band_bins(
+        sample_rate: u32,
+        band_count: usize,
+    ) -> Vec<std::ops::Range<usize>> {
+        let bin_count = Self::FFT_SIZE / 2;
+        let bin_width = sample_rate as f32 / Self::FFT_SIZE as f32;
+        let max_frequency = Self::MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+        let ratio = max_frequency / Self::MIN_FREQUENCY;
+        let to_bin = |frequency: f32| {
+            ((frequency / bin_width) as usize).clamp(1, bin_count - 1)
+        };
+        (0..band_count)
+            .map(|band| {
+                let low = Self::MIN_FREQUENCY
+                    * ratio.powf(band as f32 / band_count as f32);
+                let high = Self::MIN_FREQUENCY
+                    * ratio.powf((band + 1) as f32 / band_count as f32);
+                let start = to_bin(low);
+                let end = to_bin(high).max(start + 1);
+                start..end
+            })
+            .collect()
+    }
+}
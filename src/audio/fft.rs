@@ -0,0 +1,76 @@
+use std::f32::consts::PI;
+
+/// Compute the magnitude of each frequency bin for a block of samples.
+///
+/// A Hann window is applied before the FFT. The result has
+/// `samples.len() / 2` bins, scaled so a full-scale sine wave has a
+/// magnitude of roughly 1.
+///
+/// `samples.len()` must be a power of two.
+pub fn magnitudes(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    debug_assert!(n.is_power_of_two());
+
+    let mut real = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let window = 0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos();
+            sample * window
+        })
+        .collect::<Vec<f32>>();
+    let mut imaginary = vec![0.0; n];
+    fft_in_place(&mut real, &mut imaginary);
+
+    // The Hann window halves the average amplitude, and a real signal's
+    // energy is split between the positive and negative frequencies.
+    let scale = 4.0 / n as f32;
+    real.iter()
+        .zip(&imaginary)
+        .take(n / 2)
+        .map(|(re, im)| (re * re + im * im).sqrt() * scale)
+        .collect()
+}
+
+/// An iterative radix-2 Cooley-Tukey FFT.
+fn fft_in_place(real: &mut [f32], imaginary: &mut [f32]) {
+    let n = real.len();
+
+    // bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imaginary.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f32;
+        let (w_re, w_im) = (angle.cos(), angle.sin());
+        for start in (0..n).step_by(length) {
+            let (mut re, mut im) = (1.0f32, 0.0f32);
+            for k in 0..length / 2 {
+                let a = start + k;
+                let b = a + length / 2;
+                let t_re = real[b] * re - imaginary[b] * im;
+                let t_im = real[b] * im + imaginary[b] * re;
+                real[b] = real[a] - t_re;
+                imaginary[b] = imaginary[a] - t_im;
+                real[a] += t_re;
+                imaginary[a] += t_im;
+                let next_re = re * w_re - im * w_im;
+                im = re * w_im + im * w_re;
+                re = next_re;
+            }
+        }
+        length <<= 1;
+    }
+}
@@ -0,0 +1,13 @@
+//! Audio input analysis for sound-reactive sketches.
+//!
+//! Enabled by the `audio` feature. AudioInput captures an input device with
+//! cpal, runs an FFT on a worker thread, and exposes smoothed magnitudes for
+//! logarithmically-spaced frequency bands. AudioBuffer copies the bands into
+//! a per-frame storage buffer which shaders can read with
+//! `shaders/audio.glsl`.
+
+mod audio_buffer;
+mod audio_input;
+mod fft;
+
+pub use self::{audio_buffer::AudioBuffer, audio_input::AudioInput};
@@ -0,0 +1,36 @@
+// The layout of the AudioBuffer storage buffer. Must match AudioHeader in
+// audio_buffer.rs.
+//
+// Define AUDIO_SET and AUDIO_BINDING before including this file, e.g.:
+//
+//   #define AUDIO_SET 0
+//   #define AUDIO_BINDING 4
+//   #include "audio.glsl"
+//
+//   float bass = audio_band(0);
+
+layout(std430, set = AUDIO_SET, binding = AUDIO_BINDING)
+readonly buffer AudioBands {
+    float level;
+    uint band_count;
+    uint pad0;
+    uint pad1;
+    float bands[];
+} audio;
+
+// The smoothed magnitude of a band, or 0 for bands out of range.
+float audio_band(uint band) {
+    return band < audio.band_count ? audio.bands[band] : 0.0;
+}
+
+// Sample the bands as a continuous spectrum, where t = 0 is the lowest band
+// and t = 1 is the highest.
+float audio_spectrum(float t) {
+    if (audio.band_count == 0) {
+        return 0.0;
+    }
+    float position = clamp(t, 0.0, 1.0) * float(audio.band_count - 1);
+    uint low = uint(floor(position));
+    uint high = min(low + 1, audio.band_count - 1);
+    return mix(audio.bands[low], audio.bands[high], fract(position));
+}
@@ -0,0 +1,53 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Draw calls recorded since the counters were last taken.
+static DRAW_CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// The sum of the GPU times recorded since the counters were last taken.
+static GPU_TIME_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// The number of GPU times recorded since the counters were last taken.
+static GPU_TIME_SAMPLES: AtomicU64 = AtomicU64::new(0);
+
+/// The counters collected while a single frame was updated.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FrameCounters {
+    pub draw_calls: u64,
+    pub gpu_time: Option<Duration>,
+}
+
+/// Count draw calls recorded by a renderer.
+///
+/// This is cheap enough to call outside of benchmarks, the counters are
+/// simply discarded.
+pub fn record_draw_calls(count: u64) {
+    DRAW_CALLS.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Record how long the GPU spent on a frame.
+///
+/// GpuTimer calls this automatically. If several times are recorded during
+/// one update, the report uses their average.
+pub fn record_gpu_time(gpu_time: Duration) {
+    GPU_TIME_NANOS.fetch_add(gpu_time.as_nanos() as u64, Ordering::Relaxed);
+    GPU_TIME_SAMPLES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Take the counters recorded since the last call and reset them.
+pub(crate) fn take_frame_counters() -> FrameCounters {
+    let draw_calls = DRAW_CALLS.swap(0, Ordering::Relaxed);
+    let gpu_time_nanos = GPU_TIME_NANOS.swap(0, Ordering::Relaxed);
+    let gpu_time_samples = GPU_TIME_SAMPLES.swap(0, Ordering::Relaxed);
+    let gpu_time = if gpu_time_samples > 0 {
+        Some(Duration::from_nanos(gpu_time_nanos / gpu_time_samples))
+    } else {
+        None
+    };
+    FrameCounters {
+        draw_calls,
+        gpu_time,
+    }
+}
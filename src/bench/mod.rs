@@ -0,0 +1,20 @@
+//! Headless benchmarks for sketches and renderers.
+//!
+//! `Application::bench` runs a State in a hidden window for a fixed number
+//! of frames at each requested size and returns a BenchReport. The report
+//! can be written as JSON so regressions can be caught by comparing reports
+//! between runs.
+//!
+//! CPU time is measured around `State::update`. Renderers report draw calls
+//! with `record_draw_calls`. GPU time comes from a GpuTimer, which records
+//! its measurements here automatically.
+
+mod counters;
+mod report;
+
+pub use self::{
+    counters::{record_draw_calls, record_gpu_time},
+    report::{BenchConfig, BenchReport, SizeReport, TimingSummary},
+};
+
+pub(crate) use self::counters::{take_frame_counters, FrameCounters};
@@ -0,0 +1,175 @@
+use {
+    super::FrameCounters,
+    anyhow::{Context, Result},
+    serde_json::{json, Value},
+    std::{path::Path, path::PathBuf, time::Duration},
+};
+
+/// How `Application::bench` runs a State.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// The number of measured frames at each size.
+    pub frames: u64,
+
+    /// Frames rendered at each size before measurements start, so pipeline
+    /// creation and first-use costs aren't included.
+    pub warmup_frames: u64,
+
+    /// The window sizes to benchmark, in order.
+    pub sizes: Vec<(u32, u32)>,
+
+    /// Where to write the JSON report, if anywhere.
+    pub output: Option<PathBuf>,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            frames: 300,
+            warmup_frames: 30,
+            sizes: vec![(1366, 768)],
+            output: None,
+        }
+    }
+}
+
+/// Summary statistics for a set of timings, in milliseconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimingSummary {
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+impl TimingSummary {
+    /// Summarize a set of timings. None when there are no timings.
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut millis: Vec<f64> = samples
+            .iter()
+            .map(|sample| sample.as_secs_f64() * 1000.0)
+            .collect();
+        millis.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let percentile = |p: f64| {
+            let index = ((millis.len() - 1) as f64 * p).round() as usize;
+            millis[index]
+        };
+        Some(Self {
+            mean_ms: millis.iter().sum::<f64>() / millis.len() as f64,
+            min_ms: millis[0],
+            max_ms: millis[millis.len() - 1],
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+        })
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "mean_ms": self.mean_ms,
+            "min_ms": self.min_ms,
+            "max_ms": self.max_ms,
+            "p50_ms": self.p50_ms,
+            "p95_ms": self.p95_ms,
+        })
+    }
+}
+
+/// Measurements for a single window size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeReport {
+    /// The framebuffer size, which can differ from the requested window size
+    /// on high-dpi displays.
+    pub framebuffer_size: (u32, u32),
+
+    /// The number of measured frames.
+    pub frames: u64,
+
+    /// Time spent in `State::update`.
+    pub cpu: Option<TimingSummary>,
+
+    /// GPU time reported by a GpuTimer. None if the State doesn't use one.
+    pub gpu: Option<TimingSummary>,
+
+    /// The average number of draw calls recorded each frame.
+    pub draw_calls_per_frame: f64,
+}
+
+impl SizeReport {
+    /// Build a report from the CPU time and counters for each frame.
+    pub(crate) fn from_frames(
+        framebuffer_size: (u32, u32),
+        frames: &[(Duration, FrameCounters)],
+    ) -> Self {
+        let cpu_times: Vec<Duration> =
+            frames.iter().map(|(cpu_time, _)| *cpu_time).collect();
+        let gpu_times: Vec<Duration> = frames
+            .iter()
+            .filter_map(|(_, counters)| counters.gpu_time)
+            .collect();
+        let draw_calls: u64 =
+            frames.iter().map(|(_, counters)| counters.draw_calls).sum();
+        Self {
+            framebuffer_size,
+            frames: frames.len() as u64,
+            cpu: TimingSummary::from_samples(&cpu_times),
+            gpu: TimingSummary::from_samples(&gpu_times),
+            draw_calls_per_frame: draw_calls as f64
+                / frames.len().max(1) as f64,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "width": self.framebuffer_size.0,
+            "height": self.framebuffer_size.1,
+            "frames": self.frames,
+            "cpu": self.cpu.map(TimingSummary::to_json),
+            "gpu": self.gpu.map(TimingSummary::to_json),
+            "draw_calls_per_frame": self.draw_calls_per_frame,
+        })
+    }
+}
+
+/// The results of benchmarking a State.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// The benchmarked State's type name.
+    pub name: String,
+
+    /// One report for each benchmarked size, in order.
+    pub sizes: Vec<SizeReport>,
+}
+
+impl BenchReport {
+    /// Create an empty report.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            sizes: vec![],
+        }
+    }
+
+    /// The report as a JSON value.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "sizes": self
+                .sizes
+                .iter()
+                .map(SizeReport::to_json)
+                .collect::<Vec<Value>>(),
+        })
+    }
+
+    /// Write the report to a file as pretty-printed JSON.
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        std::fs::write(&path, json).with_context(|| {
+            format!("Unable to write bench report to {:?}", path.as_ref())
+        })
+    }
+}
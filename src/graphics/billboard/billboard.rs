@@ -0,0 +1,94 @@
+use crate::{graphics::sprite::TextureAtlas, math::Vec3};
+
+/// A single camera-facing quad.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Billboard {
+    /// The world-space center of the quad.
+    pub position: Vec3,
+
+    /// The quad's width and height in world units.
+    pub size: [f32; 2],
+
+    /// The quad's rotation around its facing direction, in radians.
+    pub rotation: f32,
+
+    /// Multiplies the sampled texture.
+    pub color: [f32; 4],
+
+    /// The texture's index in the textures given to the BillboardRenderer,
+    /// or None for a solid color quad.
+    pub texture: Option<u32>,
+
+    /// The region of the texture to draw, as [left, top, right, bottom] uv
+    /// coordinates.
+    pub uv_bounds: [f32; 4],
+}
+
+impl Billboard {
+    /// An untextured, unrotated billboard.
+    pub fn new(position: Vec3, size: [f32; 2], color: [f32; 4]) -> Self {
+        Self {
+            position,
+            size,
+            rotation: 0.0,
+            color,
+            texture: None,
+            uv_bounds: [0.0, 0.0, 1.0, 1.0],
+        }
+    }
+
+    /// A billboard which draws a named region of a texture atlas.
+    ///
+    /// Returns None when the atlas has no region with the given name.
+    pub fn from_atlas(
+        position: Vec3,
+        size: [f32; 2],
+        atlas: &TextureAtlas,
+        region: &str,
+    ) -> Option<Self> {
+        let region = atlas.region(region)?;
+        Some(Self {
+            texture: Some(atlas.texture_index()),
+            uv_bounds: atlas.uv_bounds(
+                region.x as f32,
+                region.y as f32,
+                region.width as f32,
+                region.height as f32,
+            ),
+            ..Self::new(position, size, [1.0, 1.0, 1.0, 1.0])
+        })
+    }
+}
+
+/// The GPU representation of a Billboard.
+///
+/// Must match the Billboard struct in `shaders/billboard.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub(super) struct BillboardData {
+    pub position: [f32; 3],
+    pub rotation: f32,
+    pub color: [f32; 4],
+    pub uv_bounds: [f32; 4],
+    pub size: [f32; 2],
+    pub texture: i32,
+    pub pad: u32,
+}
+
+impl BillboardData {
+    pub fn new(billboard: &Billboard) -> Self {
+        Self {
+            position: [
+                billboard.position.x,
+                billboard.position.y,
+                billboard.position.z,
+            ],
+            rotation: billboard.rotation,
+            color: billboard.color,
+            uv_bounds: billboard.uv_bounds,
+            size: billboard.size,
+            texture: billboard.texture.map(|index| index as i32).unwrap_or(-1),
+            pad: 0,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//! Camera-facing textured quads in 3D.
+//!
+//! A BillboardRenderer draws each Billboard as an instanced quad which turns
+//! to face the camera, either fully or only around a fixed axis. Billboards
+//! are given to every draw, so they suit particles, labels in 3D, and point
+//! sprites which change every frame. Textures are referenced by index into
+//! the same texture list given to BindlessTriangles, so TextureAtlas regions
+//! work for billboards too.
+
+mod billboard;
+mod renderer;
+
+pub use self::{
+    billboard::Billboard,
+    renderer::{BillboardMode, BillboardRenderer},
+};
@@ -0,0 +1,416 @@
+use {
+    super::{billboard::BillboardData, Billboard},
+    crate::{
+        graphics::{
+            vulkan_api::{
+                raii, ColorPass, Frame, FramesInFlight,
+                GraphicsPipelineBuilder, RenderDevice, Texture2D,
+            },
+            GraphicsError,
+        },
+        math::{inverse, Mat4, Vec3},
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// How billboards turn to face the camera.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BillboardMode {
+    /// Billboards always face the camera, like point sprites and labels.
+    Full,
+
+    /// Billboards keep their up direction along the given world-space axis
+    /// and only spin around it, like trees or flames.
+    AxisLocked(Vec3),
+}
+
+/// Push constants for the billboard shaders. Must match BillboardConstants
+/// in `shaders/billboard.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct BillboardConstants {
+    view_projection: Mat4,
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+    camera_position: [f32; 4],
+    axis: [f32; 4],
+}
+
+/// Draws camera-facing textured quads.
+///
+/// Billboards are written into a host-visible storage buffer for the frame
+/// on every draw and expanded into quads in the vertex shader. Textures are
+/// indexed with a runtime descriptor array, so the RenderDevice needs the
+/// same `runtimeDescriptorArray` and
+/// `shaderSampledImageArrayNonUniformIndexing` features as
+/// BindlessTriangles.
+pub struct BillboardRenderer {
+    /// How billboards face the camera.
+    pub mode: BillboardMode,
+
+    max_billboards: usize,
+    extent: vk::Extent2D,
+    buffers: Vec<raii::Buffer>,
+    buffer_ptrs: Vec<*mut BillboardData>,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    _sampler: raii::Sampler,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl BillboardRenderer {
+    /// Create the billboard pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - one billboard buffer is kept per frame
+    /// * `color_pass` - the render pass the billboards are drawn into
+    /// * `textures` - at least one texture, usually the same textures given
+    ///   to BindlessTriangles, which billboards refer to by index
+    /// * `max_billboards` - the most billboards drawn at once
+    /// * `depth_test` - when true, billboards are hidden behind scene depth.
+    ///   The ColorPass must be created with a DepthPrePass. Billboards never
+    ///   write depth because they are alpha blended.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        color_pass: &ColorPass,
+        textures: &[Arc<Texture2D>],
+        max_billboards: usize,
+        depth_test: bool,
+    ) -> Result<Self, GraphicsError> {
+        if textures.is_empty() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Billboards need at least one texture"
+            )));
+        }
+        let max_billboards = max_billboards.max(1);
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut buffers = Vec::with_capacity(frame_count);
+        let mut buffer_ptrs = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let (buffer, ptr) =
+                Self::allocate_buffer(&render_device, max_billboards)?;
+            buffers.push(buffer);
+            buffer_ptrs.push(ptr);
+        }
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::VERTEX,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 1,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: textures.len() as u32,
+                        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                ],
+            )?;
+        let set_count = frame_count as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            set_count,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: set_count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: set_count * textures.len() as u32,
+                },
+            ],
+        )?;
+        let layouts = (0..set_count)
+            .map(|_| &descriptor_set_layout)
+            .collect::<Vec<_>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        let texture_infos = textures
+            .iter()
+            .map(|texture| vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: texture.image_view.raw(),
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            })
+            .collect::<Vec<_>>();
+        for (index, buffer) in buffers.iter().enumerate() {
+            let dst_set = descriptor_pool.descriptor_set(index);
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            };
+            render_device.device().update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 0,
+                        dst_array_element: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        p_buffer_info: &buffer_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 1,
+                        dst_array_element: 0,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: texture_infos.len() as u32,
+                        p_image_info: texture_infos.as_ptr(),
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                ],
+                &[],
+            );
+        }
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX,
+                    offset: 0,
+                    size: std::mem::size_of::<BillboardConstants>() as u32,
+                }],
+            )?;
+
+        let pipeline = GraphicsPipelineBuilder::new()
+            .vertex_shader(include_bytes!("./shaders/billboard.vert.spv"))
+            .fragment_shader(include_bytes!("./shaders/billboard.frag.spv"))
+            .color_blend_attachment(
+                GraphicsPipelineBuilder::alpha_blend_attachment(),
+            )
+            .depth_test(depth_test)
+            .depth_write(false)
+            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .build(
+                render_device.clone(),
+                &pipeline_layout,
+                color_pass.render_pass(),
+            )?;
+        pipeline.set_debug_name("Billboard Pipeline");
+
+        Ok(Self {
+            mode: BillboardMode::Full,
+            max_billboards,
+            extent: color_pass.extent(),
+            buffers,
+            buffer_ptrs,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            _sampler: sampler,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// The most billboards drawn at once. Extra billboards passed to `draw`
+    /// are ignored.
+    pub fn max_billboards(&self) -> usize {
+        self.max_billboards
+    }
+
+    /// Write the billboards into the frame's buffer and add commands to draw
+    /// them.
+    ///
+    /// Billboards are blended in the order given, so sort them back to front
+    /// when they overlap.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `view` - the camera's view transform, used to face the camera
+    /// * `projection` - the camera's projection transform
+    /// * `billboards` - the billboards to draw this frame
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    pub unsafe fn draw(
+        &self,
+        frame: &Frame,
+        view: &Mat4,
+        projection: &Mat4,
+        billboards: &[Billboard],
+    ) {
+        let count = billboards.len().min(self.max_billboards);
+        if count == 0 {
+            return;
+        }
+
+        // SAFE because the frame's previous commands are complete, so its
+        // buffer is no longer in use.
+        let data = std::slice::from_raw_parts_mut(
+            self.buffer_ptrs[frame.frame_index()],
+            count,
+        );
+        for (gpu_billboard, billboard) in data.iter_mut().zip(billboards) {
+            *gpu_billboard = BillboardData::new(billboard);
+        }
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(frame.frame_index())],
+            &[],
+        );
+        let constants = self.constants(view, projection);
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const BillboardConstants as *const u8,
+                std::mem::size_of::<BillboardConstants>(),
+            ),
+        );
+        device.cmd_draw(command_buffer, 6, count as u32, 0, 0);
+    }
+}
+
+// Private API
+// -----------
+
+impl BillboardRenderer {
+    /// Compute the camera vectors used to face billboards.
+    fn constants(&self, view: &Mat4, projection: &Mat4) -> BillboardConstants {
+        // The rows of the view rotation are the camera's axes in world
+        // space.
+        let right = Vec3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+        let up = Vec3::new(view[(1, 0)], view[(1, 1)], view[(1, 2)]);
+        let world_from_view = inverse(view).unwrap_or_else(Mat4::identity);
+        let camera_position = Vec3::new(
+            world_from_view[(0, 3)],
+            world_from_view[(1, 3)],
+            world_from_view[(2, 3)],
+        );
+        let axis = match self.mode {
+            BillboardMode::Full => [0.0, 1.0, 0.0, 0.0],
+            BillboardMode::AxisLocked(axis) => {
+                let axis =
+                    axis.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::y);
+                [axis.x, axis.y, axis.z, 1.0]
+            }
+        };
+        BillboardConstants {
+            view_projection: projection * view,
+            camera_right: [right.x, right.y, right.z, 0.0],
+            camera_up: [up.x, up.y, up.z, 0.0],
+            camera_position: [
+                camera_position.x,
+                camera_position.y,
+                camera_position.z,
+                1.0,
+            ],
+            axis,
+        }
+    }
+
+    /// Allocate a host-visible storage buffer with room for `capacity`
+    /// billboards.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not use the associated memory-mapped pointer
+    ///     once the buffer has been dropped.
+    unsafe fn allocate_buffer(
+        render_device: &Arc<RenderDevice>,
+        capacity: usize,
+    ) -> Result<(raii::Buffer, *mut BillboardData), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (capacity * std::mem::size_of::<BillboardData>()) as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Billboards");
+        let ptr = buffer.allocation().map(render_device.device())?;
+        Ok((buffer, ptr as *mut BillboardData))
+    }
+}
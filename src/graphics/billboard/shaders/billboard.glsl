@@ -0,0 +1,27 @@
+// Shared declarations for the billboard shaders. Must match billboard.rs and
+// renderer.rs.
+
+struct Billboard {
+    vec3 position;
+    float rotation;
+    vec4 color;
+    vec4 uv_bounds;
+    vec2 size;
+    int texture_index;
+    uint pad;
+};
+
+layout(std430, set = 0, binding = 0) readonly buffer Billboards {
+    Billboard billboards[];
+};
+
+// Must match BillboardConstants in `billboard/renderer.rs`.
+layout(push_constant) uniform BillboardConstants {
+    mat4 view_projection;
+    vec4 camera_right;
+    vec4 camera_up;
+    vec4 camera_position;
+
+    // xyz is the locked axis when w is 1, full billboards use w = 0.
+    vec4 axis;
+} constants;
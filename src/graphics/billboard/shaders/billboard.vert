@@ -0,0 +1,53 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "billboard.glsl"
+
+layout(location = 0) out vec2 uv;
+layout(location = 1) out vec4 color;
+layout(location = 2) flat out int texture_index;
+
+// Two triangles per billboard, as offsets from the billboard's center.
+const vec2 CORNERS[6] = vec2[](
+    vec2(-0.5, -0.5),
+    vec2(0.5, -0.5),
+    vec2(0.5, 0.5),
+    vec2(-0.5, -0.5),
+    vec2(0.5, 0.5),
+    vec2(-0.5, 0.5)
+);
+
+void main() {
+    Billboard billboard = billboards[gl_InstanceIndex];
+    vec2 corner = CORNERS[gl_VertexIndex];
+
+    vec3 right = constants.camera_right.xyz;
+    vec3 up = constants.camera_up.xyz;
+    if (constants.axis.w > 0.5) {
+        // Spin around the axis to face the camera as much as possible.
+        up = constants.axis.xyz;
+        vec3 to_camera = constants.camera_position.xyz - billboard.position;
+        vec3 axis_right = cross(up, to_camera);
+        if (dot(axis_right, axis_right) > 1e-8) {
+            right = normalize(axis_right);
+        }
+    }
+
+    float s = sin(billboard.rotation);
+    float c = cos(billboard.rotation);
+    vec2 offset = vec2(
+        corner.x * c - corner.y * s,
+        corner.x * s + corner.y * c
+    ) * billboard.size;
+    vec3 position = billboard.position + right * offset.x + up * offset.y;
+
+    uv = mix(
+        billboard.uv_bounds.xy,
+        billboard.uv_bounds.zw,
+        vec2(corner.x + 0.5, 0.5 - corner.y)
+    );
+    color = billboard.color;
+    texture_index = billboard.texture_index;
+    gl_Position = constants.view_projection * vec4(position, 1.0);
+}
@@ -0,0 +1,528 @@
+use {
+    crate::graphics::{
+        gpu::NeighborGrid,
+        vulkan_api::{
+            raii, Barrier, BarrierAccess, BarrierStage, Frame, FramesInFlight,
+            OneTimeSubmitCommandBuffer, RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control how boids steer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FlockParams {
+    /// How strongly boids steer away from very close neighbors.
+    pub separation_weight: f32,
+
+    /// How strongly boids match their neighbors' average velocity.
+    pub alignment_weight: f32,
+
+    /// How strongly boids steer toward their neighbors' average position.
+    pub cohesion_weight: f32,
+
+    /// How strongly boids outside of the bounds steer back inside.
+    pub bounds_weight: f32,
+
+    /// Neighbors closer than this push each other apart.
+    pub separation_radius: f32,
+
+    /// Only boids within this distance are considered neighbors. This is
+    /// also the neighbor grid's cell size.
+    pub neighbor_radius: f32,
+
+    /// The slowest speed a boid will fly.
+    pub min_speed: f32,
+
+    /// The fastest speed a boid will fly.
+    pub max_speed: f32,
+
+    /// The most neighbors a single boid considers each step. This bounds the
+    /// cost of dense clusters.
+    pub max_neighbors: u32,
+
+    /// The minimum corner of the box the boids fly in.
+    pub bounds_min: [f32; 3],
+
+    /// The maximum corner of the box the boids fly in.
+    pub bounds_max: [f32; 3],
+}
+
+impl Default for FlockParams {
+    fn default() -> Self {
+        Self {
+            separation_weight: 1.5,
+            alignment_weight: 1.0,
+            cohesion_weight: 1.0,
+            bounds_weight: 4.0,
+            separation_radius: 0.05,
+            neighbor_radius: 0.15,
+            min_speed: 0.2,
+            max_speed: 0.6,
+            max_neighbors: 64,
+            bounds_min: [-1.0, -1.0, -1.0],
+            bounds_max: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// The GPU representation of FlockParams. Must match FlockUniforms in
+/// `shaders/flock.glsl`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct FlockUniforms {
+    bounds_min: [f32; 4],
+    bounds_max: [f32; 4],
+    grid_origin_and_cell_size: [f32; 4],
+    separation_weight: f32,
+    alignment_weight: f32,
+    cohesion_weight: f32,
+    bounds_weight: f32,
+    separation_radius: f32,
+    neighbor_radius: f32,
+    min_speed: f32,
+    max_speed: f32,
+    dt: f32,
+    count: u32,
+    table_size: u32,
+    max_neighbors: u32,
+}
+
+/// A flocking simulation which runs entirely in compute shaders.
+///
+/// Boid positions and velocities are stored as vec4s in two pairs of storage
+/// buffers. Each step builds a NeighborGrid over the current positions, then
+/// an integration pass reads the current buffers and writes the next ones,
+/// so no boid sees a half-updated neighbor.
+///
+/// The FlockRenderer draws the boids as instanced arrows, or sketches can
+/// read `positions` and `velocities` directly.
+pub struct Flock {
+    /// The parameters used by each call to `cmd_step`.
+    pub params: FlockParams,
+
+    boid_count: u32,
+    current: usize,
+    positions: Vec<raii::Buffer>,
+    velocities: Vec<raii::Buffer>,
+    uniform_buffers: Vec<raii::Buffer>,
+    uniform_buffer_ptrs: Vec<*mut FlockUniforms>,
+    neighbor_grid: NeighborGrid,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl Flock {
+    /// Create the simulation with boids scattered through the default
+    /// bounds, flying in random directions.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - the frames which will record `cmd_step`
+    /// * `boid_count` - the number of boids
+    /// * `seed` - the seed for the boids' starting positions and velocities
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        boid_count: u32,
+        seed: u32,
+    ) -> Result<Self, GraphicsError> {
+        let params = FlockParams::default();
+        let boid_count = boid_count.max(1);
+        let buffer_size =
+            (boid_count as usize * std::mem::size_of::<[f32; 4]>()) as u64;
+
+        let mut positions = vec![];
+        let mut velocities = vec![];
+        for index in 0..2 {
+            for (name, buffers) in [
+                ("Positions", &mut positions),
+                ("Velocities", &mut velocities),
+            ] {
+                let buffer = Self::create_buffer(
+                    &render_device,
+                    buffer_size,
+                    vk::BufferUsageFlags::STORAGE_BUFFER
+                        | vk::BufferUsageFlags::TRANSFER_DST,
+                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                )?;
+                buffer.set_debug_name(format!("Flock {} {}", name, index));
+                buffers.push(buffer);
+            }
+        }
+        Self::upload_initial_state(
+            &render_device,
+            &params,
+            seed,
+            boid_count,
+            &positions[0],
+            &velocities[0],
+        )?;
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut uniform_buffers = Vec::with_capacity(frame_count);
+        let mut uniform_buffer_ptrs = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let buffer = Self::create_buffer(
+                &render_device,
+                std::mem::size_of::<FlockUniforms>() as u64,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.set_debug_name("Flock Uniforms");
+            let ptr = buffer.allocation().map(render_device.device())?;
+            uniform_buffer_ptrs.push(ptr as *mut FlockUniforms);
+            uniform_buffers.push(buffer);
+        }
+
+        let neighbor_grid = NeighborGrid::new(
+            render_device.clone(),
+            boid_count,
+            (2 * boid_count).next_power_of_two(),
+            2,
+        )?;
+
+        let bindings = (0..7)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: if binding == 0 {
+                    vk::DescriptorType::UNIFORM_BUFFER
+                } else {
+                    vk::DescriptorType::STORAGE_BUFFER
+                },
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/flock_integrate.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Flock Integrate Pipeline");
+
+        // There is one descriptor set for each frame and each direction of
+        // the ping-pong: frame_index * 2 + current.
+        let set_count = 2 * frame_count as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            set_count,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: set_count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 6 * set_count,
+                },
+            ],
+        )?;
+        let layouts = vec![&descriptor_set_layout; set_count as usize];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        let [pairs, cell_ranges] = neighbor_grid.descriptor_buffer_infos();
+        for frame_index in 0..frame_count {
+            for current in 0..2 {
+                let next = 1 - current;
+                let buffer_info =
+                    |buffer: &raii::Buffer| vk::DescriptorBufferInfo {
+                        buffer: buffer.raw(),
+                        offset: 0,
+                        range: vk::WHOLE_SIZE,
+                    };
+                let buffer_infos = [
+                    buffer_info(&uniform_buffers[frame_index]),
+                    buffer_info(&positions[current]),
+                    buffer_info(&velocities[current]),
+                    buffer_info(&positions[next]),
+                    buffer_info(&velocities[next]),
+                    pairs,
+                    cell_ranges,
+                ];
+                let descriptor_set =
+                    descriptor_pool.descriptor_set(frame_index * 2 + current);
+                let writes = buffer_infos
+                    .iter()
+                    .enumerate()
+                    .map(|(binding, buffer_info)| vk::WriteDescriptorSet {
+                        dst_set: descriptor_set,
+                        dst_binding: binding as u32,
+                        dst_array_element: 0,
+                        descriptor_type: bindings[binding].descriptor_type,
+                        descriptor_count: 1,
+                        p_buffer_info: buffer_info,
+                        ..vk::WriteDescriptorSet::default()
+                    })
+                    .collect::<Vec<_>>();
+                render_device.device().update_descriptor_sets(&writes, &[]);
+            }
+        }
+
+        Ok(Self {
+            params,
+            boid_count,
+            current: 0,
+            positions,
+            velocities,
+            uniform_buffers,
+            uniform_buffer_ptrs,
+            neighbor_grid,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// The number of boids in the flock.
+    pub fn boid_count(&self) -> u32 {
+        self.boid_count
+    }
+
+    /// The storage buffer which holds every boid's current position as a
+    /// vec4. It changes after every step.
+    pub fn positions(&self) -> &raii::Buffer {
+        &self.positions[self.current]
+    }
+
+    /// The storage buffer which holds every boid's current velocity as a
+    /// vec4. It changes after every step.
+    pub fn velocities(&self) -> &raii::Buffer {
+        &self.velocities[self.current]
+    }
+
+    /// Record commands which advance the flock by `dt` seconds.
+    ///
+    /// The commands end with a barrier which makes the new positions and
+    /// velocities visible to vertex and compute shaders.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    pub unsafe fn cmd_step(
+        &mut self,
+        frame: &Frame,
+        dt: f32,
+    ) -> Result<(), GraphicsError> {
+        let command_buffer = frame.command_buffer();
+        let cell_size = self.params.neighbor_radius.max(f32::EPSILON);
+        self.neighbor_grid.params.cell_size = cell_size;
+        self.neighbor_grid.params.origin = self.params.bounds_min;
+        self.neighbor_grid.cmd_build(
+            command_buffer,
+            &self.positions[self.current],
+            self.boid_count,
+        )?;
+
+        let [min_x, min_y, min_z] = self.params.bounds_min;
+        let [max_x, max_y, max_z] = self.params.bounds_max;
+        let uniforms = FlockUniforms {
+            bounds_min: [min_x, min_y, min_z, 0.0],
+            bounds_max: [max_x, max_y, max_z, 0.0],
+            grid_origin_and_cell_size: [min_x, min_y, min_z, cell_size],
+            separation_weight: self.params.separation_weight,
+            alignment_weight: self.params.alignment_weight,
+            cohesion_weight: self.params.cohesion_weight,
+            bounds_weight: self.params.bounds_weight,
+            separation_radius: self.params.separation_radius,
+            neighbor_radius: self.params.neighbor_radius,
+            min_speed: self.params.min_speed,
+            max_speed: self.params.max_speed,
+            dt,
+            count: self.boid_count,
+            table_size: self.neighbor_grid.table_size(),
+            max_neighbors: self.params.max_neighbors,
+        };
+        self.uniform_buffer_ptrs[frame.frame_index()].write(uniforms);
+
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self
+                .descriptor_pool
+                .descriptor_set(frame.frame_index() * 2 + self.current)],
+            &[],
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.boid_count + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            1,
+            1,
+        );
+
+        Barrier::new()
+            .from(BarrierStage::Compute, BarrierAccess::Write)
+            .to(BarrierStage::Vertex, BarrierAccess::Read)
+            .to(BarrierStage::Compute, BarrierAccess::Read)
+            .record(device, command_buffer);
+
+        self.current = 1 - self.current;
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl Flock {
+    /// The integration shader's workgroup size.
+    const WORKGROUP_SIZE: u32 = 256;
+
+    /// The index of the buffers which hold the current state.
+    pub(super) fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// One of the two position buffers.
+    pub(super) fn positions_at(&self, index: usize) -> &raii::Buffer {
+        &self.positions[index]
+    }
+
+    /// One of the two velocity buffers.
+    pub(super) fn velocities_at(&self, index: usize) -> &raii::Buffer {
+        &self.velocities[index]
+    }
+
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+
+    /// Scatter the boids through the bounds with random headings and copy
+    /// them into the first pair of buffers.
+    unsafe fn upload_initial_state(
+        render_device: &Arc<RenderDevice>,
+        params: &FlockParams,
+        seed: u32,
+        boid_count: u32,
+        positions: &raii::Buffer,
+        velocities: &raii::Buffer,
+    ) -> Result<(), GraphicsError> {
+        // A small xorshift generator is plenty for scattering boids.
+        let mut state = seed.max(1);
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32
+        };
+
+        let mut data = Vec::with_capacity(2 * boid_count as usize);
+        for _ in 0..boid_count {
+            let position: [f32; 3] = std::array::from_fn(|axis| {
+                let t = random();
+                params.bounds_min[axis]
+                    + t * (params.bounds_max[axis] - params.bounds_min[axis])
+            });
+            data.push([position[0], position[1], position[2], 1.0]);
+        }
+        for _ in 0..boid_count {
+            let direction: [f32; 3] =
+                std::array::from_fn(|_| 2.0 * random() - 1.0);
+            let length = direction
+                .iter()
+                .map(|c| c * c)
+                .sum::<f32>()
+                .sqrt()
+                .max(f32::EPSILON);
+            let speed = params.min_speed;
+            data.push([
+                speed * direction[0] / length,
+                speed * direction[1] / length,
+                speed * direction[2] / length,
+                0.0,
+            ]);
+        }
+
+        let staging_buffer = Self::create_buffer(
+            render_device,
+            (data.len() * std::mem::size_of::<[f32; 4]>()) as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging_buffer.set_debug_name("Flock Staging");
+        let ptr = staging_buffer.allocation().map(render_device.device())?
+            as *mut [f32; 4];
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        staging_buffer.allocation().unmap(render_device.device())?;
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        let half =
+            (boid_count as usize * std::mem::size_of::<[f32; 4]>()) as u64;
+        for (buffer, src_offset) in [(positions, 0), (velocities, half)] {
+            render_device.device().cmd_copy_buffer(
+                command_buffer,
+                staging_buffer.raw(),
+                buffer.raw(),
+                &[vk::BufferCopy {
+                    src_offset,
+                    dst_offset: 0,
+                    size: half,
+                }],
+            );
+        }
+        one_time_submit.sync_submit_and_reset()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use {
+    super::Flock,
+    crate::{
+        graphics::{
+            vulkan_api::{
+                raii, ColorPass, Frame, GraphicsPipelineBuilder, RenderDevice,
+            },
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters for drawing a flock. Must match FlockDrawParams in
+/// `shaders/flock.vert`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct FlockDrawParams {
+    /// The combined projection and view matrix.
+    pub view_projection: Mat4,
+
+    /// The color of every boid.
+    pub color: [f32; 4],
+
+    /// The length of each boid's arrow, in world units.
+    pub size: f32,
+}
+
+impl Default for FlockDrawParams {
+    fn default() -> Self {
+        Self {
+            view_projection: Mat4::identity(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            size: 0.02,
+        }
+    }
+}
+
+/// Draws every boid in a Flock as an arrow pointing along its velocity.
+///
+/// Boids are drawn with a single instanced draw: three vertices per
+/// instance, with positions and velocities read from the flock's storage
+/// buffers.
+pub struct FlockRenderer {
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+impl FlockRenderer {
+    /// Create the arrow pipeline and a descriptor set for each of the
+    /// flock's buffer pairs.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `flock` - the flock which is drawn
+    /// * `color_pass` - the render pass the boids are drawn into
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the Flock or ColorPass are
+    ///     rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        flock: &Flock,
+        color_pass: &ColorPass,
+    ) -> Result<Self, GraphicsError> {
+        let bindings = (0..2)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::VERTEX,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX,
+                    offset: 0,
+                    size: std::mem::size_of::<FlockDrawParams>() as u32,
+                }],
+            )?;
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!("./shaders/flock.vert.spv"))
+                .fragment_shader(include_bytes!("./shaders/flock.frag.spv"))
+                .cull_mode(vk::CullModeFlags::NONE, vk::FrontFace::CLOCKWISE)
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .build(
+                    render_device.clone(),
+                    &pipeline_layout,
+                    color_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Flock Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            2,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 4,
+            }],
+        )?;
+        let _ = descriptor_pool.allocate_descriptor_sets(&[
+            &descriptor_set_layout,
+            &descriptor_set_layout,
+        ])?;
+        for index in 0..2 {
+            let buffer_infos = [
+                flock.positions_at(index).raw(),
+                flock.velocities_at(index).raw(),
+            ]
+            .map(|buffer| vk::DescriptorBufferInfo {
+                buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            });
+            let writes = buffer_infos
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer_info)| vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: binding as u32,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                })
+                .collect::<Vec<_>>();
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(Self {
+            extent: color_pass.extent(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Add commands to draw every boid into the current render pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - the flock's most recent step must be recorded before the render
+    ///     pass
+    pub unsafe fn draw(
+        &self,
+        frame: &Frame,
+        flock: &Flock,
+        params: &FlockDrawParams,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            frame.command_buffer(),
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(flock.current_index())],
+            &[],
+        );
+        device.cmd_push_constants(
+            frame.command_buffer(),
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            std::slice::from_raw_parts(
+                params as *const FlockDrawParams as *const u8,
+                std::mem::size_of::<FlockDrawParams>(),
+            ),
+        );
+        device.cmd_draw(frame.command_buffer(), 3, flock.boid_count(), 0, 0);
+    }
+}
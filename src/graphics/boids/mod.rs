@@ -0,0 +1,14 @@
+//! A GPU flocking simulation built on the neighbor search in `gpu`.
+//!
+//! Flock steers boids with separation, alignment, and cohesion forces whose
+//! weights live in a per-frame uniform buffer. FlockRenderer draws the boids
+//! as instanced arrows. Together they are a ready-made sketch subsystem and
+//! a reference for building other particle interactions on NeighborGrid.
+
+mod flock;
+mod flock_renderer;
+
+pub use self::{
+    flock::{Flock, FlockParams},
+    flock_renderer::{FlockDrawParams, FlockRenderer},
+};
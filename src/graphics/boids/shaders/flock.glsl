@@ -0,0 +1,25 @@
+// Declarations shared by the flock shaders. Must match flock.rs.
+
+struct SortPair {
+    uint key;
+    uint value;
+};
+
+layout(std140, set = 0, binding = 0) uniform FlockUniforms {
+    vec4 bounds_min;
+    vec4 bounds_max;
+    // xyz is the neighbor grid origin, w is the cell size.
+    vec4 grid_origin_and_cell_size;
+    float separation_weight;
+    float alignment_weight;
+    float cohesion_weight;
+    float bounds_weight;
+    float separation_radius;
+    float neighbor_radius;
+    float min_speed;
+    float max_speed;
+    float dt;
+    uint count;
+    uint table_size;
+    uint max_neighbors;
+} flock;
@@ -0,0 +1,44 @@
+#version 460
+
+layout(std430, set = 0, binding = 0) readonly buffer Positions {
+    vec4 positions[];
+};
+
+layout(std430, set = 0, binding = 1) readonly buffer Velocities {
+    vec4 velocities[];
+};
+
+// Must match FlockDrawParams in flock_renderer.rs.
+layout(push_constant) uniform FlockDrawParams {
+    mat4 view_projection;
+    vec4 color;
+    float size;
+} params;
+
+layout(location = 0) out vec4 vertex_color;
+
+// An arrow head pointing along +x, in units of size.
+const vec2 ARROW[3] = vec2[] (
+    vec2(1.0, 0.0),
+    vec2(-0.6, 0.5),
+    vec2(-0.6, -0.5)
+);
+
+void main() {
+    vec3 position = positions[gl_InstanceIndex].xyz;
+    vec3 velocity = velocities[gl_InstanceIndex].xyz;
+
+    vec3 forward = length(velocity) > 1e-6
+        ? normalize(velocity)
+        : vec3(1.0, 0.0, 0.0);
+    vec3 reference = abs(forward.y) < 0.99
+        ? vec3(0.0, 1.0, 0.0)
+        : vec3(1.0, 0.0, 0.0);
+    vec3 side = normalize(cross(forward, reference));
+
+    vec2 corner = ARROW[gl_VertexIndex % 3] * params.size;
+    vec3 world = position + forward * corner.x + side * corner.y;
+
+    vertex_color = params.color;
+    gl_Position = params.view_projection * vec4(world, 1.0);
+}
@@ -0,0 +1,182 @@
+use {
+    crate::graphics::vulkan_api::Frame,
+    serde_json::{json, Value},
+    std::fmt::Write,
+};
+
+/// A semaphore which a submission waits on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemaphoreWait {
+    /// The semaphore's name, matching its Vulkan debug name.
+    pub semaphore: String,
+
+    /// The pipeline stages which wait for the semaphore.
+    pub stage: String,
+}
+
+/// A single queue submission within a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmissionStructure {
+    /// The names of the command buffers in submission order.
+    pub command_buffers: Vec<String>,
+
+    /// The semaphores waited on before the submission executes.
+    pub waits: Vec<SemaphoreWait>,
+
+    /// The semaphore signaled when the submission completes.
+    pub signal: String,
+}
+
+/// A description of the GPU work scheduled for one frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameStructure {
+    /// The frame's index among the frames in flight.
+    pub frame_index: usize,
+
+    /// The swapchain image targeted by the frame.
+    pub swapchain_image_index: usize,
+
+    /// The queue submissions, in submission order.
+    pub submissions: Vec<SubmissionStructure>,
+
+    /// The fence signaled when every submission completes.
+    pub fence: String,
+
+    /// The semaphore which presentation waits on.
+    pub present_wait: String,
+}
+
+// Public API
+// ----------
+
+impl FrameStructure {
+    /// Describe the work recorded so far for the frame.
+    pub fn describe(frame: &Frame) -> Self {
+        let index = frame.frame_index();
+        let acquired = format!("Frame {index} Swapchain Image Acquired");
+        let completed = format!("Frame {index} Graphics Commands Completed");
+
+        let layout = frame.sync().submission_layout();
+        let last = layout.len() - 1;
+        let submissions = layout
+            .into_iter()
+            .enumerate()
+            .map(|(submission, (wait_stage, command_buffers))| {
+                let mut waits = vec![];
+                if submission > 0 {
+                    waits.push(SemaphoreWait {
+                        semaphore: format!(
+                            "Frame {index} Submission {} Completed",
+                            submission - 1
+                        ),
+                        stage: format!("{wait_stage:?}"),
+                    });
+                }
+                let signal = if submission == last {
+                    waits.push(SemaphoreWait {
+                        semaphore: acquired.clone(),
+                        stage: "COLOR_ATTACHMENT_OUTPUT".to_owned(),
+                    });
+                    completed.clone()
+                } else {
+                    format!("Frame {index} Submission {submission} Completed")
+                };
+                SubmissionStructure {
+                    command_buffers,
+                    waits,
+                    signal,
+                }
+            })
+            .collect();
+
+        Self {
+            frame_index: index,
+            swapchain_image_index: frame.swapchain_image_index(),
+            submissions,
+            fence: completed.clone(),
+            present_wait: completed,
+        }
+    }
+
+    /// Describe the frame as a Graphviz DOT graph.
+    ///
+    /// Submissions are boxes listing their command buffers, semaphores are
+    /// ellipses, and edges show which submissions signal and wait on each
+    /// semaphore.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph frame_{} {{", self.frame_index);
+        let _ = writeln!(dot, "    rankdir=LR;");
+        let _ = writeln!(
+            dot,
+            "    label=\"Frame {} / Swapchain Image {}\";",
+            self.frame_index, self.swapchain_image_index
+        );
+        let _ =
+            writeln!(dot, "    acquire [shape=diamond, label=\"Acquire\"];");
+        let _ =
+            writeln!(dot, "    present [shape=diamond, label=\"Present\"];");
+
+        for (index, submission) in self.submissions.iter().enumerate() {
+            let _ = writeln!(
+                dot,
+                "    submission_{index} [shape=box, label=\"Submission {index}\\n{}\"];",
+                submission.command_buffers.join("\\n")
+            );
+            for wait in &submission.waits {
+                let _ = writeln!(
+                    dot,
+                    "    \"{}\" -> submission_{index} [label=\"{}\"];",
+                    wait.semaphore, wait.stage
+                );
+            }
+            let _ = writeln!(
+                dot,
+                "    submission_{index} -> \"{}\";",
+                submission.signal
+            );
+        }
+
+        if let Some(last) = self.submissions.last() {
+            let acquired = last.waits.last().map(|wait| &wait.semaphore);
+            if let Some(acquired) = acquired {
+                let _ = writeln!(dot, "    acquire -> \"{acquired}\";");
+            }
+        }
+        let _ = writeln!(dot, "    \"{}\" -> present;", self.present_wait);
+        let _ = writeln!(dot, "}}");
+        dot
+    }
+
+    /// Describe the frame as JSON.
+    pub fn to_json(&self) -> Value {
+        let submissions = self
+            .submissions
+            .iter()
+            .map(|submission| {
+                let waits = submission
+                    .waits
+                    .iter()
+                    .map(|wait| {
+                        json!({
+                            "semaphore": wait.semaphore,
+                            "stage": wait.stage,
+                        })
+                    })
+                    .collect::<Vec<Value>>();
+                json!({
+                    "command_buffers": submission.command_buffers,
+                    "waits": waits,
+                    "signal": submission.signal,
+                })
+            })
+            .collect::<Vec<Value>>();
+        json!({
+            "frame_index": self.frame_index,
+            "swapchain_image_index": self.swapchain_image_index,
+            "submissions": submissions,
+            "fence": self.fence,
+            "present_wait": self.present_wait,
+        })
+    }
+}
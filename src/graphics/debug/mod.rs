@@ -0,0 +1,42 @@
+//! Tools for inspecting how the library schedules GPU work.
+//!
+//! `dump_frame_structure` writes the queue submissions, command buffers, and
+//! semaphores which make up the frame being recorded, so synchronization can
+//! be checked without a GPU debugger. Barriers recorded inside command
+//! buffers are not tracked and do not appear in the output.
+
+mod frame_structure;
+
+pub use self::frame_structure::{
+    FrameStructure, SemaphoreWait, SubmissionStructure,
+};
+
+use {
+    crate::graphics::{vulkan_api::Frame, GraphicsError},
+    anyhow::Context,
+    std::path::Path,
+};
+
+/// Write the structure of the frame being recorded to a file.
+///
+/// The output format depends on the file extension: `.json` writes JSON and
+/// anything else writes a Graphviz DOT graph.
+///
+/// Call this just before `FramesInFlight::present_frame` so every
+/// submission and command buffer has been requested.
+pub fn dump_frame_structure(
+    frame: &Frame,
+    path: impl AsRef<Path>,
+) -> Result<(), GraphicsError> {
+    let structure = FrameStructure::describe(frame);
+    let path = path.as_ref();
+    let contents = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::to_string_pretty(&structure.to_json())
+            .context("Unable to serialize the frame structure")?,
+        _ => structure.to_dot(),
+    };
+    std::fs::write(path, contents).with_context(|| {
+        format!("Unable to write frame structure to {}", path.display())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::math::{inverse, Mat4, Vec3};
+
+/// A box which projects a texture onto whatever scene depth lies inside it.
+///
+/// The box is the unit cube, centered on the origin, transformed by
+/// `transform`. The texture is projected along the box's local -Z axis with
+/// local +X to the right and +Y up, so uv (0, 0) is the top-left corner of
+/// the box when looking down -Z.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Decal {
+    /// Transforms the unit cube into the decal's world-space box.
+    pub transform: Mat4,
+
+    /// The texture's index in the textures given to the DecalRenderer.
+    pub texture: u32,
+
+    /// Multiplies the sampled texture.
+    pub color: [f32; 4],
+}
+
+impl Decal {
+    /// A decal with an arbitrary box transform.
+    pub fn new(transform: Mat4, texture: u32) -> Self {
+        Self {
+            transform,
+            texture,
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    /// A square decal stamped onto a surface, like a paint splat.
+    ///
+    /// # Params
+    ///
+    /// * `position` - the world-space point on the surface
+    /// * `normal` - the surface normal at `position`
+    /// * `size` - the width and height of the decal, the box is as deep as
+    ///   it is wide
+    /// * `rotation` - the decal's rotation around the normal, in radians
+    /// * `texture` - the texture's index in the DecalRenderer
+    pub fn splat(
+        position: &Vec3,
+        normal: &Vec3,
+        size: f32,
+        rotation: f32,
+        texture: u32,
+    ) -> Self {
+        let z = normal.normalize();
+        let reference = if z.y.abs() < 0.99 {
+            Vec3::y()
+        } else {
+            Vec3::x()
+        };
+        let x0 = reference.cross(&z).normalize();
+        let y0 = z.cross(&x0);
+        let (sin, cos) = rotation.sin_cos();
+        let x = x0 * cos + y0 * sin;
+        let y = z.cross(&x);
+        let transform = Mat4::new(
+            x.x, y.x, z.x, position.x, //
+            x.y, y.y, z.y, position.y, //
+            x.z, y.z, z.z, position.z, //
+            0.0, 0.0, 0.0, 1.0,
+        ) * Mat4::new_scaling(size);
+        Self::new(transform, texture)
+    }
+}
+
+/// The GPU representation of a Decal, with the transforms needed by the
+/// shaders precomputed for the current camera.
+///
+/// Must match the Decal struct in `shaders/decal.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub(super) struct DecalData {
+    pub clip_from_local: Mat4,
+    pub local_from_world: Mat4,
+    pub color: [f32; 4],
+    pub texture: u32,
+    pub pad: [u32; 3],
+}
+
+impl DecalData {
+    /// Prepare a decal for drawing with the given camera.
+    pub fn new(decal: &Decal, view_projection: &Mat4) -> Self {
+        Self {
+            clip_from_local: view_projection * decal.transform,
+            local_from_world: inverse(&decal.transform)
+                .unwrap_or_else(Mat4::zeros),
+            color: decal.color,
+            texture: decal.texture,
+            pad: [0, 0, 0],
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Screen-space decals which project textures onto the scene.
+//!
+//! A DecalRenderer draws each Decal as an instanced box in a ColorPass
+//! subpass which reads depth. Pixels inside a box are textured by projecting
+//! along the box's depth axis, so decals wrap over any geometry already in
+//! the depth buffer, which makes them handy for splats and paint.
+
+mod decal;
+mod renderer;
+
+pub use self::{decal::Decal, renderer::DecalRenderer};
@@ -0,0 +1,427 @@
+use {
+    super::{decal::DecalData, Decal},
+    crate::{
+        graphics::{
+            vulkan_api::{
+                raii, ColorPass, Frame, FramesInFlight,
+                GraphicsPipelineBuilder, RenderDevice,
+            },
+            GraphicsError,
+        },
+        math::{inverse, Mat4},
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for the decal shaders. Must match DecalConstants in
+/// `shaders/decal.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct DecalConstants {
+    world_from_clip: Mat4,
+    viewport_size: [f32; 2],
+}
+
+/// Projects textures onto the scene's depth buffer.
+///
+/// Decals are drawn in a ColorPass subpass which reads depth, declared with
+/// `ColorSubpass { read_depth: true, ..Default::default() }`, after the
+/// scene has been drawn. Each decal is an instanced box. Every pixel the box
+/// covers reconstructs the scene position from depth and is blended into
+/// color only when that position is inside the box.
+///
+/// The renderer keeps at most `max_decals` decals. Pushing more replaces
+/// the oldest, which suits splat and paint effects that add decals forever.
+pub struct DecalRenderer {
+    decals: Vec<Decal>,
+    oldest: usize,
+    max_decals: usize,
+    extent: vk::Extent2D,
+    buffers: Vec<raii::Buffer>,
+    buffer_ptrs: Vec<*mut DecalData>,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    _sampler: raii::Sampler,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl DecalRenderer {
+    /// The most textures a renderer can project.
+    pub const MAX_TEXTURES: usize = 16;
+
+    /// Create the decal pipeline for a ColorPass subpass.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - one decal buffer is kept per frame
+    /// * `color_pass` - the pass with the decal subpass
+    /// * `subpass` - the index of a subpass which reads depth and not color
+    /// * `textures` - between 1 and MAX_TEXTURES views, in the
+    ///   SHADER_READ_ONLY_OPTIMAL layout, which decals refer to by index
+    /// * `max_decals` - the most decals drawn at once
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    ///   - the textures must outlive this instance
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        color_pass: &ColorPass,
+        subpass: u32,
+        textures: &[&raii::ImageView],
+        max_decals: usize,
+    ) -> Result<Self, GraphicsError> {
+        let input_infos = color_pass.input_attachment_image_infos(subpass, 0);
+        let reads_only_depth = input_infos.len() == 1
+            && input_infos[0].image_layout
+                == vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL;
+        if !reads_only_depth {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Decal subpass {} must read depth and must not read color",
+                subpass
+            )));
+        }
+        if textures.is_empty() || textures.len() > Self::MAX_TEXTURES {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Decals need between 1 and {} textures, got {}",
+                Self::MAX_TEXTURES,
+                textures.len()
+            )));
+        }
+        let max_decals = max_decals.max(1);
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut buffers = Vec::with_capacity(frame_count);
+        let mut buffer_ptrs = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let (buffer, ptr) =
+                Self::allocate_buffer(&render_device, max_decals)?;
+            buffers.push(buffer);
+            buffer_ptrs.push(ptr);
+        }
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 1,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::VERTEX
+                            | vk::ShaderStageFlags::FRAGMENT,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 2,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: Self::MAX_TEXTURES as u32,
+                        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                ],
+            )?;
+        let set_count = frame_count as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            set_count,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::INPUT_ATTACHMENT,
+                    descriptor_count: set_count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: set_count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: set_count * Self::MAX_TEXTURES as u32,
+                },
+            ],
+        )?;
+        let layouts = (0..set_count)
+            .map(|_| &descriptor_set_layout)
+            .collect::<Vec<_>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        // Unused texture slots repeat the last texture so every descriptor
+        // in the array is valid.
+        let texture_infos = (0..Self::MAX_TEXTURES)
+            .map(|index| vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: textures[index.min(textures.len() - 1)].raw(),
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            })
+            .collect::<Vec<_>>();
+        for (index, buffer) in buffers.iter().enumerate() {
+            let dst_set = descriptor_pool.descriptor_set(index);
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            };
+            render_device.device().update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 0,
+                        dst_array_element: 0,
+                        descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+                        descriptor_count: 1,
+                        p_image_info: input_infos.as_ptr(),
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 1,
+                        dst_array_element: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        p_buffer_info: &buffer_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 2,
+                        dst_array_element: 0,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: texture_infos.len() as u32,
+                        p_image_info: texture_infos.as_ptr(),
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                ],
+                &[],
+            );
+        }
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX
+                        | vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<DecalConstants>() as u32,
+                }],
+            )?;
+
+        // Only back faces are drawn, so each pixel is shaded once per decal
+        // even when the camera is inside the box.
+        let pipeline = GraphicsPipelineBuilder::new()
+            .vertex_shader(include_bytes!("./shaders/decal.vert.spv"))
+            .fragment_shader(include_bytes!("./shaders/decal.frag.spv"))
+            .cull_mode(
+                vk::CullModeFlags::FRONT,
+                vk::FrontFace::COUNTER_CLOCKWISE,
+            )
+            .color_blend_attachment(
+                GraphicsPipelineBuilder::alpha_blend_attachment(),
+            )
+            .depth_test(false)
+            .subpass(subpass)
+            .build(
+                render_device.clone(),
+                &pipeline_layout,
+                color_pass.render_pass(),
+            )?;
+        pipeline.set_debug_name("Decal Pipeline");
+
+        Ok(Self {
+            decals: Vec::with_capacity(max_decals),
+            oldest: 0,
+            max_decals,
+            extent: color_pass.extent(),
+            buffers,
+            buffer_ptrs,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            _sampler: sampler,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Add a decal, replacing the oldest decal when the renderer is full.
+    pub fn push(&mut self, decal: Decal) {
+        if self.decals.len() < self.max_decals {
+            self.decals.push(decal);
+        } else {
+            self.decals[self.oldest] = decal;
+            self.oldest = (self.oldest + 1) % self.max_decals;
+        }
+    }
+
+    /// Every decal, in no particular order. Edits are visible in the next
+    /// draw.
+    pub fn decals_mut(&mut self) -> &mut [Decal] {
+        &mut self.decals
+    }
+
+    /// Remove every decal.
+    pub fn clear(&mut self) {
+        self.decals.clear();
+        self.oldest = 0;
+    }
+
+    /// The number of decals which will be drawn.
+    pub fn len(&self) -> usize {
+        self.decals.len()
+    }
+
+    /// Returns true when there are no decals.
+    pub fn is_empty(&self) -> bool {
+        self.decals.is_empty()
+    }
+
+    /// Write the decals into the frame's buffer and add commands to draw
+    /// them.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be in the decal subpass
+    ///   - `view_projection` must be the transform the scene's depth was
+    ///     drawn with
+    pub unsafe fn draw(&self, frame: &Frame, view_projection: &Mat4) {
+        if self.decals.is_empty() {
+            return;
+        }
+
+        // SAFE because the frame's previous commands are complete, so its
+        // buffer is no longer in use.
+        let data = std::slice::from_raw_parts_mut(
+            self.buffer_ptrs[frame.frame_index()],
+            self.decals.len(),
+        );
+        for (gpu_decal, decal) in data.iter_mut().zip(&self.decals) {
+            *gpu_decal = DecalData::new(decal, view_projection);
+        }
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(frame.frame_index())],
+            &[],
+        );
+        let constants = DecalConstants {
+            world_from_clip: inverse(view_projection)
+                .unwrap_or_else(Mat4::identity),
+            viewport_size: [width as f32, height as f32],
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const DecalConstants as *const u8,
+                std::mem::size_of::<DecalConstants>(),
+            ),
+        );
+        device.cmd_draw(command_buffer, 36, self.decals.len() as u32, 0, 0);
+    }
+}
+
+// Private API
+// -----------
+
+impl DecalRenderer {
+    /// Allocate a host-visible storage buffer with room for `capacity`
+    /// decals.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not use the associated memory-mapped pointer
+    ///     once the buffer has been dropped.
+    unsafe fn allocate_buffer(
+        render_device: &Arc<RenderDevice>,
+        capacity: usize,
+    ) -> Result<(raii::Buffer, *mut DecalData), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (capacity * std::mem::size_of::<DecalData>()) as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Decals");
+        let ptr = buffer.allocation().map(render_device.device())?;
+        Ok((buffer, ptr as *mut DecalData))
+    }
+}
@@ -0,0 +1,32 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+#extension GL_EXT_nonuniform_qualifier : require
+
+#include "decal.glsl"
+
+layout(input_attachment_index = 0, set = 0, binding = 0)
+    uniform subpassInput scene_depth;
+layout(set = 0, binding = 2) uniform sampler2D textures[16];
+
+layout(location = 0) flat in uint decal_index;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Reconstruct the world-space position of the scene behind this pixel.
+    float depth = subpassLoad(scene_depth).r;
+    vec2 ndc = gl_FragCoord.xy / constants.viewport_size * 2.0 - 1.0;
+    vec4 world = constants.world_from_clip * vec4(ndc, depth, 1.0);
+    world /= world.w;
+
+    Decal decal = decals[decal_index];
+    vec3 local = (decal.local_from_world * world).xyz;
+    if (any(greaterThan(abs(local), vec3(0.5)))) {
+        discard;
+    }
+
+    vec2 uv = vec2(local.x + 0.5, 0.5 - local.y);
+    vec4 color = texture(textures[nonuniformEXT(decal.texture_index)], uv);
+    out_color = color * decal.color;
+}
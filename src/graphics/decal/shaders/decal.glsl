@@ -0,0 +1,22 @@
+// Shared declarations for the decal shaders. Must match decal.rs and
+// renderer.rs.
+
+struct Decal {
+    mat4 clip_from_local;
+    mat4 local_from_world;
+    vec4 color;
+    uint texture_index;
+    uint pad0;
+    uint pad1;
+    uint pad2;
+};
+
+layout(std430, set = 0, binding = 1) readonly buffer Decals {
+    Decal decals[];
+};
+
+// Must match DecalConstants in `decal/renderer.rs`.
+layout(push_constant) uniform DecalConstants {
+    mat4 world_from_clip;
+    vec2 viewport_size;
+} constants;
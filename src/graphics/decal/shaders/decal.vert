@@ -0,0 +1,34 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "decal.glsl"
+
+layout(location = 0) flat out uint decal_index;
+
+// The 36 corners of the unit cube's 12 triangles, wound counter-clockwise
+// when viewed from outside.
+const vec3 CORNERS[8] = vec3[](
+    vec3(-0.5, -0.5, -0.5),
+    vec3(0.5, -0.5, -0.5),
+    vec3(0.5, 0.5, -0.5),
+    vec3(-0.5, 0.5, -0.5),
+    vec3(-0.5, -0.5, 0.5),
+    vec3(0.5, -0.5, 0.5),
+    vec3(0.5, 0.5, 0.5),
+    vec3(-0.5, 0.5, 0.5)
+);
+const uint INDICES[36] = uint[](
+    4, 5, 6, 4, 6, 7, // +Z
+    1, 0, 3, 1, 3, 2, // -Z
+    5, 1, 2, 5, 2, 6, // +X
+    0, 4, 7, 0, 7, 3, // -X
+    7, 6, 2, 7, 2, 3, // +Y
+    0, 1, 5, 0, 5, 4  // -Y
+);
+
+void main() {
+    decal_index = gl_InstanceIndex;
+    vec3 corner = CORNERS[INDICES[gl_VertexIndex]];
+    gl_Position = decals[gl_InstanceIndex].clip_from_local * vec4(corner, 1.0);
+}
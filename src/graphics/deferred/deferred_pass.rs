@@ -0,0 +1,445 @@
+use {
+    super::GBuffer,
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice, Swapchain},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A render pass with two subpasses for deferred shading.
+///
+/// The geometry subpass writes surface attributes into the G-buffer's color
+/// attachments and depth buffer. The lighting subpass reads the G-buffer as
+/// input attachments and writes the final shaded color into the swapchain
+/// image.
+#[derive(Debug)]
+pub struct DeferredPass {
+    extent: vk::Extent2D,
+    format: vk::Format,
+    render_pass: raii::RenderPass,
+    framebuffers: Vec<raii::Framebuffer>,
+    image_views: Vec<raii::ImageView>,
+    g_buffer: GBuffer,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl DeferredPass {
+    /// The index of the subpass which writes the G-buffer.
+    pub const GEOMETRY_SUBPASS: u32 = 0;
+
+    /// The index of the subpass which reads the G-buffer and writes the
+    /// swapchain image.
+    pub const LIGHTING_SUBPASS: u32 = 1;
+
+    /// Create a deferred render pass, G-buffer, and framebuffers for every
+    /// swapchain image.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `swapchain` - the swapchain targeted by the lighting subpass
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the framebuffers are only valid while the swapchain exists
+    ///  - if the swapchain is rebuilt, the deferred pass must be rebuilt too,
+    ///    see `rebuild_for_swapchain`
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        swapchain: &Swapchain,
+    ) -> Result<Self, GraphicsError> {
+        let g_buffer = GBuffer::new(render_device.clone(), swapchain.extent())?;
+        let render_pass = Self::create_render_pass(
+            render_device.clone(),
+            swapchain.image_format(),
+        )?;
+        let image_views = Self::create_image_views(
+            render_device.clone(),
+            swapchain.image_format(),
+            swapchain.images(),
+        )?;
+        let framebuffers = Self::create_framebuffers(
+            render_device.clone(),
+            render_pass.raw(),
+            &g_buffer,
+            &image_views,
+        )?;
+
+        Ok(Self {
+            extent: swapchain.extent(),
+            format: swapchain.image_format(),
+            render_pass,
+            framebuffers,
+            image_views,
+            g_buffer,
+            render_device,
+        })
+    }
+
+    /// Rebuild the framebuffers, and the G-buffer if the extent changed, for
+    /// a rebuilt swapchain.
+    ///
+    /// The render pass is only replaced when the swapchain's image format
+    /// changed, so pipelines built against `render_pass` stay valid unless
+    /// `SwapchainChanges::format_changed` is true. The G-buffer's views are
+    /// replaced when the extent changes, so descriptors which reference them
+    /// must be rewritten, see `LightingResolve::rebuild_descriptors`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - every frame which used the old framebuffers or G-buffer must have
+    ///    finished executing
+    pub unsafe fn rebuild_for_swapchain(
+        &mut self,
+        swapchain: &Swapchain,
+    ) -> Result<(), GraphicsError> {
+        // Drop the old framebuffers before the views they reference.
+        self.framebuffers.clear();
+
+        if swapchain.image_format() != self.format {
+            self.render_pass = Self::create_render_pass(
+                self.render_device.clone(),
+                swapchain.image_format(),
+            )?;
+            self.format = swapchain.image_format();
+        }
+        if swapchain.extent() != self.extent {
+            self.g_buffer =
+                GBuffer::new(self.render_device.clone(), swapchain.extent())?;
+            self.extent = swapchain.extent();
+        }
+
+        self.image_views = Self::create_image_views(
+            self.render_device.clone(),
+            self.format,
+            swapchain.images(),
+        )?;
+        self.framebuffers = Self::create_framebuffers(
+            self.render_device.clone(),
+            self.render_pass.raw(),
+            &self.g_buffer,
+            &self.image_views,
+        )?;
+
+        Ok(())
+    }
+
+    /// The current extent.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The swapchain image format.
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// The current render pass.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// The G-buffer targeted by the geometry subpass.
+    pub fn g_buffer(&self) -> &GBuffer {
+        &self.g_buffer
+    }
+
+    /// Begin the render pass for the frame's swapchain image. Commands
+    /// recorded after this call target the geometry subpass.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `clear_color` - the color used to clear the swapchain image and the
+    ///   G-buffer's albedo attachment. The lighting subpass uses the albedo
+    ///   directly for pixels not covered by geometry.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the DeferredPass must not be destroyed until the command buffer
+    ///     finishes executing or is discarded.
+    pub unsafe fn begin_render_pass_inline(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+    ) {
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffers[frame.swapchain_image_index()].raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent(),
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+
+    /// Advance from the geometry subpass to the lighting subpass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the render pass must have been started with
+    ///     `begin_render_pass_inline` and still be in the geometry subpass.
+    pub unsafe fn next_subpass_inline(&self, frame: &Frame) {
+        self.render_device.device().cmd_next_subpass(
+            frame.command_buffer(),
+            vk::SubpassContents::INLINE,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl DeferredPass {
+    /// Create image views for each swapchain image.
+    unsafe fn create_image_views(
+        render_device: Arc<RenderDevice>,
+        format: vk::Format,
+        images: &[vk::Image],
+    ) -> Result<Vec<raii::ImageView>, GraphicsError> {
+        let mut image_views = vec![];
+        for image in images {
+            let create_info = vk::ImageViewCreateInfo {
+                image: *image,
+                format,
+                view_type: vk::ImageViewType::TYPE_2D,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                ..Default::default()
+            };
+            image_views.push(raii::ImageView::new(
+                render_device.clone(),
+                &create_info,
+            )?);
+        }
+        Ok(image_views)
+    }
+
+    /// Create a framebuffer for each swapchain image view. Every framebuffer
+    /// shares the same G-buffer attachments.
+    unsafe fn create_framebuffers(
+        render_device: Arc<RenderDevice>,
+        render_pass: vk::RenderPass,
+        g_buffer: &GBuffer,
+        image_views: &[raii::ImageView],
+    ) -> Result<Vec<raii::Framebuffer>, GraphicsError> {
+        let mut framebuffers = vec![];
+        let vk::Extent2D { width, height } = g_buffer.extent();
+        for image_view in image_views {
+            let attachments = [
+                image_view.raw(),
+                g_buffer.albedo_view().raw(),
+                g_buffer.normal_view().raw(),
+                g_buffer.position_view().raw(),
+                g_buffer.depth_view().raw(),
+            ];
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass,
+                attachment_count: attachments.len() as u32,
+                p_attachments: attachments.as_ptr(),
+                width,
+                height,
+                layers: 1,
+                ..Default::default()
+            };
+            framebuffers.push(raii::Framebuffer::new(
+                render_device.clone(),
+                &create_info,
+            )?);
+        }
+        Ok(framebuffers)
+    }
+
+    /// Create the two-subpass render pass.
+    ///
+    /// Attachments are ordered: swapchain color, albedo, normal, position,
+    /// depth.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+        format: vk::Format,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let g_buffer_attachment =
+            |attachment_format: vk::Format| vk::AttachmentDescription {
+                format: attachment_format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            };
+        let attachments = [
+            // The swapchain color attachment
+            vk::AttachmentDescription {
+                format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            g_buffer_attachment(GBuffer::ALBEDO_FORMAT),
+            g_buffer_attachment(GBuffer::NORMAL_FORMAT),
+            g_buffer_attachment(GBuffer::POSITION_FORMAT),
+            vk::AttachmentDescription {
+                format: GBuffer::DEPTH_FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+        ];
+
+        let geometry_color_attachments =
+            [1, 2, 3].map(|attachment| vk::AttachmentReference {
+                attachment,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            });
+        let geometry_depth_attachment = vk::AttachmentReference {
+            attachment: 4,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let lighting_input_attachments =
+            [1, 2, 3].map(|attachment| vk::AttachmentReference {
+                attachment,
+                layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            });
+        let lighting_color_attachments = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let subpasses = [
+            vk::SubpassDescription {
+                flags: vk::SubpassDescriptionFlags::empty(),
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                color_attachment_count: geometry_color_attachments.len() as u32,
+                p_color_attachments: geometry_color_attachments.as_ptr(),
+                p_depth_stencil_attachment: &geometry_depth_attachment,
+                ..Default::default()
+            },
+            vk::SubpassDescription {
+                flags: vk::SubpassDescriptionFlags::empty(),
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                input_attachment_count: lighting_input_attachments.len() as u32,
+                p_input_attachments: lighting_input_attachments.as_ptr(),
+                color_attachment_count: lighting_color_attachments.len() as u32,
+                p_color_attachments: lighting_color_attachments.as_ptr(),
+                ..Default::default()
+            },
+        ];
+
+        let dependencies = [
+            // The G-buffer is shared between frames in flight, so the
+            // geometry subpass must wait for the previous frame's geometry
+            // writes and lighting reads to finish.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: Self::GEOMETRY_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // The lighting subpass reads the G-buffer written by the geometry
+            // subpass.
+            vk::SubpassDependency {
+                src_subpass: Self::GEOMETRY_SUBPASS,
+                dst_subpass: Self::LIGHTING_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::INPUT_ATTACHMENT_READ,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            },
+            // The swapchain image is acquired with a semaphore which waits at
+            // COLOR_ATTACHMENT_OUTPUT, see ColorPass for details.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: Self::LIGHTING_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::NONE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+
+        let create_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
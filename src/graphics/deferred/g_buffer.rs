@@ -0,0 +1,190 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The per-pixel surface attributes written by the geometry subpass and read
+/// by the lighting subpass.
+///
+/// A single GBuffer is shared by every frame in flight. The render pass
+/// dependencies in the DeferredPass keep frames from stomping on each other's
+/// attachments.
+#[derive(Debug)]
+pub struct GBuffer {
+    extent: vk::Extent2D,
+    albedo: (raii::Image, raii::ImageView),
+    normal: (raii::Image, raii::ImageView),
+    position: (raii::Image, raii::ImageView),
+    depth: (raii::Image, raii::ImageView),
+}
+
+// Public API
+// ----------
+
+impl GBuffer {
+    /// The format of the albedo attachment.
+    pub const ALBEDO_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    /// The format of the normal attachment. The w component is 1.0 wherever
+    /// geometry was rendered and 0.0 elsewhere.
+    pub const NORMAL_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The format of the position attachment.
+    pub const POSITION_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The format of the depth attachment.
+    pub const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+    /// Create all of the G-buffer attachments.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `extent` - the size of every attachment in pixels
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the G-buffer must be dropped before the render device
+    ///   - the application must not drop the G-buffer while it is in use by
+    ///     the GPU
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        let color_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | vk::ImageUsageFlags::INPUT_ATTACHMENT;
+        let albedo = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::ALBEDO_FORMAT,
+            color_usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        albedo.0.set_debug_name("GBuffer Albedo");
+
+        let normal = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::NORMAL_FORMAT,
+            color_usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        normal.0.set_debug_name("GBuffer Normal");
+
+        let position = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::POSITION_FORMAT,
+            color_usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        position.0.set_debug_name("GBuffer Position");
+
+        let depth = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::DEPTH_FORMAT,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::ImageAspectFlags::DEPTH,
+        )?;
+        depth.0.set_debug_name("GBuffer Depth");
+
+        Ok(Self {
+            extent,
+            albedo,
+            normal,
+            position,
+            depth,
+        })
+    }
+
+    /// The size of every attachment in pixels.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The albedo attachment's image view.
+    pub fn albedo_view(&self) -> &raii::ImageView {
+        &self.albedo.1
+    }
+
+    /// The normal attachment's image view.
+    pub fn normal_view(&self) -> &raii::ImageView {
+        &self.normal.1
+    }
+
+    /// The position attachment's image view.
+    pub fn position_view(&self) -> &raii::ImageView {
+        &self.position.1
+    }
+
+    /// The depth attachment's image view.
+    pub fn depth_view(&self) -> &raii::ImageView {
+        &self.depth.1
+    }
+}
+
+// Private API
+// -----------
+
+impl GBuffer {
+    /// Create a single device-local attachment image and a view for it.
+    unsafe fn create_attachment(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new_with_hint(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            raii::AllocationHint::Dedicated,
+        )?;
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        let image_view =
+            raii::ImageView::new(render_device.clone(), &view_create_info)?;
+
+        Ok((image, image_view))
+    }
+}
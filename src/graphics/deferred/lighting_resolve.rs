@@ -0,0 +1,416 @@
+use {
+    super::DeferredPass,
+    crate::graphics::{
+        post::Ssao,
+        vulkan_api::{raii, Frame, GraphicsPipelineBuilder, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters for the directional light applied by the lighting subpass.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct LightingParams {
+    /// The direction the light travels. Only xyz is used.
+    pub light_direction: [f32; 4],
+
+    /// The light's color. Alpha is ignored.
+    pub light_color: [f32; 4],
+
+    /// Ambient light added to every lit surface. Alpha is ignored.
+    pub ambient_color: [f32; 4],
+}
+
+impl Default for LightingParams {
+    fn default() -> Self {
+        Self {
+            light_direction: [0.0, 0.0, 1.0, 0.0],
+            light_color: [1.0, 1.0, 1.0, 1.0],
+            ambient_color: [0.1, 0.1, 0.1, 1.0],
+        }
+    }
+}
+
+/// A fullscreen pass which runs in the DeferredPass's lighting subpass and
+/// resolves the G-buffer into a shaded image.
+///
+/// The ambient term can be darkened by screen-space ambient occlusion. The
+/// DeferredPass can't run compute work between its subpasses, so the
+/// occlusion comes from a DepthPrePass rendered, and an Ssao recorded, before
+/// the DeferredPass begins.
+pub struct LightingResolve {
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    sampler: Option<raii::Sampler>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl LightingResolve {
+    /// Create the lighting pipeline and a descriptor set which references the
+    /// deferred pass's G-buffer.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - `rebuild_descriptors` must be called any time the DeferredPass is
+    ///     rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        deferred_pass: &DeferredPass,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_optional_ambient_occlusion(
+            render_device,
+            deferred_pass,
+            None,
+        )
+    }
+
+    /// Create the lighting pipeline with a descriptor set which references
+    /// the deferred pass's G-buffer and the ambient occlusion image.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `deferred_pass` - provides the G-buffer
+    /// * `ssao` - provides the ambient occlusion, must be the same size as
+    ///   the deferred pass
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - `rebuild_descriptors` must be called any time the DeferredPass or
+    ///     Ssao is rebuilt
+    ///   - the Ssao must be recorded before the DeferredPass begins in every
+    ///     frame
+    pub unsafe fn new_with_ambient_occlusion(
+        render_device: Arc<RenderDevice>,
+        deferred_pass: &DeferredPass,
+        ssao: &Ssao,
+    ) -> Result<Self, GraphicsError> {
+        Self::check_ssao_extent(deferred_pass, Some(ssao))?;
+        Self::new_with_optional_ambient_occlusion(
+            render_device,
+            deferred_pass,
+            Some(ssao),
+        )
+    }
+
+    /// Point the descriptor set at a rebuilt DeferredPass's G-buffer.
+    ///
+    /// The pipeline only depends on the render pass, so it's kept. Call
+    /// `rebuild_pipeline` as well when the swapchain format changed.
+    ///
+    /// # Params
+    ///
+    /// * `deferred_pass` - the rebuilt deferred pass
+    /// * `ssao` - the rebuilt ambient occlusion. Required when, and only
+    ///   when, the LightingResolve was created with ambient occlusion.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - every frame which used the old descriptors must have finished
+    ///     executing
+    pub unsafe fn rebuild_descriptors(
+        &mut self,
+        deferred_pass: &DeferredPass,
+        ssao: Option<&Ssao>,
+    ) -> Result<(), GraphicsError> {
+        if ssao.is_some() != self.sampler.is_some() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "A LightingResolve must be rebuilt with an Ssao if and only \
+                 if it was created with one"
+            )));
+        }
+        Self::check_ssao_extent(deferred_pass, ssao)?;
+        self.write_descriptor_set(deferred_pass, ssao);
+        self.extent = deferred_pass.extent();
+        Ok(())
+    }
+
+    /// Rebuild the lighting pipeline for the DeferredPass's current render
+    /// pass. Only needed when the render pass was replaced, e.g. when a
+    /// swapchain rebuild changed the image format.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - every frame which used the old pipeline must have finished
+    ///     executing
+    pub unsafe fn rebuild_pipeline(
+        &mut self,
+        deferred_pass: &DeferredPass,
+    ) -> Result<(), GraphicsError> {
+        self.pipeline = Self::create_pipeline(
+            self.render_device.clone(),
+            &self.pipeline_layout,
+            deferred_pass,
+            self.sampler.is_some(),
+        )?;
+        Ok(())
+    }
+
+    /// Add commands to shade every pixel using the G-buffer.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the DeferredPass must already be in the lighting subpass.
+    pub unsafe fn draw(&self, frame: &Frame, params: &LightingParams) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            frame.command_buffer(),
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        device.cmd_push_constants(
+            frame.command_buffer(),
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                params as *const LightingParams as *const u8,
+                std::mem::size_of::<LightingParams>(),
+            ),
+        );
+
+        // A single triangle which covers the entire screen.
+        device.cmd_draw(frame.command_buffer(), 3, 1, 0, 0);
+    }
+}
+
+// Private API
+// -----------
+
+impl LightingResolve {
+    unsafe fn new_with_optional_ambient_occlusion(
+        render_device: Arc<RenderDevice>,
+        deferred_pass: &DeferredPass,
+        ssao: Option<&Ssao>,
+    ) -> Result<Self, GraphicsError> {
+        let mut bindings = [0, 1, 2]
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .to_vec();
+        if ssao.is_some() {
+            bindings.push(vk::DescriptorSetLayoutBinding {
+                binding: 3,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..vk::DescriptorSetLayoutBinding::default()
+            });
+        }
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<LightingParams>() as u32,
+                }],
+            )?;
+        let pipeline = Self::create_pipeline(
+            render_device.clone(),
+            &pipeline_layout,
+            deferred_pass,
+            ssao.is_some(),
+        )?;
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::INPUT_ATTACHMENT,
+                    descriptor_count: 3,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        // The occlusion is read with texelFetch, so the sampler never
+        // filters.
+        let sampler = match ssao {
+            Some(_) => {
+                let create_info = vk::SamplerCreateInfo {
+                    mag_filter: vk::Filter::NEAREST,
+                    min_filter: vk::Filter::NEAREST,
+                    mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                    address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    max_lod: 0.0,
+                    ..Default::default()
+                };
+                Some(raii::Sampler::new(render_device.clone(), &create_info)?)
+            }
+            None => None,
+        };
+
+        let lighting_resolve = Self {
+            extent: deferred_pass.extent(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            sampler,
+            render_device,
+        };
+        lighting_resolve.write_descriptor_set(deferred_pass, ssao);
+        Ok(lighting_resolve)
+    }
+
+    /// Create the pipeline which runs in the lighting subpass.
+    unsafe fn create_pipeline(
+        render_device: Arc<RenderDevice>,
+        pipeline_layout: &raii::PipelineLayout,
+        deferred_pass: &DeferredPass,
+        ambient_occlusion: bool,
+    ) -> Result<raii::Pipeline, GraphicsError> {
+        let fragment_shader: &[u8] = if ambient_occlusion {
+            include_bytes!("./shaders/lighting_ao.frag.spv")
+        } else {
+            include_bytes!("./shaders/lighting.frag.spv")
+        };
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!("./shaders/lighting.vert.spv"))
+                .fragment_shader(fragment_shader)
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .subpass(DeferredPass::LIGHTING_SUBPASS)
+                .build(
+                    render_device,
+                    pipeline_layout,
+                    deferred_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Deferred Lighting Pipeline");
+        Ok(pipeline)
+    }
+
+    /// Point the descriptor set at the G-buffer and, when present, the
+    /// ambient occlusion image.
+    unsafe fn write_descriptor_set(
+        &self,
+        deferred_pass: &DeferredPass,
+        ssao: Option<&Ssao>,
+    ) {
+        let g_buffer = deferred_pass.g_buffer();
+        let mut image_infos = [
+            g_buffer.albedo_view(),
+            g_buffer.normal_view(),
+            g_buffer.position_view(),
+        ]
+        .map(|image_view| vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: image_view.raw(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        })
+        .to_vec();
+        if let (Some(ssao), Some(sampler)) = (ssao, &self.sampler) {
+            image_infos.push(vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: ssao.output_view().raw(),
+                image_layout: vk::ImageLayout::GENERAL,
+            });
+        }
+
+        let writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, image_info)| vk::WriteDescriptorSet {
+                dst_set: self.descriptor_pool.descriptor_set(0),
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: if binding < 3 {
+                    vk::DescriptorType::INPUT_ATTACHMENT
+                } else {
+                    vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                },
+                descriptor_count: 1,
+                p_image_info: image_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        self.render_device
+            .device()
+            .update_descriptor_sets(&writes, &[]);
+    }
+
+    /// The ambient occlusion must cover the same pixels as the G-buffer.
+    fn check_ssao_extent(
+        deferred_pass: &DeferredPass,
+        ssao: Option<&Ssao>,
+    ) -> Result<(), GraphicsError> {
+        match ssao {
+            Some(ssao) if ssao.extent() != deferred_pass.extent() => {
+                Err(GraphicsError::RuntimeError(anyhow!(
+                    "The Ssao extent {:?} must match the DeferredPass extent \
+                     {:?}",
+                    ssao.extent(),
+                    deferred_pass.extent()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
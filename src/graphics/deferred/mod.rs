@@ -0,0 +1,16 @@
+//! A starting point for deferred shading.
+//!
+//! The DeferredPass renders geometry into a G-buffer in its first subpass,
+//! then the LightingResolve shades every pixel from the G-buffer in the second
+//! subpass. The lighting can use ambient occlusion from a post::Ssao which
+//! runs on a DepthPrePass before the DeferredPass begins.
+
+mod deferred_pass;
+mod g_buffer;
+mod lighting_resolve;
+
+pub use self::{
+    deferred_pass::DeferredPass,
+    g_buffer::GBuffer,
+    lighting_resolve::{LightingParams, LightingResolve},
+};
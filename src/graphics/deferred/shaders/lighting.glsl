@@ -0,0 +1,42 @@
+// The body of the lighting subpass, shared by lighting.frag and
+// lighting_ao.frag. Define AMBIENT_OCCLUSION before including it to scale the
+// ambient term by an Ssao image.
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput g_albedo;
+layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput g_normal;
+layout(input_attachment_index = 2, set = 0, binding = 2) uniform subpassInput g_position;
+
+#ifdef AMBIENT_OCCLUSION
+layout(set = 0, binding = 3) uniform sampler2D ambient_occlusion;
+#endif
+
+layout(push_constant) uniform LightingParams {
+    vec4 light_direction;
+    vec4 light_color;
+    vec4 ambient_color;
+} params;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec4 albedo = subpassLoad(g_albedo);
+    vec4 normal = subpassLoad(g_normal);
+
+    // The normal's w component is only set where geometry was rendered.
+    if (normal.w < 0.5) {
+        out_color = albedo;
+        return;
+    }
+
+    vec3 n = normalize(normal.xyz);
+    vec3 l = normalize(-params.light_direction.xyz);
+    float diffuse = max(dot(n, l), 0.0);
+
+    vec3 ambient = params.ambient_color.rgb;
+#ifdef AMBIENT_OCCLUSION
+    ambient *= texelFetch(ambient_occlusion, ivec2(gl_FragCoord.xy), 0).r;
+#endif
+
+    vec3 lit = albedo.rgb * (ambient + diffuse * params.light_color.rgb);
+    out_color = vec4(lit, albedo.a);
+}
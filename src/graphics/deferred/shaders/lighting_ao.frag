@@ -0,0 +1,5 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+
+#define AMBIENT_OCCLUSION
+#include "lighting.glsl"
@@ -1,22 +0,0 @@
-use {
-    ash::vk, ccthw_ash_allocator::AllocatorError,
-    ccthw_ash_instance::InstanceError, thiserror::Error,
-};
-
-#[derive(Debug, Error)]
-pub enum GraphicsError {
-    #[error("No suitable physical device could be found!")]
-    NoSuitablePhysicalDevice,
-
-    #[error(transparent)]
-    RuntimeError(#[from] anyhow::Error),
-
-    #[error(transparent)]
-    InstanceError(#[from] InstanceError),
-
-    #[error(transparent)]
-    AllocatorError(#[from] AllocatorError),
-
-    #[error(transparent)]
-    VulkanError(#[from] vk::Result),
-}
@@ -0,0 +1,72 @@
+use {ash::vk, ccthw_ash_allocator::AllocatorError, thiserror::Error};
+
+/// Errors raised while allocating device memory and descriptor sets.
+#[derive(Debug, Error)]
+pub enum AllocationError {
+    #[error("Out of host memory!")]
+    OutOfHostMemory,
+
+    #[error("Out of device memory!")]
+    OutOfDeviceMemory,
+
+    #[error("The descriptor pool is too fragmented to allocate from!")]
+    FragmentedPool,
+
+    #[error("The descriptor pool is out of memory!")]
+    OutOfPoolMemory,
+
+    #[error(
+        "Unable to allocate a {size} byte buffer with usage {usage:?} in \
+         {memory_properties:?} memory: {source}"
+    )]
+    Buffer {
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memory_properties: vk::MemoryPropertyFlags,
+        source: AllocatorError,
+    },
+
+    #[error(
+        "Unable to allocate a {}x{}x{} {:?} image in {:?} memory: {}",
+        extent.width,
+        extent.height,
+        extent.depth,
+        format,
+        memory_properties,
+        source
+    )]
+    Image {
+        extent: vk::Extent3D,
+        format: vk::Format,
+        memory_properties: vk::MemoryPropertyFlags,
+        source: AllocatorError,
+    },
+
+    #[error(
+        "{count} elements of {element_size} bytes are too large to allocate"
+    )]
+    SizeOverflow { count: usize, element_size: usize },
+
+    #[error("Unexpected allocation error: {0}")]
+    Other(vk::Result),
+}
+
+impl AllocationError {
+    /// Returns true when a descriptor pool is full or fragmented. Allocating
+    /// from a new pool recovers from the error.
+    pub fn is_descriptor_pool_exhausted(&self) -> bool {
+        matches!(self, Self::FragmentedPool | Self::OutOfPoolMemory)
+    }
+}
+
+impl From<vk::Result> for AllocationError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Self::OutOfHostMemory,
+            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Self::OutOfDeviceMemory,
+            vk::Result::ERROR_FRAGMENTED_POOL => Self::FragmentedPool,
+            vk::Result::ERROR_OUT_OF_POOL_MEMORY => Self::OutOfPoolMemory,
+            _ => Self::Other(result),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use {
+    ash::vk, ccthw_ash_allocator::AllocatorError,
+    ccthw_ash_instance::InstanceError, thiserror::Error,
+};
+
+mod allocation_error;
+mod pipeline_error;
+mod shader_error;
+mod swapchain_error;
+
+pub use self::{
+    allocation_error::AllocationError, pipeline_error::PipelineError,
+    shader_error::ShaderError, swapchain_error::SwapchainError,
+};
+
+#[derive(Debug, Error)]
+pub enum GraphicsError {
+    #[error("No suitable physical device could be found!")]
+    NoSuitablePhysicalDevice,
+
+    #[error("The device extension {0} is not enabled!")]
+    DeviceExtensionNotEnabled(String),
+
+    #[error("The render device was created without a window surface!")]
+    NoWindowSurface,
+
+    #[error(transparent)]
+    Swapchain(#[from] SwapchainError),
+
+    #[error(transparent)]
+    Pipeline(#[from] PipelineError),
+
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+
+    #[error(transparent)]
+    Allocation(#[from] AllocationError),
+
+    #[error(transparent)]
+    RuntimeError(#[from] anyhow::Error),
+
+    #[error(transparent)]
+    InstanceError(#[from] InstanceError),
+
+    #[error(transparent)]
+    AllocatorError(#[from] AllocatorError),
+
+    #[error(transparent)]
+    VulkanError(#[from] vk::Result),
+}
+
+impl GraphicsError {
+    /// Find an error of a specific type anywhere in this error's chain.
+    ///
+    /// Errors are frequently wrapped with extra context on their way up the
+    /// stack, so matching on the outermost variant isn't enough to detect
+    /// recoverable cases. For example, `find::<AllocationError>()` finds a
+    /// fragmented descriptor pool even when the error was wrapped with
+    /// `anyhow::Context`.
+    pub fn find<E>(&self) -> Option<&E>
+    where
+        E: std::error::Error + 'static,
+    {
+        let mut current: Option<&(dyn std::error::Error + 'static)> =
+            Some(self);
+        while let Some(error) = current {
+            if let Some(found) = error.downcast_ref::<E>() {
+                return Some(found);
+            }
+            if let Some(GraphicsError::RuntimeError(inner)) =
+                error.downcast_ref::<GraphicsError>()
+            {
+                // anyhow's source() skips the outermost error, so walk the
+                // full chain instead.
+                return inner.chain().find_map(|error| {
+                    error.downcast_ref::<E>().or_else(|| {
+                        error
+                            .downcast_ref::<GraphicsError>()
+                            .and_then(|graphics_error| graphics_error.find())
+                    })
+                });
+            }
+            current = error.source();
+        }
+        None
+    }
+
+    /// Returns true when the error was caused by an out of date swapchain,
+    /// which can be recovered from by rebuilding the swapchain.
+    pub fn is_swapchain_out_of_date(&self) -> bool {
+        matches!(
+            self.find::<SwapchainError>(),
+            Some(SwapchainError::OutOfDate)
+        )
+    }
+
+    /// Returns true when the error was caused by a descriptor pool which is
+    /// full or fragmented, which can be recovered from by allocating from a
+    /// new pool.
+    pub fn is_descriptor_pool_exhausted(&self) -> bool {
+        self.find::<AllocationError>()
+            .map(AllocationError::is_descriptor_pool_exhausted)
+            .unwrap_or(false)
+    }
+}
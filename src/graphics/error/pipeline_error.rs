@@ -0,0 +1,21 @@
+use {super::ShaderError, ash::vk, thiserror::Error};
+
+/// Errors raised while creating pipelines and pipeline layouts.
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error("Unable to create a {kind} pipeline: {result}")]
+    CreateFailed {
+        /// The kind of pipeline, e.g. "graphics" or "compute".
+        kind: &'static str,
+        result: vk::Result,
+    },
+
+    #[error(
+        "The pipeline uses {size} bytes of push constants but the device \
+         only supports {max}!"
+    )]
+    PushConstantsTooLarge { size: u32, max: u32 },
+
+    #[error(transparent)]
+    Shader(#[from] ShaderError),
+}
@@ -0,0 +1,20 @@
+use {ash::vk, thiserror::Error};
+
+/// Errors raised while loading shader modules.
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error(
+        "Invalid SPIRV! The source is {0} bytes, which isn't a multiple of \
+         4 bytes."
+    )]
+    InvalidLength(usize),
+
+    #[error(
+        "Invalid SPIRV! Expected the magic number 0x07230203 but found \
+         {0:#010x}."
+    )]
+    InvalidMagicNumber(u32),
+
+    #[error("Unable to create a shader module: {0}")]
+    CreateFailed(vk::Result),
+}
@@ -0,0 +1,44 @@
+use {ash::vk, thiserror::Error};
+
+/// Errors raised while creating, acquiring from, or presenting to the
+/// swapchain.
+#[derive(Debug, Error)]
+pub enum SwapchainError {
+    #[error("The swapchain is out of date and must be rebuilt!")]
+    OutOfDate,
+
+    #[error("The window surface was lost!")]
+    SurfaceLost,
+
+    #[error("The window surface does not support any image formats!")]
+    NoSurfaceFormats,
+
+    #[error(
+        "Unable to create a {}x{} {:?} swapchain with {:?}: {}",
+        extent.width,
+        extent.height,
+        format,
+        present_mode,
+        result
+    )]
+    CreateFailed {
+        extent: vk::Extent2D,
+        format: vk::Format,
+        present_mode: vk::PresentModeKHR,
+        result: vk::Result,
+    },
+
+    #[error("Unable to acquire a swapchain image: {0}")]
+    AcquireFailed(vk::Result),
+
+    #[error("Unable to present swapchain image {index}: {result}")]
+    PresentFailed { index: usize, result: vk::Result },
+}
+
+impl SwapchainError {
+    /// Returns true when rebuilding the swapchain can recover from the
+    /// error.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, Self::OutOfDate)
+    }
+}
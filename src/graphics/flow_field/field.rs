@@ -0,0 +1,576 @@
+use {
+    super::{FlowFieldGenerator, FlowFieldParams},
+    crate::{
+        graphics::{
+            vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+            GraphicsError,
+        },
+        math::Vec3,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A 2D or 3D grid of vectors which lives on both the CPU and the GPU.
+///
+/// The GPU copy is an RGBA32F image which stays in the GENERAL layout, so it
+/// can be written as a storage image by generator shaders and sampled by
+/// particle shaders without layout transitions. The CPU copy is used to
+/// sample the field and advect geometry on the CPU.
+///
+/// The two copies are only synchronized by `upload` and `download`, and by
+/// the blocking `write_from_fn` and `generate` helpers.
+///
+/// Fields tile: sampling wraps around at the edges on both the CPU and the
+/// GPU.
+pub struct FlowField {
+    /// The world-space position of the field's (0, 0, 0) corner.
+    pub origin: Vec3,
+
+    /// The world-space size covered by one tile of the field. 2D fields
+    /// ignore the z component.
+    pub size: Vec3,
+
+    extent: vk::Extent3D,
+    vectors: Vec<Vec3>,
+    staging_buffer: raii::Buffer,
+    one_time_submit: OneTimeSubmitCommandBuffer,
+    sampler: raii::Sampler,
+    image_view: raii::ImageView,
+    image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl FlowField {
+    /// The format of the field's image.
+    pub const FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+
+    /// Create a flow field where every vector is zero.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `extent` - the number of vectors along each axis. A depth of 1
+    ///   creates a 2D field, anything larger creates a 3D field.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        extent: vk::Extent3D,
+    ) -> Result<Self, GraphicsError> {
+        if extent.width == 0 || extent.height == 0 || extent.depth == 0 {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Unable to create a flow field with extent {:?}",
+                extent
+            )));
+        }
+        let (image, image_view) = Self::create_image(&render_device, extent)?;
+        let texel_count =
+            (extent.width * extent.height * extent.depth) as usize;
+
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let staging_buffer = raii::Buffer::new(
+            render_device.clone(),
+            &vk::BufferCreateInfo {
+                size: (texel_count * std::mem::size_of::<[f32; 4]>()) as u64,
+                usage: vk::BufferUsageFlags::TRANSFER_SRC
+                    | vk::BufferUsageFlags::TRANSFER_DST,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                queue_family_index_count: 1,
+                p_queue_family_indices: &queue_family_index,
+                ..Default::default()
+            },
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging_buffer.set_debug_name("Flow Field Staging Buffer");
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::REPEAT,
+                address_mode_v: vk::SamplerAddressMode::REPEAT,
+                address_mode_w: vk::SamplerAddressMode::REPEAT,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+
+        let mut flow_field = Self {
+            origin: Vec3::zeros(),
+            size: Vec3::new(1.0, 1.0, 1.0),
+            extent,
+            vectors: vec![Vec3::zeros(); texel_count],
+            staging_buffer,
+            one_time_submit,
+            sampler,
+            image_view,
+            image,
+            render_device,
+        };
+        flow_field.clear()?;
+        Ok(flow_field)
+    }
+
+    /// The number of vectors along each axis.
+    pub fn extent(&self) -> vk::Extent3D {
+        self.extent
+    }
+
+    /// True when the field has more than one layer of vectors.
+    pub fn is_3d(&self) -> bool {
+        self.extent.depth > 1
+    }
+
+    /// The field's image. It is always in the GENERAL layout.
+    pub fn image(&self) -> &raii::Image {
+        &self.image
+    }
+
+    /// A 2D or 3D view of the field's image, matching `is_3d`.
+    pub fn image_view(&self) -> &raii::ImageView {
+        &self.image_view
+    }
+
+    /// The linear, repeating sampler used to read the field on the GPU.
+    pub fn sampler(&self) -> &raii::Sampler {
+        &self.sampler
+    }
+
+    /// Descriptor info for reading the field as a COMBINED_IMAGE_SAMPLER.
+    pub fn sampled_descriptor_image_info(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            sampler: self.sampler.raw(),
+            image_view: self.image_view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        }
+    }
+
+    /// Descriptor info for reading or writing the field as a STORAGE_IMAGE.
+    pub fn storage_descriptor_image_info(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: self.image_view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        }
+    }
+
+    /// The CPU copy of the field, ordered x first, then y, then z.
+    pub fn vectors(&self) -> &[Vec3] {
+        &self.vectors
+    }
+
+    /// Mutable access to the CPU copy of the field. Call `upload` to send
+    /// changes to the GPU.
+    pub fn vectors_mut(&mut self) -> &mut [Vec3] {
+        &mut self.vectors
+    }
+
+    /// Sample the CPU copy of the field at a world-space position.
+    ///
+    /// Vectors are interpolated the same way the GPU sampler interpolates
+    /// them, so geometry advected on the CPU follows the same paths as
+    /// particles advected on the GPU.
+    pub fn sample(&self, position: &Vec3) -> Vec3 {
+        let mut uv = (position - self.origin).component_div(&self.size);
+        if !self.is_3d() {
+            uv.z = 0.5;
+        }
+        self.sample_uv(&uv)
+    }
+
+    /// Sample the CPU copy of the field at a texture coordinate, where
+    /// [0, 1] covers one tile of the field on each axis.
+    pub fn sample_uv(&self, uv: &Vec3) -> Vec3 {
+        let size = [self.extent.width, self.extent.height, self.extent.depth];
+
+        // Texel centers are at i + 0.5, just like a linear sampler.
+        let texel = [0, 1, 2].map(|axis| uv[axis] * size[axis] as f32 - 0.5);
+        let base = texel.map(|x| x.floor() as i64);
+        let t = texel.map(|x| x - x.floor());
+
+        let mut result = Vec3::zeros();
+        for corner in 0..8_i64 {
+            let mut weight = 1.0;
+            let mut index = [0_usize; 3];
+            for (axis, coordinate) in index.iter_mut().enumerate() {
+                let offset = (corner >> axis) & 1;
+                weight *= if offset == 1 { t[axis] } else { 1.0 - t[axis] };
+                *coordinate = (base[axis] + offset)
+                    .rem_euclid(size[axis] as i64)
+                    as usize;
+            }
+            if weight > 0.0 {
+                result += self.vectors[self.index(index)] * weight;
+            }
+        }
+        result
+    }
+
+    /// Move a position through the field for `dt` seconds with a single
+    /// midpoint (RK2) step.
+    pub fn advect(&self, position: &Vec3, dt: f32) -> Vec3 {
+        let midpoint = position + self.sample(position) * (0.5 * dt);
+        position + self.sample(&midpoint) * dt
+    }
+
+    /// Follow the field from `start` and return every point along the way.
+    ///
+    /// The result holds `steps + 1` points, starting with `start`, which is
+    /// handy for drawing streamlines as polylines.
+    pub fn trace(&self, start: &Vec3, dt: f32, steps: usize) -> Vec<Vec3> {
+        let mut points = Vec::with_capacity(steps + 1);
+        let mut position = *start;
+        points.push(position);
+        for _ in 0..steps {
+            position = self.advect(&position, dt);
+            points.push(position);
+        }
+        points
+    }
+
+    /// Replace every vector with the result of a function and upload the
+    /// field to the GPU.
+    ///
+    /// The function is called with the texture coordinate of each vector's
+    /// center, in [0, 1) on each axis. 2D fields always pass 0.5 for z.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the field must not be in use by the GPU
+    pub unsafe fn write_from_fn<F>(
+        &mut self,
+        mut f: F,
+    ) -> Result<(), GraphicsError>
+    where
+        F: FnMut(Vec3) -> Vec3,
+    {
+        let vk::Extent3D {
+            width,
+            height,
+            depth,
+        } = self.extent;
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    let uv = Vec3::new(
+                        (x as f32 + 0.5) / width as f32,
+                        (y as f32 + 0.5) / height as f32,
+                        (z as f32 + 0.5) / depth as f32,
+                    );
+                    let index =
+                        self.index([x as usize, y as usize, z as usize]);
+                    self.vectors[index] = f(uv);
+                }
+            }
+        }
+        self.upload()
+    }
+
+    /// Run a generator over the field and copy the result back to the CPU.
+    ///
+    /// This blocks until the GPU finishes. Use
+    /// `FlowFieldGenerator::cmd_generate` to regenerate the field every frame
+    /// without waiting.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the field must not be in use by the GPU
+    pub unsafe fn generate(
+        &mut self,
+        generator: &mut FlowFieldGenerator,
+        params: &FlowFieldParams,
+    ) -> Result<(), GraphicsError> {
+        generator.cmd_generate(
+            self.one_time_submit.command_buffer(),
+            self,
+            params,
+        )?;
+        self.one_time_submit.sync_submit_and_reset()?;
+        self.download()
+    }
+
+    /// Copy the CPU copy of the field to the GPU. Blocks until the copy
+    /// completes.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the field must not be in use by the GPU
+    pub unsafe fn upload(&mut self) -> Result<(), GraphicsError> {
+        {
+            let device = self.render_device.device();
+            let ptr =
+                self.staging_buffer.allocation().map(device)? as *mut [f32; 4];
+            for (i, vector) in self.vectors.iter().enumerate() {
+                ptr.add(i).write([vector.x, vector.y, vector.z, 0.0]);
+            }
+            self.staging_buffer.allocation().unmap(device)?;
+        }
+
+        let command_buffer = self.one_time_submit.command_buffer();
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+                src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COPY,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                ..Default::default()
+            },
+        );
+        self.render_device.device().cmd_copy_buffer_to_image2(
+            command_buffer,
+            &vk::CopyBufferToImageInfo2 {
+                src_buffer: self.staging_buffer.raw(),
+                dst_image: self.image.raw(),
+                dst_image_layout: vk::ImageLayout::GENERAL,
+                region_count: 1,
+                p_regions: &self.copy_region(),
+                ..Default::default()
+            },
+        );
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: Self::shader_stages(),
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE,
+                ..Default::default()
+            },
+        );
+        self.one_time_submit.sync_submit_and_reset()?;
+        Ok(())
+    }
+
+    /// Copy the GPU copy of the field back to the CPU. Blocks until the copy
+    /// completes.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the field must not be in use by the GPU
+    pub unsafe fn download(&mut self) -> Result<(), GraphicsError> {
+        let command_buffer = self.one_time_submit.command_buffer();
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+                src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COPY,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_READ,
+                ..Default::default()
+            },
+        );
+        self.render_device.device().cmd_copy_image_to_buffer2(
+            command_buffer,
+            &vk::CopyImageToBufferInfo2 {
+                src_image: self.image.raw(),
+                src_image_layout: vk::ImageLayout::GENERAL,
+                dst_buffer: self.staging_buffer.raw(),
+                region_count: 1,
+                p_regions: &self.copy_region(),
+                ..Default::default()
+            },
+        );
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::HOST,
+                dst_access_mask: vk::AccessFlags2::HOST_READ,
+                ..Default::default()
+            },
+        );
+        self.one_time_submit.sync_submit_and_reset()?;
+
+        let device = self.render_device.device();
+        let ptr =
+            self.staging_buffer.allocation().map(device)? as *const [f32; 4];
+        for (i, vector) in self.vectors.iter_mut().enumerate() {
+            let [x, y, z, _] = ptr.add(i).read();
+            *vector = Vec3::new(x, y, z);
+        }
+        self.staging_buffer.allocation().unmap(device)?;
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl FlowField {
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Every shader stage which may read the field.
+    fn shader_stages() -> vk::PipelineStageFlags2 {
+        vk::PipelineStageFlags2::COMPUTE_SHADER
+            | vk::PipelineStageFlags2::VERTEX_SHADER
+            | vk::PipelineStageFlags2::FRAGMENT_SHADER
+    }
+
+    /// The index of the vector at texel [x, y, z].
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        let width = self.extent.width as usize;
+        let height = self.extent.height as usize;
+        x + width * (y + height * z)
+    }
+
+    /// A copy region which covers the whole image.
+    fn copy_region(&self) -> vk::BufferImageCopy2 {
+        vk::BufferImageCopy2 {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: self.extent,
+            ..Default::default()
+        }
+    }
+
+    /// Record a single global memory barrier.
+    unsafe fn memory_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: vk::MemoryBarrier2,
+    ) {
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Transition the image to the GENERAL layout and clear it to zero.
+    unsafe fn clear(&mut self) -> Result<(), GraphicsError> {
+        let command_buffer = self.one_time_submit.command_buffer();
+        let device = self.render_device.device();
+
+        let to_general = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::NONE,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::CLEAR,
+            dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: self.image.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &to_general,
+                ..Default::default()
+            },
+        );
+        device.cmd_clear_color_image(
+            command_buffer,
+            self.image.raw(),
+            vk::ImageLayout::GENERAL,
+            &vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 0.0],
+            },
+            &[Self::SUBRESOURCE_RANGE],
+        );
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::CLEAR,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: Self::shader_stages(),
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE,
+                ..Default::default()
+            },
+        );
+        self.one_time_submit.sync_submit_and_reset()?;
+        Ok(())
+    }
+
+    /// Create the field's storage image and a matching 2D or 3D view.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent3D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let (image_type, view_type) = if extent.depth > 1 {
+            (vk::ImageType::TYPE_3D, vk::ImageViewType::TYPE_3D)
+        } else {
+            (vk::ImageType::TYPE_2D, vk::ImageViewType::TYPE_2D)
+        };
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST,
+            flags: vk::ImageCreateFlags::empty(),
+            extent,
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name("Flow Field");
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type,
+            format: Self::FORMAT,
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let image_view =
+            raii::ImageView::new(render_device.clone(), &view_create_info)?;
+        Ok((image, image_view))
+    }
+}
@@ -0,0 +1,394 @@
+use {
+    super::FlowField,
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// The vector pattern written by the built-in noise generators.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum FlowPattern {
+    /// The curl of fractal Perlin noise. Curl fields are divergence-free, so
+    /// particles swirl without bunching up or spreading out.
+    Curl = 0,
+
+    /// Unit vectors rotated by fractal Perlin noise, the classic
+    /// "angle from noise" flow field. 3D fields also tilt out of the xy
+    /// plane.
+    Angle = 1,
+}
+
+/// Parameters passed to a FlowFieldGenerator's shader.
+///
+/// The built-in noise shaders use every field. Custom shaders receive the
+/// same values and may interpret them however they like.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FlowFieldParams {
+    /// The field animates as time changes. Noise fields loop every whole
+    /// unit of time.
+    pub time: f32,
+
+    /// Every vector is scaled by this amount.
+    pub strength: f32,
+
+    /// The number of noise cells across the field for the first octave.
+    pub frequency: u32,
+
+    /// The number of octaves of fractal noise.
+    pub octaves: u32,
+
+    /// Different seeds produce different, uncorrelated fields.
+    pub seed: u32,
+
+    /// The pattern written by the built-in noise shaders.
+    pub pattern: FlowPattern,
+}
+
+impl Default for FlowFieldParams {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            strength: 1.0,
+            frequency: 4,
+            octaves: 3,
+            seed: 0,
+            pattern: FlowPattern::Curl,
+        }
+    }
+}
+
+/// Push constants for flow field generator shaders. Must match
+/// FlowFieldConstants in `shaders/flow_field_noise_2d.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct FlowFieldConstants {
+    time: f32,
+    strength: f32,
+    frequency: u32,
+    octaves: u32,
+    seed: u32,
+    pattern: u32,
+}
+
+/// Writes vectors into a FlowField with a compute shader.
+///
+/// Generators are built from one of the built-in noise shaders or from a
+/// custom compute shader. Custom shaders must:
+///
+///   - write the field through `layout(set = 0, binding = 0, rgba32f)` as
+///     an `image2D` or `image3D`, matching the fields they are used with
+///   - declare the push constants:
+///
+///     ```glsl
+///     layout(push_constant) uniform FlowFieldConstants {
+///         float time;
+///         float strength;
+///         uint frequency;
+///         uint octaves;
+///         uint seed;
+///         uint pattern;
+///     } field;
+///     ```
+///
+///   - use a workgroup size of 8x8x1 for 2D fields or 4x4x4 for 3D fields
+pub struct FlowFieldGenerator {
+    is_3d: bool,
+    descriptor_sets: HashMap<vk::ImageView, usize>,
+    descriptor_pool: raii::DescriptorPool,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl FlowFieldGenerator {
+    /// Create a generator which writes curl or angle noise into 2D fields.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `max_fields` - the number of distinct fields which can be generated
+    ///   over the lifetime of this instance
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new_noise_2d(
+        render_device: Arc<RenderDevice>,
+        max_fields: u32,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_shader(
+            render_device,
+            include_bytes!("./shaders/flow_field_noise_2d.comp.spv"),
+            false,
+            max_fields,
+        )
+    }
+
+    /// Create a generator which writes curl or angle noise into 3D fields.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new_noise_3d(
+        render_device: Arc<RenderDevice>,
+        max_fields: u32,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_shader(
+            render_device,
+            include_bytes!("./shaders/flow_field_noise_3d.comp.spv"),
+            true,
+            max_fields,
+        )
+    }
+
+    /// Create a generator from a custom compute shader.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `shader_bytes` - compiled SPIR-V which follows the contract in the
+    ///   FlowFieldGenerator docs
+    /// * `is_3d` - true when the shader writes an `image3D`
+    /// * `max_fields` - the number of distinct fields which can be generated
+    ///   over the lifetime of this instance
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new_with_shader(
+        render_device: Arc<RenderDevice>,
+        shader_bytes: &[u8],
+        is_3d: bool,
+        max_fields: u32,
+    ) -> Result<Self, GraphicsError> {
+        let max_fields = max_fields.max(1);
+        let binding = vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            ..vk::DescriptorSetLayoutBinding::default()
+        };
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[binding],
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<FlowFieldConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            shader_bytes,
+        )?;
+        pipeline.set_debug_name("Flow Field Generator Pipeline");
+        let descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            max_fields,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: max_fields,
+            }],
+        )?;
+
+        Ok(Self {
+            is_3d,
+            descriptor_sets: HashMap::new(),
+            descriptor_pool,
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Record commands which overwrite every vector in the field.
+    ///
+    /// Earlier reads of the field complete before the shader runs. The
+    /// commands end with a barrier which makes the new vectors visible to
+    /// compute, vertex, and fragment shaders and to transfers.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `flow_field` - the field to write. It must be 3D if this generator
+    ///   is 3D, and 2D otherwise.
+    /// * `params` - values passed to the shader as push constants
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording and outside of a render pass
+    ///   - the field and this instance must not be dropped until the command
+    ///     buffer finishes executing
+    pub unsafe fn cmd_generate(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        flow_field: &FlowField,
+        params: &FlowFieldParams,
+    ) -> Result<(), GraphicsError> {
+        if flow_field.is_3d() != self.is_3d {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "A {} flow field generator can't write a {} flow field",
+                if self.is_3d { "3D" } else { "2D" },
+                if flow_field.is_3d() { "3D" } else { "2D" },
+            )));
+        }
+        let descriptor_set = self.descriptor_set(flow_field)?;
+
+        let device = self.render_device.device();
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::VERTEX_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::ALL_TRANSFER,
+                src_access_mask: vk::AccessFlags2::SHADER_WRITE
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        let constants = FlowFieldConstants {
+            time: params.time,
+            strength: params.strength,
+            frequency: params.frequency.max(1),
+            octaves: params.octaves.max(1),
+            seed: params.seed,
+            pattern: params.pattern as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const FlowFieldConstants as *const u8,
+                std::mem::size_of::<FlowFieldConstants>(),
+            ),
+        );
+
+        let extent = flow_field.extent();
+        let size = if self.is_3d {
+            [Self::WORKGROUP_SIZE_3D; 3]
+        } else {
+            [Self::WORKGROUP_SIZE_2D, Self::WORKGROUP_SIZE_2D, 1]
+        };
+        device.cmd_dispatch(
+            command_buffer,
+            (extent.width + size[0] - 1) / size[0],
+            (extent.height + size[1] - 1) / size[1],
+            (extent.depth + size[2] - 1) / size[2],
+        );
+
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::VERTEX_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::ALL_TRANSFER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::TRANSFER_READ,
+                ..Default::default()
+            },
+        );
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl FlowFieldGenerator {
+    /// The 2D shaders' workgroup size in x and y.
+    const WORKGROUP_SIZE_2D: u32 = 8;
+
+    /// The 3D shaders' workgroup size in x, y, and z.
+    const WORKGROUP_SIZE_3D: u32 = 4;
+
+    /// Get the descriptor set which binds the field's storage image,
+    /// allocating and writing it the first time the field is used.
+    unsafe fn descriptor_set(
+        &mut self,
+        flow_field: &FlowField,
+    ) -> Result<vk::DescriptorSet, GraphicsError> {
+        let image_view = flow_field.image_view().raw();
+        if let Some(&index) = self.descriptor_sets.get(&image_view) {
+            return Ok(self.descriptor_pool.descriptor_set(index));
+        }
+
+        let index = self
+            .descriptor_pool
+            .allocate_descriptor_sets(&[&self.descriptor_set_layout])?;
+        let descriptor_set = self.descriptor_pool.descriptor_set(index);
+        let image_info = flow_field.storage_descriptor_image_info();
+        self.render_device.device().update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            }],
+            &[],
+        );
+        self.descriptor_sets.insert(image_view, index);
+        Ok(descriptor_set)
+    }
+
+    /// Record a single global memory barrier.
+    unsafe fn memory_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: vk::MemoryBarrier2,
+    ) {
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Flow fields: 2D and 3D grids of vectors for moving things around.
+//!
+//! A FlowField keeps its vectors in a GPU image, which particle shaders can
+//! sample with `shaders/flow_field.glsl`, and in a CPU copy, which can be
+//! sampled to advect canvas geometry or trace streamlines. Fields are filled
+//! from the CPU, by the built-in curl and angle noise generators, or by
+//! custom compute shaders with a FlowFieldGenerator.
+
+mod field;
+mod generator;
+
+pub use self::{
+    field::FlowField,
+    generator::{FlowFieldGenerator, FlowFieldParams, FlowPattern},
+};
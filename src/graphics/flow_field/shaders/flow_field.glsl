@@ -0,0 +1,44 @@
+// Helpers for shaders which sample a FlowField, e.g. to advect particles.
+//
+// Bind FlowField::sampled_descriptor_image_info() as a combined image sampler
+// and pass the field's origin and size to the shader, e.g. as push
+// constants. The field's sampler repeats, so positions outside of one tile
+// wrap around.
+
+// The flow at a world-space position in a 2D field.
+vec2 flow_field_sample(sampler2D field, vec2 position, vec2 origin, vec2 size) {
+    return textureLod(field, (position - origin) / size, 0.0).xy;
+}
+
+// The flow at a world-space position in a 3D field.
+vec3 flow_field_sample(sampler3D field, vec3 position, vec3 origin, vec3 size) {
+    return textureLod(field, (position - origin) / size, 0.0).xyz;
+}
+
+// Move a position through a 2D field for dt seconds with a single midpoint
+// step. Matches FlowField::advect on the CPU.
+vec2 flow_field_advect(
+    sampler2D field,
+    vec2 position,
+    vec2 origin,
+    vec2 size,
+    float dt
+) {
+    vec2 midpoint = position
+        + flow_field_sample(field, position, origin, size) * (0.5 * dt);
+    return position + flow_field_sample(field, midpoint, origin, size) * dt;
+}
+
+// Move a position through a 3D field for dt seconds with a single midpoint
+// step. Matches FlowField::advect on the CPU.
+vec3 flow_field_advect(
+    sampler3D field,
+    vec3 position,
+    vec3 origin,
+    vec3 size,
+    float dt
+) {
+    vec3 midpoint = position
+        + flow_field_sample(field, position, origin, size) * (0.5 * dt);
+    return position + flow_field_sample(field, midpoint, origin, size) * dt;
+}
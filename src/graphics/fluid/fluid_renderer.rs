@@ -0,0 +1,205 @@
+use {
+    super::FluidSim,
+    crate::graphics::{
+        vulkan_api::{
+            raii, ColorPass, Frame, GraphicsPipelineBuilder, RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters for drawing the fluid's dye.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct DyeParams {
+    /// The color drawn where there is no dye.
+    pub background: [f32; 4],
+
+    /// A multiplier applied to the dye color.
+    pub brightness: f32,
+}
+
+impl Default for DyeParams {
+    fn default() -> Self {
+        Self {
+            background: [0.0, 0.0, 0.0, 1.0],
+            brightness: 1.0,
+        }
+    }
+}
+
+/// A fullscreen pass which draws a FluidSim's dye into a ColorPass.
+pub struct FluidRenderer {
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+impl FluidRenderer {
+    /// Create the dye pipeline and a descriptor set for each of the
+    /// simulation's dye images.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `fluid_sim` - the simulation whose dye is drawn
+    /// * `color_pass` - the render pass the dye is drawn into
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the FluidSim or ColorPass
+    ///     are rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        fluid_sim: &FluidSim,
+        color_pass: &ColorPass,
+    ) -> Result<Self, GraphicsError> {
+        let bindings = [vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<DyeParams>() as u32,
+                }],
+            )?;
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!("./shaders/fluid_dye.vert.spv"))
+                .fragment_shader(include_bytes!("./shaders/fluid_dye.frag.spv"))
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .build(
+                    render_device.clone(),
+                    &pipeline_layout,
+                    color_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Fluid Dye Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            2,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 2,
+            }],
+        )?;
+        let _ = descriptor_pool.allocate_descriptor_sets(&[
+            &descriptor_set_layout,
+            &descriptor_set_layout,
+        ])?;
+        for index in 0..2 {
+            let image_info = vk::DescriptorImageInfo {
+                sampler: fluid_sim.sampler().raw(),
+                image_view: fluid_sim.dye_view_at(index).raw(),
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let write = vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(index),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            };
+            render_device.device().update_descriptor_sets(&[write], &[]);
+        }
+
+        Ok(Self {
+            extent: color_pass.extent(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Add commands to draw the fluid's current dye into every pixel of the
+    /// current render pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - the simulation's most recent step must be recorded before the
+    ///     render pass
+    pub unsafe fn draw(
+        &self,
+        frame: &Frame,
+        fluid_sim: &FluidSim,
+        params: &DyeParams,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            frame.command_buffer(),
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(fluid_sim.dye_index())],
+            &[],
+        );
+        device.cmd_push_constants(
+            frame.command_buffer(),
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                params as *const DyeParams as *const u8,
+                std::mem::size_of::<DyeParams>(),
+            ),
+        );
+
+        // A single triangle which covers the entire screen.
+        device.cmd_draw(frame.command_buffer(), 3, 1, 0, 0);
+    }
+}
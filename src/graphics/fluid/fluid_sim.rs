@@ -0,0 +1,790 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control the fluid simulation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FluidParams {
+    /// The number of Jacobi iterations used to solve for pressure. More
+    /// iterations give a more incompressible fluid. Odd values are rounded
+    /// up.
+    pub pressure_iterations: u32,
+
+    /// How quickly velocity fades, per second.
+    pub velocity_dissipation: f32,
+
+    /// How quickly dye fades, per second.
+    pub dye_dissipation: f32,
+}
+
+impl Default for FluidParams {
+    fn default() -> Self {
+        Self {
+            pressure_iterations: 20,
+            velocity_dissipation: 0.2,
+            dye_dissipation: 1.0,
+        }
+    }
+}
+
+/// A force and dye injected into the fluid, e.g. from the mouse.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Splat {
+    /// The center of the splat in uv coordinates, [0, 1] on each axis.
+    pub position: [f32; 2],
+
+    /// The velocity added at the center of the splat, in uv units per
+    /// second.
+    pub force: [f32; 2],
+
+    /// The dye color added at the center of the splat.
+    pub color: [f32; 4],
+
+    /// The splat's radius in uv units.
+    pub radius: f32,
+}
+
+impl Splat {
+    /// Create a splat from mouse movement.
+    ///
+    /// # Params
+    ///
+    /// * `position` - the cursor position in uv coordinates
+    /// * `delta` - how far the cursor moved since the last frame, in uv
+    ///   coordinates
+    /// * `dt` - the time, in seconds, since the last frame
+    /// * `color` - the dye color to add
+    pub fn from_mouse(
+        position: [f32; 2],
+        delta: [f32; 2],
+        dt: f32,
+        color: [f32; 4],
+    ) -> Self {
+        let dt = dt.max(f32::EPSILON);
+        Self {
+            position,
+            force: [delta[0] / dt, delta[1] / dt],
+            color,
+            radius: 0.01,
+        }
+    }
+}
+
+/// Push constants shared by every fluid compute shader.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct FluidConstants {
+    color: [f32; 4],
+    point: [f32; 2],
+    radius: f32,
+    dt: f32,
+    dissipation: f32,
+}
+
+/// The images bound for a single compute pass.
+///
+/// Every pass uses the same descriptor set layout:
+///   - binding 0: the sampled field being advected
+///   - binding 1: the sampled velocity field
+///   - binding 2: the storage image written by the pass
+///   - binding 3: a storage image read by the pass (pressure)
+///   - binding 4: a storage image read by the pass (divergence)
+///
+/// Bindings a pass doesn't use still reference valid images.
+struct PassViews {
+    source: vk::ImageView,
+    velocity: vk::ImageView,
+    destination: vk::ImageView,
+    pressure: vk::ImageView,
+    divergence: vk::ImageView,
+}
+
+/// The descriptor set index of every compute pass.
+mod pass {
+    pub const SPLAT_VELOCITY: usize = 0;
+    pub const SPLAT_DYE: [usize; 2] = [1, 2];
+    pub const ADVECT_VELOCITY: usize = 3;
+    pub const ADVECT_DYE: [usize; 2] = [4, 5];
+    pub const DIVERGENCE: usize = 6;
+    pub const JACOBI: [usize; 2] = [7, 8];
+    pub const GRADIENT: usize = 9;
+    pub const COUNT: usize = 10;
+}
+
+/// A 2D stable-fluids solver which runs entirely in compute shaders.
+///
+/// Velocity and dye are stored in RGBA16F storage images, pressure and
+/// divergence in R32F storage images. Each step:
+///
+///   1. adds any splats to the velocity and dye fields
+///   2. advects velocity and dye along the velocity field
+///   3. computes the velocity field's divergence
+///   4. solves for pressure with Jacobi iterations
+///   5. subtracts the pressure gradient from the velocity
+///
+/// Every image stays in the GENERAL layout, so the dye image can be sampled
+/// by later passes, e.g. the FluidRenderer.
+pub struct FluidSim {
+    /// The parameters used by each call to `cmd_step`.
+    pub params: FluidParams,
+
+    sim_extent: vk::Extent2D,
+    dye_extent: vk::Extent2D,
+    dye_index: usize,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    splat_pipeline: raii::Pipeline,
+    advect_pipeline: raii::Pipeline,
+    divergence_pipeline: raii::Pipeline,
+    jacobi_pipeline: raii::Pipeline,
+    gradient_pipeline: raii::Pipeline,
+    sampler: raii::Sampler,
+    views: Vec<raii::ImageView>,
+    _images: Vec<raii::Image>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl FluidSim {
+    /// The format used for velocity and dye.
+    pub const FIELD_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The format used for pressure and divergence.
+    pub const SCALAR_FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+
+    /// Create the simulation images and compute pipelines. Every field starts
+    /// at zero.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `sim_extent` - the resolution of the velocity and pressure grid
+    /// * `dye_extent` - the resolution of the dye. This is typically higher
+    ///   than the simulation resolution because dye detail is cheap.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        sim_extent: vk::Extent2D,
+        dye_extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        // Images are ordered: velocity 0, velocity 1, dye 0, dye 1,
+        // pressure 0, pressure 1, divergence.
+        let image_specs = [
+            ("Fluid Velocity 0", Self::FIELD_FORMAT, sim_extent),
+            ("Fluid Velocity 1", Self::FIELD_FORMAT, sim_extent),
+            ("Fluid Dye 0", Self::FIELD_FORMAT, dye_extent),
+            ("Fluid Dye 1", Self::FIELD_FORMAT, dye_extent),
+            ("Fluid Pressure 0", Self::SCALAR_FORMAT, sim_extent),
+            ("Fluid Pressure 1", Self::SCALAR_FORMAT, sim_extent),
+            ("Fluid Divergence", Self::SCALAR_FORMAT, sim_extent),
+        ];
+        let mut images = vec![];
+        let mut views = vec![];
+        for (name, format, extent) in image_specs {
+            let (image, view) =
+                Self::create_image(&render_device, name, format, extent)?;
+            images.push(image);
+            views.push(view);
+        }
+        Self::clear_images(&render_device, &images)?;
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let binding = |binding: u32, descriptor_type: vk::DescriptorType| {
+            vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            }
+        };
+        let bindings = [
+            binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+            binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+            binding(2, vk::DescriptorType::STORAGE_IMAGE),
+            binding(3, vk::DescriptorType::STORAGE_IMAGE),
+            binding(4, vk::DescriptorType::STORAGE_IMAGE),
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<FluidConstants>() as u32,
+                }],
+            )?;
+
+        let create_pipeline = |name: &str, bytes: &[u8]| {
+            let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                bytes,
+            )?;
+            pipeline.set_debug_name(name);
+            Ok::<_, GraphicsError>(pipeline)
+        };
+        let splat_pipeline = create_pipeline(
+            "Fluid Splat Pipeline",
+            include_bytes!("./shaders/fluid_splat.comp.spv"),
+        )?;
+        let advect_pipeline = create_pipeline(
+            "Fluid Advect Pipeline",
+            include_bytes!("./shaders/fluid_advect.comp.spv"),
+        )?;
+        let divergence_pipeline = create_pipeline(
+            "Fluid Divergence Pipeline",
+            include_bytes!("./shaders/fluid_divergence.comp.spv"),
+        )?;
+        let jacobi_pipeline = create_pipeline(
+            "Fluid Jacobi Pipeline",
+            include_bytes!("./shaders/fluid_jacobi.comp.spv"),
+        )?;
+        let gradient_pipeline = create_pipeline(
+            "Fluid Gradient Pipeline",
+            include_bytes!("./shaders/fluid_gradient.comp.spv"),
+        )?;
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            pass::COUNT as u32,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 2 * pass::COUNT as u32,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 3 * pass::COUNT as u32,
+                },
+            ],
+        )?;
+        let layouts = vec![&descriptor_set_layout; pass::COUNT];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        let velocity_0 = views[0].raw();
+        let velocity_1 = views[1].raw();
+        let dye = [views[2].raw(), views[3].raw()];
+        let pressure_0 = views[4].raw();
+        let pressure_1 = views[5].raw();
+        let divergence = views[6].raw();
+        let pass_views = |destination: vk::ImageView| PassViews {
+            source: velocity_0,
+            velocity: velocity_0,
+            destination,
+            pressure: pressure_0,
+            divergence,
+        };
+        let mut passes: Vec<(usize, PassViews)> = vec![
+            (pass::SPLAT_VELOCITY, pass_views(velocity_0)),
+            (pass::ADVECT_VELOCITY, pass_views(velocity_1)),
+            (
+                pass::DIVERGENCE,
+                PassViews {
+                    velocity: velocity_1,
+                    ..pass_views(divergence)
+                },
+            ),
+            (
+                pass::JACOBI[0],
+                PassViews {
+                    pressure: pressure_0,
+                    ..pass_views(pressure_1)
+                },
+            ),
+            (
+                pass::JACOBI[1],
+                PassViews {
+                    pressure: pressure_1,
+                    ..pass_views(pressure_0)
+                },
+            ),
+            (
+                pass::GRADIENT,
+                PassViews {
+                    velocity: velocity_1,
+                    pressure: pressure_0,
+                    ..pass_views(velocity_0)
+                },
+            ),
+        ];
+        for parity in 0..2 {
+            passes.push((pass::SPLAT_DYE[parity], pass_views(dye[parity])));
+            passes.push((
+                pass::ADVECT_DYE[parity],
+                PassViews {
+                    source: dye[parity],
+                    ..pass_views(dye[1 - parity])
+                },
+            ));
+        }
+        for (index, views) in &passes {
+            Self::write_descriptor_set(
+                &render_device,
+                descriptor_pool.descriptor_set(*index),
+                &sampler,
+                views,
+            );
+        }
+
+        Ok(Self {
+            params: FluidParams::default(),
+            sim_extent,
+            dye_extent,
+            dye_index: 0,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            splat_pipeline,
+            advect_pipeline,
+            divergence_pipeline,
+            jacobi_pipeline,
+            gradient_pipeline,
+            sampler,
+            views,
+            _images: images,
+            render_device,
+        })
+    }
+
+    /// The resolution of the velocity and pressure grid.
+    pub fn sim_extent(&self) -> vk::Extent2D {
+        self.sim_extent
+    }
+
+    /// The resolution of the dye image.
+    pub fn dye_extent(&self) -> vk::Extent2D {
+        self.dye_extent
+    }
+
+    /// The image view which holds the current dye. The dye is double
+    /// buffered, so the view changes after every step.
+    pub fn dye_view(&self) -> &raii::ImageView {
+        self.dye_view_at(self.dye_index)
+    }
+
+    /// The image view which holds the current velocity, in simulation texels
+    /// per second.
+    pub fn velocity_view(&self) -> &raii::ImageView {
+        &self.views[0]
+    }
+
+    /// The linear, clamp-to-edge sampler used by the simulation.
+    pub fn sampler(&self) -> &raii::Sampler {
+        &self.sampler
+    }
+
+    /// Record commands which add splats and advance the simulation by `dt`
+    /// seconds.
+    ///
+    /// Earlier reads of the simulation images, including by fragment shaders
+    /// in previous frames, complete before the step begins. After this call
+    /// the dye and velocity can be sampled by fragment or compute shaders.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn cmd_step(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        dt: f32,
+        splats: &[Splat],
+    ) {
+        self.barrier(
+            command_buffer,
+            vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+        );
+
+        let sim_size =
+            [self.sim_extent.width as f32, self.sim_extent.height as f32];
+        for splat in splats {
+            let radius = splat.radius * splat.radius;
+            self.dispatch(
+                command_buffer,
+                &self.splat_pipeline,
+                pass::SPLAT_VELOCITY,
+                self.sim_extent,
+                &FluidConstants {
+                    color: [
+                        splat.force[0] * sim_size[0],
+                        splat.force[1] * sim_size[1],
+                        0.0,
+                        0.0,
+                    ],
+                    point: splat.position,
+                    radius,
+                    ..Default::default()
+                },
+            );
+            self.dispatch(
+                command_buffer,
+                &self.splat_pipeline,
+                pass::SPLAT_DYE[self.dye_index],
+                self.dye_extent,
+                &FluidConstants {
+                    color: splat.color,
+                    point: splat.position,
+                    radius,
+                    ..Default::default()
+                },
+            );
+        }
+
+        self.dispatch(
+            command_buffer,
+            &self.advect_pipeline,
+            pass::ADVECT_VELOCITY,
+            self.sim_extent,
+            &FluidConstants {
+                dt,
+                dissipation: self.params.velocity_dissipation,
+                ..Default::default()
+            },
+        );
+        self.dispatch(
+            command_buffer,
+            &self.advect_pipeline,
+            pass::ADVECT_DYE[self.dye_index],
+            self.dye_extent,
+            &FluidConstants {
+                dt,
+                dissipation: self.params.dye_dissipation,
+                ..Default::default()
+            },
+        );
+        self.dye_index = 1 - self.dye_index;
+
+        self.dispatch(
+            command_buffer,
+            &self.divergence_pipeline,
+            pass::DIVERGENCE,
+            self.sim_extent,
+            &FluidConstants::default(),
+        );
+
+        // Pressure starts from the previous step's solution and always ends
+        // in pressure 0.
+        let iterations = (self.params.pressure_iterations + 1) & !1;
+        for iteration in 0..iterations as usize {
+            self.dispatch(
+                command_buffer,
+                &self.jacobi_pipeline,
+                pass::JACOBI[iteration % 2],
+                self.sim_extent,
+                &FluidConstants::default(),
+            );
+        }
+
+        self.dispatch(
+            command_buffer,
+            &self.gradient_pipeline,
+            pass::GRADIENT,
+            self.sim_extent,
+            &FluidConstants::default(),
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl FluidSim {
+    /// The index of the dye image which holds the current dye.
+    pub(super) fn dye_index(&self) -> usize {
+        self.dye_index
+    }
+
+    /// One of the two dye images.
+    pub(super) fn dye_view_at(&self, index: usize) -> &raii::ImageView {
+        &self.views[2 + index]
+    }
+
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// Every simulation image has a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Bind a pass's pipeline and descriptor set, dispatch enough workgroups
+    /// to cover the output extent, then wait for the writes to be visible to
+    /// the next pass.
+    unsafe fn dispatch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &raii::Pipeline,
+        pass: usize,
+        output_extent: vk::Extent2D,
+        constants: &FluidConstants,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(pass)],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                constants as *const FluidConstants as *const u8,
+                std::mem::size_of::<FluidConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (output_extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (output_extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+        self.barrier(command_buffer, vk::PipelineStageFlags2::COMPUTE_SHADER);
+    }
+
+    /// Make compute writes visible to later compute and fragment shaders,
+    /// and order them after earlier reads in `src_stage_mask`.
+    unsafe fn barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src_stage_mask: vk::PipelineStageFlags2,
+    ) {
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: src_stage_mask
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Point a pass's descriptor set at its images.
+    unsafe fn write_descriptor_set(
+        render_device: &RenderDevice,
+        descriptor_set: vk::DescriptorSet,
+        sampler: &raii::Sampler,
+        views: &PassViews,
+    ) {
+        let sampled = |image_view: vk::ImageView| vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view,
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let storage = |image_view: vk::ImageView| vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view,
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let image_infos = [
+            (
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                sampled(views.source),
+            ),
+            (
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                sampled(views.velocity),
+            ),
+            (
+                vk::DescriptorType::STORAGE_IMAGE,
+                storage(views.destination),
+            ),
+            (vk::DescriptorType::STORAGE_IMAGE, storage(views.pressure)),
+            (vk::DescriptorType::STORAGE_IMAGE, storage(views.divergence)),
+        ];
+        let writes: Vec<vk::WriteDescriptorSet> = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, (descriptor_type, image_info))| {
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: binding as u32,
+                    dst_array_element: 0,
+                    descriptor_type: *descriptor_type,
+                    descriptor_count: 1,
+                    p_image_info: image_info,
+                    ..vk::WriteDescriptorSet::default()
+                }
+            })
+            .collect();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+    }
+
+    /// Create a device-local simulation image and a view of it.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        name: &str,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_DST,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name(name);
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let view =
+            raii::ImageView::new(render_device.clone(), &view_create_info)?;
+        Ok((image, view))
+    }
+
+    /// Transition every image to the GENERAL layout and clear it to zero.
+    unsafe fn clear_images(
+        render_device: &Arc<RenderDevice>,
+        images: &[raii::Image],
+    ) -> Result<(), GraphicsError> {
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        let device = render_device.device();
+
+        let to_general: Vec<vk::ImageMemoryBarrier2> = images
+            .iter()
+            .map(|image| vk::ImageMemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::NONE,
+                src_access_mask: vk::AccessFlags2::NONE,
+                dst_stage_mask: vk::PipelineStageFlags2::CLEAR,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                old_layout: vk::ImageLayout::UNDEFINED,
+                new_layout: vk::ImageLayout::GENERAL,
+                image: image.raw(),
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            })
+            .collect();
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: to_general.len() as u32,
+                p_image_memory_barriers: to_general.as_ptr(),
+                ..Default::default()
+            },
+        );
+        for image in images {
+            device.cmd_clear_color_image(
+                command_buffer,
+                image.raw(),
+                vk::ImageLayout::GENERAL,
+                &vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+                &[Self::SUBRESOURCE_RANGE],
+            );
+        }
+        let after_clear = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::CLEAR,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &after_clear,
+                ..Default::default()
+            },
+        );
+
+        one_time_submit.sync_submit_and_reset()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+//! A 2D stable-fluids simulation on the GPU.
+//!
+//! FluidSim advances velocity, pressure, and dye fields with compute shaders
+//! and accepts splats of force and dye, e.g. from mouse movement.
+//! FluidRenderer draws the dye into a render pass.
+
+mod fluid_renderer;
+mod fluid_sim;
+
+pub use self::{
+    fluid_renderer::{DyeParams, FluidRenderer},
+    fluid_sim::{FluidParams, FluidSim, Splat},
+};
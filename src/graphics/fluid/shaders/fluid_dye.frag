@@ -0,0 +1,17 @@
+#version 460
+
+layout(location = 0) in vec2 uv;
+
+layout(set = 0, binding = 0) uniform sampler2D dye;
+
+layout(push_constant) uniform DyeParams {
+    vec4 background;
+    float brightness;
+} params;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec3 color = texture(dye, uv).rgb * params.brightness;
+    out_color = vec4(params.background.rgb + color, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 460
+
+layout(location = 0) out vec2 uv;
+
+// A single triangle which covers the entire screen.
+vec2 positions[3] = vec2[] (
+    vec2(-1.0, -1.0),
+    vec2(3.0, -1.0),
+    vec2(-1.0, 3.0)
+);
+
+void main() {
+    vec2 position = positions[gl_VertexIndex % 3];
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,249 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::{collections::HashMap, sync::Arc},
+};
+
+/// Descriptor sets which bind a fixed number of storage buffers, cached by
+/// the buffers they reference.
+///
+/// Binding `i` of each set is the i'th buffer passed to `descriptor_set`, so
+/// recording the same buffers again reuses the same set.
+pub(super) struct BufferBindings {
+    sets: HashMap<Vec<vk::Buffer>, usize>,
+    buffer_count: u32,
+    descriptor_pool: raii::DescriptorPool,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+    render_device: Arc<RenderDevice>,
+}
+
+impl BufferBindings {
+    /// Create the descriptor set layout and a pool for `max_sets` sets.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        buffer_count: u32,
+        max_sets: u32,
+    ) -> Result<Self, GraphicsError> {
+        let bindings = (0..buffer_count)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            max_sets,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: buffer_count * max_sets,
+            }],
+        )?;
+        Ok(Self {
+            sets: HashMap::new(),
+            buffer_count,
+            descriptor_pool,
+            descriptor_set_layout,
+            render_device,
+        })
+    }
+
+    /// The layout shared by every descriptor set.
+    pub fn layout(&self) -> &raii::DescriptorSetLayout {
+        &self.descriptor_set_layout
+    }
+
+    /// Get the descriptor set which binds `buffers`, creating it the first
+    /// time these buffers are used.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the buffers must outlive any command buffer which uses the set
+    pub unsafe fn descriptor_set(
+        &mut self,
+        buffers: &[vk::Buffer],
+    ) -> Result<vk::DescriptorSet, GraphicsError> {
+        debug_assert_eq!(buffers.len() as u32, self.buffer_count);
+        if let Some(&index) = self.sets.get(buffers) {
+            return Ok(self.descriptor_pool.descriptor_set(index));
+        }
+
+        let index = self
+            .descriptor_pool
+            .allocate_descriptor_sets(&[&self.descriptor_set_layout])?;
+        let descriptor_set = self.descriptor_pool.descriptor_set(index);
+        let buffer_infos = buffers
+            .iter()
+            .map(|&buffer| vk::DescriptorBufferInfo {
+                buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            })
+            .collect::<Vec<_>>();
+        let writes = buffer_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer_info)| vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: buffer_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<_>>();
+        self.render_device
+            .device()
+            .update_descriptor_sets(&writes, &[]);
+        self.sets.insert(buffers.to_vec(), index);
+        Ok(descriptor_set)
+    }
+}
+
+/// Make compute shader writes visible to the next compute dispatch.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the command buffer must be recording
+pub(super) unsafe fn compute_barrier(
+    render_device: &RenderDevice,
+    command_buffer: vk::CommandBuffer,
+) {
+    memory_barrier(
+        render_device,
+        command_buffer,
+        vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            ..Default::default()
+        },
+    );
+}
+
+/// Make transfer writes, like `cmd_fill_buffer`, visible to the next compute
+/// dispatch.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the command buffer must be recording
+pub(super) unsafe fn transfer_barrier(
+    render_device: &RenderDevice,
+    command_buffer: vk::CommandBuffer,
+) {
+    memory_barrier(
+        render_device,
+        command_buffer,
+        vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            ..Default::default()
+        },
+    );
+}
+
+/// Order the first command of a primitive, a dispatch or a buffer fill,
+/// after earlier transfer and shader writes to its buffers, and after earlier
+/// reads by draws.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the command buffer must be recording
+pub(super) unsafe fn before_barrier(
+    render_device: &RenderDevice,
+    command_buffer: vk::CommandBuffer,
+) {
+    memory_barrier(
+        render_device,
+        command_buffer,
+        vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER
+                | vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::DRAW_INDIRECT
+                | vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT
+                | vk::PipelineStageFlags2::VERTEX_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::ALL_TRANSFER,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                | vk::AccessFlags2::TRANSFER_WRITE,
+            ..Default::default()
+        },
+    );
+}
+
+/// Make the results of a primitive visible to later compute, vertex,
+/// fragment, and indirect draw reads.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the command buffer must be recording
+pub(super) unsafe fn after_barrier(
+    render_device: &RenderDevice,
+    command_buffer: vk::CommandBuffer,
+) {
+    memory_barrier(
+        render_device,
+        command_buffer,
+        vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::DRAW_INDIRECT
+                | vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT
+                | vk::PipelineStageFlags2::VERTEX_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                | vk::PipelineStageFlags2::ALL_TRANSFER,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                | vk::AccessFlags2::INDIRECT_COMMAND_READ
+                | vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                | vk::AccessFlags2::TRANSFER_READ,
+            ..Default::default()
+        },
+    );
+}
+
+unsafe fn memory_barrier(
+    render_device: &RenderDevice,
+    command_buffer: vk::CommandBuffer,
+    memory_barrier: vk::MemoryBarrier2,
+) {
+    render_device.device().cmd_pipeline_barrier2(
+        command_buffer,
+        &vk::DependencyInfo {
+            memory_barrier_count: 1,
+            p_memory_barriers: &memory_barrier,
+            ..Default::default()
+        },
+    );
+}
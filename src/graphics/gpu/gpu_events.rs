@@ -0,0 +1,373 @@
+use {
+    crate::graphics::{
+        vulkan_api::{
+            raii, Frame, FramesInFlight, MappedBuffer,
+            OneTimeSubmitCommandBuffer, RenderDevice,
+        },
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The start of the event buffer. Must match GPU_EVENTS_BUFFER in
+/// `shaders/gpu_events.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct EventsHeader {
+    count: u32,
+    capacity: u32,
+    pad: [u32; 2],
+}
+
+/// A callback which handles one event.
+type EventCallback<T> = Box<dyn FnMut(&T)>;
+
+/// Events appended by compute shaders and handled by Rust callbacks a few
+/// frames later.
+///
+/// Shaders append events to a device-local storage buffer with the helpers
+/// in `shaders/gpu_events.glsl`. `cmd_collect` copies the frame's events
+/// into a host-visible staging buffer and empties the event buffer for the
+/// next frame. `poll` never blocks: it hands the events from every frame
+/// which has finished executing to the callbacks, oldest first, so each
+/// event is handled exactly once.
+///
+/// This lets a simulation which lives entirely on the GPU drive CPU-side
+/// logic, like playing a sound when a particle reaches its target.
+///
+/// The event type must have the same std430 layout as the event struct in
+/// the shader.
+pub struct GpuEvents<T: Copy> {
+    capacity: u32,
+    events: raii::Buffer,
+    staging: Vec<MappedBuffer<u8>>,
+    pending_collections: Vec<Option<u64>>,
+    next_collection: u64,
+    ready: Vec<T>,
+    dropped_event_count: u64,
+    callbacks: Vec<EventCallback<T>>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl<T: Copy> GpuEvents<T> {
+    /// Create the event buffer and staging buffers.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - the frames which will record `cmd_collect`
+    /// * `capacity` - the most events which can be appended each frame,
+    ///   extra events are dropped
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must not be dropped while a frame which uses it is
+    ///     in flight
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        capacity: u32,
+    ) -> Result<Self, GraphicsError> {
+        if std::mem::align_of::<T>() > std::mem::size_of::<EventsHeader>() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "GPU events must be aligned to at most {} bytes, but {} is \
+                 aligned to {}",
+                std::mem::size_of::<EventsHeader>(),
+                std::any::type_name::<T>(),
+                std::mem::align_of::<T>()
+            )));
+        }
+
+        let capacity = capacity.max(1);
+        let events = Self::create_buffer(
+            &render_device,
+            Self::size_in_bytes(capacity),
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        events.set_debug_name("GPU Events");
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut staging = Vec::with_capacity(frame_count);
+        for index in 0..frame_count {
+            let buffer = MappedBuffer::new_host_visible(
+                render_device.clone(),
+                Self::size_in_bytes(capacity) as usize,
+                vk::BufferUsageFlags::TRANSFER_DST,
+            )?;
+            buffer.set_debug_name(format!("Frame {index} GPU Events Staging"));
+            staging.push(buffer);
+        }
+
+        let gpu_events = Self {
+            capacity,
+            events,
+            staging,
+            pending_collections: vec![None; frame_count],
+            next_collection: 0,
+            ready: vec![],
+            dropped_event_count: 0,
+            callbacks: vec![],
+            render_device,
+        };
+
+        // The header must be written before any shader appends an event.
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            gpu_events.render_device.clone(),
+            gpu_events.render_device.graphics_queue().clone(),
+        )?;
+        gpu_events.cmd_reset(one_time_submit.command_buffer());
+        one_time_submit.sync_submit_and_reset()?;
+
+        Ok(gpu_events)
+    }
+
+    /// The most events which can be appended each frame.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The storage buffer which shaders append events to. Bind it wherever
+    /// the shader declares GPU_EVENTS_BUFFER.
+    pub fn buffer(&self) -> &raii::Buffer {
+        &self.events
+    }
+
+    /// The total number of events which were dropped because a frame
+    /// appended more than `capacity` events. Only includes frames which have
+    /// been polled.
+    pub fn dropped_event_count(&self) -> u64 {
+        self.dropped_event_count
+    }
+
+    /// Add a callback which is called once for every event.
+    pub fn on_event<F>(&mut self, callback: F)
+    where
+        F: FnMut(&T) + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Record commands which copy this frame's events back to the CPU and
+    /// empty the event buffer.
+    ///
+    /// Record it after the last dispatch which appends events.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    pub unsafe fn cmd_collect(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<(), GraphicsError> {
+        let index = frame.frame_index();
+
+        // FramesInFlight waits for the frame's previous submission before
+        // handing it out, so any pending collection in this slot is
+        // complete. Keep its events before the staging buffer is reused.
+        self.take_staging_events(index)?;
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+                src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_READ
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+                ..Default::default()
+            },
+        );
+        device.cmd_copy_buffer(
+            command_buffer,
+            self.events.raw(),
+            self.staging[index].raw(),
+            &[vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size: Self::size_in_bytes(self.capacity),
+            }],
+        );
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER,
+                src_access_mask: vk::AccessFlags2::TRANSFER_READ,
+                dst_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                ..Default::default()
+            },
+        );
+        self.cmd_reset(command_buffer);
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::HOST
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::HOST_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+
+        self.pending_collections[index] = Some(self.next_collection);
+        self.next_collection += 1;
+
+        Ok(())
+    }
+
+    /// Call the callbacks with the events from every frame which has
+    /// finished executing since the last poll, oldest first.
+    ///
+    /// Never blocks. Returns the number of events handled.
+    pub fn poll(
+        &mut self,
+        frames_in_flight: &FramesInFlight,
+    ) -> Result<usize, GraphicsError> {
+        let mut complete = vec![];
+        for (index, pending) in self.pending_collections.iter().enumerate() {
+            if let Some(collection) = *pending {
+                if frames_in_flight.is_frame_complete(index)? {
+                    complete.push((collection, index));
+                }
+            }
+        }
+        complete.sort_unstable();
+        for (_, index) in complete {
+            unsafe {
+                // SAFE because the frame which recorded the copy is
+                // complete.
+                self.take_staging_events(index)?;
+            }
+        }
+
+        let events = std::mem::take(&mut self.ready);
+        for event in &events {
+            for callback in &mut self.callbacks {
+                callback(event);
+            }
+        }
+        let handled = events.len();
+
+        // Reuse the allocation for the next poll.
+        self.ready = events;
+        self.ready.clear();
+
+        Ok(handled)
+    }
+}
+
+// Private API
+// -----------
+
+impl<T: Copy> GpuEvents<T> {
+    /// The size of the header and `capacity` events in bytes.
+    fn size_in_bytes(capacity: u32) -> vk::DeviceSize {
+        (std::mem::size_of::<EventsHeader>()
+            + capacity as usize * std::mem::size_of::<T>())
+            as vk::DeviceSize
+    }
+
+    /// Record a write which sets the event count to zero and the capacity
+    /// to `capacity`.
+    unsafe fn cmd_reset(&self, command_buffer: vk::CommandBuffer) {
+        let header = EventsHeader {
+            count: 0,
+            capacity: self.capacity,
+            pad: [0, 0],
+        };
+        self.render_device.device().cmd_update_buffer(
+            command_buffer,
+            self.events.raw(),
+            0,
+            std::slice::from_raw_parts(
+                &header as *const EventsHeader as *const u8,
+                std::mem::size_of::<EventsHeader>(),
+            ),
+        );
+    }
+
+    /// Append a staging buffer's events to the ready events, then mark the
+    /// staging buffer as idle.
+    ///
+    /// Unsafe because the copy into the staging buffer must be complete.
+    unsafe fn take_staging_events(
+        &mut self,
+        index: usize,
+    ) -> Result<(), GraphicsError> {
+        if self.pending_collections[index].take().is_none() {
+            return Ok(());
+        }
+        let staging = &self.staging[index];
+        staging.invalidate()?;
+        // SAFE because the collection copy is complete and invalidated, so
+        // every byte was written by the device.
+        let ptr = staging.as_slice().as_ptr();
+        let header = (ptr as *const EventsHeader).read_unaligned();
+        let count = header.count.min(self.capacity);
+        self.dropped_event_count += (header.count - count) as u64;
+        let events = ptr.add(std::mem::size_of::<EventsHeader>()) as *const T;
+        for event in 0..count as usize {
+            self.ready.push(events.add(event).read_unaligned());
+        }
+        Ok(())
+    }
+
+    /// Record a global memory barrier.
+    unsafe fn memory_barrier(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: vk::MemoryBarrier2,
+    ) {
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+}
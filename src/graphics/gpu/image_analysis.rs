@@ -0,0 +1,489 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    memoffset::offset_of,
+    std::sync::Arc,
+};
+
+/// Controls how pixels are binned into the luminance histogram.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImageAnalysisParams {
+    /// The luminance which maps to the first bin. A log2 value when
+    /// `log_scale` is true.
+    pub histogram_min: f32,
+
+    /// The luminance which maps to the last bin. A log2 value when
+    /// `log_scale` is true.
+    pub histogram_max: f32,
+
+    /// When true, bins are spaced evenly in log2 luminance and bin 0 only
+    /// holds black pixels. Otherwise bins are spaced evenly in luminance.
+    pub log_scale: bool,
+}
+
+impl Default for ImageAnalysisParams {
+    fn default() -> Self {
+        Self {
+            histogram_min: -8.0,
+            histogram_max: 4.0,
+            log_scale: true,
+        }
+    }
+}
+
+/// The results of an analysis. Must match Stats in
+/// `shaders/image_analysis.glsl`.
+///
+/// Read it back with a `ReadbackBuffer<ImageStats>` of length 1, copying
+/// from `ImageAnalysis::stats_buffer`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct ImageStats {
+    /// The number of pixels in each luminance bin.
+    pub histogram: [u32; ImageAnalysis::BIN_COUNT as usize],
+
+    /// The smallest value of each channel.
+    pub min_color: [f32; 4],
+
+    /// The largest value of each channel.
+    pub max_color: [f32; 4],
+
+    /// The mean of each channel.
+    pub average_color: [f32; 4],
+
+    /// The smallest luminance.
+    pub min_luminance: f32,
+
+    /// The largest luminance.
+    pub max_luminance: f32,
+
+    /// The mean luminance.
+    pub average_luminance: f32,
+
+    /// The geometric mean of the luminance of every non-black pixel, which
+    /// is what exposure is usually based on.
+    pub log_average_luminance: f32,
+
+    /// The number of pixels analyzed.
+    pub pixel_count: u32,
+
+    _padding: [u32; 3],
+}
+
+/// Push constants for the analysis shaders. Must match AnalysisConstants in
+/// `shaders/image_analysis.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct AnalysisConstants {
+    histogram_min: f32,
+    histogram_max: f32,
+    log_scale: u32,
+    pixel_count: u32,
+    workgroup_count: u32,
+}
+
+/// Computes a luminance histogram, per-channel min and max, and average
+/// color of a 2D image on the GPU.
+///
+/// The first compute pass runs one invocation per pixel. Each workgroup
+/// builds its histogram and min/max in shared memory before merging them
+/// into the stats buffer with atomics, and writes its color sums to a
+/// buffer of partial sums. A second single-workgroup pass adds up the
+/// partial sums and writes the averages.
+///
+/// Any format which can be sampled as floats works, and the image can be
+/// analyzed in any layout which permits sampling.
+pub struct ImageAnalysis {
+    extent: vk::Extent2D,
+    _sampler: raii::Sampler,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    analyze_pipeline: raii::Pipeline,
+    reduce_pipeline: raii::Pipeline,
+    stats: raii::Buffer,
+    _partials: raii::Buffer,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl ImageAnalysis {
+    /// The number of bins in the luminance histogram.
+    pub const BIN_COUNT: u32 = 256;
+
+    /// Create the buffers and compute pipelines for an image.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `image_view` - a view of the image to analyze, which must have been
+    ///   created with SAMPLED usage
+    /// * `image_layout` - the layout the image is in when `cmd_analyze`
+    ///   runs, e.g. SHADER_READ_ONLY_OPTIMAL or GENERAL
+    /// * `extent` - the size of the image
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the image is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        image_view: &raii::ImageView,
+        image_layout: vk::ImageLayout,
+        extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        let stats = Self::create_buffer(
+            &render_device,
+            std::mem::size_of::<ImageStats>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        )?;
+        stats.set_debug_name("Image Analysis Stats");
+        let workgroup_count = Self::workgroup_count(extent);
+        let partials = Self::create_buffer(
+            &render_device,
+            2 * workgroup_count as u64 * std::mem::size_of::<[f32; 4]>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+        )?;
+        partials.set_debug_name("Image Analysis Partial Sums");
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 2,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<AnalysisConstants>() as u32,
+                }],
+            )?;
+        let analyze_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/image_analysis.comp.spv"),
+        )?;
+        analyze_pipeline.set_debug_name("Image Analysis Pipeline");
+        let reduce_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/image_analysis_reduce.comp.spv"),
+        )?;
+        reduce_pipeline.set_debug_name("Image Analysis Reduce Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 2,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        let image_info = vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: image_view.raw(),
+            image_layout,
+        };
+        let buffer_infos =
+            [&stats, &partials].map(|buffer| vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            });
+        let mut writes = vec![vk::WriteDescriptorSet {
+            dst_set: descriptor_pool.descriptor_set(0),
+            dst_binding: 0,
+            dst_array_element: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            p_image_info: &image_info,
+            ..vk::WriteDescriptorSet::default()
+        }];
+        for (index, buffer_info) in buffer_infos.iter().enumerate() {
+            writes.push(vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 1 + index as u32,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: buffer_info,
+                ..vk::WriteDescriptorSet::default()
+            });
+        }
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            extent,
+            _sampler: sampler,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            analyze_pipeline,
+            reduce_pipeline,
+            stats,
+            _partials: partials,
+            render_device,
+        })
+    }
+
+    /// The device-local buffer which holds a single ImageStats.
+    ///
+    /// Shaders can read it as a storage buffer, and it has TRANSFER_SRC usage
+    /// so it can be copied into a ReadbackBuffer.
+    pub fn stats_buffer(&self) -> &raii::Buffer {
+        &self.stats
+    }
+
+    /// Record commands which analyze the image and replace the stats.
+    ///
+    /// Earlier color attachment, compute, and transfer writes complete before
+    /// the image is read. The commands end with a barrier which makes the
+    /// stats visible to compute shaders, fragment shaders, and transfers.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `params` - controls how the histogram is binned
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the image must be in the layout given to `new`
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn cmd_analyze(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        params: &ImageAnalysisParams,
+    ) {
+        let device = self.render_device.device();
+
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::ALL_TRANSFER,
+                src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags2::SHADER_WRITE
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::COPY,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+                ..Default::default()
+            },
+        );
+
+        // Reset the histogram and start min and max at the opposite ends of
+        // the encoded range.
+        let mut reset = [0_u32;
+            std::mem::size_of::<ImageStats>() / std::mem::size_of::<u32>()];
+        let word = |offset: usize| offset / std::mem::size_of::<u32>();
+        let min_color = word(offset_of!(ImageStats, min_color));
+        reset[min_color..min_color + 4].fill(u32::MAX);
+        reset[word(offset_of!(ImageStats, min_luminance))] = u32::MAX;
+        device.cmd_update_buffer(
+            command_buffer,
+            self.stats.raw(),
+            0,
+            std::slice::from_raw_parts(
+                reset.as_ptr() as *const u8,
+                std::mem::size_of_val(&reset),
+            ),
+        );
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE,
+                ..Default::default()
+            },
+        );
+
+        let constants = AnalysisConstants {
+            histogram_min: params.histogram_min,
+            histogram_max: params.histogram_max,
+            log_scale: params.log_scale as u32,
+            pixel_count: self.extent.width * self.extent.height,
+            workgroup_count: Self::workgroup_count(self.extent),
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const AnalysisConstants as *const u8,
+                std::mem::size_of::<AnalysisConstants>(),
+            ),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.analyze_pipeline.raw(),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.extent.width + 15) / 16,
+            (self.extent.height + 15) / 16,
+            1,
+        );
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.reduce_pipeline.raw(),
+        );
+        device.cmd_dispatch(command_buffer, 1, 1, 1);
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::ALL_TRANSFER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::TRANSFER_READ,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl ImageAnalysis {
+    /// The number of 16x16 workgroups used by the first pass.
+    fn workgroup_count(extent: vk::Extent2D) -> u32 {
+        ((extent.width + 15) / 16) * ((extent.height + 15) / 16)
+    }
+
+    unsafe fn memory_barrier(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: &vk::MemoryBarrier2,
+    ) {
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create a device-local buffer which is only used by the graphics
+    /// queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: size.max(1),
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+}
@@ -0,0 +1,29 @@
+//! Reusable compute primitives which operate on storage buffers.
+//!
+//! Each primitive is created once and can then be recorded against any
+//! storage buffer, e.g. `sort.cmd_sort(command_buffer, &buffer, count)`.
+//! Descriptor sets are created the first time a buffer is used and reused
+//! after that.
+//!
+//! ImageAnalysis is the exception: it reads a sampled image and reduces it
+//! to an ImageStats, with a luminance histogram, min and max values, and
+//! average colors, which can be read back with a ReadbackBuffer.
+//!
+//! GpuEvents goes the other way: compute shaders append events to its
+//! buffer, and they're read back asynchronously and handed to Rust callbacks
+//! a few frames later.
+
+mod buffer_bindings;
+mod gpu_events;
+mod image_analysis;
+mod neighbor_grid;
+mod scan;
+mod sort;
+
+pub use self::{
+    gpu_events::GpuEvents,
+    image_analysis::{ImageAnalysis, ImageAnalysisParams, ImageStats},
+    neighbor_grid::{NeighborGrid, NeighborGridParams},
+    scan::Scan,
+    sort::{Sort, SortPair},
+};
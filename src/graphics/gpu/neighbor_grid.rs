@@ -0,0 +1,350 @@
+use {
+    super::{
+        buffer_bindings::{
+            after_barrier, before_barrier, compute_barrier, transfer_barrier,
+            BufferBindings,
+        },
+        Sort, SortPair,
+    },
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control how positions are assigned to grid cells.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NeighborGridParams {
+    /// The world-space size of each cubic cell. Queries which visit the 27
+    /// cells around a particle find every neighbor within this distance.
+    pub cell_size: f32,
+
+    /// The world-space position of the corner of cell (0, 0, 0).
+    pub origin: [f32; 3],
+}
+
+impl Default for NeighborGridParams {
+    fn default() -> Self {
+        Self {
+            cell_size: 1.0,
+            origin: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Push constants shared by the neighbor grid compute shaders.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct NeighborGridConstants {
+    origin_and_cell_size: [f32; 4],
+    count: u32,
+    table_size: u32,
+}
+
+/// A uniform grid for finding nearby particles on the GPU.
+///
+/// Each call to `cmd_build`:
+///
+///   1. hashes every particle's cell into a SortPair of (cell hash, index)
+///   2. sorts the pairs by cell hash with `Sort`
+///   3. records the [start, end) range of sorted pairs in each cell
+///
+/// Compute shaders then read the sorted pairs and cell ranges to visit only
+/// the particles in nearby cells. `shaders/neighbor_grid.glsl` has the
+/// matching hash function and an example query.
+///
+/// The grid is unbounded: cells are hashed into a fixed-size table, so
+/// unrelated cells may share an entry.
+pub struct NeighborGrid {
+    /// The parameters used by each call to `cmd_build`.
+    pub params: NeighborGridParams,
+
+    max_particles: u32,
+    table_size: u32,
+    sort: Sort,
+    pairs: raii::Buffer,
+    cell_ranges: raii::Buffer,
+    cells_bindings: BufferBindings,
+    ranges_descriptor_set: vk::DescriptorSet,
+    _ranges_bindings: BufferBindings,
+    pipeline_layout: raii::PipelineLayout,
+    cells_pipeline: raii::Pipeline,
+    ranges_pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl NeighborGrid {
+    /// Create the grid's buffers and pipelines.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `max_particles` - the largest number of particles in a single build
+    /// * `table_size` - the number of entries in the cell range table. A
+    ///   table about twice the particle count keeps hash collisions rare.
+    /// * `max_position_buffers` - the number of distinct position buffers
+    ///   which can be used over the lifetime of this instance
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        max_particles: u32,
+        table_size: u32,
+        max_position_buffers: u32,
+    ) -> Result<Self, GraphicsError> {
+        let table_size = table_size.max(1);
+        let pairs = Self::create_buffer(
+            &render_device,
+            (max_particles.max(1) as usize * std::mem::size_of::<SortPair>())
+                as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+        )?;
+        pairs.set_debug_name("Neighbor Grid Sorted Pairs");
+        let cell_ranges = Self::create_buffer(
+            &render_device,
+            (table_size as usize * 2 * std::mem::size_of::<u32>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        )?;
+        cell_ranges.set_debug_name("Neighbor Grid Cell Ranges");
+
+        let sort = Sort::new(render_device.clone(), 1)?;
+        let cells_bindings = BufferBindings::new(
+            render_device.clone(),
+            2,
+            max_position_buffers,
+        )?;
+        let mut ranges_bindings =
+            BufferBindings::new(render_device.clone(), 2, 1)?;
+        let ranges_descriptor_set = ranges_bindings
+            .descriptor_set(&[pairs.raw(), cell_ranges.raw()])?;
+
+        // Both shaders use the same set layout, two storage buffers, so they
+        // can share a pipeline layout.
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[cells_bindings.layout().raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<NeighborGridConstants>() as u32,
+                }],
+            )?;
+        let cells_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/neighbor_grid_cells.comp.spv"),
+        )?;
+        cells_pipeline.set_debug_name("Neighbor Grid Cells Pipeline");
+        let ranges_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/neighbor_grid_ranges.comp.spv"),
+        )?;
+        ranges_pipeline.set_debug_name("Neighbor Grid Ranges Pipeline");
+
+        Ok(Self {
+            params: NeighborGridParams::default(),
+            max_particles,
+            table_size,
+            sort,
+            pairs,
+            cell_ranges,
+            cells_bindings,
+            ranges_descriptor_set,
+            _ranges_bindings: ranges_bindings,
+            pipeline_layout,
+            cells_pipeline,
+            ranges_pipeline,
+            render_device,
+        })
+    }
+
+    /// The largest number of particles in a single build.
+    pub fn max_particles(&self) -> u32 {
+        self.max_particles
+    }
+
+    /// The number of entries in the cell range table.
+    pub fn table_size(&self) -> u32 {
+        self.table_size
+    }
+
+    /// The SortPairs of (cell hash, particle index), sorted by cell hash.
+    pub fn sorted_pairs(&self) -> &raii::Buffer {
+        &self.pairs
+    }
+
+    /// The [start, end) range of sorted pairs in each cell, as a uvec2 per
+    /// table entry.
+    pub fn cell_ranges(&self) -> &raii::Buffer {
+        &self.cell_ranges
+    }
+
+    /// Descriptor infos for the sorted pairs and cell ranges, in that order,
+    /// for writing STORAGE_BUFFER descriptors.
+    pub fn descriptor_buffer_infos(&self) -> [vk::DescriptorBufferInfo; 2] {
+        [self.pairs.raw(), self.cell_ranges.raw()].map(|buffer| {
+            vk::DescriptorBufferInfo {
+                buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            }
+        })
+    }
+
+    /// Record commands which rebuild the grid from the first `count`
+    /// positions.
+    ///
+    /// The commands end with a barrier which makes the sorted pairs and cell
+    /// ranges visible to compute, vertex, and fragment shaders.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `positions` - a STORAGE_BUFFER with one vec4 per particle. xyz is
+    ///   the position and w is ignored.
+    /// * `count` - the number of particles, at most `max_particles`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording and outside of a render pass
+    ///   - the positions buffer and this instance must not be dropped until
+    ///     the command buffer finishes executing
+    pub unsafe fn cmd_build(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        positions: &raii::Buffer,
+        count: u32,
+    ) -> Result<(), GraphicsError> {
+        if count > self.max_particles {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Unable to build a neighbor grid for {} particles, the \
+                 maximum is {}",
+                count,
+                self.max_particles
+            )));
+        }
+        let cells_descriptor_set = self
+            .cells_bindings
+            .descriptor_set(&[positions.raw(), self.pairs.raw()])?;
+
+        before_barrier(&self.render_device, command_buffer);
+        self.render_device.device().cmd_fill_buffer(
+            command_buffer,
+            self.cell_ranges.raw(),
+            0,
+            vk::WHOLE_SIZE,
+            0,
+        );
+        if count == 0 {
+            after_barrier(&self.render_device, command_buffer);
+            return Ok(());
+        }
+
+        self.dispatch(
+            command_buffer,
+            &self.cells_pipeline,
+            cells_descriptor_set,
+            count,
+        );
+        self.sort.cmd_sort(command_buffer, &self.pairs, count)?;
+        transfer_barrier(&self.render_device, command_buffer);
+        self.dispatch(
+            command_buffer,
+            &self.ranges_pipeline,
+            self.ranges_descriptor_set,
+            count,
+        );
+        after_barrier(&self.render_device, command_buffer);
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl NeighborGrid {
+    /// The compute shaders' workgroup size.
+    const WORKGROUP_SIZE: u32 = 256;
+
+    /// Record one invocation per particle, followed by a barrier.
+    unsafe fn dispatch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &raii::Pipeline,
+        descriptor_set: vk::DescriptorSet,
+        count: u32,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        let [x, y, z] = self.params.origin;
+        let constants = NeighborGridConstants {
+            origin_and_cell_size: [x, y, z, self.params.cell_size],
+            count,
+            table_size: self.table_size,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const NeighborGridConstants as *const u8,
+                std::mem::size_of::<NeighborGridConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (count + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            1,
+            1,
+        );
+        compute_barrier(&self.render_device, command_buffer);
+    }
+
+    /// Create a device-local buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+}
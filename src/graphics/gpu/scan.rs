@@ -0,0 +1,293 @@
+use {
+    super::buffer_bindings::{
+        after_barrier, before_barrier, compute_barrier, BufferBindings,
+    },
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for the scan compute shaders.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct ScanConstants {
+    count: u32,
+}
+
+/// Computes exclusive prefix sums over storage buffers of u32.
+///
+/// Each workgroup scans a block of elements in shared memory and writes the
+/// block's total to an internal buffer. The block totals are scanned the same
+/// way, level by level, until a single block remains. The scanned totals are
+/// then added back down the hierarchy.
+///
+/// The result for element `i` is the sum of every input element before `i`,
+/// which makes the output a list of write offsets for stream compaction.
+pub struct Scan {
+    max_count: u32,
+    levels: Vec<raii::Buffer>,
+    block_bindings: BufferBindings,
+    add_bindings: BufferBindings,
+    block_pipeline_layout: raii::PipelineLayout,
+    block_pipeline: raii::Pipeline,
+    add_pipeline_layout: raii::PipelineLayout,
+    add_pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl Scan {
+    /// Create the scan pipelines and the block totals for up to `max_count`
+    /// elements.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `max_count` - the largest number of elements which can be scanned
+    /// * `max_buffers` - the number of distinct input/output buffer pairs
+    ///   which can be scanned over the lifetime of this instance
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        max_count: u32,
+        max_buffers: u32,
+    ) -> Result<Self, GraphicsError> {
+        let mut levels = vec![];
+        let mut count = max_count.max(1);
+        loop {
+            count = Self::block_count(count);
+            let buffer = Self::create_buffer(&render_device, count)?;
+            buffer.set_debug_name(format!("Scan Block Sums {}", levels.len()));
+            levels.push(buffer);
+            if count == 1 {
+                break;
+            }
+        }
+
+        let max_sets = max_buffers + levels.len() as u32;
+        let block_bindings =
+            BufferBindings::new(render_device.clone(), 3, max_sets)?;
+        let add_bindings =
+            BufferBindings::new(render_device.clone(), 2, max_sets)?;
+
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: std::mem::size_of::<ScanConstants>() as u32,
+        };
+        let block_pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[block_bindings.layout().raw()],
+                &[push_constant_range],
+            )?;
+        let block_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &block_pipeline_layout,
+            include_bytes!("./shaders/scan_blocks.comp.spv"),
+        )?;
+        block_pipeline.set_debug_name("Scan Blocks Pipeline");
+        let add_pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[add_bindings.layout().raw()],
+                &[push_constant_range],
+            )?;
+        let add_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &add_pipeline_layout,
+            include_bytes!("./shaders/scan_add.comp.spv"),
+        )?;
+        add_pipeline.set_debug_name("Scan Add Pipeline");
+
+        Ok(Self {
+            max_count,
+            levels,
+            block_bindings,
+            add_bindings,
+            block_pipeline_layout,
+            block_pipeline,
+            add_pipeline_layout,
+            add_pipeline,
+            render_device,
+        })
+    }
+
+    /// The largest number of elements which can be scanned.
+    pub fn max_count(&self) -> u32 {
+        self.max_count
+    }
+
+    /// Record commands which write the exclusive scan of the first `count`
+    /// values in `input` to `output`.
+    ///
+    /// `input` and `output` may be the same buffer. Barriers match
+    /// `Sort::cmd_sort`: earlier writes complete before the scan and the
+    /// results are visible to compute, vertex, fragment, indirect, and
+    /// transfer reads afterwards.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `input` - a STORAGE_BUFFER which holds at least `count` u32 values
+    /// * `output` - a STORAGE_BUFFER with room for `count` u32 values
+    /// * `count` - the number of values to scan, at most `max_count`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording and outside of a render pass
+    ///   - the buffers and this instance must not be dropped until the
+    ///     command buffer finishes executing
+    pub unsafe fn cmd_exclusive_scan(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        input: &raii::Buffer,
+        output: &raii::Buffer,
+        count: u32,
+    ) -> Result<(), GraphicsError> {
+        if count > self.max_count {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Unable to scan {} values, the maximum is {}",
+                count,
+                self.max_count
+            )));
+        }
+        if count == 0 {
+            return Ok(());
+        }
+
+        // Level 0 scans the caller's buffers, level n scans the block sums
+        // written by level n - 1.
+        let mut passes = vec![(input.raw(), output.raw(), count)];
+        let mut level_count = count;
+        while level_count > Self::WORKGROUP_SIZE {
+            let level_buffer = self.levels[passes.len() - 1].raw();
+            level_count = Self::block_count(level_count);
+            passes.push((level_buffer, level_buffer, level_count));
+        }
+
+        before_barrier(&self.render_device, command_buffer);
+        let device = self.render_device.device();
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.block_pipeline.raw(),
+        );
+        for (level, &(input, output, count)) in passes.iter().enumerate() {
+            let block_sums = self.levels[level].raw();
+            let descriptor_set = self
+                .block_bindings
+                .descriptor_set(&[input, output, block_sums])?;
+            self.dispatch(
+                command_buffer,
+                self.block_pipeline_layout.raw(),
+                descriptor_set,
+                count,
+            );
+        }
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.add_pipeline.raw(),
+        );
+        for (level, &(_, output, count)) in passes.iter().enumerate().rev() {
+            if count <= Self::WORKGROUP_SIZE {
+                // A single block is already fully scanned.
+                continue;
+            }
+            let block_offsets = self.levels[level].raw();
+            let descriptor_set =
+                self.add_bindings.descriptor_set(&[output, block_offsets])?;
+            self.dispatch(
+                command_buffer,
+                self.add_pipeline_layout.raw(),
+                descriptor_set,
+                count,
+            );
+        }
+
+        after_barrier(&self.render_device, command_buffer);
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl Scan {
+    /// The compute shaders' workgroup size, and the number of elements each
+    /// workgroup scans.
+    const WORKGROUP_SIZE: u32 = 256;
+
+    /// The number of workgroups needed to cover `count` elements.
+    fn block_count(count: u32) -> u32 {
+        (count + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE
+    }
+
+    /// Bind a descriptor set and dispatch one invocation per element,
+    /// followed by a barrier. The pipeline must already be bound.
+    unsafe fn dispatch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        descriptor_set: vk::DescriptorSet,
+        count: u32,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline_layout,
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        let constants = ScanConstants { count };
+        device.cmd_push_constants(
+            command_buffer,
+            pipeline_layout,
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const ScanConstants as *const u8,
+                std::mem::size_of::<ScanConstants>(),
+            ),
+        );
+        device.cmd_dispatch(command_buffer, Self::block_count(count), 1, 1);
+        compute_barrier(&self.render_device, command_buffer);
+    }
+
+    /// Create a device-local storage buffer with room for `count` u32s.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        count: u32,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (count as usize * std::mem::size_of::<u32>()) as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+}
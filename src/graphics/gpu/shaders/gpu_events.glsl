@@ -0,0 +1,36 @@
+// Helpers for appending events to a GpuEvents buffer. Must match
+// gpu_events.rs.
+//
+// Declare the event struct, then the buffer with GPU_EVENTS_BUFFER, then
+// append events from any invocation:
+//
+//     struct ReachedTarget {
+//         uint particle;
+//         float time;
+//     };
+//
+//     GPU_EVENTS_BUFFER(1, 0, ReachedTarget);
+//
+//     ...
+//     uint slot;
+//     if (reserve_gpu_event(slot)) {
+//         gpu_events[slot] = ReachedTarget(index, time);
+//     }
+//
+// The Rust event type must have the same std430 layout as the GLSL struct.
+
+// Declares the event buffer at the given set and binding. The buffer starts
+// with a 16 byte header followed by the events.
+#define GPU_EVENTS_BUFFER(set_index, binding_index, Event)                    \
+    layout(std430, set = set_index, binding = binding_index)                  \
+    buffer GpuEvents {                                                        \
+        uint gpu_event_count;                                                 \
+        uint gpu_event_capacity;                                              \
+        uint gpu_event_pad[2];                                                \
+        Event gpu_events[];                                                   \
+    }
+
+// Reserve a slot for one event. Returns false when the buffer is full, in
+// which case the event is dropped but still counted.
+#define reserve_gpu_event(slot)                                               \
+    ((slot = atomicAdd(gpu_event_count, 1)) < gpu_event_capacity)
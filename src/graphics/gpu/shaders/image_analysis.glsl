@@ -0,0 +1,48 @@
+#define BIN_COUNT 256
+
+// Luminance below this is counted as black. Black pixels land in bin 0 of a
+// log scale histogram and are left out of the log average.
+const float BLACK_LUMINANCE = 1e-5;
+
+// Must match ImageStats in `gpu/image_analysis.rs`. While the analysis runs,
+// the min and max fields hold the order-preserving encoding from
+// `encode_ordered` so they can be updated with integer atomics.
+layout(std430, set = 0, binding = 1) buffer Stats {
+    uint histogram[BIN_COUNT];
+    uint min_color[4];
+    uint max_color[4];
+    vec4 average_color;
+    uint min_luminance;
+    uint max_luminance;
+    float average_luminance;
+    float log_average_luminance;
+    uint pixel_count;
+} stats;
+
+// Two entries per workgroup: the sum of its colors, then the sum of its
+// luminance, the sum of its log2 luminance, and its number of lit pixels.
+layout(std430, set = 0, binding = 2) buffer Partials {
+    vec4 partials[];
+};
+
+layout(push_constant) uniform AnalysisConstants {
+    float histogram_min;
+    float histogram_max;
+    uint log_scale;
+    uint pixel_count;
+    uint workgroup_count;
+} params;
+
+// Map a float to a uint which sorts in the same order, including negative
+// values.
+uint encode_ordered(float value) {
+    uint bits = floatBitsToUint(value);
+    return (bits & 0x80000000u) != 0 ? ~bits : bits | 0x80000000u;
+}
+
+float decode_ordered(uint encoded) {
+    uint bits = (encoded & 0x80000000u) != 0
+        ? encoded & 0x7fffffffu
+        : ~encoded;
+    return uintBitsToFloat(bits);
+}
@@ -0,0 +1,31 @@
+// Helpers for reading the tables built by NeighborGrid. Must match
+// neighbor_grid.rs.
+//
+// A typical neighbor query visits the 27 cells around a particle:
+//
+//     ivec3 cell = neighbor_grid_cell(position, origin, cell_size);
+//     for (int z = -1; z <= 1; z++)
+//     for (int y = -1; y <= 1; y++)
+//     for (int x = -1; x <= 1; x++) {
+//         uint hash = neighbor_grid_hash(cell + ivec3(x, y, z), table_size);
+//         uvec2 range = cell_ranges[hash];
+//         for (uint i = range.x; i < range.y; i++) {
+//             uint other = sorted_pairs[i].value;
+//             ...
+//         }
+//     }
+//
+// Distinct cells can hash to the same entry, so queries must still check
+// the distance to each candidate.
+
+// The integer cell which contains a position.
+ivec3 neighbor_grid_cell(vec3 position, vec3 origin, float cell_size) {
+    return ivec3(floor((position - origin) / cell_size));
+}
+
+// The index of a cell's entry in the cell range table.
+uint neighbor_grid_hash(ivec3 cell, uint table_size) {
+    uvec3 c = uvec3(cell);
+    return ((c.x * 73856093u) ^ (c.y * 19349663u) ^ (c.z * 83492791u))
+        % table_size;
+}
@@ -0,0 +1,201 @@
+use {
+    super::buffer_bindings::{
+        after_barrier, before_barrier, compute_barrier, BufferBindings,
+    },
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A single key/value pair sorted by `Sort`. Must match SortPair in
+/// `shaders/bitonic_sort.comp`.
+///
+/// Keys are compared as unsigned integers. Non-negative floats, like
+/// view-space depths, sort correctly when stored with `f32::to_bits`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct SortPair {
+    pub key: u32,
+    pub value: u32,
+}
+
+/// Push constants for the bitonic sort compute shader.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SortConstants {
+    count: u32,
+    block_height: u32,
+    flip: u32,
+}
+
+/// Sorts storage buffers of SortPair by key, in ascending order, with a
+/// bitonic sorting network.
+///
+/// The sort runs entirely on the GPU. A buffer of `n` pairs takes
+/// O(log²(n)) dispatches separated by compute barriers, which is plenty
+/// fast for the hundreds of thousands of particles in a typical sketch.
+/// The sort is not stable.
+pub struct Sort {
+    bindings: BufferBindings,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl Sort {
+    /// Create the sorting pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `max_buffers` - the number of distinct buffers which can be sorted
+    ///   over the lifetime of this instance
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        max_buffers: u32,
+    ) -> Result<Self, GraphicsError> {
+        let bindings =
+            BufferBindings::new(render_device.clone(), 1, max_buffers)?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[bindings.layout().raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<SortConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/bitonic_sort.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Bitonic Sort Pipeline");
+        Ok(Self {
+            bindings,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Record commands which sort the first `count` pairs in `buffer` by key.
+    ///
+    /// Earlier transfer and shader writes to the buffer, and earlier reads by
+    /// draws, complete before the sort begins. The commands end with a
+    /// barrier which makes the sorted pairs visible to compute shaders,
+    /// vertex input, vertex and fragment shaders, indirect draws, and
+    /// transfers.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `buffer` - a STORAGE_BUFFER which holds at least `count` SortPairs
+    /// * `count` - the number of pairs to sort
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording and outside of a render pass
+    ///   - the buffer and this instance must not be dropped until the command
+    ///     buffer finishes executing
+    pub unsafe fn cmd_sort(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        buffer: &raii::Buffer,
+        count: u32,
+    ) -> Result<(), GraphicsError> {
+        if count < 2 {
+            return Ok(());
+        }
+        let descriptor_set = self.bindings.descriptor_set(&[buffer.raw()])?;
+
+        let device = self.render_device.device();
+        before_barrier(&self.render_device, command_buffer);
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[descriptor_set],
+            &[],
+        );
+
+        // Each stage doubles the size of the sorted blocks: a flip merges
+        // pairs of sorted blocks, then disperses finish the merge.
+        let padded_count = count.next_power_of_two();
+        let mut block_height = 2;
+        while block_height <= padded_count {
+            self.dispatch(command_buffer, count, block_height, true);
+            let mut disperse_height = block_height / 2;
+            while disperse_height >= 2 {
+                self.dispatch(command_buffer, count, disperse_height, false);
+                disperse_height /= 2;
+            }
+            block_height *= 2;
+        }
+
+        after_barrier(&self.render_device, command_buffer);
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl Sort {
+    /// The compute shader's workgroup size.
+    const WORKGROUP_SIZE: u32 = 256;
+
+    /// Record a single compare-and-swap pass over every pair of elements,
+    /// followed by a barrier.
+    unsafe fn dispatch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        count: u32,
+        block_height: u32,
+        flip: bool,
+    ) {
+        let device = self.render_device.device();
+        let constants = SortConstants {
+            count,
+            block_height,
+            flip: flip as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const SortConstants as *const u8,
+                std::mem::size_of::<SortConstants>(),
+            ),
+        );
+        let invocations = count.next_power_of_two() / 2;
+        device.cmd_dispatch(
+            command_buffer,
+            (invocations + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            1,
+            1,
+        );
+        compute_barrier(&self.render_device, command_buffer);
+    }
+}
@@ -0,0 +1,24 @@
+//! Evenly-spaced samples of matplotlib's perceptually-uniform colormaps.
+//!
+//! Nine samples keep the tables short while linear interpolation stays close
+//! to the full 256-entry colormaps.
+
+pub const VIRIDIS: [u32; 9] = [
+    0x440154, 0x472d7b, 0x3b528b, 0x2c728e, 0x21918c, 0x28ae80, 0x5ec962,
+    0xaadc32, 0xfde725,
+];
+
+pub const INFERNO: [u32; 9] = [
+    0x000004, 0x1b0c41, 0x4a0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925,
+    0xfb9b06, 0xfcffa4,
+];
+
+pub const MAGMA: [u32; 9] = [
+    0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55064, 0xfb8761,
+    0xfec287, 0xfcfdbf,
+];
+
+pub const PLASMA: [u32; 9] = [
+    0x0d0887, 0x4c02a1, 0x7e03a8, 0xa92395, 0xcc4778, 0xe56b5d, 0xf89540,
+    0xfdc527, 0xf0f921,
+];
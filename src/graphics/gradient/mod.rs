@@ -0,0 +1,157 @@
+//! Color gradients and scientific colormaps which can be baked into 1D
+//! lookup textures.
+//!
+//! Shaders can map any scalar (speed, density, temperature) to a color by
+//! sampling the baked texture at `vec2(value, 0.5)`.
+
+mod colormaps;
+
+use crate::graphics::{
+    vulkan_api::{Texture2D, TextureLoader},
+    GraphicsError,
+};
+
+/// A color at a position along a gradient.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorStop {
+    /// The stop's position in [0, 1].
+    pub position: f32,
+
+    /// The stop's RGBA color, with each component in [0, 1].
+    pub color: [f32; 4],
+}
+
+impl ColorStop {
+    pub fn new(position: f32, color: [f32; 4]) -> Self {
+        Self { position, color }
+    }
+
+    /// Create an opaque stop from a hex color like 0xff8800.
+    pub fn from_hex(position: f32, rgb: u32) -> Self {
+        let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+        Self::new(position, [channel(16), channel(8), channel(0), 1.0])
+    }
+}
+
+/// A piecewise-linear color gradient.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<ColorStop>,
+}
+
+// Public API
+// ----------
+
+impl Gradient {
+    /// The default width, in texels, of baked gradient textures.
+    pub const DEFAULT_TEXTURE_WIDTH: u32 = 256;
+
+    /// Create a gradient from color stops. Stops are sorted by position.
+    pub fn new(mut stops: Vec<ColorStop>) -> Self {
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self { stops }
+    }
+
+    /// The perceptually-uniform viridis colormap.
+    pub fn viridis() -> Self {
+        Self::from_hex_colors(&colormaps::VIRIDIS)
+    }
+
+    /// The perceptually-uniform inferno colormap.
+    pub fn inferno() -> Self {
+        Self::from_hex_colors(&colormaps::INFERNO)
+    }
+
+    /// The perceptually-uniform magma colormap.
+    pub fn magma() -> Self {
+        Self::from_hex_colors(&colormaps::MAGMA)
+    }
+
+    /// The perceptually-uniform plasma colormap.
+    pub fn plasma() -> Self {
+        Self::from_hex_colors(&colormaps::PLASMA)
+    }
+
+    /// The gradient's color stops, sorted by position.
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// The gradient's color at `t`. Values outside of the first and last
+    /// stops use the nearest stop's color.
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [0.0, 0.0, 0.0, 1.0],
+        };
+        if t <= first.position {
+            return first.color;
+        }
+        if t >= last.position {
+            return last.color;
+        }
+        let next = self.stops.partition_point(|stop| stop.position <= t);
+        let (a, b) = (&self.stops[next - 1], &self.stops[next]);
+        let span = b.position - a.position;
+        let amount = if span > 0.0 {
+            (t - a.position) / span
+        } else {
+            0.0
+        };
+        let mut color = [0.0; 4];
+        for (i, channel) in color.iter_mut().enumerate() {
+            *channel = a.color[i] + (b.color[i] - a.color[i]) * amount;
+        }
+        color
+    }
+
+    /// Sample the gradient at `width` evenly-spaced texel centers and return
+    /// the RGBA8 pixel data.
+    pub fn to_rgba8(&self, width: u32) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width as usize * 4);
+        for x in 0..width {
+            let t = (x as f32 + 0.5) / width as f32;
+            for channel in self.sample(t) {
+                pixels.push((channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+        pixels
+    }
+
+    /// Bake the gradient into a `width x 1` texture.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    /// - the caller is responsible for destroying the returned texture before
+    ///   render device is dropped
+    pub unsafe fn bake(
+        &self,
+        texture_loader: &mut TextureLoader,
+        width: u32,
+    ) -> Result<Texture2D, GraphicsError> {
+        let width = width.max(1);
+        texture_loader.create_texture_2d_from_rgba8(
+            width,
+            1,
+            &self.to_rgba8(width),
+        )
+    }
+}
+
+// Private API
+// -----------
+
+impl Gradient {
+    /// Create a gradient from evenly-spaced hex colors.
+    fn from_hex_colors(colors: &[u32]) -> Self {
+        let last = (colors.len().max(2) - 1) as f32;
+        Self::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, &rgb)| ColorStop::from_hex(i as f32 / last, rgb))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,167 @@
+use {
+    super::IblImage,
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Prefiltered images for lighting a scene with an environment map.
+///
+/// The descriptor set has the bindings expected by `material/shaders/pbr.glsl`:
+///
+/// * binding 0 - the diffuse irradiance `samplerCube`
+/// * binding 1 - the prefiltered specular `samplerCube`, with roughness
+///   increasing along the mip chain
+/// * binding 2 - the BRDF lookup table `sampler2D`
+pub struct EnvironmentLighting {
+    /// The cube map which was prefiltered, when the IblGenerator created it
+    /// from an equirectangular panorama.
+    pub(super) environment: Option<IblImage>,
+
+    irradiance: IblImage,
+    specular: IblImage,
+    specular_mip_levels: u32,
+    brdf_lut: Arc<IblImage>,
+    descriptor_pool: raii::DescriptorPool,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+    _sampler: raii::Sampler,
+}
+
+// Public API
+// ----------
+
+impl EnvironmentLighting {
+    /// The layout of the descriptor set returned by `descriptor_set`. Use it
+    /// when building pipelines which shade with this lighting.
+    pub fn descriptor_set_layout(&self) -> &raii::DescriptorSetLayout {
+        &self.descriptor_set_layout
+    }
+
+    /// A descriptor set which references every lighting image.
+    pub fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.descriptor_pool.descriptor_set(0)
+    }
+
+    /// The number of mip levels in the specular cube. Shaders pick a level
+    /// with `roughness * (specular_mip_levels - 1)`.
+    pub fn specular_mip_levels(&self) -> u32 {
+        self.specular_mip_levels
+    }
+
+    /// A CUBE view of the diffuse irradiance.
+    pub fn irradiance_view(&self) -> &raii::ImageView {
+        &self.irradiance.image_view
+    }
+
+    /// A CUBE view of the prefiltered specular mip chain.
+    pub fn specular_view(&self) -> &raii::ImageView {
+        &self.specular.image_view
+    }
+
+    /// A 2D view of the BRDF lookup table.
+    pub fn brdf_lut_view(&self) -> &raii::ImageView {
+        &self.brdf_lut.image_view
+    }
+
+    /// A CUBE view of the environment, useful for drawing a skybox. This is
+    /// only available when the lighting was created from an equirectangular
+    /// panorama.
+    pub fn environment_view(&self) -> Option<&raii::ImageView> {
+        self.environment
+            .as_ref()
+            .map(|environment| &environment.image_view)
+    }
+}
+
+// Private API
+// -----------
+
+impl EnvironmentLighting {
+    /// Create the descriptor set for already-generated images. Every image
+    /// must be in SHADER_READ_ONLY_OPTIMAL.
+    pub(super) unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        irradiance: IblImage,
+        specular: IblImage,
+        specular_mip_levels: u32,
+        brdf_lut: Arc<IblImage>,
+    ) -> Result<Self, GraphicsError> {
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings =
+            [0, 1, 2].map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT
+                    | vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            });
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: bindings.len() as u32,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        let image_infos = [
+            &irradiance.image_view,
+            &specular.image_view,
+            &brdf_lut.image_view,
+        ]
+        .map(|image_view| vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: image_view.raw(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        });
+        let writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, image_info)| vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                p_image_info: image_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            environment: None,
+            irradiance,
+            specular,
+            specular_mip_levels,
+            brdf_lut,
+            descriptor_pool,
+            descriptor_set_layout,
+            _sampler: sampler,
+        })
+    }
+}
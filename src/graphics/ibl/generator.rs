@@ -0,0 +1,896 @@
+use {
+    super::EnvironmentLighting,
+    crate::graphics::{
+        vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for the irradiance and specular compute shaders. Must match
+/// PrefilterConstants in `shaders/irradiance.comp` and
+/// `shaders/prefilter_specular.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct PrefilterConstants {
+    roughness: f32,
+    sample_count: u32,
+}
+
+/// A device-local image and a view which samples every mip level.
+pub struct IblImage {
+    pub image_view: raii::ImageView,
+    pub image: raii::Image,
+}
+
+/// Prefilters environment cube maps into the images used for image based
+/// lighting.
+///
+/// The BRDF lookup table doesn't depend on the environment, so it's generated
+/// once when the generator is created and shared by every
+/// EnvironmentLighting. Generation blocks until the GPU finishes.
+pub struct IblGenerator {
+    brdf_lut: Arc<IblImage>,
+    one_time_submit: OneTimeSubmitCommandBuffer,
+    sampler: raii::Sampler,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    equirect_pipeline: raii::Pipeline,
+    irradiance_pipeline: raii::Pipeline,
+    specular_pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl IblGenerator {
+    /// The format of every generated image.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The size of each irradiance cube face. Irradiance has no high
+    /// frequency detail, so it can be tiny.
+    pub const IRRADIANCE_SIZE: u32 = 32;
+
+    /// The size of the BRDF lookup table.
+    pub const BRDF_LUT_SIZE: u32 = 256;
+
+    /// The number of mip levels in the specular cube. Mip `i` is filtered for
+    /// a perceptual roughness of `i / (SPECULAR_MIP_LEVELS - 1)`.
+    pub const SPECULAR_MIP_LEVELS: u32 = 6;
+
+    /// Create the compute pipelines and generate the BRDF lookup table.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the generator must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<Self, GraphicsError> {
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 1,
+                        descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                ],
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<PrefilterConstants>() as u32,
+                }],
+            )?;
+        let equirect_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/equirect_to_cube.comp.spv"),
+            )?;
+        equirect_pipeline.set_debug_name("IBL Equirect To Cube Pipeline");
+        let irradiance_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/irradiance.comp.spv"),
+            )?;
+        irradiance_pipeline.set_debug_name("IBL Irradiance Pipeline");
+        let specular_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/prefilter_specular.comp.spv"),
+            )?;
+        specular_pipeline.set_debug_name("IBL Specular Pipeline");
+
+        let brdf_lut = Arc::new(Self::generate_brdf_lut(
+            &render_device,
+            &mut one_time_submit,
+        )?);
+
+        Ok(Self {
+            brdf_lut,
+            one_time_submit,
+            sampler,
+            descriptor_set_layout,
+            pipeline_layout,
+            equirect_pipeline,
+            irradiance_pipeline,
+            specular_pipeline,
+            render_device,
+        })
+    }
+
+    /// The BRDF lookup table shared by every EnvironmentLighting.
+    pub fn brdf_lut(&self) -> &Arc<IblImage> {
+        &self.brdf_lut
+    }
+
+    /// Project an equirectangular panorama onto a cube map, then prefilter
+    /// it.
+    ///
+    /// # Params
+    ///
+    /// * `equirectangular_view` - a 2D panorama with +Y up, in the
+    ///   SHADER_READ_ONLY_OPTIMAL layout, like a Texture2D's view
+    /// * `size` - the size of each face of the environment and specular cubes
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the returned lighting must be dropped before the render device
+    pub unsafe fn from_equirectangular(
+        &mut self,
+        equirectangular_view: &raii::ImageView,
+        size: u32,
+    ) -> Result<EnvironmentLighting, GraphicsError> {
+        let mip_levels = Self::full_mip_levels(size);
+        let environment = self.create_cube(
+            size,
+            mip_levels,
+            vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST,
+            "IBL Environment",
+        )?;
+        let storage_view = self.create_storage_view(&environment.image, 0)?;
+
+        let descriptor_pool = self.create_descriptor_pool(1)?;
+        self.write_descriptor_set(
+            &descriptor_pool,
+            0,
+            equirectangular_view,
+            &storage_view,
+        );
+
+        let command_buffer = self.one_time_submit.command_buffer();
+        self.image_barrier(
+            command_buffer,
+            &environment.image,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::GENERAL,
+            Self::cube_range(0, mip_levels),
+        );
+        self.dispatch_cube(
+            command_buffer,
+            &self.equirect_pipeline,
+            descriptor_pool.descriptor_set(0),
+            size,
+            &PrefilterConstants {
+                roughness: 0.0,
+                sample_count: 0,
+            },
+        );
+        self.generate_mipmaps(command_buffer, &environment.image, size);
+        self.one_time_submit.sync_submit_and_reset()?;
+        drop(descriptor_pool);
+        drop(storage_view);
+
+        let mut lighting = self.prefilter(&environment.image_view, size)?;
+        lighting.environment = Some(environment);
+        Ok(lighting)
+    }
+
+    /// Prefilter an existing environment cube map.
+    ///
+    /// The environment is sampled with filtered importance sampling, so a
+    /// full mip chain gives much smoother results.
+    ///
+    /// # Params
+    ///
+    /// * `environment_view` - a CUBE view of the environment, in the
+    ///   SHADER_READ_ONLY_OPTIMAL layout
+    /// * `size` - the size of each face of the specular cube
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the returned lighting must be dropped before the render device
+    pub unsafe fn from_cubemap(
+        &mut self,
+        environment_view: &raii::ImageView,
+        size: u32,
+    ) -> Result<EnvironmentLighting, GraphicsError> {
+        self.prefilter(environment_view, size)
+    }
+}
+
+// Private API
+// -----------
+
+impl IblGenerator {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// Samples per texel when convolving irradiance.
+    const IRRADIANCE_SAMPLES: u32 = 256;
+
+    /// Samples per texel when prefiltering the specular cube.
+    const SPECULAR_SAMPLES: u32 = 512;
+
+    /// The number of mip levels in a full chain for the given size.
+    fn full_mip_levels(size: u32) -> u32 {
+        32 - size.max(1).leading_zeros()
+    }
+
+    /// Every face of the given mip levels of a cube image.
+    fn cube_range(
+        base_mip_level: u32,
+        level_count: u32,
+    ) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count: 6,
+        }
+    }
+
+    /// Convolve the environment into irradiance and specular cubes.
+    unsafe fn prefilter(
+        &mut self,
+        environment_view: &raii::ImageView,
+        size: u32,
+    ) -> Result<EnvironmentLighting, GraphicsError> {
+        let usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+        let irradiance = self.create_cube(
+            Self::IRRADIANCE_SIZE,
+            1,
+            usage,
+            "IBL Irradiance",
+        )?;
+        let specular_mip_levels =
+            Self::SPECULAR_MIP_LEVELS.min(Self::full_mip_levels(size));
+        let specular =
+            self.create_cube(size, specular_mip_levels, usage, "IBL Specular")?;
+
+        // Set 0 writes irradiance and set 1 + i writes specular mip i.
+        let mut storage_views =
+            vec![self.create_storage_view(&irradiance.image, 0)?];
+        for mip in 0..specular_mip_levels {
+            storage_views.push(self.create_storage_view(&specular.image, mip)?);
+        }
+        let descriptor_pool =
+            self.create_descriptor_pool(storage_views.len() as u32)?;
+        for (index, storage_view) in storage_views.iter().enumerate() {
+            self.write_descriptor_set(
+                &descriptor_pool,
+                index,
+                environment_view,
+                storage_view,
+            );
+        }
+
+        let command_buffer = self.one_time_submit.command_buffer();
+        self.image_barrier(
+            command_buffer,
+            &irradiance.image,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::GENERAL,
+            Self::cube_range(0, 1),
+        );
+        self.image_barrier(
+            command_buffer,
+            &specular.image,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::GENERAL,
+            Self::cube_range(0, specular_mip_levels),
+        );
+        self.dispatch_cube(
+            command_buffer,
+            &self.irradiance_pipeline,
+            descriptor_pool.descriptor_set(0),
+            Self::IRRADIANCE_SIZE,
+            &PrefilterConstants {
+                roughness: 1.0,
+                sample_count: Self::IRRADIANCE_SAMPLES,
+            },
+        );
+        for mip in 0..specular_mip_levels {
+            let roughness = if specular_mip_levels > 1 {
+                mip as f32 / (specular_mip_levels - 1) as f32
+            } else {
+                0.0
+            };
+            self.dispatch_cube(
+                command_buffer,
+                &self.specular_pipeline,
+                descriptor_pool.descriptor_set(1 + mip as usize),
+                (size >> mip).max(1),
+                &PrefilterConstants {
+                    roughness,
+                    sample_count: Self::SPECULAR_SAMPLES,
+                },
+            );
+        }
+        self.image_barrier(
+            command_buffer,
+            &irradiance.image,
+            vk::ImageLayout::GENERAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            Self::cube_range(0, 1),
+        );
+        self.image_barrier(
+            command_buffer,
+            &specular.image,
+            vk::ImageLayout::GENERAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            Self::cube_range(0, specular_mip_levels),
+        );
+
+        // The descriptor pool and storage views can be dropped as soon as the
+        // commands finish.
+        self.one_time_submit.sync_submit_and_reset()?;
+
+        EnvironmentLighting::new(
+            self.render_device.clone(),
+            irradiance,
+            specular,
+            specular_mip_levels,
+            self.brdf_lut.clone(),
+        )
+    }
+
+    /// Fill the BRDF lookup table.
+    unsafe fn generate_brdf_lut(
+        render_device: &Arc<RenderDevice>,
+        one_time_submit: &mut OneTimeSubmitCommandBuffer,
+    ) -> Result<IblImage, GraphicsError> {
+        let binding = vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            ..vk::DescriptorSetLayoutBinding::default()
+        };
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[binding],
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/brdf_lut.comp.spv"),
+        )?;
+        pipeline.set_debug_name("IBL BRDF LUT Pipeline");
+
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: Self::BRDF_LUT_SIZE,
+                height: Self::BRDF_LUT_SIZE,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name("IBL BRDF LUT");
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let image_view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        let image_info = vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: image_view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        render_device.device().update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            }],
+            &[],
+        );
+
+        let device = render_device.device();
+        let command_buffer = one_time_submit.command_buffer();
+        let barrier_before = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::TOP_OF_PIPE,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_before,
+                ..Default::default()
+            },
+        );
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline_layout.raw(),
+            0,
+            &[descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        let group_count = (Self::BRDF_LUT_SIZE + Self::WORKGROUP_SIZE - 1)
+            / Self::WORKGROUP_SIZE;
+        device.cmd_dispatch(command_buffer, group_count, group_count, 1);
+        let barrier_after = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            image: image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_after,
+                ..Default::default()
+            },
+        );
+
+        // The pipeline and descriptors can be dropped as soon as the commands
+        // finish.
+        one_time_submit.sync_submit_and_reset()?;
+
+        Ok(IblImage { image_view, image })
+    }
+
+    /// Downsample mip 0 of a cube in GENERAL into the rest of the chain with
+    /// blits. Every level ends in SHADER_READ_ONLY_OPTIMAL.
+    unsafe fn generate_mipmaps(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image: &raii::Image,
+        size: u32,
+    ) {
+        let mip_levels = Self::full_mip_levels(size);
+        for mip in 1..mip_levels {
+            // The previous level was written by compute or by a blit.
+            let (old_layout, src_stage_mask, src_access_mask) = if mip == 1 {
+                (
+                    vk::ImageLayout::GENERAL,
+                    vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                )
+            } else {
+                (
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::PipelineStageFlags2::BLIT,
+                    vk::AccessFlags2::TRANSFER_WRITE,
+                )
+            };
+            let barriers = [
+                vk::ImageMemoryBarrier2 {
+                    src_stage_mask,
+                    src_access_mask,
+                    dst_stage_mask: vk::PipelineStageFlags2::BLIT,
+                    dst_access_mask: vk::AccessFlags2::TRANSFER_READ,
+                    old_layout,
+                    new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    image: image.raw(),
+                    subresource_range: Self::cube_range(mip - 1, 1),
+                    ..Default::default()
+                },
+                vk::ImageMemoryBarrier2 {
+                    src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    src_access_mask: vk::AccessFlags2::NONE,
+                    dst_stage_mask: vk::PipelineStageFlags2::BLIT,
+                    dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                    old_layout: vk::ImageLayout::UNDEFINED,
+                    new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    image: image.raw(),
+                    subresource_range: Self::cube_range(mip, 1),
+                    ..Default::default()
+                },
+            ];
+            self.render_device.device().cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo {
+                    image_memory_barrier_count: barriers.len() as u32,
+                    p_image_memory_barriers: barriers.as_ptr(),
+                    ..Default::default()
+                },
+            );
+
+            let src_size = (size >> (mip - 1)).max(1) as i32;
+            let dst_size = (size >> mip).max(1) as i32;
+            let layers = |mip_level: u32| vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level,
+                base_array_layer: 0,
+                layer_count: 6,
+            };
+            self.render_device.device().cmd_blit_image(
+                command_buffer,
+                image.raw(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image.raw(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::ImageBlit {
+                    src_subresource: layers(mip - 1),
+                    src_offsets: [
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                        vk::Offset3D {
+                            x: src_size,
+                            y: src_size,
+                            z: 1,
+                        },
+                    ],
+                    dst_subresource: layers(mip),
+                    dst_offsets: [
+                        vk::Offset3D { x: 0, y: 0, z: 0 },
+                        vk::Offset3D {
+                            x: dst_size,
+                            y: dst_size,
+                            z: 1,
+                        },
+                    ],
+                }],
+                vk::Filter::LINEAR,
+            );
+        }
+
+        // Every level but the last is a blit source, the last level was
+        // written by a blit or, with a single level, by compute.
+        if mip_levels > 1 {
+            self.image_barrier(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                Self::cube_range(0, mip_levels - 1),
+            );
+            self.image_barrier(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                Self::cube_range(mip_levels - 1, 1),
+            );
+        } else {
+            self.image_barrier(
+                command_buffer,
+                image,
+                vk::ImageLayout::GENERAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                Self::cube_range(0, 1),
+            );
+        }
+    }
+
+    /// A layout transition which waits for all prior compute and transfer
+    /// work.
+    unsafe fn image_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image: &raii::Image,
+        old_layout: vk::ImageLayout,
+        new_layout: vk::ImageLayout,
+        subresource_range: vk::ImageSubresourceRange,
+    ) {
+        let barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::BLIT,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE
+                | vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            old_layout,
+            new_layout,
+            image: image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Run a cube pass with one invocation per texel of every face.
+    unsafe fn dispatch_cube(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &raii::Pipeline,
+        descriptor_set: vk::DescriptorSet,
+        size: u32,
+        constants: &PrefilterConstants,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                constants as *const PrefilterConstants as *const u8,
+                std::mem::size_of::<PrefilterConstants>(),
+            ),
+        );
+        let group_count =
+            (size + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE;
+        device.cmd_dispatch(command_buffer, group_count, group_count, 6);
+    }
+
+    /// Create a pool with `count` sets which each read one image and write
+    /// another.
+    unsafe fn create_descriptor_pool(
+        &self,
+        count: u32,
+    ) -> Result<raii::DescriptorPool, GraphicsError> {
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            self.render_device.clone(),
+            count,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: count,
+                },
+            ],
+        )?;
+        let layouts = (0..count)
+            .map(|_| &self.descriptor_set_layout)
+            .collect::<Vec<_>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        Ok(descriptor_pool)
+    }
+
+    /// Point a descriptor set at its source and destination images.
+    unsafe fn write_descriptor_set(
+        &self,
+        descriptor_pool: &raii::DescriptorPool,
+        index: usize,
+        source_view: &raii::ImageView,
+        storage_view: &raii::ImageView,
+    ) {
+        let source_info = vk::DescriptorImageInfo {
+            sampler: self.sampler.raw(),
+            image_view: source_view.raw(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let storage_info = vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: storage_view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let dst_set = descriptor_pool.descriptor_set(index);
+        self.render_device.device().update_descriptor_sets(
+            &[
+                vk::WriteDescriptorSet {
+                    dst_set,
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    p_image_info: &source_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set,
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 1,
+                    p_image_info: &storage_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ],
+            &[],
+        );
+    }
+
+    /// Create a cube image and a CUBE view of every mip level.
+    unsafe fn create_cube(
+        &self,
+        size: u32,
+        mip_levels: u32,
+        usage: vk::ImageUsageFlags,
+        name: &str,
+    ) -> Result<IblImage, GraphicsError> {
+        let queue_family_index =
+            self.render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels,
+            array_layers: 6,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            flags: vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            extent: vk::Extent3D {
+                width: size,
+                height: size,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            self.render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name(name);
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::CUBE,
+            format: Self::FORMAT,
+            subresource_range: Self::cube_range(0, mip_levels),
+            ..Default::default()
+        };
+        let image_view = raii::ImageView::new(
+            self.render_device.clone(),
+            &view_create_info,
+        )?;
+        Ok(IblImage { image_view, image })
+    }
+
+    /// Create a 2D array view of every face of one mip level, for writing
+    /// with imageStore.
+    unsafe fn create_storage_view(
+        &self,
+        image: &raii::Image,
+        mip_level: u32,
+    ) -> Result<raii::ImageView, GraphicsError> {
+        let create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+            format: Self::FORMAT,
+            subresource_range: Self::cube_range(mip_level, 1),
+            ..Default::default()
+        };
+        raii::ImageView::new(self.render_device.clone(), &create_info)
+    }
+}
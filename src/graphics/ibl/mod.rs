@@ -0,0 +1,15 @@
+//! Image based lighting generated with compute shaders.
+//!
+//! The IblGenerator prefilters an environment cube map, or an equirectangular
+//! panorama, into a diffuse irradiance cube and a specular cube whose mip
+//! levels are filtered for increasing roughness. Together with a BRDF lookup
+//! table they let `material/shaders/pbr.glsl` light PBR materials with the
+//! environment.
+
+mod environment;
+mod generator;
+
+pub use self::{
+    environment::EnvironmentLighting,
+    generator::{IblGenerator, IblImage},
+};
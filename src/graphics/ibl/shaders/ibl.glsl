@@ -0,0 +1,70 @@
+// Helpers shared by the IBL compute shaders.
+
+const float PI = 3.14159265359;
+
+// The direction through a cube face texel. Faces are ordered +X, -X, +Y, -Y,
+// +Z, -Z and uv is in [0, 1] with v pointing down, matching Vulkan's cube
+// map layout.
+vec3 cube_direction(uint face, vec2 uv) {
+    vec2 p = uv * 2.0 - 1.0;
+    vec3 direction;
+    switch (face) {
+        case 0: direction = vec3(1.0, -p.y, -p.x); break;
+        case 1: direction = vec3(-1.0, -p.y, p.x); break;
+        case 2: direction = vec3(p.x, 1.0, p.y); break;
+        case 3: direction = vec3(p.x, -1.0, -p.y); break;
+        case 4: direction = vec3(p.x, -p.y, 1.0); break;
+        default: direction = vec3(-p.x, -p.y, -1.0); break;
+    }
+    return normalize(direction);
+}
+
+// The invocation's cube face direction, or false when it's outside the
+// destination.
+bool invocation_direction(ivec2 size, out vec3 direction) {
+    ivec3 texel = ivec3(gl_GlobalInvocationID);
+    if (texel.x >= size.x || texel.y >= size.y) {
+        return false;
+    }
+    vec2 uv = (vec2(texel.xy) + 0.5) / vec2(size);
+    direction = cube_direction(uint(texel.z), uv);
+    return true;
+}
+
+// A low-discrepancy point in [0, 1)^2.
+vec2 hammersley(uint i, uint count) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// A tangent frame around n.
+mat3 tangent_frame(vec3 n) {
+    vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, n));
+    vec3 bitangent = cross(n, tangent);
+    return mat3(tangent, bitangent, n);
+}
+
+// A GGX-distributed half vector around n for the given perceptual
+// roughness.
+vec3 importance_sample_ggx(vec2 xi, vec3 n, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    return tangent_frame(n) * h;
+}
+
+// The GGX normal distribution function.
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
@@ -0,0 +1,15 @@
+//! Drawing many copies of a mesh with GPU-driven level of detail.
+//!
+//! InstancedMeshRenderer holds up to four QuantizedMesh levels of detail and
+//! a storage buffer of MeshInstance transforms. Each frame, a compute pass
+//! measures every instance's bounding sphere in pixels, picks a level, and
+//! appends the instance to that level's list with an atomic counter. Each
+//! level is then drawn with its own indirect draw, so the CPU never touches
+//! per-instance data after upload.
+//!
+//! Renderers made with `new_with_velocity` draw into a post::Taa render pass
+//! and write per-pixel velocity from the camera and instance motion.
+
+mod renderer;
+
+pub use self::renderer::{InstanceLod, InstancedMeshRenderer, MeshInstance};
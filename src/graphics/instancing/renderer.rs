@@ -0,0 +1,944 @@
+use {
+    crate::{
+        graphics::{
+            mesh::{QuantizedMesh, QuantizedVertex, VertexFormat},
+            post::Taa,
+            vulkan_api::{
+                raii, ColorPass, Frame, GraphicsPipelineBuilder, RenderDevice,
+            },
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A single copy of the mesh. Must match MeshInstance in
+/// `shaders/instancing.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct MeshInstance {
+    /// Transforms the mesh from model space to world space.
+    pub transform: Mat4,
+
+    /// Multiplied with the mesh's vertex colors.
+    pub color: [f32; 4],
+}
+
+impl Default for MeshInstance {
+    fn default() -> Self {
+        Self {
+            transform: Mat4::identity(),
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// One level of detail for an InstancedMeshRenderer.
+pub struct InstanceLod {
+    /// The mesh drawn for instances at this level.
+    pub mesh: QuantizedMesh<QuantizedVertex>,
+
+    /// Instances whose bounding sphere is at least this many pixels across
+    /// on screen can use this level.
+    pub min_screen_size: f32,
+}
+
+/// Push constants for the LOD selection shader. Must match
+/// SelectLodConstants in `shaders/select_lod.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SelectLodConstants {
+    view: Mat4,
+    bounds: [f32; 4],
+    min_screen_sizes: [f32; 4],
+    pixels_per_unit: f32,
+    lod_count: u32,
+    instance_count: u32,
+    max_instances: u32,
+}
+
+/// Push constants for the draw pipeline. Must match DrawConstants in
+/// `shaders/instanced_mesh.vert`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct DrawConstants {
+    view_projection: Mat4,
+    dequantize_scale: [f32; 4],
+    dequantize_offset: [f32; 3],
+    visible_offset: u32,
+}
+
+/// Push constants for the velocity draw pipeline. Must match
+/// VelocityDrawConstants in `shaders/instanced_mesh_velocity.vert`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct VelocityDrawConstants {
+    view_projection: Mat4,
+    previous_view_projection: Mat4,
+}
+
+/// Per-level data for the velocity draw pipeline, which has no room left in
+/// its push constants. Must match Lod in
+/// `shaders/instanced_mesh_velocity.vert`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct LodUniform {
+    dequantize_scale: [f32; 4],
+    dequantize_offset: [f32; 3],
+    visible_offset: u32,
+}
+
+/// Draws many copies of a mesh, choosing each copy's level of detail on the
+/// GPU.
+///
+/// Levels are ordered from most to least detailed. `cmd_select_lods` runs
+/// one compute invocation per instance which measures the instance's
+/// bounding sphere in pixels and picks the first level whose
+/// `min_screen_size` it meets. Instances smaller than every level are
+/// skipped. Each level has its own VkDrawIndirectCommand and list of visible
+/// instance indices, so `draw` records one indirect draw per level.
+///
+/// The bounding sphere is taken from the first level's quantizer, so every
+/// level should cover the same space.
+///
+/// Renderers created with `new_with_velocity` draw into a Taa render pass
+/// and also write each fragment's screen-space motion. Motion comes from
+/// the change in camera, given to `draw_with_velocity`, and from the change
+/// in each instance's transform since the previous `write_instances`.
+pub struct InstancedMeshRenderer {
+    lods: Vec<InstanceLod>,
+    bounds: [f32; 4],
+    max_instances: u32,
+    instance_count: u32,
+    extent: vk::Extent2D,
+    instance_buffer: raii::Buffer,
+    previous_instance_buffer: raii::Buffer,
+    draw_buffer: raii::Buffer,
+    _visible_buffer: raii::Buffer,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    select_pipeline_layout: raii::PipelineLayout,
+    select_pipeline: raii::Pipeline,
+    draw_pipeline_layout: raii::PipelineLayout,
+    draw_pipeline: raii::Pipeline,
+    lod_descriptors: Option<LodDescriptors>,
+    render_device: Arc<RenderDevice>,
+}
+
+/// One uniform buffer descriptor set per level of detail, used by the
+/// velocity pipeline.
+struct LodDescriptors {
+    _buffer: raii::Buffer,
+    descriptor_pool: raii::DescriptorPool,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+}
+
+// Public API
+// ----------
+
+impl InstancedMeshRenderer {
+    /// The largest number of levels of detail.
+    pub const MAX_LODS: usize = 4;
+
+    /// Create the buffers and pipelines. There are no instances until
+    /// `write_instances` is called.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `color_pass` - the render pass the instances are drawn into. It
+    ///   must be created with a DepthPrePass.
+    /// * `lods` - between one and MAX_LODS levels of detail, ordered from
+    ///   most to least detailed
+    /// * `max_instances` - the capacity of the instance buffer
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        color_pass: &ColorPass,
+        lods: Vec<InstanceLod>,
+        max_instances: u32,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_for_render_pass(
+            render_device,
+            color_pass.render_pass(),
+            color_pass.extent(),
+            None,
+            lods,
+            max_instances,
+        )
+    }
+
+    /// Create a renderer which draws into a Taa render pass and writes
+    /// velocity. There are no instances until `write_instances` is called.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `taa` - provides the render pass and sample count
+    /// * `lods` - between one and MAX_LODS levels of detail, ordered from
+    ///   most to least detailed
+    /// * `max_instances` - the capacity of the instance buffer
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the Taa is rebuilt
+    pub unsafe fn new_with_velocity(
+        render_device: Arc<RenderDevice>,
+        taa: &Taa,
+        lods: Vec<InstanceLod>,
+        max_instances: u32,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_for_render_pass(
+            render_device,
+            taa.render_pass(),
+            taa.extent(),
+            Some(taa.samples()),
+            lods,
+            max_instances,
+        )
+    }
+
+    /// The levels of detail, from most to least detailed.
+    pub fn lods(&self) -> &[InstanceLod] {
+        &self.lods
+    }
+
+    /// Change the on-screen size threshold for a level of detail.
+    pub fn set_min_screen_size(&mut self, lod: usize, min_screen_size: f32) {
+        self.lods[lod].min_screen_size = min_screen_size;
+    }
+
+    /// The capacity of the instance buffer.
+    pub fn max_instances(&self) -> u32 {
+        self.max_instances
+    }
+
+    /// The number of instances considered by `cmd_select_lods`.
+    pub fn instance_count(&self) -> u32 {
+        self.instance_count
+    }
+
+    /// The host-visible storage buffer of MeshInstance values.
+    ///
+    /// Compute shaders can write instances here directly, then call
+    /// `set_instance_count`.
+    pub fn instance_buffer(&self) -> &raii::Buffer {
+        &self.instance_buffer
+    }
+
+    /// The host-visible storage buffer of MeshInstance values from the
+    /// previous frame, used for velocity.
+    ///
+    /// `write_instances` keeps this up to date. Compute shaders which write
+    /// the instance buffer directly should copy the old instances here
+    /// first.
+    pub fn previous_instance_buffer(&self) -> &raii::Buffer {
+        &self.previous_instance_buffer
+    }
+
+    /// Set the number of instances considered by `cmd_select_lods`, for
+    /// instances written directly to the instance buffer. The count is
+    /// clamped to `max_instances`.
+    pub fn set_instance_count(&mut self, instance_count: u32) {
+        self.instance_count = instance_count.min(self.max_instances);
+    }
+
+    /// Replace the instances. Instances past `max_instances` are ignored.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the instance buffer must not be in use by the GPU
+    pub unsafe fn write_instances(
+        &mut self,
+        instances: &[MeshInstance],
+    ) -> Result<(), GraphicsError> {
+        let count = instances.len().min(self.max_instances as usize);
+        let device = self.render_device.device();
+
+        // Instances keep their index between writes, so the current values
+        // become the previous frame's. New instances haven't moved.
+        let kept = count.min(self.instance_count as usize);
+        let mut previous = Vec::with_capacity(count);
+        let ptr =
+            self.instance_buffer.allocation().map(device)? as *mut MeshInstance;
+        previous.extend_from_slice(std::slice::from_raw_parts(ptr, kept));
+        self.instance_buffer.allocation().unmap(device)?;
+        previous.extend_from_slice(&instances[kept..count]);
+
+        let ptr = self.previous_instance_buffer.allocation().map(device)?
+            as *mut MeshInstance;
+        std::ptr::copy_nonoverlapping(previous.as_ptr(), ptr, count);
+        self.previous_instance_buffer.allocation().unmap(device)?;
+
+        let ptr =
+            self.instance_buffer.allocation().map(device)? as *mut MeshInstance;
+        std::ptr::copy_nonoverlapping(instances.as_ptr(), ptr, count);
+        self.instance_buffer.allocation().unmap(device)?;
+        self.instance_count = count as u32;
+        Ok(())
+    }
+
+    /// Record commands which choose a level of detail for every instance.
+    ///
+    /// Earlier draws finish reading the draw commands before they are
+    /// reset. The commands end with a barrier which makes the results
+    /// visible to indirect draws and vertex shaders.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `view` - transforms world space to view space
+    /// * `projection` - the projection used by `draw`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn cmd_select_lods(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        view: &Mat4,
+        projection: &Mat4,
+    ) {
+        let device = self.render_device.device();
+
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::DRAW_INDIRECT
+                    | vk::PipelineStageFlags2::VERTEX_SHADER,
+                src_access_mask: vk::AccessFlags2::NONE,
+                dst_stage_mask: vk::PipelineStageFlags2::COPY
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+
+        let mut reset = [vk::DrawIndirectCommand::default(); Self::MAX_LODS];
+        for (draw, lod) in reset.iter_mut().zip(&self.lods) {
+            draw.vertex_count = lod.mesh.vertex_count();
+        }
+        device.cmd_update_buffer(
+            command_buffer,
+            self.draw_buffer.raw(),
+            0,
+            std::slice::from_raw_parts(
+                reset.as_ptr() as *const u8,
+                std::mem::size_of_val(&reset),
+            ),
+        );
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.select_pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.select_pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        let mut min_screen_sizes = [0.0; 4];
+        for (size, lod) in min_screen_sizes.iter_mut().zip(&self.lods) {
+            *size = lod.min_screen_size;
+        }
+        let constants = SelectLodConstants {
+            view: *view,
+            bounds: self.bounds,
+            min_screen_sizes,
+            pixels_per_unit: projection[(1, 1)].abs()
+                * self.extent.height as f32
+                * 0.5,
+            lod_count: self.lods.len() as u32,
+            instance_count: self.instance_count,
+            max_instances: self.max_instances,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.select_pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const SelectLodConstants as *const u8,
+                std::mem::size_of::<SelectLodConstants>(),
+            ),
+        );
+        let groups = (self.instance_count + Self::WORKGROUP_SIZE - 1)
+            / Self::WORKGROUP_SIZE;
+        device.cmd_dispatch(command_buffer, groups, 1, 1);
+
+        self.memory_barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::DRAW_INDIRECT
+                    | vk::PipelineStageFlags2::VERTEX_SHADER,
+                dst_access_mask: vk::AccessFlags2::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_READ,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Add commands to draw every level of detail with the instances chosen
+    /// by the most recent `cmd_select_lods`.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `view_projection` - transforms world space to clip space. Should be
+    ///   `projection * view` using the matrices given to `cmd_select_lods`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - `cmd_select_lods` must be recorded earlier, outside the render
+    ///     pass
+    pub unsafe fn draw(&self, frame: &Frame, view_projection: &Mat4) {
+        self.draw_with_velocity(frame, view_projection, view_projection);
+    }
+
+    /// Add commands to draw every level of detail, like `draw`, with the
+    /// camera from the previous frame for velocity.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `view_projection` - transforms world space to clip space, including
+    ///   the Taa jitter
+    /// * `previous_view_projection` - the previous frame's
+    ///   `view_projection`, including its jitter. Ignored by renderers
+    ///   created without velocity.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the render pass must already be started
+    ///   - `cmd_select_lods` must be recorded earlier, outside the render
+    ///     pass
+    pub unsafe fn draw_with_velocity(
+        &self,
+        frame: &Frame,
+        view_projection: &Mat4,
+        previous_view_projection: &Mat4,
+    ) {
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.draw_pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.draw_pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+
+        if self.lod_descriptors.is_some() {
+            let constants = VelocityDrawConstants {
+                view_projection: *view_projection,
+                previous_view_projection: *previous_view_projection,
+            };
+            device.cmd_push_constants(
+                command_buffer,
+                self.draw_pipeline_layout.raw(),
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                std::slice::from_raw_parts(
+                    &constants as *const VelocityDrawConstants as *const u8,
+                    std::mem::size_of::<VelocityDrawConstants>(),
+                ),
+            );
+        }
+
+        let stride = std::mem::size_of::<vk::DrawIndirectCommand>();
+        for (index, lod) in self.lods.iter().enumerate() {
+            lod.mesh.cmd_bind_vertex_buffer(command_buffer, 0);
+            if let Some(lod_descriptors) = &self.lod_descriptors {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.draw_pipeline_layout.raw(),
+                    1,
+                    &[lod_descriptors.descriptor_pool.descriptor_set(index)],
+                    &[],
+                );
+                device.cmd_draw_indirect(
+                    command_buffer,
+                    self.draw_buffer.raw(),
+                    (index * stride) as u64,
+                    1,
+                    stride as u32,
+                );
+                continue;
+            }
+
+            let quantizer = lod.mesh.quantizer();
+            let scale = quantizer.extent();
+            let constants = DrawConstants {
+                view_projection: *view_projection,
+                dequantize_scale: [scale.x, scale.y, scale.z, 1.0],
+                dequantize_offset: [
+                    quantizer.min.x,
+                    quantizer.min.y,
+                    quantizer.min.z,
+                ],
+                visible_offset: index as u32 * self.max_instances,
+            };
+            device.cmd_push_constants(
+                command_buffer,
+                self.draw_pipeline_layout.raw(),
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                std::slice::from_raw_parts(
+                    &constants as *const DrawConstants as *const u8,
+                    std::mem::size_of::<DrawConstants>(),
+                ),
+            );
+            device.cmd_draw_indirect(
+                command_buffer,
+                self.draw_buffer.raw(),
+                (index * stride) as u64,
+                1,
+                stride as u32,
+            );
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl InstancedMeshRenderer {
+    /// The LOD selection shader's workgroup size.
+    const WORKGROUP_SIZE: u32 = 64;
+
+    /// Create the buffers and pipelines for a render pass.
+    ///
+    /// `samples` is provided for Taa render passes, which get the velocity
+    /// pipeline.
+    unsafe fn new_for_render_pass(
+        render_device: Arc<RenderDevice>,
+        render_pass: &raii::RenderPass,
+        extent: vk::Extent2D,
+        samples: Option<vk::SampleCountFlags>,
+        lods: Vec<InstanceLod>,
+        max_instances: u32,
+    ) -> Result<Self, GraphicsError> {
+        if lods.is_empty() || lods.len() > Self::MAX_LODS {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Expected between 1 and {} levels of detail but got {}",
+                Self::MAX_LODS,
+                lods.len()
+            )));
+        }
+        let max_instances = max_instances.max(1);
+
+        let bounds = {
+            let quantizer = lods[0].mesh.quantizer();
+            let center = (quantizer.min + quantizer.max) * 0.5;
+            let radius = (quantizer.max - quantizer.min).norm() * 0.5;
+            [center.x, center.y, center.z, radius]
+        };
+
+        let instance_buffer = Self::create_buffer(
+            &render_device,
+            max_instances as u64 * std::mem::size_of::<MeshInstance>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        instance_buffer.set_debug_name("Instanced Mesh Instances");
+
+        let previous_instance_buffer = Self::create_buffer(
+            &render_device,
+            max_instances as u64 * std::mem::size_of::<MeshInstance>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        previous_instance_buffer
+            .set_debug_name("Instanced Mesh Previous Instances");
+
+        let draw_buffer = Self::create_buffer(
+            &render_device,
+            (Self::MAX_LODS * std::mem::size_of::<vk::DrawIndirectCommand>())
+                as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        draw_buffer.set_debug_name("Instanced Mesh Draw Commands");
+
+        let visible_buffer = Self::create_buffer(
+            &render_device,
+            (lods.len() as u64)
+                * max_instances as u64
+                * std::mem::size_of::<u32>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        visible_buffer.set_debug_name("Instanced Mesh Visible Instances");
+
+        let bindings = (0..4)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE
+                    | vk::ShaderStageFlags::VERTEX,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 4,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        {
+            let descriptor_set = descriptor_pool.descriptor_set(0);
+            let buffer_infos = [
+                instance_buffer.raw(),
+                draw_buffer.raw(),
+                visible_buffer.raw(),
+                previous_instance_buffer.raw(),
+            ]
+            .map(|buffer| vk::DescriptorBufferInfo {
+                buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            });
+            let writes = buffer_infos
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer_info)| vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: binding as u32,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                })
+                .collect::<Vec<_>>();
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        let select_pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<SelectLodConstants>() as u32,
+                }],
+            )?;
+        let select_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &select_pipeline_layout,
+            include_bytes!("./shaders/select_lod.comp.spv"),
+        )?;
+        select_pipeline.set_debug_name("Instanced Mesh LOD Selection");
+
+        let (draw_pipeline_layout, draw_pipeline, lod_descriptors) =
+            match samples {
+                None => {
+                    let layout =
+                        raii::PipelineLayout::new_with_layouts_and_ranges(
+                            render_device.clone(),
+                            &[descriptor_set_layout.raw()],
+                            &[vk::PushConstantRange {
+                                stage_flags: vk::ShaderStageFlags::VERTEX,
+                                offset: 0,
+                                size: std::mem::size_of::<DrawConstants>()
+                                    as u32,
+                            }],
+                        )?;
+                    let pipeline = GraphicsPipelineBuilder::new()
+                        .vertex_shader(include_bytes!(
+                            "./shaders/instanced_mesh.vert.spv"
+                        ))
+                        .fragment_shader(include_bytes!(
+                            "./shaders/instanced_mesh.frag.spv"
+                        ))
+                        .vertex_input(
+                            &[QuantizedVertex::vertex_input_binding(0)],
+                            &QuantizedVertex::vertex_input_attributes(0, 0),
+                        )
+                        .color_blend_attachment(
+                            GraphicsPipelineBuilder::opaque_attachment(),
+                        )
+                        .depth_test(true)
+                        .depth_write(true)
+                        .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+                        .build(render_device.clone(), &layout, render_pass)?;
+                    (layout, pipeline, None)
+                }
+                Some(samples) => {
+                    let lod_descriptors = LodDescriptors::new(
+                        &render_device,
+                        &lods,
+                        max_instances,
+                    )?;
+                    let layout =
+                        raii::PipelineLayout::new_with_layouts_and_ranges(
+                            render_device.clone(),
+                            &[
+                                descriptor_set_layout.raw(),
+                                lod_descriptors.descriptor_set_layout.raw(),
+                            ],
+                            &[vk::PushConstantRange {
+                                stage_flags: vk::ShaderStageFlags::VERTEX,
+                                offset: 0,
+                                size: std::mem::size_of::<VelocityDrawConstants>(
+                                ) as u32,
+                            }],
+                        )?;
+                    // Color and velocity are both written without blending.
+                    let pipeline = GraphicsPipelineBuilder::new()
+                        .vertex_shader(include_bytes!(
+                            "./shaders/instanced_mesh_velocity.vert.spv"
+                        ))
+                        .fragment_shader(include_bytes!(
+                            "./shaders/instanced_mesh_velocity.frag.spv"
+                        ))
+                        .vertex_input(
+                            &[QuantizedVertex::vertex_input_binding(0)],
+                            &QuantizedVertex::vertex_input_attributes(0, 0),
+                        )
+                        .color_blend_attachment(
+                            GraphicsPipelineBuilder::opaque_attachment(),
+                        )
+                        .color_blend_attachment(
+                            GraphicsPipelineBuilder::opaque_attachment(),
+                        )
+                        .samples(samples)
+                        .depth_test(true)
+                        .depth_write(true)
+                        .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+                        .build(render_device.clone(), &layout, render_pass)?;
+                    (layout, pipeline, Some(lod_descriptors))
+                }
+            };
+        draw_pipeline.set_debug_name("Instanced Mesh Pipeline");
+
+        Ok(Self {
+            lods,
+            bounds,
+            max_instances,
+            instance_count: 0,
+            extent,
+            instance_buffer,
+            previous_instance_buffer,
+            draw_buffer,
+            _visible_buffer: visible_buffer,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            select_pipeline_layout,
+            select_pipeline,
+            draw_pipeline_layout,
+            draw_pipeline,
+            lod_descriptors,
+            render_device,
+        })
+    }
+
+    /// Record a single global memory barrier.
+    unsafe fn memory_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: vk::MemoryBarrier2,
+    ) {
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+}
+
+impl LodDescriptors {
+    /// The distance between levels in the uniform buffer. 256 bytes is the
+    /// largest minUniformBufferOffsetAlignment allowed by the spec, so every
+    /// level's offset is valid on every device.
+    const STRIDE: u64 = 256;
+
+    /// Write each level's dequantization and visible instance offset into a
+    /// uniform buffer, with one descriptor set per level.
+    unsafe fn new(
+        render_device: &Arc<RenderDevice>,
+        lods: &[InstanceLod],
+        max_instances: u32,
+    ) -> Result<Self, GraphicsError> {
+        let buffer = InstancedMeshRenderer::create_buffer(
+            render_device,
+            lods.len() as u64 * Self::STRIDE,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Instanced Mesh LODs");
+        {
+            let device = render_device.device();
+            let ptr = buffer.allocation().map(device)? as *mut u8;
+            for (index, lod) in lods.iter().enumerate() {
+                let quantizer = lod.mesh.quantizer();
+                let scale = quantizer.extent();
+                let uniform = LodUniform {
+                    dequantize_scale: [scale.x, scale.y, scale.z, 1.0],
+                    dequantize_offset: [
+                        quantizer.min.x,
+                        quantizer.min.y,
+                        quantizer.min.z,
+                    ],
+                    visible_offset: index as u32 * max_instances,
+                };
+                std::ptr::write(
+                    ptr.add(index * Self::STRIDE as usize) as *mut LodUniform,
+                    uniform,
+                );
+            }
+            buffer.allocation().unmap(device)?;
+        }
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[vk::DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: 1,
+                    stage_flags: vk::ShaderStageFlags::VERTEX,
+                    ..vk::DescriptorSetLayoutBinding::default()
+                }],
+            )?;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            lods.len() as u32,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: lods.len() as u32,
+            }],
+        )?;
+        let layouts = vec![&descriptor_set_layout; lods.len()];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        for index in 0..lods.len() {
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: index as u64 * Self::STRIDE,
+                range: std::mem::size_of::<LodUniform>() as u64,
+            };
+            let write = vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(index),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: &buffer_info,
+                ..vk::WriteDescriptorSet::default()
+            };
+            render_device.device().update_descriptor_sets(&[write], &[]);
+        }
+
+        Ok(Self {
+            _buffer: buffer,
+            descriptor_pool,
+            descriptor_set_layout,
+        })
+    }
+}
@@ -0,0 +1,13 @@
+#version 460
+
+layout(location = 0) in vec4 vertex_color;
+layout(location = 1) in vec3 world_normal;
+
+layout(location = 0) out vec4 out_color;
+
+const vec3 LIGHT_DIRECTION = normalize(vec3(0.4, 1.0, 0.6));
+
+void main() {
+    float diffuse = max(dot(normalize(world_normal), LIGHT_DIRECTION), 0.0);
+    out_color = vec4(vertex_color.rgb * (0.25 + 0.75 * diffuse), vertex_color.a);
+}
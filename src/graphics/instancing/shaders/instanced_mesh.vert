@@ -0,0 +1,41 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+
+#include "instancing.glsl"
+
+layout(set = 0, binding = 2) readonly buffer VisibleInstances {
+    uint visible_instances[];
+};
+
+// Must match DrawConstants in `instancing/renderer.rs`.
+layout(push_constant) uniform DrawConstants {
+    mat4 view_projection;
+    // Moves quantized positions in [0, 1] back into model space.
+    vec4 dequantize_scale;
+    vec3 dequantize_offset;
+    // Where this level's visible instance indices start.
+    uint visible_offset;
+} constants;
+
+// One QuantizedVertex per vertex.
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 normal;
+layout(location = 2) in vec2 uv;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) out vec4 vertex_color;
+layout(location = 1) out vec3 world_normal;
+
+void main() {
+    uint index = visible_instances[constants.visible_offset + gl_InstanceIndex];
+    MeshInstance instance = instances[index];
+
+    vec3 model_position =
+        position.xyz * constants.dequantize_scale.xyz + constants.dequantize_offset;
+    vec3 model_normal = normal.xyz * 2.0 - 1.0;
+
+    vertex_color = color * instance.color;
+    world_normal = mat3(instance.transform) * model_normal;
+    gl_Position =
+        constants.view_projection * instance.transform * vec4(model_position, 1.0);
+}
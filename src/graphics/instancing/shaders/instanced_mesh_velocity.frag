@@ -0,0 +1,21 @@
+#version 460
+
+layout(location = 0) in vec4 vertex_color;
+layout(location = 1) in vec3 world_normal;
+layout(location = 2) in vec4 current_clip;
+layout(location = 3) in vec4 previous_clip;
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) out vec2 out_velocity;
+
+const vec3 LIGHT_DIRECTION = normalize(vec3(0.4, 1.0, 0.6));
+
+void main() {
+    float diffuse = max(dot(normalize(world_normal), LIGHT_DIRECTION), 0.0);
+    out_color = vec4(vertex_color.rgb * (0.25 + 0.75 * diffuse), vertex_color.a);
+
+    // The screen-space motion since the previous frame, in uv units.
+    vec2 current_ndc = current_clip.xy / current_clip.w;
+    vec2 previous_ndc = previous_clip.xy / previous_clip.w;
+    out_velocity = (current_ndc - previous_ndc) * 0.5;
+}
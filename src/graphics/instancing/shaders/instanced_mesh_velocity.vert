@@ -0,0 +1,60 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+
+#include "instancing.glsl"
+
+layout(set = 0, binding = 2) readonly buffer VisibleInstances {
+    uint visible_instances[];
+};
+
+// The instances as they were in the previous frame.
+layout(set = 0, binding = 3) readonly buffer PreviousInstances {
+    MeshInstance previous_instances[];
+};
+
+// One per level of detail. Must match LodUniform in `instancing/renderer.rs`.
+layout(set = 1, binding = 0) uniform Lod {
+    // Moves quantized positions in [0, 1] back into model space.
+    vec4 dequantize_scale;
+    vec3 dequantize_offset;
+    // Where this level's visible instance indices start.
+    uint visible_offset;
+} lod;
+
+// Must match VelocityDrawConstants in `instancing/renderer.rs`.
+layout(push_constant) uniform VelocityDrawConstants {
+    mat4 view_projection;
+    mat4 previous_view_projection;
+} constants;
+
+// One QuantizedVertex per vertex.
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 normal;
+layout(location = 2) in vec2 uv;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) out vec4 vertex_color;
+layout(location = 1) out vec3 world_normal;
+layout(location = 2) out vec4 current_clip;
+layout(location = 3) out vec4 previous_clip;
+
+void main() {
+    uint index = visible_instances[lod.visible_offset + gl_InstanceIndex];
+    MeshInstance instance = instances[index];
+    MeshInstance previous_instance = previous_instances[index];
+
+    vec4 model_position = vec4(
+        position.xyz * lod.dequantize_scale.xyz + lod.dequantize_offset,
+        1.0
+    );
+    vec3 model_normal = normal.xyz * 2.0 - 1.0;
+
+    vertex_color = color * instance.color;
+    world_normal = mat3(instance.transform) * model_normal;
+    current_clip =
+        constants.view_projection * instance.transform * model_position;
+    previous_clip = constants.previous_view_projection
+        * previous_instance.transform
+        * model_position;
+    gl_Position = current_clip;
+}
@@ -0,0 +1,11 @@
+// A single instance. Must match MeshInstance in `instancing/renderer.rs`.
+struct MeshInstance {
+    // Transforms the mesh from model space to world space.
+    mat4 transform;
+    vec4 color;
+};
+
+// Read by both the LOD selection and draw pipelines.
+layout(set = 0, binding = 0) readonly buffer Instances {
+    MeshInstance instances[];
+};
@@ -0,0 +1,152 @@
+/// The index of a light in a LightList.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LightId(pub u32);
+
+/// The shape of a light's emission.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LightKind {
+    /// Emits in every direction from `position`, fading to zero at `range`.
+    Point,
+
+    /// Emits a cone from `position` along `direction`, fading to zero at
+    /// `range`.
+    Spot,
+
+    /// Emits parallel rays along `direction` with no falloff, like the sun.
+    Directional,
+}
+
+/// A single light.
+///
+/// Fields which don't apply to a light's kind are ignored, e.g. a
+/// directional light has no position or range.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+
+    /// The world-space position of point and spot lights.
+    pub position: [f32; 3],
+
+    /// The world-space direction spot and directional lights travel.
+    pub direction: [f32; 3],
+
+    /// The light's linear color.
+    pub color: [f32; 3],
+
+    /// Multiplies the color.
+    pub intensity: f32,
+
+    /// The distance where point and spot lights fade to zero.
+    pub range: f32,
+
+    /// The angle, in radians, from the spot direction where the cone starts
+    /// to fade.
+    pub inner_angle: f32,
+
+    /// The angle, in radians, from the spot direction where the cone reaches
+    /// zero.
+    pub outer_angle: f32,
+
+    /// Disabled lights are skipped when the list is written for a frame.
+    pub enabled: bool,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            position: [0.0, 0.0, 0.0],
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            range: 10.0,
+            inner_angle: 0.3,
+            outer_angle: 0.5,
+            enabled: true,
+        }
+    }
+}
+
+impl Light {
+    /// A point light.
+    pub fn point(position: [f32; 3], color: [f32; 3], range: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            position,
+            color,
+            range,
+            ..Default::default()
+        }
+    }
+
+    /// A spot light with a cone which fades between the inner and outer
+    /// angles, in radians.
+    pub fn spot(
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [f32; 3],
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot,
+            position,
+            direction,
+            color,
+            range,
+            inner_angle,
+            outer_angle,
+            ..Default::default()
+        }
+    }
+
+    /// A directional light.
+    pub fn directional(direction: [f32; 3], color: [f32; 3]) -> Self {
+        Self {
+            kind: LightKind::Directional,
+            direction,
+            color,
+            ..Default::default()
+        }
+    }
+}
+
+/// The GPU representation of a Light.
+///
+/// Must match the Light struct in `shaders/lights.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub(super) struct LightData {
+    pub position: [f32; 3],
+    pub range: f32,
+    pub direction: [f32; 3],
+    pub kind: u32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub cos_inner_angle: f32,
+    pub cos_outer_angle: f32,
+    pub pad: [u32; 2],
+}
+
+impl From<&Light> for LightData {
+    fn from(light: &Light) -> Self {
+        let [x, y, z] = light.direction;
+        let length = (x * x + y * y + z * z).sqrt().max(std::f32::EPSILON);
+        Self {
+            position: light.position,
+            range: light.range,
+            direction: [x / length, y / length, z / length],
+            kind: match light.kind {
+                LightKind::Point => 0,
+                LightKind::Spot => 1,
+                LightKind::Directional => 2,
+            },
+            color: light.color,
+            intensity: light.intensity,
+            cos_inner_angle: light.inner_angle.cos(),
+            cos_outer_angle: light.outer_angle.cos(),
+            pad: [0, 0],
+        }
+    }
+}
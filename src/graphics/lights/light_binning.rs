@@ -0,0 +1,332 @@
+use {
+    super::LightList,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for the binning compute shader. Must match
+/// BinningConstants in `shaders/light_binning.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct BinningConstants {
+    view: Mat4,
+    projection_scale: [f32; 2],
+    extent: [u32; 2],
+    tile_count: [u32; 2],
+}
+
+/// A compute pass which sorts the lights in a LightList into screen-space
+/// tiles.
+///
+/// Each tile gets a count followed by the indices of the lights whose
+/// bounding spheres can reach it, so fragment shaders only visit nearby
+/// lights. `shaders/light_tiles.glsl` has the matching layout and lookup
+/// helpers. Directional lights reach every tile.
+///
+/// Binning is optional: scenes with a handful of lights can loop over the
+/// whole light buffer instead.
+pub struct LightBinning {
+    extent: vk::Extent2D,
+    tile_count: vk::Extent2D,
+    tiles: raii::Buffer,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl LightBinning {
+    /// The size of each square tile, in pixels. Must match LIGHT_TILE_SIZE in
+    /// `shaders/light_tiles.glsl`.
+    pub const TILE_SIZE: u32 = 16;
+
+    /// The largest number of lights in a single tile. Must match
+    /// MAX_LIGHTS_PER_TILE in `shaders/light_tiles.glsl`.
+    pub const MAX_LIGHTS_PER_TILE: u32 = 63;
+
+    /// Create the tile buffer and binning pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - one descriptor set is kept per frame
+    /// * `extent` - the size of the render target which reads the tiles
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt when the render target is resized
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        let tile_count = vk::Extent2D {
+            width: (extent.width + Self::TILE_SIZE - 1) / Self::TILE_SIZE,
+            height: (extent.height + Self::TILE_SIZE - 1) / Self::TILE_SIZE,
+        };
+        let tiles = {
+            let queue_family_index =
+                render_device.graphics_queue().family_index();
+            let tile_stride = (Self::MAX_LIGHTS_PER_TILE + 1) as u64
+                * std::mem::size_of::<u32>() as u64;
+            let create_info = vk::BufferCreateInfo {
+                size: (tile_count.width * tile_count.height).max(1) as u64
+                    * tile_stride,
+                usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+                queue_family_index_count: 1,
+                p_queue_family_indices: &queue_family_index,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                ..Default::default()
+            };
+            raii::Buffer::new(
+                render_device.clone(),
+                &create_info,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?
+        };
+        tiles.set_debug_name("Light Tiles");
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    LightList::descriptor_set_layout_binding(
+                        0,
+                        vk::ShaderStageFlags::COMPUTE,
+                    ),
+                    Self::descriptor_set_layout_binding(
+                        1,
+                        vk::ShaderStageFlags::COMPUTE,
+                    ),
+                ],
+            )?;
+        let frame_count = frames_in_flight.frame_count() as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            frame_count,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 2 * frame_count,
+            }],
+        )?;
+        let layouts = (0..frame_count)
+            .map(|_| &descriptor_set_layout)
+            .collect::<Vec<_>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<BinningConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/light_binning.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Light Binning Pipeline");
+
+        Ok(Self {
+            extent,
+            tile_count,
+            tiles,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// The number of tiles in x and y. Shaders need the x count to find a
+    /// pixel's tile.
+    pub fn tile_count(&self) -> vk::Extent2D {
+        self.tile_count
+    }
+
+    /// The descriptor info for the tile buffer, for writing a STORAGE_BUFFER
+    /// descriptor.
+    pub fn descriptor_buffer_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.tiles.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// A descriptor set layout binding for the tile buffer.
+    pub fn descriptor_set_layout_binding(
+        binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }
+    }
+
+    /// Add commands to bin the frame's lights into tiles.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `lights` - must already be written for this frame with
+    ///   `write_lights_for_frame`
+    /// * `view` - the camera's view matrix
+    /// * `projection` - the camera's perspective projection
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must be recorded outside of a render pass
+    ///   - the tiles are ready for fragment and compute shaders after this
+    ///     call
+    pub unsafe fn record(
+        &self,
+        frame: &Frame,
+        lights: &LightList,
+        view: &Mat4,
+        projection: &Mat4,
+    ) {
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        let descriptor_set =
+            self.descriptor_pool.descriptor_set(frame.frame_index());
+
+        // SAFE because the frame's previous commands are complete, so its
+        // descriptor set is no longer in use.
+        let light_info = lights.descriptor_buffer_info(frame);
+        let tile_info = self.descriptor_buffer_info();
+        device.update_descriptor_sets(
+            &[
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: &light_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: &tile_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ],
+            &[],
+        );
+
+        // The tile buffer is shared by every frame in flight, so wait for
+        // earlier frames to finish reading it.
+        self.barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::NONE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        let constants = BinningConstants {
+            view: *view,
+            projection_scale: [projection[(0, 0)], projection[(1, 1)]],
+            extent: [self.extent.width, self.extent.height],
+            tile_count: [self.tile_count.width, self.tile_count.height],
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const BinningConstants as *const u8,
+                std::mem::size_of::<BinningConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.tile_count.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (self.tile_count.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+
+        self.barrier(
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl LightBinning {
+    /// The compute shader's workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    unsafe fn barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: vk::MemoryBarrier2,
+    ) {
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use {
+    super::{light::LightData, Light, LightId},
+    crate::graphics::{
+        vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The start of the light buffer. Must match the Lights buffer in
+/// `shaders/lights.glsl`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct LightHeader {
+    count: u32,
+    pad: [u32; 3],
+}
+
+/// Owns every light in a scene and keeps a copy of the enabled lights in a
+/// storage buffer for each frame in flight.
+///
+/// Lights can be added, animated, or removed at any time. Changes are
+/// uploaded by `write_lights_for_frame`, which should be called once per
+/// frame before any work which reads lights. Disabled and removed lights are
+/// skipped, so a light's position in the buffer is not its LightId.
+pub struct LightList {
+    lights: Vec<Option<Light>>,
+    free_ids: Vec<LightId>,
+    buffers: Vec<raii::Buffer>,
+    buffer_ptrs: Vec<*mut u8>,
+    capacities: Vec<usize>,
+    counts: Vec<u32>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl LightList {
+    /// Create a new light list with room for `initial_capacity` lights
+    /// before any buffers need to grow.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        initial_capacity: usize,
+    ) -> Result<Self, GraphicsError> {
+        let buffer_count = frames_in_flight.frame_count();
+        let mut buffers = Vec::with_capacity(buffer_count);
+        let mut buffer_ptrs = Vec::with_capacity(buffer_count);
+        for _ in 0..buffer_count {
+            let (buffer, ptr) =
+                Self::allocate_buffer(&render_device, initial_capacity)?;
+            buffers.push(buffer);
+            buffer_ptrs.push(ptr);
+        }
+        Ok(Self {
+            lights: vec![],
+            free_ids: vec![],
+            buffers,
+            buffer_ptrs,
+            capacities: vec![initial_capacity.max(1); buffer_count],
+            counts: vec![0; buffer_count],
+            render_device,
+        })
+    }
+
+    /// Add a light to the list. Ids of removed lights are reused.
+    pub fn add(&mut self, light: Light) -> LightId {
+        if let Some(id) = self.free_ids.pop() {
+            self.lights[id.0 as usize] = Some(light);
+            return id;
+        }
+        self.lights.push(Some(light));
+        LightId(self.lights.len() as u32 - 1)
+    }
+
+    /// Remove a light from the list.
+    pub fn remove(&mut self, id: LightId) -> Option<Light> {
+        let light = self.lights.get_mut(id.0 as usize)?.take();
+        if light.is_some() {
+            self.free_ids.push(id);
+        }
+        light
+    }
+
+    /// Get a light, or None if it was removed.
+    pub fn get(&self, id: LightId) -> Option<&Light> {
+        self.lights.get(id.0 as usize)?.as_ref()
+    }
+
+    /// Get a mutable reference to a light, or None if it was removed.
+    /// Changes are visible to shaders after the next call to
+    /// `write_lights_for_frame`.
+    pub fn get_mut(&mut self, id: LightId) -> Option<&mut Light> {
+        self.lights.get_mut(id.0 as usize)?.as_mut()
+    }
+
+    /// Iterate over every light, e.g. to animate them all each frame.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (LightId, &mut Light)> {
+        self.lights
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, light)| {
+                light.as_mut().map(|light| (LightId(index as u32), light))
+            })
+    }
+
+    /// The number of lights in the list, including disabled lights.
+    pub fn len(&self) -> usize {
+        self.lights.len() - self.free_ids.len()
+    }
+
+    /// Returns true when the list has no lights.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of lights written by the last call to
+    /// `write_lights_for_frame` for this frame.
+    pub fn count_for_frame(&self, frame: &Frame) -> u32 {
+        self.counts[frame.frame_index()]
+    }
+
+    /// Copy every enabled light into the frame's storage buffer.
+    ///
+    /// Returns true when the buffer was reallocated, in which case any
+    /// descriptor sets which reference it must be rewritten with
+    /// `descriptor_buffer_info` before drawing.
+    pub fn write_lights_for_frame(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<bool, GraphicsError> {
+        let index = frame.frame_index();
+        let enabled = self
+            .lights
+            .iter()
+            .flatten()
+            .filter(|light| light.enabled)
+            .collect::<Vec<&Light>>();
+        let mut reallocated = false;
+        if self.capacities[index] < enabled.len() {
+            unsafe {
+                // SAFE because the frame's previous commands are complete, so
+                // the old buffer is no longer in use.
+                self.buffers[index]
+                    .allocation()
+                    .unmap(self.render_device.device())?;
+                let capacity = enabled.len() * 2;
+                let (buffer, ptr) =
+                    Self::allocate_buffer(&self.render_device, capacity)?;
+                self.buffers[index] = buffer;
+                self.buffer_ptrs[index] = ptr;
+                self.capacities[index] = capacity;
+            }
+            reallocated = true;
+        }
+
+        let (header, data) = unsafe {
+            let ptr = self.buffer_ptrs[index];
+            (
+                &mut *(ptr as *mut LightHeader),
+                std::slice::from_raw_parts_mut(
+                    ptr.add(std::mem::size_of::<LightHeader>())
+                        as *mut LightData,
+                    enabled.len(),
+                ),
+            )
+        };
+        header.count = enabled.len() as u32;
+        for (gpu_light, light) in data.iter_mut().zip(enabled) {
+            *gpu_light = LightData::from(light);
+        }
+        self.counts[index] = header.count;
+        Ok(reallocated)
+    }
+
+    /// The frame's light buffer.
+    pub fn buffer(&self, frame: &Frame) -> &raii::Buffer {
+        &self.buffers[frame.frame_index()]
+    }
+
+    /// The descriptor info for the frame's light buffer, for writing a
+    /// STORAGE_BUFFER descriptor.
+    pub fn descriptor_buffer_info(
+        &self,
+        frame: &Frame,
+    ) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffer(frame).raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// A descriptor set layout binding for the light buffer.
+    pub fn descriptor_set_layout_binding(
+        binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl LightList {
+    /// Allocate a host-visible storage buffer with a header and room for
+    /// `capacity` lights.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not use the associated memory-mapped pointer
+    ///     once the buffer has been dropped.
+    unsafe fn allocate_buffer(
+        render_device: &Arc<RenderDevice>,
+        capacity: usize,
+    ) -> Result<(raii::Buffer, *mut u8), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (std::mem::size_of::<LightHeader>()
+                + capacity.max(1) * std::mem::size_of::<LightData>())
+                as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Lights");
+        let ptr = buffer.allocation().map(render_device.device())?;
+        Ok((buffer, ptr as *mut u8))
+    }
+}
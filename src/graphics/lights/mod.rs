@@ -0,0 +1,21 @@
+//! Point, spot, and directional lights stored in a storage buffer.
+//!
+//! A LightList owns the lights in a scene and uploads the enabled ones into
+//! a per-frame storage buffer, like the MaterialLibrary does for materials.
+//! Shaders include `shaders/lights.glsl` for the matching GLSL struct and an
+//! attenuation function.
+//!
+//! Scenes with many lights can add a LightBinning, which sorts the lights
+//! into screen-space tiles with a compute pass so each fragment only visits
+//! the lights which can reach it. `shaders/light_tiles.glsl` reads the
+//! tiles.
+
+mod light;
+mod light_binning;
+mod light_list;
+
+pub use self::{
+    light::{Light, LightId, LightKind},
+    light_binning::LightBinning,
+    light_list::LightList,
+};
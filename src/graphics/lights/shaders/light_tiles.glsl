@@ -0,0 +1,34 @@
+// Helpers for reading the per-tile light lists written by LightBinning.
+// Must match light_binning.rs.
+//
+// Declare the tile buffer, then visit only the lights which can reach the
+// fragment's tile:
+//
+//   layout(std430, set = 0, binding = 4) readonly buffer LightTiles {
+//       uint light_tiles[];
+//   };
+//
+//   uint tile = light_tile_index(gl_FragCoord.xy, tile_count_x);
+//   uint count = light_tiles[light_tile_offset(tile)];
+//   for (uint i = 0; i < count; i++) {
+//       Light light = lights[light_tiles[light_tile_offset(tile) + 1 + i]];
+//       ...
+//   }
+
+// The size of each square tile, in pixels.
+#define LIGHT_TILE_SIZE 16
+
+// The largest number of lights in a single tile. Extra lights are dropped.
+#define MAX_LIGHTS_PER_TILE 63
+
+// The index of the tile which contains a pixel.
+uint light_tile_index(vec2 pixel, uint tile_count_x) {
+    uvec2 tile = uvec2(pixel) / LIGHT_TILE_SIZE;
+    return tile.y * tile_count_x + tile.x;
+}
+
+// The offset of a tile's light count in the tile buffer. The tile's light
+// indices follow the count.
+uint light_tile_offset(uint tile) {
+    return tile * (MAX_LIGHTS_PER_TILE + 1);
+}
@@ -0,0 +1,59 @@
+// The GPU representation of the lights in a LightList. Must match LightData
+// and LightHeader in the lights module.
+//
+// Declare the light buffer after including this file, e.g.:
+//
+//   layout(std430, set = 0, binding = 3) readonly buffer Lights {
+//       uint light_count;
+//       Light lights[];
+//   };
+//
+// and accumulate every light with:
+//
+//   for (uint i = 0; i < light_count; i++) {
+//       vec3 to_light;
+//       vec3 radiance = light_radiance(lights[i], world_position, to_light);
+//       ...
+//   }
+
+#define LIGHT_KIND_POINT 0
+#define LIGHT_KIND_SPOT 1
+#define LIGHT_KIND_DIRECTIONAL 2
+
+struct Light {
+    vec3 position;
+    float range;
+    vec3 direction;
+    uint kind;
+    vec3 color;
+    float intensity;
+    float cos_inner_angle;
+    float cos_outer_angle;
+    uint pad0;
+    uint pad1;
+};
+
+// The light arriving at a world-space position, and the normalized
+// direction from the position toward the light.
+vec3 light_radiance(Light light, vec3 position, out vec3 to_light) {
+    vec3 radiance = light.color * light.intensity;
+    if (light.kind == LIGHT_KIND_DIRECTIONAL) {
+        to_light = -light.direction;
+        return radiance;
+    }
+
+    vec3 offset = light.position - position;
+    float distance = length(offset);
+    to_light = offset / max(distance, 1e-4);
+
+    // Inverse square falloff, windowed so it reaches zero at the range.
+    float window = clamp(1.0 - pow(distance / light.range, 4.0), 0.0, 1.0);
+    float attenuation = window * window / (distance * distance + 1.0);
+
+    if (light.kind == LIGHT_KIND_SPOT) {
+        float cos_angle = dot(-to_light, light.direction);
+        attenuation *= smoothstep(
+            light.cos_outer_angle, light.cos_inner_angle, cos_angle);
+    }
+    return radiance * attenuation;
+}
@@ -0,0 +1,163 @@
+use {
+    crate::graphics::{
+        vulkan_api::{
+            raii, BindlessVertex, OneTimeSubmitCommandBuffer, RenderDevice,
+        },
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Turtle geometry cached in device-local memory.
+///
+/// Expanding a deep L-system and building its triangles can take a while,
+/// so sketches which draw the same plant every frame can upload it once.
+/// The vertices keep the BindlessVertex layout and the buffer is usable as a
+/// STORAGE_BUFFER or a VERTEX_BUFFER.
+pub struct LSystemMesh {
+    vertex_count: u32,
+    vertex_buffer: raii::Buffer,
+}
+
+impl LSystemMesh {
+    /// Upload vertices to a new device-local buffer. Blocks until the upload
+    /// completes.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `vertices` - the vertices to upload, typically from
+    ///   `TurtleDrawing::triangles`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        vertices: &[BindlessVertex],
+    ) -> Result<Self, GraphicsError> {
+        if vertices.is_empty() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Unable to create an LSystemMesh without any vertices"
+            )));
+        }
+        let size = std::mem::size_of_val(vertices) as u64;
+
+        let staging_buffer = Self::create_buffer(
+            &render_device,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging_buffer.set_debug_name("LSystem Mesh Staging");
+        {
+            let device = render_device.device();
+            let ptr =
+                staging_buffer.allocation().map(device)? as *mut BindlessVertex;
+            std::ptr::copy_nonoverlapping(
+                vertices.as_ptr(),
+                ptr,
+                vertices.len(),
+            );
+            staging_buffer.allocation().unmap(device)?;
+        }
+
+        let vertex_buffer = Self::create_buffer(
+            &render_device,
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        vertex_buffer.set_debug_name("LSystem Mesh Vertices");
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        let device = render_device.device();
+        device.cmd_copy_buffer(
+            command_buffer,
+            staging_buffer.raw(),
+            vertex_buffer.raw(),
+            &[vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size,
+            }],
+        );
+        let barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::VERTEX_INPUT
+                | vk::PipelineStageFlags2::VERTEX_SHADER,
+            dst_access_mask: vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &barrier,
+                ..Default::default()
+            },
+        );
+        one_time_submit.sync_submit_and_reset()?;
+
+        Ok(Self {
+            vertex_count: vertices.len() as u32,
+            vertex_buffer,
+        })
+    }
+
+    /// The number of vertices in the buffer.
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    /// The device-local buffer which holds the vertices.
+    pub fn vertex_buffer(&self) -> &raii::Buffer {
+        &self.vertex_buffer
+    }
+
+    /// Descriptor info for binding the vertices as a STORAGE_BUFFER.
+    pub fn descriptor_buffer_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.vertex_buffer.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+}
+
+impl LSystemMesh {
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+}
@@ -0,0 +1,16 @@
+//! L-systems and turtle graphics.
+//!
+//! An LSystem rewrites a string of symbols with deterministic or stochastic
+//! rules. A Turtle interprets the expanded symbols as drawing commands and
+//! produces polylines, which can be turned into thick-line triangles for
+//! BindlessTriangles and cached in device-local memory with LSystemMesh.
+
+mod mesh;
+mod rules;
+mod turtle;
+
+pub use self::{
+    mesh::LSystemMesh,
+    rules::LSystem,
+    turtle::{Turtle, TurtleDrawing, TurtleParams, TurtlePath, TurtleStyle},
+};
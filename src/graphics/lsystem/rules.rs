@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+/// One possible replacement for a symbol.
+#[derive(Debug, Clone, PartialEq)]
+struct Production {
+    weight: f32,
+    replacement: Vec<char>,
+}
+
+/// A Lindenmayer system: an axiom and a set of rewriting rules.
+///
+/// Each iteration of `expand` replaces every symbol which has rules with one
+/// of its productions. Symbols without rules are copied unchanged. When a
+/// symbol has more than one production, one is picked at random with
+/// probability proportional to its weight, which makes the system
+/// stochastic. Expansion is deterministic for a given seed.
+///
+/// For example, `LSystem::new("X").with_rule('X', "F+[[X]-X]-F[-FX]+X")
+/// .with_rule('F', "FF")` grows the classic fractal plant.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LSystem {
+    axiom: Vec<char>,
+    rules: HashMap<char, Vec<Production>>,
+}
+
+impl LSystem {
+    /// Create an L-system which starts from the given axiom and has no
+    /// rules.
+    pub fn new(axiom: &str) -> Self {
+        Self {
+            axiom: axiom.chars().collect(),
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Add a production which always replaces `symbol`.
+    ///
+    /// Adding more than one production for the same symbol makes the rule
+    /// stochastic, with every production equally likely.
+    pub fn with_rule(self, symbol: char, replacement: &str) -> Self {
+        self.with_stochastic_rule(symbol, 1.0, replacement)
+    }
+
+    /// Add a weighted production for `symbol`.
+    ///
+    /// Each time the symbol is rewritten, a production is picked with
+    /// probability `weight / total_weight`, where `total_weight` is the sum
+    /// of the weights of every production for the symbol.
+    pub fn with_stochastic_rule(
+        mut self,
+        symbol: char,
+        weight: f32,
+        replacement: &str,
+    ) -> Self {
+        self.rules.entry(symbol).or_default().push(Production {
+            weight: weight.max(0.0),
+            replacement: replacement.chars().collect(),
+        });
+        self
+    }
+
+    /// The symbols the system starts from.
+    pub fn axiom(&self) -> &[char] {
+        &self.axiom
+    }
+
+    /// Apply the rules `iterations` times, starting from the axiom.
+    ///
+    /// # Params
+    ///
+    /// * `iterations` - the number of rewriting passes
+    /// * `seed` - selects productions for stochastic rules. The same seed
+    ///   always produces the same symbols.
+    pub fn expand(&self, iterations: u32, seed: u64) -> Vec<char> {
+        let mut rng = XorShift::new(seed);
+        let mut symbols = self.axiom.clone();
+        for _ in 0..iterations {
+            let mut next = Vec::with_capacity(symbols.len() * 2);
+            for symbol in &symbols {
+                match self.rules.get(symbol) {
+                    Some(productions) => next.extend_from_slice(
+                        &Self::choose(productions, &mut rng).replacement,
+                    ),
+                    None => next.push(*symbol),
+                }
+            }
+            symbols = next;
+        }
+        symbols
+    }
+
+    /// Apply the rules and collect the result into a String.
+    pub fn expand_to_string(&self, iterations: u32, seed: u64) -> String {
+        self.expand(iterations, seed).into_iter().collect()
+    }
+}
+
+impl LSystem {
+    /// Pick a production with probability proportional to its weight.
+    fn choose<'a>(
+        productions: &'a [Production],
+        rng: &mut XorShift,
+    ) -> &'a Production {
+        if productions.len() == 1 {
+            return &productions[0];
+        }
+        let total: f32 = productions.iter().map(|p| p.weight).sum();
+        if total <= 0.0 {
+            return &productions[0];
+        }
+        let mut target = rng.next_f32() * total;
+        for production in productions {
+            if target < production.weight {
+                return production;
+            }
+            target -= production.weight;
+        }
+        productions.last().unwrap()
+    }
+}
+
+/// A tiny deterministic random number generator for stochastic rules.
+struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // Mix the seed so small seeds still give well distributed values.
+        // The state must never be zero.
+        Self {
+            state: (seed ^ 0x9E37_79B9_7F4A_7C15).max(1),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A value in [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1_u64 << 24) as f32
+    }
+}
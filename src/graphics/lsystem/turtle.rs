@@ -0,0 +1,219 @@
+use crate::{graphics::vulkan_api::BindlessVertex, math::Vec2};
+
+/// How a turtle interprets symbols.
+///
+/// The supported commands are:
+///
+///   - `F` and `G`: move forward one step and draw a line
+///   - `f`: move forward one step without drawing
+///   - `+` and `-`: turn left or right by `angle`
+///   - `|`: turn around
+///   - `[` and `]`: push and pop the turtle's state, starting a branch
+///
+/// Every other symbol is ignored, so L-systems can use extra symbols as
+/// placeholders.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TurtleParams {
+    /// Where the turtle starts.
+    pub start: Vec2,
+
+    /// The direction the turtle starts facing, in radians counter-clockwise
+    /// from the +x axis.
+    pub heading: f32,
+
+    /// The length of a single step.
+    pub step: f32,
+
+    /// The angle, in radians, for each `+` or `-`.
+    pub angle: f32,
+
+    /// The step length is multiplied by this value inside each branch, so
+    /// nested branches get shorter.
+    pub branch_step_scale: f32,
+}
+
+impl Default for TurtleParams {
+    fn default() -> Self {
+        Self {
+            start: Vec2::new(0.0, 0.0),
+            heading: std::f32::consts::FRAC_PI_2,
+            step: 0.01,
+            angle: 25_f32.to_radians(),
+            branch_step_scale: 1.0,
+        }
+    }
+}
+
+/// The appearance of the lines drawn at one branch depth.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TurtleStyle {
+    /// The line color.
+    pub color: [f32; 4],
+
+    /// The line width, in the same units as the turtle's positions.
+    pub width: f32,
+}
+
+impl Default for TurtleStyle {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0, 1.0],
+            width: 0.005,
+        }
+    }
+}
+
+/// A continuous line drawn by the turtle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurtlePath {
+    /// The points along the line, in drawing order.
+    pub points: Vec<Vec2>,
+
+    /// The number of enclosing branches when the line was drawn. The trunk
+    /// has depth 0.
+    pub depth: u32,
+}
+
+/// Every line drawn by a turtle.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TurtleDrawing {
+    pub paths: Vec<TurtlePath>,
+}
+
+/// Interprets L-system symbols as turtle graphics commands.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Turtle {
+    pub params: TurtleParams,
+}
+
+/// The turtle's position and heading, saved by `[` and restored by `]`.
+#[derive(Debug, Copy, Clone)]
+struct TurtleState {
+    position: Vec2,
+    heading: f32,
+    step: f32,
+    depth: u32,
+}
+
+impl Turtle {
+    /// Create a turtle with the given parameters.
+    pub fn new(params: TurtleParams) -> Self {
+        Self { params }
+    }
+
+    /// Walk the turtle through the symbols and collect the lines it draws.
+    ///
+    /// Unmatched `]` symbols are ignored.
+    pub fn draw(&self, symbols: &[char]) -> TurtleDrawing {
+        let mut drawing = TurtleDrawing::default();
+        let mut stack: Vec<TurtleState> = vec![];
+        let mut state = TurtleState {
+            position: self.params.start,
+            heading: self.params.heading,
+            step: self.params.step,
+            depth: 0,
+        };
+        let mut path: Option<TurtlePath> = None;
+
+        for symbol in symbols {
+            match symbol {
+                'F' | 'G' => {
+                    let path = path.get_or_insert_with(|| TurtlePath {
+                        points: vec![state.position],
+                        depth: state.depth,
+                    });
+                    state.position +=
+                        Self::direction(state.heading) * state.step;
+                    path.points.push(state.position);
+                }
+                'f' => {
+                    drawing.end_path(&mut path);
+                    state.position +=
+                        Self::direction(state.heading) * state.step;
+                }
+                '+' => state.heading += self.params.angle,
+                '-' => state.heading -= self.params.angle,
+                '|' => state.heading += std::f32::consts::PI,
+                '[' => {
+                    drawing.end_path(&mut path);
+                    stack.push(state);
+                    state.depth += 1;
+                    state.step *= self.params.branch_step_scale;
+                }
+                ']' => {
+                    if let Some(saved) = stack.pop() {
+                        drawing.end_path(&mut path);
+                        state = saved;
+                    }
+                }
+                _ => (),
+            }
+        }
+        drawing.end_path(&mut path);
+        drawing
+    }
+}
+
+impl Turtle {
+    /// A unit vector pointing along the heading.
+    fn direction(heading: f32) -> Vec2 {
+        Vec2::new(heading.cos(), heading.sin())
+    }
+}
+
+impl TurtleDrawing {
+    /// The deepest branch depth of any path.
+    pub fn max_depth(&self) -> u32 {
+        self.paths.iter().map(|path| path.depth).max().unwrap_or(0)
+    }
+
+    /// Build thick lines out of triangles, ready for BindlessTriangles.
+    ///
+    /// Each segment becomes a quad styled by its path's depth: paths at depth
+    /// `d` use `styles[d]`, and paths deeper than the last style reuse the
+    /// last style. Vertices are untextured.
+    pub fn triangles(&self, styles: &[TurtleStyle]) -> Vec<BindlessVertex> {
+        let default_style = [TurtleStyle::default()];
+        let styles = if styles.is_empty() {
+            &default_style[..]
+        } else {
+            styles
+        };
+
+        let mut vertices = vec![];
+        for path in &self.paths {
+            let style = styles[(path.depth as usize).min(styles.len() - 1)];
+            let vertex = |point: Vec2| BindlessVertex {
+                pos: [point.x, point.y, 0.0, 1.0],
+                uv: [0.0, 0.0, -1.0],
+                pad: [0.0],
+                color: style.color,
+            };
+            for segment in path.points.windows(2) {
+                let (a, b) = (segment[0], segment[1]);
+                let along = b - a;
+                if along.norm_squared() <= f32::EPSILON {
+                    continue;
+                }
+                let normal = Vec2::new(-along.y, along.x).normalize()
+                    * (0.5 * style.width);
+                vertices.extend_from_slice(&[
+                    vertex(a - normal),
+                    vertex(a + normal),
+                    vertex(b + normal),
+                    vertex(a - normal),
+                    vertex(b + normal),
+                    vertex(b - normal),
+                ]);
+            }
+        }
+        vertices
+    }
+
+    /// Move the current path, if there is one, into the drawing.
+    fn end_path(&mut self, path: &mut Option<TurtlePath>) {
+        if let Some(path) = path.take() {
+            self.paths.push(path);
+        }
+    }
+}
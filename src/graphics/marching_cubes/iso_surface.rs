@@ -0,0 +1,695 @@
+use {
+    super::tables,
+    crate::graphics::{
+        vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A vertex emitted by the marching cubes shader. Must match IsoVertex in
+/// `shaders/iso_vertex.glsl`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[repr(C)]
+pub struct IsoVertex {
+    /// xyz is the world-space position, w is always 1.
+    pub position: [f32; 4],
+
+    /// xyz is the outward-facing surface normal, w is always 0.
+    pub normal: [f32; 4],
+}
+
+/// Parameters which control how the surface is extracted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IsoSurfaceParams {
+    /// Grid points with a density at or above this value are inside the
+    /// surface.
+    pub iso_level: f32,
+
+    /// The world-space position of the density field's first grid point.
+    pub bounds_min: [f32; 3],
+
+    /// The world-space position of the density field's last grid point.
+    pub bounds_max: [f32; 3],
+}
+
+impl Default for IsoSurfaceParams {
+    fn default() -> Self {
+        Self {
+            iso_level: 0.5,
+            bounds_min: [-1.0, -1.0, -1.0],
+            bounds_max: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Push constants for the marching cubes compute shader.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct MarchingCubesConstants {
+    bounds_min: [f32; 4],
+    bounds_max: [f32; 4],
+    iso_level: f32,
+    max_vertices: u32,
+}
+
+/// The indirect draw command written by the compute shader, followed by the
+/// number of vertices reserved so far.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct DrawCommand {
+    draw: vk::DrawIndirectCommand,
+    reserved_vertices: u32,
+}
+
+/// Extracts a triangle mesh from a 3D density field on the GPU.
+///
+/// The density field is an R32F 3D storage image which stays in the GENERAL
+/// layout. Sketches can fill it from the CPU with `write_density`, or write
+/// it directly with their own compute shaders through `density_view`.
+///
+/// Each call to `cmd_extract` resets the draw command, then runs one
+/// invocation per grid cell. Cells which cross the surface reserve space in
+/// the vertex buffer with an atomic counter and write their triangles. The
+/// vertex count lands in a VkDrawIndirectCommand, so `cmd_draw` draws the
+/// mesh without the CPU knowing how many triangles were made.
+///
+/// Vertex shaders read the vertices as a storage buffer of IsoVertex, see
+/// `shaders/iso_vertex.glsl`, or bind `vertex_buffer` as a vertex buffer.
+pub struct MarchingCubes {
+    /// The parameters used by each call to `cmd_extract`.
+    pub params: IsoSurfaceParams,
+
+    resolution: [u32; 3],
+    max_triangles: u32,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _triangle_table: raii::Buffer,
+    vertex_buffer: raii::Buffer,
+    draw_buffer: raii::Buffer,
+    density_view: raii::ImageView,
+    density_image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl MarchingCubes {
+    /// The format of the density field.
+    pub const DENSITY_FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+
+    /// Create the density field, output buffers, and compute pipeline. The
+    /// density starts at zero everywhere.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `resolution` - the number of density grid points on each axis. The
+    ///   grid has one fewer cell than points on each axis.
+    /// * `max_triangles` - the capacity of the vertex buffer. Triangles past
+    ///   the capacity are dropped.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        resolution: [u32; 3],
+        max_triangles: u32,
+    ) -> Result<Self, GraphicsError> {
+        let (density_image, density_view) =
+            Self::create_density_image(&render_device, resolution)?;
+
+        let table: Vec<i32> =
+            tables::triangle_table().into_iter().flatten().collect();
+        let triangle_table = Self::create_buffer(
+            &render_device,
+            (table.len() * std::mem::size_of::<i32>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        triangle_table.set_debug_name("Marching Cubes Triangle Table");
+        {
+            let ptr = triangle_table.allocation().map(render_device.device())?
+                as *mut i32;
+            std::ptr::copy_nonoverlapping(table.as_ptr(), ptr, table.len());
+            triangle_table.allocation().unmap(render_device.device())?;
+        }
+
+        let vertex_buffer = Self::create_buffer(
+            &render_device,
+            (3 * max_triangles.max(1) as usize
+                * std::mem::size_of::<IsoVertex>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        vertex_buffer.set_debug_name("Marching Cubes Vertices");
+
+        let draw_buffer = Self::create_buffer(
+            &render_device,
+            std::mem::size_of::<DrawCommand>() as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        draw_buffer.set_debug_name("Marching Cubes Draw Command");
+
+        let binding = |binding: u32, descriptor_type: vk::DescriptorType| {
+            vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            }
+        };
+        let bindings = [
+            binding(0, vk::DescriptorType::STORAGE_IMAGE),
+            binding(1, vk::DescriptorType::STORAGE_BUFFER),
+            binding(2, vk::DescriptorType::STORAGE_BUFFER),
+            binding(3, vk::DescriptorType::STORAGE_BUFFER),
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<MarchingCubesConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/marching_cubes.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Marching Cubes Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 1,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 3,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        {
+            let descriptor_set = descriptor_pool.descriptor_set(0);
+            let image_info = vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: density_view.raw(),
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let buffer_infos =
+                [triangle_table.raw(), vertex_buffer.raw(), draw_buffer.raw()]
+                    .map(|buffer| vk::DescriptorBufferInfo {
+                        buffer,
+                        offset: 0,
+                        range: vk::WHOLE_SIZE,
+                    });
+            let mut writes = vec![vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            }];
+            for (index, buffer_info) in buffer_infos.iter().enumerate() {
+                writes.push(vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: 1 + index as u32,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                });
+            }
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(Self {
+            params: IsoSurfaceParams::default(),
+            resolution,
+            max_triangles,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _triangle_table: triangle_table,
+            vertex_buffer,
+            draw_buffer,
+            density_view,
+            density_image,
+            render_device,
+        })
+    }
+
+    /// The number of density grid points on each axis.
+    pub fn resolution(&self) -> [u32; 3] {
+        self.resolution
+    }
+
+    /// The maximum number of triangles a single extraction can emit.
+    pub fn max_triangles(&self) -> u32 {
+        self.max_triangles
+    }
+
+    /// The density field image. It is always in the GENERAL layout.
+    pub fn density_image(&self) -> &raii::Image {
+        &self.density_image
+    }
+
+    /// A view of the density field, for compute shaders which write it as an
+    /// `r32f` image3D.
+    pub fn density_view(&self) -> &raii::ImageView {
+        &self.density_view
+    }
+
+    /// The buffer which holds the extracted IsoVertex vertices. It can be
+    /// bound as a vertex buffer or read as a storage buffer.
+    pub fn vertex_buffer(&self) -> &raii::Buffer {
+        &self.vertex_buffer
+    }
+
+    /// The descriptor info for the vertex buffer, for writing a
+    /// STORAGE_BUFFER descriptor.
+    pub fn descriptor_buffer_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.vertex_buffer.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// Replace the density field with values from the CPU.
+    ///
+    /// Values are ordered x first, then y, then z, with one value per grid
+    /// point. This blocks until the upload completes.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the density image must not be in use by the GPU
+    pub unsafe fn write_density(
+        &mut self,
+        values: &[f32],
+    ) -> Result<(), GraphicsError> {
+        let [width, height, depth] = self.resolution;
+        let expected = (width * height * depth) as usize;
+        if values.len() != expected {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Expected {} density values but got {}",
+                expected,
+                values.len()
+            )));
+        }
+
+        let staging_buffer = Self::create_buffer(
+            &self.render_device,
+            std::mem::size_of_val(values) as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging_buffer.set_debug_name("Marching Cubes Density Staging");
+        {
+            let device = self.render_device.device();
+            let ptr = staging_buffer.allocation().map(device)? as *mut f32;
+            std::ptr::copy_nonoverlapping(values.as_ptr(), ptr, values.len());
+            staging_buffer.allocation().unmap(device)?;
+        }
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            self.render_device.clone(),
+            self.render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        let device = self.render_device.device();
+
+        let region = vk::BufferImageCopy2 {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D {
+                width,
+                height,
+                depth,
+            },
+            ..Default::default()
+        };
+        device.cmd_copy_buffer_to_image2(
+            command_buffer,
+            &vk::CopyBufferToImageInfo2 {
+                src_buffer: staging_buffer.raw(),
+                dst_image: self.density_image.raw(),
+                dst_image_layout: vk::ImageLayout::GENERAL,
+                region_count: 1,
+                p_regions: &region,
+                ..Default::default()
+            },
+        );
+        Self::memory_barrier(
+            &self.render_device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ,
+                ..Default::default()
+            },
+        );
+
+        one_time_submit.sync_submit_and_reset()?;
+        Ok(())
+    }
+
+    /// Record commands which extract the surface from the density field.
+    ///
+    /// Compute shader writes to the density field and earlier draws of the
+    /// mesh complete before extraction begins. The commands end with a
+    /// barrier which makes the draw command and vertices visible to indirect
+    /// draws, vertex input, and vertex shaders.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn cmd_extract(&self, command_buffer: vk::CommandBuffer) {
+        let device = self.render_device.device();
+
+        Self::memory_barrier(
+            &self.render_device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::DRAW_INDIRECT
+                    | vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT
+                    | vk::PipelineStageFlags2::VERTEX_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COPY
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE
+                    | vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+
+        let reset = DrawCommand {
+            draw: vk::DrawIndirectCommand {
+                vertex_count: 0,
+                instance_count: 1,
+                first_vertex: 0,
+                first_instance: 0,
+            },
+            reserved_vertices: 0,
+        };
+        device.cmd_update_buffer(
+            command_buffer,
+            self.draw_buffer.raw(),
+            0,
+            std::slice::from_raw_parts(
+                &reset as *const DrawCommand as *const u8,
+                std::mem::size_of::<DrawCommand>(),
+            ),
+        );
+        Self::memory_barrier(
+            &self.render_device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        let [min_x, min_y, min_z] = self.params.bounds_min;
+        let [max_x, max_y, max_z] = self.params.bounds_max;
+        let constants = MarchingCubesConstants {
+            bounds_min: [min_x, min_y, min_z, 1.0],
+            bounds_max: [max_x, max_y, max_z, 1.0],
+            iso_level: self.params.iso_level,
+            max_vertices: 3 * self.max_triangles,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const MarchingCubesConstants as *const u8,
+                std::mem::size_of::<MarchingCubesConstants>(),
+            ),
+        );
+        let groups = self.resolution.map(|points| {
+            let cells = points.saturating_sub(1);
+            (cells + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE
+        });
+        device.cmd_dispatch(command_buffer, groups[0], groups[1], groups[2]);
+
+        Self::memory_barrier(
+            &self.render_device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::DRAW_INDIRECT
+                    | vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT
+                    | vk::PipelineStageFlags2::VERTEX_SHADER,
+                dst_access_mask: vk::AccessFlags2::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_READ,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Record an indirect draw of the most recently extracted mesh.
+    ///
+    /// The caller binds a graphics pipeline which reads IsoVertex vertices,
+    /// either from a storage buffer with gl_VertexIndex or from the vertex
+    /// buffer, and draws them as a triangle list.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording inside a render pass
+    ///   - `cmd_extract` must be recorded earlier, outside the render pass
+    pub unsafe fn cmd_draw(&self, command_buffer: vk::CommandBuffer) {
+        self.render_device.device().cmd_draw_indirect(
+            command_buffer,
+            self.draw_buffer.raw(),
+            0,
+            1,
+            std::mem::size_of::<vk::DrawIndirectCommand>() as u32,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl MarchingCubes {
+    /// The compute shader workgroup size on each axis.
+    const WORKGROUP_SIZE: u32 = 4;
+
+    /// The density image has a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Record a single global memory barrier.
+    unsafe fn memory_barrier(
+        render_device: &RenderDevice,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: vk::MemoryBarrier2,
+    ) {
+        render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+
+    /// Create the 3D density image, transition it to the GENERAL layout, and
+    /// clear it to zero.
+    unsafe fn create_density_image(
+        render_device: &Arc<RenderDevice>,
+        resolution: [u32; 3],
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let [width, height, depth] = resolution;
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_3D,
+            format: Self::DENSITY_FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_DST,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width,
+                height,
+                depth,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name("Marching Cubes Density");
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_3D,
+            format: Self::DENSITY_FORMAT,
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let view =
+            raii::ImageView::new(render_device.clone(), &view_create_info)?;
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        let device = render_device.device();
+        let to_general = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::NONE,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::CLEAR,
+            dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: image.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &to_general,
+                ..Default::default()
+            },
+        );
+        device.cmd_clear_color_image(
+            command_buffer,
+            image.raw(),
+            vk::ImageLayout::GENERAL,
+            &vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 0.0],
+            },
+            &[Self::SUBRESOURCE_RANGE],
+        );
+        Self::memory_barrier(
+            render_device,
+            command_buffer,
+            vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::CLEAR,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                ..Default::default()
+            },
+        );
+        one_time_submit.sync_submit_and_reset()?;
+
+        Ok((image, view))
+    }
+}
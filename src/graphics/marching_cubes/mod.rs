@@ -0,0 +1,11 @@
+//! Isosurface extraction with a marching cubes compute shader.
+//!
+//! MarchingCubes reads a density field from a 3D storage image, emits
+//! triangles into a storage buffer with an atomic counter, and draws the
+//! result with an indirect draw, so metaball and terrain sketches never need
+//! to read the mesh back to the CPU.
+
+mod iso_surface;
+mod tables;
+
+pub use self::iso_surface::{IsoSurfaceParams, IsoVertex, MarchingCubes};
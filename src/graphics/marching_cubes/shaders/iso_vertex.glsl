@@ -0,0 +1,16 @@
+// A vertex emitted by MarchingCubes. Must match IsoVertex in
+// iso_surface.rs.
+//
+// Vertex shaders read vertices with gl_VertexIndex, e.g.
+//
+//     layout(set = 0, binding = 0) readonly buffer IsoVertices {
+//         IsoVertex vertices[];
+//     };
+
+struct IsoVertex {
+    // xyz is the world-space position, w is always 1.
+    vec4 position;
+
+    // xyz is the outward-facing surface normal, w is always 0.
+    vec4 normal;
+};
@@ -0,0 +1,161 @@
+//! The marching cubes triangle table.
+//!
+//! Rather than embedding the classic 256-case table, it is generated by
+//! walking the faces of the cube. On each face, the corners inside the
+//! surface are cut off by segments between the crossed edges, with
+//! ambiguous faces always separating the inside corners so neighboring cells
+//! agree. The segments join into closed loops which are triangulated as fans.
+
+/// The maximum number of edge indices for a single case, including the -1
+/// terminator padding.
+pub const ENTRIES_PER_CASE: usize = 16;
+
+/// Each cube corner's offset from the cell's minimum corner. Must match
+/// `CORNERS` in `shaders/marching_cubes.comp`.
+const CORNERS: [[i32; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [1, 1, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [1, 1, 1],
+    [0, 1, 1],
+];
+
+/// The corners at either end of each cube edge. Must match `EDGES` in
+/// `shaders/marching_cubes.comp`.
+const EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [1, 2],
+    [2, 3],
+    [3, 0],
+    [4, 5],
+    [5, 6],
+    [6, 7],
+    [7, 4],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+/// The corners of each cube face, in cyclic order.
+const FACES: [[usize; 4]; 6] = [
+    [0, 1, 2, 3],
+    [4, 5, 6, 7],
+    [0, 1, 5, 4],
+    [3, 2, 6, 7],
+    [0, 3, 7, 4],
+    [1, 2, 6, 5],
+];
+
+/// Build the triangle table.
+///
+/// Case `i` has bit `c` set when corner `c` is inside the surface. Each
+/// entry lists the crossed edges of up to five triangles, padded with -1.
+/// Triangles wind counter-clockwise when viewed from outside the surface.
+pub fn triangle_table() -> Vec<[i32; ENTRIES_PER_CASE]> {
+    let faces = oriented_faces();
+    (0..256)
+        .map(|case| {
+            let mut entry = [-1; ENTRIES_PER_CASE];
+            let edges = case_triangles(case, &faces).into_iter().flatten();
+            for (slot, edge) in entry.iter_mut().zip(edges) {
+                *slot = edge as i32;
+            }
+            entry
+        })
+        .collect()
+}
+
+/// The triangles for a single case, as edge indices.
+fn case_triangles(case: usize, faces: &[[usize; 4]]) -> Vec<[usize; 3]> {
+    let inside = |corner: usize| case & (1 << corner) != 0;
+
+    // next[a] == b when a surface segment runs from edge a to edge b.
+    let mut next = [None; 12];
+    for face in faces {
+        let mut crossings = vec![];
+        for i in 0..4 {
+            let (a, b) = (face[i], face[(i + 1) % 4]);
+            if inside(a) != inside(b) {
+                crossings.push((edge_index(a, b), inside(b)));
+            }
+        }
+
+        // Walking the face, each run of inside corners is entered at one
+        // crossing and exited at the next. Cut it off with a segment.
+        for i in 0..crossings.len() {
+            let (entry_edge, is_entering) = crossings[i];
+            if is_entering {
+                let (exit_edge, _) = crossings[(i + 1) % crossings.len()];
+                next[exit_edge] = Some(entry_edge);
+            }
+        }
+    }
+
+    let mut visited = [false; 12];
+    let mut triangles = vec![];
+    for start in 0..12 {
+        if visited[start] || next[start].is_none() {
+            continue;
+        }
+        let mut loop_edges = vec![];
+        let mut edge = start;
+        while !visited[edge] {
+            visited[edge] = true;
+            loop_edges.push(edge);
+            edge = next[edge].unwrap();
+        }
+
+        // Loops run clockwise when viewed from outside, so reverse each
+        // triangle.
+        for i in 1..loop_edges.len() - 1 {
+            triangles.push([loop_edges[0], loop_edges[i + 1], loop_edges[i]]);
+        }
+    }
+    triangles
+}
+
+/// Each face's corners, ordered counter-clockwise when viewed from outside
+/// the cube.
+fn oriented_faces() -> Vec<[usize; 4]> {
+    let corner = |index: usize| CORNERS[index].map(|c| c as f32 - 0.5);
+    FACES
+        .iter()
+        .map(|&face| {
+            let [a, b, c, _] = face.map(corner);
+            let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let bc = [c[0] - b[0], c[1] - b[1], c[2] - b[2]];
+            let normal = [
+                ab[1] * bc[2] - ab[2] * bc[1],
+                ab[2] * bc[0] - ab[0] * bc[2],
+                ab[0] * bc[1] - ab[1] * bc[0],
+            ];
+
+            // Corners are centered on the cube, so the sum of the face's
+            // corners points outward.
+            let outward =
+                face.iter().map(|&i| corner(i)).fold([0.0; 3], |sum, p| {
+                    [sum[0] + p[0], sum[1] + p[1], sum[2] + p[2]]
+                });
+            let facing = normal[0] * outward[0]
+                + normal[1] * outward[1]
+                + normal[2] * outward[2];
+            if facing > 0.0 {
+                face
+            } else {
+                [face[3], face[2], face[1], face[0]]
+            }
+        })
+        .collect()
+}
+
+/// The index of the edge between two corners.
+fn edge_index(a: usize, b: usize) -> usize {
+    EDGES
+        .iter()
+        .position(|&[x, y]| (x == a && y == b) || (x == b && y == a))
+        .unwrap()
+}
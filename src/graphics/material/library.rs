@@ -0,0 +1,191 @@
+use {
+    super::{material::MaterialData, Material, MaterialId},
+    crate::graphics::{
+        vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Owns every material in a scene and keeps a copy of them in a storage
+/// buffer for each frame in flight.
+///
+/// Materials can be added or edited at any time. Changes are uploaded by
+/// `write_materials_for_frame`, which should be called once per frame before
+/// any draws which read materials.
+pub struct MaterialLibrary {
+    materials: Vec<Material>,
+    buffers: Vec<raii::Buffer>,
+    buffer_ptrs: Vec<*mut MaterialData>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl MaterialLibrary {
+    /// Create a new material library with room for `initial_capacity`
+    /// materials before any buffers need to grow.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        initial_capacity: usize,
+    ) -> Result<Self, GraphicsError> {
+        let buffer_count = frames_in_flight.frame_count();
+        let mut buffers = Vec::with_capacity(buffer_count);
+        let mut buffer_ptrs = Vec::with_capacity(buffer_count);
+        for _ in 0..buffer_count {
+            let (buffer, ptr) =
+                Self::allocate_buffer(&render_device, initial_capacity)?;
+            buffers.push(buffer);
+            buffer_ptrs.push(ptr);
+        }
+        Ok(Self {
+            materials: vec![],
+            buffers,
+            buffer_ptrs,
+            render_device,
+        })
+    }
+
+    /// Add a material to the library.
+    pub fn add(&mut self, material: Material) -> MaterialId {
+        self.materials.push(material);
+        MaterialId(self.materials.len() as u32 - 1)
+    }
+
+    /// Get a material.
+    pub fn get(&self, id: MaterialId) -> &Material {
+        &self.materials[id.0 as usize]
+    }
+
+    /// Get a mutable reference to a material. Changes are visible to shaders
+    /// after the next call to `write_materials_for_frame`.
+    pub fn get_mut(&mut self, id: MaterialId) -> &mut Material {
+        &mut self.materials[id.0 as usize]
+    }
+
+    /// The number of materials in the library.
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    /// Returns true when the library has no materials.
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// Copy every material into the frame's storage buffer.
+    ///
+    /// Returns true when the buffer was reallocated, in which case any
+    /// descriptor sets which reference it must be rewritten with
+    /// `descriptor_buffer_info` before drawing.
+    pub fn write_materials_for_frame(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<bool, GraphicsError> {
+        let index = frame.frame_index();
+        let required_size =
+            (self.materials.len() * std::mem::size_of::<MaterialData>()) as u64;
+        let mut reallocated = false;
+        if self.buffers[index].allocation().size_in_bytes() < required_size {
+            unsafe {
+                // SAFE because the frame's previous commands are complete, so
+                // the old buffer is no longer in use.
+                self.buffers[index]
+                    .allocation()
+                    .unmap(self.render_device.device())?;
+                let (buffer, ptr) = Self::allocate_buffer(
+                    &self.render_device,
+                    self.materials.len() * 2,
+                )?;
+                self.buffers[index] = buffer;
+                self.buffer_ptrs[index] = ptr;
+            }
+            reallocated = true;
+        }
+
+        let data = unsafe {
+            std::slice::from_raw_parts_mut(
+                self.buffer_ptrs[index],
+                self.materials.len(),
+            )
+        };
+        for (gpu_material, material) in data.iter_mut().zip(&self.materials) {
+            *gpu_material = MaterialData::from(material);
+        }
+        Ok(reallocated)
+    }
+
+    /// The descriptor info for the frame's material buffer, for writing a
+    /// STORAGE_BUFFER descriptor.
+    pub fn descriptor_buffer_info(
+        &self,
+        frame: &Frame,
+    ) -> vk::DescriptorBufferInfo {
+        let buffer = &self.buffers[frame.frame_index()];
+        vk::DescriptorBufferInfo {
+            buffer: buffer.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// A descriptor set layout binding for the material buffer.
+    pub fn descriptor_set_layout_binding(
+        binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl MaterialLibrary {
+    /// Allocate a host-visible storage buffer with room for `capacity`
+    /// materials.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not use the associated memory-mapped pointer
+    ///     once the buffer has been dropped.
+    unsafe fn allocate_buffer(
+        render_device: &Arc<RenderDevice>,
+        capacity: usize,
+    ) -> Result<(raii::Buffer, *mut MaterialData), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (capacity.max(1) * std::mem::size_of::<MaterialData>())
+                as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Materials");
+        let ptr = buffer.allocation().map(render_device.device())?;
+        Ok((buffer, ptr as *mut MaterialData))
+    }
+}
@@ -0,0 +1,144 @@
+/// The index of a texture in a bindless texture array.
+pub type TextureId = u32;
+
+/// The index of a material in a MaterialLibrary.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub u32);
+
+/// How a material's surface responds to light.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShadingModel {
+    /// The base color and emissive are used as-is.
+    Unlit,
+
+    /// Metallic-roughness shading, lit by direct lights and an
+    /// EnvironmentLighting. See `shaders/pbr.glsl`.
+    Pbr,
+}
+
+impl Default for ShadingModel {
+    fn default() -> Self {
+        ShadingModel::Unlit
+    }
+}
+
+/// Surface parameters for a single material.
+///
+/// Texture ids refer to the bindless texture array bound alongside the
+/// material buffer. Scalar and color parameters multiply the sampled texture
+/// values, so a material with no textures is a flat color.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Material {
+    /// The base color, multiplied with the albedo texture.
+    pub base_color: [f32; 4],
+
+    /// The emitted color, multiplied with the emissive texture.
+    pub emissive: [f32; 3],
+
+    /// Perceptual roughness in [0, 1], multiplied with the roughness texture.
+    pub roughness: f32,
+
+    /// Metalness in [0, 1].
+    pub metallic: f32,
+
+    pub albedo_texture: Option<TextureId>,
+    pub normal_texture: Option<TextureId>,
+    pub roughness_texture: Option<TextureId>,
+    pub emissive_texture: Option<TextureId>,
+
+    /// How shaders light this material.
+    pub shading: ShadingModel,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            emissive: [0.0, 0.0, 0.0],
+            roughness: 0.5,
+            metallic: 0.0,
+            albedo_texture: None,
+            normal_texture: None,
+            roughness_texture: None,
+            emissive_texture: None,
+            shading: ShadingModel::Unlit,
+        }
+    }
+}
+
+impl Material {
+    /// A material with a flat base color and no textures.
+    pub fn new_color(base_color: [f32; 4]) -> Self {
+        Self {
+            base_color,
+            ..Default::default()
+        }
+    }
+
+    /// A PBR material with a flat base color and no textures.
+    pub fn new_pbr(
+        base_color: [f32; 4],
+        roughness: f32,
+        metallic: f32,
+    ) -> Self {
+        Self {
+            base_color,
+            roughness,
+            metallic,
+            shading: ShadingModel::Pbr,
+            ..Default::default()
+        }
+    }
+
+    /// A material which samples the albedo texture.
+    pub fn new_textured(albedo_texture: TextureId) -> Self {
+        Self {
+            albedo_texture: Some(albedo_texture),
+            ..Default::default()
+        }
+    }
+}
+
+/// The GPU representation of a Material.
+///
+/// Must match the Material struct in `shaders/material.glsl`. Missing
+/// textures are stored as -1 and the shading model is stored as one of the
+/// SHADING_MODEL constants in `shaders/material.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub(super) struct MaterialData {
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 4],
+    pub roughness: f32,
+    pub metallic: f32,
+    pub albedo_texture: i32,
+    pub normal_texture: i32,
+    pub roughness_texture: i32,
+    pub emissive_texture: i32,
+    pub shading_model: u32,
+    pub pad: i32,
+}
+
+impl From<&Material> for MaterialData {
+    fn from(material: &Material) -> Self {
+        let texture_index = |texture: Option<TextureId>| {
+            texture.map(|id| id as i32).unwrap_or(-1)
+        };
+        let [r, g, b] = material.emissive;
+        Self {
+            base_color: material.base_color,
+            emissive: [r, g, b, 0.0],
+            roughness: material.roughness,
+            metallic: material.metallic,
+            albedo_texture: texture_index(material.albedo_texture),
+            normal_texture: texture_index(material.normal_texture),
+            roughness_texture: texture_index(material.roughness_texture),
+            emissive_texture: texture_index(material.emissive_texture),
+            shading_model: match material.shading {
+                ShadingModel::Unlit => 0,
+                ShadingModel::Pbr => 1,
+            },
+            pad: 0,
+        }
+    }
+}
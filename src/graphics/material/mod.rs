@@ -0,0 +1,19 @@
+//! Materials which reference bindless textures by index.
+//!
+//! A MaterialLibrary owns a list of Materials and uploads them into a
+//! per-frame storage buffer. Shaders index the buffer with a per-draw material
+//! id and sample textures from a bindless texture array, like the one used by
+//! BindlessTriangles. The `shaders/material.glsl` file declares the matching
+//! GLSL struct and can be included by any shader which reads materials.
+//!
+//! Materials with the Pbr shading model can be lit with
+//! `shaders/pbr.glsl`, which implements metallic-roughness shading for direct
+//! lights and for the image based lighting generated by the `ibl` module.
+
+mod library;
+mod material;
+
+pub use self::{
+    library::MaterialLibrary,
+    material::{Material, MaterialId, ShadingModel, TextureId},
+};
@@ -0,0 +1,32 @@
+// The GPU representation of a Material. Must match MaterialData in
+// material.rs.
+//
+// Include this file after enabling GL_EXT_nonuniform_qualifier, then declare
+// the material buffer and bindless textures, e.g.:
+//
+//   layout(std430, set = 0, binding = 2) readonly buffer Materials {
+//       Material materials[];
+//   };
+//   layout(set = 0, binding = 1) uniform sampler2D textures[];
+
+struct Material {
+    vec4 base_color;
+    vec4 emissive;
+    float roughness;
+    float metallic;
+    int albedo_texture;
+    int normal_texture;
+    int roughness_texture;
+    int emissive_texture;
+    uint shading_model;
+    int pad;
+};
+
+// Values of Material.shading_model. Must match ShadingModel in material.rs.
+#define SHADING_MODEL_UNLIT 0
+#define SHADING_MODEL_PBR 1
+
+// Sample a bindless texture, or return the fallback when the texture index is
+// negative.
+#define SAMPLE_MATERIAL_TEXTURE(textures, index, uv, fallback) \
+    ((index) >= 0 ? texture(textures[nonuniformEXT(index)], (uv)) : (fallback))
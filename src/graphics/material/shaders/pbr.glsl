@@ -0,0 +1,122 @@
+// Metallic-roughness shading for materials with SHADING_MODEL_PBR.
+//
+// Include this file after material.glsl. Image based lighting reads the
+// images in an EnvironmentLighting, declared with the descriptor set layout
+// from `EnvironmentLighting::descriptor_set_layout`, e.g.:
+//
+//   layout(set = 1, binding = 0) uniform samplerCube irradiance;
+//   layout(set = 1, binding = 1) uniform samplerCube specular;
+//   layout(set = 1, binding = 2) uniform sampler2D brdf_lut;
+//
+// All vectors are in the same space, usually world space, and point away from
+// the surface.
+
+const float PBR_PI = 3.14159265359;
+
+// The surface parameters used by every lighting function.
+struct PbrSurface {
+    vec3 albedo;
+    vec3 normal;
+    float roughness;
+    float metallic;
+};
+
+// Reflectance at normal incidence. Dielectrics reflect 4%, metals reflect
+// their albedo.
+vec3 pbr_f0(PbrSurface surface) {
+    return mix(vec3(0.04), surface.albedo, surface.metallic);
+}
+
+// The GGX normal distribution function.
+float pbr_distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PBR_PI * d * d);
+}
+
+// Smith's geometry term with the Schlick-GGX approximation for direct lights.
+float pbr_geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    float g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+vec3 pbr_fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Fresnel for ambient light, which has no single half vector, so rough
+// surfaces are darkened at grazing angles.
+vec3 pbr_fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    vec3 f90 = max(vec3(1.0 - roughness), f0);
+    return f0 + (f90 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// The light reflected towards the viewer from a single light.
+//
+// `to_view` and `to_light` are normalized and `radiance` is the light's color
+// times its intensity and attenuation.
+vec3 pbr_direct_light(
+    PbrSurface surface,
+    vec3 to_view,
+    vec3 to_light,
+    vec3 radiance
+) {
+    vec3 h = normalize(to_view + to_light);
+    float n_dot_v = max(dot(surface.normal, to_view), 1e-4);
+    float n_dot_l = max(dot(surface.normal, to_light), 0.0);
+    float n_dot_h = max(dot(surface.normal, h), 0.0);
+    float roughness = max(surface.roughness, 0.04);
+
+    vec3 f = pbr_fresnel_schlick(max(dot(h, to_view), 0.0), pbr_f0(surface));
+    float d = pbr_distribution_ggx(n_dot_h, roughness);
+    float g = pbr_geometry_smith(n_dot_v, n_dot_l, roughness);
+    vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 1e-4);
+
+    vec3 diffuse = (1.0 - f) * (1.0 - surface.metallic) * surface.albedo / PBR_PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+// The light reflected towards the viewer from the environment.
+//
+// `specular_mip_levels` is `EnvironmentLighting::specular_mip_levels`.
+vec3 pbr_ambient_light(
+    PbrSurface surface,
+    vec3 to_view,
+    samplerCube irradiance,
+    samplerCube specular,
+    sampler2D brdf_lut,
+    float specular_mip_levels
+) {
+    float n_dot_v = max(dot(surface.normal, to_view), 1e-4);
+    vec3 f = pbr_fresnel_schlick_roughness(
+        n_dot_v, pbr_f0(surface), surface.roughness);
+
+    vec3 diffuse = texture(irradiance, surface.normal).rgb * surface.albedo;
+    diffuse *= (1.0 - f) * (1.0 - surface.metallic);
+
+    vec3 reflected = reflect(-to_view, surface.normal);
+    float lod = surface.roughness * (specular_mip_levels - 1.0);
+    vec3 prefiltered = textureLod(specular, reflected, lod).rgb;
+    vec2 scale_bias = texture(brdf_lut, vec2(n_dot_v, surface.roughness)).rg;
+
+    return diffuse + prefiltered * (f * scale_bias.x + scale_bias.y);
+}
+
+// Build the surface for a material whose textures have already been sampled.
+PbrSurface pbr_surface(
+    Material material,
+    vec4 albedo_sample,
+    float roughness_sample,
+    vec3 normal
+) {
+    PbrSurface surface;
+    surface.albedo = material.base_color.rgb * albedo_sample.rgb;
+    surface.normal = normalize(normal);
+    surface.roughness = clamp(material.roughness * roughness_sample, 0.0, 1.0);
+    surface.metallic = clamp(material.metallic, 0.0, 1.0);
+    return surface;
+}
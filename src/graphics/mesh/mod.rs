@@ -0,0 +1,20 @@
+//! Quantized vertex formats for reducing vertex bandwidth.
+//!
+//! A Quantizer packs positions into 16-bit unorm values relative to the
+//! mesh's bounding box, and normals, texture coordinates, and colors are
+//! packed into 10-bit, 16-bit, and 8-bit unorm values. QuantizedVertex and
+//! QuantizedPoint describe their own vertex input through VertexFormat, so
+//! pipelines read them with `GraphicsPipelineBuilder::vertex_input`, and
+//! QuantizedMesh uploads them to a device-local vertex buffer.
+
+mod quantize;
+mod quantized_mesh;
+mod vertex;
+
+pub use self::{
+    quantize::{
+        decode_normal, encode_color, encode_normal, encode_uv, Quantizer,
+    },
+    quantized_mesh::QuantizedMesh,
+    vertex::{MeshVertex, QuantizedPoint, QuantizedVertex, VertexFormat},
+};
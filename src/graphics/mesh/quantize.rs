@@ -0,0 +1,120 @@
+use crate::math::{Mat4, Vec2, Vec3};
+
+/// Maps positions inside of an axis-aligned bounding box to 16-bit unsigned
+/// normalized integers.
+///
+/// Quantized positions are read by the vertex shader as values in [0, 1].
+/// Multiplying by `dequantize_transform` moves them back into model space,
+/// so the transform is usually folded into the model matrix.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quantizer {
+    /// The smallest corner of the bounding box.
+    pub min: Vec3,
+
+    /// The largest corner of the bounding box.
+    pub max: Vec3,
+}
+
+impl Quantizer {
+    /// Create a quantizer for positions inside of the given bounds.
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Create a quantizer which tightly bounds every position.
+    ///
+    /// An empty slice produces a unit box at the origin.
+    pub fn from_positions(positions: &[Vec3]) -> Self {
+        if positions.is_empty() {
+            return Self::new(Vec3::zeros(), Vec3::new(1.0, 1.0, 1.0));
+        }
+        let mut min = positions[0];
+        let mut max = positions[0];
+        for position in positions {
+            min = min.inf(position);
+            max = max.sup(position);
+        }
+        Self { min, max }
+    }
+
+    /// The size of the bounding box along each axis. Flat axes are treated as
+    /// having unit size so they don't divide by zero.
+    pub fn extent(&self) -> Vec3 {
+        (self.max - self.min).map(|size| if size > 0.0 { size } else { 1.0 })
+    }
+
+    /// The largest error introduced by quantizing a position, per axis.
+    pub fn precision(&self) -> Vec3 {
+        self.extent() / (2.0 * u16::MAX as f32)
+    }
+
+    /// Encode a position as four 16-bit unorm values. The fourth component
+    /// is always 1 so the shader can read a homogeneous position.
+    pub fn encode_position(&self, position: &Vec3) -> [u16; 4] {
+        let normalized = (position - self.min).component_div(&self.extent());
+        [
+            encode_unorm16(normalized.x),
+            encode_unorm16(normalized.y),
+            encode_unorm16(normalized.z),
+            u16::MAX,
+        ]
+    }
+
+    /// Decode a position produced by `encode_position`.
+    pub fn decode_position(&self, encoded: &[u16; 4]) -> Vec3 {
+        let normalized = Vec3::new(
+            decode_unorm16(encoded[0]),
+            decode_unorm16(encoded[1]),
+            decode_unorm16(encoded[2]),
+        );
+        self.min + normalized.component_mul(&self.extent())
+    }
+
+    /// The transform from normalized positions in [0, 1] back to model
+    /// space.
+    pub fn dequantize_transform(&self) -> Mat4 {
+        Mat4::new_translation(&self.min)
+            * Mat4::new_nonuniform_scaling(&self.extent())
+    }
+}
+
+/// Encode a unit normal as A2B10G10R10_UNORM_PACK32.
+///
+/// Each component is mapped from [-1, 1] to [0, 1], so shaders recover the
+/// normal with `normal * 2.0 - 1.0`. The unsigned format is used because
+/// signed packed formats are optional for vertex buffers.
+pub fn encode_normal(normal: &Vec3) -> u32 {
+    let normal = normal.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z);
+    let encode = |value: f32| -> u32 {
+        ((value * 0.5 + 0.5).clamp(0.0, 1.0) * 1023.0).round() as u32
+    };
+    (3 << 30)
+        | (encode(normal.z) << 20)
+        | (encode(normal.y) << 10)
+        | encode(normal.x)
+}
+
+/// Decode a normal produced by `encode_normal`.
+pub fn decode_normal(encoded: u32) -> Vec3 {
+    let decode =
+        |shift: u32| ((encoded >> shift) & 1023) as f32 / 1023.0 * 2.0 - 1.0;
+    Vec3::new(decode(0), decode(10), decode(20))
+}
+
+/// Encode texture coordinates in [0, 1] as two 16-bit unorm values.
+pub fn encode_uv(uv: &Vec2) -> [u16; 2] {
+    [encode_unorm16(uv.x), encode_unorm16(uv.y)]
+}
+
+/// Encode a linear rgba color as four 8-bit unorm values.
+pub fn encode_color(color: &[f32; 4]) -> [u8; 4] {
+    color.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn encode_unorm16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+fn decode_unorm16(value: u16) -> f32 {
+    value as f32 / u16::MAX as f32
+}
@@ -0,0 +1,217 @@
+use {
+    super::{Quantizer, VertexFormat},
+    crate::{
+        graphics::{
+            vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::{marker::PhantomData, sync::Arc},
+};
+
+/// Quantized vertices in a device-local vertex buffer.
+///
+/// Bind the buffer with `cmd_bind_vertex_buffer` and build the pipeline
+/// with `GraphicsPipelineBuilder::vertex_input`, using the descriptions from
+/// the vertex type's VertexFormat implementation.
+pub struct QuantizedMesh<V: VertexFormat> {
+    quantizer: Quantizer,
+    vertex_count: u32,
+    vertex_buffer: raii::Buffer,
+    render_device: Arc<RenderDevice>,
+    _vertex: PhantomData<V>,
+}
+
+impl<V: VertexFormat> QuantizedMesh<V> {
+    /// Upload vertices to a new device-local buffer. Blocks until the upload
+    /// completes.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `quantizer` - the quantizer used to encode the vertex positions
+    /// * `vertices` - the quantized vertices
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        quantizer: Quantizer,
+        vertices: &[V],
+    ) -> Result<Self, GraphicsError> {
+        if vertices.is_empty() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Unable to create a QuantizedMesh without any vertices"
+            )));
+        }
+        let size = std::mem::size_of_val(vertices) as u64;
+
+        let staging_buffer = Self::create_buffer(
+            &render_device,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging_buffer.set_debug_name("Quantized Mesh Staging");
+        {
+            let device = render_device.device();
+            let ptr = staging_buffer.allocation().map(device)? as *mut V;
+            std::ptr::copy_nonoverlapping(
+                vertices.as_ptr(),
+                ptr,
+                vertices.len(),
+            );
+            staging_buffer.allocation().unmap(device)?;
+        }
+
+        let vertex_buffer = Self::create_buffer(
+            &render_device,
+            size,
+            vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        vertex_buffer.set_debug_name("Quantized Mesh Vertices");
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        let device = render_device.device();
+        device.cmd_copy_buffer(
+            command_buffer,
+            staging_buffer.raw(),
+            vertex_buffer.raw(),
+            &[vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size,
+            }],
+        );
+        let barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::VERTEX_INPUT
+                | vk::PipelineStageFlags2::VERTEX_SHADER,
+            dst_access_mask: vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &barrier,
+                ..Default::default()
+            },
+        );
+        one_time_submit.sync_submit_and_reset()?;
+
+        Ok(Self {
+            quantizer,
+            vertex_count: vertices.len() as u32,
+            vertex_buffer,
+            render_device,
+            _vertex: PhantomData,
+        })
+    }
+
+    /// The quantizer used to encode the vertex positions.
+    pub fn quantizer(&self) -> &Quantizer {
+        &self.quantizer
+    }
+
+    /// The transform from quantized positions to model space. Multiply the
+    /// model matrix by this transform before drawing.
+    pub fn dequantize_transform(&self) -> Mat4 {
+        self.quantizer.dequantize_transform()
+    }
+
+    /// The number of vertices in the buffer.
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    /// The device-local buffer which holds the vertices.
+    pub fn vertex_buffer(&self) -> &raii::Buffer {
+        &self.vertex_buffer
+    }
+
+    /// The size of the vertex buffer in bytes.
+    pub fn size_in_bytes(&self) -> u64 {
+        self.vertex_count as u64 * std::mem::size_of::<V>() as u64
+    }
+
+    /// Bind the vertex buffer.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the mesh must not be dropped until the command buffer finishes
+    ///     executing
+    pub unsafe fn cmd_bind_vertex_buffer(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        binding: u32,
+    ) {
+        self.render_device.device().cmd_bind_vertex_buffers(
+            command_buffer,
+            binding,
+            &[self.vertex_buffer.raw()],
+            &[0],
+        );
+    }
+
+    /// Bind the vertex buffer and draw every vertex.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - a pipeline with matching vertex input must be bound inside of a
+    ///     render pass
+    ///   - the mesh must not be dropped until the command buffer finishes
+    ///     executing
+    pub unsafe fn cmd_draw(&self, command_buffer: vk::CommandBuffer) {
+        self.cmd_bind_vertex_buffer(command_buffer, 0);
+        self.render_device.device().cmd_draw(
+            command_buffer,
+            self.vertex_count,
+            1,
+            0,
+            0,
+        );
+    }
+}
+
+impl<V: VertexFormat> QuantizedMesh<V> {
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+}
@@ -0,0 +1,198 @@
+use {
+    super::{encode_color, encode_normal, encode_uv, Quantizer},
+    crate::math::{Vec2, Vec3},
+    ash::vk,
+    memoffset::offset_of,
+};
+
+/// A full-precision mesh vertex, used as the input for quantization.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+    pub color: [f32; 4],
+}
+
+/// Vertex types which can be read with fixed-function vertex input.
+pub trait VertexFormat: Copy {
+    /// The attributes for this vertex type, read from a single binding.
+    ///
+    /// # Params
+    ///
+    /// * `binding` - the vertex buffer binding index
+    /// * `first_location` - the shader location of the first attribute.
+    ///   Attributes use consecutive locations.
+    fn vertex_input_attributes(
+        binding: u32,
+        first_location: u32,
+    ) -> Vec<vk::VertexInputAttributeDescription>;
+
+    /// The per-vertex binding description for this vertex type.
+    fn vertex_input_binding(binding: u32) -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription {
+            binding,
+            stride: std::mem::size_of::<Self>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }
+    }
+}
+
+/// A 20 byte mesh vertex.
+///
+/// In GLSL, the attributes are:
+///
+/// ```glsl
+/// layout(location = 0) in vec4 position; // dequantize_transform * position
+/// layout(location = 1) in vec4 normal;   // normal.xyz * 2.0 - 1.0
+/// layout(location = 2) in vec2 uv;
+/// layout(location = 3) in vec4 color;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct QuantizedVertex {
+    /// R16G16B16A16_UNORM position inside of the Quantizer's bounds.
+    pub position: [u16; 4],
+
+    /// A2B10G10R10_UNORM_PACK32 normal.
+    pub normal: u32,
+
+    /// R16G16_UNORM texture coordinates.
+    pub uv: [u16; 2],
+
+    /// R8G8B8A8_UNORM color.
+    pub color: [u8; 4],
+}
+
+impl QuantizedVertex {
+    /// Quantize a single vertex.
+    pub fn encode(quantizer: &Quantizer, vertex: &MeshVertex) -> Self {
+        Self {
+            position: quantizer.encode_position(&vertex.position),
+            normal: encode_normal(&vertex.normal),
+            uv: encode_uv(&vertex.uv),
+            color: encode_color(&vertex.color),
+        }
+    }
+
+    /// Quantize every vertex with a quantizer which bounds all of their
+    /// positions.
+    pub fn encode_all(vertices: &[MeshVertex]) -> (Quantizer, Vec<Self>) {
+        let positions = vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect::<Vec<Vec3>>();
+        let quantizer = Quantizer::from_positions(&positions);
+        let encoded = vertices
+            .iter()
+            .map(|vertex| Self::encode(&quantizer, vertex))
+            .collect();
+        (quantizer, encoded)
+    }
+}
+
+impl VertexFormat for QuantizedVertex {
+    fn vertex_input_attributes(
+        binding: u32,
+        first_location: u32,
+    ) -> Vec<vk::VertexInputAttributeDescription> {
+        vec![
+            vk::VertexInputAttributeDescription {
+                location: first_location,
+                binding,
+                format: vk::Format::R16G16B16A16_UNORM,
+                offset: offset_of!(QuantizedVertex, position) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: first_location + 1,
+                binding,
+                format: vk::Format::A2B10G10R10_UNORM_PACK32,
+                offset: offset_of!(QuantizedVertex, normal) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: first_location + 2,
+                binding,
+                format: vk::Format::R16G16_UNORM,
+                offset: offset_of!(QuantizedVertex, uv) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: first_location + 3,
+                binding,
+                format: vk::Format::R8G8B8A8_UNORM,
+                offset: offset_of!(QuantizedVertex, color) as u32,
+            },
+        ]
+    }
+}
+
+/// A 12 byte point with a position and color, for large point clouds.
+///
+/// In GLSL, the attributes are:
+///
+/// ```glsl
+/// layout(location = 0) in vec4 position; // dequantize_transform * position
+/// layout(location = 1) in vec4 color;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct QuantizedPoint {
+    /// R16G16B16A16_UNORM position inside of the Quantizer's bounds.
+    pub position: [u16; 4],
+
+    /// R8G8B8A8_UNORM color.
+    pub color: [u8; 4],
+}
+
+impl QuantizedPoint {
+    /// Quantize a single point.
+    pub fn encode(
+        quantizer: &Quantizer,
+        position: &Vec3,
+        color: &[f32; 4],
+    ) -> Self {
+        Self {
+            position: quantizer.encode_position(position),
+            color: encode_color(color),
+        }
+    }
+
+    /// Quantize every point with a quantizer which bounds all of them.
+    /// Points past the end of `colors` are white.
+    pub fn encode_all(
+        positions: &[Vec3],
+        colors: &[[f32; 4]],
+    ) -> (Quantizer, Vec<Self>) {
+        let quantizer = Quantizer::from_positions(positions);
+        let encoded = positions
+            .iter()
+            .enumerate()
+            .map(|(i, position)| {
+                let color = colors.get(i).unwrap_or(&[1.0, 1.0, 1.0, 1.0]);
+                Self::encode(&quantizer, position, color)
+            })
+            .collect();
+        (quantizer, encoded)
+    }
+}
+
+impl VertexFormat for QuantizedPoint {
+    fn vertex_input_attributes(
+        binding: u32,
+        first_location: u32,
+    ) -> Vec<vk::VertexInputAttributeDescription> {
+        vec![
+            vk::VertexInputAttributeDescription {
+                location: first_location,
+                binding,
+                format: vk::Format::R16G16B16A16_UNORM,
+                offset: offset_of!(QuantizedPoint, position) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: first_location + 1,
+                binding,
+                format: vk::Format::R8G8B8A8_UNORM,
+                offset: offset_of!(QuantizedPoint, color) as u32,
+            },
+        ]
+    }
+}
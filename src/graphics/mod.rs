@@ -1,4 +1,37 @@
+pub mod billboard;
+pub mod boids;
+pub mod debug;
+pub mod decal;
+pub mod deferred;
 mod error;
+pub mod flow_field;
+pub mod fluid;
+pub mod gpu;
+pub mod gradient;
+pub mod ibl;
+pub mod instancing;
+pub mod lights;
+pub mod lsystem;
+pub mod marching_cubes;
+pub mod material;
+pub mod mesh;
+pub mod noise;
+pub mod oit;
+pub mod paint;
+pub mod particles;
+pub mod point_cloud;
+pub mod post;
+pub mod scene;
+pub mod sdf;
+pub mod sprite;
+pub mod stereo;
+pub mod transfer;
+#[cfg(feature = "video")]
+pub mod video;
 pub mod vulkan_api;
+#[cfg(feature = "xr")]
+pub mod xr;
 
-pub use self::error::GraphicsError;
+pub use self::error::{
+    AllocationError, GraphicsError, PipelineError, ShaderError, SwapchainError,
+};
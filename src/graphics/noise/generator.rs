@@ -0,0 +1,397 @@
+use {
+    super::NoiseParams,
+    crate::graphics::{
+        vulkan_api::{
+            raii, read_image_to_rgba8, save_rgba8_png,
+            OneTimeSubmitCommandBuffer, RenderDevice,
+        },
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::{collections::HashMap, path::Path, sync::Arc},
+};
+
+/// A noise texture generated on the GPU.
+pub struct NoiseTexture {
+    pub params: NoiseParams,
+    pub image_view: raii::ImageView,
+    pub image: raii::Image,
+}
+
+impl NoiseTexture {
+    /// Copy a 2D noise texture back to the CPU and save it as a PNG.
+    ///
+    /// Values are clamped to [0, 1], so signed noise like curl saves its
+    /// negative components as 0.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the texture must not be in use by any in-flight commands
+    pub unsafe fn save_png(
+        &self,
+        render_device: Arc<RenderDevice>,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GraphicsError> {
+        if self.params.is_3d() {
+            return Err(anyhow!(
+                "Only 2D noise textures can be saved as png images"
+            )
+            .into());
+        }
+        let image = read_image_to_rgba8(
+            render_device,
+            &self.image,
+            NoiseGenerator::FORMAT,
+            vk::Extent2D {
+                width: self.params.extent.width,
+                height: self.params.extent.height,
+            },
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )?;
+        save_rgba8_png(&image, path)
+    }
+}
+
+/// Push constants for the noise compute shaders.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct NoiseConstants {
+    kind: u32,
+    frequency: u32,
+    octaves: u32,
+    seed: u32,
+}
+
+/// Generates tileable noise textures with compute shaders.
+///
+/// Generated textures are cached by their parameters, so asking for the same
+/// noise twice returns the same texture without touching the GPU.
+pub struct NoiseGenerator {
+    cache: HashMap<NoiseParams, Arc<NoiseTexture>>,
+    one_time_submit: OneTimeSubmitCommandBuffer,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline_2d: raii::Pipeline,
+    pipeline_3d: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl NoiseGenerator {
+    /// The format of every generated noise texture.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// Create the noise compute pipelines.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the generator must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<Self, GraphicsError> {
+        let one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+
+        let binding = vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            ..vk::DescriptorSetLayoutBinding::default()
+        };
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[binding],
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<NoiseConstants>() as u32,
+                }],
+            )?;
+        let pipeline_2d = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/noise_2d.comp.spv"),
+        )?;
+        pipeline_2d.set_debug_name("Noise 2D Pipeline");
+        let pipeline_3d = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/noise_3d.comp.spv"),
+        )?;
+        pipeline_3d.set_debug_name("Noise 3D Pipeline");
+
+        Ok(Self {
+            cache: HashMap::new(),
+            one_time_submit,
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline_2d,
+            pipeline_3d,
+            render_device,
+        })
+    }
+
+    /// Get the noise texture for the given parameters, generating it if it
+    /// isn't already cached.
+    ///
+    /// The returned texture is in the SHADER_READ_ONLY_OPTIMAL layout and is
+    /// ready to be sampled. Generation blocks until the GPU finishes.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the returned texture must be dropped before the render device
+    pub unsafe fn get_or_generate(
+        &mut self,
+        params: NoiseParams,
+    ) -> Result<Arc<NoiseTexture>, GraphicsError> {
+        if let Some(texture) = self.cache.get(&params) {
+            return Ok(texture.clone());
+        }
+        let texture = Arc::new(self.generate(params)?);
+        self.cache.insert(params, texture.clone());
+        Ok(texture)
+    }
+
+    /// Drop the generator's references to every cached texture.
+    ///
+    /// Textures which are still referenced elsewhere are kept alive.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+// Private API
+// -----------
+
+impl NoiseGenerator {
+    /// The 2D compute shader's workgroup size in x and y.
+    const WORKGROUP_SIZE_2D: u32 = 8;
+
+    /// The 3D compute shader's workgroup size in x, y, and z.
+    const WORKGROUP_SIZE_3D: u32 = 4;
+
+    /// Generate a new noise texture and block until it is ready.
+    unsafe fn generate(
+        &mut self,
+        params: NoiseParams,
+    ) -> Result<NoiseTexture, GraphicsError> {
+        let (image, image_view) = self.create_image(&params)?;
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            self.render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&self.descriptor_set_layout])?;
+        let image_info = vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: image_view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        self.render_device.device().update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            }],
+            &[],
+        );
+
+        let device = self.render_device.device();
+        let command_buffer = self.one_time_submit.command_buffer();
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        let barrier_before = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::TOP_OF_PIPE,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_before,
+                ..Default::default()
+            },
+        );
+
+        let (pipeline, group_counts) = if params.is_3d() {
+            let size = Self::WORKGROUP_SIZE_3D;
+            (
+                &self.pipeline_3d,
+                [
+                    (params.extent.width + size - 1) / size,
+                    (params.extent.height + size - 1) / size,
+                    (params.extent.depth + size - 1) / size,
+                ],
+            )
+        } else {
+            let size = Self::WORKGROUP_SIZE_2D;
+            (
+                &self.pipeline_2d,
+                [
+                    (params.extent.width + size - 1) / size,
+                    (params.extent.height + size - 1) / size,
+                    1,
+                ],
+            )
+        };
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        let constants = NoiseConstants {
+            kind: params.kind as u32,
+            frequency: params.frequency.max(1),
+            octaves: params.octaves.max(1),
+            seed: params.seed,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const NoiseConstants as *const u8,
+                std::mem::size_of::<NoiseConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            group_counts[0],
+            group_counts[1],
+            group_counts[2],
+        );
+
+        let barrier_after = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            image: image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_after,
+                ..Default::default()
+            },
+        );
+
+        // The descriptor pool can be dropped as soon as the commands finish.
+        self.one_time_submit.sync_submit_and_reset()?;
+
+        Ok(NoiseTexture {
+            params,
+            image_view,
+            image,
+        })
+    }
+
+    /// Create the storage image and view for a noise texture.
+    unsafe fn create_image(
+        &self,
+        params: &NoiseParams,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let (image_type, view_type) = if params.is_3d() {
+            (vk::ImageType::TYPE_3D, vk::ImageViewType::TYPE_3D)
+        } else {
+            (vk::ImageType::TYPE_2D, vk::ImageViewType::TYPE_2D)
+        };
+        let queue_family_index =
+            self.render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: params.extent,
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            self.render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name(format!("{:?} Noise", params.kind));
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type,
+            format: Self::FORMAT,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        let image_view = raii::ImageView::new(
+            self.render_device.clone(),
+            &view_create_info,
+        )?;
+        Ok((image, image_view))
+    }
+}
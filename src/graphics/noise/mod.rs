@@ -0,0 +1,13 @@
+//! Tileable Perlin, simplex, and curl noise generated with compute shaders.
+//!
+//! The NoiseGenerator renders noise into 2D or 3D storage images on demand
+//! and caches the results by their parameters, so sketches can ask for the
+//! same noise every frame without regenerating it.
+
+mod generator;
+mod params;
+
+pub use self::{
+    generator::{NoiseGenerator, NoiseTexture},
+    params::{NoiseKind, NoiseParams},
+};
@@ -0,0 +1,109 @@
+use ash::vk;
+
+/// The noise function evaluated by the NoiseGenerator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum NoiseKind {
+    /// Classic gradient noise. Every channel holds the same signed value.
+    Perlin = 0,
+
+    /// Simplex noise. Every channel holds the same signed value.
+    Simplex = 1,
+
+    /// A divergence-free vector field computed from the curl of Perlin noise.
+    /// 2D textures store the field in rg, 3D textures store it in rgb.
+    Curl = 2,
+}
+
+/// Parameters which fully describe a generated noise texture.
+///
+/// Textures are cached by their parameters, so every field is an integer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NoiseParams {
+    /// The noise function.
+    pub kind: NoiseKind,
+
+    /// The size of the texture in texels. A depth of 1 creates a 2D texture,
+    /// anything larger creates a 3D texture.
+    pub extent: vk::Extent3D,
+
+    /// The number of noise cells across the texture for the first octave. The
+    /// texture tiles seamlessly in every dimension.
+    pub frequency: u32,
+
+    /// The number of octaves of fractal noise. Each octave doubles the
+    /// frequency and halves the amplitude.
+    pub octaves: u32,
+
+    /// Different seeds produce different, uncorrelated noise.
+    pub seed: u32,
+}
+
+impl NoiseParams {
+    /// Parameters for a 2D noise texture.
+    pub fn new_2d(kind: NoiseKind, width: u32, height: u32) -> Self {
+        Self {
+            kind,
+            extent: vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Parameters for a 3D noise texture.
+    pub fn new_3d(
+        kind: NoiseKind,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) -> Self {
+        Self {
+            kind,
+            extent: vk::Extent3D {
+                width,
+                height,
+                depth,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Set the number of noise cells across the texture.
+    pub fn frequency(self, frequency: u32) -> Self {
+        Self { frequency, ..self }
+    }
+
+    /// Set the number of octaves.
+    pub fn octaves(self, octaves: u32) -> Self {
+        Self { octaves, ..self }
+    }
+
+    /// Set the seed.
+    pub fn seed(self, seed: u32) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// True when the parameters describe a 3D texture.
+    pub fn is_3d(&self) -> bool {
+        self.extent.depth > 1
+    }
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Perlin,
+            extent: vk::Extent3D {
+                width: 256,
+                height: 256,
+                depth: 1,
+            },
+            frequency: 4,
+            octaves: 4,
+            seed: 0,
+        }
+    }
+}
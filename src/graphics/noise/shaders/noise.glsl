@@ -0,0 +1,167 @@
+// Shared noise functions for the noise compute shaders.
+//
+// The including shader must declare the NoiseConstants push constants as
+// `params` before including this file.
+
+const uint PERLIN = 0;
+const uint SIMPLEX = 1;
+const uint CURL = 2;
+
+// PCG-style 3D hash.
+uvec3 pcg3d(uvec3 v) {
+    v = v * 1664525u + 1013904223u;
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    v ^= v >> 16u;
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    return v;
+}
+
+// A pseudo-random unit gradient for a lattice point. The lattice wraps at the
+// period, which is what makes the noise tile.
+vec3 gradient(ivec3 lattice, ivec3 period, uint seed) {
+    uvec3 wrapped = uvec3(((lattice % period) + period) % period);
+    uvec3 h = pcg3d(wrapped + seed * uvec3(73856093u, 19349663u, 83492791u));
+    vec3 g = vec3(h & 0xFFFFu) / 32767.5 - 1.0;
+    return normalize(g + vec3(1e-6));
+}
+
+vec3 quintic(vec3 t) {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+// Periodic gradient noise in roughly [-1, 1].
+float perlin(vec3 p, ivec3 period, uint seed) {
+    ivec3 cell = ivec3(floor(p));
+    vec3 f = fract(p);
+    vec3 u = quintic(f);
+
+    float n000 = dot(gradient(cell + ivec3(0, 0, 0), period, seed), f - vec3(0, 0, 0));
+    float n100 = dot(gradient(cell + ivec3(1, 0, 0), period, seed), f - vec3(1, 0, 0));
+    float n010 = dot(gradient(cell + ivec3(0, 1, 0), period, seed), f - vec3(0, 1, 0));
+    float n110 = dot(gradient(cell + ivec3(1, 1, 0), period, seed), f - vec3(1, 1, 0));
+    float n001 = dot(gradient(cell + ivec3(0, 0, 1), period, seed), f - vec3(0, 0, 1));
+    float n101 = dot(gradient(cell + ivec3(1, 0, 1), period, seed), f - vec3(1, 0, 1));
+    float n011 = dot(gradient(cell + ivec3(0, 1, 1), period, seed), f - vec3(0, 1, 1));
+    float n111 = dot(gradient(cell + ivec3(1, 1, 1), period, seed), f - vec3(1, 1, 1));
+
+    float nx00 = mix(n000, n100, u.x);
+    float nx10 = mix(n010, n110, u.x);
+    float nx01 = mix(n001, n101, u.x);
+    float nx11 = mix(n011, n111, u.x);
+    float nxy0 = mix(nx00, nx10, u.y);
+    float nxy1 = mix(nx01, nx11, u.y);
+    return mix(nxy0, nxy1, u.z) * 1.1547;
+}
+
+// Simplex noise by Ian McEwan and Stefan Gustavson (MIT license).
+vec4 mod289(vec4 x) { return x - floor(x * (1.0 / 289.0)) * 289.0; }
+vec3 mod289(vec3 x) { return x - floor(x * (1.0 / 289.0)) * 289.0; }
+vec4 permute(vec4 x) { return mod289(((x * 34.0) + 1.0) * x); }
+vec4 taylor_inv_sqrt(vec4 r) { return 1.79284291400159 - 0.85373472095314 * r; }
+
+float simplex(vec3 v) {
+    const vec2 C = vec2(1.0 / 6.0, 1.0 / 3.0);
+    const vec4 D = vec4(0.0, 0.5, 1.0, 2.0);
+
+    vec3 i = floor(v + dot(v, C.yyy));
+    vec3 x0 = v - i + dot(i, C.xxx);
+
+    vec3 g = step(x0.yzx, x0.xyz);
+    vec3 l = 1.0 - g;
+    vec3 i1 = min(g.xyz, l.zxy);
+    vec3 i2 = max(g.xyz, l.zxy);
+
+    vec3 x1 = x0 - i1 + C.xxx;
+    vec3 x2 = x0 - i2 + C.yyy;
+    vec3 x3 = x0 - D.yyy;
+
+    i = mod289(i);
+    vec4 p = permute(permute(permute(
+                i.z + vec4(0.0, i1.z, i2.z, 1.0))
+            + i.y + vec4(0.0, i1.y, i2.y, 1.0))
+        + i.x + vec4(0.0, i1.x, i2.x, 1.0));
+
+    float n_ = 0.142857142857;
+    vec3 ns = n_ * D.wyz - D.xzx;
+
+    vec4 j = p - 49.0 * floor(p * ns.z * ns.z);
+    vec4 x_ = floor(j * ns.z);
+    vec4 y_ = floor(j - 7.0 * x_);
+
+    vec4 x = x_ * ns.x + ns.yyyy;
+    vec4 y = y_ * ns.x + ns.yyyy;
+    vec4 h = 1.0 - abs(x) - abs(y);
+
+    vec4 b0 = vec4(x.xy, y.xy);
+    vec4 b1 = vec4(x.zw, y.zw);
+
+    vec4 s0 = floor(b0) * 2.0 + 1.0;
+    vec4 s1 = floor(b1) * 2.0 + 1.0;
+    vec4 sh = -step(h, vec4(0.0));
+
+    vec4 a0 = b0.xzyw + s0.xzyw * sh.xxyy;
+    vec4 a1 = b1.xzyw + s1.xzyw * sh.zzww;
+
+    vec3 p0 = vec3(a0.xy, h.x);
+    vec3 p1 = vec3(a0.zw, h.y);
+    vec3 p2 = vec3(a1.xy, h.z);
+    vec3 p3 = vec3(a1.zw, h.w);
+
+    vec4 norm = taylor_inv_sqrt(
+        vec4(dot(p0, p0), dot(p1, p1), dot(p2, p2), dot(p3, p3)));
+    p0 *= norm.x;
+    p1 *= norm.y;
+    p2 *= norm.z;
+    p3 *= norm.w;
+
+    vec4 m = max(
+        0.6 - vec4(dot(x0, x0), dot(x1, x1), dot(x2, x2), dot(x3, x3)),
+        0.0);
+    m = m * m;
+    return 42.0 * dot(
+        m * m, vec4(dot(p0, x0), dot(p1, x1), dot(p2, x2), dot(p3, x3)));
+}
+
+// Simplex noise isn't periodic on a rectangular lattice, so the tiled version
+// blends samples offset by one period in each tiled dimension. The result
+// matches exactly across tile boundaries.
+float tiled_simplex(vec3 p, vec3 period, bool tile_z, uint seed) {
+    vec3 offset = vec3(seed % 289u, (seed / 289u) % 289u, 0.0) * 17.0;
+    vec3 w = p / period;
+    float result = 0.0;
+    for (int z = 0; z < (tile_z ? 2 : 1); z++) {
+        for (int y = 0; y < 2; y++) {
+            for (int x = 0; x < 2; x++) {
+                vec3 corner = vec3(x, y, z);
+                vec3 weight = mix(1.0 - w, w, corner);
+                float wz = tile_z ? weight.z : 1.0;
+                result += simplex(p - corner * period + offset)
+                    * weight.x * weight.y * wz;
+            }
+        }
+    }
+    return result;
+}
+
+float fbm(vec3 p, bool tile_z, uint seed) {
+    float value = 0.0;
+    float amplitude = 0.5;
+    float total_amplitude = 0.0;
+    int frequency = int(params.frequency);
+    for (uint octave = 0; octave < params.octaves; octave++) {
+        ivec3 period = ivec3(frequency, frequency, tile_z ? frequency : 1);
+        vec3 sample_point = p * float(frequency);
+        float n = params.kind == SIMPLEX
+            ? tiled_simplex(sample_point, vec3(period), tile_z, seed + octave)
+            : perlin(sample_point, period, seed + octave);
+        value += n * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2;
+    }
+    return value / total_amplitude;
+}
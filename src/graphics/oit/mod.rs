@@ -0,0 +1,11 @@
+//! Weighted-blended order-independent transparency.
+//!
+//! The OitPass renders opaque geometry, accumulates transparent geometry into
+//! weighted color and revealage targets, then the OitComposite blends the
+//! result over the opaque image. Transparent geometry can be drawn in any
+//! order, so particle and glass-heavy sketches don't need to sort.
+
+mod oit_composite;
+mod oit_pass;
+
+pub use self::{oit_composite::OitComposite, oit_pass::OitPass};
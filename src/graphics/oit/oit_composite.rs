@@ -0,0 +1,158 @@
+use {
+    super::OitPass,
+    crate::graphics::{
+        vulkan_api::{raii, Frame, GraphicsPipelineBuilder, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A fullscreen pass which runs in the OitPass's composite subpass and blends
+/// the accumulated transparent geometry over the opaque image.
+pub struct OitComposite {
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+impl OitComposite {
+    /// Create the composite pipeline and a descriptor set which references
+    /// the OIT pass's accumulation and revealage targets.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the OitPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        oit_pass: &OitPass,
+    ) -> Result<Self, GraphicsError> {
+        let bindings = [0, 1].map(|binding| vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..vk::DescriptorSetLayoutBinding::default()
+        });
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[],
+            )?;
+        let pipeline = GraphicsPipelineBuilder::new()
+            .vertex_shader(include_bytes!("./shaders/composite.vert.spv"))
+            .fragment_shader(include_bytes!("./shaders/composite.frag.spv"))
+            .color_blend_attachment(
+                GraphicsPipelineBuilder::alpha_blend_attachment(),
+            )
+            .subpass(OitPass::COMPOSITE_SUBPASS)
+            .build(
+                render_device.clone(),
+                &pipeline_layout,
+                oit_pass.render_pass(),
+            )?;
+        pipeline.set_debug_name("OIT Composite Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::INPUT_ATTACHMENT,
+                descriptor_count: 2,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        let image_infos =
+            [oit_pass.accumulation_view(), oit_pass.revealage_view()].map(
+                |image_view| vk::DescriptorImageInfo {
+                    sampler: vk::Sampler::null(),
+                    image_view: image_view.raw(),
+                    image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                },
+            );
+        let writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, image_info)| vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+                descriptor_count: 1,
+                p_image_info: image_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            extent: oit_pass.extent(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Add commands to composite the transparent geometry over every pixel.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the OitPass must already be in the composite subpass.
+    pub unsafe fn draw(&self, frame: &Frame) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            frame.command_buffer(),
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+
+        // A single triangle which covers the entire screen.
+        device.cmd_draw(frame.command_buffer(), 3, 1, 0, 0);
+    }
+}
@@ -0,0 +1,544 @@
+use {
+    crate::graphics::{
+        vulkan_api::{
+            raii, Frame, GraphicsPipelineBuilder, RenderDevice, Swapchain,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A render pass for weighted-blended order-independent transparency.
+///
+/// The pass has three subpasses:
+///
+/// 1. Opaque geometry is rendered into the swapchain image and depth buffer.
+/// 2. Transparent geometry is depth-tested against the opaque geometry and
+///    accumulated into the accumulation and revealage targets. Pipelines for
+///    this subpass should use `accumulation_blend_attachments` and disable
+///    depth writes.
+/// 3. The OitComposite pass blends the accumulated color over the opaque
+///    image.
+///
+/// Transparent fragment shaders write two outputs:
+///
+/// ```glsl
+/// layout(location = 0) out vec4 out_accumulation;
+/// layout(location = 1) out float out_revealage;
+///
+/// void main() {
+///     vec4 color = ...; // premultiplied alpha is not required
+///     float weight = clamp(
+///         pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 *
+///         pow(1.0 - gl_FragCoord.z * 0.9, 3.0),
+///         1e-2,
+///         3e3
+///     );
+///     out_accumulation = vec4(color.rgb * color.a, color.a) * weight;
+///     out_revealage = color.a;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct OitPass {
+    extent: vk::Extent2D,
+    format: vk::Format,
+    render_pass: raii::RenderPass,
+    framebuffers: Vec<raii::Framebuffer>,
+    _image_views: Vec<raii::ImageView>,
+    _depth: (raii::Image, raii::ImageView),
+    accumulation: (raii::Image, raii::ImageView),
+    revealage: (raii::Image, raii::ImageView),
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl OitPass {
+    /// The subpass for opaque geometry.
+    pub const OPAQUE_SUBPASS: u32 = 0;
+
+    /// The subpass for transparent geometry.
+    pub const ACCUMULATE_SUBPASS: u32 = 1;
+
+    /// The subpass which composites transparent geometry over the opaque
+    /// geometry.
+    pub const COMPOSITE_SUBPASS: u32 = 2;
+
+    /// The format of the shared depth buffer.
+    pub const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+    /// The format of the weighted color accumulation target.
+    pub const ACCUMULATION_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The format of the revealage target.
+    pub const REVEALAGE_FORMAT: vk::Format = vk::Format::R16_SFLOAT;
+
+    /// Create the render pass, OIT targets, and framebuffers for every
+    /// swapchain image.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the framebuffers are only valid while the swapchain exists
+    ///  - if the swapchain is rebuilt, the OitPass should be destroyed and
+    ///    rebuilt too
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        swapchain: &Swapchain,
+    ) -> Result<Self, GraphicsError> {
+        let extent = swapchain.extent();
+        let depth = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::DEPTH_FORMAT,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::ImageAspectFlags::DEPTH,
+        )?;
+        depth.0.set_debug_name("OIT Depth");
+
+        let target_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | vk::ImageUsageFlags::INPUT_ATTACHMENT;
+        let accumulation = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::ACCUMULATION_FORMAT,
+            target_usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        accumulation.0.set_debug_name("OIT Accumulation");
+
+        let revealage = Self::create_attachment(
+            &render_device,
+            extent,
+            Self::REVEALAGE_FORMAT,
+            target_usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        revealage.0.set_debug_name("OIT Revealage");
+
+        let render_pass = Self::create_render_pass(
+            render_device.clone(),
+            swapchain.image_format(),
+        )?;
+
+        let mut image_views = vec![];
+        let mut framebuffers = vec![];
+        for image in swapchain.images() {
+            let create_info = vk::ImageViewCreateInfo {
+                image: *image,
+                format: swapchain.image_format(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                ..Default::default()
+            };
+            let image_view =
+                raii::ImageView::new(render_device.clone(), &create_info)?;
+
+            let attachments = [
+                image_view.raw(),
+                depth.1.raw(),
+                accumulation.1.raw(),
+                revealage.1.raw(),
+            ];
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass: render_pass.raw(),
+                attachment_count: attachments.len() as u32,
+                p_attachments: attachments.as_ptr(),
+                width: extent.width,
+                height: extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            framebuffers.push(raii::Framebuffer::new(
+                render_device.clone(),
+                &create_info,
+            )?);
+            image_views.push(image_view);
+        }
+
+        Ok(Self {
+            extent,
+            format: swapchain.image_format(),
+            render_pass,
+            framebuffers,
+            _image_views: image_views,
+            _depth: depth,
+            accumulation,
+            revealage,
+            render_device,
+        })
+    }
+
+    /// The blend states required by pipelines in the accumulate subpass.
+    ///
+    /// The accumulation target is additively blended and the revealage target
+    /// is multiplied by `1 - alpha` for every fragment.
+    pub fn accumulation_blend_attachments(
+    ) -> [vk::PipelineColorBlendAttachmentState; 2] {
+        [
+            vk::PipelineColorBlendAttachmentState {
+                color_write_mask: vk::ColorComponentFlags::RGBA,
+                blend_enable: vk::TRUE,
+                src_color_blend_factor: vk::BlendFactor::ONE,
+                dst_color_blend_factor: vk::BlendFactor::ONE,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ONE,
+                dst_alpha_blend_factor: vk::BlendFactor::ONE,
+                alpha_blend_op: vk::BlendOp::ADD,
+            },
+            vk::PipelineColorBlendAttachmentState {
+                color_write_mask: vk::ColorComponentFlags::R,
+                blend_enable: vk::TRUE,
+                src_color_blend_factor: vk::BlendFactor::ZERO,
+                dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_COLOR,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ZERO,
+                dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                alpha_blend_op: vk::BlendOp::ADD,
+            },
+        ]
+    }
+
+    /// A pipeline builder configured for the accumulate subpass: OIT blend
+    /// states, depth testing against the opaque geometry, and no depth writes.
+    pub fn accumulate_pipeline_builder<'a>() -> GraphicsPipelineBuilder<'a> {
+        let [accumulation, revealage] = Self::accumulation_blend_attachments();
+        GraphicsPipelineBuilder::new()
+            .color_blend_attachment(accumulation)
+            .color_blend_attachment(revealage)
+            .depth_test(true)
+            .depth_write(false)
+            .subpass(Self::ACCUMULATE_SUBPASS)
+    }
+
+    /// The current extent.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The swapchain image format.
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// The current render pass.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// The weighted color accumulation target's image view.
+    pub fn accumulation_view(&self) -> &raii::ImageView {
+        &self.accumulation.1
+    }
+
+    /// The revealage target's image view.
+    pub fn revealage_view(&self) -> &raii::ImageView {
+        &self.revealage.1
+    }
+
+    /// Begin the render pass for the frame's swapchain image. Commands
+    /// recorded after this call target the opaque subpass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the OitPass must not be destroyed until the command buffer finishes
+    ///     executing or is discarded.
+    pub unsafe fn begin_render_pass_inline(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+    ) {
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [1.0, 1.0, 1.0, 1.0],
+                },
+            },
+        ];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffers[frame.swapchain_image_index()].raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+
+    /// Advance to the next subpass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the render pass must have been started with
+    ///     `begin_render_pass_inline` and must not be in the last subpass.
+    pub unsafe fn next_subpass_inline(&self, frame: &Frame) {
+        self.render_device.device().cmd_next_subpass(
+            frame.command_buffer(),
+            vk::SubpassContents::INLINE,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl OitPass {
+    /// Create a single device-local attachment image and a view for it.
+    unsafe fn create_attachment(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        let image_view =
+            raii::ImageView::new(render_device.clone(), &view_create_info)?;
+        Ok((image, image_view))
+    }
+
+    /// Create the three-subpass render pass.
+    ///
+    /// Attachments are ordered: swapchain color, depth, accumulation,
+    /// revealage.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+        format: vk::Format,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let transient_attachment =
+            |attachment_format: vk::Format, final_layout: vk::ImageLayout| {
+                vk::AttachmentDescription {
+                    format: attachment_format,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    load_op: vk::AttachmentLoadOp::CLEAR,
+                    store_op: vk::AttachmentStoreOp::DONT_CARE,
+                    stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                    stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                    initial_layout: vk::ImageLayout::UNDEFINED,
+                    final_layout,
+                    flags: vk::AttachmentDescriptionFlags::empty(),
+                }
+            };
+        let attachments = [
+            // The swapchain color attachment
+            vk::AttachmentDescription {
+                format,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            transient_attachment(
+                Self::DEPTH_FORMAT,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            ),
+            transient_attachment(
+                Self::ACCUMULATION_FORMAT,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            transient_attachment(
+                Self::REVEALAGE_FORMAT,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+        ];
+
+        let swapchain_attachment = [vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let depth_attachment = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let target_attachments =
+            [2, 3].map(|attachment| vk::AttachmentReference {
+                attachment,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            });
+        let target_inputs = [2, 3].map(|attachment| vk::AttachmentReference {
+            attachment,
+            layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        });
+        let subpasses = [
+            // opaque
+            vk::SubpassDescription {
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                color_attachment_count: swapchain_attachment.len() as u32,
+                p_color_attachments: swapchain_attachment.as_ptr(),
+                p_depth_stencil_attachment: &depth_attachment,
+                ..Default::default()
+            },
+            // accumulate
+            vk::SubpassDescription {
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                color_attachment_count: target_attachments.len() as u32,
+                p_color_attachments: target_attachments.as_ptr(),
+                p_depth_stencil_attachment: &depth_attachment,
+                ..Default::default()
+            },
+            // composite
+            vk::SubpassDescription {
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                input_attachment_count: target_inputs.len() as u32,
+                p_input_attachments: target_inputs.as_ptr(),
+                color_attachment_count: swapchain_attachment.len() as u32,
+                p_color_attachments: swapchain_attachment.as_ptr(),
+                ..Default::default()
+            },
+        ];
+
+        let dependencies = [
+            // The swapchain image is acquired with a semaphore which waits at
+            // COLOR_ATTACHMENT_OUTPUT. The depth and OIT targets are shared
+            // between frames in flight, so also wait for the previous frame
+            // to finish with them.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: Self::OPAQUE_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: Self::ACCUMULATE_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // Transparent geometry is tested against opaque depth.
+            vk::SubpassDependency {
+                src_subpass: Self::OPAQUE_SUBPASS,
+                dst_subpass: Self::ACCUMULATE_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            },
+            // The composite blends over the opaque color.
+            vk::SubpassDependency {
+                src_subpass: Self::OPAQUE_SUBPASS,
+                dst_subpass: Self::COMPOSITE_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            },
+            // The composite reads the accumulated targets.
+            vk::SubpassDependency {
+                src_subpass: Self::ACCUMULATE_SUBPASS,
+                dst_subpass: Self::COMPOSITE_SUBPASS,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::INPUT_ATTACHMENT_READ,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            },
+        ];
+
+        let create_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
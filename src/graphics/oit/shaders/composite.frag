@@ -0,0 +1,22 @@
+#version 460
+
+layout(input_attachment_index = 0, set = 0, binding = 0)
+    uniform subpassInput accumulation_input;
+layout(input_attachment_index = 1, set = 0, binding = 1)
+    uniform subpassInput revealage_input;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    float revealage = subpassLoad(revealage_input).r;
+    if (revealage >= 1.0) {
+        // no transparent fragments touched this pixel
+        discard;
+    }
+
+    vec4 accumulation = subpassLoad(accumulation_input);
+    vec3 average_color = accumulation.rgb / max(accumulation.a, 1e-5);
+
+    // blended over the opaque image with standard alpha blending
+    out_color = vec4(average_color, 1.0 - revealage);
+}
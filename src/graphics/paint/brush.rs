@@ -0,0 +1,54 @@
+/// The round brush used to paint strokes on a PaintCanvas.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Brush {
+    /// The stamp radius, in canvas pixels.
+    pub radius: f32,
+
+    /// The paint color. Alpha controls how much each stamp covers what's
+    /// already on the canvas.
+    pub color: [f32; 4],
+
+    /// How much of the radius is fully opaque, in [0, 1]. Lower values give
+    /// a softer edge.
+    pub hardness: f32,
+
+    /// The distance between stamps along a stroke, as a fraction of the
+    /// radius. Smaller values give smoother strokes at the cost of more
+    /// stamps.
+    pub spacing: f32,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            radius: 8.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            hardness: 0.5,
+            spacing: 0.25,
+        }
+    }
+}
+
+/// A single brush stamp.
+///
+/// Must match the Stamp struct in `shaders/paint_stamps.comp`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub(super) struct Stamp {
+    pub center: [f32; 2],
+    pub radius: f32,
+    pub hardness: f32,
+    pub color: [f32; 4],
+}
+
+impl Stamp {
+    pub fn new(brush: &Brush, center: [f32; 2]) -> Self {
+        Self {
+            center,
+            radius: brush.radius.max(0.5),
+            // The shader's smoothstep needs a non-empty edge.
+            hardness: brush.hardness.clamp(0.0, 0.99),
+            color: brush.color,
+        }
+    }
+}
@@ -0,0 +1,748 @@
+use {
+    super::{brush::Stamp, Brush},
+    crate::graphics::{
+        vulkan_api::{
+            raii, read_image_to_rgba8, save_rgba8_png, Frame, FramesInFlight,
+            OneTimeSubmitCommandBuffer, RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::{collections::VecDeque, path::Path, sync::Arc},
+};
+
+/// Push constants for the stamp compute shader. Must match StampConstants in
+/// `shaders/paint_stamps.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct StampConstants {
+    origin: [i32; 2],
+    size: [u32; 2],
+    first_stamp: u32,
+    stamp_count: u32,
+}
+
+/// Work queued by the CPU API and recorded by `record`, in order.
+#[derive(Debug, Copy, Clone)]
+enum CanvasOp {
+    Stamp(Stamp),
+    Snapshot,
+    Undo,
+    Clear([f32; 4]),
+}
+
+/// A persistent image which brushes paint into with compute shaders.
+///
+/// Strokes are built on the CPU from pointer positions with `begin_stroke`
+/// and `stroke_to`, which place evenly-spaced brush stamps along the path.
+/// Nothing touches the GPU until `record` is called for a frame, which
+/// applies every queued stamp, clear, and undo in order.
+///
+/// Each stroke saves an undo snapshot on the GPU before it paints. The
+/// oldest snapshot is reused once `max_undo_levels` are saved.
+///
+/// The canvas stays in the GENERAL layout, so it can be sampled for display
+/// or read back with `read_back_to_image`.
+pub struct PaintCanvas {
+    /// The brush used by new stamps.
+    pub brush: Brush,
+
+    extent: vk::Extent2D,
+    pending: Vec<CanvasOp>,
+    last_position: Option<[f32; 2]>,
+    carried_distance: f32,
+    max_undo_levels: usize,
+    snapshots: VecDeque<raii::Image>,
+    spare_snapshots: Vec<raii::Image>,
+    stamp_buffers: Vec<raii::Buffer>,
+    stamp_ptrs: Vec<*mut Stamp>,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    image_view: raii::ImageView,
+    image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl PaintCanvas {
+    /// The canvas format.
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    /// The most stamps applied by a single call to `record`. Extra stamps
+    /// wait for the next frame.
+    pub const MAX_STAMPS_PER_FRAME: usize = 4096;
+
+    /// Create a canvas and fill it with a color. Blocks until the GPU
+    /// finishes the clear.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - one stamp buffer is kept per frame
+    /// * `extent` - the canvas size, in pixels
+    /// * `background` - the initial color of every pixel
+    /// * `max_undo_levels` - the number of snapshots kept for undo
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        extent: vk::Extent2D,
+        background: [f32; 4],
+        max_undo_levels: usize,
+    ) -> Result<Self, GraphicsError> {
+        let image = Self::create_image(
+            &render_device,
+            extent,
+            vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST,
+        )?;
+        image.set_debug_name("Paint Canvas");
+        let image_view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut stamp_buffers = Vec::with_capacity(frame_count);
+        let mut stamp_ptrs = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let (buffer, ptr) = Self::allocate_stamp_buffer(&render_device)?;
+            stamp_buffers.push(buffer);
+            stamp_ptrs.push(ptr);
+        }
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 1,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                ],
+            )?;
+        let set_count = frame_count as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            set_count,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: set_count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: set_count,
+                },
+            ],
+        )?;
+        let layouts = (0..set_count)
+            .map(|_| &descriptor_set_layout)
+            .collect::<Vec<_>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        let image_info = vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: image_view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        for (index, buffer) in stamp_buffers.iter().enumerate() {
+            let dst_set = descriptor_pool.descriptor_set(index);
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            };
+            render_device.device().update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 0,
+                        dst_array_element: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                        descriptor_count: 1,
+                        p_image_info: &image_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                    vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: 1,
+                        dst_array_element: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        p_buffer_info: &buffer_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                ],
+                &[],
+            );
+        }
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<StampConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/paint_stamps.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Paint Stamps Pipeline");
+
+        let canvas = Self {
+            brush: Brush::default(),
+            extent,
+            pending: vec![],
+            last_position: None,
+            carried_distance: 0.0,
+            max_undo_levels,
+            snapshots: VecDeque::with_capacity(max_undo_levels),
+            spare_snapshots: vec![],
+            stamp_buffers,
+            stamp_ptrs,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            image_view,
+            image,
+            render_device,
+        };
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            canvas.render_device.clone(),
+            canvas.render_device.graphics_queue().clone(),
+        )?;
+        let command_buffer = one_time_submit.command_buffer();
+        canvas.image_barrier(command_buffer, vk::ImageLayout::UNDEFINED);
+        canvas.cmd_clear(command_buffer, background);
+        one_time_submit.sync_submit_and_reset()?;
+
+        Ok(canvas)
+    }
+
+    /// The canvas size, in pixels.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// A view of the canvas, always in the GENERAL layout.
+    pub fn image_view(&self) -> &raii::ImageView {
+        &self.image_view
+    }
+
+    /// The canvas image.
+    pub fn image(&self) -> &raii::Image {
+        &self.image
+    }
+
+    /// The number of strokes which can currently be undone.
+    pub fn undo_levels(&self) -> usize {
+        let queued = self
+            .pending
+            .iter()
+            .map(|op| match op {
+                CanvasOp::Snapshot => 1,
+                CanvasOp::Undo => -1,
+                _ => 0,
+            })
+            .sum::<isize>();
+        (self.snapshots.len() as isize + queued)
+            .clamp(0, self.max_undo_levels as isize) as usize
+    }
+
+    /// Start a stroke at a position, in canvas pixels, and stamp the brush
+    /// there. The canvas is snapshotted first so the stroke can be undone.
+    pub fn begin_stroke(&mut self, position: [f32; 2]) {
+        if self.max_undo_levels > 0 {
+            self.pending.push(CanvasOp::Snapshot);
+        }
+        self.pending
+            .push(CanvasOp::Stamp(Stamp::new(&self.brush, position)));
+        self.last_position = Some(position);
+        self.carried_distance = 0.0;
+    }
+
+    /// Continue the current stroke to a new position, stamping the brush at
+    /// even intervals along the way. Starts a new stroke if there isn't one.
+    pub fn stroke_to(&mut self, position: [f32; 2]) {
+        let start = match self.last_position {
+            Some(start) => start,
+            None => return self.begin_stroke(position),
+        };
+        let delta = [position[0] - start[0], position[1] - start[1]];
+        let length = (delta[0] * delta[0] + delta[1] * delta[1]).sqrt();
+        let spacing = (self.brush.radius * self.brush.spacing).max(1.0);
+
+        // The distance along the segment to the next stamp.
+        let mut distance = spacing - self.carried_distance;
+        while distance <= length {
+            let t = distance / length;
+            let center = [start[0] + delta[0] * t, start[1] + delta[1] * t];
+            self.pending
+                .push(CanvasOp::Stamp(Stamp::new(&self.brush, center)));
+            distance += spacing;
+        }
+        self.carried_distance = length - (distance - spacing);
+        self.last_position = Some(position);
+    }
+
+    /// Finish the current stroke.
+    pub fn end_stroke(&mut self) {
+        self.last_position = None;
+        self.carried_distance = 0.0;
+    }
+
+    /// Fill the whole canvas with a color. This can be undone.
+    pub fn clear(&mut self, color: [f32; 4]) {
+        if self.max_undo_levels > 0 {
+            self.pending.push(CanvasOp::Snapshot);
+        }
+        self.pending.push(CanvasOp::Clear(color));
+    }
+
+    /// Restore the canvas to how it was before the most recent stroke or
+    /// clear. Does nothing when there's nothing to undo.
+    pub fn undo(&mut self) {
+        if self.undo_levels() > 0 {
+            self.end_stroke();
+            self.pending.push(CanvasOp::Undo);
+        }
+    }
+
+    /// Add commands to apply all of the queued work to the canvas.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must be recorded outside of a render pass
+    ///   - the canvas is ready for fragment and compute shader reads after
+    ///     this call
+    pub unsafe fn record(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<(), GraphicsError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let command_buffer = frame.command_buffer();
+        let frame_index = frame.frame_index();
+
+        // SAFE because the frame's previous commands are complete, so its
+        // stamp buffer is no longer in use.
+        let stamps = std::slice::from_raw_parts_mut(
+            self.stamp_ptrs[frame_index],
+            Self::MAX_STAMPS_PER_FRAME,
+        );
+        let mut stamp_count = 0;
+        let mut batch_start = 0;
+        let mut consumed = 0;
+        let pending = std::mem::take(&mut self.pending);
+        for op in &pending {
+            if let CanvasOp::Stamp(stamp) = op {
+                if stamp_count == Self::MAX_STAMPS_PER_FRAME {
+                    break;
+                }
+                stamps[stamp_count] = *stamp;
+                stamp_count += 1;
+                consumed += 1;
+                continue;
+            }
+
+            self.cmd_stamps(
+                command_buffer,
+                frame_index,
+                &stamps[batch_start..stamp_count],
+                batch_start,
+            );
+            batch_start = stamp_count;
+            match *op {
+                CanvasOp::Snapshot => self.cmd_snapshot(command_buffer)?,
+                CanvasOp::Undo => self.cmd_undo(command_buffer),
+                CanvasOp::Clear(color) => {
+                    self.image_barrier(
+                        command_buffer,
+                        vk::ImageLayout::GENERAL,
+                    );
+                    self.cmd_clear(command_buffer, color);
+                }
+                CanvasOp::Stamp(_) => unreachable!(),
+            }
+            consumed += 1;
+        }
+        self.cmd_stamps(
+            command_buffer,
+            frame_index,
+            &stamps[batch_start..stamp_count],
+            batch_start,
+        );
+        self.image_barrier(command_buffer, vk::ImageLayout::GENERAL);
+        self.pending = pending[consumed..].to_vec();
+        Ok(())
+    }
+
+    /// Copy the canvas back to the CPU. Blocks until the GPU finishes the
+    /// copy.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the canvas must not be in use by any in-flight commands
+    pub unsafe fn read_back_to_image(
+        &self,
+    ) -> Result<image::RgbaImage, GraphicsError> {
+        read_image_to_rgba8(
+            self.render_device.clone(),
+            &self.image,
+            Self::FORMAT,
+            self.extent,
+            vk::ImageLayout::GENERAL,
+        )
+    }
+
+    /// Copy the canvas back to the CPU and save it as a PNG.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the canvas must not be in use by any in-flight commands
+    pub unsafe fn save_png(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GraphicsError> {
+        save_rgba8_png(&self.read_back_to_image()?, path)
+    }
+}
+
+// Private API
+// -----------
+
+impl PaintCanvas {
+    /// The compute shader's workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Apply a batch of stamps with a single dispatch over the pixels they
+    /// touch.
+    unsafe fn cmd_stamps(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        stamps: &[Stamp],
+        first_stamp: usize,
+    ) {
+        if stamps.is_empty() {
+            return;
+        }
+        let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+        for stamp in stamps {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(stamp.center[axis] - stamp.radius);
+                max[axis] = max[axis].max(stamp.center[axis] + stamp.radius);
+            }
+        }
+        let origin = [
+            (min[0].floor() as i32).max(0),
+            (min[1].floor() as i32).max(0),
+        ];
+        let end = [
+            (max[0].ceil() as i32).min(self.extent.width as i32),
+            (max[1].ceil() as i32).min(self.extent.height as i32),
+        ];
+        if end[0] <= origin[0] || end[1] <= origin[1] {
+            return;
+        }
+        let size = [(end[0] - origin[0]) as u32, (end[1] - origin[1]) as u32];
+
+        self.image_barrier(command_buffer, vk::ImageLayout::GENERAL);
+
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(frame_index)],
+            &[],
+        );
+        let constants = StampConstants {
+            origin,
+            size,
+            first_stamp: first_stamp as u32,
+            stamp_count: stamps.len() as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const StampConstants as *const u8,
+                std::mem::size_of::<StampConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (size[0] + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            (size[1] + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            1,
+        );
+    }
+
+    /// Copy the canvas into a snapshot, reusing the oldest snapshot when
+    /// there are already `max_undo_levels`.
+    unsafe fn cmd_snapshot(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+    ) -> Result<(), GraphicsError> {
+        let snapshot = if self.snapshots.len() == self.max_undo_levels {
+            self.snapshots.pop_front().unwrap()
+        } else if let Some(spare) = self.spare_snapshots.pop() {
+            spare
+        } else {
+            let image = Self::create_image(
+                &self.render_device,
+                self.extent,
+                vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )?;
+            image.set_debug_name("Paint Canvas Undo Snapshot");
+            self.image_barrier_for(
+                command_buffer,
+                &image,
+                vk::ImageLayout::UNDEFINED,
+            );
+            image
+        };
+        self.image_barrier(command_buffer, vk::ImageLayout::GENERAL);
+        self.image_barrier_for(
+            command_buffer,
+            &snapshot,
+            vk::ImageLayout::GENERAL,
+        );
+        self.cmd_copy(command_buffer, &self.image, &snapshot);
+        self.snapshots.push_back(snapshot);
+        Ok(())
+    }
+
+    /// Copy the newest snapshot back into the canvas.
+    unsafe fn cmd_undo(&mut self, command_buffer: vk::CommandBuffer) {
+        let snapshot = match self.snapshots.pop_back() {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        self.image_barrier(command_buffer, vk::ImageLayout::GENERAL);
+        self.image_barrier_for(
+            command_buffer,
+            &snapshot,
+            vk::ImageLayout::GENERAL,
+        );
+        self.cmd_copy(command_buffer, &snapshot, &self.image);
+
+        // The copy may still be executing, so keep the snapshot alive for
+        // reuse rather than dropping it.
+        self.spare_snapshots.push(snapshot);
+    }
+
+    unsafe fn cmd_copy(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src: &raii::Image,
+        dst: &raii::Image,
+    ) {
+        let layers = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        self.render_device.device().cmd_copy_image(
+            command_buffer,
+            src.raw(),
+            vk::ImageLayout::GENERAL,
+            dst.raw(),
+            vk::ImageLayout::GENERAL,
+            &[vk::ImageCopy {
+                src_subresource: layers,
+                src_offset: vk::Offset3D::default(),
+                dst_subresource: layers,
+                dst_offset: vk::Offset3D::default(),
+                extent: vk::Extent3D {
+                    width: self.extent.width,
+                    height: self.extent.height,
+                    depth: 1,
+                },
+            }],
+        );
+    }
+
+    unsafe fn cmd_clear(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        color: [f32; 4],
+    ) {
+        self.render_device.device().cmd_clear_color_image(
+            command_buffer,
+            self.image.raw(),
+            vk::ImageLayout::GENERAL,
+            &vk::ClearColorValue { float32: color },
+            &[Self::SUBRESOURCE_RANGE],
+        );
+    }
+
+    /// Wait for every earlier read and write of the canvas, then move it
+    /// into the GENERAL layout.
+    unsafe fn image_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        old_layout: vk::ImageLayout,
+    ) {
+        self.image_barrier_for(command_buffer, &self.image, old_layout);
+    }
+
+    /// Wait for every earlier compute, fragment, and transfer access to an
+    /// image, then move it into the GENERAL layout.
+    unsafe fn image_barrier_for(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        image: &raii::Image,
+        old_layout: vk::ImageLayout,
+    ) {
+        let stages = vk::PipelineStageFlags2::COMPUTE_SHADER
+            | vk::PipelineStageFlags2::FRAGMENT_SHADER
+            | vk::PipelineStageFlags2::ALL_TRANSFER;
+        let barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: stages,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE
+                | vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: stages,
+            dst_access_mask: vk::AccessFlags2::SHADER_STORAGE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                | vk::AccessFlags2::SHADER_SAMPLED_READ
+                | vk::AccessFlags2::TRANSFER_READ
+                | vk::AccessFlags2::TRANSFER_WRITE,
+            old_layout,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: image.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create a device-local canvas-sized image.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<raii::Image, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+
+    /// Allocate a host-visible storage buffer for one frame's stamps.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not use the associated memory-mapped pointer
+    ///     once the buffer has been dropped.
+    unsafe fn allocate_stamp_buffer(
+        render_device: &Arc<RenderDevice>,
+    ) -> Result<(raii::Buffer, *mut Stamp), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (Self::MAX_STAMPS_PER_FRAME * std::mem::size_of::<Stamp>())
+                as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Paint Canvas Stamps");
+        let ptr = buffer.allocation().map(render_device.device())?;
+        Ok((buffer, ptr as *mut Stamp))
+    }
+}
@@ -0,0 +1,11 @@
+//! A persistent canvas for paint-style sketches.
+//!
+//! A PaintCanvas keeps an RGBA8 storage image which Brush stamps are
+//! painted into with a compute shader. Pointer positions are turned into
+//! evenly-spaced stamps on the CPU, each stroke can be undone from GPU-side
+//! snapshots, and the canvas can be read back or saved as a PNG.
+
+mod brush;
+mod canvas;
+
+pub use self::{brush::Brush, canvas::PaintCanvas};
@@ -0,0 +1,295 @@
+use {
+    super::ParticleSystem,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+            GraphicsError,
+        },
+        interop::osc::Params,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// How an attractor's strength changes with distance.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Falloff {
+    /// Full strength everywhere inside the radius.
+    Constant = 0,
+
+    /// Fades linearly from full strength at the center to zero at the
+    /// radius.
+    Linear = 1,
+
+    /// Fades smoothly from full strength at the center to zero at the
+    /// radius.
+    Smooth = 2,
+
+    /// Grows with the inverse square of the distance toward the center,
+    /// capped at 100 times full strength. Full strength at the radius.
+    InverseSquare = 3,
+}
+
+/// A point which pulls particles in, or pushes them away.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Attractor {
+    /// The attractor's position in world space.
+    pub position: [f32; 3],
+
+    /// Particles farther away than this are not affected.
+    pub radius: f32,
+
+    /// The acceleration toward the attractor, in world units per second
+    /// squared. Negative values push particles away.
+    pub strength: f32,
+
+    /// How the strength changes with distance.
+    pub falloff: Falloff,
+}
+
+impl Attractor {
+    /// An attractor which pulls particles toward `position`.
+    pub fn attractor(position: [f32; 3], radius: f32, strength: f32) -> Self {
+        Self {
+            position,
+            radius,
+            strength: strength.abs(),
+            falloff: Falloff::Smooth,
+        }
+    }
+
+    /// An attractor which pushes particles away from `position`.
+    pub fn repulsor(position: [f32; 3], radius: f32, strength: f32) -> Self {
+        Self {
+            strength: -strength.abs(),
+            ..Self::attractor(position, radius, strength)
+        }
+    }
+
+    /// Update the attractor from OSC parameters.
+    ///
+    /// Reads the parameters named `{prefix}/x`, `{prefix}/y`, `{prefix}/z`,
+    /// `{prefix}/radius`, and `{prefix}/strength`. Missing parameters leave
+    /// the attractor unchanged.
+    pub fn update_from_params(&mut self, params: &Params, prefix: &str) {
+        let get = |name: &str| params.get(&format!("{}/{}", prefix, name));
+        for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+            if let Some(value) = get(name) {
+                self.position[axis] = value;
+            }
+        }
+        if let Some(radius) = get("radius") {
+            self.radius = radius;
+        }
+        if let Some(strength) = get("strength") {
+            self.strength = strength;
+        }
+    }
+}
+
+/// The GPU representation of an Attractor. Must match Attractor in
+/// `shaders/attractor_force.comp`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct AttractorData {
+    position: [f32; 3],
+    radius: f32,
+    strength: f32,
+    falloff: u32,
+    pad: [u32; 2],
+}
+
+/// Must match AttractorUniforms in `shaders/attractor_force.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct AttractorUniforms {
+    attractor_count: u32,
+    particle_count: u32,
+    dt: f32,
+    pad: u32,
+    attractors: [AttractorData; AttractorForce::MAX_ATTRACTORS],
+}
+
+/// Pulls particles toward attractors and pushes them away from repulsors.
+///
+/// The attractors live in a small per-frame uniform buffer which is
+/// rewritten by every call to `cmd_apply`, so they can follow the mouse or
+/// be driven by OSC each frame.
+pub struct AttractorForce {
+    /// The attractors used by each call to `cmd_apply`. Only the first
+    /// MAX_ATTRACTORS are used.
+    pub attractors: Vec<Attractor>,
+
+    uniform_buffers: Vec<raii::Buffer>,
+    uniform_buffer_ptrs: Vec<*mut AttractorUniforms>,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+}
+
+// Public API
+// ----------
+
+impl AttractorForce {
+    /// The most attractors which can affect particles at once.
+    pub const MAX_ATTRACTORS: usize = 16;
+
+    /// Create the attractor pipeline for a particle system.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - the frames which will record `cmd_apply`
+    /// * `particles` - the particle system the force applies to
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        particles: &ParticleSystem,
+    ) -> Result<Self, GraphicsError> {
+        let frame_count = frames_in_flight.frame_count();
+        let mut uniform_buffers = Vec::with_capacity(frame_count);
+        let mut uniform_buffer_ptrs = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let buffer = ParticleSystem::create_buffer(
+                &render_device,
+                std::mem::size_of::<AttractorUniforms>() as u64,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.set_debug_name("Attractor Uniforms");
+            let ptr = buffer.allocation().map(render_device.device())?;
+            uniform_buffer_ptrs.push(ptr as *mut AttractorUniforms);
+            uniform_buffers.push(buffer);
+        }
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[vk::DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: 1,
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    ..vk::DescriptorSetLayoutBinding::default()
+                }],
+            )?;
+        let set_count = frame_count as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            set_count,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: set_count,
+            }],
+        )?;
+        let layouts = vec![&descriptor_set_layout; frame_count];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        for (index, buffer) in uniform_buffers.iter().enumerate() {
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            };
+            render_device.device().update_descriptor_sets(
+                &[vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: &buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                }],
+                &[],
+            );
+        }
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[
+                    particles.descriptor_set_layout().raw(),
+                    descriptor_set_layout.raw(),
+                ],
+                &[],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device,
+            &pipeline_layout,
+            include_bytes!("./shaders/attractor_force.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Attractor Force Pipeline");
+
+        Ok(Self {
+            attractors: vec![],
+            uniform_buffers,
+            uniform_buffer_ptrs,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+        })
+    }
+
+    /// Record commands which accelerate every particle toward or away from
+    /// the attractors for `dt` seconds.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    ///   - `particles` must be the system this force was created for
+    pub unsafe fn cmd_apply(
+        &self,
+        frame: &Frame,
+        particles: &ParticleSystem,
+        dt: f32,
+    ) {
+        if self.attractors.is_empty() {
+            return;
+        }
+
+        let mut uniforms = AttractorUniforms {
+            attractor_count: 0,
+            particle_count: particles.particle_count(),
+            dt,
+            pad: 0,
+            attractors: [AttractorData::default(); Self::MAX_ATTRACTORS],
+        };
+        for (data, attractor) in uniforms
+            .attractors
+            .iter_mut()
+            .zip(self.attractors.iter().take(Self::MAX_ATTRACTORS))
+        {
+            *data = AttractorData {
+                position: attractor.position,
+                radius: attractor.radius.max(f32::EPSILON),
+                strength: attractor.strength,
+                falloff: attractor.falloff as u32,
+                pad: [0, 0],
+            };
+            uniforms.attractor_count += 1;
+        }
+
+        // SAFE because the frame's previous commands are complete, so its
+        // uniform buffer is no longer in use.
+        self.uniform_buffer_ptrs[frame.frame_index()].write(uniforms);
+
+        particles.cmd_dispatch(
+            frame,
+            &self.pipeline_layout,
+            &self.pipeline,
+            &[self.descriptor_pool.descriptor_set(frame.frame_index())],
+            &[],
+        );
+    }
+}
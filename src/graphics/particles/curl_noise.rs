@@ -0,0 +1,153 @@
+use {
+    super::ParticleSystem,
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control the curl noise force.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CurlNoiseParams {
+    /// The number of noise features per world unit. Larger values make
+    /// smaller swirls.
+    pub scale: f32,
+
+    /// The number of octaves of fractal noise. Each octave adds detail at
+    /// twice the frequency and half the strength of the last.
+    pub octaves: u32,
+
+    /// The acceleration applied to particles, in world units per second
+    /// squared.
+    pub strength: f32,
+
+    /// How quickly the noise field changes over time. Zero makes the field
+    /// static.
+    pub animation_speed: f32,
+
+    /// Different seeds produce different, uncorrelated fields.
+    pub seed: u32,
+}
+
+impl Default for CurlNoiseParams {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            octaves: 2,
+            strength: 1.0,
+            animation_speed: 0.1,
+            seed: 0,
+        }
+    }
+}
+
+/// Push constants for the curl noise shader. Must match CurlNoiseConstants
+/// in `shaders/curl_noise_force.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct CurlNoiseConstants {
+    kind: u32,
+    frequency: u32,
+    octaves: u32,
+    seed: u32,
+    scale: f32,
+    strength: f32,
+    time: f32,
+    dt: f32,
+    count: u32,
+}
+
+/// Accelerates particles along a divergence-free curl noise field.
+///
+/// Curl noise has no sources or sinks, so particles swirl around each other
+/// like smoke instead of clumping together. The field is computed in the
+/// compute shader from fractal simplex noise at each particle's position, so
+/// there is no texture to size or tile.
+pub struct CurlNoiseForce {
+    /// The parameters used by each call to `cmd_apply`.
+    pub params: CurlNoiseParams,
+
+    time: f32,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+}
+
+// Public API
+// ----------
+
+impl CurlNoiseForce {
+    /// Create the curl noise pipeline for a particle system.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        particles: &ParticleSystem,
+    ) -> Result<Self, GraphicsError> {
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[particles.descriptor_set_layout().raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<CurlNoiseConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device,
+            &pipeline_layout,
+            include_bytes!("./shaders/curl_noise_force.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Curl Noise Force Pipeline");
+        Ok(Self {
+            params: CurlNoiseParams::default(),
+            time: 0.0,
+            pipeline_layout,
+            pipeline,
+        })
+    }
+
+    /// Record commands which accelerate every particle for `dt` seconds and
+    /// advance the noise animation.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    ///   - `particles` must be the system this force was created for
+    pub unsafe fn cmd_apply(
+        &mut self,
+        frame: &Frame,
+        particles: &ParticleSystem,
+        dt: f32,
+    ) {
+        self.time += dt * self.params.animation_speed;
+        let constants = CurlNoiseConstants {
+            kind: 0,
+            frequency: 1,
+            octaves: self.params.octaves.max(1),
+            seed: self.params.seed,
+            scale: self.params.scale,
+            strength: self.params.strength,
+            time: self.time,
+            dt,
+            count: particles.particle_count(),
+        };
+        particles.cmd_dispatch(
+            frame,
+            &self.pipeline_layout,
+            &self.pipeline,
+            &[],
+            std::slice::from_raw_parts(
+                &constants as *const CurlNoiseConstants as *const u8,
+                std::mem::size_of::<CurlNoiseConstants>(),
+            ),
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use {
+    super::{particle_system::IntegrateConstants, ParticleSystem},
+    crate::{
+        graphics::{
+            vulkan_api::{
+                raii, DepthPrePass, Frame, FramesInFlight, RenderDevice,
+            },
+            GraphicsError,
+        },
+        math::{inverse, Mat4},
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// What happens to a particle when it hits the scene.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CollisionResponse {
+    /// The particle is moved onto the surface and its velocity is reflected
+    /// off of the reconstructed surface normal.
+    Bounce = 0,
+
+    /// The particle dies and its slot goes back on the free list.
+    Kill = 1,
+}
+
+/// Parameters which control how particles collide with the depth buffer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DepthCollisionParams {
+    /// What happens to a particle when it hits the scene.
+    pub response: CollisionResponse,
+
+    /// The fraction of a particle's speed into the surface which is kept
+    /// after a bounce. 0 makes particles slide, 1 is perfectly elastic.
+    pub restitution: f32,
+
+    /// How far behind the visible surface, in world units, a particle can
+    /// be and still collide. Particles farther back are treated as hidden
+    /// behind the surface and move freely.
+    pub thickness: f32,
+}
+
+impl Default for DepthCollisionParams {
+    fn default() -> Self {
+        Self {
+            response: CollisionResponse::Bounce,
+            restitution: 0.5,
+            thickness: 0.25,
+        }
+    }
+}
+
+/// Must match CollisionUniforms in `shaders/particles_integrate.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct CollisionUniforms {
+    view_projection: Mat4,
+    inverse_view_projection: Mat4,
+    camera_position: [f32; 3],
+    response: u32,
+    restitution: f32,
+    thickness: f32,
+    pad: [u32; 2],
+}
+
+/// Integrates particles like `ParticleSystem::cmd_integrate`, but also
+/// collides them with the scene's depth buffer.
+///
+/// After each particle moves, it's projected onto the screen and compared
+/// with the depth written by a DepthPrePass. Particles which end up just
+/// behind the visible surface bounce off of it or die. Only what the camera
+/// can see is solid, so particles pass through anything offscreen or hidden.
+///
+/// Record `cmd_integrate` after the DepthPrePass's render pass and before
+/// the ColorPass which loads the depth buffer, in place of the particle
+/// system's own `cmd_integrate`.
+pub struct DepthCollision {
+    /// The parameters used by each call to `cmd_integrate`.
+    pub params: DepthCollisionParams,
+
+    depth_image: vk::Image,
+    uniform_buffers: Vec<raii::Buffer>,
+    uniform_buffer_ptrs: Vec<*mut CollisionUniforms>,
+    _sampler: raii::Sampler,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl DepthCollision {
+    /// Create the collision pipeline for a particle system.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - the frames which will record `cmd_integrate`
+    /// * `particles` - the particle system to integrate
+    /// * `depth_pre_pass` - provides the scene's depth buffer
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the DepthPrePass must outlive this instance
+    ///   - this instance must be rebuilt any time the DepthPrePass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        particles: &ParticleSystem,
+        depth_pre_pass: &DepthPrePass,
+    ) -> Result<Self, GraphicsError> {
+        let frame_count = frames_in_flight.frame_count();
+        let mut uniform_buffers = Vec::with_capacity(frame_count);
+        let mut uniform_buffer_ptrs = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let buffer = ParticleSystem::create_buffer(
+                &render_device,
+                std::mem::size_of::<CollisionUniforms>() as u64,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.set_debug_name("Depth Collision Uniforms");
+            let ptr = buffer.allocation().map(render_device.device())?;
+            uniform_buffer_ptrs.push(ptr as *mut CollisionUniforms);
+            uniform_buffers.push(buffer);
+        }
+
+        // Depth is read at arbitrary uvs but must never be interpolated.
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 1,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                ],
+            )?;
+        let set_count = frame_count as u32;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            set_count,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: set_count,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: set_count,
+                },
+            ],
+        )?;
+        let layouts = vec![&descriptor_set_layout; frame_count];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        let depth_info = vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: depth_pre_pass.depth_view().raw(),
+            image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+        };
+        for (index, buffer) in uniform_buffers.iter().enumerate() {
+            let buffer_info = vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            };
+            render_device.device().update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet {
+                        dst_set: descriptor_pool.descriptor_set(index),
+                        dst_binding: 0,
+                        dst_array_element: 0,
+                        descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                        descriptor_count: 1,
+                        p_buffer_info: &buffer_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                    vk::WriteDescriptorSet {
+                        dst_set: descriptor_pool.descriptor_set(index),
+                        dst_binding: 1,
+                        dst_array_element: 0,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: 1,
+                        p_image_info: &depth_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                ],
+                &[],
+            );
+        }
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[
+                    particles.descriptor_set_layout().raw(),
+                    descriptor_set_layout.raw(),
+                ],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<IntegrateConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!(
+                "./shaders/particles_integrate_depth_collision.comp.spv"
+            ),
+        )?;
+        pipeline.set_debug_name("Particle Depth Collision Pipeline");
+
+        Ok(Self {
+            params: DepthCollisionParams::default(),
+            depth_image: depth_pre_pass.depth_image().raw(),
+            uniform_buffers,
+            uniform_buffer_ptrs,
+            _sampler: sampler,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Record commands which integrate every particle for `dt` seconds and
+    /// collide them with the depth buffer.
+    ///
+    /// The depth buffer is moved to DEPTH_STENCIL_READ_ONLY_OPTIMAL while
+    /// it's read, then returned to DEPTH_STENCIL_ATTACHMENT_OPTIMAL so a
+    /// ColorPass can still load it.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame to record into
+    /// * `particles` - the particle system this was created for
+    /// * `dt` - the time step in seconds
+    /// * `view` - the view transform the depth was rendered with
+    /// * `projection` - the projection the depth was rendered with
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    ///   - the DepthPrePass's render pass must have ended earlier in the
+    ///     command buffer
+    pub unsafe fn cmd_integrate(
+        &self,
+        frame: &Frame,
+        particles: &ParticleSystem,
+        dt: f32,
+        view: &Mat4,
+        projection: &Mat4,
+    ) {
+        let view_projection = projection * view;
+        let camera = inverse(view).unwrap_or_else(Mat4::identity);
+        let uniforms = CollisionUniforms {
+            view_projection,
+            inverse_view_projection: inverse(&view_projection)
+                .unwrap_or_else(Mat4::identity),
+            camera_position: [camera[(0, 3)], camera[(1, 3)], camera[(2, 3)]],
+            response: self.params.response as u32,
+            restitution: self.params.restitution.clamp(0.0, 1.0),
+            thickness: self.params.thickness.max(0.0),
+            pad: [0, 0],
+        };
+
+        // SAFE because the frame's previous commands are complete, so its
+        // uniform buffer is no longer in use.
+        self.uniform_buffer_ptrs[frame.frame_index()].write(uniforms);
+
+        self.cmd_transition_depth(
+            frame,
+            vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+            vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_READ,
+            vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+        );
+        particles.cmd_integrate_with(
+            frame,
+            &self.pipeline_layout,
+            &self.pipeline,
+            &[self.descriptor_pool.descriptor_set(frame.frame_index())],
+            dt,
+        );
+        self.cmd_transition_depth(
+            frame,
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::NONE,
+            vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+            vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl DepthCollision {
+    /// Record a layout transition for the depth buffer.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn cmd_transition_depth(
+        &self,
+        frame: &Frame,
+        src_stage_mask: vk::PipelineStageFlags2,
+        src_access_mask: vk::AccessFlags2,
+        old_layout: vk::ImageLayout,
+        dst_stage_mask: vk::PipelineStageFlags2,
+        dst_access_mask: vk::AccessFlags2,
+        new_layout: vk::ImageLayout,
+    ) {
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask,
+            src_access_mask,
+            dst_stage_mask,
+            dst_access_mask,
+            old_layout,
+            new_layout,
+            image: self.depth_image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            frame.command_buffer(),
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
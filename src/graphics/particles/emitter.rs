@@ -0,0 +1,407 @@
+use {
+    super::ParticleSystem,
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Where an emitter spawns particles.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EmitterShape {
+    /// Every particle starts at the same point.
+    Point { position: [f32; 3] },
+
+    /// Particles start anywhere on the line segment.
+    Line { start: [f32; 3], end: [f32; 3] },
+
+    /// Particles start anywhere inside a flat disc.
+    Circle {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+    },
+
+    /// Particles start anywhere on the surface of the emitter's mesh, with
+    /// the same density everywhere. See `ParticleEmitter::new_with_mesh`.
+    MeshSurface,
+}
+
+/// Parameters which control how an emitter spawns particles.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EmitterParams {
+    /// Where particles start.
+    pub shape: EmitterShape,
+
+    /// The number of particles spawned per second. Zero only spawns
+    /// particles in bursts.
+    pub rate: f32,
+
+    /// The shortest and longest lifetimes, in seconds. Each particle picks a
+    /// lifetime uniformly in this range. Lifetimes are at least a
+    /// millisecond, and the bounds can be given in either order.
+    pub lifetime: [f32; 2],
+
+    /// The slowest and fastest starting speeds. Each particle picks a speed
+    /// uniformly in this range.
+    pub speed: [f32; 2],
+
+    /// The center of the cone particles are launched in. A zero vector uses
+    /// the shape's normal, which is +Y for points and lines.
+    pub direction: [f32; 3],
+
+    /// The cone's half-angle in radians. Zero launches every particle along
+    /// `direction` and PI launches particles in every direction.
+    pub spread: f32,
+}
+
+impl Default for EmitterParams {
+    fn default() -> Self {
+        Self {
+            shape: EmitterShape::Point {
+                position: [0.0, 0.0, 0.0],
+            },
+            rate: 100.0,
+            lifetime: [1.0, 2.0],
+            speed: [0.5, 1.0],
+            direction: [0.0, 1.0, 0.0],
+            spread: std::f32::consts::FRAC_PI_8,
+        }
+    }
+}
+
+/// Push constants for the emitter shader. Must match EmitterConstants in
+/// `shaders/emitter.comp`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct EmitterConstants {
+    shape: u32,
+    spawn_count: u32,
+    seed: u32,
+    triangle_count: u32,
+    position: [f32; 4],
+    extent: [f32; 4],
+    direction: [f32; 4],
+    lifetime: [f32; 2],
+    speed: [f32; 2],
+}
+
+/// A mesh triangle with the running total of triangle areas. Must match
+/// Triangle in `shaders/emitter.comp`.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct EmitterTriangle {
+    a: [f32; 4],
+    b: [f32; 4],
+    c: [f32; 4],
+}
+
+/// Spawns particles by reusing dead particles from a ParticleSystem's free
+/// list.
+///
+/// Emitters spawn `rate` particles per second, carrying fractional
+/// particles over to the next frame, plus any bursts requested since the
+/// last call to `cmd_emit`. When every particle is alive, spawns are
+/// dropped.
+pub struct ParticleEmitter {
+    /// The parameters used by each call to `cmd_emit`.
+    pub params: EmitterParams,
+
+    pending: f32,
+    seed: u32,
+    triangle_count: u32,
+    _triangles: raii::Buffer,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+}
+
+// Public API
+// ----------
+
+impl ParticleEmitter {
+    /// The shortest lifetime a particle is spawned with, in seconds.
+    ///
+    /// The integrate kernel only frees particles which die while it runs, so
+    /// a particle spawned already dead would never return to the free list.
+    const MIN_LIFETIME: f32 = 1.0e-3;
+
+    /// Create an emitter for a particle system.
+    ///
+    /// The emitter has no mesh, so it can't use EmitterShape::MeshSurface.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        particles: &ParticleSystem,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_triangles(
+            render_device,
+            particles,
+            &[EmitterTriangle::default()],
+            0,
+        )
+    }
+
+    /// Create an emitter which can spawn particles on the surface of a
+    /// triangle mesh.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `particles` - the particle system the emitter spawns into
+    /// * `positions` - the mesh's vertex positions
+    /// * `indices` - three indices per triangle
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new_with_mesh(
+        render_device: Arc<RenderDevice>,
+        particles: &ParticleSystem,
+        positions: &[[f32; 3]],
+        indices: &[u32],
+    ) -> Result<Self, GraphicsError> {
+        let mut triangles = Vec::with_capacity(indices.len() / 3);
+        let mut total_area = 0.0;
+        for triangle in indices.chunks_exact(3) {
+            let vertex = |i: usize| {
+                positions.get(triangle[i] as usize).copied().ok_or_else(|| {
+                    anyhow!(
+                        "Emitter mesh index {} is out of range",
+                        triangle[i]
+                    )
+                })
+            };
+            let [a, b, c] = [vertex(0)?, vertex(1)?, vertex(2)?];
+            let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let cross = [
+                ab[1] * ac[2] - ab[2] * ac[1],
+                ab[2] * ac[0] - ab[0] * ac[2],
+                ab[0] * ac[1] - ab[1] * ac[0],
+            ];
+            let area = 0.5 * cross.iter().map(|c| c * c).sum::<f32>().sqrt();
+            if area <= f32::EPSILON {
+                continue;
+            }
+            total_area += area;
+            triangles.push(EmitterTriangle {
+                a: [a[0], a[1], a[2], total_area],
+                b: [b[0], b[1], b[2], 0.0],
+                c: [c[0], c[1], c[2], 0.0],
+            });
+        }
+        if triangles.is_empty() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Emitter meshes need at least one triangle with an area"
+            )));
+        }
+        let triangle_count = triangles.len() as u32;
+        Self::new_with_triangles(
+            render_device,
+            particles,
+            &triangles,
+            triangle_count,
+        )
+    }
+
+    /// Spawn `count` particles on the next call to `cmd_emit`, in addition
+    /// to the steady rate.
+    pub fn burst(&mut self, count: u32) {
+        self.pending += count as f32;
+    }
+
+    /// Record commands which spawn the particles due in the last `dt`
+    /// seconds.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    ///   - `particles` must be the system this emitter was created for
+    pub unsafe fn cmd_emit(
+        &mut self,
+        frame: &Frame,
+        particles: &ParticleSystem,
+        dt: f32,
+    ) -> Result<(), GraphicsError> {
+        self.pending += self.params.rate.max(0.0) * dt;
+        let spawn_count =
+            (self.pending.floor() as u32).min(particles.particle_count());
+        self.pending = self.pending.fract();
+        if spawn_count == 0 {
+            return Ok(());
+        }
+
+        let [min_lifetime, max_lifetime] = self
+            .params
+            .lifetime
+            .map(|lifetime| lifetime.max(Self::MIN_LIFETIME));
+        let mut constants = EmitterConstants {
+            spawn_count,
+            seed: self.seed,
+            triangle_count: self.triangle_count,
+            direction: {
+                let [x, y, z] = self.params.direction;
+                [x, y, z, self.params.spread.max(0.0)]
+            },
+            lifetime: [
+                min_lifetime.min(max_lifetime),
+                min_lifetime.max(max_lifetime),
+            ],
+            speed: self.params.speed,
+            ..Default::default()
+        };
+        match self.params.shape {
+            EmitterShape::Point {
+                position: [x, y, z],
+            } => {
+                constants.shape = 0;
+                constants.position = [x, y, z, 0.0];
+            }
+            EmitterShape::Line {
+                start: [x, y, z],
+                end: [ex, ey, ez],
+            } => {
+                constants.shape = 1;
+                constants.position = [x, y, z, 0.0];
+                constants.extent = [ex, ey, ez, 0.0];
+            }
+            EmitterShape::Circle {
+                center: [x, y, z],
+                normal: [nx, ny, nz],
+                radius,
+            } => {
+                constants.shape = 2;
+                constants.position = [x, y, z, 0.0];
+                constants.extent = [nx, ny, nz, radius];
+            }
+            EmitterShape::MeshSurface => {
+                if self.triangle_count == 0 {
+                    return Err(GraphicsError::RuntimeError(anyhow!(
+                        "This emitter was created without a mesh"
+                    )));
+                }
+                constants.shape = 3;
+            }
+        }
+        self.seed = self.seed.wrapping_add(1);
+
+        particles.cmd_dispatch_invocations(
+            frame,
+            &self.pipeline_layout,
+            &self.pipeline,
+            &[self.descriptor_pool.descriptor_set(0)],
+            std::slice::from_raw_parts(
+                &constants as *const EmitterConstants as *const u8,
+                std::mem::size_of::<EmitterConstants>(),
+            ),
+            spawn_count,
+        );
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl ParticleEmitter {
+    /// Upload the triangles and create the emitter pipeline.
+    unsafe fn new_with_triangles(
+        render_device: Arc<RenderDevice>,
+        particles: &ParticleSystem,
+        triangles: &[EmitterTriangle],
+        triangle_count: u32,
+    ) -> Result<Self, GraphicsError> {
+        let triangle_buffer = ParticleSystem::create_buffer(
+            &render_device,
+            std::mem::size_of_val(triangles) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        triangle_buffer.set_debug_name("Emitter Triangles");
+        ParticleSystem::upload(&render_device, triangles, &triangle_buffer)?;
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[vk::DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    ..vk::DescriptorSetLayoutBinding::default()
+                }],
+            )?;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        let buffer_info = vk::DescriptorBufferInfo {
+            buffer: triangle_buffer.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        };
+        render_device.device().update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: &buffer_info,
+                ..vk::WriteDescriptorSet::default()
+            }],
+            &[],
+        );
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[
+                    particles.descriptor_set_layout().raw(),
+                    descriptor_set_layout.raw(),
+                ],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<EmitterConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device,
+            &pipeline_layout,
+            include_bytes!("./shaders/emitter.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Particle Emitter Pipeline");
+
+        Ok(Self {
+            params: EmitterParams::default(),
+            pending: 0.0,
+            seed: 1,
+            triangle_count,
+            _triangles: triangle_buffer,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+        })
+    }
+}
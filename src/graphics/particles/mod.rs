@@ -0,0 +1,44 @@
+//! GPU particles which are simulated entirely in compute shaders.
+//!
+//! A ParticleSystem keeps every particle's position and velocity in a single
+//! storage buffer and advances them with an integration compute shader.
+//! Particles which outlive their lifetime go on a GPU free list, and a
+//! ParticleEmitter spawns new particles into those slots. Forces are
+//! separate compute kernels which edit velocities before the particles are
+//! integrated, so sketches can mix and match them:
+//!
+//! ```text
+//! emitter.cmd_emit(&frame, &particles, dt)?;
+//! curl_noise.cmd_apply(&frame, &particles, dt);
+//! attractors.cmd_apply(&frame, &particles, dt);
+//! particles.cmd_integrate(&frame, dt);
+//! ```
+//!
+//! DepthCollision replaces `cmd_integrate` with a variant of the integration
+//! shader which reads the depth buffer from a DepthPrePass, so particles
+//! bounce off of, or die on, whatever the camera can see.
+//!
+//! A ParticleRenderer draws the living particles as textured quads, and can
+//! sort them back to front first so alpha blended textures look right.
+//!
+//! AttractorForce's attractors are plain data which can be edited every
+//! frame, e.g. moved to the mouse cursor while a button is held or updated
+//! from an OscListener's parameters.
+
+mod attractor;
+mod curl_noise;
+mod depth_collision;
+mod emitter;
+mod particle_system;
+mod renderer;
+
+pub use self::{
+    attractor::{Attractor, AttractorForce, Falloff},
+    curl_noise::{CurlNoiseForce, CurlNoiseParams},
+    depth_collision::{
+        CollisionResponse, DepthCollision, DepthCollisionParams,
+    },
+    emitter::{EmitterParams, EmitterShape, ParticleEmitter},
+    particle_system::{Particle, ParticleSystem},
+    renderer::ParticleRenderer,
+};
@@ -0,0 +1,475 @@
+use {
+    crate::graphics::{
+        vulkan_api::{
+            raii, Barrier, BarrierAccess, BarrierStage, Frame,
+            OneTimeSubmitCommandBuffer, RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A single particle. Must match Particle in `shaders/particles.glsl`.
+///
+/// A particle is alive while its age is less than its lifetime. The default
+/// particle is dead.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub struct Particle {
+    /// The particle's position in xyz and its age, in seconds, in w.
+    pub position: [f32; 4],
+
+    /// The particle's velocity in xyz and its lifetime, in seconds, in w.
+    pub velocity: [f32; 4],
+}
+
+impl Particle {
+    /// A particle at `position` moving with `velocity` which never dies.
+    pub fn new(position: [f32; 3], velocity: [f32; 3]) -> Self {
+        Self::new_with_lifetime(position, velocity, f32::MAX)
+    }
+
+    /// A particle which dies after `lifetime` seconds.
+    pub fn new_with_lifetime(
+        position: [f32; 3],
+        velocity: [f32; 3],
+        lifetime: f32,
+    ) -> Self {
+        let [x, y, z] = position;
+        let [vx, vy, vz] = velocity;
+        Self {
+            position: [x, y, z, 0.0],
+            velocity: [vx, vy, vz, lifetime],
+        }
+    }
+
+    /// Returns true while the particle's age is less than its lifetime.
+    pub fn is_alive(&self) -> bool {
+        self.position[3] < self.velocity[3]
+    }
+}
+
+/// Push constants for the integration shader. Must match
+/// IntegrateConstants in `shaders/particles_integrate.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct IntegrateConstants {
+    dt: f32,
+    drag: f32,
+    count: u32,
+}
+
+/// A pool of particles which lives in a device-local storage buffer.
+///
+/// Particles which die are added to a GPU free list, and emitters reuse
+/// those slots for new particles, so the pool never grows.
+///
+/// Every compute shader which touches the particles, including the force
+/// kernels in this module, binds the particle buffer and free list with the
+/// system's descriptor set at set 0. See `shaders/particles.glsl`.
+pub struct ParticleSystem {
+    /// How quickly particles slow down, as a fraction of their velocity
+    /// lost per second.
+    pub drag: f32,
+
+    particle_count: u32,
+    particles: raii::Buffer,
+    free_list: raii::Buffer,
+    descriptor_pool: raii::DescriptorPool,
+    descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl ParticleSystem {
+    /// The workgroup size used by every particle compute shader.
+    pub const WORKGROUP_SIZE: u32 = 256;
+
+    /// Create the particle buffer and upload the initial particles.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `particles` - the initial state of every particle, there must be at
+    ///   least one. Dead particles start on the free list.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        particles: &[Particle],
+    ) -> Result<Self, GraphicsError> {
+        let particle_count = particles.len().max(1) as u32;
+        let buffer = Self::create_buffer(
+            &render_device,
+            (particle_count as usize * std::mem::size_of::<Particle>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        buffer.set_debug_name("Particles");
+        let placeholder = [Particle::default()];
+        let particles: &[Particle] = if particles.is_empty() {
+            &placeholder
+        } else {
+            particles
+        };
+        Self::upload(&render_device, particles, &buffer)?;
+
+        // The free list is a count followed by one index per particle.
+        let free_list = Self::create_buffer(
+            &render_device,
+            ((1 + particle_count as usize) * std::mem::size_of::<u32>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        free_list.set_debug_name("Particle Free List");
+        let mut free_indices = particles
+            .iter()
+            .enumerate()
+            .filter(|(_, particle)| !particle.is_alive())
+            .map(|(index, _)| index as u32)
+            .collect::<Vec<u32>>();
+        free_indices.insert(0, free_indices.len() as u32);
+        Self::upload(&render_device, &free_indices, &free_list)?;
+
+        let bindings = (0..2)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE
+                    | vk::ShaderStageFlags::VERTEX,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 2,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        let buffer_infos =
+            [&buffer, &free_list].map(|buffer| vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            });
+        let writes = buffer_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer_info)| vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: buffer_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<_>>();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<IntegrateConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/particles_integrate.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Particle Integrate Pipeline");
+
+        Ok(Self {
+            drag: 0.0,
+            particle_count,
+            particles: buffer,
+            free_list,
+            descriptor_pool,
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Create a pool of dead particles which emitters can bring to life.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new_pool(
+        render_device: Arc<RenderDevice>,
+        capacity: u32,
+    ) -> Result<Self, GraphicsError> {
+        Self::new(
+            render_device,
+            &vec![Particle::default(); capacity.max(1) as usize],
+        )
+    }
+
+    /// The number of particles in the system, alive or dead.
+    pub fn particle_count(&self) -> u32 {
+        self.particle_count
+    }
+
+    /// The storage buffer which holds every Particle.
+    pub fn particles(&self) -> &raii::Buffer {
+        &self.particles
+    }
+
+    /// Record commands which move every living particle by its velocity for
+    /// `dt` seconds and age it. Particles which die are added to the free
+    /// list.
+    ///
+    /// The commands end with a barrier which makes the new particles visible
+    /// to vertex and compute shaders.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    pub unsafe fn cmd_integrate(&self, frame: &Frame, dt: f32) {
+        self.cmd_integrate_with(
+            frame,
+            &self.pipeline_layout,
+            &self.pipeline,
+            &[],
+            dt,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl ParticleSystem {
+    /// The layout of the descriptor set which binds the particle buffer.
+    pub(super) fn descriptor_set_layout(&self) -> &raii::DescriptorSetLayout {
+        &self.descriptor_set_layout
+    }
+
+    /// The descriptor set which binds the particle buffer and free list.
+    pub(super) fn descriptor_set(&self) -> vk::DescriptorSet {
+        self.descriptor_pool.descriptor_set(0)
+    }
+
+    /// Record an integration dispatch with a pipeline built from a variant of
+    /// the integration shader.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - see `cmd_dispatch`
+    ///   - the pipeline layout's push constant range must hold
+    ///     IntegrateConstants
+    pub(super) unsafe fn cmd_integrate_with(
+        &self,
+        frame: &Frame,
+        pipeline_layout: &raii::PipelineLayout,
+        pipeline: &raii::Pipeline,
+        descriptor_sets: &[vk::DescriptorSet],
+        dt: f32,
+    ) {
+        let constants = IntegrateConstants {
+            dt,
+            drag: self.drag.max(0.0),
+            count: self.particle_count,
+        };
+        self.cmd_dispatch(
+            frame,
+            pipeline_layout,
+            pipeline,
+            descriptor_sets,
+            std::slice::from_raw_parts(
+                &constants as *const IntegrateConstants as *const u8,
+                std::mem::size_of::<IntegrateConstants>(),
+            ),
+        );
+    }
+
+    /// Record a dispatch with one invocation per particle, followed by a
+    /// barrier which makes the particle writes visible to vertex and compute
+    /// shaders.
+    ///
+    /// # Params
+    ///
+    /// * `descriptor_sets` - extra descriptor sets bound starting at set 1
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    ///   - the pipeline layout must use `descriptor_set_layout` for set 0
+    ///     and have a compute push constant range at least as large as
+    ///     `push_constants`, if there are any
+    pub(super) unsafe fn cmd_dispatch(
+        &self,
+        frame: &Frame,
+        pipeline_layout: &raii::PipelineLayout,
+        pipeline: &raii::Pipeline,
+        descriptor_sets: &[vk::DescriptorSet],
+        push_constants: &[u8],
+    ) {
+        self.cmd_dispatch_invocations(
+            frame,
+            pipeline_layout,
+            pipeline,
+            descriptor_sets,
+            push_constants,
+            self.particle_count,
+        );
+    }
+
+    /// Like `cmd_dispatch`, but with at least `invocations` invocations
+    /// instead of one per particle.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - see `cmd_dispatch`
+    pub(super) unsafe fn cmd_dispatch_invocations(
+        &self,
+        frame: &Frame,
+        pipeline_layout: &raii::PipelineLayout,
+        pipeline: &raii::Pipeline,
+        descriptor_sets: &[vk::DescriptorSet],
+        push_constants: &[u8],
+        invocations: u32,
+    ) {
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline_layout.raw(),
+            0,
+            &[self.descriptor_set()],
+            &[],
+        );
+        if !descriptor_sets.is_empty() {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline_layout.raw(),
+                1,
+                descriptor_sets,
+                &[],
+            );
+        }
+        if !push_constants.is_empty() {
+            device.cmd_push_constants(
+                command_buffer,
+                pipeline_layout.raw(),
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                push_constants,
+            );
+        }
+        device.cmd_dispatch(
+            command_buffer,
+            (invocations + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            1,
+            1,
+        );
+
+        Barrier::new()
+            .from(BarrierStage::Compute, BarrierAccess::Write)
+            .to(BarrierStage::Vertex, BarrierAccess::Read)
+            .to(BarrierStage::Compute, BarrierAccess::ReadWrite)
+            .record(device, command_buffer);
+    }
+
+    /// Create a buffer which is only used by the graphics queue.
+    pub(super) unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+
+    /// Copy data into the start of a device-local buffer.
+    pub(super) unsafe fn upload<T: Copy>(
+        render_device: &Arc<RenderDevice>,
+        data: &[T],
+        buffer: &raii::Buffer,
+    ) -> Result<(), GraphicsError> {
+        let size = std::mem::size_of_val(data) as u64;
+        let staging_buffer = Self::create_buffer(
+            render_device,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        staging_buffer.set_debug_name("Particle Staging");
+        let ptr =
+            staging_buffer.allocation().map(render_device.device())? as *mut T;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        staging_buffer.allocation().unmap(render_device.device())?;
+
+        let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        render_device.device().cmd_copy_buffer(
+            one_time_submit.command_buffer(),
+            staging_buffer.raw(),
+            buffer.raw(),
+            &[vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size,
+            }],
+        );
+        one_time_submit.sync_submit_and_reset()?;
+        Ok(())
+    }
+}
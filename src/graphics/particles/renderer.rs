@@ -0,0 +1,399 @@
+use {
+    super::ParticleSystem,
+    crate::{
+        graphics::{
+            gpu::{Sort, SortPair},
+            vulkan_api::{
+                raii, Barrier, BarrierAccess, BarrierStage, ColorPass, Frame,
+                GraphicsPipelineBuilder, RenderDevice, Texture2D,
+            },
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for the depth key shader. Must match DepthKeyConstants in
+/// `shaders/particle_depth_keys.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct DepthKeyConstants {
+    view: Mat4,
+    count: u32,
+}
+
+/// Push constants for the particle shaders. Must match DrawConstants in
+/// `shaders/particle_render.vert`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct DrawConstants {
+    view_projection: Mat4,
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+    color: [f32; 4],
+    sorted: u32,
+}
+
+/// Draws a ParticleSystem's living particles as camera-facing textured
+/// quads.
+///
+/// Particles are alpha blended, which only looks right when they are drawn
+/// back to front. With `depth_sort` enabled, `cmd_sort` writes each
+/// particle's distance from the camera into a buffer of SortPairs and sorts
+/// it with `gpu::Sort`, then `draw` visits particles in that order.
+pub struct ParticleRenderer {
+    /// The width and height of each particle in world units.
+    pub size: f32,
+
+    /// Multiplies the particle texture.
+    pub color: [f32; 4],
+
+    /// When true, particles are drawn in the order computed by `cmd_sort`.
+    pub depth_sort: bool,
+
+    particle_count: u32,
+    extent: vk::Extent2D,
+    sort: Sort,
+    pairs: raii::Buffer,
+    _texture: Arc<Texture2D>,
+    _sampler: raii::Sampler,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    key_pipeline_layout: raii::PipelineLayout,
+    key_pipeline: raii::Pipeline,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl ParticleRenderer {
+    /// Create the particle pipelines.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `color_pass` - the render pass the particles are drawn into
+    /// * `particles` - the particle system to draw
+    /// * `texture` - the texture drawn on each particle, usually with a soft
+    ///   alpha falloff
+    /// * `depth_test` - when true, particles are hidden behind scene depth.
+    ///   The ColorPass must be created with a DepthPrePass. Particles never
+    ///   write depth because they are alpha blended.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        color_pass: &ColorPass,
+        particles: &ParticleSystem,
+        texture: Arc<Texture2D>,
+        depth_test: bool,
+    ) -> Result<Self, GraphicsError> {
+        let particle_count = particles.particle_count();
+        let pairs = ParticleSystem::create_buffer(
+            &render_device,
+            (particle_count as usize * std::mem::size_of::<SortPair>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        pairs.set_debug_name("Particle Sort Pairs");
+        let sort = Sort::new(render_device.clone(), 1)?;
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 0,
+                        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::COMPUTE
+                            | vk::ShaderStageFlags::VERTEX,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                    vk::DescriptorSetLayoutBinding {
+                        binding: 1,
+                        descriptor_type:
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: 1,
+                        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                        ..vk::DescriptorSetLayoutBinding::default()
+                    },
+                ],
+            )?;
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        let buffer_info = vk::DescriptorBufferInfo {
+            buffer: pairs.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        };
+        let image_info = vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: texture.image_view.raw(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        render_device.device().update_descriptor_sets(
+            &[
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(0),
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: &buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(0),
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    p_image_info: &image_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ],
+            &[],
+        );
+        let set_layouts = [
+            particles.descriptor_set_layout().raw(),
+            descriptor_set_layout.raw(),
+        ];
+
+        let key_pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &set_layouts,
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<DepthKeyConstants>() as u32,
+                }],
+            )?;
+        let key_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &key_pipeline_layout,
+            include_bytes!("./shaders/particle_depth_keys.comp.spv"),
+        )?;
+        key_pipeline.set_debug_name("Particle Depth Key Pipeline");
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &set_layouts,
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX
+                        | vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<DrawConstants>() as u32,
+                }],
+            )?;
+        let pipeline = GraphicsPipelineBuilder::new()
+            .vertex_shader(include_bytes!("./shaders/particle_render.vert.spv"))
+            .fragment_shader(include_bytes!(
+                "./shaders/particle_render.frag.spv"
+            ))
+            .color_blend_attachment(
+                GraphicsPipelineBuilder::alpha_blend_attachment(),
+            )
+            .depth_test(depth_test)
+            .depth_write(false)
+            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .build(
+                render_device.clone(),
+                &pipeline_layout,
+                color_pass.render_pass(),
+            )?;
+        pipeline.set_debug_name("Particle Pipeline");
+
+        Ok(Self {
+            size: 0.05,
+            color: [1.0, 1.0, 1.0, 1.0],
+            depth_sort: false,
+            particle_count,
+            extent: color_pass.extent(),
+            sort,
+            pairs,
+            _texture: texture,
+            _sampler: sampler,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            key_pipeline_layout,
+            key_pipeline,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// Record commands which sort the particles back to front for the given
+    /// camera. Does nothing unless `depth_sort` is enabled.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `particles` - the particle system this renderer was created for
+    /// * `view` - the camera's view transform, the same one given to `draw`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    pub unsafe fn cmd_sort(
+        &mut self,
+        frame: &Frame,
+        particles: &ParticleSystem,
+        view: &Mat4,
+    ) -> Result<(), GraphicsError> {
+        if !self.depth_sort {
+            return Ok(());
+        }
+
+        // Wait for the previous draw to finish reading the pairs.
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        Barrier::new()
+            .from(BarrierStage::Vertex, BarrierAccess::Read)
+            .to(BarrierStage::Compute, BarrierAccess::Write)
+            .record(device, command_buffer);
+
+        let constants = DepthKeyConstants {
+            view: *view,
+            count: self.particle_count,
+        };
+        particles.cmd_dispatch(
+            frame,
+            &self.key_pipeline_layout,
+            &self.key_pipeline,
+            &[self.descriptor_pool.descriptor_set(0)],
+            std::slice::from_raw_parts(
+                &constants as *const DepthKeyConstants as *const u8,
+                std::mem::size_of::<DepthKeyConstants>(),
+            ),
+        );
+        self.sort
+            .cmd_sort(command_buffer, &self.pairs, self.particle_count)
+    }
+
+    /// Add commands to draw the particles.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `particles` - the particle system this renderer was created for
+    /// * `view` - the camera's view transform, used to face the camera
+    /// * `projection` - the camera's projection transform
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - when `depth_sort` is enabled, `cmd_sort` must have been recorded
+    ///     earlier in the frame
+    pub unsafe fn draw(
+        &self,
+        frame: &Frame,
+        particles: &ParticleSystem,
+        view: &Mat4,
+        projection: &Mat4,
+    ) {
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[
+                particles.descriptor_set(),
+                self.descriptor_pool.descriptor_set(0),
+            ],
+            &[],
+        );
+
+        // The rows of the view rotation are the camera's axes in world
+        // space.
+        let constants = DrawConstants {
+            view_projection: projection * view,
+            camera_right: [view[(0, 0)], view[(0, 1)], view[(0, 2)], self.size],
+            camera_up: [view[(1, 0)], view[(1, 1)], view[(1, 2)], 0.0],
+            color: self.color,
+            sorted: self.depth_sort as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const DrawConstants as *const u8,
+                std::mem::size_of::<DrawConstants>(),
+            ),
+        );
+        device.cmd_draw(command_buffer, 6, self.particle_count, 0, 0);
+    }
+}
@@ -0,0 +1,20 @@
+#version 460
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 1, binding = 1) uniform sampler2D particle_texture;
+
+// Must match DrawConstants in `particles/renderer.rs`.
+layout(push_constant) uniform DrawConstants {
+    mat4 view_projection;
+    vec4 camera_right;
+    vec4 camera_up;
+    vec4 color;
+    uint sorted;
+} constants;
+
+void main() {
+    out_color = texture(particle_texture, uv) * constants.color;
+}
@@ -0,0 +1,57 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+
+#include "particles.glsl"
+
+struct SortPair {
+    uint key;
+    uint value;
+};
+
+layout(std430, set = 1, binding = 0) readonly buffer SortPairs {
+    SortPair pairs[];
+};
+
+// Must match DrawConstants in `particles/renderer.rs`.
+layout(push_constant) uniform DrawConstants {
+    mat4 view_projection;
+    // xyz is the camera's right vector, w is the particle size.
+    vec4 camera_right;
+    vec4 camera_up;
+    vec4 color;
+    uint sorted;
+} constants;
+
+layout(location = 0) out vec2 uv;
+
+// Two triangles per particle, as offsets from the particle's center.
+const vec2 CORNERS[6] = vec2[](
+    vec2(-0.5, -0.5),
+    vec2(0.5, -0.5),
+    vec2(0.5, 0.5),
+    vec2(-0.5, -0.5),
+    vec2(0.5, 0.5),
+    vec2(-0.5, 0.5)
+);
+
+void main() {
+    uint index = constants.sorted == 1
+        ? pairs[gl_InstanceIndex].value
+        : gl_InstanceIndex;
+    Particle particle = particles[index];
+    if (!is_alive(particle)) {
+        // Collapse dead particles to a point so they aren't rasterized.
+        gl_Position = vec4(0.0);
+        uv = vec2(0.0);
+        return;
+    }
+
+    vec2 corner = CORNERS[gl_VertexIndex];
+    uv = vec2(corner.x + 0.5, 0.5 - corner.y);
+
+    float size = constants.camera_right.w;
+    vec3 position = particle.position.xyz
+        + constants.camera_right.xyz * corner.x * size
+        + constants.camera_up.xyz * corner.y * size;
+    gl_Position = constants.view_projection * vec4(position, 1.0);
+}
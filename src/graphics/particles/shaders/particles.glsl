@@ -0,0 +1,23 @@
+// Declarations shared by the particle shaders. Must match
+// particle_system.rs.
+
+struct Particle {
+    // xyz is the position, w is the particle's age in seconds.
+    vec4 position;
+    // xyz is the velocity, w is the particle's lifetime in seconds.
+    vec4 velocity;
+};
+
+layout(std430, set = 0, binding = 0) buffer Particles {
+    Particle particles[];
+};
+
+// The indices of dead particles which emitters can reuse.
+layout(std430, set = 0, binding = 1) buffer FreeList {
+    int free_count;
+    uint free_indices[];
+};
+
+bool is_alive(Particle particle) {
+    return particle.position.w < particle.velocity.w;
+}
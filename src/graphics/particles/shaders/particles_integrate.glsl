@@ -0,0 +1,131 @@
+// The integration kernel shared by `particles_integrate.comp` and
+// `particles_integrate_depth_collision.comp`. Define DEPTH_COLLISION before
+// including it to collide particles with the scene's depth buffer.
+
+#include "particles.glsl"
+
+layout(local_size_x = 256) in;
+
+// Must match IntegrateConstants in `particles/particle_system.rs`.
+layout(push_constant) uniform IntegrateConstants {
+    float dt;
+    float drag;
+    uint count;
+} constants;
+
+#ifdef DEPTH_COLLISION
+
+// Must match CollisionUniforms in `particles/depth_collision.rs`.
+layout(std140, set = 1, binding = 0) uniform CollisionUniforms {
+    mat4 view_projection;
+    mat4 inverse_view_projection;
+    vec3 camera_position;
+    // 0 bounces particles off of surfaces, 1 kills them.
+    uint response;
+    // The fraction of velocity kept along the normal after a bounce.
+    float restitution;
+    // How far behind a surface a particle can be and still collide.
+    float thickness;
+} collision;
+
+layout(set = 1, binding = 1) uniform sampler2D depth;
+
+const uint RESPONSE_BOUNCE = 0;
+const uint RESPONSE_KILL = 1;
+
+vec3 world_position(vec2 uv) {
+    float d = textureLod(depth, uv, 0.0).r;
+    vec4 position =
+        collision.inverse_view_projection * vec4(uv * 2.0 - 1.0, d, 1.0);
+    return position.xyz / position.w;
+}
+
+// Reconstruct the surface normal from neighboring depths, choosing the
+// neighbor on each axis which is closest to the center so edges don't smear.
+vec3 surface_normal(vec2 uv, vec3 center) {
+    vec2 texel_size = 1.0 / vec2(textureSize(depth, 0));
+    vec3 right = world_position(uv + vec2(texel_size.x, 0.0)) - center;
+    vec3 left = center - world_position(uv - vec2(texel_size.x, 0.0));
+    vec3 down = world_position(uv + vec2(0.0, texel_size.y)) - center;
+    vec3 up = center - world_position(uv - vec2(0.0, texel_size.y));
+    vec3 dx = dot(right, right) < dot(left, left) ? right : left;
+    vec3 dy = dot(down, down) < dot(up, up) ? down : up;
+    vec3 normal = normalize(cross(dy, dx));
+
+    // Only the side facing the camera is visible, so it's the side hit.
+    return dot(normal, collision.camera_position - center) < 0.0
+        ? -normal
+        : normal;
+}
+
+// Handle particles which have moved just behind the visible surface. They're
+// either left on the surface with their velocity reflected, or killed.
+void collide(inout Particle particle) {
+    vec4 clip = collision.view_projection * vec4(particle.position.xyz, 1.0);
+    if (clip.w <= 0.0) {
+        return;
+    }
+    vec3 ndc = clip.xyz / clip.w;
+    if (any(greaterThan(abs(ndc.xy), vec2(1.0))) || ndc.z > 1.0) {
+        return;
+    }
+
+    vec2 uv = ndc.xy * 0.5 + 0.5;
+    float surface_depth = textureLod(depth, uv, 0.0).r;
+    if (surface_depth >= 1.0 || ndc.z < surface_depth) {
+        // Nothing was drawn here, or the particle is in front of it.
+        return;
+    }
+    vec3 surface = world_position(uv);
+    if (distance(surface, particle.position.xyz) > collision.thickness) {
+        // The particle is hidden behind the surface, not inside it.
+        return;
+    }
+
+    if (collision.response == RESPONSE_KILL) {
+        // Aging the particle to its lifetime kills it.
+        particle.position.w = particle.velocity.w;
+        return;
+    }
+
+    vec3 normal = surface_normal(uv, surface);
+    vec3 velocity = particle.velocity.xyz;
+    float normal_speed = dot(velocity, normal);
+    if (normal_speed < 0.0) {
+        velocity -= (1.0 + collision.restitution) * normal_speed * normal;
+    }
+    particle.position.xyz = surface;
+    particle.velocity.xyz = velocity;
+}
+
+#endif
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= constants.count) {
+        return;
+    }
+
+    Particle particle = particles[index];
+    if (!is_alive(particle)) {
+        return;
+    }
+
+    vec3 velocity = particle.velocity.xyz
+        * exp(-constants.drag * constants.dt);
+    particle.position.xyz += velocity * constants.dt;
+    particle.position.w += constants.dt;
+    particle.velocity.xyz = velocity;
+
+#ifdef DEPTH_COLLISION
+    collide(particle);
+#endif
+
+    particles[index] = particle;
+
+    // Particles which just died go back on the free list.
+    if (!is_alive(particle)) {
+        int slot = atomicAdd(free_count, 1);
+        free_indices[slot] = index;
+    }
+}
@@ -0,0 +1,504 @@
+use {
+    crate::math::Vec3,
+    anyhow::{anyhow, bail, Context, Result},
+    std::{convert::TryInto, path::Path},
+};
+
+/// Points with colors, loaded from disk or built by the application.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloud {
+    /// Point positions, relative to `origin`.
+    pub positions: Vec<Vec3>,
+
+    /// Linear rgba colors, one per point. Points without a color are white.
+    pub colors: Vec<[f32; 4]>,
+
+    /// The position of the cloud's origin in the file's coordinates.
+    ///
+    /// Survey data often uses coordinates which are far too large for f32,
+    /// so loaders move the origin to the center of the cloud.
+    pub origin: [f64; 3],
+}
+
+impl PointCloud {
+    /// Load a point cloud, picking the loader with the file's extension.
+    #[tracing::instrument(skip_all, fields(path = %path.as_ref().display()))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ply") => Self::load_ply(path),
+            Some("las") => Self::load_las(path),
+            _ => bail!(
+                "Unsupported point cloud file {:?}, expected .ply or .las",
+                path.as_ref()
+            ),
+        }
+    }
+
+    /// Load the vertices of an ascii or binary PLY file.
+    ///
+    /// Vertices need `x`, `y`, and `z` properties, and may have `red`,
+    /// `green`, `blue`, and `alpha`. Faces and other elements are ignored.
+    pub fn load_ply(path: impl AsRef<Path>) -> Result<Self> {
+        let bytes = std::fs::read(&path).with_context(|| {
+            format!("Unable to read PLY file {:?}", path.as_ref())
+        })?;
+        parse_ply(&bytes).with_context(|| {
+            format!("Unable to parse PLY file {:?}", path.as_ref())
+        })
+    }
+
+    /// Load the points of an uncompressed LAS file, versions 1.0 to 1.4.
+    ///
+    /// Colors are read from point formats which have them.
+    pub fn load_las(path: impl AsRef<Path>) -> Result<Self> {
+        let bytes = std::fs::read(&path).with_context(|| {
+            format!("Unable to read LAS file {:?}", path.as_ref())
+        })?;
+        parse_las(&bytes).with_context(|| {
+            format!("Unable to parse LAS file {:?}", path.as_ref())
+        })
+    }
+
+    /// The number of points.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// True when the cloud has no points.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Build a cloud from double-precision positions, moving the origin to
+    /// the center of their bounds.
+    fn from_f64_positions(
+        positions: &[[f64; 3]],
+        colors: Vec<[f32; 4]>,
+    ) -> Self {
+        let mut min = [f64::MAX; 3];
+        let mut max = [f64::MIN; 3];
+        for position in positions {
+            for (axis, value) in position.iter().enumerate() {
+                min[axis] = min[axis].min(*value);
+                max[axis] = max[axis].max(*value);
+            }
+        }
+        let origin = if positions.is_empty() {
+            [0.0; 3]
+        } else {
+            [
+                0.5 * (min[0] + max[0]),
+                0.5 * (min[1] + max[1]),
+                0.5 * (min[2] + max[2]),
+            ]
+        };
+        let positions = positions
+            .iter()
+            .map(|p| {
+                Vec3::new(
+                    (p[0] - origin[0]) as f32,
+                    (p[1] - origin[1]) as f32,
+                    (p[2] - origin[2]) as f32,
+                )
+            })
+            .collect();
+        Self {
+            positions,
+            colors,
+            origin,
+        }
+    }
+}
+
+// PLY
+// ---
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone)]
+enum PlyProperty {
+    Scalar { name: String, ty: PlyType },
+    List { count: PlyType, item: PlyType },
+}
+
+#[derive(Debug, Clone)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => bail!("Unknown PLY property type {}", name),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// The value that maps to full intensity for color channels.
+    fn color_scale(self) -> f64 {
+        match self {
+            Self::U8 | Self::I8 => 255.0,
+            Self::U16 | Self::I16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+
+    fn read_binary(self, bytes: &[u8], format: PlyFormat) -> f64 {
+        macro_rules! read {
+            ($ty:ty) => {{
+                let mut array = [0_u8; std::mem::size_of::<$ty>()];
+                array.copy_from_slice(bytes);
+                if format == PlyFormat::BinaryBigEndian {
+                    <$ty>::from_be_bytes(array) as f64
+                } else {
+                    <$ty>::from_le_bytes(array) as f64
+                }
+            }};
+        }
+        match self {
+            Self::I8 => read!(i8),
+            Self::U8 => read!(u8),
+            Self::I16 => read!(i16),
+            Self::U16 => read!(u16),
+            Self::I32 => read!(i32),
+            Self::U32 => read!(u32),
+            Self::F32 => read!(f32),
+            Self::F64 => read!(f64),
+        }
+    }
+}
+
+fn parse_ply(bytes: &[u8]) -> Result<PointCloud> {
+    const END_HEADER: &[u8] = b"end_header";
+    let header_end = bytes
+        .windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .context("Missing end_header")?;
+    let body_start = bytes[header_end..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map(|newline| header_end + newline + 1)
+        .unwrap_or(bytes.len());
+    let header = std::str::from_utf8(&bytes[..header_end])
+        .context("The PLY header is not valid text")?;
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        bail!("Missing the 'ply' magic number");
+    }
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    for line in lines {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["format", name, ..] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => bail!("Unknown PLY format {}", name),
+                });
+            }
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse().context("Invalid element count")?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, _] => elements
+                .last_mut()
+                .context("Property before any element")?
+                .properties
+                .push(PlyProperty::List {
+                    count: PlyType::parse(count)?,
+                    item: PlyType::parse(item)?,
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .context("Property before any element")?
+                .properties
+                .push(PlyProperty::Scalar {
+                    name: name.to_string(),
+                    ty: PlyType::parse(ty)?,
+                }),
+            _ => (),
+        }
+    }
+    let format = format.context("Missing the format line")?;
+
+    let mut reader = PlyReader {
+        format,
+        body: &bytes[body_start..],
+        offset: 0,
+        words: vec![],
+    };
+    for element in &elements {
+        if element.name != "vertex" {
+            for _ in 0..element.count {
+                reader.read_element(&element.properties, |_, _, _| ())?;
+            }
+            continue;
+        }
+
+        let index_of = |name: &str| {
+            element.properties.iter().position(|property| {
+                matches!(property, PlyProperty::Scalar { name: n, .. } if n == name)
+            })
+        };
+        let xyz = [index_of("x"), index_of("y"), index_of("z")];
+        if xyz.iter().any(Option::is_none) {
+            bail!("Vertices need x, y, and z properties");
+        }
+        let rgba = [
+            index_of("red"),
+            index_of("green"),
+            index_of("blue"),
+            index_of("alpha"),
+        ];
+
+        let mut positions = Vec::with_capacity(element.count);
+        let mut colors = Vec::with_capacity(element.count);
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut color = [1.0_f32; 4];
+            reader.read_element(&element.properties, |index, ty, value| {
+                let is_property =
+                    |&property: &Option<usize>| property == Some(index);
+                if let Some(axis) = xyz.iter().position(is_property) {
+                    position[axis] = value;
+                }
+                if let Some(channel) = rgba.iter().position(is_property) {
+                    color[channel] = (value / ty.color_scale()) as f32;
+                }
+            })?;
+            positions.push(position);
+            colors.push(color);
+        }
+        return Ok(PointCloud::from_f64_positions(&positions, colors));
+    }
+    Err(anyhow!("The file has no vertex element"))
+}
+
+/// Reads property values from the body of a PLY file.
+struct PlyReader<'a> {
+    format: PlyFormat,
+    body: &'a [u8],
+    offset: usize,
+    words: Vec<f64>,
+}
+
+impl<'a> PlyReader<'a> {
+    /// Read one element, calling `visit` with the index, type, and value of
+    /// each scalar property. List properties are skipped.
+    fn read_element(
+        &mut self,
+        properties: &[PlyProperty],
+        mut visit: impl FnMut(usize, PlyType, f64),
+    ) -> Result<()> {
+        if self.format == PlyFormat::Ascii {
+            self.read_ascii_line()?;
+        }
+        let mut word = 0;
+        for (index, property) in properties.iter().enumerate() {
+            match property {
+                PlyProperty::Scalar { ty, .. } => {
+                    let value = self.read_value(*ty, &mut word)?;
+                    visit(index, *ty, value);
+                }
+                PlyProperty::List { count, item } => {
+                    let count = self.read_value(*count, &mut word)? as usize;
+                    for _ in 0..count {
+                        self.read_value(*item, &mut word)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_value(&mut self, ty: PlyType, word: &mut usize) -> Result<f64> {
+        if self.format == PlyFormat::Ascii {
+            let value = *self
+                .words
+                .get(*word)
+                .context("Too few values on an ascii PLY line")?;
+            *word += 1;
+            return Ok(value);
+        }
+        let end = self.offset + ty.size();
+        let bytes = self
+            .body
+            .get(self.offset..end)
+            .context("Unexpected end of PLY data")?;
+        self.offset = end;
+        Ok(ty.read_binary(bytes, self.format))
+    }
+
+    /// Split the next non-empty ascii line into values.
+    fn read_ascii_line(&mut self) -> Result<()> {
+        loop {
+            if self.offset >= self.body.len() {
+                bail!("Unexpected end of PLY data");
+            }
+            let rest = &self.body[self.offset..];
+            let length = rest
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .unwrap_or(rest.len());
+            self.offset += length + 1;
+            let line = std::str::from_utf8(&rest[..length])
+                .context("Invalid text in an ascii PLY file")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            self.words = line
+                .split_whitespace()
+                .map(|word| word.parse::<f64>())
+                .collect::<Result<_, _>>()
+                .context("Invalid number in an ascii PLY file")?;
+            return Ok(());
+        }
+    }
+}
+
+// LAS
+// ---
+
+fn parse_las(bytes: &[u8]) -> Result<PointCloud> {
+    let read = |offset: usize, length: usize| -> Result<&[u8]> {
+        bytes
+            .get(offset..offset + length)
+            .context("Unexpected end of LAS data")
+    };
+    let u8_at = |offset: usize| -> Result<u8> { Ok(read(offset, 1)?[0]) };
+    let u16_at = |offset: usize| -> Result<u16> {
+        Ok(u16::from_le_bytes(read(offset, 2)?.try_into().unwrap()))
+    };
+    let u32_at = |offset: usize| -> Result<u32> {
+        Ok(u32::from_le_bytes(read(offset, 4)?.try_into().unwrap()))
+    };
+    let i32_at = |offset: usize| -> Result<i32> {
+        Ok(i32::from_le_bytes(read(offset, 4)?.try_into().unwrap()))
+    };
+    let u64_at = |offset: usize| -> Result<u64> {
+        Ok(u64::from_le_bytes(read(offset, 8)?.try_into().unwrap()))
+    };
+    let f64_at = |offset: usize| -> Result<f64> {
+        Ok(f64::from_le_bytes(read(offset, 8)?.try_into().unwrap()))
+    };
+
+    if read(0, 4)? != b"LASF" {
+        bail!("Missing the 'LASF' signature");
+    }
+    let version = (u8_at(24)?, u8_at(25)?);
+    let header_size = u16_at(94)? as usize;
+    let point_offset = u32_at(96)? as usize;
+    let point_format = u8_at(104)?;
+    let record_length = u16_at(105)? as usize;
+    if point_format & 0x80 != 0 {
+        bail!("Compressed LAZ files are not supported");
+    }
+    let point_format = point_format & 0x3f;
+    let mut point_count = u32_at(107)? as u64;
+    if version >= (1, 4) && header_size >= 255 {
+        point_count = point_count.max(u64_at(247)?);
+    }
+    let scale = [f64_at(131)?, f64_at(139)?, f64_at(147)?];
+    let offset = [f64_at(155)?, f64_at(163)?, f64_at(171)?];
+
+    let color_offset = match point_format {
+        2 => Some(20),
+        3 | 5 => Some(28),
+        7 | 8 | 10 => Some(30),
+        0 | 1 | 4 | 6 | 9 => None,
+        _ => bail!("Unsupported LAS point format {}", point_format),
+    };
+    if record_length < color_offset.map_or(12, |offset| offset + 6) {
+        bail!(
+            "LAS point records are too short for format {}",
+            point_format
+        );
+    }
+
+    let mut positions = Vec::with_capacity(point_count as usize);
+    let mut raw_colors = Vec::with_capacity(point_count as usize);
+    for i in 0..point_count as usize {
+        let record = point_offset + i * record_length;
+        positions.push([
+            i32_at(record)? as f64 * scale[0] + offset[0],
+            i32_at(record + 4)? as f64 * scale[1] + offset[1],
+            i32_at(record + 8)? as f64 * scale[2] + offset[2],
+        ]);
+        if let Some(color_offset) = color_offset {
+            let color = record + color_offset;
+            raw_colors.push([
+                u16_at(color)?,
+                u16_at(color + 2)?,
+                u16_at(color + 4)?,
+            ]);
+        }
+    }
+
+    // The spec says colors are 16-bit, but many writers store 8-bit values.
+    let color_max = raw_colors
+        .iter()
+        .flat_map(|color| color.iter().copied())
+        .max()
+        .unwrap_or(0);
+    let color_scale = if color_max <= 255 { 255.0 } else { 65535.0 };
+    let colors = if raw_colors.is_empty() {
+        vec![[1.0; 4]; positions.len()]
+    } else {
+        raw_colors
+            .iter()
+            .map(|[r, g, b]| {
+                [
+                    *r as f32 / color_scale,
+                    *g as f32 / color_scale,
+                    *b as f32 / color_scale,
+                    1.0,
+                ]
+            })
+            .collect()
+    };
+    Ok(PointCloud::from_f64_positions(&positions, colors))
+}
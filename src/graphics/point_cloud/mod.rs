@@ -0,0 +1,14 @@
+//! Loading and drawing large point clouds.
+//!
+//! PointCloud loads XYZ and color points from PLY and LAS files.
+//! PointCloudRenderer quantizes the points into a device-local vertex buffer
+//! and draws them as squares or circles with a fixed size in pixels,
+//! decimating very large clouds based on how much of the screen they cover.
+
+mod loader;
+mod renderer;
+
+pub use self::{
+    loader::PointCloud,
+    renderer::{PointCloudLod, PointCloudRenderer, PointShape},
+};
@@ -0,0 +1,318 @@
+use {
+    super::PointCloud,
+    crate::{
+        graphics::{
+            mesh::{QuantizedMesh, QuantizedPoint, VertexFormat},
+            vulkan_api::{
+                raii, ColorPass, Frame, GraphicsPipelineBuilder, RenderDevice,
+            },
+            GraphicsError,
+        },
+        math::{Mat4, Vec3, Vec4},
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The shape of each point on screen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum PointShape {
+    /// Square quads, the cheapest option for dense clouds.
+    Square = 0,
+
+    /// Round points, made by discarding the corners of each quad.
+    Round = 1,
+}
+
+/// Controls how many points are drawn for very large clouds.
+///
+/// Points are shuffled when they are uploaded, so the first `n` points are
+/// always an even sample of the whole cloud. Each draw estimates how many
+/// pixels the cloud covers and only draws as many points as are needed to
+/// fill them, growing the points to hide the gaps.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointCloudLod {
+    /// The number of points drawn for each pixel the cloud covers.
+    pub points_per_pixel: f32,
+
+    /// Never draw more than this many points, no matter how close the
+    /// cloud is.
+    pub max_points: u32,
+
+    /// The largest factor points grow by when the cloud is decimated.
+    pub max_size_scale: f32,
+}
+
+impl Default for PointCloudLod {
+    fn default() -> Self {
+        Self {
+            points_per_pixel: 0.5,
+            max_points: 8_000_000,
+            max_size_scale: 3.0,
+        }
+    }
+}
+
+/// Push constants for the point cloud shaders. Must match PointConstants in
+/// `shaders/point_cloud.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct PointConstants {
+    transform: Mat4,
+    viewport_size: [f32; 2],
+    point_size: f32,
+    shape: u32,
+}
+
+/// Draws point clouds as screen-space-sized squares or circles.
+///
+/// Points are quantized and stored in a device-local vertex buffer, and each
+/// point is drawn as an instanced quad so the size can be set in pixels.
+pub struct PointCloudRenderer {
+    /// The diameter of each point, in pixels.
+    pub point_size: f32,
+
+    /// The shape of each point.
+    pub shape: PointShape,
+
+    /// Level of detail settings for large clouds.
+    pub lod: PointCloudLod,
+
+    points: QuantizedMesh<QuantizedPoint>,
+    origin: [f64; 3],
+    extent: vk::Extent2D,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl PointCloudRenderer {
+    /// Upload the points and create the pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `color_pass` - the render pass the points are drawn into
+    /// * `cloud` - the points to draw
+    /// * `depth_test` - when true, points are depth tested and write depth.
+    ///   The ColorPass must be created with a DepthPrePass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        color_pass: &ColorPass,
+        cloud: &PointCloud,
+        depth_test: bool,
+    ) -> Result<Self, GraphicsError> {
+        let (quantizer, mut encoded) =
+            QuantizedPoint::encode_all(&cloud.positions, &cloud.colors);
+        shuffle(&mut encoded);
+        let points =
+            QuantizedMesh::new(render_device.clone(), quantizer, &encoded)?;
+
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX
+                        | vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<PointConstants>() as u32,
+                }],
+            )?;
+
+        // One point per instance, expanded to a quad in the vertex shader.
+        let binding = vk::VertexInputBindingDescription {
+            input_rate: vk::VertexInputRate::INSTANCE,
+            ..QuantizedPoint::vertex_input_binding(0)
+        };
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!("./shaders/point_cloud.vert.spv"))
+                .fragment_shader(include_bytes!(
+                    "./shaders/point_cloud.frag.spv"
+                ))
+                .vertex_input(
+                    &[binding],
+                    &QuantizedPoint::vertex_input_attributes(0, 0),
+                )
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .depth_test(depth_test)
+                .depth_write(depth_test)
+                .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+                .build(
+                    render_device.clone(),
+                    &pipeline_layout,
+                    color_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Point Cloud Pipeline");
+
+        Ok(Self {
+            point_size: 2.0,
+            shape: PointShape::Round,
+            lod: PointCloudLod::default(),
+            points,
+            origin: cloud.origin,
+            extent: color_pass.extent(),
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// The number of points in the cloud.
+    pub fn point_count(&self) -> u32 {
+        self.points.vertex_count()
+    }
+
+    /// The cloud's origin in the file's coordinates. See
+    /// `PointCloud::origin`.
+    pub fn origin(&self) -> [f64; 3] {
+        self.origin
+    }
+
+    /// The number of points `draw` would use for a given transform.
+    ///
+    /// # Params
+    ///
+    /// * `view_projection` - transforms the cloud's positions to clip space
+    pub fn lod_point_count(&self, view_projection: &Mat4) -> u32 {
+        let covered_pixels = self.covered_pixels(view_projection);
+        let wanted = (covered_pixels * self.lod.points_per_pixel.max(0.0))
+            .ceil()
+            .min(u32::MAX as f32) as u32;
+        wanted
+            .min(self.lod.max_points)
+            .min(self.point_count())
+            .max(1)
+    }
+
+    /// Add commands to draw the cloud.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `view_projection` - transforms the cloud's positions to clip space,
+    ///   usually `projection * view * model`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    pub unsafe fn draw(&self, frame: &Frame, view_projection: &Mat4) {
+        let point_count = self.lod_point_count(view_projection);
+        let size_scale = (self.point_count() as f32 / point_count as f32)
+            .sqrt()
+            .min(self.lod.max_size_scale.max(1.0));
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        let constants = PointConstants {
+            transform: view_projection * self.points.dequantize_transform(),
+            viewport_size: [width as f32, height as f32],
+            point_size: self.point_size * size_scale,
+            shape: self.shape as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const PointConstants as *const u8,
+                std::mem::size_of::<PointConstants>(),
+            ),
+        );
+        self.points.cmd_bind_vertex_buffer(command_buffer, 0);
+        device.cmd_draw(command_buffer, 6, point_count, 0, 0);
+    }
+}
+
+// Private API
+// -----------
+
+impl PointCloudRenderer {
+    /// Estimate the number of pixels covered by the cloud's bounding box.
+    fn covered_pixels(&self, view_projection: &Mat4) -> f32 {
+        let quantizer = self.points.quantizer();
+        let (min, max) = (quantizer.min, quantizer.max);
+        let mut screen_min = [f32::MAX; 2];
+        let mut screen_max = [f32::MIN; 2];
+        for corner in 0..8 {
+            let pick = |bit: u32, axis: usize| {
+                if corner & bit == 0 {
+                    min[axis]
+                } else {
+                    max[axis]
+                }
+            };
+            let position = Vec3::new(pick(1, 0), pick(2, 1), pick(4, 2));
+            let clip = view_projection
+                * Vec4::new(position.x, position.y, position.z, 1.0);
+            if clip.w <= f32::EPSILON {
+                // The camera is inside of the bounds, so assume the cloud
+                // fills the screen.
+                return (self.extent.width * self.extent.height) as f32;
+            }
+            let ndc = [
+                (clip.x / clip.w).clamp(-1.0, 1.0),
+                (clip.y / clip.w).clamp(-1.0, 1.0),
+            ];
+            screen_min = [screen_min[0].min(ndc[0]), screen_min[1].min(ndc[1])];
+            screen_max = [screen_max[0].max(ndc[0]), screen_max[1].max(ndc[1])];
+        }
+        let width = 0.5 * (screen_max[0] - screen_min[0]);
+        let height = 0.5 * (screen_max[1] - screen_min[1]);
+        width * self.extent.width as f32 * height * self.extent.height as f32
+    }
+}
+
+/// Shuffle the points so any prefix is an even sample of the cloud.
+fn shuffle(points: &mut [QuantizedPoint]) {
+    // A small xorshift generator is plenty for decimation.
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for i in (1..points.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let j = (state % (i as u64 + 1)) as usize;
+        points.swap(i, j);
+    }
+}
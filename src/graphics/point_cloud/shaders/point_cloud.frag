@@ -0,0 +1,17 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "point_cloud.glsl"
+
+layout(location = 0) in vec4 point_color;
+layout(location = 1) in vec2 corner;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    if (constants.shape == SHAPE_ROUND && dot(corner, corner) > 1.0) {
+        discard;
+    }
+    out_color = point_color;
+}
@@ -0,0 +1,14 @@
+// Push constants for the point cloud shaders. Must match PointConstants in
+// `point_cloud/renderer.rs`.
+layout(push_constant) uniform PointConstants {
+    // Transforms quantized positions to clip space.
+    mat4 transform;
+    vec2 viewport_size;
+    // The diameter of each point in pixels.
+    float point_size;
+    // 0 for square points, 1 for round points.
+    uint shape;
+} constants;
+
+const uint SHAPE_SQUARE = 0;
+const uint SHAPE_ROUND = 1;
@@ -0,0 +1,33 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "point_cloud.glsl"
+
+// One QuantizedPoint per instance.
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 point_color;
+layout(location = 1) out vec2 corner;
+
+// Two triangles per point, as offsets from the point's center.
+const vec2 CORNERS[6] = vec2[](
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+void main() {
+    corner = CORNERS[gl_VertexIndex];
+    point_color = color;
+
+    // Offset in clip space so points have the same size in pixels at any
+    // depth.
+    vec4 clip = constants.transform * position;
+    clip.xy += corner * constants.point_size / constants.viewport_size * clip.w;
+    gl_Position = clip;
+}
@@ -0,0 +1,370 @@
+use {
+    super::HdrTarget,
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::{any::Any, sync::Arc},
+};
+
+/// Parameters which control how accumulated samples are resolved.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AccumulationParams {
+    /// Multiplies the averaged samples.
+    pub exposure: f32,
+
+    /// Stop adding frames once this many samples are accumulated, 0 never
+    /// stops. The average keeps being resolved into the HDR image.
+    pub max_samples: u32,
+}
+
+impl Default for AccumulationParams {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            max_samples: 0,
+        }
+    }
+}
+
+/// Push constants for the accumulate shader. Must match
+/// AccumulateConstants in `shaders/accumulate.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct AccumulateConstants {
+    mode: u32,
+    scale: f32,
+}
+
+/// Averages many frames of an HdrTarget for progressive rendering.
+///
+/// Each frame renders one noisy sample, e.g. a path traced frame or a
+/// jittered generative render, into the HdrTarget. `record` adds it to an
+/// RGBA32F running sum, then overwrites the HdrTarget with the exposed
+/// average, so Bloom and Tonemap see the converged image.
+///
+/// The sum must be reset whenever the camera or scene changes, with `reset`
+/// or `reset_if_changed`.
+pub struct AccumulationTarget {
+    sample_count: u32,
+    needs_reset: bool,
+    last_key: Option<Box<dyn Any>>,
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _accumulation_view: raii::ImageView,
+    accumulation: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl AccumulationTarget {
+    /// The format of the running sum.
+    pub const FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+
+    /// Create the accumulation image and the compute pipeline.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the HdrTarget must outlive this instance
+    ///   - this instance must be rebuilt any time the HdrTarget is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        hdr_target: &HdrTarget,
+    ) -> Result<Self, GraphicsError> {
+        let extent = hdr_target.extent();
+        let (accumulation, accumulation_view) =
+            Self::create_image(&render_device, extent)?;
+        accumulation.set_debug_name("Accumulation Sum");
+
+        let bindings = [0, 1].map(|binding| vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            ..vk::DescriptorSetLayoutBinding::default()
+        });
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<AccumulateConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/accumulate.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Accumulate Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 2,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        let image_infos =
+            [hdr_target.image_view(), &accumulation_view].map(|image_view| {
+                vk::DescriptorImageInfo {
+                    sampler: vk::Sampler::null(),
+                    image_view: image_view.raw(),
+                    image_layout: vk::ImageLayout::GENERAL,
+                }
+            });
+        let writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, image_info)| vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                p_image_info: image_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<_>>();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            sample_count: 0,
+            needs_reset: true,
+            last_key: None,
+            extent,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _accumulation_view: accumulation_view,
+            accumulation,
+            render_device,
+        })
+    }
+
+    /// The number of samples in the running sum. Renderers can use it to
+    /// seed each frame's random numbers or jitter.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Discard the running sum. The next recorded frame starts a new
+    /// average.
+    pub fn reset(&mut self) {
+        self.needs_reset = true;
+        self.sample_count = 0;
+    }
+
+    /// Reset when the key differs from the key passed last time, e.g. the
+    /// camera's view matrix or a tuple of the render's parameters.
+    pub fn reset_if_changed<K>(&mut self, key: &K)
+    where
+        K: PartialEq + Clone + 'static,
+    {
+        let unchanged = self
+            .last_key
+            .as_ref()
+            .and_then(|last_key| last_key.downcast_ref::<K>())
+            .map(|last_key| last_key == key)
+            .unwrap_or(false);
+        if !unchanged {
+            self.last_key = Some(Box::new(key.clone()));
+            self.reset();
+        }
+    }
+
+    /// Add the HdrTarget's contents to the running sum, unless max_samples
+    /// are already accumulated, and replace them with the exposed average.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the HdrTarget's render pass must have ended earlier in the command
+    ///     buffer
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        params: &AccumulationParams,
+    ) {
+        let device = self.render_device.device();
+
+        // The sum's previous contents are discarded on reset.
+        let old_layout = if self.needs_reset {
+            vk::ImageLayout::UNDEFINED
+        } else {
+            vk::ImageLayout::GENERAL
+        };
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            old_layout,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: self.accumulation.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let hdr_memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::SHADER_WRITE
+                | vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &hdr_memory_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+
+        let full =
+            params.max_samples > 0 && self.sample_count >= params.max_samples;
+        let mode = if self.needs_reset {
+            self.sample_count = 1;
+            Self::MODE_RESET
+        } else if full {
+            Self::MODE_RESOLVE
+        } else {
+            self.sample_count += 1;
+            Self::MODE_ADD
+        };
+        self.needs_reset = false;
+        let constants = AccumulateConstants {
+            mode,
+            scale: params.exposure / self.sample_count as f32,
+        };
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const AccumulateConstants as *const u8,
+                std::mem::size_of::<AccumulateConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (self.extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl AccumulationTarget {
+    /// The compute shader's workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// Push constant modes. Must match `shaders/accumulate.comp`.
+    const MODE_RESET: u32 = 0;
+    const MODE_ADD: u32 = 1;
+    const MODE_RESOLVE: u32 = 2;
+
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Create the device-local sum image and a view for it.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+        Ok((image, view))
+    }
+}
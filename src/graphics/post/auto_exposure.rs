@@ -0,0 +1,337 @@
+use {
+    super::HdrTarget,
+    crate::graphics::{
+        gpu::{ImageAnalysis, ImageAnalysisParams},
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control automatic exposure.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AutoExposureParams {
+    /// The log2 of the darkest luminance included in the histogram.
+    pub min_log_luminance: f32,
+
+    /// The log2 of the brightest luminance included in the histogram.
+    pub max_log_luminance: f32,
+
+    /// How quickly the exposure adapts to changes in scene brightness. Larger
+    /// values adapt faster.
+    pub adaptation_rate: f32,
+}
+
+impl Default for AutoExposureParams {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -8.0,
+            max_log_luminance: 4.0,
+            adaptation_rate: 1.5,
+        }
+    }
+}
+
+/// Push constants for the average compute shader.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct ExposureConstants {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    time_coefficient: f32,
+    pixel_count: u32,
+}
+
+/// Automatic exposure computed from a luminance histogram of the HDR image.
+///
+/// Every frame, an ImageAnalysis bins each pixel's log luminance into a 256
+/// bin histogram, then a compute shader reduces the histogram to an average
+/// luminance which smoothly adapts over time. The Tonemap pass reads the
+/// average luminance to scale the image's exposure.
+pub struct AutoExposure {
+    extent: vk::Extent2D,
+    analysis: ImageAnalysis,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    average_pipeline: raii::Pipeline,
+    luminance: raii::Buffer,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl AutoExposure {
+    /// The number of bins in the luminance histogram.
+    pub const BIN_COUNT: u32 = ImageAnalysis::BIN_COUNT;
+
+    /// Create the histogram buffers and compute pipelines for the HDR target.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the HdrTarget is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        hdr_target: &HdrTarget,
+    ) -> Result<Self, GraphicsError> {
+        let analysis = ImageAnalysis::new(
+            render_device.clone(),
+            hdr_target.image_view(),
+            vk::ImageLayout::GENERAL,
+            hdr_target.extent(),
+        )?;
+        let luminance =
+            create_storage_buffer(&render_device, &[MIDDLE_GREY.to_bits()])?;
+        luminance.set_debug_name("Average Luminance");
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 2,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<ExposureConstants>() as u32,
+                }],
+            )?;
+        let average_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/luminance_average.comp.spv"),
+        )?;
+        average_pipeline.set_debug_name("Luminance Average Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 2,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        // The histogram is the first field of the analysis stats, so the
+        // average shader reads the stats buffer directly.
+        let buffer_infos =
+            [analysis.stats_buffer(), &luminance].map(|buffer| {
+                vk::DescriptorBufferInfo {
+                    buffer: buffer.raw(),
+                    offset: 0,
+                    range: vk::WHOLE_SIZE,
+                }
+            });
+        let writes = [
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 1,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: &buffer_infos[0],
+                ..vk::WriteDescriptorSet::default()
+            },
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 2,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: &buffer_infos[1],
+                ..vk::WriteDescriptorSet::default()
+            },
+        ];
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            extent: hdr_target.extent(),
+            analysis,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            average_pipeline,
+            luminance,
+            render_device,
+        })
+    }
+
+    /// The storage buffer which holds the adapted average luminance as a
+    /// single float.
+    pub fn luminance_buffer(&self) -> &raii::Buffer {
+        &self.luminance
+    }
+
+    /// The analysis of the HDR image, updated by each call to `record`.
+    ///
+    /// Copy its stats buffer into a ReadbackBuffer to inspect the histogram
+    /// or the image's min, max, and average on the CPU.
+    pub fn analysis(&self) -> &ImageAnalysis {
+        &self.analysis
+    }
+
+    /// Record commands which update the average luminance from the HDR image.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `params` - the auto exposure parameters
+    /// * `time_delta` - the time since the last update in seconds, used to
+    ///   smoothly adapt the exposure
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the HDR image must be in the GENERAL layout
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        params: &AutoExposureParams,
+        time_delta: f32,
+    ) {
+        let device = self.render_device.device();
+
+        // The analysis waits for the HDR image to be written. Also wait for
+        // the previous frame's tonemap pass to finish reading the average
+        // luminance.
+        self.analysis.cmd_analyze(
+            command_buffer,
+            &ImageAnalysisParams {
+                histogram_min: params.min_log_luminance,
+                histogram_max: params.max_log_luminance,
+                log_scale: true,
+            },
+        );
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        Self::memory_barrier(device, command_buffer, &memory_barrier);
+
+        let constants = ExposureConstants {
+            min_log_luminance: params.min_log_luminance,
+            log_luminance_range: params.max_log_luminance
+                - params.min_log_luminance,
+            time_coefficient: (1.0
+                - (-time_delta * params.adaptation_rate).exp())
+            .clamp(0.0, 1.0),
+            pixel_count: self.extent.width * self.extent.height,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const ExposureConstants as *const u8,
+                std::mem::size_of::<ExposureConstants>(),
+            ),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.average_pipeline.raw(),
+        );
+        device.cmd_dispatch(command_buffer, 1, 1, 1);
+
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        Self::memory_barrier(device, command_buffer, &memory_barrier);
+    }
+}
+
+// Private API
+// -----------
+
+impl AutoExposure {
+    unsafe fn memory_barrier(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: &vk::MemoryBarrier2,
+    ) {
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// The luminance which maps to an exposure of 1.0.
+pub(super) const MIDDLE_GREY: f32 = 0.18;
+
+/// Create a small host-visible storage buffer filled with the initial data.
+pub(super) unsafe fn create_storage_buffer(
+    render_device: &Arc<RenderDevice>,
+    initial_data: &[u32],
+) -> Result<raii::Buffer, GraphicsError> {
+    let queue_family_index = render_device.graphics_queue().family_index();
+    let create_info = vk::BufferCreateInfo {
+        size: std::mem::size_of_val(initial_data) as u64,
+        usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+        queue_family_index_count: 1,
+        p_queue_family_indices: &queue_family_index,
+        sharing_mode: vk::SharingMode::EXCLUSIVE,
+        ..Default::default()
+    };
+    let buffer = raii::Buffer::new(
+        render_device.clone(),
+        &create_info,
+        vk::MemoryPropertyFlags::HOST_VISIBLE
+            | vk::MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+    let ptr = buffer.allocation().map(render_device.device())?;
+    let data =
+        std::slice::from_raw_parts_mut(ptr as *mut u32, initial_data.len());
+    data.copy_from_slice(initial_data);
+    Ok(buffer)
+}
@@ -0,0 +1,531 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control the look of the bloom effect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct BloomParams {
+    /// Pixels with a luminance below this value do not contribute to bloom.
+    pub threshold: f32,
+
+    /// The width of the soft transition around the threshold.
+    pub knee: f32,
+
+    /// How much of the blurred bloom is added back to the source image.
+    pub intensity: f32,
+
+    /// The tent filter radius used while upsampling, in texels. Larger values
+    /// give a wider, softer glow.
+    pub radius: f32,
+}
+
+impl Default for BloomParams {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.8,
+            radius: 1.0,
+        }
+    }
+}
+
+/// A compute-based bloom effect.
+///
+/// Bright pixels are extracted from the source image into the first level of
+/// a mip chain, progressively downsampled, then progressively upsampled and
+/// accumulated back up the chain. The final result is added to the source
+/// image in place.
+///
+/// The source image must be created with STORAGE and SAMPLED usage and must be
+/// in the GENERAL layout when the bloom commands execute.
+pub struct Bloom {
+    extent: vk::Extent2D,
+    mip_extents: Vec<vk::Extent2D>,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    prefilter_pipeline: raii::Pipeline,
+    downsample_pipeline: raii::Pipeline,
+    upsample_pipeline: raii::Pipeline,
+    composite_pipeline: raii::Pipeline,
+    _sampler: raii::Sampler,
+    _mip_views: Vec<raii::ImageView>,
+    mip_chain: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl Bloom {
+    /// The format used for every level of the bloom mip chain.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// Create the bloom mip chain and compute pipelines for a source image.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `source_view` - the HDR image which bloom is read from and added to
+    /// * `extent` - the size of the source image
+    /// * `max_levels` - the maximum number of mip levels in the bloom chain.
+    ///   Fewer levels are used if the source image is too small.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the source image is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        source_view: &raii::ImageView,
+        extent: vk::Extent2D,
+        max_levels: u32,
+    ) -> Result<Self, GraphicsError> {
+        let mip_extents = Self::mip_extents(extent, max_levels);
+        let mip_chain = Self::create_mip_chain(&render_device, &mip_extents)?;
+        let mip_views =
+            Self::create_mip_views(&render_device, &mip_chain, &mip_extents)?;
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<BloomParams>() as u32,
+                }],
+            )?;
+
+        let prefilter_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/bloom_prefilter.comp.spv"),
+            )?;
+        prefilter_pipeline.set_debug_name("Bloom Prefilter Pipeline");
+        let downsample_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/bloom_downsample.comp.spv"),
+            )?;
+        downsample_pipeline.set_debug_name("Bloom Downsample Pipeline");
+        let upsample_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/bloom_upsample.comp.spv"),
+            )?;
+        upsample_pipeline.set_debug_name("Bloom Upsample Pipeline");
+        let composite_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &pipeline_layout,
+                include_bytes!("./shaders/bloom_composite.comp.spv"),
+            )?;
+        composite_pipeline.set_debug_name("Bloom Composite Pipeline");
+
+        // Each pass reads from one image and writes to another:
+        //   - prefilter: source -> mip 0
+        //   - downsample: mip i-1 -> mip i
+        //   - upsample: mip i+1 -> mip i
+        //   - composite: mip 0 -> source
+        let mut passes = vec![(source_view.raw(), mip_views[0].raw())];
+        for pair in mip_views.windows(2) {
+            passes.push((pair[0].raw(), pair[1].raw()));
+        }
+        for pair in mip_views.windows(2).rev() {
+            passes.push((pair[1].raw(), pair[0].raw()));
+        }
+        passes.push((mip_views[0].raw(), source_view.raw()));
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            passes.len() as u32,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: passes.len() as u32,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: passes.len() as u32,
+                },
+            ],
+        )?;
+        let layouts = vec![&descriptor_set_layout; passes.len()];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        for (index, (input, output)) in passes.iter().enumerate() {
+            let input_info = vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: *input,
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let output_info = vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: *output,
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let writes = [
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    p_image_info: &input_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 1,
+                    p_image_info: &output_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ];
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(Self {
+            extent,
+            mip_extents,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+            _sampler: sampler,
+            _mip_views: mip_views,
+            mip_chain,
+            render_device,
+        })
+    }
+
+    /// The number of levels in the bloom mip chain.
+    pub fn levels(&self) -> u32 {
+        self.mip_extents.len() as u32
+    }
+
+    /// Record the bloom compute passes into a command buffer.
+    ///
+    /// Writes to the source image from color attachments or compute shaders
+    /// earlier in the command buffer are made visible before bloom begins.
+    /// After this call, the source image is ready to be read by fragment or
+    /// compute shaders.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the source image must be in the GENERAL layout
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        params: &BloomParams,
+    ) {
+        let device = self.render_device.device();
+
+        // The mip chain's previous contents are never needed, so it's always
+        // transitioned from UNDEFINED. This also orders the bloom passes
+        // after any previous frame's use of the shared mip chain.
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: self.mip_chain.raw(),
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: self.levels(),
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        let source_memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &source_memory_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                params as *const BloomParams as *const u8,
+                std::mem::size_of::<BloomParams>(),
+            ),
+        );
+
+        let levels = self.mip_extents.len();
+        let mut pass = 0;
+
+        self.dispatch(
+            command_buffer,
+            &self.prefilter_pipeline,
+            pass,
+            self.mip_extents[0],
+        );
+        pass += 1;
+
+        for extent in &self.mip_extents[1..] {
+            self.dispatch(
+                command_buffer,
+                &self.downsample_pipeline,
+                pass,
+                *extent,
+            );
+            pass += 1;
+        }
+
+        for extent in self.mip_extents[..levels - 1].iter().rev() {
+            self.dispatch(
+                command_buffer,
+                &self.upsample_pipeline,
+                pass,
+                *extent,
+            );
+            pass += 1;
+        }
+
+        self.dispatch(
+            command_buffer,
+            &self.composite_pipeline,
+            pass,
+            self.extent,
+        );
+
+        // Make the composited source image visible to later passes.
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl Bloom {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// Bind the pipeline and descriptor set for a single pass, dispatch enough
+    /// workgroups to cover the output extent, then wait for the writes to be
+    /// visible to the next pass.
+    unsafe fn dispatch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &raii::Pipeline,
+        pass: usize,
+        output_extent: vk::Extent2D,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(pass)],
+            &[],
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (output_extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (output_extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Compute the extent of every level in the bloom chain. The first level
+    /// is half the size of the source image.
+    fn mip_extents(extent: vk::Extent2D, max_levels: u32) -> Vec<vk::Extent2D> {
+        let mut extents = vec![];
+        let mut width = (extent.width / 2).max(1);
+        let mut height = (extent.height / 2).max(1);
+        while extents.len() < max_levels.max(1) as usize {
+            extents.push(vk::Extent2D { width, height });
+            if width == 1 && height == 1 {
+                break;
+            }
+            width = (width / 2).max(1);
+            height = (height / 2).max(1);
+        }
+        extents
+    }
+
+    /// Create the device-local image which holds every level of the bloom
+    /// chain.
+    unsafe fn create_mip_chain(
+        render_device: &Arc<RenderDevice>,
+        mip_extents: &[vk::Extent2D],
+    ) -> Result<raii::Image, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: mip_extents.len() as u32,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: mip_extents[0].width,
+                height: mip_extents[0].height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name("Bloom Mip Chain");
+        Ok(image)
+    }
+
+    /// Create one image view for each level of the mip chain.
+    unsafe fn create_mip_views(
+        render_device: &Arc<RenderDevice>,
+        mip_chain: &raii::Image,
+        mip_extents: &[vk::Extent2D],
+    ) -> Result<Vec<raii::ImageView>, GraphicsError> {
+        let mut views = vec![];
+        for level in 0..mip_extents.len() as u32 {
+            let create_info = vk::ImageViewCreateInfo {
+                image: mip_chain.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: level,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                ..Default::default()
+            };
+            views.push(raii::ImageView::new(
+                render_device.clone(),
+                &create_info,
+            )?);
+        }
+        Ok(views)
+    }
+}
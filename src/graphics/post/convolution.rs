@@ -0,0 +1,543 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A separable 2D kernel, applied as a horizontal pass followed by a vertical
+/// pass.
+///
+/// Each list of weights must have an odd length, centered on the pixel being
+/// filtered, and at most `SeparableConvolution::MAX_TAPS` entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvolutionKernel {
+    /// Weights for the horizontal pass, from left to right.
+    pub horizontal: Vec<f32>,
+
+    /// Weights for the vertical pass, from top to bottom.
+    pub vertical: Vec<f32>,
+}
+
+impl ConvolutionKernel {
+    /// A kernel with different weights in each direction.
+    pub fn new(horizontal: Vec<f32>, vertical: Vec<f32>) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    /// A kernel with the same weights in both directions.
+    pub fn symmetric(weights: Vec<f32>) -> Self {
+        Self {
+            horizontal: weights.clone(),
+            vertical: weights,
+        }
+    }
+
+    /// A normalized gaussian blur. The radius is three standard deviations,
+    /// limited to the largest supported radius.
+    pub fn gaussian(sigma: f32) -> Self {
+        let max_radius = SeparableConvolution::MAX_TAPS as i32 / 2;
+        let sigma = sigma.max(f32::EPSILON);
+        let radius = ((3.0 * sigma).ceil() as i32).clamp(0, max_radius);
+        let weights: Vec<f32> = (-radius..=radius)
+            .map(|offset| {
+                let x = offset as f32;
+                (-(x * x) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        let total: f32 = weights.iter().sum();
+        Self::symmetric(weights.iter().map(|weight| weight / total).collect())
+    }
+
+    /// An evenly weighted blur over a square of `2 * radius + 1` pixels.
+    pub fn box_blur(radius: u32) -> Self {
+        let max_radius = SeparableConvolution::MAX_TAPS / 2;
+        let taps = 2 * radius.min(max_radius) + 1;
+        Self::symmetric(vec![1.0 / taps as f32; taps as usize])
+    }
+
+    /// Sharpens the image by subtracting `amount` of each neighbor. An
+    /// amount of 0 leaves the image unchanged.
+    pub fn sharpen(amount: f32) -> Self {
+        Self::symmetric(vec![-amount, 1.0 + 2.0 * amount, -amount])
+    }
+
+    /// The Sobel operator for horizontal gradients, which highlights
+    /// vertical edges. Results can be negative.
+    pub fn sobel_x() -> Self {
+        Self::new(vec![-1.0, 0.0, 1.0], vec![1.0, 2.0, 1.0])
+    }
+
+    /// The Sobel operator for vertical gradients, which highlights
+    /// horizontal edges. Results can be negative.
+    pub fn sobel_y() -> Self {
+        Self::new(vec![1.0, 2.0, 1.0], vec![-1.0, 0.0, 1.0])
+    }
+}
+
+/// The kernel as it is laid out in the uniform buffer. Must match Kernel in
+/// `shaders/separable_convolution.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct KernelUniform {
+    horizontal_radius: u32,
+    vertical_radius: u32,
+    _padding: [u32; 2],
+    horizontal: [f32; 64],
+    vertical: [f32; 64],
+}
+
+/// Applies a separable kernel to an image with two compute passes.
+///
+/// The horizontal pass reads the source image and writes an internal
+/// intermediate image, then the vertical pass reads the intermediate image
+/// and writes the destination. The destination can be the source image, in
+/// which case the convolution happens in place.
+///
+/// The source and destination images must be created with STORAGE and
+/// SAMPLED usage, must use the R16G16B16A16_SFLOAT format, and must be in
+/// the GENERAL layout when the commands execute. Pixels outside of the image
+/// are clamped to the edge.
+pub struct SeparableConvolution {
+    extent: vk::Extent2D,
+    kernel: raii::Buffer,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _sampler: raii::Sampler,
+    _intermediate_view: raii::ImageView,
+    intermediate: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl SeparableConvolution {
+    /// The format of the intermediate image, and the format the destination
+    /// image must have.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The largest number of weights in each direction.
+    pub const MAX_TAPS: u32 = 63;
+
+    /// Create the intermediate image, kernel uniform, and compute pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `source_view` - the image which is filtered
+    /// * `destination_view` - the image which receives the result, which can
+    ///   be the same as `source_view`
+    /// * `extent` - the size of both images
+    /// * `kernel` - the initial kernel
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time either image is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        source_view: &raii::ImageView,
+        destination_view: &raii::ImageView,
+        extent: vk::Extent2D,
+        kernel: &ConvolutionKernel,
+    ) -> Result<Self, GraphicsError> {
+        let intermediate = Self::create_intermediate(&render_device, extent)?;
+        let intermediate_view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: intermediate.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+
+        let kernel_buffer = {
+            let queue_family_index =
+                render_device.graphics_queue().family_index();
+            let create_info = vk::BufferCreateInfo {
+                size: std::mem::size_of::<KernelUniform>() as u64,
+                usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
+                queue_family_index_count: 1,
+                p_queue_family_indices: &queue_family_index,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                ..Default::default()
+            };
+            raii::Buffer::new(
+                render_device.clone(),
+                &create_info,
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?
+        };
+        kernel_buffer.set_debug_name("Convolution Kernel");
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 2,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<u32>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/separable_convolution.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Separable Convolution Pipeline");
+
+        // The horizontal pass reads the source and writes the intermediate
+        // image, the vertical pass reads the intermediate image and writes
+        // the destination.
+        let passes = [
+            (source_view.raw(), intermediate_view.raw()),
+            (intermediate_view.raw(), destination_view.raw()),
+        ];
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            passes.len() as u32,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: passes.len() as u32,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: passes.len() as u32,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: passes.len() as u32,
+                },
+            ],
+        )?;
+        let layouts = vec![&descriptor_set_layout; passes.len()];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        let kernel_info = vk::DescriptorBufferInfo {
+            buffer: kernel_buffer.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        };
+        for (index, (input, output)) in passes.iter().enumerate() {
+            let input_info = vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: *input,
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let output_info = vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: *output,
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let writes = [
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    p_image_info: &input_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 1,
+                    p_image_info: &output_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 2,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: &kernel_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ];
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        let mut convolution = Self {
+            extent,
+            kernel: kernel_buffer,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _sampler: sampler,
+            _intermediate_view: intermediate_view,
+            intermediate,
+            render_device,
+        };
+        convolution.set_kernel(kernel)?;
+        Ok(convolution)
+    }
+
+    /// Replace the kernel used by later calls to `record`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the kernel uniform buffer must not be in use by the GPU
+    pub unsafe fn set_kernel(
+        &mut self,
+        kernel: &ConvolutionKernel,
+    ) -> Result<(), GraphicsError> {
+        for weights in [&kernel.horizontal, &kernel.vertical] {
+            if weights.len() % 2 == 0 || weights.len() > Self::MAX_TAPS as usize
+            {
+                return Err(GraphicsError::RuntimeError(anyhow!(
+                    "Convolution kernels need an odd number of weights, at \
+                    most {}, but got {}",
+                    Self::MAX_TAPS,
+                    weights.len()
+                )));
+            }
+        }
+
+        let mut uniform = KernelUniform {
+            horizontal_radius: kernel.horizontal.len() as u32 / 2,
+            vertical_radius: kernel.vertical.len() as u32 / 2,
+            _padding: [0; 2],
+            horizontal: [0.0; 64],
+            vertical: [0.0; 64],
+        };
+        uniform.horizontal[..kernel.horizontal.len()]
+            .copy_from_slice(&kernel.horizontal);
+        uniform.vertical[..kernel.vertical.len()]
+            .copy_from_slice(&kernel.vertical);
+
+        let device = self.render_device.device();
+        let ptr = self.kernel.allocation().map(device)? as *mut KernelUniform;
+        std::ptr::write(ptr, uniform);
+        self.kernel.allocation().unmap(device)?;
+        Ok(())
+    }
+
+    /// Record the horizontal and vertical passes into a command buffer.
+    ///
+    /// Writes to the source image from color attachments or compute shaders
+    /// earlier in the command buffer are made visible before the first pass.
+    /// After this call, the destination image is ready to be read by
+    /// fragment or compute shaders.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the source and destination images must be in the GENERAL layout
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(&self, command_buffer: vk::CommandBuffer) {
+        let device = self.render_device.device();
+
+        // The intermediate image's previous contents are never needed, so
+        // it's always transitioned from UNDEFINED.
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: self.intermediate.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let source_memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &source_memory_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        for pass in 0..2 {
+            self.dispatch(command_buffer, pass);
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl SeparableConvolution {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// The intermediate image has a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Dispatch one pass, 0 for horizontal and 1 for vertical, then make its
+    /// writes visible to the next pass and to fragment shaders.
+    unsafe fn dispatch(&self, command_buffer: vk::CommandBuffer, pass: u32) {
+        let device = self.render_device.device();
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(pass as usize)],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            &pass.to_ne_bytes(),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (self.extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create the device-local image which holds the horizontal pass's
+    /// result.
+    unsafe fn create_intermediate(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<raii::Image, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name("Convolution Intermediate");
+        Ok(image)
+    }
+}
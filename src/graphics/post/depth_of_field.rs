@@ -0,0 +1,482 @@
+use {
+    super::{HdrTarget, Taa},
+    crate::{
+        graphics::{
+            vulkan_api::{raii, RenderDevice},
+            GraphicsError,
+        },
+        math::{Mat4, Vec3},
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control the focus and the strength of depth of field.
+///
+/// These are plain values, so they can be animated from `update`, e.g. by
+/// following a Spring or by calling `focus_on` with the camera's view matrix
+/// every frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DofParams {
+    /// The view-space distance which is perfectly sharp.
+    pub focus_distance: f32,
+
+    /// The blur radius, in pixels, of objects infinitely far away. Larger
+    /// values give a shallower depth of field, like a wider lens aperture.
+    /// 0.0 disables the blur.
+    pub aperture: f32,
+
+    /// The largest blur radius in pixels. The cost grows with its square.
+    /// Clamped to MAX_RADIUS.
+    pub max_radius: f32,
+
+    /// The near clipping plane of the projection the scene was drawn with.
+    pub near: f32,
+
+    /// The far clipping plane of the projection the scene was drawn with.
+    pub far: f32,
+}
+
+impl Default for DofParams {
+    fn default() -> Self {
+        Self {
+            focus_distance: 10.0,
+            aperture: 8.0,
+            max_radius: 16.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl DofParams {
+    /// Focus on a point in world space.
+    ///
+    /// # Params
+    ///
+    /// * `view` - the camera's view matrix, like the one from `look_at`
+    /// * `point` - the world-space point which should be sharp
+    pub fn focus_on(&mut self, view: &Mat4, point: &Vec3) {
+        let view_point = view.transform_point(&(*point).into());
+        self.focus_distance = (-view_point.z).max(self.near);
+    }
+}
+
+/// Push constants for the depth of field shader. Must match DofConstants in
+/// `shaders/depth_of_field.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct DofConstants {
+    near: f32,
+    far: f32,
+    focus_distance: f32,
+    aperture: f32,
+    max_radius: f32,
+}
+
+/// A depth of field effect which blurs the scene based on distance from the
+/// focus, using the depth written during Taa's render pass.
+///
+/// Each pixel's circle of confusion is computed from its depth, then bokeh is
+/// gathered along a spiral of taps in a single compute pass. Depth must be
+/// written by a `perspective` projection with the near and far planes given
+/// in DofParams.
+///
+/// The result is written to an owned image then copied back into the HDR
+/// image, so record it after Taa and before effects like Bloom and Tonemap.
+/// Taa must not use MSAA because the multisampled depth can't be filtered.
+pub struct DepthOfField {
+    extent: vk::Extent2D,
+    hdr_image: vk::Image,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _samplers: [raii::Sampler; 2],
+    _output_view: raii::ImageView,
+    output: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl DepthOfField {
+    /// The format of the blurred image.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The largest supported blur radius in pixels.
+    pub const MAX_RADIUS: f32 = 32.0;
+
+    /// Create the output image and the compute pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `hdr_target` - the image which is blurred
+    /// * `taa` - provides the depth image, must not use MSAA
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the HdrTarget and Taa must outlive this instance
+    ///   - this instance must be rebuilt any time the HdrTarget or Taa is
+    ///     rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        hdr_target: &HdrTarget,
+        taa: &Taa,
+    ) -> Result<Self, GraphicsError> {
+        if taa.samples() != vk::SampleCountFlags::TYPE_1 {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Depth of field requires a single-sample depth image, but Taa \
+                 uses {:?}",
+                taa.mode()
+            )));
+        }
+
+        let extent = hdr_target.extent();
+        let (output, output_view) = Self::create_image(&render_device, extent)?;
+        output.set_debug_name("Depth of Field Output");
+
+        // The scene is read between texels, depth is never interpolated.
+        let samplers =
+            [vk::Filter::LINEAR, vk::Filter::NEAREST].map(|filter| {
+                let create_info = vk::SamplerCreateInfo {
+                    mag_filter: filter,
+                    min_filter: filter,
+                    mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                    address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    max_lod: 0.0,
+                    ..Default::default()
+                };
+                raii::Sampler::new(render_device.clone(), &create_info)
+            });
+        let [linear, nearest] = samplers;
+        let samplers = [linear?, nearest?];
+
+        let bindings = (0..3)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: if binding == 2 {
+                    vk::DescriptorType::STORAGE_IMAGE
+                } else {
+                    vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                },
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<DofConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/depth_of_field.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Depth of Field Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 2,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 1,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+        let image_infos = [
+            vk::DescriptorImageInfo {
+                sampler: samplers[0].raw(),
+                image_view: hdr_target.image_view().raw(),
+                image_layout: vk::ImageLayout::GENERAL,
+            },
+            vk::DescriptorImageInfo {
+                sampler: samplers[1].raw(),
+                image_view: taa.depth_view().raw(),
+                image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            },
+            vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: output_view.raw(),
+                image_layout: vk::ImageLayout::GENERAL,
+            },
+        ];
+        let writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, image_info)| vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: binding as u32,
+                dst_array_element: 0,
+                descriptor_type: bindings[binding].descriptor_type,
+                descriptor_count: 1,
+                p_image_info: image_info,
+                ..vk::WriteDescriptorSet::default()
+            })
+            .collect::<Vec<_>>();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            extent,
+            hdr_image: hdr_target.image().raw(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _samplers: samplers,
+            _output_view: output_view,
+            output,
+            render_device,
+        })
+    }
+
+    /// Record the blur and the copy back into the HDR image. Does nothing
+    /// when the aperture is 0.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - Taa's render pass must have ended earlier in the command buffer
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        params: &DofParams,
+    ) {
+        if params.aperture <= 0.0 {
+            return;
+        }
+        let device = self.render_device.device();
+
+        // The output's previous contents are never needed. Wait for the
+        // previous frame to finish copying it.
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: self.output.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let source_memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::SHADER_WRITE
+                | vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &source_memory_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+
+        let constants = DofConstants {
+            near: params.near,
+            far: params.far,
+            focus_distance: params.focus_distance.max(params.near),
+            aperture: params.aperture,
+            max_radius: params.max_radius.clamp(0.0, Self::MAX_RADIUS),
+        };
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const DofConstants as *const u8,
+                std::mem::size_of::<DofConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (self.extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+
+        // The copy overwrites the HDR image, so the blur must also be done
+        // reading it.
+        self.memory_barrier(
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COPY,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_READ
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+                ..Default::default()
+            },
+        );
+        let subresource = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        device.cmd_copy_image(
+            command_buffer,
+            self.output.raw(),
+            vk::ImageLayout::GENERAL,
+            self.hdr_image,
+            vk::ImageLayout::GENERAL,
+            &[vk::ImageCopy {
+                src_subresource: subresource,
+                src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                dst_subresource: subresource,
+                dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                extent: vk::Extent3D {
+                    width: self.extent.width,
+                    height: self.extent.height,
+                    depth: 1,
+                },
+            }],
+        );
+        self.memory_barrier(
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl DepthOfField {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// The output image has a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    unsafe fn memory_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: &vk::MemoryBarrier2,
+    ) {
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create the output image and its view.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+        Ok((image, view))
+    }
+}
@@ -0,0 +1,434 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for both FSR passes. Must match FsrConstants in
+/// `shaders/fsr.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct FsrConstants {
+    source_size: [i32; 2],
+    output_size: [i32; 2],
+    sharpness: f32,
+}
+
+/// A compute port of AMD FidelityFX Super Resolution 1.0.
+///
+/// The EASU pass upscales the top left `source_extent` area of a source image
+/// into an intermediate image, then the RCAS pass sharpens the result into the
+/// output image. Both images are the output size and stay in the GENERAL
+/// layout.
+///
+/// FSR expects colors in [0, 1], so HDR input is tonemapped with a reversible
+/// curve by EASU and restored to linear HDR by RCAS.
+pub struct Fsr {
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    easu_pipeline: raii::Pipeline,
+    rcas_pipeline: raii::Pipeline,
+    _sampler: raii::Sampler,
+    _intermediate_view: raii::ImageView,
+    intermediate: raii::Image,
+    output_view: raii::ImageView,
+    output: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl Fsr {
+    /// The format of the intermediate and output images.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The default RCAS sharpness, in stops. 0 is the sharpest and each stop
+    /// halves the sharpening.
+    pub const DEFAULT_SHARPNESS: f32 = 0.2;
+
+    /// Create the intermediate and output images and the compute pipelines.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `source_view` - the image to upscale
+    /// * `source_layout` - the layout the source image is in when `record`
+    ///   executes
+    /// * `extent` - the size of the output image
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the source image view must outlive this instance
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        source_view: &raii::ImageView,
+        source_layout: vk::ImageLayout,
+        extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        let (intermediate, intermediate_view) =
+            Self::create_image(&render_device, extent)?;
+        intermediate.set_debug_name("FSR EASU Image");
+        let (output, output_view) = Self::create_image(&render_device, extent)?;
+        output.set_debug_name("FSR RCAS Image");
+
+        // Both passes read with texelFetch, so the filter never matters.
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<FsrConstants>() as u32,
+                }],
+            )?;
+        let easu_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/fsr_easu.comp.spv"),
+        )?;
+        easu_pipeline.set_debug_name("FSR EASU Pipeline");
+        let rcas_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/fsr_rcas.comp.spv"),
+        )?;
+        rcas_pipeline.set_debug_name("FSR RCAS Pipeline");
+
+        // EASU reads the source and writes the intermediate image, RCAS reads
+        // the intermediate image and writes the output.
+        let passes = [
+            (source_view.raw(), source_layout, intermediate_view.raw()),
+            (
+                intermediate_view.raw(),
+                vk::ImageLayout::GENERAL,
+                output_view.raw(),
+            ),
+        ];
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            passes.len() as u32,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: passes.len() as u32,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: passes.len() as u32,
+                },
+            ],
+        )?;
+        let layouts = vec![&descriptor_set_layout; passes.len()];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        for (index, (input, input_layout, output)) in passes.iter().enumerate()
+        {
+            let input_info = vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: *input,
+                image_layout: *input_layout,
+            };
+            let output_info = vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: *output,
+                image_layout: vk::ImageLayout::GENERAL,
+            };
+            let writes = [
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    p_image_info: &input_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 1,
+                    p_image_info: &output_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ];
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(Self {
+            extent,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            easu_pipeline,
+            rcas_pipeline,
+            _sampler: sampler,
+            _intermediate_view: intermediate_view,
+            intermediate,
+            output_view,
+            output,
+            render_device,
+        })
+    }
+
+    /// The size of the output image.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The upscaled and sharpened image. It's always in the GENERAL layout.
+    pub fn output(&self) -> &raii::Image {
+        &self.output
+    }
+
+    /// The image view for the output image.
+    pub fn output_view(&self) -> &raii::ImageView {
+        &self.output_view
+    }
+
+    /// Record the EASU and RCAS passes into a command buffer.
+    ///
+    /// Writes to the source image from color attachments or compute shaders
+    /// earlier in the command buffer are made visible before the first pass.
+    /// After this call, the output image is ready to be read by fragment or
+    /// compute shaders.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `source_extent` - the size of the area in the top left corner of the
+    ///   source image which is upscaled
+    /// * `sharpness` - RCAS sharpening in stops, see DEFAULT_SHARPNESS
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the source image must be in the layout provided to `new`
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        source_extent: vk::Extent2D,
+        sharpness: f32,
+    ) {
+        let device = self.render_device.device();
+
+        // Neither image's previous contents are needed, so they're always
+        // transitioned from UNDEFINED. Wait for the previous frame to finish
+        // reading them.
+        let image_memory_barriers =
+            [&self.intermediate, &self.output].map(|image| {
+                vk::ImageMemoryBarrier2 {
+                    src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                        | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                    src_access_mask: vk::AccessFlags2::NONE,
+                    dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    dst_access_mask: vk::AccessFlags2::SHADER_READ
+                        | vk::AccessFlags2::SHADER_WRITE,
+                    old_layout: vk::ImageLayout::UNDEFINED,
+                    new_layout: vk::ImageLayout::GENERAL,
+                    image: image.raw(),
+                    subresource_range: Self::SUBRESOURCE_RANGE,
+                    ..Default::default()
+                }
+            });
+        let source_memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &source_memory_barrier,
+                image_memory_barrier_count: image_memory_barriers.len() as u32,
+                p_image_memory_barriers: image_memory_barriers.as_ptr(),
+                ..Default::default()
+            },
+        );
+
+        let constants = FsrConstants {
+            source_size: [
+                source_extent.width as i32,
+                source_extent.height as i32,
+            ],
+            output_size: [self.extent.width as i32, self.extent.height as i32],
+            sharpness: sharpness.max(0.0),
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const FsrConstants as *const u8,
+                std::mem::size_of::<FsrConstants>(),
+            ),
+        );
+        self.dispatch(command_buffer, &self.easu_pipeline, 0);
+        self.dispatch(command_buffer, &self.rcas_pipeline, 1);
+    }
+}
+
+// Private API
+// -----------
+
+impl Fsr {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// The images have a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Dispatch one pass over the output, then make its writes visible to
+    /// the next pass and to fragment shaders.
+    unsafe fn dispatch(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline: &raii::Pipeline,
+        descriptor_set: usize,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(descriptor_set)],
+            &[],
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (self.extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Create an output-sized storage image and its view.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+        Ok((image, view))
+    }
+}
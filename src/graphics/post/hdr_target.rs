@@ -0,0 +1,256 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// An offscreen RGBA16F render target for HDR scene rendering.
+///
+/// The scene is rendered into the target with a single-subpass render pass.
+/// When the render pass ends, the image is left in the GENERAL layout so
+/// post-processing compute effects like Bloom can read and write it, and the
+/// Tonemap pass can sample it.
+///
+/// The target is shared by every frame in flight.
+#[derive(Debug)]
+pub struct HdrTarget {
+    extent: vk::Extent2D,
+    render_pass: raii::RenderPass,
+    framebuffer: raii::Framebuffer,
+    image_view: raii::ImageView,
+    image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl HdrTarget {
+    /// The format of the HDR image.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// Create the HDR image, render pass, and framebuffer.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `extent` - the size of the HDR image. This is typically the swapchain
+    ///   extent.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the target must be dropped before the render device
+    ///  - the target should be rebuilt when the swapchain is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        let image = {
+            let queue_family_index =
+                render_device.graphics_queue().family_index();
+            let create_info = vk::ImageCreateInfo {
+                image_type: vk::ImageType::TYPE_2D,
+                format: Self::FORMAT,
+                mip_levels: 1,
+                array_layers: 1,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                samples: vk::SampleCountFlags::TYPE_1,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                queue_family_index_count: 1,
+                p_queue_family_indices: &queue_family_index,
+                tiling: vk::ImageTiling::OPTIMAL,
+                usage: vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::STORAGE
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::SAMPLED,
+                flags: vk::ImageCreateFlags::empty(),
+                extent: vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                ..vk::ImageCreateInfo::default()
+            };
+            raii::Image::new_with_hint(
+                render_device.clone(),
+                &create_info,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                raii::AllocationHint::Dedicated,
+            )?
+        };
+        image.set_debug_name("HDR Target");
+
+        let image_view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+
+        let render_pass = Self::create_render_pass(render_device.clone())?;
+
+        let raw_image_view = image_view.raw();
+        let framebuffer = {
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass: render_pass.raw(),
+                attachment_count: 1,
+                p_attachments: &raw_image_view,
+                width: extent.width,
+                height: extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            raii::Framebuffer::new(render_device.clone(), &create_info)?
+        };
+
+        Ok(Self {
+            extent,
+            render_pass,
+            framebuffer,
+            image_view,
+            image,
+            render_device,
+        })
+    }
+
+    /// The size of the HDR image.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The render pass used to draw into the HDR image.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// The HDR image.
+    pub fn image(&self) -> &raii::Image {
+        &self.image
+    }
+
+    /// The image view for the HDR image.
+    pub fn image_view(&self) -> &raii::ImageView {
+        &self.image_view
+    }
+
+    /// Begin the render pass for the HDR image. The image is cleared to the
+    /// clear color.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the HdrTarget must not be destroyed until the command buffer
+    ///     finishes executing or is discarded.
+    pub unsafe fn begin_render_pass_inline(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+    ) {
+        let clear_values = [vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: clear_color,
+            },
+        }];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffer.raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl HdrTarget {
+    /// Create a render pass with a single color subpass which leaves the HDR
+    /// image in the GENERAL layout.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let attachments = [vk::AttachmentDescription {
+            format: Self::FORMAT,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout: vk::ImageLayout::GENERAL,
+            flags: vk::AttachmentDescriptionFlags::empty(),
+        }];
+        let color_attachment = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+        let subpasses = [vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment,
+            ..Default::default()
+        }];
+        let dependencies = [
+            // The HDR image is shared between frames in flight, so wait for
+            // the previous frame's post-processing to finish with it.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::COMPUTE_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // Make the rendered image visible to post-processing.
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::COMPUTE_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::SHADER_READ
+                    | vk::AccessFlags::SHADER_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+        let create_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
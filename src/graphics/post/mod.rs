@@ -0,0 +1,60 @@
+//! Post-processing effects which run on an HDR image after the scene has been
+//! rendered.
+//!
+//! A typical frame renders the scene into an HdrTarget, records compute
+//! effects like Bloom and AutoExposure directly into the frame's command
+//! buffer, then draws the Tonemap pass into a ColorPass which targets the
+//! swapchain.
+//!
+//! SeparableConvolution is a standalone building block which applies any
+//! separable kernel, like a gaussian blur, sharpen, or Sobel edge detector,
+//! to an image with a horizontal and a vertical compute pass.
+//!
+//! RenderScale renders the scene at a resolution independent of the
+//! swapchain, then filters it onto the ColorPass. The scale can change every
+//! frame without recreating the swapchain. Low scales can be upscaled with
+//! bilinear or bicubic filtering, or with Fsr, a compute port of AMD FSR 1.0.
+//!
+//! Taa owns the render pass for an HdrTarget and adds temporal
+//! anti-aliasing, or MSAA for comparison. Pipelines drawn with it also write
+//! per-pixel velocity.
+//!
+//! MotionBlur reuses that velocity to blur each pixel along its motion, which
+//! gives smooth streaks in animations exported with batch mode.
+//!
+//! DepthOfField uses Taa's depth to blur everything away from a focus
+//! distance which can be animated from `update`.
+//!
+//! Ssao runs earlier in the frame, between a DepthPrePass and the pass which
+//! lights the scene, and produces an ambient occlusion image for lighting.
+//!
+//! AccumulationTarget averages the HdrTarget over many frames for progressive
+//! rendering, like path tracing, and resets whenever the camera changes.
+
+mod accumulation;
+mod auto_exposure;
+mod bloom;
+mod convolution;
+mod depth_of_field;
+mod fsr;
+mod hdr_target;
+mod motion_blur;
+mod render_scale;
+mod ssao;
+mod taa;
+mod tonemap;
+
+pub use self::{
+    accumulation::{AccumulationParams, AccumulationTarget},
+    auto_exposure::{AutoExposure, AutoExposureParams},
+    bloom::{Bloom, BloomParams},
+    convolution::{ConvolutionKernel, SeparableConvolution},
+    depth_of_field::{DepthOfField, DofParams},
+    fsr::Fsr,
+    hdr_target::HdrTarget,
+    motion_blur::{MotionBlur, MotionBlurParams},
+    render_scale::{RenderScale, ScaleFilter},
+    ssao::{Ssao, SsaoParams},
+    taa::{AntiAliasing, Taa, TaaParams},
+    tonemap::{Tonemap, TonemapOperator, TonemapParams},
+};
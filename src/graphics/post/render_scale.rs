@@ -0,0 +1,638 @@
+use {
+    super::Fsr,
+    crate::graphics::{
+        vulkan_api::{
+            raii, ColorPass, DepthPrePass, Frame, GraphicsPipelineBuilder,
+            RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// How the scaled scene is filtered when it is drawn to the screen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScaleFilter {
+    /// Blocky pixels, good for pixel art and integer scales.
+    Nearest = 0,
+
+    /// Bilinear filtering, good for smooth content at fractional scales.
+    Linear = 1,
+
+    /// Catmull-Rom bicubic filtering, sharper than Linear when upscaling.
+    Bicubic = 2,
+
+    /// AMD FidelityFX Super Resolution 1.0, edge adaptive upscaling followed
+    /// by sharpening. Requires `RenderScale::record` each frame.
+    Fsr = 3,
+}
+
+/// Push constants for the upscale fragment shader. Must match
+/// ScaleConstants in `shaders/render_scale.frag`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct ScaleConstants {
+    uv_scale: [f32; 2],
+    uv_max: [f32; 2],
+    source_size: [f32; 2],
+    filter_mode: u32,
+}
+
+/// Renders the scene at a different resolution than the swapchain.
+///
+/// The offscreen color and depth images are allocated once, large enough
+/// for `max_scale`. Each frame the scene is rendered into the top left
+/// corner of the images, sized by the current scale, then `draw` filters
+/// that area onto every pixel of a ColorPass. When the filter is
+/// ScaleFilter::Fsr, `record` must run between the two passes to upscale the
+/// scene with compute shaders. Changing the scale only
+/// changes the render area, so it can be adjusted every frame without
+/// touching the swapchain or reallocating anything.
+///
+/// Pipelines which draw the scene must be built with `render_pass` and
+/// should set their viewport and scissor to `scaled_extent`.
+pub struct RenderScale {
+    /// The filter used by `draw`.
+    pub filter: ScaleFilter,
+
+    /// The RCAS sharpness, in stops, used by ScaleFilter::Fsr.
+    pub sharpness: f32,
+
+    scale: f32,
+    max_scale: f32,
+    output_extent: vk::Extent2D,
+    max_extent: vk::Extent2D,
+    render_pass: raii::RenderPass,
+    framebuffer: raii::Framebuffer,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _samplers: [raii::Sampler; 2],
+    fsr: Fsr,
+    _depth_view: raii::ImageView,
+    _depth_image: raii::Image,
+    color_view: raii::ImageView,
+    color_image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl RenderScale {
+    /// The format of the offscreen color image.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// The smallest supported scale.
+    pub const MIN_SCALE: f32 = 0.25;
+
+    /// Create the offscreen images and the pipeline which draws them into a
+    /// ColorPass. The scale starts at 1.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `color_pass` - the render pass the scaled scene is drawn into,
+    ///   usually targeting the swapchain
+    /// * `max_scale` - the largest scale `set_scale` accepts, e.g. 2.0 for
+    ///   supersampling. The offscreen images are allocated at this scale.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        color_pass: &ColorPass,
+        max_scale: f32,
+    ) -> Result<Self, GraphicsError> {
+        let max_scale = max_scale.max(1.0);
+        let output_extent = color_pass.extent();
+        let max_extent = vk::Extent2D {
+            width: ((output_extent.width as f32 * max_scale).ceil() as u32)
+                .max(1),
+            height: ((output_extent.height as f32 * max_scale).ceil() as u32)
+                .max(1),
+        };
+
+        let (color_image, color_view) = Self::create_image(
+            &render_device,
+            max_extent,
+            Self::FORMAT,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        color_image.set_debug_name("Render Scale Color");
+        let (depth_image, depth_view) = Self::create_image(
+            &render_device,
+            max_extent,
+            DepthPrePass::DEPTH_FORMAT,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::ImageAspectFlags::DEPTH,
+        )?;
+        depth_image.set_debug_name("Render Scale Depth");
+
+        let render_pass = Self::create_render_pass(render_device.clone())?;
+        let attachments = [color_view.raw(), depth_view.raw()];
+        let framebuffer = {
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass: render_pass.raw(),
+                attachment_count: attachments.len() as u32,
+                p_attachments: attachments.as_ptr(),
+                width: max_extent.width,
+                height: max_extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            raii::Framebuffer::new(render_device.clone(), &create_info)?
+        };
+
+        let fsr = Fsr::new(
+            render_device.clone(),
+            &color_view,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            output_extent,
+        )?;
+
+        // Nearest and linear samplers for the scene, bicubic and FSR use
+        // texelFetch so they don't need their own.
+        let samplers =
+            [vk::Filter::NEAREST, vk::Filter::LINEAR].map(|filter| {
+                let create_info = vk::SamplerCreateInfo {
+                    mag_filter: filter,
+                    min_filter: filter,
+                    mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                    address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    max_lod: 0.0,
+                    ..Default::default()
+                };
+                raii::Sampler::new(render_device.clone(), &create_info)
+            });
+        let [nearest, linear] = samplers;
+        let samplers = [nearest?, linear?];
+
+        let bindings = [vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<ScaleConstants>() as u32,
+                }],
+            )?;
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!(
+                    "./shaders/render_scale.vert.spv"
+                ))
+                .fragment_shader(include_bytes!(
+                    "./shaders/render_scale.frag.spv"
+                ))
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .build(
+                    render_device.clone(),
+                    &pipeline_layout,
+                    color_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Render Scale Pipeline");
+
+        // Descriptor sets for the scene with each sampler, then FSR's output.
+        let sources = [
+            (
+                &samplers[0],
+                color_view.raw(),
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (
+                &samplers[1],
+                color_view.raw(),
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (
+                &samplers[0],
+                fsr.output_view().raw(),
+                vk::ImageLayout::GENERAL,
+            ),
+        ];
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            sources.len() as u32,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: sources.len() as u32,
+            }],
+        )?;
+        let layouts = vec![&descriptor_set_layout; sources.len()];
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        for (index, (sampler, image_view, image_layout)) in
+            sources.iter().enumerate()
+        {
+            let image_info = vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: *image_view,
+                image_layout: *image_layout,
+            };
+            let write = vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(index),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            };
+            render_device.device().update_descriptor_sets(&[write], &[]);
+        }
+
+        Ok(Self {
+            filter: ScaleFilter::Linear,
+            sharpness: Fsr::DEFAULT_SHARPNESS,
+            scale: 1.0,
+            max_scale,
+            output_extent,
+            max_extent,
+            render_pass,
+            framebuffer,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _samplers: samplers,
+            fsr,
+            _depth_view: depth_view,
+            _depth_image: depth_image,
+            color_view,
+            color_image,
+            render_device,
+        })
+    }
+
+    /// The current scale.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The largest supported scale.
+    pub fn max_scale(&self) -> f32 {
+        self.max_scale
+    }
+
+    /// Change the resolution the scene is rendered at, relative to the
+    /// ColorPass. The scale is clamped to [MIN_SCALE, max_scale].
+    ///
+    /// Takes effect the next time the render pass begins.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.clamp(Self::MIN_SCALE, self.max_scale);
+    }
+
+    /// The size of the area the scene is rendered into at the current scale.
+    pub fn scaled_extent(&self) -> vk::Extent2D {
+        let scale = |size: u32, max: u32| {
+            ((size as f32 * self.scale).round() as u32).clamp(1, max)
+        };
+        vk::Extent2D {
+            width: scale(self.output_extent.width, self.max_extent.width),
+            height: scale(self.output_extent.height, self.max_extent.height),
+        }
+    }
+
+    /// The size of the ColorPass the scene is drawn into.
+    pub fn output_extent(&self) -> vk::Extent2D {
+        self.output_extent
+    }
+
+    /// The render pass used to draw the scene. It has a color attachment
+    /// with FORMAT and a depth attachment with DepthPrePass::DEPTH_FORMAT.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// The offscreen color image. Only the `scaled_extent` area in the top
+    /// left corner holds the current scene.
+    pub fn image(&self) -> &raii::Image {
+        &self.color_image
+    }
+
+    /// The image view for the offscreen color image.
+    pub fn image_view(&self) -> &raii::ImageView {
+        &self.color_view
+    }
+
+    /// Begin the render pass for the scaled scene. The color is cleared to
+    /// the clear color and depth is cleared to 1.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must not be destroyed until the command buffer
+    ///     finishes executing or is discarded.
+    pub unsafe fn begin_render_pass_inline(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+    ) {
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffer.raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.scaled_extent(),
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+
+    /// Record compute passes which upscale the scene when the filter is
+    /// ScaleFilter::Fsr. Does nothing for the other filters.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the scene's render pass must have ended earlier in the command
+    ///     buffer
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(&self, command_buffer: vk::CommandBuffer) {
+        if self.filter == ScaleFilter::Fsr {
+            self.fsr.record(
+                command_buffer,
+                self.scaled_extent(),
+                self.sharpness,
+            );
+        }
+    }
+
+    /// Add commands to draw the scaled scene into every pixel of the
+    /// current render pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - the scene's render pass must have ended earlier in the frame with
+    ///     the same scale
+    ///   - `record` must have been called after the scene's render pass with
+    ///     the same filter
+    pub unsafe fn draw(&self, frame: &Frame) {
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.output_extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.output_extent,
+            }],
+        );
+
+        // FSR's output already matches the ColorPass, so it's copied 1:1
+        // with the nearest filter.
+        let (descriptor_set, source, size) = match self.filter {
+            ScaleFilter::Fsr => (2, self.output_extent, self.output_extent),
+            ScaleFilter::Bicubic => (0, self.scaled_extent(), self.max_extent),
+            _ => (self.filter as usize, self.scaled_extent(), self.max_extent),
+        };
+        let filter_mode = match self.filter {
+            ScaleFilter::Fsr => ScaleFilter::Nearest,
+            filter => filter,
+        };
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(descriptor_set)],
+            &[],
+        );
+
+        let width = source.width as f32;
+        let height = source.height as f32;
+        let constants = ScaleConstants {
+            uv_scale: [width / size.width as f32, height / size.height as f32],
+            uv_max: [
+                (width - 0.5) / size.width as f32,
+                (height - 0.5) / size.height as f32,
+            ],
+            source_size: [width, height],
+            filter_mode: filter_mode as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const ScaleConstants as *const u8,
+                std::mem::size_of::<ScaleConstants>(),
+            ),
+        );
+        device.cmd_draw(command_buffer, 3, 1, 0, 0);
+    }
+}
+
+// Private API
+// -----------
+
+impl RenderScale {
+    /// Create a device-local image and a view of its only mip level.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+        Ok((image, view))
+    }
+
+    /// Create a render pass with a single subpass which leaves the color
+    /// image ready to be sampled.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let attachments = [
+            vk::AttachmentDescription {
+                format: Self::FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            vk::AttachmentDescription {
+                format: DepthPrePass::DEPTH_FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+        ];
+        let color_attachment = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+        let depth_attachment = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let subpasses = [vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment,
+            p_depth_stencil_attachment: &depth_attachment,
+            ..Default::default()
+        }];
+        let dependencies = [
+            // The images are shared between frames in flight, so wait for
+            // the previous frame to finish sampling the color image and
+            // writing depth.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::COMPUTE_SHADER
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // Make the rendered scene visible to the upscale pass.
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+        let create_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
@@ -0,0 +1,32 @@
+// Shared declarations for the FSR 1.0 upscaling passes.
+//
+// Both passes read `source` with texelFetch and write `destination`. The
+// intermediate image holds colors in a reversible tonemapped space because
+// EASU and RCAS expect values in [0, 1], see fsr_tonemap below.
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+layout(set = 0, binding = 1, rgba16f) uniform writeonly image2D destination;
+
+// Must match FsrConstants in `post/render_scale.rs`.
+layout(push_constant) uniform FsrConstants {
+    // The size of the rendered area in the source image.
+    ivec2 source_size;
+    // The size of the destination image.
+    ivec2 output_size;
+    // RCAS sharpening in stops, 0 is the sharpest.
+    float sharpness;
+} params;
+
+float fsr_luma(vec3 color) {
+    return color.b * 0.5 + (color.r * 0.5 + color.g);
+}
+
+vec3 fsr_tonemap(vec3 color) {
+    return color / (1.0 + max(max(color.r, color.g), color.b));
+}
+
+vec3 fsr_inverse_tonemap(vec3 color) {
+    return color / max(1.0 / 32768.0, 1.0 - max(max(color.r, color.g), color.b));
+}
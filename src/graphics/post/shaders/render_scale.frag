@@ -0,0 +1,64 @@
+#version 460
+
+#define FILTER_NEAREST 0
+#define FILTER_LINEAR 1
+#define FILTER_BICUBIC 2
+
+layout(location = 0) in vec2 uv;
+
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Must match ScaleConstants in `post/render_scale.rs`.
+layout(push_constant) uniform ScaleConstants {
+    // The fraction of the target which holds the scene.
+    vec2 uv_scale;
+    // The center of the last rendered texel, so linear filtering never
+    // reads outside of the rendered area.
+    vec2 uv_max;
+    // The size of the rendered area in texels.
+    vec2 source_size;
+    // One of the FILTER_* constants.
+    uint filter_mode;
+} params;
+
+layout(location = 0) out vec4 out_color;
+
+// Catmull-Rom weights for the four texels around a sample point, where t is
+// the sample's fractional offset from the second texel.
+vec4 catmull_rom_weights(float t) {
+    float t2 = t * t;
+    float t3 = t2 * t;
+    return vec4(
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2
+    );
+}
+
+vec4 bicubic(vec2 position) {
+    vec2 texel = position - 0.5;
+    vec2 base = floor(texel);
+    vec2 t = texel - base;
+    vec4 wx = catmull_rom_weights(t.x);
+    vec4 wy = catmull_rom_weights(t.y);
+
+    ivec2 last = ivec2(params.source_size) - 1;
+    vec4 sum = vec4(0.0);
+    for (int y = 0; y < 4; y++) {
+        for (int x = 0; x < 4; x++) {
+            ivec2 coord = clamp(ivec2(base) + ivec2(x - 1, y - 1), ivec2(0), last);
+            sum += texelFetch(scene, coord, 0) * wx[x] * wy[y];
+        }
+    }
+    // Catmull-Rom overshoots at hard edges, which would be negative light.
+    return max(sum, vec4(0.0));
+}
+
+void main() {
+    if (params.filter_mode == FILTER_BICUBIC) {
+        out_color = bicubic(uv * params.source_size);
+    } else {
+        out_color = texture(scene, min(uv * params.uv_scale, params.uv_max));
+    }
+}
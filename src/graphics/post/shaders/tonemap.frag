@@ -0,0 +1,46 @@
+#version 460
+
+layout(set = 0, binding = 0) uniform sampler2D hdr_image;
+layout(set = 0, binding = 1) readonly buffer Luminance {
+    float average;
+} luminance;
+
+layout(push_constant) uniform TonemapParams {
+    uint tonemap_operator;
+    float exposure;
+} params;
+
+layout(location = 0) out vec4 out_color;
+
+const uint LINEAR_CLAMP = 0;
+const uint REINHARD = 1;
+const uint ACES = 2;
+
+const float MIDDLE_GREY = 0.18;
+
+// Krzysztof Narkowicz's fitted approximation of the ACES filmic curve.
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main() {
+    vec3 hdr = texelFetch(hdr_image, ivec2(gl_FragCoord.xy), 0).rgb;
+    float exposure =
+        params.exposure * MIDDLE_GREY / max(luminance.average, 1e-4);
+    vec3 color = hdr * exposure;
+
+    if (params.tonemap_operator == REINHARD) {
+        color = color / (1.0 + color);
+    } else if (params.tonemap_operator == ACES) {
+        color = aces(color);
+    } else {
+        color = clamp(color, 0.0, 1.0);
+    }
+
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,599 @@
+use {
+    crate::{
+        graphics::{
+            vulkan_api::{raii, DepthPrePass, RenderDevice},
+            GraphicsError,
+        },
+        math::{inverse, Mat4},
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Parameters which control the strength and smoothness of ambient
+/// occlusion.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SsaoParams {
+    /// The view-space distance searched for occluders.
+    pub radius: f32,
+
+    /// How dark occluded areas become.
+    pub intensity: f32,
+
+    /// Ignores occluders which are nearly in the surface's plane, in
+    /// view-space units. Raise it if flat surfaces look dirty.
+    pub bias: f32,
+
+    /// The number of depth samples per pixel. Clamped to MAX_SAMPLES.
+    pub samples: u32,
+
+    /// The radius of the depth-aware blur in pixels. 0 disables the blur.
+    /// Clamped to MAX_BLUR_RADIUS.
+    pub blur_radius: u32,
+}
+
+impl Default for SsaoParams {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            intensity: 1.0,
+            bias: 0.01,
+            samples: 16,
+            blur_radius: 4,
+        }
+    }
+}
+
+/// Push constants for the occlusion shader. Must match SsaoConstants in
+/// `shaders/ssao.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SsaoConstants {
+    inverse_projection: Mat4,
+    projection_scale: [f32; 2],
+    radius: f32,
+    intensity: f32,
+    bias: f32,
+    sample_count: u32,
+    has_normals: u32,
+}
+
+/// Push constants for the blur shader. Must match BlurConstants in
+/// `shaders/ssao_blur.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct BlurConstants {
+    direction: [i32; 2],
+    radius: i32,
+}
+
+/// Screen-space ambient occlusion computed from a DepthPrePass.
+///
+/// Occlusion is gathered from the depth buffer with Scalable Ambient
+/// Obscurance, then smoothed with a separable blur which doesn't cross depth
+/// edges. Normals are reconstructed from depth unless a view-space normal
+/// image is provided.
+///
+/// Record it after the DepthPrePass and before the pass which lights the
+/// scene. That pass samples `output_view`, which holds the ambient term
+/// multiplier in the red channel. LightingResolve can apply it with
+/// `new_with_ambient_occlusion`.
+pub struct Ssao {
+    extent: vk::Extent2D,
+    depth_image: vk::Image,
+    has_normals: bool,
+    descriptor_pool: raii::DescriptorPool,
+    _occlusion_descriptor_set_layout: raii::DescriptorSetLayout,
+    _blur_descriptor_set_layout: raii::DescriptorSetLayout,
+    occlusion_pipeline_layout: raii::PipelineLayout,
+    blur_pipeline_layout: raii::PipelineLayout,
+    occlusion_pipeline: raii::Pipeline,
+    blur_pipeline: raii::Pipeline,
+    _sampler: raii::Sampler,
+    output_view: raii::ImageView,
+    _intermediate_view: raii::ImageView,
+    images: [raii::Image; 2],
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl Ssao {
+    /// The format of the output image. Occlusion is in r and the view-space
+    /// distance is in g.
+    pub const FORMAT: vk::Format = vk::Format::R32G32_SFLOAT;
+
+    /// The most depth samples taken per pixel.
+    pub const MAX_SAMPLES: u32 = 64;
+
+    /// The largest blur radius in pixels.
+    pub const MAX_BLUR_RADIUS: u32 = 16;
+
+    /// Create the occlusion images and compute pipelines.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `depth_pre_pass` - provides the depth buffer
+    /// * `normal_view` - optional view-space normals in xyz, in the
+    ///   SHADER_READ_ONLY_OPTIMAL layout when the commands execute
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the DepthPrePass and normal image must outlive this instance
+    ///   - this instance must be rebuilt any time the DepthPrePass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        depth_pre_pass: &DepthPrePass,
+        normal_view: Option<&raii::ImageView>,
+    ) -> Result<Self, GraphicsError> {
+        let extent = depth_pre_pass.extent();
+        let (output, output_view) = Self::create_image(&render_device, extent)?;
+        output.set_debug_name("SSAO Output");
+        let (intermediate, intermediate_view) =
+            Self::create_image(&render_device, extent)?;
+        intermediate.set_debug_name("SSAO Blur Intermediate");
+
+        // Every image is read with texelFetch, except depth which is read
+        // at arbitrary uvs but must never be interpolated.
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let storage_binding = |binding: u32| vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            ..vk::DescriptorSetLayoutBinding::default()
+        };
+        let sampler_binding = |binding: u32| vk::DescriptorSetLayoutBinding {
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            ..storage_binding(binding)
+        };
+        let occlusion_descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[sampler_binding(0), sampler_binding(1), storage_binding(2)],
+            )?;
+        let blur_descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &[sampler_binding(0), storage_binding(1)],
+            )?;
+
+        let occlusion_pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[occlusion_descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<SsaoConstants>() as u32,
+                }],
+            )?;
+        let blur_pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[blur_descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<BlurConstants>() as u32,
+                }],
+            )?;
+        let occlusion_pipeline =
+            raii::Pipeline::new_compute_pipeline_from_bytes(
+                render_device.clone(),
+                &occlusion_pipeline_layout,
+                include_bytes!("./shaders/ssao.comp.spv"),
+            )?;
+        occlusion_pipeline.set_debug_name("SSAO Pipeline");
+        let blur_pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &blur_pipeline_layout,
+            include_bytes!("./shaders/ssao_blur.comp.spv"),
+        )?;
+        blur_pipeline.set_debug_name("SSAO Blur Pipeline");
+
+        // Set 0 writes occlusion into the output, set 1 blurs the output
+        // into the intermediate image, and set 2 blurs it back.
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            3,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 4,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 3,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool.allocate_descriptor_sets(&[
+            &occlusion_descriptor_set_layout,
+            &blur_descriptor_set_layout,
+            &blur_descriptor_set_layout,
+        ])?;
+
+        // Without normals the binding still needs a valid image, so depth is
+        // bound again and ignored by the shader.
+        let depth_info = vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: depth_pre_pass.depth_view().raw(),
+            image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+        };
+        let normal_info = match normal_view {
+            Some(normal_view) => vk::DescriptorImageInfo {
+                sampler: sampler.raw(),
+                image_view: normal_view.raw(),
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            },
+            None => depth_info,
+        };
+        let sampled_info = |view: &raii::ImageView| vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let storage_info = |view: &raii::ImageView| vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: view.raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let sets = [
+            vec![depth_info, normal_info, storage_info(&output_view)],
+            vec![sampled_info(&output_view), storage_info(&intermediate_view)],
+            vec![sampled_info(&intermediate_view), storage_info(&output_view)],
+        ];
+        let writes = sets
+            .iter()
+            .enumerate()
+            .flat_map(|(index, image_infos)| {
+                let dst_set = descriptor_pool.descriptor_set(index);
+                let storage_binding = image_infos.len() - 1;
+                image_infos.iter().enumerate().map(
+                    move |(binding, image_info)| vk::WriteDescriptorSet {
+                        dst_set,
+                        dst_binding: binding as u32,
+                        dst_array_element: 0,
+                        descriptor_type: if binding == storage_binding {
+                            vk::DescriptorType::STORAGE_IMAGE
+                        } else {
+                            vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                        },
+                        descriptor_count: 1,
+                        p_image_info: image_info,
+                        ..vk::WriteDescriptorSet::default()
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            extent,
+            depth_image: depth_pre_pass.depth_image().raw(),
+            has_normals: normal_view.is_some(),
+            descriptor_pool,
+            _occlusion_descriptor_set_layout: occlusion_descriptor_set_layout,
+            _blur_descriptor_set_layout: blur_descriptor_set_layout,
+            occlusion_pipeline_layout,
+            blur_pipeline_layout,
+            occlusion_pipeline,
+            blur_pipeline,
+            _sampler: sampler,
+            output_view,
+            _intermediate_view: intermediate_view,
+            images: [output, intermediate],
+            render_device,
+        })
+    }
+
+    /// The size of the occlusion image.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The blurred occlusion. It's in the GENERAL layout after `record`.
+    pub fn output_view(&self) -> &raii::ImageView {
+        &self.output_view
+    }
+
+    /// Record the occlusion and blur passes.
+    ///
+    /// The depth buffer is moved to DEPTH_STENCIL_READ_ONLY_OPTIMAL while
+    /// it's read, then returned to DEPTH_STENCIL_ATTACHMENT_OPTIMAL so a
+    /// ColorPass can still load it.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer to record into
+    /// * `projection` - the projection the depth was rendered with
+    /// * `params` - the occlusion and blur parameters
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the DepthPrePass's render pass must have ended earlier in the
+    ///     command buffer
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        projection: &Mat4,
+        params: &SsaoParams,
+    ) {
+        let device = self.render_device.device();
+
+        // Wait for the depth writes and the previous frame's reads of the
+        // occlusion images. Their contents are never needed.
+        let image_memory_barriers = [
+            vk::ImageMemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ,
+                old_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                new_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+                image: self.depth_image,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::DEPTH,
+                    ..Self::SUBRESOURCE_RANGE
+                },
+                ..Default::default()
+            },
+            self.discard_barrier(0),
+            self.discard_barrier(1),
+        ];
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: image_memory_barriers.len() as u32,
+                p_image_memory_barriers: image_memory_barriers.as_ptr(),
+                ..Default::default()
+            },
+        );
+
+        let inverse_projection =
+            inverse(projection).unwrap_or_else(Mat4::identity);
+        let constants = SsaoConstants {
+            inverse_projection,
+            projection_scale: [
+                projection[(0, 0)].abs(),
+                projection[(1, 1)].abs(),
+            ],
+            radius: params.radius.max(0.001),
+            intensity: params.intensity,
+            bias: params.bias,
+            sample_count: params.samples.clamp(1, Self::MAX_SAMPLES),
+            has_normals: self.has_normals as u32,
+        };
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.occlusion_pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.occlusion_pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.occlusion_pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const SsaoConstants as *const u8,
+                std::mem::size_of::<SsaoConstants>(),
+            ),
+        );
+        self.dispatch(command_buffer);
+
+        let blur_radius = params.blur_radius.min(Self::MAX_BLUR_RADIUS);
+        if blur_radius > 0 {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.blur_pipeline.raw(),
+            );
+            for (set, direction) in [(1, [1, 0]), (2, [0, 1])] {
+                self.shader_memory_barrier(command_buffer);
+                let constants = BlurConstants {
+                    direction,
+                    radius: blur_radius as i32,
+                };
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.blur_pipeline_layout.raw(),
+                    0,
+                    &[self.descriptor_pool.descriptor_set(set)],
+                    &[],
+                );
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.blur_pipeline_layout.raw(),
+                    vk::ShaderStageFlags::COMPUTE,
+                    0,
+                    std::slice::from_raw_parts(
+                        &constants as *const BlurConstants as *const u8,
+                        std::mem::size_of::<BlurConstants>(),
+                    ),
+                );
+                self.dispatch(command_buffer);
+            }
+        }
+
+        // Return the depth buffer for the color pass and make the occlusion
+        // visible to the lighting pass.
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+            dst_access_mask: vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            old_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            new_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            image: self.depth_image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                ..Self::SUBRESOURCE_RANGE
+            },
+            ..Default::default()
+        };
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl Ssao {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// Every image has a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// A transition to GENERAL which discards the image's contents once the
+    /// previous frame's lighting pass is done reading it.
+    fn discard_barrier(&self, index: usize) -> vk::ImageMemoryBarrier2 {
+        vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: self.images[index].raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        }
+    }
+
+    /// Make one pass's writes visible to the next pass's reads.
+    unsafe fn shader_memory_barrier(&self, command_buffer: vk::CommandBuffer) {
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ
+                | vk::AccessFlags2::SHADER_WRITE,
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Dispatch one invocation per pixel.
+    unsafe fn dispatch(&self, command_buffer: vk::CommandBuffer) {
+        self.render_device.device().cmd_dispatch(
+            command_buffer,
+            (self.extent.width + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            (self.extent.height + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+        );
+    }
+
+    /// Create an occlusion image and its view.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: Self::FORMAT,
+                subresource_range: Self::SUBRESOURCE_RANGE,
+                ..Default::default()
+            };
+            raii::ImageView::new(render_device.clone(), &create_info)?
+        };
+        Ok((image, view))
+    }
+}
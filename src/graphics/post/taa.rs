@@ -0,0 +1,961 @@
+use {
+    super::HdrTarget,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, DepthPrePass, Frame, RenderDevice},
+            GraphicsError,
+        },
+        math::{jitter_projection, taa_jitter, Mat4, Vec2},
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// How the scene rendered by Taa's render pass is anti-aliased.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AntiAliasing {
+    /// Jitter the projection every frame and blend each frame with the
+    /// reprojected history.
+    Taa,
+
+    /// Render with the given number of samples per pixel and resolve into
+    /// the HDR target when the render pass ends. `TYPE_1` disables
+    /// anti-aliasing entirely.
+    Msaa(vk::SampleCountFlags),
+}
+
+/// Parameters for the temporal resolve.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TaaParams {
+    /// How much of the history is kept each frame, in [0, 1). Higher values
+    /// are smoother but take longer to respond to changes.
+    pub history_weight: f32,
+}
+
+impl Default for TaaParams {
+    fn default() -> Self {
+        Self {
+            history_weight: 0.9,
+        }
+    }
+}
+
+/// Push constants for the resolve shader. Must match ResolveConstants in
+/// `shaders/taa_resolve.comp`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct ResolveConstants {
+    jitter_delta: [f32; 2],
+    history_weight: f32,
+    history_valid: u32,
+}
+
+/// Temporal anti-aliasing for an HdrTarget.
+///
+/// Taa owns the render pass the scene is drawn with. The pass has two color
+/// attachments, the HDR image and a velocity image, and a depth attachment.
+/// Pipelines write the scene's color to location 0 and, at location 1, how
+/// far each fragment moved in uv units since the previous frame, measured
+/// between jittered positions. InstancedMeshRenderer can write velocity with
+/// `new_with_velocity`.
+///
+/// Each frame:
+///
+/// 1. Draw the scene with `jittered_projection` inside
+///    `begin_render_pass_inline`.
+/// 2. Call `record` to blend the frame with the history. The result is
+///    copied back into the HDR image so the rest of the post chain is
+///    unchanged.
+///
+/// For comparison, the same render pass can use MSAA instead. Switching
+/// modes means rebuilding Taa and every pipeline built against its render
+/// pass, because the sample count changes.
+pub struct Taa {
+    mode: AntiAliasing,
+    extent: vk::Extent2D,
+    frame_index: u32,
+    previous_jitter: Vec2,
+    resolve: Option<TemporalResolve>,
+    render_pass: raii::RenderPass,
+    framebuffer: raii::Framebuffer,
+    multisample_view: Option<raii::ImageView>,
+    _multisample_image: Option<raii::Image>,
+    _multisample_velocity_view: Option<raii::ImageView>,
+    _multisample_velocity_image: Option<raii::Image>,
+    depth_view: raii::ImageView,
+    _depth_image: raii::Image,
+    velocity_view: raii::ImageView,
+    _velocity_image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+/// The history images and compute pipeline used by AntiAliasing::Taa.
+struct TemporalResolve {
+    history_index: usize,
+    history_valid: bool,
+    hdr_image: vk::Image,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _samplers: [raii::Sampler; 2],
+    _history_views: [raii::ImageView; 2],
+    history: [raii::Image; 2],
+}
+
+// Public API
+// ----------
+
+impl Taa {
+    /// The format of the velocity image.
+    pub const VELOCITY_FORMAT: vk::Format = vk::Format::R16G16_SFLOAT;
+
+    /// Create the velocity and depth images, the render pass, and, for
+    /// AntiAliasing::Taa, the history images and resolve pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `hdr_target` - the image the scene is rendered into
+    /// * `mode` - the anti-aliasing technique
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the HdrTarget must outlive this instance
+    ///   - this instance must be rebuilt any time the HdrTarget is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        hdr_target: &HdrTarget,
+        mode: AntiAliasing,
+    ) -> Result<Self, GraphicsError> {
+        let extent = hdr_target.extent();
+        let samples = match mode {
+            AntiAliasing::Taa => vk::SampleCountFlags::TYPE_1,
+            AntiAliasing::Msaa(samples) => samples,
+        };
+        let multisampled = samples != vk::SampleCountFlags::TYPE_1;
+
+        let (velocity_image, velocity_view) = create_image(
+            &render_device,
+            extent,
+            Self::VELOCITY_FORMAT,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        velocity_image.set_debug_name("TAA Velocity");
+        let (depth_image, depth_view) = create_image(
+            &render_device,
+            extent,
+            DepthPrePass::DEPTH_FORMAT,
+            samples,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED,
+            vk::ImageAspectFlags::DEPTH,
+        )?;
+        depth_image.set_debug_name("TAA Depth");
+
+        // With MSAA the scene is drawn into multisampled images which are
+        // resolved into the HDR target and the velocity image.
+        let (multisample_image, multisample_view) = if multisampled {
+            let (image, view) = create_image(
+                &render_device,
+                extent,
+                HdrTarget::FORMAT,
+                samples,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                vk::ImageAspectFlags::COLOR,
+            )?;
+            image.set_debug_name("MSAA Color");
+            (Some(image), Some(view))
+        } else {
+            (None, None)
+        };
+        let (multisample_velocity_image, multisample_velocity_view) =
+            if multisampled {
+                let (image, view) = create_image(
+                    &render_device,
+                    extent,
+                    Self::VELOCITY_FORMAT,
+                    samples,
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT
+                        | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                    vk::ImageAspectFlags::COLOR,
+                )?;
+                image.set_debug_name("MSAA Velocity");
+                (Some(image), Some(view))
+            } else {
+                (None, None)
+            };
+
+        let render_pass =
+            Self::create_render_pass(render_device.clone(), samples)?;
+        let attachments = match (&multisample_view, &multisample_velocity_view)
+        {
+            (Some(multisample_view), Some(multisample_velocity_view)) => vec![
+                multisample_view.raw(),
+                multisample_velocity_view.raw(),
+                depth_view.raw(),
+                hdr_target.image_view().raw(),
+                velocity_view.raw(),
+            ],
+            _ => vec![
+                hdr_target.image_view().raw(),
+                velocity_view.raw(),
+                depth_view.raw(),
+            ],
+        };
+        let framebuffer = {
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass: render_pass.raw(),
+                attachment_count: attachments.len() as u32,
+                p_attachments: attachments.as_ptr(),
+                width: extent.width,
+                height: extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            raii::Framebuffer::new(render_device.clone(), &create_info)?
+        };
+
+        let resolve = if mode == AntiAliasing::Taa {
+            Some(TemporalResolve::new(
+                &render_device,
+                hdr_target,
+                &velocity_view,
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            mode,
+            extent,
+            frame_index: 0,
+            previous_jitter: Vec2::zeros(),
+            resolve,
+            render_pass,
+            framebuffer,
+            multisample_view,
+            _multisample_image: multisample_image,
+            _multisample_velocity_view: multisample_velocity_view,
+            _multisample_velocity_image: multisample_velocity_image,
+            depth_view,
+            _depth_image: depth_image,
+            velocity_view,
+            _velocity_image: velocity_image,
+            render_device,
+        })
+    }
+
+    /// The anti-aliasing technique.
+    pub fn mode(&self) -> AntiAliasing {
+        self.mode
+    }
+
+    /// The sample count pipelines must use with `render_pass`.
+    pub fn samples(&self) -> vk::SampleCountFlags {
+        match self.mode {
+            AntiAliasing::Taa => vk::SampleCountFlags::TYPE_1,
+            AntiAliasing::Msaa(samples) => samples,
+        }
+    }
+
+    /// The size of the render area.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The render pass the scene is drawn with. It has two color
+    /// attachments, HdrTarget::FORMAT and VELOCITY_FORMAT, and a depth
+    /// attachment with DepthPrePass::DEPTH_FORMAT.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// The velocity image. It always has a single sample, with MSAA the
+    /// samples are resolved into it. After the render pass it's in the
+    /// SHADER_READ_ONLY_OPTIMAL layout.
+    pub fn velocity_view(&self) -> &raii::ImageView {
+        &self.velocity_view
+    }
+
+    /// The depth image. After the render pass it's in the
+    /// DEPTH_STENCIL_READ_ONLY_OPTIMAL layout. With MSAA it has the same
+    /// sample count as the scene.
+    pub fn depth_view(&self) -> &raii::ImageView {
+        &self.depth_view
+    }
+
+    /// The current frame's jitter in pixels. Always zero with MSAA.
+    pub fn jitter(&self) -> Vec2 {
+        match self.mode {
+            AntiAliasing::Taa => taa_jitter(self.frame_index),
+            AntiAliasing::Msaa(_) => Vec2::zeros(),
+        }
+    }
+
+    /// Offset a projection by the current frame's jitter.
+    pub fn jittered_projection(&self, projection: &Mat4) -> Mat4 {
+        jitter_projection(
+            projection,
+            &self.jitter(),
+            self.extent.width,
+            self.extent.height,
+        )
+    }
+
+    /// Discard the history, for example after a camera cut. The next frame
+    /// is used as-is.
+    pub fn reset_history(&mut self) {
+        if let Some(resolve) = &mut self.resolve {
+            resolve.history_valid = false;
+        }
+    }
+
+    /// Begin the render pass for the scene. The color and velocity are
+    /// cleared to the clear color and zero, and depth is cleared to 1.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must not be destroyed until the command buffer
+    ///     finishes executing or is discarded.
+    pub unsafe fn begin_render_pass_inline(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+    ) {
+        let mut clear_values = vec![
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+        if self.multisample_view.is_some() {
+            // The resolve attachments are never cleared, but need entries.
+            clear_values.push(vk::ClearValue::default());
+            clear_values.push(vk::ClearValue::default());
+        }
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffer.raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+
+    /// Record the temporal resolve and advance to the next jitter. Does
+    /// nothing with MSAA, which resolves when the render pass ends.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be in the recording state and must not be
+    ///     inside a render pass
+    ///   - the scene's render pass must have ended earlier in the command
+    ///     buffer
+    ///   - this instance must not be dropped until the command buffer
+    ///     finishes executing
+    pub unsafe fn record(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        params: &TaaParams,
+    ) {
+        let jitter = self.jitter();
+        let resolve = match &mut self.resolve {
+            Some(resolve) => resolve,
+            None => return,
+        };
+        let jitter_delta = jitter - self.previous_jitter;
+        let constants = ResolveConstants {
+            jitter_delta: [
+                jitter_delta.x / self.extent.width as f32,
+                jitter_delta.y / self.extent.height as f32,
+            ],
+            history_weight: params.history_weight.clamp(0.0, 0.99),
+            history_valid: resolve.history_valid as u32,
+        };
+        resolve.record(
+            self.render_device.device(),
+            command_buffer,
+            self.extent,
+            &constants,
+        );
+
+        self.previous_jitter = jitter;
+        self.frame_index = self.frame_index.wrapping_add(1);
+    }
+}
+
+// Private API
+// -----------
+
+impl Taa {
+    /// Create the render pass. With more than one sample the color and
+    /// velocity are resolved into a fourth and fifth attachment, the HDR
+    /// target and the single-sample velocity image.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+        samples: vk::SampleCountFlags,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let multisampled = samples != vk::SampleCountFlags::TYPE_1;
+        let mut attachments = vec![
+            vk::AttachmentDescription {
+                format: HdrTarget::FORMAT,
+                samples,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: if multisampled {
+                    vk::AttachmentStoreOp::DONT_CARE
+                } else {
+                    vk::AttachmentStoreOp::STORE
+                },
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: if multisampled {
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                } else {
+                    vk::ImageLayout::GENERAL
+                },
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            vk::AttachmentDescription {
+                format: Self::VELOCITY_FORMAT,
+                samples,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: if multisampled {
+                    vk::AttachmentStoreOp::DONT_CARE
+                } else {
+                    vk::AttachmentStoreOp::STORE
+                },
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: if multisampled {
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                } else {
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+                },
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            vk::AttachmentDescription {
+                format: DepthPrePass::DEPTH_FORMAT,
+                samples,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+        ];
+        if multisampled {
+            attachments.push(vk::AttachmentDescription {
+                format: HdrTarget::FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::DONT_CARE,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::GENERAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            });
+            attachments.push(vk::AttachmentDescription {
+                format: Self::VELOCITY_FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::DONT_CARE,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            });
+        }
+
+        let color_attachments = [
+            vk::AttachmentReference {
+                attachment: 0,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            },
+            vk::AttachmentReference {
+                attachment: 1,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            },
+        ];
+        let resolve_attachments = [
+            vk::AttachmentReference {
+                attachment: 3,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            },
+            vk::AttachmentReference {
+                attachment: 4,
+                layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            },
+        ];
+        let depth_attachment = vk::AttachmentReference {
+            attachment: 2,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let subpasses = [vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: color_attachments.len() as u32,
+            p_color_attachments: color_attachments.as_ptr(),
+            p_resolve_attachments: if multisampled {
+                resolve_attachments.as_ptr()
+            } else {
+                std::ptr::null()
+            },
+            p_depth_stencil_attachment: &depth_attachment,
+            ..Default::default()
+        }];
+        let dependencies = [
+            // The images are shared between frames in flight, so wait for
+            // the previous frame's post-processing and resolve copy to finish
+            // with them.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::COMPUTE_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::TRANSFER
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                src_access_mask: vk::AccessFlags::SHADER_WRITE
+                    | vk::AccessFlags::TRANSFER_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // Make the rendered images visible to post-processing.
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::COMPUTE_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::SHADER_READ
+                    | vk::AccessFlags::SHADER_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+        let create_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
+
+impl TemporalResolve {
+    /// The compute shader workgroup size in x and y.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    /// Every image has a single color mip level and layer.
+    const SUBRESOURCE_RANGE: vk::ImageSubresourceRange =
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+    /// Create the history images and the resolve pipeline.
+    unsafe fn new(
+        render_device: &Arc<RenderDevice>,
+        hdr_target: &HdrTarget,
+        velocity_view: &raii::ImageView,
+    ) -> Result<Self, GraphicsError> {
+        let extent = hdr_target.extent();
+        let usage = vk::ImageUsageFlags::STORAGE
+            | vk::ImageUsageFlags::SAMPLED
+            | vk::ImageUsageFlags::TRANSFER_SRC;
+        let (history_0, history_view_0) = create_image(
+            render_device,
+            extent,
+            HdrTarget::FORMAT,
+            vk::SampleCountFlags::TYPE_1,
+            usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        history_0.set_debug_name("TAA History 0");
+        let (history_1, history_view_1) = create_image(
+            render_device,
+            extent,
+            HdrTarget::FORMAT,
+            vk::SampleCountFlags::TYPE_1,
+            usage,
+            vk::ImageAspectFlags::COLOR,
+        )?;
+        history_1.set_debug_name("TAA History 1");
+
+        // The current frame and velocity are read with texelFetch, the
+        // history is read between texels.
+        let samplers =
+            [vk::Filter::NEAREST, vk::Filter::LINEAR].map(|filter| {
+                let create_info = vk::SamplerCreateInfo {
+                    mag_filter: filter,
+                    min_filter: filter,
+                    mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                    address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    max_lod: 0.0,
+                    ..Default::default()
+                };
+                raii::Sampler::new(render_device.clone(), &create_info)
+            });
+        let [nearest, linear] = samplers;
+        let samplers = [nearest?, linear?];
+
+        let bindings = (0..4)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: if binding == 3 {
+                    vk::DescriptorType::STORAGE_IMAGE
+                } else {
+                    vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                },
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<ResolveConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/taa_resolve.comp.spv"),
+        )?;
+        pipeline.set_debug_name("TAA Resolve Pipeline");
+
+        // Set i writes history i and reads the other history image.
+        let history_views = [history_view_0, history_view_1];
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            2,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 6,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: 2,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool.allocate_descriptor_sets(&[
+            &descriptor_set_layout,
+            &descriptor_set_layout,
+        ])?;
+        for index in 0..2 {
+            let image_infos = [
+                vk::DescriptorImageInfo {
+                    sampler: samplers[0].raw(),
+                    image_view: hdr_target.image_view().raw(),
+                    image_layout: vk::ImageLayout::GENERAL,
+                },
+                vk::DescriptorImageInfo {
+                    sampler: samplers[0].raw(),
+                    image_view: velocity_view.raw(),
+                    image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                },
+                vk::DescriptorImageInfo {
+                    sampler: samplers[1].raw(),
+                    image_view: history_views[1 - index].raw(),
+                    image_layout: vk::ImageLayout::GENERAL,
+                },
+                vk::DescriptorImageInfo {
+                    sampler: vk::Sampler::null(),
+                    image_view: history_views[index].raw(),
+                    image_layout: vk::ImageLayout::GENERAL,
+                },
+            ];
+            let writes = image_infos
+                .iter()
+                .enumerate()
+                .map(|(binding, image_info)| vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: binding as u32,
+                    dst_array_element: 0,
+                    descriptor_type: bindings[binding].descriptor_type,
+                    descriptor_count: 1,
+                    p_image_info: image_info,
+                    ..vk::WriteDescriptorSet::default()
+                })
+                .collect::<Vec<_>>();
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(Self {
+            history_index: 0,
+            history_valid: false,
+            hdr_image: hdr_target.image().raw(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _samplers: samplers,
+            _history_views: history_views,
+            history: [history_0, history_1],
+        })
+    }
+
+    /// Blend the HDR image with the history into the next history image,
+    /// then copy the result back into the HDR image.
+    unsafe fn record(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        constants: &ResolveConstants,
+    ) {
+        let history = &self.history[self.history_index];
+
+        // The destination's previous contents are never needed. Wait for
+        // the previous frame to finish reading and copying it.
+        let image_memory_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                | vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::GENERAL,
+            image: history.raw(),
+            subresource_range: Self::SUBRESOURCE_RANGE,
+            ..Default::default()
+        };
+        let source_memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags2::SHADER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &source_memory_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &image_memory_barrier,
+                ..Default::default()
+            },
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(self.history_index)],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                constants as *const ResolveConstants as *const u8,
+                std::mem::size_of::<ResolveConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (extent.width + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            (extent.height + Self::WORKGROUP_SIZE - 1) / Self::WORKGROUP_SIZE,
+            1,
+        );
+
+        // The copy overwrites the HDR image, so the resolve must also be done
+        // reading it.
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+                src_access_mask: vk::AccessFlags2::SHADER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COPY,
+                dst_access_mask: vk::AccessFlags2::TRANSFER_READ
+                    | vk::AccessFlags2::TRANSFER_WRITE,
+                ..Default::default()
+            },
+        );
+        let subresource = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        device.cmd_copy_image(
+            command_buffer,
+            history.raw(),
+            vk::ImageLayout::GENERAL,
+            self.hdr_image,
+            vk::ImageLayout::GENERAL,
+            &[vk::ImageCopy {
+                src_subresource: subresource,
+                src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                dst_subresource: subresource,
+                dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                extent: vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+            }],
+        );
+        Self::memory_barrier(
+            device,
+            command_buffer,
+            &vk::MemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::COPY,
+                src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                dst_access_mask: vk::AccessFlags2::SHADER_READ
+                    | vk::AccessFlags2::SHADER_WRITE,
+                ..Default::default()
+            },
+        );
+
+        self.history_index = 1 - self.history_index;
+        self.history_valid = true;
+    }
+
+    unsafe fn memory_barrier(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        memory_barrier: &vk::MemoryBarrier2,
+    ) {
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: memory_barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Create a device-local image and a view of its only mip level.
+unsafe fn create_image(
+    render_device: &Arc<RenderDevice>,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    samples: vk::SampleCountFlags,
+    usage: vk::ImageUsageFlags,
+    aspect_mask: vk::ImageAspectFlags,
+) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+    let queue_family_index = render_device.graphics_queue().family_index();
+    let create_info = vk::ImageCreateInfo {
+        image_type: vk::ImageType::TYPE_2D,
+        format,
+        mip_levels: 1,
+        array_layers: 1,
+        initial_layout: vk::ImageLayout::UNDEFINED,
+        samples,
+        sharing_mode: vk::SharingMode::EXCLUSIVE,
+        queue_family_index_count: 1,
+        p_queue_family_indices: &queue_family_index,
+        tiling: vk::ImageTiling::OPTIMAL,
+        usage,
+        flags: vk::ImageCreateFlags::empty(),
+        extent: vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+        ..vk::ImageCreateInfo::default()
+    };
+    let image = raii::Image::new(
+        render_device.clone(),
+        &create_info,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+    let view = {
+        let create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_2D,
+            format,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        raii::ImageView::new(render_device.clone(), &create_info)?
+    };
+    Ok((image, view))
+}
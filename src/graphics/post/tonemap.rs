@@ -0,0 +1,284 @@
+use {
+    super::{
+        auto_exposure::{create_storage_buffer, MIDDLE_GREY},
+        AutoExposure, HdrTarget,
+    },
+    crate::graphics::{
+        vulkan_api::{
+            raii, ColorPass, Frame, GraphicsPipelineBuilder, RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The curve used to map HDR colors into the displayable range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum TonemapOperator {
+    /// Clamp each channel to [0, 1].
+    LinearClamp = 0,
+
+    /// The Reinhard operator, `c / (1 + c)`.
+    Reinhard = 1,
+
+    /// A fitted approximation of the ACES filmic curve.
+    Aces = 2,
+}
+
+/// Parameters for the Tonemap pass.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct TonemapParams {
+    /// The tonemapping curve.
+    pub operator: TonemapOperator,
+
+    /// A manual exposure multiplier. When auto exposure is enabled this is
+    /// applied on top of the automatic exposure, like exposure compensation.
+    pub exposure: f32,
+}
+
+impl Default for TonemapParams {
+    fn default() -> Self {
+        Self {
+            operator: TonemapOperator::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+/// A fullscreen pass which tonemaps an HdrTarget into a ColorPass, typically
+/// the swapchain.
+pub struct Tonemap {
+    extent: vk::Extent2D,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _sampler: raii::Sampler,
+    _fixed_luminance: Option<raii::Buffer>,
+    render_device: Arc<RenderDevice>,
+}
+
+impl Tonemap {
+    /// Create the tonemap pipeline and a descriptor set which references the
+    /// HDR target.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `hdr_target` - the HDR image to tonemap
+    /// * `color_pass` - the render pass the tonemapped image is drawn into
+    /// * `auto_exposure` - when provided, the exposure is scaled by the
+    ///   average luminance computed by AutoExposure. Otherwise only the manual
+    ///   exposure in TonemapParams is used.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the HdrTarget, ColorPass, or
+    ///     AutoExposure are rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        hdr_target: &HdrTarget,
+        color_pass: &ColorPass,
+        auto_exposure: Option<&AutoExposure>,
+    ) -> Result<Self, GraphicsError> {
+        // Without auto exposure, the shader reads a constant middle grey
+        // luminance which leaves the manual exposure unchanged.
+        let (fixed_luminance, luminance_buffer) = match auto_exposure {
+            Some(auto_exposure) => {
+                (None, auto_exposure.luminance_buffer().raw())
+            }
+            None => {
+                let buffer = create_storage_buffer(
+                    &render_device,
+                    &[MIDDLE_GREY.to_bits()],
+                )?;
+                let raw = buffer.raw();
+                (Some(buffer), raw)
+            }
+        };
+
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<TonemapParams>() as u32,
+                }],
+            )?;
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!("./shaders/tonemap.vert.spv"))
+                .fragment_shader(include_bytes!("./shaders/tonemap.frag.spv"))
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .build(
+                    render_device.clone(),
+                    &pipeline_layout,
+                    color_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Tonemap Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                },
+            ],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        let image_info = vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: hdr_target.image_view().raw(),
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let buffer_info = vk::DescriptorBufferInfo {
+            buffer: luminance_buffer,
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        };
+        let writes = [
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            },
+            vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 1,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                p_buffer_info: &buffer_info,
+                ..vk::WriteDescriptorSet::default()
+            },
+        ];
+        render_device.device().update_descriptor_sets(&writes, &[]);
+
+        Ok(Self {
+            extent: color_pass.extent(),
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _sampler: sampler,
+            _fixed_luminance: fixed_luminance,
+            render_device,
+        })
+    }
+
+    /// Add commands to tonemap the HDR image into every pixel of the current
+    /// render pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - the HDR image must be in the GENERAL layout
+    pub unsafe fn draw(&self, frame: &Frame, params: &TonemapParams) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            frame.command_buffer(),
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        device.cmd_push_constants(
+            frame.command_buffer(),
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                params as *const TonemapParams as *const u8,
+                std::mem::size_of::<TonemapParams>(),
+            ),
+        );
+
+        // A single triangle which covers the entire screen.
+        device.cmd_draw(frame.command_buffer(), 3, 1, 0, 0);
+    }
+}
@@ -0,0 +1,243 @@
+use {
+    super::{NodeId, SceneGraph},
+    crate::math::Vec3,
+    nalgebra::{Quaternion, UnitQuaternion, Vector4},
+};
+
+/// How a channel's keyframes are interpolated. Matches glTF's sampler
+/// interpolation modes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Hold each keyframe's value until the next keyframe.
+    Step,
+
+    /// Linearly interpolate translation and scale, and slerp rotations.
+    Linear,
+
+    /// Cubic Hermite spline. Each keyframe stores three values: the
+    /// in-tangent, the value, and the out-tangent.
+    CubicSpline,
+}
+
+/// The keyframe values for a channel, along with the node property they
+/// animate.
+#[derive(Debug, Clone)]
+pub enum ChannelValues {
+    Translation(Vec<Vec3>),
+    /// Rotations are stored as raw quaternions because cubic spline tangents
+    /// are not unit length. Keyframe values are normalized when sampled.
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vec3>),
+}
+
+/// Animates one property of one node in a SceneGraph.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub target: NodeId,
+    pub interpolation: Interpolation,
+
+    /// Keyframe times in seconds, in increasing order.
+    pub times: Vec<f32>,
+
+    /// One value per keyframe, or three per keyframe for CubicSpline.
+    pub values: ChannelValues,
+}
+
+/// A set of channels which play together, like a glTF animation.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+// Public API
+// ----------
+
+impl Animation {
+    /// Create an animation from its channels. The duration is the time of
+    /// the last keyframe in any channel.
+    pub fn new(channels: Vec<Channel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+        Self { channels, duration }
+    }
+
+    /// The animation's length in seconds.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// The animation's channels.
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Evaluate every channel at `time` seconds and write the results into
+    /// the target nodes' local transforms.
+    ///
+    /// Times outside of the animation are clamped to the first or last
+    /// keyframe. Use `duration` to loop, e.g. `time % animation.duration()`.
+    pub fn apply(&self, scene: &mut SceneGraph, time: f32) {
+        for channel in &self.channels {
+            channel.apply(scene, time);
+        }
+    }
+}
+
+impl Channel {
+    /// Evaluate the channel at `time` seconds and write the result into the
+    /// target node's local transform.
+    pub fn apply(&self, scene: &mut SceneGraph, time: f32) {
+        if self.times.is_empty() {
+            return;
+        }
+        let (index, t, dt) = self.keyframe_at(time);
+        match &self.values {
+            ChannelValues::Translation(values) => {
+                let value = self.sample_vector(values, index, t, dt);
+                scene.update_local(self.target, |local| {
+                    local.translation = value
+                });
+            }
+            ChannelValues::Scale(values) => {
+                let value = self.sample_vector(values, index, t, dt);
+                scene.update_local(self.target, |local| local.scale = value);
+            }
+            ChannelValues::Rotation(values) => {
+                let value = self.sample_rotation(values, index, t, dt);
+                scene.update_local(self.target, |local| local.rotation = value);
+            }
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl Channel {
+    /// Find the keyframe which starts the segment containing `time`.
+    ///
+    /// Returns the keyframe index, the normalized position within the
+    /// segment, and the segment's length in seconds. The position is always
+    /// 0 when `time` is before the first keyframe or after the last.
+    fn keyframe_at(&self, time: f32) -> (usize, f32, f32) {
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return (0, 0.0, 0.0);
+        }
+        if time >= self.times[last] {
+            return (last, 0.0, 0.0);
+        }
+        let next = self.times.partition_point(|&t| t <= time);
+        let index = next - 1;
+        let dt = self.times[next] - self.times[index];
+        let t = if dt > 0.0 {
+            (time - self.times[index]) / dt
+        } else {
+            0.0
+        };
+        (index, t, dt)
+    }
+
+    fn sample_vector(
+        &self,
+        values: &[Vec3],
+        index: usize,
+        t: f32,
+        dt: f32,
+    ) -> Vec3 {
+        match self.interpolation {
+            Interpolation::Step => values[index],
+            Interpolation::Linear => {
+                if t == 0.0 {
+                    values[index]
+                } else {
+                    values[index].lerp(&values[index + 1], t)
+                }
+            }
+            Interpolation::CubicSpline => {
+                if t == 0.0 {
+                    values[index * 3 + 1]
+                } else {
+                    cubic_spline(
+                        values[index * 3 + 1],
+                        values[index * 3 + 2],
+                        values[index * 3 + 3],
+                        values[index * 3 + 4],
+                        t,
+                        dt,
+                    )
+                }
+            }
+        }
+    }
+
+    fn sample_rotation(
+        &self,
+        values: &[Quaternion<f32>],
+        index: usize,
+        t: f32,
+        dt: f32,
+    ) -> UnitQuaternion<f32> {
+        match self.interpolation {
+            Interpolation::Step => UnitQuaternion::new_normalize(values[index]),
+            Interpolation::Linear => {
+                let start = UnitQuaternion::new_normalize(values[index]);
+                if t == 0.0 {
+                    start
+                } else {
+                    let end = UnitQuaternion::new_normalize(values[index + 1]);
+                    start.slerp(&end, t)
+                }
+            }
+            Interpolation::CubicSpline => {
+                if t == 0.0 {
+                    UnitQuaternion::new_normalize(values[index * 3 + 1])
+                } else {
+                    // The spline is evaluated per-component and the result
+                    // is renormalized, as described by the glTF spec.
+                    let coords: Vector4<f32> = cubic_spline(
+                        values[index * 3 + 1].coords,
+                        values[index * 3 + 2].coords,
+                        values[index * 3 + 3].coords,
+                        values[index * 3 + 4].coords,
+                        t,
+                        dt,
+                    );
+                    UnitQuaternion::new_normalize(Quaternion::from(coords))
+                }
+            }
+        }
+    }
+}
+
+/// Evaluate the glTF cubic Hermite spline between two keyframes.
+///
+/// # Params
+///
+/// * `value_0` - the value at the first keyframe
+/// * `out_tangent_0` - the out-tangent of the first keyframe
+/// * `in_tangent_1` - the in-tangent of the second keyframe
+/// * `value_1` - the value at the second keyframe
+/// * `t` - the normalized position between the keyframes
+/// * `dt` - the time between the keyframes in seconds
+fn cubic_spline<V>(
+    value_0: V,
+    out_tangent_0: V,
+    in_tangent_1: V,
+    value_1: V,
+    t: f32,
+    dt: f32,
+) -> V
+where
+    V: std::ops::Mul<f32, Output = V> + std::ops::Add<Output = V>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    value_0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + out_tangent_0 * ((t3 - 2.0 * t2 + t) * dt)
+        + value_1 * (-2.0 * t3 + 3.0 * t2)
+        + in_tangent_1 * ((t3 - t2) * dt)
+}
@@ -0,0 +1,35 @@
+//! A lightweight scene graph with hierarchical transforms.
+//!
+//! Nodes have a local translation/rotation/scale and an optional parent.
+//! SceneGraph::update_world_transforms recomputes world matrices for nodes
+//! whose local transform (or any ancestor's) changed, and SceneBuffer uploads
+//! the world matrices into a per-frame storage buffer indexed by NodeId so
+//! shaders can look up each instance's transform.
+//!
+//! Animations and skins operate on the same nodes. An Animation writes
+//! keyframed values into nodes' local transforms, and a Skin turns the world
+//! matrices of its joint nodes into joint matrices for GPU skinning. The
+//! `shaders/skinning.glsl` file declares the matching vertex layout.
+//! MorphTargets blends weighted blend shapes into a per-frame vertex buffer
+//! with a compute shader before the vertices are skinned and drawn.
+//!
+//! Scenes which span huge distances can keep the camera near the origin by
+//! pairing `math::WorldOrigin` with SceneGraph::shift_origin, or keep f64
+//! positions and subtract them on the GPU with the SplitVec3 helpers in
+//! `shaders/large_world.glsl`.
+
+mod animation;
+mod morph;
+mod scene_buffer;
+mod scene_graph;
+mod skin;
+mod transform;
+
+pub use self::{
+    animation::{Animation, Channel, ChannelValues, Interpolation},
+    morph::{MorphTarget, MorphTargets},
+    scene_buffer::SceneBuffer,
+    scene_graph::{NodeId, SceneGraph},
+    skin::{Skin, SkinnedVertex},
+    transform::Transform,
+};
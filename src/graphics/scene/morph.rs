@@ -0,0 +1,392 @@
+use {
+    super::SkinnedVertex,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+            GraphicsError,
+        },
+        math::Vec3,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Per-vertex offsets for one blend shape, like a glTF morph target.
+///
+/// Each list has one entry per base vertex. An empty list means the target
+/// doesn't change that attribute.
+#[derive(Debug, Clone, Default)]
+pub struct MorphTarget {
+    pub position_deltas: Vec<Vec3>,
+    pub normal_deltas: Vec<Vec3>,
+}
+
+/// The GPU representation of a single vertex's morph target offsets.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct MorphDelta {
+    position: [f32; 4],
+    normal: [f32; 4],
+}
+
+/// Push constants for the morph compute shader.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct MorphConstants {
+    vertex_count: u32,
+    target_count: u32,
+}
+
+/// Blends morph targets into a per-frame vertex buffer with a compute shader.
+///
+/// The base vertices and target deltas are uploaded once. Each frame,
+/// `cmd_blend` uploads the current weights and records a dispatch which
+/// writes the blended vertices into the frame's vertex buffer. The blended
+/// vertices keep their joints and weights, so they can be skinned afterwards.
+pub struct MorphTargets {
+    weights: Vec<f32>,
+    vertex_count: u32,
+
+    _base_vertices: raii::Buffer,
+    _deltas: raii::Buffer,
+    weight_buffers: Vec<raii::Buffer>,
+    weight_buffer_ptrs: Vec<*mut f32>,
+    vertex_buffers: Vec<raii::Buffer>,
+
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl MorphTargets {
+    /// Upload the base vertices and morph targets and create the blending
+    /// pipeline. Every weight starts at 0.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        base_vertices: &[SkinnedVertex],
+        targets: &[MorphTarget],
+    ) -> Result<Self, GraphicsError> {
+        let vertex_count = base_vertices.len();
+        let mut deltas =
+            vec![MorphDelta::default(); vertex_count * targets.len()];
+        for (target_index, target) in targets.iter().enumerate() {
+            let target_deltas = &mut deltas[target_index * vertex_count
+                ..(target_index + 1) * vertex_count];
+            for (delta, position) in
+                target_deltas.iter_mut().zip(&target.position_deltas)
+            {
+                delta.position = [position.x, position.y, position.z, 0.0];
+            }
+            for (delta, normal) in
+                target_deltas.iter_mut().zip(&target.normal_deltas)
+            {
+                delta.normal = [normal.x, normal.y, normal.z, 0.0];
+            }
+        }
+
+        let base_vertices_buffer = Self::create_buffer_with_data(
+            &render_device,
+            base_vertices,
+            "Morph Base Vertices",
+        )?;
+        let deltas_buffer = Self::create_buffer_with_data(
+            &render_device,
+            &deltas,
+            "Morph Deltas",
+        )?;
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut weight_buffers = Vec::with_capacity(frame_count);
+        let mut weight_buffer_ptrs = Vec::with_capacity(frame_count);
+        let mut vertex_buffers = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let weight_buffer = Self::create_buffer(
+                &render_device,
+                (targets.len().max(1) * std::mem::size_of::<f32>()) as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            weight_buffer.set_debug_name("Morph Weights");
+            let ptr = weight_buffer.allocation().map(render_device.device())?;
+            weight_buffer_ptrs.push(ptr as *mut f32);
+            weight_buffers.push(weight_buffer);
+
+            let vertex_buffer = Self::create_buffer(
+                &render_device,
+                (vertex_count.max(1) * std::mem::size_of::<SkinnedVertex>())
+                    as u64,
+                vk::BufferUsageFlags::STORAGE_BUFFER
+                    | vk::BufferUsageFlags::VERTEX_BUFFER,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+            vertex_buffer.set_debug_name("Morph Blended Vertices");
+            vertex_buffers.push(vertex_buffer);
+        }
+
+        let bindings = (0..4)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..vk::DescriptorSetLayoutBinding::default()
+            })
+            .collect::<Vec<_>>();
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: std::mem::size_of::<MorphConstants>() as u32,
+                }],
+            )?;
+        let pipeline = raii::Pipeline::new_compute_pipeline_from_bytes(
+            render_device.clone(),
+            &pipeline_layout,
+            include_bytes!("./shaders/morph.comp.spv"),
+        )?;
+        pipeline.set_debug_name("Morph Targets Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            frame_count as u32,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 4 * frame_count as u32,
+            }],
+        )?;
+        let layouts = (0..frame_count)
+            .map(|_| &descriptor_set_layout)
+            .collect::<Vec<&raii::DescriptorSetLayout>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+        for index in 0..frame_count {
+            let buffer_infos = [
+                base_vertices_buffer.raw(),
+                deltas_buffer.raw(),
+                weight_buffers[index].raw(),
+                vertex_buffers[index].raw(),
+            ]
+            .map(|buffer| vk::DescriptorBufferInfo {
+                buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            });
+            let writes = buffer_infos
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer_info)| vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: binding as u32,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                })
+                .collect::<Vec<_>>();
+            render_device.device().update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(Self {
+            weights: vec![0.0; targets.len()],
+            vertex_count: vertex_count as u32,
+            _base_vertices: base_vertices_buffer,
+            _deltas: deltas_buffer,
+            weight_buffers,
+            weight_buffer_ptrs,
+            vertex_buffers,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        })
+    }
+
+    /// The number of morph targets.
+    pub fn target_count(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// The number of vertices in the blended vertex buffers.
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    /// The current weight for every morph target.
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Mutable access to every weight. Changes are used by the next call to
+    /// `cmd_blend`.
+    pub fn weights_mut(&mut self) -> &mut [f32] {
+        &mut self.weights
+    }
+
+    /// Upload the current weights and record commands which blend the morph
+    /// targets into the frame's vertex buffer.
+    ///
+    /// The commands end with a barrier which makes the blended vertices
+    /// visible to vertex input, vertex shaders, and compute shaders. Record
+    /// this before the render pass which draws the vertices.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    pub unsafe fn cmd_blend(&mut self, frame: &Frame) {
+        let index = frame.frame_index();
+        std::ptr::copy_nonoverlapping(
+            self.weights.as_ptr(),
+            self.weight_buffer_ptrs[index],
+            self.weights.len(),
+        );
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.raw(),
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(index)],
+            &[],
+        );
+        let constants = MorphConstants {
+            vertex_count: self.vertex_count,
+            target_count: self.weights.len() as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const MorphConstants as *const u8,
+                std::mem::size_of::<MorphConstants>(),
+            ),
+        );
+        device.cmd_dispatch(
+            command_buffer,
+            (self.vertex_count + Self::WORKGROUP_SIZE - 1)
+                / Self::WORKGROUP_SIZE,
+            1,
+            1,
+        );
+
+        let barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT
+                | vk::PipelineStageFlags2::VERTEX_SHADER
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                | vk::AccessFlags2::SHADER_STORAGE_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &barrier,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// The frame's blended vertex buffer. It can be bound as a vertex buffer
+    /// or read as a storage buffer of SkinnedVertex.
+    pub fn vertex_buffer(&self, frame: &Frame) -> &raii::Buffer {
+        &self.vertex_buffers[frame.frame_index()]
+    }
+
+    /// The descriptor info for the frame's blended vertex buffer, for writing
+    /// a STORAGE_BUFFER descriptor.
+    pub fn descriptor_buffer_info(
+        &self,
+        frame: &Frame,
+    ) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.vertex_buffer(frame).raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl MorphTargets {
+    /// The compute shader's workgroup size.
+    const WORKGROUP_SIZE: u32 = 64;
+
+    /// Create a buffer which is only used by the graphics queue.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+
+    /// Create a host-visible storage buffer which holds a copy of `data`.
+    unsafe fn create_buffer_with_data<T: Copy>(
+        render_device: &Arc<RenderDevice>,
+        data: &[T],
+        name: &str,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let buffer = Self::create_buffer(
+            render_device,
+            (data.len().max(1) * std::mem::size_of::<T>()) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name(name);
+        let ptr = buffer.allocation().map(render_device.device())? as *mut T;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        buffer.allocation().unmap(render_device.device())?;
+        Ok(buffer)
+    }
+}
@@ -0,0 +1,188 @@
+use {
+    super::SceneGraph,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, Frame, FramesInFlight, RenderDevice},
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A storage buffer of matrices for each frame in flight.
+///
+/// Typically this holds every node's world matrix, indexed by NodeId, but it
+/// can hold any list of matrices, such as a Skin's joint matrices.
+///
+/// Shaders can declare the buffer as
+/// `layout(std430, set = 0, binding = N) readonly buffer WorldMatrices {
+/// mat4 world[]; };` and index it with the node id of each instance.
+pub struct SceneBuffer {
+    buffers: Vec<raii::Buffer>,
+    buffer_ptrs: Vec<*mut Mat4>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl SceneBuffer {
+    /// Create a new scene buffer with room for `initial_capacity` nodes
+    /// before any buffers need to grow.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        initial_capacity: usize,
+    ) -> Result<Self, GraphicsError> {
+        let buffer_count = frames_in_flight.frame_count();
+        let mut buffers = Vec::with_capacity(buffer_count);
+        let mut buffer_ptrs = Vec::with_capacity(buffer_count);
+        for _ in 0..buffer_count {
+            let (buffer, ptr) =
+                Self::allocate_buffer(&render_device, initial_capacity)?;
+            buffers.push(buffer);
+            buffer_ptrs.push(ptr);
+        }
+        Ok(Self {
+            buffers,
+            buffer_ptrs,
+            render_device,
+        })
+    }
+
+    /// Copy every node's world matrix into the frame's storage buffer.
+    ///
+    /// The caller should call `SceneGraph::update_world_transforms` first so
+    /// the uploaded matrices are current.
+    ///
+    /// Returns true when the buffer was reallocated, in which case any
+    /// descriptor sets which reference it must be rewritten with
+    /// `descriptor_buffer_info` before drawing.
+    pub fn write_world_matrices_for_frame(
+        &mut self,
+        frame: &Frame,
+        scene: &SceneGraph,
+    ) -> Result<bool, GraphicsError> {
+        self.write_for_frame(frame, scene.len(), scene.world_matrices())
+    }
+
+    /// Copy arbitrary matrices into the frame's storage buffer.
+    ///
+    /// Returns true when the buffer was reallocated, in which case any
+    /// descriptor sets which reference it must be rewritten with
+    /// `descriptor_buffer_info` before drawing.
+    pub fn write_matrices_for_frame(
+        &mut self,
+        frame: &Frame,
+        matrices: &[Mat4],
+    ) -> Result<bool, GraphicsError> {
+        self.write_for_frame(frame, matrices.len(), matrices.iter())
+    }
+
+    /// The descriptor info for the frame's world matrix buffer, for writing a
+    /// STORAGE_BUFFER descriptor.
+    pub fn descriptor_buffer_info(
+        &self,
+        frame: &Frame,
+    ) -> vk::DescriptorBufferInfo {
+        let buffer = &self.buffers[frame.frame_index()];
+        vk::DescriptorBufferInfo {
+            buffer: buffer.raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// A descriptor set layout binding for the world matrix buffer.
+    pub fn descriptor_set_layout_binding(
+        binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: 1,
+            stage_flags,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl SceneBuffer {
+    /// Copy `count` matrices into the frame's storage buffer, growing the
+    /// buffer if needed.
+    fn write_for_frame<'a>(
+        &mut self,
+        frame: &Frame,
+        count: usize,
+        matrices: impl Iterator<Item = &'a Mat4>,
+    ) -> Result<bool, GraphicsError> {
+        let index = frame.frame_index();
+        let required_size = (count * std::mem::size_of::<Mat4>()) as u64;
+        let mut reallocated = false;
+        if self.buffers[index].allocation().size_in_bytes() < required_size {
+            unsafe {
+                // SAFE because the frame's previous commands are complete, so
+                // the old buffer is no longer in use.
+                self.buffers[index]
+                    .allocation()
+                    .unmap(self.render_device.device())?;
+                let (buffer, ptr) =
+                    Self::allocate_buffer(&self.render_device, count * 2)?;
+                self.buffers[index] = buffer;
+                self.buffer_ptrs[index] = ptr;
+            }
+            reallocated = true;
+        }
+
+        let data = unsafe {
+            std::slice::from_raw_parts_mut(self.buffer_ptrs[index], count)
+        };
+        for (gpu_matrix, matrix) in data.iter_mut().zip(matrices) {
+            *gpu_matrix = *matrix;
+        }
+        Ok(reallocated)
+    }
+
+    /// Allocate a host-visible storage buffer with room for `capacity`
+    /// matrices.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not use the associated memory-mapped pointer
+    ///     once the buffer has been dropped.
+    unsafe fn allocate_buffer(
+        render_device: &Arc<RenderDevice>,
+        capacity: usize,
+    ) -> Result<(raii::Buffer, *mut Mat4), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (capacity.max(1) * std::mem::size_of::<Mat4>()) as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Scene Matrices");
+        let ptr = buffer.allocation().map(render_device.device())?;
+        Ok((buffer, ptr as *mut Mat4))
+    }
+}
@@ -0,0 +1,205 @@
+use {
+    super::Transform,
+    crate::math::{Mat4, Vec3},
+};
+
+/// The index of a node in a SceneGraph.
+///
+/// Node ids are stable for the lifetime of the graph and double as the
+/// node's index in the SceneBuffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+#[derive(Debug, Clone)]
+struct Node {
+    local: Transform,
+    world: Mat4,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    dirty: bool,
+}
+
+/// A hierarchy of nodes with local transforms.
+///
+/// World matrices are cached. Changing a node's local transform or parent
+/// marks it dirty, and the next call to `update_world_transforms` recomputes
+/// the world matrix for every dirty node and all of its descendants.
+#[derive(Debug, Clone, Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+
+// Public API
+// ----------
+
+impl SceneGraph {
+    /// Create an empty scene graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a node with the given local transform and optional parent.
+    pub fn add_node(
+        &mut self,
+        local: Transform,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(Node {
+            local,
+            world: Mat4::identity(),
+            parent,
+            children: vec![],
+            dirty: true,
+        });
+        if let Some(parent) = parent {
+            self.node_mut(parent).children.push(id);
+        }
+        id
+    }
+
+    /// The number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true when the graph has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The node's transform relative to its parent.
+    pub fn local(&self, id: NodeId) -> &Transform {
+        &self.node(id).local
+    }
+
+    /// Replace the node's local transform.
+    pub fn set_local(&mut self, id: NodeId, local: Transform) {
+        let node = self.node_mut(id);
+        node.local = local;
+        node.dirty = true;
+    }
+
+    /// Modify the node's local transform in place.
+    pub fn update_local(
+        &mut self,
+        id: NodeId,
+        update: impl FnOnce(&mut Transform),
+    ) {
+        let node = self.node_mut(id);
+        update(&mut node.local);
+        node.dirty = true;
+    }
+
+    /// The node's parent, if any.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// The node's direct children.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Move a node to a new parent, or make it a root with `None`.
+    ///
+    /// Returns false and leaves the graph unchanged if the new parent is the
+    /// node itself or one of its descendants.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if let Some(new_parent) = parent {
+            if self.is_self_or_descendant(id, new_parent) {
+                return false;
+            }
+        }
+        if let Some(old_parent) = self.node(id).parent {
+            self.node_mut(old_parent)
+                .children
+                .retain(|&child| child != id);
+        }
+        if let Some(new_parent) = parent {
+            self.node_mut(new_parent).children.push(id);
+        }
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+        true
+    }
+
+    /// The node's world matrix as of the last call to
+    /// `update_world_transforms`.
+    pub fn world(&self, id: NodeId) -> &Mat4 {
+        &self.node(id).world
+    }
+
+    /// Recompute world matrices for every dirty node and its descendants.
+    pub fn update_world_transforms(&mut self) {
+        let roots = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(index, _)| NodeId(index as u32))
+            .collect::<Vec<_>>();
+
+        // (node, parent world matrix, whether an ancestor changed)
+        let mut stack = roots
+            .into_iter()
+            .map(|id| (id, Mat4::identity(), false))
+            .collect::<Vec<_>>();
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = self.node_mut(id);
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world = parent_world * node.local.to_matrix();
+                node.dirty = false;
+            }
+            let world = node.world;
+            for &child in &self.node(id).children {
+                stack.push((child, world, changed));
+            }
+        }
+    }
+
+    /// Move every root node by `-shift`, keeping the whole scene in place
+    /// relative to a new origin.
+    ///
+    /// Pair with `math::WorldOrigin::update` so positions near the camera
+    /// stay small enough for f32. Descendants follow their roots, so only
+    /// root translations change.
+    pub fn shift_origin(&mut self, shift: &Vec3) {
+        for node in self.nodes.iter_mut().filter(|node| node.parent.is_none()) {
+            node.local.translation -= shift;
+            node.dirty = true;
+        }
+    }
+
+    /// Every node's world matrix, indexed by NodeId.
+    pub fn world_matrices(&self) -> impl Iterator<Item = &Mat4> + '_ {
+        self.nodes.iter().map(|node| &node.world)
+    }
+}
+
+// Private API
+// -----------
+
+impl SceneGraph {
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0 as usize]
+    }
+
+    /// Returns true when `node` is `id` or one of `id`'s descendants.
+    fn is_self_or_descendant(&self, id: NodeId, node: NodeId) -> bool {
+        let mut current = Some(node);
+        while let Some(current_id) = current {
+            if current_id == id {
+                return true;
+            }
+            current = self.node(current_id).parent;
+        }
+        false
+    }
+}
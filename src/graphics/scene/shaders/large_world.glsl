@@ -0,0 +1,16 @@
+// An f64 position split into high and low f32 parts. Must match SplitVec3 in
+// `math/large_world.rs`.
+struct SplitVec3 {
+    vec4 high;
+    vec4 low;
+};
+
+// The offset from `camera` to `position` without losing precision when
+// both are far from the origin. The high parts cancel exactly when the
+// points are close, so the subtractions must not be reordered or fused.
+vec3 camera_relative(SplitVec3 position, SplitVec3 camera) {
+    precise vec3 high = position.high.xyz - camera.high.xyz;
+    precise vec3 low = position.low.xyz - camera.low.xyz;
+    precise vec3 offset = high + low;
+    return offset;
+}
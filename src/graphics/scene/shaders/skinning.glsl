@@ -0,0 +1,34 @@
+// The GPU representation of a skinned vertex. Must match SkinnedVertex in
+// skin.rs.
+//
+// Declare the joint matrix buffer before including this file, then use
+// SKIN_MATRIX to build the skinning matrix for a vertex, e.g.:
+//
+//   layout(std430, set = 0, binding = 3) readonly buffer Joints {
+//       mat4 joints[];
+//   };
+//
+//   mat4 skin = SKIN_MATRIX(joints, vertex.joints, vertex.weights);
+//   vec4 pos = skin * vertex.pos;
+//
+// Defining SKINNING_DISABLED (e.g. with CCTHW_SHADER_DEFINES) makes
+// SKIN_MATRIX return the identity so one shader source can serve both skinned
+// and rigid meshes.
+
+struct SkinnedVertex {
+    vec4 pos;
+    vec4 normal;
+    vec4 uv;
+    uvec4 joints;
+    vec4 weights;
+};
+
+#ifdef SKINNING_DISABLED
+#define SKIN_MATRIX(joint_matrices, joint_indices, joint_weights) mat4(1.0)
+#else
+#define SKIN_MATRIX(joint_matrices, joint_indices, joint_weights) \
+    ((joint_weights).x * joint_matrices[(joint_indices).x] +     \
+     (joint_weights).y * joint_matrices[(joint_indices).y] +     \
+     (joint_weights).z * joint_matrices[(joint_indices).z] +     \
+     (joint_weights).w * joint_matrices[(joint_indices).w])
+#endif
@@ -0,0 +1,81 @@
+use {
+    super::{NodeId, SceneGraph},
+    crate::math::Mat4,
+};
+
+/// A vertex with up to four joint influences for GPU skinning.
+///
+/// Matches the `SkinnedVertex` struct in `shaders/skinning.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub struct SkinnedVertex {
+    pub pos: [f32; 4],
+    pub normal: [f32; 4],
+    pub uv: [f32; 4],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+/// A set of joints which deform a mesh, like a glTF skin.
+///
+/// Joints are ordinary nodes in a SceneGraph, so they can be animated with an
+/// Animation or moved by hand.
+#[derive(Debug, Clone)]
+pub struct Skin {
+    joints: Vec<NodeId>,
+    inverse_bind_matrices: Vec<Mat4>,
+}
+
+// Public API
+// ----------
+
+impl Skin {
+    /// Create a skin from its joints and their inverse bind matrices.
+    ///
+    /// Missing inverse bind matrices default to the identity, as in glTF.
+    pub fn new(
+        joints: Vec<NodeId>,
+        mut inverse_bind_matrices: Vec<Mat4>,
+    ) -> Self {
+        inverse_bind_matrices.resize(joints.len(), Mat4::identity());
+        Self {
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+
+    /// The skin's joints. A vertex's joint indices refer to this list.
+    pub fn joints(&self) -> &[NodeId] {
+        &self.joints
+    }
+
+    /// Compute the matrix for every joint.
+    ///
+    /// The caller should call `SceneGraph::update_world_transforms` first.
+    /// The results can be uploaded with
+    /// `SceneBuffer::write_matrices_for_frame`.
+    ///
+    /// # Params
+    ///
+    /// * `scene` - the scene which contains the joints
+    /// * `mesh_node` - the node which the skinned mesh is attached to. Joint
+    ///   matrices are relative to this node, so the mesh's world matrix can
+    ///   still be applied in the vertex shader. Use None when vertices should
+    ///   be transformed straight into world space.
+    pub fn joint_matrices(
+        &self,
+        scene: &SceneGraph,
+        mesh_node: Option<NodeId>,
+    ) -> Vec<Mat4> {
+        let inverse_mesh_world = mesh_node
+            .and_then(|node| scene.world(node).try_inverse())
+            .unwrap_or_else(Mat4::identity);
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind_matrix)| {
+                inverse_mesh_world * scene.world(joint) * inverse_bind_matrix
+            })
+            .collect()
+    }
+}
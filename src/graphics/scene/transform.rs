@@ -0,0 +1,58 @@
+use {
+    crate::math::{Mat4, Vec3},
+    nalgebra::UnitQuaternion,
+};
+
+/// A translation, rotation, and scale applied in scale -> rotate -> translate
+/// order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Transform {
+    /// A transform which only translates.
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Default::default()
+        }
+    }
+
+    /// Set the translation.
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Set the rotation.
+    pub fn with_rotation(mut self, rotation: UnitQuaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set a uniform scale.
+    pub fn with_uniform_scale(mut self, scale: f32) -> Self {
+        self.scale = Vec3::new(scale, scale, scale);
+        self
+    }
+
+    /// The transform as a homogeneous matrix.
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Mat4::new_nonuniform_scaling(&self.scale)
+    }
+}
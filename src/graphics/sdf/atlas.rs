@@ -0,0 +1,345 @@
+use {
+    crate::graphics::{
+        vulkan_api::{Texture2D, TextureLoader},
+        GraphicsError,
+    },
+    anyhow::{anyhow, Context},
+    std::{collections::HashMap, path::Path},
+};
+
+/// How distances are stored in an SdfAtlas texture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum SdfKind {
+    /// A single distance in the red channel.
+    SingleChannel = 0,
+
+    /// Three distances in rgb, combined with a median. Multi-channel fields
+    /// keep sharp corners at any scale.
+    MultiChannel = 1,
+}
+
+/// The placement of a single glyph in an SdfAtlas.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SdfGlyph {
+    /// How far the cursor moves after this glyph, in ems.
+    pub advance: f32,
+
+    /// The glyph's quad relative to the cursor on the baseline, in ems, as
+    /// [left, bottom, right, top]. +y is up.
+    pub plane_bounds: [f32; 4],
+
+    /// The glyph's region of the atlas texture in uv coordinates, as
+    /// [left, top, right, bottom].
+    pub atlas_bounds: [f32; 4],
+}
+
+impl SdfGlyph {
+    /// True when the glyph has no visible quad, like a space.
+    pub fn is_empty(&self) -> bool {
+        let [left, bottom, right, top] = self.plane_bounds;
+        left >= right || bottom >= top
+    }
+}
+
+/// Parameters for generating an SdfAtlas from a grid of glyph images.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SdfGridParams {
+    /// The number of glyph cells in each row of the image.
+    pub columns: u32,
+
+    /// The number of rows of glyph cells in the image.
+    pub rows: u32,
+
+    /// The character in the top-left cell. Cells are assigned consecutive
+    /// characters, left to right and then top to bottom.
+    pub first_char: char,
+
+    /// The largest distance, in pixels, stored in the field. Glyphs should
+    /// have at least this much empty space around them in their cells.
+    pub spread: u32,
+
+    /// The height of the baseline above the bottom of each cell, as a
+    /// fraction of the cell height.
+    pub baseline: f32,
+}
+
+impl Default for SdfGridParams {
+    fn default() -> Self {
+        Self {
+            columns: 16,
+            rows: 6,
+            first_char: ' ',
+            spread: 4,
+            baseline: 0.2,
+        }
+    }
+}
+
+/// A texture of signed distance fields for glyphs, plus the metrics needed
+/// to lay out text with them.
+///
+/// Distances are stored so that 0.5 is the edge of a glyph and larger
+/// values are inside, matching the output of msdf-atlas-gen.
+pub struct SdfAtlas {
+    /// The distance field texture.
+    pub texture: Texture2D,
+
+    /// How distances are stored in the texture.
+    pub kind: SdfKind,
+
+    /// The range of distances stored in the texture, in texels. A stored
+    /// value of 0 is `distance_range / 2` texels outside of the edge.
+    pub distance_range: f32,
+
+    /// The distance between lines of text, in ems.
+    pub line_height: f32,
+
+    glyphs: HashMap<char, SdfGlyph>,
+}
+
+// Public API
+// ----------
+
+impl SdfAtlas {
+    /// Generate a single-channel atlas from an image with a grid of glyphs.
+    ///
+    /// Each cell of the image holds one glyph, drawn light on dark. Every
+    /// cell becomes an em-high glyph which advances by the cell's aspect
+    /// ratio, so grid atlases are monospaced.
+    ///
+    /// # Params
+    ///
+    /// * `texture_loader` - used to create the atlas texture
+    /// * `coverage` - the glyph image. Pixels brighter than 50% are inside.
+    /// * `params` - describes the grid of glyphs
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the atlas must be dropped before the RenderDevice is destroyed
+    pub unsafe fn generate_from_grid(
+        texture_loader: &mut TextureLoader,
+        coverage: &image::GrayImage,
+        params: &SdfGridParams,
+    ) -> Result<Self, GraphicsError> {
+        if params.columns == 0 || params.rows == 0 {
+            return Err(anyhow!("An SDF grid needs at least one cell").into());
+        }
+        let (width, height) = coverage.dimensions();
+        let cell_width = width / params.columns;
+        let cell_height = height / params.rows;
+        if cell_width == 0 || cell_height == 0 {
+            return Err(anyhow!(
+                "A {}x{} image is too small for a {}x{} grid",
+                width,
+                height,
+                params.columns,
+                params.rows
+            )
+            .into());
+        }
+
+        let spread = params.spread.max(1);
+        let mut pixels = vec![0_u8; (width * height * 4) as usize];
+        let mut glyphs = HashMap::new();
+        let aspect = cell_width as f32 / cell_height as f32;
+        for row in 0..params.rows {
+            for column in 0..params.columns {
+                let x0 = column * cell_width;
+                let y0 = row * cell_height;
+                let distances = signed_distances(
+                    coverage,
+                    [x0, y0],
+                    [cell_width, cell_height],
+                    spread,
+                );
+                for (i, distance) in distances.iter().enumerate() {
+                    let x = x0 + i as u32 % cell_width;
+                    let y = y0 + i as u32 / cell_width;
+                    let encoded = (0.5 - distance / (2.0 * spread as f32))
+                        .clamp(0.0, 1.0);
+                    let value = (encoded * 255.0).round() as u8;
+                    let offset = ((y * width + x) * 4) as usize;
+                    pixels[offset..offset + 4]
+                        .copy_from_slice(&[value, value, value, 255]);
+                }
+
+                let index = row * params.columns + column;
+                let character =
+                    match char::from_u32(params.first_char as u32 + index) {
+                        Some(character) => character,
+                        None => continue,
+                    };
+                glyphs.insert(
+                    character,
+                    SdfGlyph {
+                        advance: aspect,
+                        plane_bounds: [
+                            0.0,
+                            -params.baseline,
+                            aspect,
+                            1.0 - params.baseline,
+                        ],
+                        atlas_bounds: [
+                            x0 as f32 / width as f32,
+                            y0 as f32 / height as f32,
+                            (x0 + cell_width) as f32 / width as f32,
+                            (y0 + cell_height) as f32 / height as f32,
+                        ],
+                    },
+                );
+            }
+        }
+
+        let texture = texture_loader
+            .create_texture_2d_from_rgba8(width, height, &pixels)?;
+        Ok(Self {
+            texture,
+            kind: SdfKind::SingleChannel,
+            distance_range: 2.0 * spread as f32,
+            line_height: 1.2,
+            glyphs,
+        })
+    }
+
+    /// Load a multi-channel atlas created by msdf-atlas-gen.
+    ///
+    /// The atlas should be generated with `-type msdf -format png -csv`,
+    /// using the default bottom y origin. The CSV has one glyph per line:
+    /// `unicode,advance,planeLeft,planeBottom,planeRight,planeTop,
+    /// atlasLeft,atlasBottom,atlasRight,atlasTop`.
+    ///
+    /// # Params
+    ///
+    /// * `texture_loader` - used to create the atlas texture
+    /// * `image_path` - the atlas png
+    /// * `csv_path` - the glyph layout csv
+    /// * `distance_range` - the `-pxrange` used to generate the atlas
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the atlas must be dropped before the RenderDevice is destroyed
+    #[tracing::instrument(
+        skip_all,
+        fields(image_path = %image_path.as_ref().display())
+    )]
+    pub unsafe fn load_msdf(
+        texture_loader: &mut TextureLoader,
+        image_path: impl AsRef<Path>,
+        csv_path: impl AsRef<Path>,
+        distance_range: f32,
+    ) -> Result<Self, GraphicsError> {
+        let csv = std::fs::read_to_string(&csv_path).with_context(|| {
+            format!("Unable to read glyph csv at {:?}", csv_path.as_ref())
+        })?;
+        let texture = texture_loader.load_texture_2d(image_path)?;
+        let width = texture.extent.width as f32;
+        let height = texture.extent.height as f32;
+
+        let mut glyphs = HashMap::new();
+        for (line_number, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let values = line
+                .split(',')
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .filter(|values| values.len() == 10)
+                .with_context(|| {
+                    format!(
+                        "Invalid glyph on line {} of {:?}",
+                        line_number + 1,
+                        csv_path.as_ref()
+                    )
+                })?;
+            let character = match char::from_u32(values[0] as u32) {
+                Some(character) => character,
+                None => continue,
+            };
+            let v = |i: usize| values[i] as f32;
+            glyphs.insert(
+                character,
+                SdfGlyph {
+                    advance: v(1),
+                    plane_bounds: [v(2), v(3), v(4), v(5)],
+                    atlas_bounds: [
+                        v(6) / width,
+                        1.0 - v(9) / height,
+                        v(8) / width,
+                        1.0 - v(7) / height,
+                    ],
+                },
+            );
+        }
+
+        Ok(Self {
+            texture,
+            kind: SdfKind::MultiChannel,
+            distance_range,
+            line_height: 1.2,
+            glyphs,
+        })
+    }
+
+    /// The glyph for a character, if the atlas has one.
+    pub fn glyph(&self, character: char) -> Option<&SdfGlyph> {
+        self.glyphs.get(&character)
+    }
+
+    /// The width of a single line of text, in ems.
+    pub fn measure(&self, text: &str) -> f32 {
+        text.chars()
+            .filter_map(|character| self.glyph(character))
+            .map(|glyph| glyph.advance)
+            .sum()
+    }
+}
+
+/// Compute the signed distance, in pixels, from each pixel in a rectangle of
+/// the coverage image to the nearest edge. Distances are negative inside and
+/// clamped to `spread`.
+///
+/// This is a brute-force search of the `spread` neighborhood around each
+/// pixel, which is plenty fast for the small spreads used by glyph atlases.
+fn signed_distances(
+    coverage: &image::GrayImage,
+    [x0, y0]: [u32; 2],
+    [width, height]: [u32; 2],
+    spread: u32,
+) -> Vec<f32> {
+    let inside = |x: i64, y: i64| -> bool {
+        coverage.get_pixel((x0 as i64 + x) as u32, (y0 as i64 + y) as u32)[0]
+            >= 128
+    };
+    let spread = spread as i64;
+    let (width, height) = (width as i64, height as i64);
+
+    let mut distances = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let is_inside = inside(x, y);
+            let mut nearest = spread as f32;
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    let (sx, sy) = (x + dx, y + dy);
+                    if sx < 0 || sy < 0 || sx >= width || sy >= height {
+                        continue;
+                    }
+                    if inside(sx, sy) != is_inside {
+                        // The edge is halfway between the two pixel centers.
+                        let distance =
+                            ((dx * dx + dy * dy) as f32).sqrt() - 0.5;
+                        nearest = nearest.min(distance);
+                    }
+                }
+            }
+            distances.push(if is_inside { -nearest } else { nearest });
+        }
+    }
+    distances
+}
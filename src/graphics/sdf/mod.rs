@@ -0,0 +1,14 @@
+//! Signed distance field text and shapes.
+//!
+//! An SdfAtlas holds glyph distance fields, either generated from a grid of
+//! glyph images or loaded from msdf-atlas-gen output. The SdfRenderer draws
+//! text from an atlas, plus analytic circles and rounded rectangles, with
+//! crisp edges at any scale and a per-draw outline and glow.
+
+mod atlas;
+mod renderer;
+
+pub use self::{
+    atlas::{SdfAtlas, SdfGlyph, SdfGridParams, SdfKind},
+    renderer::{SdfRenderer, SdfStyle},
+};
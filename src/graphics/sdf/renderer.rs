@@ -0,0 +1,575 @@
+use {
+    super::{SdfAtlas, SdfKind},
+    crate::{
+        graphics::{
+            vulkan_api::{
+                raii, ColorPass, Frame, FramesInFlight,
+                GraphicsPipelineBuilder, RenderDevice, Texture2D,
+                TextureLoader,
+            },
+            GraphicsError,
+        },
+        math::{Mat4, Vec2},
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Fill, outline, and glow settings for a single SDF draw.
+///
+/// Widths are in world units, the same units as the positions passed to
+/// the SdfRenderer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SdfStyle {
+    /// The color inside the shape.
+    pub fill_color: [f32; 4],
+
+    /// The color of the band just outside the shape.
+    pub outline_color: [f32; 4],
+
+    /// The width of the outline. 0 disables the outline.
+    pub outline_width: f32,
+
+    /// The color of the glow outside of the outline.
+    pub glow_color: [f32; 4],
+
+    /// How far the glow extends past the outline. 0 disables the glow.
+    pub glow_width: f32,
+
+    /// Extra blur added to every edge, in world units. 0 gives crisp,
+    /// antialiased edges.
+    pub softness: f32,
+}
+
+impl Default for SdfStyle {
+    fn default() -> Self {
+        Self {
+            fill_color: [1.0, 1.0, 1.0, 1.0],
+            outline_color: [0.0, 0.0, 0.0, 1.0],
+            outline_width: 0.0,
+            glow_color: [1.0, 1.0, 1.0, 0.5],
+            glow_width: 0.0,
+            softness: 0.0,
+        }
+    }
+}
+
+/// A single quad drawn by the SdfRenderer. Must match SdfQuad in
+/// `shaders/sdf.glsl`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[repr(C)]
+struct SdfQuad {
+    /// The quad's corners in world space as [min x, min y, max x, max y].
+    rect: [f32; 4],
+
+    /// Glyphs: the atlas uv at the quad's min corner in xy and max corner in
+    /// zw. Shapes: the shape's bounds in world space.
+    shape: [f32; 4],
+
+    fill_color: [f32; 4],
+    outline_color: [f32; 4],
+    glow_color: [f32; 4],
+
+    /// [outline width, glow width, softness, corner radius]
+    widths: [f32; 4],
+
+    kind: u32,
+    _pad: [u32; 3],
+}
+
+/// Quad kinds. Must match the constants in `shaders/sdf.glsl`.
+mod kind {
+    pub const GLYPH: u32 = 0;
+    pub const CIRCLE: u32 = 1;
+    pub const ROUNDED_RECT: u32 = 2;
+}
+
+/// Push constants for the SDF shaders. Must match SdfConstants in
+/// `shaders/sdf.glsl`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SdfConstants {
+    projection: Mat4,
+    atlas_size: [f32; 2],
+    distance_range: f32,
+    atlas_kind: u32,
+}
+
+/// Draws text and shapes with signed distance fields, so edges stay crisp at
+/// any scale and every draw can add its own outline and glow.
+///
+/// Text is read from an SdfAtlas. Circles and rounded rectangles are
+/// computed analytically in the fragment shader.
+///
+/// Each frame, queue text and shapes with the `push_*` methods and then call
+/// `draw` inside the render pass. Queued draws are kept until `clear`.
+pub struct SdfRenderer {
+    quads: Vec<SdfQuad>,
+    atlas_size: [f32; 2],
+    distance_range: f32,
+    atlas_kind: SdfKind,
+    extent: vk::Extent2D,
+
+    quad_buffers: Vec<raii::Buffer>,
+    quad_buffer_ptrs: Vec<*mut SdfQuad>,
+    quad_capacities: Vec<usize>,
+
+    _placeholder: Option<Texture2D>,
+    atlas_view: vk::ImageView,
+    sampler: raii::Sampler,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl SdfRenderer {
+    /// Create the SDF pipeline and per-frame quad buffers.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `frames_in_flight` - quads are double-buffered per frame
+    /// * `color_pass` - the render pass everything is drawn into
+    /// * `atlas` - the glyphs used by `push_text`. Renderers which only draw
+    ///   shapes can pass None.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - the atlas must not be dropped before this instance
+    ///   - this instance must be rebuilt any time the ColorPass is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        color_pass: &ColorPass,
+        atlas: Option<&SdfAtlas>,
+    ) -> Result<Self, GraphicsError> {
+        // Shape-only renderers still need something bound to the atlas
+        // binding.
+        let mut placeholder = None;
+        let (atlas_texture, distance_range, atlas_kind) = match atlas {
+            Some(atlas) => (&atlas.texture, atlas.distance_range, atlas.kind),
+            None => (
+                &*placeholder.insert(
+                    TextureLoader::new(render_device.clone())?
+                        .create_texture_2d_from_rgba8(1, 1, &[0, 0, 0, 0])?,
+                ),
+                1.0,
+                SdfKind::SingleChannel,
+            ),
+        };
+        let atlas_view = atlas_texture.image_view.raw();
+        let atlas_size = atlas_texture.extent;
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::VERTEX
+                    | vk::ShaderStageFlags::FRAGMENT,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
+        ];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::VERTEX
+                        | vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<SdfConstants>() as u32,
+                }],
+            )?;
+        let pipeline = GraphicsPipelineBuilder::new()
+            .vertex_shader(include_bytes!("./shaders/sdf.vert.spv"))
+            .fragment_shader(include_bytes!("./shaders/sdf.frag.spv"))
+            .cull_mode(vk::CullModeFlags::NONE, vk::FrontFace::CLOCKWISE)
+            .color_blend_attachment(
+                GraphicsPipelineBuilder::alpha_blend_attachment(),
+            )
+            .build(
+                render_device.clone(),
+                &pipeline_layout,
+                color_pass.render_pass(),
+            )?;
+        pipeline.set_debug_name("SDF Pipeline");
+
+        let sampler = raii::Sampler::new(
+            render_device.clone(),
+            &vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            },
+        )?;
+
+        let frame_count = frames_in_flight.frame_count();
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            frame_count as u32,
+            &[
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: frame_count as u32,
+                },
+                vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: frame_count as u32,
+                },
+            ],
+        )?;
+        let layouts = (0..frame_count)
+            .map(|_| &descriptor_set_layout)
+            .collect::<Vec<_>>();
+        let _ = descriptor_pool.allocate_descriptor_sets(&layouts)?;
+
+        let mut renderer = Self {
+            quads: vec![],
+            atlas_size: [atlas_size.width as f32, atlas_size.height as f32],
+            distance_range,
+            atlas_kind,
+            extent: color_pass.extent(),
+            quad_buffers: Vec::with_capacity(frame_count),
+            quad_buffer_ptrs: Vec::with_capacity(frame_count),
+            quad_capacities: Vec::with_capacity(frame_count),
+            _placeholder: placeholder,
+            atlas_view,
+            sampler,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            render_device,
+        };
+        for index in 0..frame_count {
+            let (buffer, ptr) = renderer.allocate_quad_buffer(256)?;
+            renderer.quad_buffers.push(buffer);
+            renderer.quad_buffer_ptrs.push(ptr);
+            renderer.quad_capacities.push(256);
+            renderer.write_descriptor_set(index);
+        }
+        Ok(renderer)
+    }
+
+    /// Remove every queued draw.
+    pub fn clear(&mut self) {
+        self.quads.clear();
+    }
+
+    /// Queue a line of text.
+    ///
+    /// Text is laid out along +x with +y up, starting with the baseline at
+    /// `position`. Newlines move down by the atlas's line height. Characters
+    /// which aren't in the atlas are skipped.
+    ///
+    /// # Params
+    ///
+    /// * `atlas` - the atlas this renderer was created with
+    /// * `text` - the text to draw
+    /// * `position` - the start of the first line's baseline
+    /// * `size` - the height of an em in world units
+    /// * `style` - the fill, outline, and glow
+    pub fn push_text(
+        &mut self,
+        atlas: &SdfAtlas,
+        text: &str,
+        position: Vec2,
+        size: f32,
+        style: &SdfStyle,
+    ) {
+        // Grow each quad so outlines and glows aren't clipped, but never
+        // past the distances stored in the atlas.
+        let margin = style.outline_width + style.glow_width + style.softness;
+        let mut cursor = position;
+        for character in text.chars() {
+            if character == '\n' {
+                cursor =
+                    Vec2::new(position.x, cursor.y - atlas.line_height * size);
+                continue;
+            }
+            let glyph = match atlas.glyph(character) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if !glyph.is_empty() {
+                let [left, bottom, right, top] = glyph.plane_bounds;
+                let [u0, v0, u1, v1] = glyph.atlas_bounds;
+                let world_per_texel =
+                    (right - left) * size / ((u1 - u0) * self.atlas_size[0]);
+                let margin_texels =
+                    (margin / world_per_texel).min(0.5 * atlas.distance_range);
+                let grow = margin_texels * world_per_texel;
+                let du = margin_texels / self.atlas_size[0];
+                let dv = margin_texels / self.atlas_size[1];
+                self.quads.push(SdfQuad {
+                    rect: [
+                        cursor.x + left * size - grow,
+                        cursor.y + bottom * size - grow,
+                        cursor.x + right * size + grow,
+                        cursor.y + top * size + grow,
+                    ],
+                    shape: [u0 - du, v1 + dv, u1 + du, v0 - dv],
+                    kind: kind::GLYPH,
+                    ..Self::styled_quad(style, 0.0)
+                });
+            }
+            cursor.x += glyph.advance * size;
+        }
+    }
+
+    /// Queue a circle.
+    pub fn push_circle(&mut self, center: Vec2, radius: f32, style: &SdfStyle) {
+        let extent = Vec2::new(radius, radius);
+        self.push_shape(
+            kind::CIRCLE,
+            center - extent,
+            center + extent,
+            radius,
+            style,
+        );
+    }
+
+    /// Queue a rectangle with rounded corners. A corner radius of 0 gives
+    /// sharp corners.
+    pub fn push_rounded_rect(
+        &mut self,
+        min: Vec2,
+        max: Vec2,
+        corner_radius: f32,
+        style: &SdfStyle,
+    ) {
+        let half_size = (max - min) * 0.5;
+        let corner_radius =
+            corner_radius.clamp(0.0, half_size.x.min(half_size.y));
+        self.push_shape(kind::ROUNDED_RECT, min, max, corner_radius, style);
+    }
+
+    /// Add commands to draw every queued text and shape.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `projection` - transforms world space to clip space, e.g. from
+    ///   `math::ortho_projection`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    pub unsafe fn draw(
+        &mut self,
+        frame: &Frame,
+        projection: &Mat4,
+    ) -> Result<(), GraphicsError> {
+        if self.quads.is_empty() {
+            return Ok(());
+        }
+        let index = frame.frame_index();
+        if self.quad_capacities[index] < self.quads.len() {
+            let capacity = self.quads.len().next_power_of_two();
+            let (buffer, ptr) = self.allocate_quad_buffer(capacity)?;
+            self.quad_buffers[index] = buffer;
+            self.quad_buffer_ptrs[index] = ptr;
+            self.quad_capacities[index] = capacity;
+            self.write_descriptor_set(index);
+        }
+        std::slice::from_raw_parts_mut(
+            self.quad_buffer_ptrs[index],
+            self.quads.len(),
+        )
+        .copy_from_slice(&self.quads);
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(index)],
+            &[],
+        );
+        let constants = SdfConstants {
+            projection: *projection,
+            atlas_size: self.atlas_size,
+            distance_range: self.distance_range,
+            atlas_kind: self.atlas_kind as u32,
+        };
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const SdfConstants as *const u8,
+                std::mem::size_of::<SdfConstants>(),
+            ),
+        );
+        device.cmd_draw(command_buffer, 6 * self.quads.len() as u32, 1, 0, 0);
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl SdfRenderer {
+    /// A quad with the style's colors and widths and no geometry.
+    fn styled_quad(style: &SdfStyle, corner_radius: f32) -> SdfQuad {
+        SdfQuad {
+            fill_color: style.fill_color,
+            outline_color: style.outline_color,
+            glow_color: style.glow_color,
+            widths: [
+                style.outline_width.max(0.0),
+                style.glow_width.max(0.0),
+                style.softness.max(0.0),
+                corner_radius,
+            ],
+            ..SdfQuad::default()
+        }
+    }
+
+    /// Queue an analytic shape, growing its quad to fit the outline and
+    /// glow.
+    fn push_shape(
+        &mut self,
+        kind: u32,
+        min: Vec2,
+        max: Vec2,
+        corner_radius: f32,
+        style: &SdfStyle,
+    ) {
+        // A little extra room keeps antialiased edges from being clipped.
+        let grow = style.outline_width.max(0.0)
+            + style.glow_width.max(0.0)
+            + style.softness.max(0.0)
+            + (max - min).norm() * 0.01;
+        self.quads.push(SdfQuad {
+            rect: [min.x - grow, min.y - grow, max.x + grow, max.y + grow],
+            shape: [min.x, min.y, max.x, max.y],
+            kind,
+            ..Self::styled_quad(style, corner_radius)
+        });
+    }
+
+    /// Allocate a persistently mapped, host-visible buffer of quads.
+    unsafe fn allocate_quad_buffer(
+        &self,
+        capacity: usize,
+    ) -> Result<(raii::Buffer, *mut SdfQuad), GraphicsError> {
+        let queue_family_index =
+            self.render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (capacity * std::mem::size_of::<SdfQuad>()) as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            self.render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("SDF Quads");
+        let ptr = buffer.allocation().map(self.render_device.device())?;
+        Ok((buffer, ptr as *mut SdfQuad))
+    }
+
+    /// Point a frame's descriptor set at its quad buffer and the atlas.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the descriptor set must not be in use by the GPU
+    unsafe fn write_descriptor_set(&self, index: usize) {
+        let descriptor_set = self.descriptor_pool.descriptor_set(index);
+        let buffer_info = vk::DescriptorBufferInfo {
+            buffer: self.quad_buffers[index].raw(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        };
+        let image_info = vk::DescriptorImageInfo {
+            sampler: self.sampler.raw(),
+            image_view: self.atlas_view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        self.render_device.device().update_descriptor_sets(
+            &[
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: 0,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                    p_buffer_info: &buffer_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_set,
+                    dst_binding: 1,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    p_image_info: &image_info,
+                    ..vk::WriteDescriptorSet::default()
+                },
+            ],
+            &[],
+        );
+    }
+}
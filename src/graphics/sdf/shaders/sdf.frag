@@ -0,0 +1,78 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "sdf.glsl"
+
+layout(location = 0) in vec2 world_position;
+layout(location = 1) in vec2 uv;
+layout(location = 2) flat in uint quad_index;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 1) uniform sampler2D atlas;
+
+float median(vec3 v) {
+    return max(min(v.r, v.g), min(max(v.r, v.g), v.b));
+}
+
+// The distance to a glyph's edge in world units, positive outside.
+float glyph_distance(SdfQuad quad) {
+    vec4 texel = texture(atlas, uv);
+    float encoded = constants.atlas_kind == MULTI_CHANNEL
+        ? median(texel.rgb)
+        : texel.r;
+    float texels_across = abs(quad.shape.z - quad.shape.x)
+        * constants.atlas_size.x;
+    float world_per_texel = (quad.rect.z - quad.rect.x) / texels_across;
+    return (0.5 - encoded) * constants.distance_range * world_per_texel;
+}
+
+// The distance to a rounded rectangle's edge, positive outside.
+float rounded_rect_distance(vec2 p, vec4 bounds, float radius) {
+    vec2 center = 0.5 * (bounds.xy + bounds.zw);
+    vec2 half_size = 0.5 * (bounds.zw - bounds.xy);
+    vec2 q = abs(p - center) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    SdfQuad quad = quads.data[quad_index];
+    float outline_width = quad.widths.x;
+    float glow_width = quad.widths.y;
+    float softness = quad.widths.z;
+
+    float d;
+    if (quad.kind == GLYPH) {
+        d = glyph_distance(quad);
+    } else if (quad.kind == CIRCLE) {
+        vec2 center = 0.5 * (quad.shape.xy + quad.shape.zw);
+        d = length(world_position - center) - quad.widths.w;
+    } else {
+        d = rounded_rect_distance(world_position, quad.shape, quad.widths.w);
+    }
+
+    // Antialias over about one pixel, plus any requested softness.
+    float edge_width = max(fwidth(d), 1e-6) + softness;
+    float fill = clamp(0.5 - d / edge_width, 0.0, 1.0);
+    float body = clamp(0.5 - (d - outline_width) / edge_width, 0.0, 1.0);
+
+    vec4 inner = outline_width > 0.0
+        ? mix(quad.outline_color, quad.fill_color, fill)
+        : quad.fill_color;
+    float body_alpha = inner.a * body;
+
+    float glow_alpha = 0.0;
+    if (glow_width > 0.0) {
+        glow_alpha = quad.glow_color.a * (1.0 - smoothstep(
+            outline_width, outline_width + glow_width, d));
+    }
+
+    float alpha = body_alpha + glow_alpha * (1.0 - body_alpha);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    vec3 color = (inner.rgb * body_alpha
+        + quad.glow_color.rgb * glow_alpha * (1.0 - body_alpha)) / alpha;
+    out_color = vec4(color, alpha);
+}
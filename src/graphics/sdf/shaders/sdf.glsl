@@ -0,0 +1,42 @@
+// Declarations shared by the SDF vertex and fragment shaders.
+
+struct SdfQuad {
+    // The quad's corners in world space: min in xy, max in zw.
+    vec4 rect;
+
+    // Glyphs: the atlas uv at the quad's min corner in xy and max corner in
+    // zw. Shapes: the shape's bounds in world space.
+    vec4 shape;
+
+    vec4 fill_color;
+    vec4 outline_color;
+    vec4 glow_color;
+
+    // outline width, glow width, softness, corner radius
+    vec4 widths;
+
+    uint kind;
+    uint pad0;
+    uint pad1;
+    uint pad2;
+};
+
+// Quad kinds, see `kind` in renderer.rs.
+const uint GLYPH = 0;
+const uint CIRCLE = 1;
+const uint ROUNDED_RECT = 2;
+
+// Atlas kinds, see SdfKind in atlas.rs.
+const uint SINGLE_CHANNEL = 0;
+const uint MULTI_CHANNEL = 1;
+
+layout(std430, set = 0, binding = 0) readonly buffer Quads {
+    SdfQuad data[];
+} quads;
+
+layout(push_constant) uniform SdfConstants {
+    mat4 projection;
+    vec2 atlas_size;
+    float distance_range;
+    uint atlas_kind;
+} constants;
@@ -0,0 +1,29 @@
+#version 460
+
+#extension GL_GOOGLE_include_directive : require
+
+#include "sdf.glsl"
+
+layout(location = 0) out vec2 world_position;
+layout(location = 1) out vec2 uv;
+layout(location = 2) flat out uint quad_index;
+
+// Two triangles per quad, as offsets from the min to the max corner.
+const vec2 CORNERS[6] = vec2[](
+    vec2(0.0, 0.0),
+    vec2(1.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 1.0)
+);
+
+void main() {
+    quad_index = gl_VertexIndex / 6;
+    SdfQuad quad = quads.data[quad_index];
+    vec2 corner = CORNERS[gl_VertexIndex % 6];
+
+    world_position = mix(quad.rect.xy, quad.rect.zw, corner);
+    uv = mix(quad.shape.xy, quad.shape.zw, corner);
+    gl_Position = constants.projection * vec4(world_position, 0.0, 1.0);
+}
@@ -0,0 +1,278 @@
+use {
+    crate::graphics::GraphicsError,
+    anyhow::{anyhow, Context},
+    serde_json::Value,
+    std::{collections::HashMap, path::Path},
+};
+
+/// Border widths, in texels, which keep their size when a region is drawn
+/// with 9-slice scaling. Everything between the borders stretches.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct NineSlice {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// A rectangular region of an atlas texture, in texels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasRegion {
+    /// The region's left edge.
+    pub x: u32,
+
+    /// The region's top edge.
+    pub y: u32,
+
+    pub width: u32,
+    pub height: u32,
+
+    /// Borders for 9-slice scaling, if the region is meant to be drawn as a
+    /// resizable panel.
+    pub nine_slice: Option<NineSlice>,
+}
+
+/// Describes a uniform grid of cells in an atlas texture.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasGrid {
+    pub cell_width: u32,
+    pub cell_height: u32,
+
+    /// Empty texels between the edge of the texture and the first cell.
+    pub margin: u32,
+
+    /// Empty texels between neighboring cells.
+    pub spacing: u32,
+}
+
+/// Named regions of a single texture which is bound to BindlessTriangles.
+pub struct TextureAtlas {
+    texture_index: u32,
+    width: u32,
+    height: u32,
+    regions: Vec<AtlasRegion>,
+    names: HashMap<String, usize>,
+}
+
+// Public API
+// ----------
+
+impl TextureAtlas {
+    /// Create an atlas without any regions.
+    ///
+    /// # Params
+    ///
+    /// * `texture_index` - the texture's index in the BindlessTriangles
+    ///   texture array
+    /// * `width` - the texture's width in texels
+    /// * `height` - the texture's height in texels
+    pub fn new(texture_index: u32, width: u32, height: u32) -> Self {
+        Self {
+            texture_index,
+            width,
+            height,
+            regions: vec![],
+            names: HashMap::new(),
+        }
+    }
+
+    /// Create an atlas with one region for every full cell of a grid.
+    ///
+    /// Cells are ordered left to right and then top to bottom. Each cell is
+    /// named by its index, so `region("3")` and `region_at(3)` are the same.
+    pub fn from_grid(
+        texture_index: u32,
+        width: u32,
+        height: u32,
+        grid: &AtlasGrid,
+    ) -> Result<Self, GraphicsError> {
+        if grid.cell_width == 0 || grid.cell_height == 0 {
+            return Err(anyhow!("Atlas grid cells must not be empty").into());
+        }
+        let cells = |size: u32, cell: u32| -> u32 {
+            let usable = size.saturating_sub(2 * grid.margin) + grid.spacing;
+            usable / (cell + grid.spacing)
+        };
+        let columns = cells(width, grid.cell_width);
+        let rows = cells(height, grid.cell_height);
+
+        let mut atlas = Self::new(texture_index, width, height);
+        for row in 0..rows {
+            for column in 0..columns {
+                let index = atlas.regions.len();
+                atlas.add_region(
+                    index.to_string(),
+                    AtlasRegion {
+                        x: grid.margin
+                            + column * (grid.cell_width + grid.spacing),
+                        y: grid.margin
+                            + row * (grid.cell_height + grid.spacing),
+                        width: grid.cell_width,
+                        height: grid.cell_height,
+                        nine_slice: None,
+                    },
+                );
+            }
+        }
+        Ok(atlas)
+    }
+
+    /// Parse an atlas from TexturePacker-style JSON, in either the hash or
+    /// the array format.
+    ///
+    /// Every frame needs a `frame` rect with `x`, `y`, `w`, and `h`, and
+    /// `meta.size` must have the texture's `w` and `h`. Frames may also
+    /// have `borders` with `left`, `top`, `right`, and `bottom` to enable
+    /// 9-slice scaling. Rotated frames are not supported.
+    ///
+    /// # Params
+    ///
+    /// * `texture_index` - the texture's index in the BindlessTriangles
+    ///   texture array
+    /// * `json` - the atlas definition
+    pub fn from_json(
+        texture_index: u32,
+        json: &str,
+    ) -> Result<Self, GraphicsError> {
+        let root: Value =
+            serde_json::from_str(json).context("Unable to parse atlas json")?;
+        let size = &root["meta"]["size"];
+        let mut atlas = Self::new(
+            texture_index,
+            json_u32(&size["w"], "meta.size.w")?,
+            json_u32(&size["h"], "meta.size.h")?,
+        );
+
+        match &root["frames"] {
+            Value::Object(frames) => {
+                for (name, frame) in frames {
+                    atlas.add_region(name.clone(), parse_frame(name, frame)?);
+                }
+            }
+            Value::Array(frames) => {
+                for frame in frames {
+                    let name = frame["filename"]
+                        .as_str()
+                        .context("Atlas frames need a filename")?;
+                    atlas.add_region(name, parse_frame(name, frame)?);
+                }
+            }
+            _ => return Err(anyhow!("Atlas json needs a frames object").into()),
+        }
+        Ok(atlas)
+    }
+
+    /// Read and parse an atlas json file. See `from_json` for the format.
+    #[tracing::instrument(skip_all, fields(path = %path.as_ref().display()))]
+    pub fn load_json(
+        texture_index: u32,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, GraphicsError> {
+        let json = std::fs::read_to_string(&path).with_context(|| {
+            format!("Unable to read atlas json at {:?}", path.as_ref())
+        })?;
+        Self::from_json(texture_index, &json)
+    }
+
+    /// Add a region and return its index. Adding a name which is already in
+    /// use points the name at the new region.
+    pub fn add_region(
+        &mut self,
+        name: impl Into<String>,
+        region: AtlasRegion,
+    ) -> usize {
+        let index = self.regions.len();
+        self.regions.push(region);
+        self.names.insert(name.into(), index);
+        index
+    }
+
+    /// The region with the given name.
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.names.get(name).map(|&index| &self.regions[index])
+    }
+
+    /// The region at the given index.
+    pub fn region_at(&self, index: usize) -> Option<&AtlasRegion> {
+        self.regions.get(index)
+    }
+
+    /// The index of the region with the given name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// The number of regions in the atlas.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// True when the atlas has no regions.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// The texture's index in the BindlessTriangles texture array.
+    pub fn texture_index(&self) -> u32 {
+        self.texture_index
+    }
+
+    /// The texture's size in texels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Convert a rect in texels to uv coordinates as
+    /// [left, top, right, bottom].
+    pub fn uv_bounds(
+        &self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> [f32; 4] {
+        let (w, h) = (self.width as f32, self.height as f32);
+        [x / w, y / h, (x + width) / w, (y + height) / h]
+    }
+}
+
+/// Parse a single TexturePacker frame.
+fn parse_frame(
+    name: &str,
+    frame: &Value,
+) -> Result<AtlasRegion, GraphicsError> {
+    if frame["rotated"].as_bool().unwrap_or(false) {
+        return Err(anyhow!("Atlas frame {} is rotated", name).into());
+    }
+    let rect = &frame["frame"];
+    let field = |value: &Value, key: &str| {
+        json_u32(&value[key], &format!("{}.{}", name, key))
+    };
+    let nine_slice = match &frame["borders"] {
+        Value::Null => None,
+        borders => Some(NineSlice {
+            left: field(borders, "left")?,
+            top: field(borders, "top")?,
+            right: field(borders, "right")?,
+            bottom: field(borders, "bottom")?,
+        }),
+    };
+    Ok(AtlasRegion {
+        x: field(rect, "x")?,
+        y: field(rect, "y")?,
+        width: field(rect, "w")?,
+        height: field(rect, "h")?,
+        nine_slice,
+    })
+}
+
+/// Read a non-negative integer from a json value.
+fn json_u32(value: &Value, name: &str) -> Result<u32, GraphicsError> {
+    value
+        .as_u64()
+        .map(|value| value as u32)
+        .with_context(|| {
+            format!("Expected a non-negative integer for {}", name)
+        })
+        .map_err(GraphicsError::from)
+}
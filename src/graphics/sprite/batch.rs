@@ -0,0 +1,228 @@
+use {
+    super::{AtlasRegion, TextureAtlas},
+    crate::{
+        graphics::vulkan_api::BindlessVertex,
+        math::{Mat4, Vec2, Vec4},
+    },
+};
+
+/// Builds textured and colored quads for BindlessTriangles.
+///
+/// Positions are given in world space and transformed by the batch's
+/// projection on the CPU, so the vertices are ready for
+/// `BindlessTriangles::write_vertices_for_frame`. With a y-up projection,
+/// like `math::ortho_projection`, the top of each texture region is drawn at
+/// the top of its rectangle.
+pub struct SpriteBatch {
+    projection: Mat4,
+    vertices: Vec<BindlessVertex>,
+}
+
+// Public API
+// ----------
+
+impl SpriteBatch {
+    /// Create an empty batch which transforms positions by `projection`.
+    pub fn new(projection: Mat4) -> Self {
+        Self {
+            projection,
+            vertices: vec![],
+        }
+    }
+
+    /// Replace the projection used for quads added from now on.
+    pub fn set_projection(&mut self, projection: Mat4) {
+        self.projection = projection;
+    }
+
+    /// Remove every quad.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// The vertices for every quad added since the last clear.
+    pub fn vertices(&self) -> &[BindlessVertex] {
+        &self.vertices
+    }
+
+    /// Add an untextured rectangle.
+    pub fn draw_rect(&mut self, min: Vec2, max: Vec2, color: [f32; 4]) {
+        self.push_quad(min, max, -1.0, [0.0, 0.0, 0.0, 0.0], color);
+    }
+
+    /// Add a rectangle which shows a whole texture.
+    ///
+    /// # Params
+    ///
+    /// * `texture_index` - the texture's index in the BindlessTriangles
+    ///   texture array
+    /// * `color` - multiplied with the texture
+    pub fn draw_texture(
+        &mut self,
+        min: Vec2,
+        max: Vec2,
+        texture_index: u32,
+        color: [f32; 4],
+    ) {
+        self.push_quad(
+            min,
+            max,
+            texture_index as f32,
+            [0.0, 0.0, 1.0, 1.0],
+            color,
+        );
+    }
+
+    /// Add a rectangle which shows a single region of an atlas, stretched to
+    /// fill the rectangle.
+    pub fn draw_region(
+        &mut self,
+        atlas: &TextureAtlas,
+        region: &AtlasRegion,
+        min: Vec2,
+        max: Vec2,
+        color: [f32; 4],
+    ) {
+        let uv = atlas.uv_bounds(
+            region.x as f32,
+            region.y as f32,
+            region.width as f32,
+            region.height as f32,
+        );
+        self.push_quad(min, max, atlas.texture_index() as f32, uv, color);
+    }
+
+    /// Add a rectangle which shows a region with 9-slice scaling.
+    ///
+    /// The corners keep their size, the edges stretch along one axis, and
+    /// the center stretches to fill the rest. When the rectangle is too
+    /// small for the borders they shrink to fit. Regions without 9-slice
+    /// borders are drawn like `draw_region`.
+    ///
+    /// # Params
+    ///
+    /// * `atlas` - the atlas which owns the region
+    /// * `region` - the region to draw
+    /// * `min` - the rectangle's min corner in world space
+    /// * `max` - the rectangle's max corner in world space
+    /// * `border_scale` - world units per texel for the borders
+    /// * `color` - multiplied with the texture
+    pub fn draw_nine_slice(
+        &mut self,
+        atlas: &TextureAtlas,
+        region: &AtlasRegion,
+        min: Vec2,
+        max: Vec2,
+        border_scale: f32,
+        color: [f32; 4],
+    ) {
+        let borders = match region.nine_slice {
+            Some(borders) => borders,
+            None => return self.draw_region(atlas, region, min, max, color),
+        };
+
+        // Texel offsets of the slice lines, left to right and top to bottom.
+        let (x, y) = (region.x as f32, region.y as f32);
+        let (width, height) = (region.width as f32, region.height as f32);
+        let texel_x = [
+            x,
+            x + borders.left as f32,
+            x + width - borders.right as f32,
+            x + width,
+        ];
+        let texel_y = [
+            y,
+            y + borders.top as f32,
+            y + height - borders.bottom as f32,
+            y + height,
+        ];
+
+        // World positions of the same lines. Texel rows run top to bottom
+        // while world y is up, so rows start at max.y.
+        let size = max - min;
+        let fit = |start: f32, end: f32, extent: f32| -> f32 {
+            let total = (start + end) * border_scale;
+            if total > extent.abs() && total > 0.0 {
+                extent.abs() / total
+            } else {
+                1.0
+            }
+        };
+        let scale_x = border_scale
+            * fit(borders.left as f32, borders.right as f32, size.x);
+        let scale_y = border_scale
+            * fit(borders.top as f32, borders.bottom as f32, size.y);
+        let world_x = [
+            min.x,
+            min.x + borders.left as f32 * scale_x,
+            max.x - borders.right as f32 * scale_x,
+            max.x,
+        ];
+        let world_y = [
+            max.y,
+            max.y - borders.top as f32 * scale_y,
+            min.y + borders.bottom as f32 * scale_y,
+            min.y,
+        ];
+
+        let texture_index = atlas.texture_index() as f32;
+        for (rows, world_rows) in texel_y.windows(2).zip(world_y.windows(2)) {
+            for (columns, world_columns) in
+                texel_x.windows(2).zip(world_x.windows(2))
+            {
+                let slice_min = Vec2::new(world_columns[0], world_rows[1]);
+                let slice_max = Vec2::new(world_columns[1], world_rows[0]);
+                if slice_max.x <= slice_min.x || slice_max.y <= slice_min.y {
+                    continue;
+                }
+                let uv = atlas.uv_bounds(
+                    columns[0],
+                    rows[0],
+                    columns[1] - columns[0],
+                    rows[1] - rows[0],
+                );
+                self.push_quad(slice_min, slice_max, texture_index, uv, color);
+            }
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl SpriteBatch {
+    /// Add two triangles covering the rectangle from `min` to `max`.
+    ///
+    /// `uv` is [left, top, right, bottom], with top drawn at `max.y`.
+    fn push_quad(
+        &mut self,
+        min: Vec2,
+        max: Vec2,
+        texture_index: f32,
+        uv: [f32; 4],
+        color: [f32; 4],
+    ) {
+        let [left, top, right, bottom] = uv;
+        let vertex = |x: f32, y: f32, u: f32, v: f32| {
+            let pos = self.projection * Vec4::new(x, y, 0.0, 1.0);
+            BindlessVertex {
+                pos: [pos.x, pos.y, pos.z, pos.w],
+                uv: [u, v, texture_index],
+                pad: [0.0],
+                color,
+            }
+        };
+        let top_left = vertex(min.x, max.y, left, top);
+        let top_right = vertex(max.x, max.y, right, top);
+        let bottom_right = vertex(max.x, min.y, right, bottom);
+        let bottom_left = vertex(min.x, min.y, left, bottom);
+        self.vertices.extend_from_slice(&[
+            top_left,
+            top_right,
+            bottom_right,
+            top_left,
+            bottom_right,
+            bottom_left,
+        ]);
+    }
+}
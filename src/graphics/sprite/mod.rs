@@ -0,0 +1,13 @@
+//! Sprites drawn from texture atlases.
+//!
+//! A TextureAtlas names regions of a texture, either from a uniform grid or
+//! from TexturePacker-style JSON. A SpriteBatch turns regions into quads for
+//! BindlessTriangles, including 9-slice scaling for resizable UI panels.
+
+mod atlas;
+mod batch;
+
+pub use self::{
+    atlas::{AtlasGrid, AtlasRegion, NineSlice, TextureAtlas},
+    batch::SpriteBatch,
+};
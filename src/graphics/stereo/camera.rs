@@ -0,0 +1,61 @@
+use crate::math::{Mat4, Vec3};
+
+/// One of the two eyes in a stereo view.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Eye {
+    Left = 0,
+    Right = 1,
+}
+
+impl Eye {
+    /// Both eyes, in the order they are laid out in a StereoTarget.
+    pub const BOTH: [Eye; 2] = [Eye::Left, Eye::Right];
+
+    /// The eye's index, which is also its multiview view index.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A camera which produces a view matrix for each eye.
+///
+/// The eyes are offset from the camera along the view's x axis and look in
+/// the same direction, so the projection is shared.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StereoCamera {
+    /// The view matrix for a point between the eyes, e.g. from
+    /// `math::look_at`.
+    pub view: Mat4,
+
+    /// The projection for each eye. Each eye is rendered into half of the
+    /// target, so use the aspect ratio of a single eye.
+    pub projection: Mat4,
+
+    /// The distance between the eyes, in world units. 0.064 is a typical
+    /// interpupillary distance in meters.
+    pub eye_separation: f32,
+}
+
+impl StereoCamera {
+    /// The view matrix for one eye.
+    pub fn view(&self, eye: Eye) -> Mat4 {
+        let offset = match eye {
+            Eye::Left => 0.5 * self.eye_separation,
+            Eye::Right => -0.5 * self.eye_separation,
+        };
+        // Moving an eye left is the same as moving the world right in view
+        // space.
+        Mat4::new_translation(&Vec3::new(offset, 0.0, 0.0)) * self.view
+    }
+
+    /// The combined projection and view matrix for one eye.
+    pub fn view_projection(&self, eye: Eye) -> Mat4 {
+        self.projection * self.view(eye)
+    }
+
+    /// The view-projection matrix for each eye, indexed by `Eye::index`.
+    /// Multiview shaders can index this array with `gl_ViewIndex`.
+    pub fn view_projections(&self) -> [Mat4; 2] {
+        Eye::BOTH.map(|eye| self.view_projection(eye))
+    }
+}
@@ -0,0 +1,212 @@
+use {
+    super::{StereoMode, StereoTarget},
+    crate::graphics::{
+        vulkan_api::{
+            raii, ColorPass, Frame, GraphicsPipelineBuilder, RenderDevice,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Push constants for the composite shader. Must match
+/// `shaders/stereo_composite.frag`.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct CompositeConstants {
+    output_size: [f32; 2],
+    layered: u32,
+}
+
+/// A fullscreen pass which draws a StereoTarget into a ColorPass, typically
+/// the swapchain, with the left eye on the left half and the right eye on
+/// the right half.
+///
+/// Colors are copied as-is, so HDR scenes should be kept in the displayable
+/// range or tonemapped by the scene's shaders.
+pub struct StereoComposite {
+    extent: vk::Extent2D,
+    layered: bool,
+    descriptor_pool: raii::DescriptorPool,
+    _descriptor_set_layout: raii::DescriptorSetLayout,
+    pipeline_layout: raii::PipelineLayout,
+    pipeline: raii::Pipeline,
+    _sampler: raii::Sampler,
+    render_device: Arc<RenderDevice>,
+}
+
+impl StereoComposite {
+    /// Create the composite pipeline and a descriptor set which references
+    /// the stereo target.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must be rebuilt any time the StereoTarget or
+    ///     ColorPass are rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        stereo_target: &StereoTarget,
+        color_pass: &ColorPass,
+    ) -> Result<Self, GraphicsError> {
+        let sampler = {
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::LINEAR,
+                min_filter: vk::Filter::LINEAR,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: 0.0,
+                ..Default::default()
+            };
+            raii::Sampler::new(render_device.clone(), &create_info)?
+        };
+
+        let bindings = [vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..vk::DescriptorSetLayoutBinding::default()
+        }];
+        let descriptor_set_layout =
+            raii::DescriptorSetLayout::new_with_bindings(
+                render_device.clone(),
+                &bindings,
+            )?;
+        let pipeline_layout =
+            raii::PipelineLayout::new_with_layouts_and_ranges(
+                render_device.clone(),
+                &[descriptor_set_layout.raw()],
+                &[vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<CompositeConstants>() as u32,
+                }],
+            )?;
+        let pipeline =
+            GraphicsPipelineBuilder::new()
+                .vertex_shader(include_bytes!(
+                    "./shaders/stereo_composite.vert.spv"
+                ))
+                .fragment_shader(include_bytes!(
+                    "./shaders/stereo_composite.frag.spv"
+                ))
+                .color_blend_attachment(
+                    GraphicsPipelineBuilder::opaque_attachment(),
+                )
+                .build(
+                    render_device.clone(),
+                    &pipeline_layout,
+                    color_pass.render_pass(),
+                )?;
+        pipeline.set_debug_name("Stereo Composite Pipeline");
+
+        let mut descriptor_pool = raii::DescriptorPool::new_with_sizes(
+            render_device.clone(),
+            1,
+            &[vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+            }],
+        )?;
+        let _ = descriptor_pool
+            .allocate_descriptor_sets(&[&descriptor_set_layout])?;
+
+        let image_info = vk::DescriptorImageInfo {
+            sampler: sampler.raw(),
+            image_view: stereo_target.sampled_view().raw(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        render_device.device().update_descriptor_sets(
+            &[vk::WriteDescriptorSet {
+                dst_set: descriptor_pool.descriptor_set(0),
+                dst_binding: 0,
+                dst_array_element: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                p_image_info: &image_info,
+                ..vk::WriteDescriptorSet::default()
+            }],
+            &[],
+        );
+
+        Ok(Self {
+            extent: color_pass.extent(),
+            layered: stereo_target.mode() == StereoMode::Multiview,
+            descriptor_pool,
+            _descriptor_set_layout: descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            _sampler: sampler,
+            render_device,
+        })
+    }
+
+    /// Add commands to draw both eyes into every pixel of the current render
+    /// pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must already be started
+    ///   - the StereoTarget must already be rendered for this frame
+    pub unsafe fn draw(&self, frame: &Frame) {
+        let device = self.render_device.device();
+        device.cmd_bind_pipeline(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline.raw(),
+        );
+
+        let vk::Extent2D { width, height } = self.extent;
+        device.cmd_set_viewport(
+            frame.command_buffer(),
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.raw(),
+            0,
+            &[self.descriptor_pool.descriptor_set(0)],
+            &[],
+        );
+        let constants = CompositeConstants {
+            output_size: [width as f32, height as f32],
+            layered: self.layered as u32,
+        };
+        device.cmd_push_constants(
+            frame.command_buffer(),
+            self.pipeline_layout.raw(),
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            std::slice::from_raw_parts(
+                &constants as *const CompositeConstants as *const u8,
+                std::mem::size_of::<CompositeConstants>(),
+            ),
+        );
+
+        // A single triangle which covers the entire screen.
+        device.cmd_draw(frame.command_buffer(), 3, 1, 0, 0);
+    }
+}
@@ -0,0 +1,16 @@
+//! Stereo rendering for side-by-side 3D displays and as groundwork for VR.
+//!
+//! A StereoCamera offsets a view matrix for each eye. The scene is recorded
+//! into a StereoTarget, either once per eye into the halves of a wide image
+//! or once in total with VK_KHR_multiview, and StereoComposite draws the
+//! result side-by-side into a ColorPass.
+
+mod camera;
+mod composite;
+mod target;
+
+pub use self::{
+    camera::{Eye, StereoCamera},
+    composite::StereoComposite,
+    target::{StereoMode, StereoPass, StereoTarget},
+};
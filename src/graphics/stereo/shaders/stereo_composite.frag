@@ -0,0 +1,24 @@
+#version 460
+
+// Side-by-side targets have one wide layer. Multiview targets have one layer
+// per eye.
+layout(set = 0, binding = 0) uniform sampler2DArray stereo_image;
+
+layout(push_constant) uniform CompositeConstants {
+    vec2 output_size;
+    uint layered;
+} constants;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / constants.output_size;
+    float layer = 0.0;
+    if (constants.layered == 1) {
+        // The left half of the screen shows layer 0, the right half shows
+        // layer 1.
+        layer = uv.x < 0.5 ? 0.0 : 1.0;
+        uv.x = fract(uv.x * 2.0);
+    }
+    out_color = texture(stereo_image, vec3(uv, layer));
+}
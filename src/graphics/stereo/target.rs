@@ -0,0 +1,469 @@
+use {
+    super::Eye,
+    crate::graphics::{
+        vulkan_api::{raii, DepthPrePass, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// How a StereoTarget renders the two eyes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StereoMode {
+    /// One wide image with the left eye in the left half and the right eye
+    /// in the right half. The scene is recorded once for each eye.
+    SideBySide,
+
+    /// A two-layer image rendered with VK_KHR_multiview. The scene is
+    /// recorded once and shaders pick each eye's camera with `gl_ViewIndex`.
+    Multiview,
+}
+
+/// What the scene should record for one pass of `StereoTarget::render`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StereoPass {
+    /// Draw the scene for a single eye. The viewport and scissor are
+    /// already set to the eye's half of the target.
+    Eye(Eye),
+
+    /// Draw the scene once for both eyes. The viewport and scissor cover a
+    /// single eye, and shaders should use `gl_ViewIndex` as the eye index.
+    Multiview,
+}
+
+/// An offscreen color and depth target which holds a view for each eye.
+///
+/// The color image is RGBA16F and is left in SHADER_READ_ONLY_OPTIMAL when
+/// the render pass ends, ready for StereoComposite. The target is shared by
+/// every frame in flight.
+pub struct StereoTarget {
+    mode: StereoMode,
+    eye_extent: vk::Extent2D,
+    render_pass: raii::RenderPass,
+    framebuffer: raii::Framebuffer,
+    sampled_view: raii::ImageView,
+    _color_view: raii::ImageView,
+    _color_image: raii::Image,
+    _depth_view: raii::ImageView,
+    _depth_image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl StereoTarget {
+    /// The format of the color image.
+    pub const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+    /// Create the target.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `eye_extent` - the size of the image for a single eye
+    /// * `prefer_multiview` - use StereoMode::Multiview when the device
+    ///   supports it. The application must also enable the `multiview`
+    ///   feature with `vulkan_11_features_mut` when creating the device.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the target must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        eye_extent: vk::Extent2D,
+        prefer_multiview: bool,
+    ) -> Result<Self, GraphicsError> {
+        let mode = if prefer_multiview && render_device.is_multiview_supported()
+        {
+            StereoMode::Multiview
+        } else {
+            StereoMode::SideBySide
+        };
+        let (image_extent, layers) = match mode {
+            StereoMode::SideBySide => (
+                vk::Extent2D {
+                    width: eye_extent.width * 2,
+                    height: eye_extent.height,
+                },
+                1,
+            ),
+            StereoMode::Multiview => (eye_extent, 2),
+        };
+
+        let color_image = Self::create_image(
+            &render_device,
+            Self::FORMAT,
+            image_extent,
+            layers,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED,
+        )?;
+        color_image.set_debug_name("Stereo Target Color");
+        let depth_image = Self::create_image(
+            &render_device,
+            DepthPrePass::DEPTH_FORMAT,
+            image_extent,
+            layers,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+        )?;
+        depth_image.set_debug_name("Stereo Target Depth");
+
+        let attachment_view_type = match mode {
+            StereoMode::SideBySide => vk::ImageViewType::TYPE_2D,
+            StereoMode::Multiview => vk::ImageViewType::TYPE_2D_ARRAY,
+        };
+        let color_view = Self::create_view(
+            &render_device,
+            &color_image,
+            Self::FORMAT,
+            vk::ImageAspectFlags::COLOR,
+            attachment_view_type,
+            layers,
+        )?;
+        let depth_view = Self::create_view(
+            &render_device,
+            &depth_image,
+            DepthPrePass::DEPTH_FORMAT,
+            vk::ImageAspectFlags::DEPTH,
+            attachment_view_type,
+            layers,
+        )?;
+
+        // StereoComposite always samples an array, one layer per eye for
+        // multiview or a single wide layer for side-by-side.
+        let sampled_view = Self::create_view(
+            &render_device,
+            &color_image,
+            Self::FORMAT,
+            vk::ImageAspectFlags::COLOR,
+            vk::ImageViewType::TYPE_2D_ARRAY,
+            layers,
+        )?;
+
+        let render_pass =
+            Self::create_render_pass(render_device.clone(), mode)?;
+
+        let attachments = [color_view.raw(), depth_view.raw()];
+        let framebuffer = {
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass: render_pass.raw(),
+                attachment_count: attachments.len() as u32,
+                p_attachments: attachments.as_ptr(),
+                width: image_extent.width,
+                height: image_extent.height,
+                // Multiview framebuffers use a single layer, the views come
+                // from the attachments' array layers.
+                layers: 1,
+                ..Default::default()
+            };
+            raii::Framebuffer::new(render_device.clone(), &create_info)?
+        };
+
+        Ok(Self {
+            mode,
+            eye_extent,
+            render_pass,
+            framebuffer,
+            sampled_view,
+            _color_view: color_view,
+            _color_image: color_image,
+            _depth_view: depth_view,
+            _depth_image: depth_image,
+            render_device,
+        })
+    }
+
+    /// How the eyes are rendered.
+    pub fn mode(&self) -> StereoMode {
+        self.mode
+    }
+
+    /// The size of the image for a single eye.
+    pub fn eye_extent(&self) -> vk::Extent2D {
+        self.eye_extent
+    }
+
+    /// The render pass used to draw into the target. Scene pipelines must
+    /// be built against this render pass.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// A 2D array view of the color image, for sampling.
+    pub fn sampled_view(&self) -> &raii::ImageView {
+        &self.sampled_view
+    }
+
+    /// Record the scene for both eyes.
+    ///
+    /// Begins the render pass, clearing color and depth, then calls `record`
+    /// once per eye for side-by-side targets or once in total for multiview
+    /// targets, and ends the render pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the StereoTarget must not be destroyed until the command buffer
+    ///     finishes executing or is discarded
+    ///   - pipelines bound in the callback must use dynamic viewport and
+    ///     scissor state
+    pub unsafe fn render<F>(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+        mut record: F,
+    ) -> Result<(), GraphicsError>
+    where
+        F: FnMut(StereoPass) -> Result<(), GraphicsError>,
+    {
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        let vk::Extent2D { width, height } = self.eye_extent;
+        let render_width = match self.mode {
+            StereoMode::SideBySide => width * 2,
+            StereoMode::Multiview => width,
+        };
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffer.raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: vk::Extent2D {
+                    width: render_width,
+                    height,
+                },
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        device.cmd_begin_render_pass(
+            command_buffer,
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+
+        let set_eye_viewport = |x: u32| {
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[vk::Viewport {
+                    x: x as f32,
+                    y: 0.0,
+                    width: width as f32,
+                    height: height as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                }],
+            );
+            device.cmd_set_scissor(
+                command_buffer,
+                0,
+                &[vk::Rect2D {
+                    offset: vk::Offset2D { x: x as i32, y: 0 },
+                    extent: self.eye_extent,
+                }],
+            );
+        };
+        let result = match self.mode {
+            StereoMode::SideBySide => Eye::BOTH.iter().try_for_each(|&eye| {
+                set_eye_viewport(eye.index() as u32 * width);
+                record(StereoPass::Eye(eye))
+            }),
+            StereoMode::Multiview => {
+                set_eye_viewport(0);
+                record(StereoPass::Multiview)
+            }
+        };
+
+        device.cmd_end_render_pass(command_buffer);
+        result
+    }
+}
+
+// Private API
+// -----------
+
+impl StereoTarget {
+    /// Create a device-local image with the given number of array layers.
+    unsafe fn create_image(
+        render_device: &Arc<RenderDevice>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        layers: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<raii::Image, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format,
+            mip_levels: 1,
+            array_layers: layers,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        raii::Image::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+    }
+
+    /// Create a view of every layer of an image.
+    unsafe fn create_view(
+        render_device: &Arc<RenderDevice>,
+        image: &raii::Image,
+        format: vk::Format,
+        aspect_mask: vk::ImageAspectFlags,
+        view_type: vk::ImageViewType,
+        layers: u32,
+    ) -> Result<raii::ImageView, GraphicsError> {
+        let create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type,
+            format,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: layers,
+            },
+            ..Default::default()
+        };
+        raii::ImageView::new(render_device.clone(), &create_info)
+    }
+
+    /// Create a render pass with a single color and depth subpass. Multiview
+    /// targets render both views in the one subpass.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+        mode: StereoMode,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let attachments = [
+            vk::AttachmentDescription {
+                format: Self::FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            vk::AttachmentDescription {
+                format: DepthPrePass::DEPTH_FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+        ];
+        let color_attachment = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+        let depth_attachment = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let subpasses = [vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment,
+            p_depth_stencil_attachment: &depth_attachment,
+            ..Default::default()
+        }];
+        let dependencies = [
+            // The target is shared between frames in flight, so wait for the
+            // previous frame's composite to finish reading it.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // Make the rendered eyes visible to the composite.
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+
+        // Both views are rendered by subpass 0 and are spatially correlated,
+        // which lets implementations share work between them.
+        let view_mask: u32 = 0b11;
+        let multiview_info = vk::RenderPassMultiviewCreateInfo {
+            subpass_count: 1,
+            p_view_masks: &view_mask,
+            correlation_mask_count: 1,
+            p_correlation_masks: &view_mask,
+            ..Default::default()
+        };
+        let create_info = vk::RenderPassCreateInfo {
+            p_next: match mode {
+                StereoMode::SideBySide => std::ptr::null(),
+                StereoMode::Multiview => {
+                    &multiview_info as *const vk::RenderPassMultiviewCreateInfo
+                        as *const std::ffi::c_void
+                }
+            },
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
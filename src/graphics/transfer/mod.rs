@@ -0,0 +1,280 @@
+//! Helpers for one-off image transfers.
+//!
+//! Each helper records a single transfer command along with the layout
+//! transitions and barriers around it. The image is moved into a transfer
+//! layout, the command runs, and the image is moved into its final layout
+//! with its writes made visible to every later command.
+//!
+//! The barriers are deliberately conservative: they wait for all earlier
+//! commands and make results available to all later commands. That's fine
+//! for occasional copies, but hot paths should record their own barriers.
+
+mod transfer_image;
+
+pub use self::transfer_image::TransferImage;
+
+use {crate::graphics::vulkan_api::raii, ash::vk};
+
+/// Blit the whole source image onto the whole destination image, scaling
+/// with the given filter if the extents differ.
+///
+/// # Params
+///
+/// * `device` - the device used to record commands
+/// * `command_buffer` - the command buffer to record into
+/// * `src` - the source image, created with TRANSFER_SRC usage
+/// * `dst` - the destination image, created with TRANSFER_DST usage
+/// * `filter` - the filter used when scaling. LINEAR requires a format
+///   which supports linear filtering.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the images must not be the same image
+///   - the images must outlive the command buffer's execution
+pub unsafe fn blit_image(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    src: &TransferImage,
+    dst: &TransferImage,
+    filter: vk::Filter,
+) {
+    let src_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+    let dst_layout = vk::ImageLayout::TRANSFER_DST_OPTIMAL;
+    transition_before(
+        device,
+        command_buffer,
+        &[
+            (src, src_layout, vk::AccessFlags2::TRANSFER_READ),
+            (dst, dst_layout, vk::AccessFlags2::TRANSFER_WRITE),
+        ],
+    );
+
+    let region = vk::ImageBlit2 {
+        src_subresource: src.subresource_layers(),
+        src_offsets: [vk::Offset3D::default(), src.far_corner()],
+        dst_subresource: dst.subresource_layers(),
+        dst_offsets: [vk::Offset3D::default(), dst.far_corner()],
+        ..Default::default()
+    };
+    device.cmd_blit_image2(
+        command_buffer,
+        &vk::BlitImageInfo2 {
+            src_image: src.image,
+            src_image_layout: src_layout,
+            dst_image: dst.image,
+            dst_image_layout: dst_layout,
+            region_count: 1,
+            p_regions: &region,
+            filter,
+            ..Default::default()
+        },
+    );
+
+    transition_after(
+        device,
+        command_buffer,
+        &[
+            (src, src_layout, vk::AccessFlags2::NONE),
+            (dst, dst_layout, vk::AccessFlags2::TRANSFER_WRITE),
+        ],
+    );
+}
+
+/// Copy the whole image into a buffer, tightly packed.
+///
+/// The copy is made visible to later commands and to host reads, so the
+/// buffer can be mapped once the command buffer finishes executing.
+///
+/// # Params
+///
+/// * `device` - the device used to record commands
+/// * `command_buffer` - the command buffer to record into
+/// * `src` - the source image, created with TRANSFER_SRC usage
+/// * `buffer` - the destination buffer, created with TRANSFER_DST usage
+/// * `buffer_offset` - the byte offset where the copy begins in the buffer
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the buffer must have room for the whole image after `buffer_offset`
+///   - the image and buffer must outlive the command buffer's execution
+pub unsafe fn copy_image_to_buffer(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    src: &TransferImage,
+    buffer: &raii::Buffer,
+    buffer_offset: u64,
+) {
+    let src_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+    transition_before(
+        device,
+        command_buffer,
+        &[(src, src_layout, vk::AccessFlags2::TRANSFER_READ)],
+    );
+
+    let region = vk::BufferImageCopy2 {
+        buffer_offset,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: src.subresource_layers(),
+        image_offset: vk::Offset3D::default(),
+        image_extent: vk::Extent3D {
+            width: src.extent.width,
+            height: src.extent.height,
+            depth: 1,
+        },
+        ..Default::default()
+    };
+    device.cmd_copy_image_to_buffer2(
+        command_buffer,
+        &vk::CopyImageToBufferInfo2 {
+            src_image: src.image,
+            src_image_layout: src_layout,
+            dst_buffer: buffer.raw(),
+            region_count: 1,
+            p_regions: &region,
+            ..Default::default()
+        },
+    );
+
+    transition_after(
+        device,
+        command_buffer,
+        &[(src, src_layout, vk::AccessFlags2::NONE)],
+    );
+    let buffer_barrier = vk::BufferMemoryBarrier2 {
+        src_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+        src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        dst_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS
+            | vk::PipelineStageFlags2::HOST,
+        dst_access_mask: vk::AccessFlags2::MEMORY_READ
+            | vk::AccessFlags2::HOST_READ,
+        buffer: buffer.raw(),
+        offset: buffer_offset,
+        size: vk::WHOLE_SIZE,
+        ..Default::default()
+    };
+    device.cmd_pipeline_barrier2(
+        command_buffer,
+        &vk::DependencyInfo {
+            buffer_memory_barrier_count: 1,
+            p_buffer_memory_barriers: &buffer_barrier,
+            ..Default::default()
+        },
+    );
+}
+
+/// Clear the whole image to a single color.
+///
+/// # Params
+///
+/// * `device` - the device used to record commands
+/// * `command_buffer` - the command buffer to record into
+/// * `image` - the image to clear, created with TRANSFER_DST usage. The
+///   image can be in the UNDEFINED layout, in which case its previous
+///   contents are discarded.
+/// * `color` - the clear color. Integer formats should use
+///   `vk::ClearColorValue::int32` or `uint32`.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the image must outlive the command buffer's execution
+pub unsafe fn clear_color_image(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: &TransferImage,
+    color: vk::ClearColorValue,
+) {
+    let layout = vk::ImageLayout::TRANSFER_DST_OPTIMAL;
+    transition_before(
+        device,
+        command_buffer,
+        &[(image, layout, vk::AccessFlags2::TRANSFER_WRITE)],
+    );
+
+    device.cmd_clear_color_image(
+        command_buffer,
+        image.image,
+        layout,
+        &color,
+        &[image.subresource_range()],
+    );
+
+    transition_after(
+        device,
+        command_buffer,
+        &[(image, layout, vk::AccessFlags2::TRANSFER_WRITE)],
+    );
+}
+
+// Private API
+// -----------
+
+/// Move images from their current layouts into the transfer layouts after
+/// all earlier commands finish.
+unsafe fn transition_before(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    images: &[(&TransferImage, vk::ImageLayout, vk::AccessFlags2)],
+) {
+    let barriers = images
+        .iter()
+        .map(
+            |&(image, transfer_layout, access)| vk::ImageMemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+                src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+                dst_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+                dst_access_mask: access,
+                old_layout: image.layout,
+                new_layout: transfer_layout,
+                image: image.image,
+                subresource_range: image.subresource_range(),
+                ..Default::default()
+            },
+        )
+        .collect::<Vec<_>>();
+    device.cmd_pipeline_barrier2(
+        command_buffer,
+        &vk::DependencyInfo {
+            image_memory_barrier_count: barriers.len() as u32,
+            p_image_memory_barriers: barriers.as_ptr(),
+            ..Default::default()
+        },
+    );
+}
+
+/// Move images from the transfer layouts into their final layouts, making
+/// any transfer writes visible to all later commands.
+unsafe fn transition_after(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    images: &[(&TransferImage, vk::ImageLayout, vk::AccessFlags2)],
+) {
+    let barriers = images
+        .iter()
+        .map(
+            |&(image, transfer_layout, access)| vk::ImageMemoryBarrier2 {
+                src_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+                src_access_mask: access,
+                dst_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+                dst_access_mask: vk::AccessFlags2::MEMORY_READ
+                    | vk::AccessFlags2::MEMORY_WRITE,
+                old_layout: transfer_layout,
+                new_layout: image.final_layout,
+                image: image.image,
+                subresource_range: image.subresource_range(),
+                ..Default::default()
+            },
+        )
+        .collect::<Vec<_>>();
+    device.cmd_pipeline_barrier2(
+        command_buffer,
+        &vk::DependencyInfo {
+            image_memory_barrier_count: barriers.len() as u32,
+            p_image_memory_barriers: barriers.as_ptr(),
+            ..Default::default()
+        },
+    );
+}
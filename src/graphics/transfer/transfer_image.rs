@@ -0,0 +1,88 @@
+use ash::vk;
+
+/// A 2D color image used by the transfer helpers.
+///
+/// Only the first mip level and array layer are transferred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TransferImage {
+    /// The raw image handle. Swapchain images can be used directly.
+    pub image: vk::Image,
+
+    /// The size of the image in pixels.
+    pub extent: vk::Extent2D,
+
+    /// The image's layout before the transfer.
+    pub layout: vk::ImageLayout,
+
+    /// The image's layout after the transfer.
+    pub final_layout: vk::ImageLayout,
+}
+
+// Public API
+// ----------
+
+impl TransferImage {
+    /// Describe an image which is returned to its current layout after the
+    /// transfer.
+    ///
+    /// Images in the UNDEFINED layout can't be returned to it, so they end
+    /// in the GENERAL layout unless `with_final_layout` says otherwise.
+    pub fn new(
+        image: vk::Image,
+        extent: vk::Extent2D,
+        layout: vk::ImageLayout,
+    ) -> Self {
+        let final_layout = if layout == vk::ImageLayout::UNDEFINED {
+            vk::ImageLayout::GENERAL
+        } else {
+            layout
+        };
+        Self {
+            image,
+            extent,
+            layout,
+            final_layout,
+        }
+    }
+
+    /// Leave the image in a different layout after the transfer.
+    pub fn with_final_layout(self, final_layout: vk::ImageLayout) -> Self {
+        Self {
+            final_layout,
+            ..self
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl TransferImage {
+    pub(super) fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    pub(super) fn subresource_layers(&self) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+
+    /// The offset of the image's far corner, used for blit regions.
+    pub(super) fn far_corner(&self) -> vk::Offset3D {
+        vk::Offset3D {
+            x: self.extent.width as i32,
+            y: self.extent.height as i32,
+            z: 1,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use {
+    crate::graphics::GraphicsError,
+    anyhow::{anyhow, Context},
+    std::{
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::{sync_channel, Receiver, TryRecvError},
+            Arc,
+        },
+        thread::JoinHandle,
+    },
+};
+
+/// Decodes Motion JPEG frames on a worker thread.
+///
+/// The worker stays a couple of frames ahead of the consumer. Frames are
+/// delivered as RGBA8 images in order.
+pub struct MjpegDecoder {
+    width: u32,
+    height: u32,
+    frames: Option<Receiver<image::RgbaImage>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+// Public API
+// ----------
+
+impl MjpegDecoder {
+    /// The number of decoded frames which can wait for the consumer.
+    const QUEUE_DEPTH: usize = 2;
+
+    /// Read an MJPEG file and start decoding frames on a worker thread.
+    ///
+    /// The first frame is decoded immediately to determine the video's size.
+    ///
+    /// # Params
+    ///
+    /// * `path` - the file to read
+    /// * `looping` - when true the video restarts after the last frame
+    pub fn open(
+        path: impl AsRef<Path>,
+        looping: bool,
+    ) -> Result<Self, GraphicsError> {
+        let data = std::fs::read(&path).with_context(|| {
+            format!("Unable to read video from {:?}", path.as_ref())
+        })?;
+        let frame_ranges = find_jpeg_frames(&data);
+        let first_frame = frame_ranges
+            .first()
+            .ok_or_else(|| {
+                anyhow!("No MJPEG frames found in {:?}", path.as_ref())
+            })
+            .and_then(|range| decode_frame(&data[range.clone()]))?;
+        let (width, height) = first_frame.dimensions();
+
+        let (sender, receiver) = sync_channel(Self::QUEUE_DEPTH);
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = stop.clone();
+        let worker = std::thread::Builder::new()
+            .name("mjpeg-decoder".to_owned())
+            .spawn(move || {
+                if sender.send(first_frame).is_err() {
+                    return;
+                }
+                let mut ranges = frame_ranges.iter().skip(1);
+                while !worker_stop.load(Ordering::Relaxed) {
+                    let range = match ranges.next() {
+                        Some(range) => range,
+                        None if looping => {
+                            ranges = frame_ranges.iter().skip(0);
+                            continue;
+                        }
+                        None => return,
+                    };
+                    let frame = match decode_frame(&data[range.clone()]) {
+                        Ok(frame) if frame.dimensions() == (width, height) => {
+                            frame
+                        }
+                        Ok(_) => {
+                            log::warn!("Skipping resized MJPEG frame");
+                            continue;
+                        }
+                        Err(error) => {
+                            log::warn!("Skipping MJPEG frame: {:?}", error);
+                            continue;
+                        }
+                    };
+                    if sender.send(frame).is_err() {
+                        // the receiver was dropped
+                        return;
+                    }
+                }
+            })
+            .context("Unable to spawn the MJPEG decoder thread")?;
+
+        Ok(Self {
+            width,
+            height,
+            frames: Some(receiver),
+            stop,
+            worker: Some(worker),
+        })
+    }
+
+    /// The size of every frame in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Take the next decoded frame if one is ready.
+    ///
+    /// Never blocks. Returns None when the worker hasn't finished the next
+    /// frame yet or when a non-looping video has ended.
+    pub fn try_next_frame(&mut self) -> Option<image::RgbaImage> {
+        match self.frames.as_ref()?.try_recv() {
+            Ok(frame) => Some(frame),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.frames = None;
+                None
+            }
+        }
+    }
+
+    /// Returns true when a non-looping video has delivered every frame.
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_none()
+    }
+}
+
+impl Drop for MjpegDecoder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // Dropping the receiver unblocks a worker which is waiting to send.
+        self.frames = None;
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::warn!("The MJPEG decoder thread panicked");
+            }
+        }
+    }
+}
+
+// Private API
+// -----------
+
+/// Find the byte range of every JPEG image in the data by scanning for
+/// start-of-image and end-of-image markers.
+///
+/// Entropy-coded JPEG data escapes 0xFF bytes, so an end-of-image marker can
+/// only appear at the end of a frame.
+fn find_jpeg_frames(data: &[u8]) -> Vec<std::ops::Range<usize>> {
+    const MARKER: u8 = 0xFF;
+    const START_OF_IMAGE: u8 = 0xD8;
+    const END_OF_IMAGE: u8 = 0xD9;
+
+    let mut frames = vec![];
+    let mut start = None;
+    let mut index = 0;
+    while index + 1 < data.len() {
+        // 0xFF can also be a fill byte before a marker
+        if data[index] != MARKER || data[index + 1] == MARKER {
+            index += 1;
+            continue;
+        }
+        match (data[index + 1], start) {
+            (START_OF_IMAGE, None) => start = Some(index),
+            (END_OF_IMAGE, Some(frame_start)) => {
+                frames.push(frame_start..index + 2);
+                start = None;
+            }
+            _ => (),
+        }
+        index += 2;
+    }
+    frames
+}
+
+fn decode_frame(jpeg: &[u8]) -> anyhow::Result<image::RgbaImage> {
+    let frame =
+        image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)
+            .context("Unable to decode MJPEG frame")?;
+    Ok(frame.into_rgba8())
+}
@@ -0,0 +1,12 @@
+//! Video textures which stream decoded frames from a worker thread.
+//!
+//! Enabled by the `video` feature. Decoding uses the `image` crate's JPEG
+//! decoder, so only Motion JPEG sources are supported. Raw `.mjpeg` streams
+//! work directly, and MJPEG frames inside AVI or MOV containers are found by
+//! scanning for JPEG markers. Other codecs can be converted with e.g.
+//! `ffmpeg -i input.mp4 -c:v mjpeg -q:v 3 output.mjpeg`.
+
+mod mjpeg_decoder;
+mod video_texture;
+
+pub use self::{mjpeg_decoder::MjpegDecoder, video_texture::VideoTexture};
@@ -0,0 +1,250 @@
+use {
+    super::MjpegDecoder,
+    crate::graphics::{
+        vulkan_api::{
+            raii, Frame, FramesInFlight, RenderDevice, Texture2D, TextureLoader,
+        },
+        GraphicsError,
+    },
+    ash::vk,
+    std::{path::Path, sync::Arc},
+};
+
+/// A texture which plays back a video.
+///
+/// Frames are decoded on a worker thread. When a new frame is due,
+/// `cmd_update` copies it into the frame's staging buffer and records a copy
+/// into the texture, so decoding never stalls rendering. The texture is
+/// always in the SHADER_READ_ONLY_OPTIMAL layout outside of `cmd_update`.
+pub struct VideoTexture {
+    decoder: MjpegDecoder,
+    frame_duration: f32,
+    next_frame_time: Option<f32>,
+
+    staging_buffers: Vec<raii::Buffer>,
+    staging_buffer_ptrs: Vec<*mut u8>,
+    texture: Texture2D,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl VideoTexture {
+    /// Open an MJPEG video.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create the texture
+    /// * `texture_loader` - used to create the initial, black texture
+    /// * `frames_in_flight` - a staging buffer is created for each frame
+    /// * `path` - the video file
+    /// * `frames_per_second` - the playback rate. MJPEG streams don't store
+    ///   timing, so it must be provided.
+    /// * `looping` - when true the video restarts after the last frame
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn open_mjpeg(
+        render_device: Arc<RenderDevice>,
+        texture_loader: &mut TextureLoader,
+        frames_in_flight: &FramesInFlight,
+        path: impl AsRef<Path>,
+        frames_per_second: f32,
+        looping: bool,
+    ) -> Result<Self, GraphicsError> {
+        let decoder = MjpegDecoder::open(path, looping)?;
+        let (width, height) = decoder.dimensions();
+        let size = (width * height * 4) as usize;
+
+        let texture = texture_loader.create_texture_2d_from_rgba8(
+            width,
+            height,
+            &vec![0; size],
+        )?;
+        texture.image.set_debug_name("Video Texture");
+
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let mut staging_buffers = vec![];
+        let mut staging_buffer_ptrs = vec![];
+        for _ in 0..frames_in_flight.frame_count() {
+            let buffer = raii::Buffer::new(
+                render_device.clone(),
+                &vk::BufferCreateInfo {
+                    size: size as u64,
+                    usage: vk::BufferUsageFlags::TRANSFER_SRC,
+                    sharing_mode: vk::SharingMode::EXCLUSIVE,
+                    queue_family_index_count: 1,
+                    p_queue_family_indices: &queue_family_index,
+                    ..Default::default()
+                },
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.set_debug_name("Video Staging Buffer");
+            let ptr = buffer.allocation().map(render_device.device())?;
+            staging_buffer_ptrs.push(ptr as *mut u8);
+            staging_buffers.push(buffer);
+        }
+
+        Ok(Self {
+            decoder,
+            frame_duration: 1.0 / frames_per_second.max(f32::EPSILON),
+            next_frame_time: None,
+            staging_buffers,
+            staging_buffer_ptrs,
+            texture,
+            render_device,
+        })
+    }
+
+    /// The texture which holds the current video frame.
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    /// Returns true when a non-looping video has shown every frame.
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Record commands which copy the next video frame into the texture if
+    /// one is due.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `time` - the current time in seconds. Playback starts at the time
+    ///   passed to the first call.
+    ///
+    /// # Returns
+    ///
+    /// Returns true when the texture was updated.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    pub unsafe fn cmd_update(&mut self, frame: &Frame, time: f32) -> bool {
+        let next_frame_time = *self.next_frame_time.get_or_insert(time);
+        if time < next_frame_time {
+            return false;
+        }
+        let video_frame = match self.decoder.try_next_frame() {
+            Some(video_frame) => video_frame,
+            None => return false,
+        };
+
+        // Skip ahead instead of trying to catch up when playback falls far
+        // behind, e.g. after the window was minimized.
+        self.next_frame_time =
+            Some((next_frame_time + self.frame_duration).max(time));
+
+        let index = frame.frame_index();
+        let pixels = video_frame.as_raw();
+        std::ptr::copy_nonoverlapping(
+            pixels.as_ptr(),
+            self.staging_buffer_ptrs[index],
+            pixels.len(),
+        );
+        self.cmd_copy_staging_to_texture(frame.command_buffer(), index);
+        true
+    }
+}
+
+// Private API
+// -----------
+
+impl VideoTexture {
+    /// Record a copy from a staging buffer into the texture.
+    unsafe fn cmd_copy_staging_to_texture(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        staging_index: usize,
+    ) {
+        let device = self.render_device.device();
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        // Wait for previous frames to finish sampling the texture.
+        let barrier_before = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+            dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            old_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            image: self.texture.image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_before,
+                ..Default::default()
+            },
+        );
+
+        let region = vk::BufferImageCopy2 {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D {
+                width: self.texture.extent.width,
+                height: self.texture.extent.height,
+                depth: 1,
+            },
+            ..Default::default()
+        };
+        device.cmd_copy_buffer_to_image2(
+            command_buffer,
+            &vk::CopyBufferToImageInfo2 {
+                src_buffer: self.staging_buffers[staging_index].raw(),
+                dst_image: self.texture.image.raw(),
+                dst_image_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                region_count: 1,
+                p_regions: &region,
+                ..Default::default()
+            },
+        );
+
+        let barrier_after = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER
+                | vk::PipelineStageFlags2::COMPUTE_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            image: self.texture.image.raw(),
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_after,
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use {crate::graphics::vulkan_api::raii, ash::vk};
+
+/// A pipeline stage on one side of a Barrier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BarrierStage {
+    /// Reading indirect draw and dispatch arguments.
+    DrawIndirect,
+
+    /// Reading vertex and index buffers.
+    VertexInput,
+
+    /// The vertex shader.
+    Vertex,
+
+    /// The fragment shader.
+    Fragment,
+
+    /// Color attachment reads and writes.
+    ColorAttachment,
+
+    /// Compute shaders.
+    Compute,
+
+    /// Copies, blits, and clears.
+    Transfer,
+
+    /// Host reads and writes of mapped memory.
+    Host,
+
+    /// Every stage.
+    AllCommands,
+}
+
+/// The kind of memory access on one side of a Barrier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BarrierAccess {
+    /// No memory access, only an execution dependency.
+    None,
+
+    /// The stage reads the memory.
+    Read,
+
+    /// The stage writes the memory.
+    Write,
+
+    /// The stage both reads and writes the memory.
+    ReadWrite,
+}
+
+/// A small builder for synchronization2 barriers on buffers and memory.
+///
+/// Stages and accesses are given in plain terms and converted to the
+/// matching Vulkan flags. For example, a compute shader which writes a
+/// buffer that is then read as vertex data:
+///
+/// ```text
+/// Barrier::new()
+///     .buffer(&vertices)
+///     .from(BarrierStage::Compute, BarrierAccess::Write)
+///     .to(BarrierStage::VertexInput, BarrierAccess::Read)
+///     .record(device, command_buffer);
+/// ```
+///
+/// Calling `from` or `to` more than once adds to the stages and accesses.
+/// When no buffers are given the barrier is a global memory barrier.
+#[derive(Debug, Clone, Default)]
+pub struct Barrier {
+    memory_barrier: vk::MemoryBarrier2,
+    buffer_barriers: Vec<vk::BufferMemoryBarrier2>,
+}
+
+// Public API
+// ----------
+
+impl Barrier {
+    /// Create an empty barrier.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the barrier to the whole buffer.
+    pub fn buffer(self, buffer: &raii::Buffer) -> Self {
+        self.buffer_range(buffer, 0, vk::WHOLE_SIZE)
+    }
+
+    /// Limit the barrier to a range of bytes in the buffer.
+    pub fn buffer_range(
+        mut self,
+        buffer: &raii::Buffer,
+        offset: u64,
+        size: u64,
+    ) -> Self {
+        self.buffer_barriers.push(vk::BufferMemoryBarrier2 {
+            src_stage_mask: self.memory_barrier.src_stage_mask,
+            src_access_mask: self.memory_barrier.src_access_mask,
+            dst_stage_mask: self.memory_barrier.dst_stage_mask,
+            dst_access_mask: self.memory_barrier.dst_access_mask,
+            buffer: buffer.raw(),
+            offset,
+            size,
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Add the stage and access which must complete before the barrier.
+    pub fn from(mut self, stage: BarrierStage, access: BarrierAccess) -> Self {
+        self.memory_barrier.src_stage_mask |= stage.stage_mask();
+        self.memory_barrier.src_access_mask |= stage.access_mask(access);
+        for barrier in &mut self.buffer_barriers {
+            barrier.src_stage_mask = self.memory_barrier.src_stage_mask;
+            barrier.src_access_mask = self.memory_barrier.src_access_mask;
+        }
+        self
+    }
+
+    /// Add the stage and access which waits for the barrier.
+    pub fn to(mut self, stage: BarrierStage, access: BarrierAccess) -> Self {
+        self.memory_barrier.dst_stage_mask |= stage.stage_mask();
+        self.memory_barrier.dst_access_mask |= stage.access_mask(access);
+        for barrier in &mut self.buffer_barriers {
+            barrier.dst_stage_mask = self.memory_barrier.dst_stage_mask;
+            barrier.dst_access_mask = self.memory_barrier.dst_access_mask;
+        }
+        self
+    }
+
+    /// The dependency info for this barrier.
+    ///
+    /// The returned struct points into the Barrier, so the Barrier must not
+    /// be moved or dropped while the dependency info is in use.
+    pub fn dependency_info(&self) -> vk::DependencyInfo {
+        if self.buffer_barriers.is_empty() {
+            vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &self.memory_barrier,
+                ..Default::default()
+            }
+        } else {
+            vk::DependencyInfo {
+                buffer_memory_barrier_count: self.buffer_barriers.len() as u32,
+                p_buffer_memory_barriers: self.buffer_barriers.as_ptr(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Record the barrier into a command buffer.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording
+    ///   - any buffers must outlive the command buffer's execution
+    pub unsafe fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        device.cmd_pipeline_barrier2(command_buffer, &self.dependency_info());
+    }
+}
+
+// Private API
+// -----------
+
+impl BarrierStage {
+    fn stage_mask(self) -> vk::PipelineStageFlags2 {
+        match self {
+            Self::DrawIndirect => vk::PipelineStageFlags2::DRAW_INDIRECT,
+            Self::VertexInput => vk::PipelineStageFlags2::VERTEX_INPUT,
+            Self::Vertex => vk::PipelineStageFlags2::VERTEX_SHADER,
+            Self::Fragment => vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            Self::ColorAttachment => {
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+            }
+            Self::Compute => vk::PipelineStageFlags2::COMPUTE_SHADER,
+            Self::Transfer => vk::PipelineStageFlags2::TRANSFER,
+            Self::Host => vk::PipelineStageFlags2::HOST,
+            Self::AllCommands => vk::PipelineStageFlags2::ALL_COMMANDS,
+        }
+    }
+
+    /// The access flags which this stage uses for reads and writes.
+    fn access_mask(self, access: BarrierAccess) -> vk::AccessFlags2 {
+        let (read, write) = match self {
+            Self::DrawIndirect => (
+                vk::AccessFlags2::INDIRECT_COMMAND_READ,
+                vk::AccessFlags2::NONE,
+            ),
+            Self::VertexInput => (
+                vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags2::INDEX_READ,
+                vk::AccessFlags2::NONE,
+            ),
+            Self::Vertex | Self::Fragment | Self::Compute => (
+                vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_SAMPLED_READ
+                    | vk::AccessFlags2::UNIFORM_READ,
+                vk::AccessFlags2::SHADER_STORAGE_WRITE,
+            ),
+            Self::ColorAttachment => (
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ,
+                vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            ),
+            Self::Transfer => (
+                vk::AccessFlags2::TRANSFER_READ,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            ),
+            Self::Host => {
+                (vk::AccessFlags2::HOST_READ, vk::AccessFlags2::HOST_WRITE)
+            }
+            Self::AllCommands => (
+                vk::AccessFlags2::MEMORY_READ,
+                vk::AccessFlags2::MEMORY_WRITE,
+            ),
+        };
+        match access {
+            BarrierAccess::None => vk::AccessFlags2::NONE,
+            BarrierAccess::Read => read,
+            BarrierAccess::Write => write,
+            BarrierAccess::ReadWrite => read | write,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use {
     super::Frame,
     crate::graphics::{
-        vulkan_api::{raii, FramesInFlight, RenderDevice, Texture2D},
+        vulkan_api::{raii, FramesInFlight, PerFrame, RenderDevice, Texture2D},
         GraphicsError,
     },
     ash::vk,
@@ -14,19 +14,65 @@ mod pipeline;
 #[repr(C)]
 pub struct BindlessVertex {
     pub pos: [f32; 4],
+
+    /// Texture coordinates in x and y, and the texture index in z. A negative
+    /// texture index draws without a texture.
     pub uv: [f32; 3],
+
+    /// The index of the vertex's BindlessObject storage buffer plus one, or
+    /// zero when the vertex doesn't use object data.
     pub pad: [f32; 1],
+
     pub color: [f32; 4],
 }
 
+/// Per-object data which vertices can fetch by index, like textures.
+///
+/// Each object lives in its own storage buffer, see
+/// `BindlessTriangles::create_object_buffer`. The object's transform is
+/// applied to the vertex position and its tint is multiplied with the vertex
+/// color.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct BindlessObject {
+    /// A column-major transform matrix.
+    pub transform: [[f32; 4]; 4],
+    pub tint: [f32; 4],
+}
+
+impl Default for BindlessObject {
+    fn default() -> Self {
+        Self {
+            transform: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            tint: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// A host-visible vertex buffer and its persistently mapped pointer.
+struct VertexBuffer {
+    buffer: raii::Buffer,
+    ptr: *mut BindlessVertex,
+}
+
 /// A utility for rendering high-performance textured triangles using bindless
-/// textures.
+/// textures and per-object storage buffers.
+///
+/// The render device needs the `runtimeDescriptorArray`,
+/// `shaderSampledImageArrayNonUniformIndexing`, and
+/// `shaderStorageBufferArrayNonUniformIndexing` descriptor indexing
+/// features.
 pub struct BindlessTriangles {
     textures: Vec<Arc<Texture2D>>,
+    storage_buffers: Vec<Arc<raii::Buffer>>,
 
     vertex_count: u32,
-    vertex_buffers: Vec<raii::Buffer>,
-    vertex_buffer_ptrs: Vec<*mut BindlessVertex>,
+    vertex_buffers: PerFrame<VertexBuffer>,
 
     sampler: raii::Sampler,
     descriptor_pool: raii::DescriptorPool,
@@ -50,10 +96,52 @@ impl BindlessTriangles {
         frames_in_flight: &FramesInFlight,
         textures: &[Arc<Texture2D>],
     ) -> Result<Self, GraphicsError> {
+        Self::new_with_storage_buffers(
+            render_device,
+            render_pass,
+            frames_in_flight,
+            textures,
+            &[],
+        )
+    }
+
+    /// Create a new instance of bindless triangles which can also fetch
+    /// per-object data from storage buffers.
+    ///
+    /// # Params
+    ///
+    /// * `textures` - the textures vertices can reference by index
+    /// * `storage_buffers` - buffers which each hold one BindlessObject,
+    ///   referenced by index with `BindlessVertex::pad`. See
+    ///   `create_object_buffer`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - This instance must be dropped before the RenderDevice is destroyed.
+    pub unsafe fn new_with_storage_buffers(
+        render_device: Arc<RenderDevice>,
+        render_pass: &raii::RenderPass,
+        frames_in_flight: &FramesInFlight,
+        textures: &[Arc<Texture2D>],
+        storage_buffers: &[Arc<raii::Buffer>],
+    ) -> Result<Self, GraphicsError> {
+        // Every descriptor in the array must be valid, so bind a default
+        // object when none are provided.
+        let storage_buffers = if storage_buffers.is_empty() {
+            vec![Arc::new(Self::create_object_buffer(
+                &render_device,
+                &BindlessObject::default(),
+            )?)]
+        } else {
+            storage_buffers.to_vec()
+        };
+
         let (descriptor_set_layout, pipeline_layout) =
             pipeline::create_layouts(
                 render_device.clone(),
                 textures.len() as u32,
+                storage_buffers.len() as u32,
             )?;
 
         let pipeline = pipeline::create_pipeline(
@@ -71,11 +159,13 @@ impl BindlessTriangles {
             &[
                 vk::DescriptorPoolSize {
                     ty: vk::DescriptorType::STORAGE_BUFFER,
-                    descriptor_count,
+                    descriptor_count: descriptor_count
+                        * (1 + storage_buffers.len() as u32),
                 },
                 vk::DescriptorPoolSize {
                     ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                    descriptor_count,
+                    descriptor_count: descriptor_count
+                        * (textures.len() as u32).max(1),
                 },
             ],
         )?;
@@ -94,32 +184,26 @@ impl BindlessTriangles {
             },
         )?;
 
-        let vertex_buffer_count = frames_in_flight.frame_count();
-        let mut vertex_buffers = Vec::with_capacity(vertex_buffer_count);
-        let mut vertex_buffer_ptrs = Vec::with_capacity(vertex_buffer_count);
-        for _ in 0..vertex_buffer_count {
-            let (buffer, ptr) =
+        let vertex_buffers = frames_in_flight.create_per_frame(|index| {
+            let vertex_buffer =
                 Self::allocate_vertex_buffer(&render_device, 1000)?;
-            vertex_buffer_ptrs.push(ptr);
-            vertex_buffers.push(buffer);
-        }
-
-        for (index, vertex_buffer) in vertex_buffers.iter().enumerate() {
             Self::write_descriptor_set(
                 &render_device,
                 &descriptor_pool,
                 index,
-                vertex_buffer,
+                &vertex_buffer.buffer,
                 textures,
+                &storage_buffers,
                 &sampler,
             );
-        }
+            Ok(vertex_buffer)
+        })?;
 
         Ok(Self {
             textures: textures.to_owned(),
+            storage_buffers,
             vertex_count: 0,
             vertex_buffers,
-            vertex_buffer_ptrs,
             sampler,
             descriptor_pool,
             _descriptor_set_layout: descriptor_set_layout,
@@ -129,12 +213,39 @@ impl BindlessTriangles {
         })
     }
 
+    /// Rebuild the graphics pipeline for a new render pass.
+    ///
+    /// Only the pipeline depends on the render pass, so the vertex buffers,
+    /// textures, and descriptor sets are kept. This is only needed when the
+    /// render pass was replaced, e.g. when a swapchain rebuild changed the
+    /// image format.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - every frame which used the old pipeline must have finished
+    ///     executing
+    pub unsafe fn rebuild_pipeline(
+        &mut self,
+        render_pass: &raii::RenderPass,
+    ) -> Result<(), GraphicsError> {
+        self.pipeline = pipeline::create_pipeline(
+            self.render_device.clone(),
+            include_bytes!("./shaders/bindless.vert.spv"),
+            include_bytes!("./shaders/bindless.frag.spv"),
+            &self.pipeline_layout,
+            render_pass,
+        )?;
+        Ok(())
+    }
+
     pub fn write_vertices_for_frame(
         &mut self,
         frame: &Frame,
         vertices: &[BindlessVertex],
     ) -> Result<(), GraphicsError> {
-        if self.vertex_buffers[frame.frame_index()]
+        if self.vertex_buffers[frame]
+            .buffer
             .allocation()
             .size_in_bytes()
             < std::mem::size_of_val(vertices) as u64
@@ -145,8 +256,9 @@ impl BindlessTriangles {
                     &self.render_device,
                     &self.descriptor_pool,
                     frame.frame_index(),
-                    &self.vertex_buffers[frame.frame_index()],
+                    &self.vertex_buffers[frame].buffer,
                     &self.textures,
+                    &self.storage_buffers,
                     &self.sampler,
                 );
             };
@@ -154,7 +266,7 @@ impl BindlessTriangles {
 
         let buffer_data = unsafe {
             std::slice::from_raw_parts_mut(
-                self.vertex_buffer_ptrs[frame.frame_index()],
+                self.vertex_buffers[frame].ptr,
                 vertices.len(),
             )
         };
@@ -218,9 +330,43 @@ impl BindlessTriangles {
             0,
             0,
         );
+        crate::bench::record_draw_calls(1);
 
         Ok(())
     }
+
+    /// Create a host-visible storage buffer which holds a single
+    /// BindlessObject.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the buffer must be dropped before the RenderDevice is destroyed
+    ///   - the buffer must not be in use by the GPU when it is dropped
+    pub unsafe fn create_object_buffer(
+        render_device: &Arc<RenderDevice>,
+        object: &BindlessObject,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: std::mem::size_of::<BindlessObject>() as u64,
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        let ptr = buffer.allocation().map(render_device.device())?;
+        std::ptr::write_unaligned(ptr as *mut BindlessObject, *object);
+        buffer.allocation().unmap(render_device.device())?;
+        Ok(buffer)
+    }
 }
 
 impl BindlessTriangles {
@@ -236,17 +382,15 @@ impl BindlessTriangles {
         frame: &Frame,
         vertex_count: u64,
     ) -> Result<(), GraphicsError> {
-        let index = frame.frame_index();
-
-        self.vertex_buffers[index]
+        let vertex_buffer = &mut self.vertex_buffers[frame];
+        vertex_buffer
+            .buffer
             .allocation()
             .unmap(self.render_device.device())?;
-        self.vertex_buffer_ptrs[index] = std::ptr::null_mut();
+        vertex_buffer.ptr = std::ptr::null_mut();
 
-        let (buffer, ptr) =
+        *vertex_buffer =
             Self::allocate_vertex_buffer(&self.render_device, vertex_count)?;
-        self.vertex_buffers[index] = buffer;
-        self.vertex_buffer_ptrs[index] = ptr;
 
         Ok(())
     }
@@ -261,7 +405,7 @@ impl BindlessTriangles {
     unsafe fn allocate_vertex_buffer(
         render_device: &Arc<RenderDevice>,
         vertex_count: u64,
-    ) -> Result<(raii::Buffer, *mut BindlessVertex), GraphicsError> {
+    ) -> Result<VertexBuffer, GraphicsError> {
         let queue_family_index = render_device.graphics_queue().family_index();
         let create_info = vk::BufferCreateInfo {
             size: std::mem::size_of::<BindlessVertex>() as u64 * vertex_count,
@@ -283,7 +427,10 @@ impl BindlessTriangles {
             "CPU Ptr must be align for Vertex data!"
         );
 
-        Ok((buffer, ptr as *mut BindlessVertex))
+        Ok(VertexBuffer {
+            buffer,
+            ptr: ptr as *mut BindlessVertex,
+        })
     }
 
     /// Write the descriptor set for frame index.
@@ -298,6 +445,7 @@ impl BindlessTriangles {
         index: usize,
         vertex_buffer: &raii::Buffer,
         textures: &[Arc<Texture2D>],
+        storage_buffers: &[Arc<raii::Buffer>],
         sampler: &raii::Sampler,
     ) {
         let buffer_info = vk::DescriptorBufferInfo {
@@ -313,6 +461,14 @@ impl BindlessTriangles {
                 image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
             })
             .collect::<Vec<vk::DescriptorImageInfo>>();
+        let storage_buffer_infos = storage_buffers
+            .iter()
+            .map(|buffer| vk::DescriptorBufferInfo {
+                buffer: buffer.raw(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            })
+            .collect::<Vec<vk::DescriptorBufferInfo>>();
         render_device.device().update_descriptor_sets(
             &[
                 vk::WriteDescriptorSet {
@@ -333,6 +489,15 @@ impl BindlessTriangles {
                     p_image_info: image_infos.as_ptr(),
                     ..vk::WriteDescriptorSet::default()
                 },
+                vk::WriteDescriptorSet {
+                    dst_set: descriptor_pool.descriptor_set(index),
+                    dst_binding: 2,
+                    dst_array_element: 0,
+                    descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: storage_buffer_infos.len() as u32,
+                    p_buffer_info: storage_buffer_infos.as_ptr(),
+                    ..vk::WriteDescriptorSet::default()
+                },
             ],
             &[],
         );
@@ -10,6 +10,7 @@ use {
 pub unsafe fn create_layouts(
     render_device: Arc<RenderDevice>,
     texture_count: u32,
+    storage_buffer_count: u32,
 ) -> Result<(raii::DescriptorSetLayout, raii::PipelineLayout), GraphicsError> {
     let descriptor_set_layout = raii::DescriptorSetLayout::new_with_bindings(
         render_device.clone(),
@@ -28,6 +29,13 @@ pub unsafe fn create_layouts(
                 stage_flags: vk::ShaderStageFlags::FRAGMENT,
                 ..vk::DescriptorSetLayoutBinding::default()
             },
+            vk::DescriptorSetLayoutBinding {
+                binding: 2,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: storage_buffer_count,
+                stage_flags: vk::ShaderStageFlags::VERTEX,
+                ..vk::DescriptorSetLayoutBinding::default()
+            },
         ],
     )?;
     let pipeline_layout = raii::PipelineLayout::new_with_layouts_and_ranges(
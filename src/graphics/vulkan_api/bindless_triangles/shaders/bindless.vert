@@ -1,8 +1,10 @@
 #version 460
+#extension GL_EXT_nonuniform_qualifier : enable
 
 struct Vertex{
     vec4 pos;
     vec3 uv;
+    float objectIndex;
     vec4 rgba;
 };
 
@@ -10,6 +12,11 @@ layout(std140, set = 0, binding = 0) readonly buffer Data{
     Vertex vertices[];
 } data;
 
+layout(std430, set = 0, binding = 2) readonly buffer ObjectData{
+    mat4 transform;
+    vec4 tint;
+} objects[];
+
 layout(location = 0) out vec2 uv;
 layout(location = 1) out vec4 rgba;
 layout(location = 2) flat out int textureIndex;
@@ -21,5 +28,14 @@ void main() {
     rgba = vertex.rgba;
     textureIndex = int(vertex.uv.z);
 
-    gl_Position = vec4(vertex.pos.x, vertex.pos.y, 0.0, 1.0);
+    vec4 pos = vec4(vertex.pos.x, vertex.pos.y, 0.0, 1.0);
+
+    // object indices are offset by one so zero means no object
+    int objectIndex = int(vertex.objectIndex) - 1;
+    if (objectIndex >= 0) {
+        pos = objects[nonuniformEXT(objectIndex)].transform * pos;
+        rgba *= objects[nonuniformEXT(objectIndex)].tint;
+    }
+
+    gl_Position = pos;
 }
@@ -0,0 +1,291 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Queue, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::Context,
+    ash::vk,
+    ccthw_ash_instance::VulkanHandle,
+    std::{any::Any, sync::Arc},
+};
+
+/// Identifies a submission made with an AsyncSubmitPool.
+///
+/// Tickets are cheap to copy and remain valid after the submission
+/// completes, at which point they always report completion.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubmissionTicket {
+    slot: usize,
+    generation: u64,
+}
+
+/// A command buffer, its fence, and anything which must outlive the
+/// commands.
+struct Slot {
+    command_buffer: vk::CommandBuffer,
+    fence: raii::Fence,
+    generation: u64,
+    in_flight: bool,
+    keep_alive: Option<Box<dyn Any>>,
+}
+
+/// A pool of one-time-submit command buffers which are submitted without
+/// blocking the CPU.
+///
+/// Each submission returns a SubmissionTicket backed by a fence. Command
+/// buffers are recycled once their tickets complete, so many uploads can be
+/// in flight at once, e.g. while a loading screen animates.
+pub struct AsyncSubmitPool {
+    command_pool: raii::CommandPool,
+    slots: Vec<Slot>,
+    queue: Queue,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl AsyncSubmitPool {
+    /// Create a new, empty pool.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - used to create the underlying Vulkan resources
+    /// * `queue` - the queue used for every submission
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the application must destroy this resource before exiting
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        queue: Queue,
+    ) -> Result<Self, GraphicsError> {
+        let command_pool = raii::CommandPool::new(
+            render_device.clone(),
+            &vk::CommandPoolCreateInfo {
+                flags: vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+                queue_family_index: queue.family_index(),
+                ..Default::default()
+            },
+        )?;
+        command_pool.set_debug_name("Async Submit Pool");
+        Ok(Self {
+            command_pool,
+            slots: vec![],
+            queue,
+            render_device,
+        })
+    }
+
+    /// Record commands and submit them without waiting for them to finish.
+    ///
+    /// The value returned by `record` is kept alive until the submission
+    /// completes. This is typically a staging buffer, or `()` when there is
+    /// nothing to keep.
+    ///
+    /// # Params
+    ///
+    /// * `record` - called with a started command buffer. The command buffer
+    ///   is ended and submitted by the pool.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the application is responsible for synchronizing access to any
+    ///     resources referenced by the commands as they execute
+    ///   - the command buffer must not be used after `record` returns
+    pub unsafe fn submit<T, F>(
+        &mut self,
+        record: F,
+    ) -> Result<SubmissionTicket, GraphicsError>
+    where
+        T: 'static,
+        F: FnOnce(vk::CommandBuffer) -> Result<T, GraphicsError>,
+    {
+        self.reclaim_completed()?;
+        let index = self.acquire_slot()?;
+        let command_buffer = self.slots[index].command_buffer;
+
+        let device = self.render_device.device();
+        device
+            .reset_command_buffer(
+                command_buffer,
+                vk::CommandBufferResetFlags::empty(),
+            )
+            .context("Error resetting an async submit command buffer")?;
+        device.begin_command_buffer(
+            command_buffer,
+            &vk::CommandBufferBeginInfo {
+                flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                ..Default::default()
+            },
+        )?;
+        let keep_alive = record(command_buffer)?;
+        device.end_command_buffer(command_buffer)?;
+
+        let slot = &mut self.slots[index];
+        device.reset_fences(&[slot.fence.raw()])?;
+        let command_buffer_info = vk::CommandBufferSubmitInfo {
+            command_buffer,
+            ..Default::default()
+        };
+        device.queue_submit2(
+            *self.queue.raw(),
+            &[vk::SubmitInfo2 {
+                command_buffer_info_count: 1,
+                p_command_buffer_infos: &command_buffer_info,
+                ..Default::default()
+            }],
+            slot.fence.raw(),
+        )?;
+
+        slot.generation += 1;
+        slot.in_flight = true;
+        slot.keep_alive = Some(Box::new(keep_alive));
+
+        Ok(SubmissionTicket {
+            slot: index,
+            generation: slot.generation,
+        })
+    }
+
+    /// Returns true when the ticket's commands have finished executing.
+    pub fn is_complete(
+        &self,
+        ticket: SubmissionTicket,
+    ) -> Result<bool, GraphicsError> {
+        let slot = &self.slots[ticket.slot];
+        if slot.generation != ticket.generation || !slot.in_flight {
+            return Ok(true);
+        }
+        let is_complete = unsafe {
+            self.render_device
+                .device()
+                .get_fence_status(slot.fence.raw())
+                .context("Error checking an async submission's fence")?
+        };
+        Ok(is_complete)
+    }
+
+    /// Block until the ticket's commands have finished executing.
+    pub fn wait(
+        &mut self,
+        ticket: SubmissionTicket,
+    ) -> Result<(), GraphicsError> {
+        if self.is_complete(ticket)? {
+            return Ok(());
+        }
+        unsafe {
+            self.render_device
+                .device()
+                .wait_for_fences(
+                    &[self.slots[ticket.slot].fence.raw()],
+                    true,
+                    u64::MAX,
+                )
+                .context("Error waiting for an async submission")?;
+        }
+        self.reclaim_completed()
+    }
+
+    /// Block until every submission has finished executing.
+    pub fn wait_all(&mut self) -> Result<(), GraphicsError> {
+        let fences = self
+            .slots
+            .iter()
+            .filter(|slot| slot.in_flight)
+            .map(|slot| slot.fence.raw())
+            .collect::<Vec<vk::Fence>>();
+        if !fences.is_empty() {
+            unsafe {
+                self.render_device
+                    .device()
+                    .wait_for_fences(&fences, true, u64::MAX)
+                    .context("Error waiting for async submissions")?;
+            }
+        }
+        self.reclaim_completed()
+    }
+
+    /// The number of submissions which have not been reclaimed yet.
+    pub fn in_flight_count(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.in_flight).count()
+    }
+
+    /// Release the command buffers and kept-alive values for every completed
+    /// submission.
+    ///
+    /// This happens automatically on each submit and wait, but can be called
+    /// to free staging memory sooner.
+    pub fn reclaim_completed(&mut self) -> Result<(), GraphicsError> {
+        for slot in &mut self.slots {
+            if !slot.in_flight {
+                continue;
+            }
+            let is_complete = unsafe {
+                self.render_device
+                    .device()
+                    .get_fence_status(slot.fence.raw())
+                    .context("Error checking an async submission's fence")?
+            };
+            if is_complete {
+                slot.in_flight = false;
+                slot.keep_alive = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AsyncSubmitPool {
+    fn drop(&mut self) {
+        self.wait_all()
+            .expect("Error while waiting for async submissions to complete!");
+    }
+}
+
+impl std::fmt::Debug for AsyncSubmitPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncSubmitPool")
+            .field("command_pool", &self.command_pool)
+            .field("slot_count", &self.slots.len())
+            .field("in_flight_count", &self.in_flight_count())
+            .finish()
+    }
+}
+
+// Private API
+// -----------
+
+impl AsyncSubmitPool {
+    /// Find a slot which isn't in flight, allocating a new one if needed.
+    unsafe fn acquire_slot(&mut self) -> Result<usize, GraphicsError> {
+        if let Some(index) = self.slots.iter().position(|slot| !slot.in_flight)
+        {
+            return Ok(index);
+        }
+
+        let buffer_index =
+            self.command_pool.allocate_primary_command_buffers(1)?;
+        let fence = raii::Fence::new(
+            self.render_device.clone(),
+            &vk::FenceCreateInfo::default(),
+        )?;
+        fence.set_debug_name(format!(
+            "Async Submit Pool Fence {}",
+            self.slots.len()
+        ));
+        self.slots.push(Slot {
+            command_buffer: self
+                .command_pool
+                .primary_command_buffer(buffer_index),
+            fence,
+            generation: 0,
+            in_flight: false,
+            keep_alive: None,
+        });
+        Ok(self.slots.len() - 1)
+    }
+}
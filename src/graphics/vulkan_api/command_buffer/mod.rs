@@ -1,3 +1,5 @@
+mod async_submit_pool;
+
 use {
     crate::graphics::{
         vulkan_api::{raii, Queue, RenderDevice},
@@ -8,7 +10,11 @@ use {
     std::sync::Arc,
 };
 
+pub use self::async_submit_pool::{AsyncSubmitPool, SubmissionTicket};
+
 /// A utility for managing a small command pool which runs synchronous commands.
+///
+/// See AsyncSubmitPool for submissions which don't block the CPU.
 pub struct OneTimeSubmitCommandBuffer {
     command_pool: raii::CommandPool,
     fence: raii::Fence,
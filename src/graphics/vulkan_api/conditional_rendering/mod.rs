@@ -0,0 +1,265 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, OcclusionQueries, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Commands for VK_EXT_conditional_rendering.
+///
+/// Draws and dispatches recorded between `cmd_begin` and `cmd_end` are
+/// discarded by the GPU when the 32-bit predicate in a buffer is zero. The
+/// predicate is typically written by a compute shader (e.g. GPU culling) or
+/// copied from occlusion query results with `cmd_copy_occlusion_results`.
+///
+/// The render device must be created with the extension named by
+/// `ConditionalRendering::extension_name()`.
+pub struct ConditionalRendering {
+    fp: vk::ExtConditionalRenderingFn,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl ConditionalRendering {
+    /// The name of the device extension required for conditional rendering.
+    pub fn extension_name() -> String {
+        vk::ExtConditionalRenderingFn::name()
+            .to_owned()
+            .into_string()
+            .unwrap()
+    }
+
+    /// Load the conditional rendering extension functions.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<Self, GraphicsError> {
+        let extension_name = Self::extension_name();
+        if !render_device.is_device_extension_enabled(&extension_name) {
+            return Err(GraphicsError::DeviceExtensionNotEnabled(
+                extension_name,
+            ));
+        }
+        let device = render_device.device().handle();
+        let fp = vk::ExtConditionalRenderingFn::load(|name| {
+            std::mem::transmute(
+                render_device
+                    .ash()
+                    .get_device_proc_addr(device, name.as_ptr()),
+            )
+        });
+        Ok(Self { fp, render_device })
+    }
+
+    /// Create a device-local buffer which holds `count` 32-bit predicates.
+    ///
+    /// The buffer can be written by compute shaders, transfers, or
+    /// `cmd_copy_occlusion_results`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the buffer must be dropped before the render device
+    pub unsafe fn create_predicate_buffer(
+        &self,
+        count: u32,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index =
+            self.render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (count.max(1) as usize * std::mem::size_of::<u32>())
+                as vk::DeviceSize,
+            usage: vk::BufferUsageFlags::CONDITIONAL_RENDERING_EXT
+                | vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            self.render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        buffer.set_debug_name("Conditional Rendering Predicates");
+        Ok(buffer)
+    }
+
+    /// Copy occlusion query results into a predicate buffer so later draws
+    /// can be skipped when nothing was visible.
+    ///
+    /// Unlike `OcclusionQueries::samples_passed`, this uses results from the
+    /// current frame without a CPU round trip. A barrier is recorded so the
+    /// predicates are ready for conditional rendering.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must be recorded outside of a render pass, after the queries end
+    ///   - the predicate buffer must hold at least `query_count` predicates
+    pub unsafe fn cmd_copy_occlusion_results(
+        &self,
+        frame: &Frame,
+        occlusion_queries: &OcclusionQueries,
+        first_query: u32,
+        query_count: u32,
+        predicate_buffer: &raii::Buffer,
+    ) {
+        let device = self.render_device.device();
+        device.cmd_copy_query_pool_results(
+            frame.command_buffer(),
+            occlusion_queries.query_pool(frame),
+            first_query,
+            query_count,
+            predicate_buffer.raw(),
+            0,
+            std::mem::size_of::<u32>() as vk::DeviceSize,
+            vk::QueryResultFlags::WAIT,
+        );
+        self.cmd_predicate_barrier(
+            frame.command_buffer(),
+            vk::PipelineStageFlags2::COPY,
+            vk::AccessFlags2::TRANSFER_WRITE,
+        );
+    }
+
+    /// Make compute shader writes to a predicate buffer visible to
+    /// conditional rendering.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must be recorded outside of a render pass
+    pub unsafe fn cmd_compute_to_predicate_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        self.cmd_predicate_barrier(
+            command_buffer,
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_STORAGE_WRITE,
+        );
+    }
+
+    /// Begin a conditional rendering scope.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer being recorded
+    /// * `predicate_buffer` - a buffer created with `create_predicate_buffer`
+    /// * `predicate_index` - which 32-bit predicate in the buffer to use
+    /// * `inverted` - when true, commands are discarded if the predicate is
+    ///   non-zero instead
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - scopes can't be nested and must be ended with `cmd_end`
+    ///   - a scope started inside a render pass must end in the same subpass
+    pub unsafe fn cmd_begin(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        predicate_buffer: &raii::Buffer,
+        predicate_index: u32,
+        inverted: bool,
+    ) {
+        let flags = if inverted {
+            vk::ConditionalRenderingFlagsEXT::INVERTED
+        } else {
+            vk::ConditionalRenderingFlagsEXT::empty()
+        };
+        let begin_info = vk::ConditionalRenderingBeginInfoEXT {
+            buffer: predicate_buffer.raw(),
+            offset: (predicate_index as usize * std::mem::size_of::<u32>())
+                as vk::DeviceSize,
+            flags,
+            ..Default::default()
+        };
+        (self.fp.cmd_begin_conditional_rendering_ext)(
+            command_buffer,
+            &begin_info,
+        );
+    }
+
+    /// End the current conditional rendering scope.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - a scope must have been started with `cmd_begin`
+    pub unsafe fn cmd_end(&self, command_buffer: vk::CommandBuffer) {
+        (self.fp.cmd_end_conditional_rendering_ext)(command_buffer);
+    }
+
+    /// Record an indirect draw which only executes when the predicate is
+    /// non-zero.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - a graphics pipeline must be bound inside of a render pass
+    ///   - the indirect buffer must hold at least `draw_count`
+    ///     VkDrawIndirectCommand structs starting at `offset`
+    pub unsafe fn cmd_draw_indirect(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        predicate_buffer: &raii::Buffer,
+        predicate_index: u32,
+        indirect_buffer: &raii::Buffer,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+    ) {
+        self.cmd_begin(
+            command_buffer,
+            predicate_buffer,
+            predicate_index,
+            false,
+        );
+        self.render_device.device().cmd_draw_indirect(
+            command_buffer,
+            indirect_buffer.raw(),
+            offset,
+            draw_count,
+            std::mem::size_of::<vk::DrawIndirectCommand>() as u32,
+        );
+        self.cmd_end(command_buffer);
+    }
+}
+
+// Private API
+// -----------
+
+impl ConditionalRendering {
+    /// Make writes to predicate buffers visible to conditional rendering.
+    unsafe fn cmd_predicate_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src_stage_mask: vk::PipelineStageFlags2,
+        src_access_mask: vk::AccessFlags2,
+    ) {
+        let barrier = vk::MemoryBarrier2 {
+            src_stage_mask,
+            src_access_mask,
+            dst_stage_mask: vk::PipelineStageFlags2::CONDITIONAL_RENDERING_EXT,
+            dst_access_mask: vk::AccessFlags2::CONDITIONAL_RENDERING_READ_EXT,
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &barrier,
+                ..Default::default()
+            },
+        );
+    }
+}
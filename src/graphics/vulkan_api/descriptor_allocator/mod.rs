@@ -0,0 +1,195 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Allocates descriptor sets from as many descriptor pools as needed.
+///
+/// `raii::DescriptorPool` needs exact sizes up front. The
+/// GrowableDescriptorAllocator instead sizes each pool from a set of ratios,
+/// e.g. two combined image samplers and one uniform buffer per set, and
+/// creates a new, larger pool whenever the current one is full or
+/// fragmented.
+///
+/// Calling `reset` returns every set to its pool at once, which makes the
+/// allocator a good fit for transient descriptor sets: keep one allocator
+/// per frame in flight (see PerFrame) and reset it once the frame's
+/// previous submission has completed.
+pub struct GrowableDescriptorAllocator {
+    name: String,
+    pool_ratios: Vec<(vk::DescriptorType, f32)>,
+    sets_per_pool: u32,
+    ready_pools: Vec<raii::DescriptorPool>,
+    full_pools: Vec<raii::DescriptorPool>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl GrowableDescriptorAllocator {
+    /// The largest number of sets any single pool is sized for.
+    const MAX_SETS_PER_POOL: u32 = 4096;
+
+    /// Create a new allocator. No pools are created until the first set is
+    /// allocated.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create descriptor pools
+    /// * `pool_ratios` - the number of descriptors of each type to reserve
+    ///   per descriptor set
+    /// * `initial_sets_per_pool` - how many sets the first pool can hold.
+    ///   Each new pool holds twice as many sets as the last, up to a limit.
+    pub fn new(
+        render_device: Arc<RenderDevice>,
+        pool_ratios: &[(vk::DescriptorType, f32)],
+        initial_sets_per_pool: u32,
+    ) -> Self {
+        Self {
+            name: "GrowableDescriptorAllocator".to_owned(),
+            pool_ratios: pool_ratios.to_vec(),
+            sets_per_pool: initial_sets_per_pool
+                .clamp(1, Self::MAX_SETS_PER_POOL),
+            ready_pools: vec![],
+            full_pools: vec![],
+            render_device,
+        }
+    }
+
+    /// Set the name used for the debug names of every pool this allocator
+    /// creates.
+    pub fn set_debug_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+        for (index, pool) in
+            self.full_pools.iter().chain(&self.ready_pools).enumerate()
+        {
+            pool.set_debug_name(format!("{} Pool {}", self.name, index));
+        }
+    }
+
+    /// Allocate a descriptor set with the given layout.
+    ///
+    /// Ready pools are tried in turn, and full pools are set aside until the
+    /// next reset. A new pool is only created when no ready pool can fit the
+    /// set.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the descriptor set is destroyed when the allocator is reset or
+    ///     dropped, the caller must not use it afterwards
+    ///   - the layout's descriptor types must be included in the pool ratios
+    pub unsafe fn allocate(
+        &mut self,
+        layout: &raii::DescriptorSetLayout,
+    ) -> Result<vk::DescriptorSet, GraphicsError> {
+        while let Some(pool) = self.ready_pools.last_mut() {
+            match pool.allocate_descriptor_sets(&[layout]) {
+                Ok(index) => return Ok(pool.descriptor_set(index)),
+                Err(err) if err.is_descriptor_pool_exhausted() => {
+                    let full_pool = self.ready_pools.pop().unwrap();
+                    self.full_pools.push(full_pool);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Every ready pool is full, or there were no pools yet.
+        let mut pool = self.create_pool()?;
+        let index = pool.allocate_descriptor_sets(&[layout])?;
+        let descriptor_set = pool.descriptor_set(index);
+        self.ready_pools.push(pool);
+        Ok(descriptor_set)
+    }
+
+    /// Return every descriptor set to its pool. Full pools become ready to
+    /// allocate from again.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - no descriptor sets from this allocator can be in use by the GPU
+    ///   - the caller must not keep any descriptor sets allocated before the
+    ///     reset
+    pub unsafe fn reset(&mut self) -> Result<(), GraphicsError> {
+        for pool in &mut self.ready_pools {
+            pool.reset()?;
+        }
+        for mut pool in self.full_pools.drain(..) {
+            pool.reset()?;
+            self.ready_pools.push(pool);
+        }
+        Ok(())
+    }
+
+    /// The number of descriptor pools created so far.
+    pub fn pool_count(&self) -> usize {
+        self.ready_pools.len() + self.full_pools.len()
+    }
+
+    /// The number of descriptor sets allocated since the last reset.
+    pub fn allocated_set_count(&self) -> usize {
+        self.full_pools
+            .iter()
+            .chain(&self.ready_pools)
+            .map(raii::DescriptorPool::descriptor_set_count)
+            .sum()
+    }
+}
+
+// Private API
+// -----------
+
+impl GrowableDescriptorAllocator {
+    /// Create a pool sized for the current number of sets per pool, then grow
+    /// the size used for the next pool.
+    unsafe fn create_pool(
+        &mut self,
+    ) -> Result<raii::DescriptorPool, GraphicsError> {
+        let max_sets = self.sets_per_pool;
+        let pool_sizes: Vec<vk::DescriptorPoolSize> = self
+            .pool_ratios
+            .iter()
+            .map(|&(ty, ratio)| vk::DescriptorPoolSize {
+                ty,
+                descriptor_count: ((ratio * max_sets as f32).ceil() as u32)
+                    .max(1),
+            })
+            .collect();
+        let pool = raii::DescriptorPool::new_with_sizes(
+            self.render_device.clone(),
+            max_sets,
+            &pool_sizes,
+        )?;
+        pool.set_debug_name(format!(
+            "{} Pool {}",
+            self.name,
+            self.pool_count()
+        ));
+        log::trace!(
+            "{} created a pool for {} sets: {:#?}",
+            self.name,
+            max_sets,
+            pool_sizes
+        );
+        self.sets_per_pool = (max_sets * 2).min(Self::MAX_SETS_PER_POOL);
+        Ok(pool)
+    }
+}
+
+impl std::fmt::Debug for GrowableDescriptorAllocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GrowableDescriptorAllocator")
+            .field("name", &self.name)
+            .field("pool_ratios", &self.pool_ratios)
+            .field("sets_per_pool", &self.sets_per_pool)
+            .field("ready_pools", &self.ready_pools)
+            .field("full_pools", &self.full_pools)
+            .finish()
+    }
+}
@@ -0,0 +1,343 @@
+use {
+    super::texture::{bytes_per_pixel, convert_to_rgba8},
+    crate::graphics::{
+        vulkan_api::{
+            raii, save_rgba8_png, Frame, FramesInFlight, RenderDevice,
+            Swapchain,
+        },
+        GraphicsError,
+    },
+    anyhow::{anyhow, Context},
+    ash::vk,
+    std::{path::PathBuf, sync::Arc},
+};
+
+/// A swapchain image which has been copied into a staging buffer but not yet
+/// written to disk.
+struct PendingCapture {
+    frame_number: u64,
+    format: vk::Format,
+    extent: vk::Extent2D,
+}
+
+/// A host-visible staging buffer and the capture it holds, if any.
+struct CaptureSlot {
+    staging: Option<(raii::Buffer, *const u8, vk::DeviceSize)>,
+    pending: Option<PendingCapture>,
+}
+
+/// Writes rendered frames to numbered PNG files.
+///
+/// There is one staging buffer for every frame in flight. `cmd_capture`
+/// records a copy of the frame's swapchain image, and the PNG is written the
+/// next time the same frame index is captured, once its commands are known
+/// to be complete. Call `finish` before exiting to write the last frames.
+pub struct FrameRecorder {
+    output_dir: PathBuf,
+    next_frame_number: u64,
+    slots: Vec<CaptureSlot>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl FrameRecorder {
+    /// Create a recorder which writes `frame_000000.png`, `frame_000001.png`,
+    /// and so on into `output_dir`. The directory is created if needed.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    ///   - this instance must not be dropped while a capture is in flight
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        output_dir: impl Into<PathBuf>,
+    ) -> Result<Self, GraphicsError> {
+        let output_dir = output_dir.into();
+        std::fs::create_dir_all(&output_dir).with_context(|| {
+            format!("Unable to create frame output directory {:?}", output_dir)
+        })?;
+        let slots = (0..frames_in_flight.frame_count())
+            .map(|_| CaptureSlot {
+                staging: None,
+                pending: None,
+            })
+            .collect();
+        Ok(Self {
+            output_dir,
+            next_frame_number: 0,
+            slots,
+            render_device,
+        })
+    }
+
+    /// The number of frames captured so far.
+    pub fn frame_count(&self) -> u64 {
+        self.next_frame_number
+    }
+
+    /// Record a copy of the frame's swapchain image.
+    ///
+    /// Any earlier capture made with the same frame index is written to disk
+    /// first.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must be recorded outside of a render pass, after all rendering to
+    ///     the swapchain image
+    ///   - the swapchain image must be in the PRESENT_SRC_KHR layout, and is
+    ///     left in that layout
+    pub unsafe fn cmd_capture(
+        &mut self,
+        frame: &Frame,
+        swapchain: &Swapchain,
+    ) -> Result<(), GraphicsError> {
+        if !swapchain
+            .image_usage()
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        {
+            return Err(anyhow!(
+                "The swapchain images do not support TRANSFER_SRC usage"
+            )
+            .into());
+        }
+
+        let index = frame.frame_index();
+
+        // FramesInFlight waits for the frame's previous submission before
+        // handing it out, so the pending capture in this slot is complete.
+        self.write_pending(index)?;
+
+        let format = swapchain.image_format();
+        let extent = swapchain.extent();
+        let size = (extent.width * extent.height) as vk::DeviceSize
+            * bytes_per_pixel(format)? as vk::DeviceSize;
+        let staging_buffer = self.staging_buffer(index, size)?;
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        let image = swapchain.images()[frame.swapchain_image_index()];
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        let barrier_before = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COPY,
+            dst_access_mask: vk::AccessFlags2::TRANSFER_READ,
+            old_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_before,
+                ..Default::default()
+            },
+        );
+
+        let region = vk::BufferImageCopy2 {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..Default::default()
+        };
+        device.cmd_copy_image_to_buffer2(
+            command_buffer,
+            &vk::CopyImageToBufferInfo2 {
+                src_image: image,
+                src_image_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                dst_buffer: staging_buffer,
+                region_count: 1,
+                p_regions: &region,
+                ..Default::default()
+            },
+        );
+
+        // Presentation waits on the frame's semaphore, so returning the image
+        // to PRESENT_SRC_KHR needs no further visibility.
+        let barrier_after = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+            dst_access_mask: vk::AccessFlags2::NONE,
+            old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            new_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+        let buffer_barrier = vk::BufferMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::HOST,
+            dst_access_mask: vk::AccessFlags2::HOST_READ,
+            buffer: staging_buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                buffer_memory_barrier_count: 1,
+                p_buffer_memory_barriers: &buffer_barrier,
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &barrier_after,
+                ..Default::default()
+            },
+        );
+
+        self.slots[index].pending = Some(PendingCapture {
+            frame_number: self.next_frame_number,
+            format,
+            extent,
+        });
+        self.next_frame_number += 1;
+        Ok(())
+    }
+
+    /// Wait for every frame in flight, then write all remaining captures.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must not be called between `acquire_frame` and `present_frame`
+    pub unsafe fn finish(
+        &mut self,
+        frames_in_flight: &FramesInFlight,
+    ) -> Result<(), GraphicsError> {
+        frames_in_flight.wait_for_all_frames_to_complete()?;
+        let mut indices: Vec<usize> = (0..self.slots.len()).collect();
+        indices.sort_by_key(|&index| {
+            self.slots[index]
+                .pending
+                .as_ref()
+                .map(|pending| pending.frame_number)
+        });
+        for index in indices {
+            self.write_pending(index)?;
+        }
+        log::info!(
+            "Wrote {} frames to {:?}",
+            self.next_frame_number,
+            self.output_dir
+        );
+        Ok(())
+    }
+}
+
+impl Drop for FrameRecorder {
+    fn drop(&mut self) {
+        let unwritten = self
+            .slots
+            .iter()
+            .filter(|slot| slot.pending.is_some())
+            .count();
+        if unwritten > 0 {
+            log::warn!(
+                "{} captured frames were never written, call finish() first",
+                unwritten
+            );
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl FrameRecorder {
+    /// Get the slot's staging buffer, replacing it if it is smaller than
+    /// `size` bytes.
+    ///
+    /// Unsafe because the slot's previous capture must be complete.
+    unsafe fn staging_buffer(
+        &mut self,
+        index: usize,
+        size: vk::DeviceSize,
+    ) -> Result<vk::Buffer, GraphicsError> {
+        let slot = &mut self.slots[index];
+        let is_large_enough = slot
+            .staging
+            .as_ref()
+            .map(|(_, _, capacity)| *capacity >= size)
+            .unwrap_or(false);
+        if !is_large_enough {
+            let queue_family_index =
+                self.render_device.graphics_queue().family_index();
+            let buffer = raii::Buffer::new(
+                self.render_device.clone(),
+                &vk::BufferCreateInfo {
+                    size,
+                    usage: vk::BufferUsageFlags::TRANSFER_DST,
+                    sharing_mode: vk::SharingMode::EXCLUSIVE,
+                    queue_family_index_count: 1,
+                    p_queue_family_indices: &queue_family_index,
+                    ..Default::default()
+                },
+                vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | vk::MemoryPropertyFlags::HOST_COHERENT,
+            )?;
+            buffer.set_debug_name(format!("Frame {index} Capture Buffer"));
+            let ptr = buffer.allocation().map(self.render_device.device())?;
+            slot.staging = Some((buffer, ptr as *const u8, size));
+        }
+        Ok(slot.staging.as_ref().unwrap().0.raw())
+    }
+
+    /// Write the slot's pending capture to disk, if there is one.
+    ///
+    /// Unsafe because the commands which made the capture must be complete.
+    unsafe fn write_pending(
+        &mut self,
+        index: usize,
+    ) -> Result<(), GraphicsError> {
+        let slot = &mut self.slots[index];
+        let pending = match slot.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let (_, ptr, _) = slot
+            .staging
+            .as_ref()
+            .context("A pending capture has no staging buffer")?;
+        let size = (pending.extent.width * pending.extent.height) as usize
+            * bytes_per_pixel(pending.format)?;
+        let data = std::slice::from_raw_parts(*ptr, size);
+        let image = image::RgbaImage::from_raw(
+            pending.extent.width,
+            pending.extent.height,
+            convert_to_rgba8(pending.format, data),
+        )
+        .context("Capture data does not match the frame size")?;
+        let path = self
+            .output_dir
+            .join(format!("frame_{:06}.png", pending.frame_number));
+        save_rgba8_png(&image, path)
+    }
+}
@@ -1,4 +1,11 @@
-use {super::FrameSync, ash::vk};
+use {
+    super::{FrameSync, TransientSlice},
+    crate::graphics::{
+        vulkan_api::{raii, SplitScreen, ViewportRegion},
+        GraphicsError,
+    },
+    ash::vk,
+};
 
 /// The current animation Frame.
 ///
@@ -25,16 +32,175 @@ impl Frame {
         self.sync.command_pool.primary_command_buffer(0)
     }
 
+    /// A named primary command buffer for the current frame, e.g. "compute"
+    /// or "upload".
+    ///
+    /// The buffer is allocated the first time the name is used and reused by
+    /// this frame afterwards. It is already started, so commands can be
+    /// recorded right away.
+    ///
+    /// All named buffers are submitted with the frame when it is returned for
+    /// presentation. They execute in the order their names were first
+    /// requested this frame, followed by the frame's main `command_buffer`.
+    ///
+    /// Each buffer belongs to the submission which was current when its name
+    /// was first requested this frame. See `begin_submission`.
+    pub fn named_command_buffer(
+        &mut self,
+        name: &str,
+    ) -> Result<vk::CommandBuffer, GraphicsError> {
+        self.sync.named_primary_command_buffer(name)
+    }
+
+    /// Start a new queue submission within this frame.
+    ///
+    /// Named command buffers requested after this call are placed in the new
+    /// submission, and the frame's main `command_buffer` is always part of
+    /// the last submission. A semaphore is inserted between each submission
+    /// so work like transfers or compute dispatches is complete before the
+    /// next submission reaches `wait_stage`.
+    ///
+    /// For example, record uploads into an "upload" buffer, call
+    /// `begin_submission(COMPUTE_SHADER)` and record dispatches into a
+    /// "compute" buffer, then call `begin_submission(VERTEX_SHADER)` before
+    /// drawing with the main command buffer.
+    ///
+    /// # Params
+    ///
+    /// * `wait_stage` - the first pipeline stage in the new submission which
+    ///   depends on the results of the earlier submissions.
+    pub fn begin_submission(
+        &mut self,
+        wait_stage: vk::PipelineStageFlags2,
+    ) -> Result<(), GraphicsError> {
+        self.sync.begin_submission(wait_stage)
+    }
+
+    /// A named secondary command buffer for the current frame.
+    ///
+    /// The buffer is allocated the first time the name is used and reused by
+    /// this frame afterwards. Unlike primary buffers, secondary buffers are
+    /// not started automatically. The caller must begin the buffer with the
+    /// relevant inheritance info and execute it from a primary buffer.
+    pub fn named_secondary_command_buffer(
+        &mut self,
+        name: &str,
+    ) -> Result<vk::CommandBuffer, GraphicsError> {
+        self.sync.named_secondary_command_buffer(name)
+    }
+
+    /// Allocate a descriptor set which is only valid for this frame.
+    ///
+    /// Transient sets come from a descriptor pool owned by the frame, which
+    /// is reset once the frame's commands have finished executing and the
+    /// frame is acquired again. Immediate-mode renderers can allocate and
+    /// write a fresh set every frame without tracking when it is safe to
+    /// free.
+    ///
+    /// The pool reserves combined image samplers, sampled images, samplers,
+    /// storage images, uniform buffers, and storage buffers. It grows as
+    /// needed.
+    ///
+    /// Descriptor set handles must not be used after `present_frame`
+    /// returns, the same as the frame's command buffers.
+    pub fn allocate_transient_descriptor_set(
+        &mut self,
+        layout: &raii::DescriptorSetLayout,
+    ) -> Result<vk::DescriptorSet, GraphicsError> {
+        unsafe {
+            // SAFE because the set is only freed when the frame is restarted
+            // after its previous submission has completed.
+            self.sync.allocate_transient_descriptor_set(layout)
+        }
+    }
+
+    /// Allocate room for `count` elements of scratch data which is only
+    /// valid for this frame.
+    ///
+    /// Transient memory is bump-allocated from host-visible buffers owned by
+    /// the frame, so immediate-mode renderers can upload vertices and
+    /// uniforms every frame without creating buffers. Everything is freed at
+    /// once when the frame's fence signals and the frame is acquired again.
+    /// Writes are flushed automatically when the frame is presented.
+    ///
+    /// The slice is aligned for use as a vertex, index, uniform, or storage
+    /// buffer.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the slice must not be written after `present_frame` returns, the
+    ///     device may still be reading it and the memory is handed out again
+    ///     when the frame is next acquired
+    ///   - the slice must not be written after the FramesInFlight is
+    ///     dropped, the memory is unmapped and freed
+    ///   - the slice's buffer must only be used by commands recorded for
+    ///     this frame
+    pub unsafe fn alloc_transient<T: Copy>(
+        &mut self,
+        count: usize,
+    ) -> Result<TransientSlice<T>, GraphicsError> {
+        self.sync.allocate_transient(count)
+    }
+
     /// The current frame's index. Always in the range [0-N) where N is the
     /// number of frames in flight.
+    ///
+    /// Frame indices advance in a fixed round-robin order and are unrelated
+    /// to the swapchain image index. Index CPU-written resources, like
+    /// uniform buffers, with this value. See PerFrame.
     pub fn frame_index(&self) -> usize {
         self.sync.index
     }
 
-    /// The index of the swapchain image being targeted by this frame.
+    /// The index of the swapchain image being targeted by this frame. Always
+    /// in the range [0-M) where M is the number of swapchain images.
+    ///
+    /// The presentation engine picks the image, so the order is arbitrary
+    /// and M can differ from the number of frames in flight. Index
+    /// resources tied to a swapchain image, like framebuffers, with this
+    /// value. See PerSwapchainImage.
     pub fn swapchain_image_index(&self) -> usize {
         self.swapchain_image_index
     }
+
+    /// Record commands once for each region of a SplitScreen.
+    ///
+    /// The dynamic viewport and scissor are set to each region before the
+    /// callback runs, so draws are clipped to the region. The callback
+    /// typically pushes a per-region camera, using the region's index to
+    /// pick what to draw and its aspect ratio for the projection.
+    ///
+    /// Renderers which set their own viewport, like BindlessTriangles,
+    /// replace the region's viewport and scissor and so draw across the
+    /// whole framebuffer.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the render pass must already be started
+    ///   - pipelines bound in the callback must use dynamic viewport and
+    ///     scissor state
+    pub unsafe fn for_each_viewport<F>(
+        &self,
+        device: &ash::Device,
+        split_screen: &SplitScreen,
+        mut record: F,
+    ) -> Result<(), GraphicsError>
+    where
+        F: FnMut(&ViewportRegion) -> Result<(), GraphicsError>,
+    {
+        for region in split_screen.regions() {
+            device.cmd_set_viewport(
+                self.command_buffer(),
+                0,
+                &[region.viewport],
+            );
+            device.cmd_set_scissor(self.command_buffer(), 0, &[region.scissor]);
+            record(region)?;
+        }
+        Ok(())
+    }
 }
 
 // Private API
@@ -43,14 +209,18 @@ impl Frame {
 impl Frame {
     /// Create a new Frame which the Application can use to schedule graphics
     /// commands.
-    pub(super) fn new(sync: FrameSync, swapchain_image_index: usize) -> Self {
+    pub(crate) fn new(sync: FrameSync, swapchain_image_index: usize) -> Self {
         Self {
             sync,
             swapchain_image_index,
         }
     }
 
-    pub(super) fn take_sync(self) -> FrameSync {
+    pub(crate) fn sync(&self) -> &FrameSync {
+        &self.sync
+    }
+
+    pub(crate) fn take_sync(self) -> FrameSync {
         self.sync
     }
 }
@@ -1,25 +1,55 @@
 use {
+    super::{TransientArena, TransientSlice},
     crate::graphics::{
-        vulkan_api::{raii, RenderDevice},
+        vulkan_api::{raii, GrowableDescriptorAllocator, RenderDevice},
         GraphicsError,
     },
     anyhow::Context,
     ash::vk,
-    std::sync::Arc,
+    std::{collections::HashMap, sync::Arc},
 };
 
+/// A group of primary command buffers which are submitted together.
+#[derive(Debug)]
+struct Submission {
+    /// The stage where this submission waits for the previous submission.
+    wait_stage: vk::PipelineStageFlags2,
+
+    /// Indices of the command pool's primary buffers, in submission order.
+    command_buffers: Vec<usize>,
+}
+
 /// All of the per-frame synchronization resources.
 #[derive(Debug)]
-pub(super) struct FrameSync {
-    pub(super) index: usize,
-    pub(super) command_pool: raii::CommandPool,
-    pub(super) swapchain_image_acquired_semaphore: raii::Semaphore,
-    pub(super) graphics_commands_completed_semaphore: raii::Semaphore,
-    pub(super) graphics_commands_completed_fence: raii::Fence,
+pub(crate) struct FrameSync {
+    pub(crate) index: usize,
+    pub(crate) command_pool: raii::CommandPool,
+    pub(crate) swapchain_image_acquired_semaphore: raii::Semaphore,
+    pub(crate) graphics_commands_completed_semaphore: raii::Semaphore,
+    pub(crate) graphics_commands_completed_fence: raii::Fence,
+    named_primary_command_buffers: HashMap<String, usize>,
+    named_secondary_command_buffers: HashMap<String, usize>,
+    submissions: Vec<Submission>,
+    submission_semaphores: Vec<raii::Semaphore>,
+    transient_descriptors: GrowableDescriptorAllocator,
+    transient_memory: TransientArena,
     render_device: Arc<RenderDevice>,
 }
 
 impl FrameSync {
+    /// Descriptors reserved per transient descriptor set, by type.
+    const TRANSIENT_DESCRIPTOR_RATIOS: &'static [(vk::DescriptorType, f32)] = &[
+        (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4.0),
+        (vk::DescriptorType::SAMPLED_IMAGE, 2.0),
+        (vk::DescriptorType::SAMPLER, 1.0),
+        (vk::DescriptorType::STORAGE_IMAGE, 1.0),
+        (vk::DescriptorType::UNIFORM_BUFFER, 2.0),
+        (vk::DescriptorType::STORAGE_BUFFER, 2.0),
+    ];
+
+    /// The number of transient descriptor sets the first pool can hold.
+    const INITIAL_TRANSIENT_SETS: u32 = 64;
+
     /// Create synchronization resources for a single in-flight frame.
     ///
     /// # Params
@@ -68,7 +98,8 @@ impl FrameSync {
 
         let mut command_pool = unsafe {
             let create_info = vk::CommandPoolCreateInfo {
-                flags: vk::CommandPoolCreateFlags::TRANSIENT,
+                flags: vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
                 ..Default::default()
             };
             raii::CommandPool::new(render_device.clone(), &create_info)?
@@ -76,12 +107,29 @@ impl FrameSync {
         command_pool.set_debug_name(format!("Frame {index} Command Pool"));
         let _ = command_pool.allocate_primary_command_buffers(1);
 
+        let mut transient_descriptors = GrowableDescriptorAllocator::new(
+            render_device.clone(),
+            Self::TRANSIENT_DESCRIPTOR_RATIOS,
+            Self::INITIAL_TRANSIENT_SETS,
+        );
+        transient_descriptors
+            .set_debug_name(format!("Frame {index} Transient Descriptors"));
+
         Ok(Self {
             index,
             command_pool,
             swapchain_image_acquired_semaphore,
             graphics_commands_completed_semaphore,
             graphics_commands_completed_fence,
+            named_primary_command_buffers: HashMap::new(),
+            named_secondary_command_buffers: HashMap::new(),
+            submissions: vec![Submission {
+                wait_stage: vk::PipelineStageFlags2::NONE,
+                command_buffers: vec![],
+            }],
+            submission_semaphores: vec![],
+            transient_descriptors,
+            transient_memory: TransientArena::new(render_device.clone(), index),
             render_device,
         })
     }
@@ -114,49 +162,359 @@ impl FrameSync {
     /// # Params
     ///
     /// * `render_device` - the device used to create the frame sync resources
-    pub fn wait_and_restart_command_buffer(&self) -> Result<(), GraphicsError> {
+    pub fn wait_and_restart_command_buffer(
+        &mut self,
+    ) -> Result<(), GraphicsError> {
         self.wait_for_graphics_commands_to_complete()?;
-        unsafe {
-            // SAFE because we wait for the previous submission's commands to
-            // complete before resetting and restarting resources.
-            self.render_device
-                .device()
-                .reset_fences(&[self.graphics_commands_completed_fence.raw()])
-                .with_context(|| {
-                    format!(
-                        "Could not reset graphics completed fence for frame {}",
-                        self.index
-                    )
-                })?;
-            self.render_device
-                .device()
-                .reset_command_pool(
-                    self.command_pool.raw(),
-                    vk::CommandPoolResetFlags::empty(),
+        // SAFE because we wait for the previous submission's commands to
+        // complete before resetting and restarting resources.
+        unsafe { self.restart_command_buffer() }
+    }
+
+    /// Reset and restart the command buffer for this frame without waiting.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's previous submission must have completed, e.g. by
+    ///     calling `wait_for_graphics_commands_to_complete()` first
+    pub unsafe fn restart_command_buffer(
+        &mut self,
+    ) -> Result<(), GraphicsError> {
+        self.render_device
+            .device()
+            .reset_fences(&[self.graphics_commands_completed_fence.raw()])
+            .with_context(|| {
+                format!(
+                    "Could not reset graphics completed fence for frame {}",
+                    self.index
                 )
-                .with_context(|| {
-                    format!(
-                        "Could not reset command pool for frame {}",
-                        self.index
-                    )
-                })?;
-            let begin_info = vk::CommandBufferBeginInfo {
-                flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
-                ..Default::default()
+            })?;
+        self.render_device
+            .device()
+            .reset_command_pool(
+                self.command_pool.raw(),
+                vk::CommandPoolResetFlags::empty(),
+            )
+            .with_context(|| {
+                format!("Could not reset command pool for frame {}", self.index)
+            })?;
+        self.transient_descriptors.reset().with_context(|| {
+            format!(
+                "Could not reset transient descriptors for frame {}",
+                self.index
+            )
+        })?;
+        self.transient_memory.reset();
+        self.submissions.truncate(1);
+        self.submissions[0].command_buffers.clear();
+        let begin_info = vk::CommandBufferBeginInfo {
+            flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            ..Default::default()
+        };
+        self.render_device
+            .device()
+            .begin_command_buffer(
+                self.command_pool.primary_command_buffer(0),
+                &begin_info,
+            )
+            .with_context(|| {
+                format!(
+                    "Could not begin command buffer for frame {}",
+                    self.index
+                )
+            })?;
+        Ok(())
+    }
+
+    /// Allocate a descriptor set which is freed when this frame is next
+    /// restarted.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the caller must not use the set after the frame is restarted
+    pub unsafe fn allocate_transient_descriptor_set(
+        &mut self,
+        layout: &raii::DescriptorSetLayout,
+    ) -> Result<vk::DescriptorSet, GraphicsError> {
+        self.transient_descriptors.allocate(layout)
+    }
+
+    /// Allocate transient memory which is freed when this frame is next
+    /// restarted.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the caller must not use the memory after the frame is restarted
+    pub unsafe fn allocate_transient<T: Copy>(
+        &mut self,
+        count: usize,
+    ) -> Result<TransientSlice<T>, GraphicsError> {
+        self.transient_memory.allocate(count)
+    }
+
+    /// Get the primary command buffer with the given name, allocating it the
+    /// first time the name is used.
+    ///
+    /// The buffer is started the first time it is requested each frame.
+    /// Buffers are remembered by name so the same handles are reused every
+    /// time this frame comes around.
+    pub fn named_primary_command_buffer(
+        &mut self,
+        name: &str,
+    ) -> Result<vk::CommandBuffer, GraphicsError> {
+        let index = match self.named_primary_command_buffers.get(name) {
+            Some(&index) => index,
+            None => {
+                let index =
+                    self.command_pool.allocate_primary_command_buffers(1)?;
+                self.render_device.set_debug_name(
+                    self.command_pool.primary_command_buffer(index),
+                    vk::ObjectType::COMMAND_BUFFER,
+                    format!("Frame {} {name} Command Buffer", self.index),
+                );
+                self.named_primary_command_buffers
+                    .insert(name.to_owned(), index);
+                index
+            }
+        };
+        let command_buffer = self.command_pool.primary_command_buffer(index);
+
+        let is_started = self
+            .submissions
+            .iter()
+            .any(|submission| submission.command_buffers.contains(&index));
+        if !is_started {
+            unsafe {
+                // SAFE because the command pool is reset at the start of
+                // every frame, so the buffer is in the initial state.
+                let begin_info = vk::CommandBufferBeginInfo {
+                    flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                    ..Default::default()
+                };
+                self.render_device
+                    .device()
+                    .begin_command_buffer(command_buffer, &begin_info)
+                    .with_context(|| {
+                        format!(
+                            "Could not begin {name} command buffer for frame {}",
+                            self.index
+                        )
+                    })?;
+            }
+            self.submissions
+                .last_mut()
+                .unwrap()
+                .command_buffers
+                .push(index);
+        }
+
+        Ok(command_buffer)
+    }
+
+    /// Get the secondary command buffer with the given name, allocating it the
+    /// first time the name is used.
+    ///
+    /// Secondary buffers are not started automatically because they require
+    /// inheritance info which only the caller knows.
+    pub fn named_secondary_command_buffer(
+        &mut self,
+        name: &str,
+    ) -> Result<vk::CommandBuffer, GraphicsError> {
+        let index = match self.named_secondary_command_buffers.get(name) {
+            Some(&index) => index,
+            None => {
+                let index =
+                    self.command_pool.allocate_secondary_command_buffers(1)?;
+                self.render_device.set_debug_name(
+                    self.command_pool.secondary_command_buffer(index),
+                    vk::ObjectType::COMMAND_BUFFER,
+                    format!("Frame {} {name} Command Buffer", self.index),
+                );
+                self.named_secondary_command_buffers
+                    .insert(name.to_owned(), index);
+                index
+            }
+        };
+        Ok(self.command_pool.secondary_command_buffer(index))
+    }
+
+    /// Start a new queue submission which waits for every earlier
+    /// submission in this frame to complete.
+    ///
+    /// # Params
+    ///
+    /// * `wait_stage` - the first pipeline stage in the new submission which
+    ///   depends on the results of the earlier submissions.
+    pub fn begin_submission(
+        &mut self,
+        wait_stage: vk::PipelineStageFlags2,
+    ) -> Result<(), GraphicsError> {
+        // one semaphore is needed between each pair of submissions
+        let semaphore_index = self.submissions.len() - 1;
+        if self.submission_semaphores.len() <= semaphore_index {
+            let semaphore = unsafe {
+                raii::Semaphore::new(
+                    self.render_device.clone(),
+                    &vk::SemaphoreCreateInfo::default(),
+                )?
             };
+            semaphore.set_debug_name(format!(
+                "Frame {} Submission {} Completed",
+                self.index, semaphore_index
+            ));
+            self.submission_semaphores.push(semaphore);
+        }
+        self.submissions.push(Submission {
+            wait_stage,
+            command_buffers: vec![],
+        });
+        Ok(())
+    }
+
+    /// The wait stage and command buffer names for each submission recorded
+    /// so far this frame, in submission order.
+    ///
+    /// The frame's main command buffer is named "main" and is always part of
+    /// the last submission.
+    pub fn submission_layout(
+        &self,
+    ) -> Vec<(vk::PipelineStageFlags2, Vec<String>)> {
+        let name_of = |index: usize| {
+            self.named_primary_command_buffers
+                .iter()
+                .find(|(_, &named_index)| named_index == index)
+                .map(|(name, _)| name.clone())
+                .unwrap_or_else(|| format!("command buffer {index}"))
+        };
+        let last = self.submissions.len() - 1;
+        self.submissions
+            .iter()
+            .enumerate()
+            .map(|(index, submission)| {
+                let mut names: Vec<String> = submission
+                    .command_buffers
+                    .iter()
+                    .map(|&buffer_index| name_of(buffer_index))
+                    .collect();
+                if index == last {
+                    names.push("main".to_owned());
+                }
+                (submission.wait_stage, names)
+            })
+            .collect()
+    }
+
+    /// End every command buffer recorded this frame and submit them all to
+    /// the graphics queue.
+    ///
+    /// Each submission waits for the one before it. The final submission also
+    /// holds the frame's main command buffer, waits for the swapchain image
+    /// to be acquired, and signals the graphics commands completed semaphore
+    /// and fence.
+    pub fn end_and_submit(&mut self) -> Result<(), GraphicsError> {
+        self.transient_memory.flush()?;
+        self.submissions.last_mut().unwrap().command_buffers.push(0);
+
+        let command_buffer_infos: Vec<Vec<vk::CommandBufferSubmitInfo>> = self
+            .submissions
+            .iter()
+            .map(|submission| {
+                submission
+                    .command_buffers
+                    .iter()
+                    .map(|&index| vk::CommandBufferSubmitInfo {
+                        command_buffer: self
+                            .command_pool
+                            .primary_command_buffer(index),
+                        ..Default::default()
+                    })
+                    .collect()
+            })
+            .collect();
+        for info in command_buffer_infos.iter().flatten() {
+            unsafe {
+                self.render_device
+                    .device()
+                    .end_command_buffer(info.command_buffer)
+                    .with_context(|| {
+                        format!(
+                            "Error ending command buffer for frame {}",
+                            self.index
+                        )
+                    })?;
+            }
+        }
+
+        let last = self.submissions.len() - 1;
+        let mut wait_infos = Vec::with_capacity(self.submissions.len());
+        let mut signal_infos = Vec::with_capacity(self.submissions.len());
+        for (index, submission) in self.submissions.iter().enumerate() {
+            let mut waits = vec![];
+            if index > 0 {
+                waits.push(vk::SemaphoreSubmitInfo {
+                    semaphore: self.submission_semaphores[index - 1].raw(),
+                    stage_mask: submission.wait_stage,
+                    ..Default::default()
+                });
+            }
+            if index == last {
+                waits.push(vk::SemaphoreSubmitInfo {
+                    semaphore: self.swapchain_image_acquired_semaphore.raw(),
+                    stage_mask:
+                        vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                    ..Default::default()
+                });
+            }
+            wait_infos.push(waits);
+
+            let signal = if index == last {
+                vk::SemaphoreSubmitInfo {
+                    semaphore: self.graphics_commands_completed_semaphore.raw(),
+                    stage_mask:
+                        vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                    ..Default::default()
+                }
+            } else {
+                vk::SemaphoreSubmitInfo {
+                    semaphore: self.submission_semaphores[index].raw(),
+                    stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+                    ..Default::default()
+                }
+            };
+            signal_infos.push(signal);
+        }
+
+        let submit_infos: Vec<vk::SubmitInfo2> = (0..self.submissions.len())
+            .map(|index| vk::SubmitInfo2 {
+                p_wait_semaphore_infos: wait_infos[index].as_ptr(),
+                wait_semaphore_info_count: wait_infos[index].len() as u32,
+                p_command_buffer_infos: command_buffer_infos[index].as_ptr(),
+                command_buffer_info_count: command_buffer_infos[index].len()
+                    as u32,
+                p_signal_semaphore_infos: &signal_infos[index],
+                signal_semaphore_info_count: 1,
+                ..Default::default()
+            })
+            .collect();
+        unsafe {
             self.render_device
                 .device()
-                .begin_command_buffer(
-                    self.command_pool.primary_command_buffer(0),
-                    &begin_info,
+                .queue_submit2(
+                    *self.render_device.graphics_queue().raw(),
+                    &submit_infos,
+                    self.graphics_commands_completed_fence.raw(),
                 )
                 .with_context(|| {
                     format!(
-                        "Could not begin command buffer for frame {}",
+                        "Error submitting graphics commands for frame {}",
                         self.index
                     )
                 })?;
         }
+
         Ok(())
     }
 }
@@ -1,17 +1,28 @@
 mod frame;
 mod frame_sync;
+mod per_frame;
+mod swapchain_dependent;
+mod transient_arena;
 
 use {
-    self::frame_sync::FrameSync,
     super::{RenderDevice, SwapchainStatus},
     crate::graphics::{vulkan_api::Swapchain, GraphicsError},
     anyhow::Context,
     ash::vk,
     ccthw_ash_instance::VulkanHandle,
-    std::sync::Arc,
+    std::{cell::RefCell, rc::Rc, sync::Arc},
 };
 
-pub use self::frame::Frame;
+pub use self::{
+    frame::Frame,
+    per_frame::{PerFrame, PerSwapchainImage},
+    swapchain_dependent::{SwapchainChanges, SwapchainDependent},
+    transient_arena::TransientSlice,
+};
+
+use self::transient_arena::TransientArena;
+
+pub(crate) use self::frame_sync::FrameSync;
 
 /// The result of a call to FramesInFlight::acquire_frame.
 pub enum FrameStatus {
@@ -29,6 +40,7 @@ pub struct FramesInFlight {
     current_frame: usize,
     frames: Vec<Option<FrameSync>>,
     swapchain: Option<Swapchain>,
+    swapchain_dependents: Vec<Box<dyn SwapchainDependent>>,
     render_device: Arc<RenderDevice>,
 }
 
@@ -75,6 +87,7 @@ impl FramesInFlight {
             current_frame: 0,
             frames,
             swapchain: Some(swapchain),
+            swapchain_dependents: vec![],
             render_device,
         })
     }
@@ -111,19 +124,27 @@ impl FramesInFlight {
 
     /// Wait for every frame to finish executing then rebuild the swapchain.
     ///
+    /// Every registered SwapchainDependent is rebuilt, in registration order,
+    /// once the new swapchain is ready. Returns what changed, so resources
+    /// which are rebuilt by hand can skip the ones which still match.
+    ///
     /// # Safety
     ///
     /// Unsafe because:
     ///  - it is invalid to rebuild the swapchain while recording a frame. In
     ///    other words, never call this function after getting a frame from
     ///    `acquire_frame` and before returning that frame with `present_frame`.
+    #[tracing::instrument(skip_all)]
     pub unsafe fn stall_and_rebuild_swapchain(
         &mut self,
         framebuffer_size: (i32, i32),
-    ) -> Result<(), GraphicsError> {
+    ) -> Result<SwapchainChanges, GraphicsError> {
         self.wait_for_all_frames_to_complete()?;
 
         let old_swapchain = self.swapchain.take();
+        let old_properties = old_swapchain
+            .as_ref()
+            .map(|swapchain| (swapchain.extent(), swapchain.image_format()));
         let (w, h) = framebuffer_size;
         let new_swapchain = Swapchain::new(
             self.render_device.clone(),
@@ -134,7 +155,34 @@ impl FramesInFlight {
 
         self.swapchain_needs_rebuild = false;
 
-        Ok(())
+        let swapchain = self.swapchain.as_ref().unwrap();
+        let changes = match old_properties {
+            Some((extent, format)) => SwapchainChanges {
+                extent_changed: extent != swapchain.extent(),
+                format_changed: format != swapchain.image_format(),
+            },
+            None => SwapchainChanges {
+                extent_changed: true,
+                format_changed: true,
+            },
+        };
+        for dependent in &mut self.swapchain_dependents {
+            dependent.rebuild_for_swapchain(swapchain, &changes)?;
+        }
+
+        Ok(changes)
+    }
+
+    /// Register a resource which is rebuilt automatically every time the
+    /// swapchain is rebuilt by `stall_and_rebuild_swapchain`.
+    ///
+    /// The dependent is not invoked for the current swapchain, so resources
+    /// should be built for it when they are registered.
+    pub fn on_swapchain_rebuilt(
+        &mut self,
+        dependent: impl SwapchainDependent + 'static,
+    ) {
+        self.swapchain_dependents.push(Box::new(dependent));
     }
 
     /// Get the current swapchain.
@@ -156,12 +204,87 @@ impl FramesInFlight {
         self.frames.len()
     }
 
+    /// Create one resource for each frame in flight.
+    ///
+    /// The number of frames in flight never changes, so the resources are
+    /// not rebuilt with the swapchain. Use `create_per_swapchain_image` for
+    /// resources which depend on the swapchain's images.
+    ///
+    /// # Params
+    ///
+    /// * `create` - called once for each frame index in the range [0-N)
+    ///   where N is the number of frames in flight.
+    pub fn create_per_frame<T, F>(
+        &self,
+        create: F,
+    ) -> Result<PerFrame<T>, GraphicsError>
+    where
+        F: FnMut(usize) -> Result<T, GraphicsError>,
+    {
+        PerFrame::from_fn(self.frame_count(), create)
+    }
+
+    /// Create one resource for each swapchain image, rebuilt automatically
+    /// every time the swapchain is rebuilt.
+    ///
+    /// The container is registered with `on_swapchain_rebuilt`, so it's
+    /// rebuilt after any dependents registered before it. Hold on to the
+    /// returned handle to read the values while recording frames.
+    ///
+    /// # Params
+    ///
+    /// * `create` - called with the swapchain and each image index in the
+    ///   range [0-N) where N is the number of swapchain images. It's called
+    ///   again for every image each time the swapchain is rebuilt.
+    pub fn create_per_swapchain_image<T, F>(
+        &mut self,
+        create: F,
+    ) -> Result<Rc<RefCell<PerSwapchainImage<T>>>, GraphicsError>
+    where
+        T: 'static,
+        F: FnMut(&Swapchain, usize) -> Result<T, GraphicsError> + 'static,
+    {
+        let per_swapchain_image = Rc::new(RefCell::new(
+            PerSwapchainImage::for_swapchain(self.swapchain(), create)?,
+        ));
+        self.on_swapchain_rebuilt(per_swapchain_image.clone());
+        Ok(per_swapchain_image)
+    }
+
+    /// Returns true when the most recent submission for the given frame index
+    /// has finished executing on the GPU.
+    ///
+    /// Always false for the frame which is currently being recorded.
+    pub fn is_frame_complete(
+        &self,
+        frame_index: usize,
+    ) -> Result<bool, GraphicsError> {
+        let frame_sync = match &self.frames[frame_index] {
+            Some(frame_sync) => frame_sync,
+            None => return Ok(false),
+        };
+        let is_complete = unsafe {
+            self.render_device
+                .device()
+                .get_fence_status(
+                    frame_sync.graphics_commands_completed_fence.raw(),
+                )
+                .with_context(|| {
+                    format!(
+                        "Error checking fence status for frame {frame_index}"
+                    )
+                })?
+        };
+        Ok(is_complete)
+    }
+
     /// Acquire the next frame for rendering.
     ///
     /// # Params
     ///
     /// * `render_device` - the render device used to create the frames in
     ///   flight.
+    #[tracing::instrument(skip_all)]
     pub fn acquire_frame(&mut self) -> Result<FrameStatus, GraphicsError> {
         if self.swapchain_needs_rebuild {
             return Ok(FrameStatus::SwapchainNeedsRebuild);
@@ -171,28 +294,57 @@ impl FramesInFlight {
         self.current_frame = (self.current_frame + 1) % self.frames.len();
 
         // grab the synchronization resources for the current in-flight frame.
-        let frame_sync =
+        let mut frame_sync =
             self.frames[self.current_frame].take().with_context(|| {
                 format!("Unable to acquire frame {}", self.current_frame)
             })?;
 
+        // Each frame in flight reuses its own acquire semaphore rather than
+        // pulling a fresh one every frame. The semaphore is only safe to
+        // signal again once the frame's previous submission, which waited on
+        // it, has finished executing.
+        if let Err(err) = frame_sync.wait_for_graphics_commands_to_complete() {
+            self.frames[self.current_frame] = Some(frame_sync);
+            return Err(err);
+        }
+
         let result = unsafe {
             self.swapchain().acquire_swapchain_image(
                 frame_sync.swapchain_image_acquired_semaphore.raw(),
                 vk::Fence::null(),
-            )?
+            )
         };
         let swapchain_image_index = match result {
-            SwapchainStatus::Index(index) => index,
-            SwapchainStatus::NeedsRebuild => {
+            Ok(SwapchainStatus::Index(index)) => index,
+            Ok(SwapchainStatus::Suboptimal(index)) => {
+                // The image is acquired and the semaphore will be signaled,
+                // so render and present this frame and rebuild on the next
+                // call. Returning the sync resources now would leave the
+                // semaphore with a signal nothing waits for.
+                self.swapchain_needs_rebuild = true;
+                index
+            }
+            Ok(SwapchainStatus::NeedsRebuild) => {
+                // Return the sync resources so the frame can be acquired
+                // again after the swapchain is rebuilt.
+                self.frames[self.current_frame] = Some(frame_sync);
                 self.swapchain_needs_rebuild = true;
                 return Ok(FrameStatus::SwapchainNeedsRebuild);
             }
+            Err(err) => {
+                self.frames[self.current_frame] = Some(frame_sync);
+                return Err(err);
+            }
         };
 
-        // wait for the previous submission's commands to finish, then restart
-        // the command buffer.
-        frame_sync.wait_and_restart_command_buffer()?;
+        // restart the command buffer now that the previous submission is
+        // known to be complete.
+        //
+        // SAFE because the frame's fence was waited on above.
+        if let Err(err) = unsafe { frame_sync.restart_command_buffer() } {
+            self.frames[self.current_frame] = Some(frame_sync);
+            return Err(err);
+        }
 
         let frame = Frame::new(frame_sync, swapchain_image_index);
         Ok(FrameStatus::FrameAcquired(frame))
@@ -206,56 +358,20 @@ impl FramesInFlight {
     /// * `render_device` - the render device used to create the frames in
     ///   flight.
     /// * `frame` - the frame to present
+    #[tracing::instrument(skip_all)]
     pub fn present_frame(&mut self, frame: Frame) -> Result<(), GraphicsError> {
         debug_assert!(frame.frame_index() == self.current_frame);
 
         let frame_index = frame.frame_index();
         let swapchain_image_index = frame.swapchain_image_index();
         self.frames[frame_index] = Some(frame.take_sync());
-        let sync = self.frames[frame_index].as_ref().unwrap();
-        let command_buffer = sync.command_pool.primary_command_buffer(0);
-
-        // end the command buffer and submit
-        unsafe {
-            self.render_device
-                .device()
-                .end_command_buffer(command_buffer)
-                .with_context(|| {
-                    format!(
-                        "Error ending graphics command buffer for frame {}",
-                        self.current_frame
-                    )
-                })?;
 
-            let command_buffer_infos = [vk::CommandBufferSubmitInfo {
-                command_buffer,
-                ..Default::default()
-            }];
-            let wait_infos = [vk::SemaphoreSubmitInfo {
-                semaphore: sync.swapchain_image_acquired_semaphore.raw(),
-                stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
-                ..Default::default()
-            }];
-            let signal_infos = [vk::SemaphoreSubmitInfo {
-                semaphore: sync.graphics_commands_completed_semaphore.raw(),
-                stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
-                ..Default::default()
-            }];
-            let submit_info = vk::SubmitInfo2 {
-                p_wait_semaphore_infos: wait_infos.as_ptr(),
-                wait_semaphore_info_count: wait_infos.len() as u32,
-                p_command_buffer_infos: command_buffer_infos.as_ptr(),
-                command_buffer_info_count: command_buffer_infos.len() as u32,
-                p_signal_semaphore_infos: signal_infos.as_ptr(),
-                signal_semaphore_info_count: signal_infos.len() as u32,
-                ..Default::default()
-            };
-            self.render_device.device().queue_submit2(
-                *self.render_device.graphics_queue().raw(),
-                &[submit_info],
-                sync.graphics_commands_completed_fence.raw(),
-            )?;
-        }
+        // end every command buffer recorded this frame and submit
+        self.frames[frame_index]
+            .as_mut()
+            .unwrap()
+            .end_and_submit()?;
+        let sync = self.frames[frame_index].as_ref().unwrap();
 
         unsafe {
             let status = self
@@ -0,0 +1,226 @@
+use {
+    super::{Frame, SwapchainChanges, SwapchainDependent},
+    crate::graphics::{vulkan_api::Swapchain, GraphicsError},
+    anyhow::anyhow,
+    std::ops::{Index, IndexMut},
+};
+
+/// One value for each frame in flight, indexed by a Frame.
+///
+/// Use this for resources which the CPU writes while recording a frame, like
+/// host-visible uniform buffers or the descriptor sets which refer to them.
+/// Such resources can be reused as soon as the frame's previous submission
+/// completes, regardless of which swapchain image it targets.
+#[derive(Debug, Clone)]
+pub struct PerFrame<T> {
+    values: Vec<T>,
+}
+
+/// One value for each swapchain image, indexed by a Frame.
+///
+/// Use this for resources which are tied to a specific swapchain image, like
+/// framebuffers or per-image presentation semaphores.
+///
+/// The number of swapchain images can change when the swapchain is rebuilt.
+/// A container created with `for_swapchain` keeps its create function and
+/// is a SwapchainDependent, so it can be rebuilt automatically. See
+/// `FramesInFlight::create_per_swapchain_image`.
+pub struct PerSwapchainImage<T> {
+    values: Vec<T>,
+    create: Option<CreateFn<T>>,
+}
+
+/// Creates the value for one swapchain image.
+type CreateFn<T> =
+    Box<dyn FnMut(&Swapchain, usize) -> Result<T, GraphicsError>>;
+
+// Public API
+// ----------
+
+impl<T> PerFrame<T> {
+    /// Create a container from one value per frame in flight.
+    ///
+    /// The caller must provide exactly `FramesInFlight::frame_count()`
+    /// values, ordered by frame index.
+    pub fn new(values: Vec<T>) -> Self {
+        Self { values }
+    }
+
+    /// Create a container by calling `create` once for each frame index in
+    /// the range [0-frame_count).
+    pub fn from_fn<E, F>(frame_count: usize, create: F) -> Result<Self, E>
+    where
+        F: FnMut(usize) -> Result<T, E>,
+    {
+        let values = (0..frame_count).map(create).collect::<Result<_, _>>()?;
+        Ok(Self { values })
+    }
+
+    /// The number of values. Always the number of frames in flight.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Always false for a container created with at least one frame.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over every frame's value, ordered by frame index.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+
+    /// Mutably iterate over every frame's value, ordered by frame index.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.values.iter_mut()
+    }
+}
+
+impl<T> Index<&Frame> for PerFrame<T> {
+    type Output = T;
+
+    fn index(&self, frame: &Frame) -> &T {
+        &self.values[frame.frame_index()]
+    }
+}
+
+impl<T> IndexMut<&Frame> for PerFrame<T> {
+    fn index_mut(&mut self, frame: &Frame) -> &mut T {
+        &mut self.values[frame.frame_index()]
+    }
+}
+
+impl<T> PerSwapchainImage<T> {
+    /// Create a container from one value per swapchain image.
+    ///
+    /// The caller must provide exactly one value for each image in
+    /// `Swapchain::images()`, ordered by image index. The container must be
+    /// replaced whenever the swapchain is rebuilt.
+    pub fn new(values: Vec<T>) -> Self {
+        Self {
+            values,
+            create: None,
+        }
+    }
+
+    /// Create a container by calling `create` once for each swapchain image
+    /// index in the range [0-image_count). The container must be replaced
+    /// whenever the swapchain is rebuilt.
+    pub fn from_fn<E, F>(image_count: usize, create: F) -> Result<Self, E>
+    where
+        F: FnMut(usize) -> Result<T, E>,
+    {
+        let values = (0..image_count).map(create).collect::<Result<_, _>>()?;
+        Ok(Self::new(values))
+    }
+
+    /// Create a container by calling `create` once for each of the
+    /// swapchain's images.
+    ///
+    /// `create` is kept and called again for every image each time the
+    /// container is rebuilt with `rebuild_for_swapchain`.
+    ///
+    /// # Params
+    ///
+    /// * `swapchain` - the swapchain to create values for
+    /// * `create` - called with the swapchain and each image index in the
+    ///   range [0-image_count)
+    pub fn for_swapchain<F>(
+        swapchain: &Swapchain,
+        create: F,
+    ) -> Result<Self, GraphicsError>
+    where
+        F: FnMut(&Swapchain, usize) -> Result<T, GraphicsError> + 'static,
+    {
+        let mut per_swapchain_image = Self {
+            values: vec![],
+            create: Some(Box::new(create)),
+        };
+        per_swapchain_image.recreate_values(swapchain)?;
+        Ok(per_swapchain_image)
+    }
+
+    /// The number of values. Always the number of swapchain images.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Always false for a container created with at least one image.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over every image's value, ordered by swapchain image index.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+
+    /// Mutably iterate over every image's value, ordered by swapchain image
+    /// index.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.values.iter_mut()
+    }
+}
+
+impl<T> Index<&Frame> for PerSwapchainImage<T> {
+    type Output = T;
+
+    fn index(&self, frame: &Frame) -> &T {
+        &self.values[frame.swapchain_image_index()]
+    }
+}
+
+impl<T> IndexMut<&Frame> for PerSwapchainImage<T> {
+    fn index_mut(&mut self, frame: &Frame) -> &mut T {
+        &mut self.values[frame.swapchain_image_index()]
+    }
+}
+
+impl<T> SwapchainDependent for PerSwapchainImage<T> {
+    /// Replace every value, one for each of the new swapchain's images.
+    ///
+    /// Fails for containers created without a create function.
+    fn rebuild_for_swapchain(
+        &mut self,
+        swapchain: &Swapchain,
+        _changes: &SwapchainChanges,
+    ) -> Result<(), GraphicsError> {
+        self.recreate_values(swapchain)
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for PerSwapchainImage<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PerSwapchainImage")
+            .field("values", &self.values)
+            .field("rebuildable", &self.create.is_some())
+            .finish()
+    }
+}
+
+// Private API
+// -----------
+
+impl<T> PerSwapchainImage<T> {
+    /// Replace every value by calling the create function for each of the
+    /// swapchain's images.
+    fn recreate_values(
+        &mut self,
+        swapchain: &Swapchain,
+    ) -> Result<(), GraphicsError> {
+        let create = self.create.as_mut().ok_or_else(|| {
+            GraphicsError::RuntimeError(anyhow!(
+                "A PerSwapchainImage can only be rebuilt when it was created \
+                 with for_swapchain"
+            ))
+        })?;
+        // Drop the old values first, they may refer to the old swapchain's
+        // images.
+        self.values.clear();
+        for index in 0..swapchain.image_count() {
+            self.values.push(create(swapchain, index)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use {
+    crate::graphics::{vulkan_api::Swapchain, GraphicsError},
+    std::{cell::RefCell, rc::Rc},
+};
+
+/// What changed when the swapchain was rebuilt.
+///
+/// The swapchain images are always new, so anything which references them
+/// directly, like image views and framebuffers, must always be rebuilt. The
+/// rest only needs to be rebuilt when the property it depends on changed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SwapchainChanges {
+    /// The extent changed, so images sized to match the swapchain, like
+    /// depth buffers and offscreen targets, must be rebuilt.
+    pub extent_changed: bool,
+
+    /// The image format changed, so render passes which target the
+    /// swapchain, and every pipeline built against them, must be rebuilt.
+    pub format_changed: bool,
+}
+
+/// A resource which must be rebuilt any time the swapchain is rebuilt.
+///
+/// Register dependents with `FramesInFlight::on_swapchain_rebuilt` and they
+/// are rebuilt automatically, in registration order, at the end of
+/// `FramesInFlight::stall_and_rebuild_swapchain`. Every frame has finished
+/// executing by then, so dependents can freely destroy and replace resources
+/// like framebuffers and pipelines.
+///
+/// Dependents should only rebuild what the swapchain changes require:
+///
+/// - swapchain image views and framebuffers, always
+/// - extent-sized images and their descriptor sets, when the extent changed
+/// - render passes and pipelines, when the format changed
+///
+/// Vertex buffers, textures, storage buffers, and the descriptor sets which
+/// bind them never depend on the swapchain and must be kept. A ColorPass
+/// keeps its render pass while the format is unchanged, so pipelines built
+/// against it, like BindlessTriangles, keep working.
+///
+/// Closures are dependents, as is any `Rc<RefCell<T>>` where T is a
+/// dependent. The latter lets the application keep using the resource
+/// between rebuilds. A PerSwapchainImage created with `for_swapchain` is a
+/// dependent which recreates its values for the new images.
+pub trait SwapchainDependent {
+    /// Rebuild this resource for the new swapchain.
+    fn rebuild_for_swapchain(
+        &mut self,
+        swapchain: &Swapchain,
+        changes: &SwapchainChanges,
+    ) -> Result<(), GraphicsError>;
+}
+
+impl<F> SwapchainDependent for F
+where
+    F: FnMut(&Swapchain, &SwapchainChanges) -> Result<(), GraphicsError>,
+{
+    fn rebuild_for_swapchain(
+        &mut self,
+        swapchain: &Swapchain,
+        changes: &SwapchainChanges,
+    ) -> Result<(), GraphicsError> {
+        self(swapchain, changes)
+    }
+}
+
+impl<T> SwapchainDependent for Rc<RefCell<T>>
+where
+    T: SwapchainDependent,
+{
+    fn rebuild_for_swapchain(
+        &mut self,
+        swapchain: &Swapchain,
+        changes: &SwapchainChanges,
+    ) -> Result<(), GraphicsError> {
+        self.borrow_mut().rebuild_for_swapchain(swapchain, changes)
+    }
+}
@@ -0,0 +1,292 @@
+use {
+    crate::graphics::{
+        vulkan_api::{HostVisibleDeviceMemory, MappedBuffer, RenderDevice},
+        AllocationError, GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A range of a frame's transient memory, returned by
+/// `Frame::alloc_transient`.
+///
+/// The range is only valid for the frame which allocated it. Nothing ties
+/// the slice to the frame's lifetime, so `Frame::alloc_transient` is unsafe
+/// and the caller must stop using the slice when the frame is presented.
+#[derive(Debug)]
+pub struct TransientSlice<T: Copy> {
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    len: usize,
+    ptr: *mut T,
+}
+
+/// Host-visible buffers which per-frame data is bump-allocated from, then
+/// freed all at once when the frame is restarted.
+#[derive(Debug)]
+pub(crate) struct TransientArena {
+    blocks: Vec<Block>,
+    current_block: usize,
+    min_alignment: vk::DeviceSize,
+    memory_property_flags: Option<vk::MemoryPropertyFlags>,
+    index: usize,
+    render_device: Arc<RenderDevice>,
+}
+
+/// A single buffer in the arena.
+#[derive(Debug)]
+struct Block {
+    buffer: MappedBuffer<u8>,
+    offset: usize,
+}
+
+// Public API
+// ----------
+
+impl<T: Copy> TransientSlice<T> {
+    /// The buffer which holds the slice.
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// The slice's offset in bytes from the start of `buffer()`.
+    ///
+    /// Aligned for use as a vertex, index, uniform, or storage buffer.
+    pub fn offset(&self) -> vk::DeviceSize {
+        self.offset
+    }
+
+    /// The number of elements in the slice.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true when the slice has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The slice's size in bytes.
+    pub fn size_in_bytes(&self) -> vk::DeviceSize {
+        (self.len * std::mem::size_of::<T>()) as vk::DeviceSize
+    }
+
+    /// The buffer info for binding the slice to a uniform or storage buffer
+    /// descriptor.
+    pub fn descriptor_buffer_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffer,
+            offset: self.offset,
+            range: self.size_in_bytes(),
+        }
+    }
+
+    /// Copy `data` into the start of the slice.
+    ///
+    /// The slice is only written, never read, because its contents are
+    /// undefined until they're written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` has more than `len()` elements.
+    pub fn write(&mut self, data: &[T]) {
+        assert!(
+            data.len() <= self.len,
+            "Cannot write {} elements to a TransientSlice of {}",
+            data.len(),
+            self.len
+        );
+        // SAFE because the pointer is aligned, the range was checked above,
+        // and `Frame::alloc_transient`'s caller guarantees the memory is
+        // still mapped and owned by this frame.
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.ptr, data.len());
+        }
+    }
+}
+
+impl TransientArena {
+    /// The size of each block. Larger allocations get a block of their own.
+    const BLOCK_SIZE: usize = 1024 * 1024;
+
+    /// How transient memory can be used by the device.
+    const USAGE: vk::BufferUsageFlags = vk::BufferUsageFlags::from_raw(
+        vk::BufferUsageFlags::VERTEX_BUFFER.as_raw()
+            | vk::BufferUsageFlags::INDEX_BUFFER.as_raw()
+            | vk::BufferUsageFlags::UNIFORM_BUFFER.as_raw()
+            | vk::BufferUsageFlags::STORAGE_BUFFER.as_raw()
+            | vk::BufferUsageFlags::INDIRECT_BUFFER.as_raw()
+            | vk::BufferUsageFlags::TRANSFER_SRC.as_raw(),
+    );
+
+    /// Create an empty arena. Blocks are allocated on first use.
+    pub fn new(render_device: Arc<RenderDevice>, index: usize) -> Self {
+        let limits = unsafe {
+            render_device
+                .ash()
+                .get_physical_device_properties(render_device.physical_device())
+                .limits
+        };
+        let min_alignment = limits
+            .min_uniform_buffer_offset_alignment
+            .max(limits.min_storage_buffer_offset_alignment)
+            .max(1);
+
+        // Prefer memory the GPU reads at full speed, like SmartBuffer.
+        let memory_property_flags =
+            HostVisibleDeviceMemory::find(&render_device).map(|memory| {
+                vk::MemoryPropertyFlags::DEVICE_LOCAL
+                    | vk::MemoryPropertyFlags::HOST_VISIBLE
+                    | (memory.memory_property_flags
+                        & vk::MemoryPropertyFlags::HOST_COHERENT)
+            });
+
+        Self {
+            blocks: vec![],
+            current_block: 0,
+            min_alignment,
+            memory_property_flags,
+            index,
+            render_device,
+        }
+    }
+
+    /// Allocate room for `count` elements.
+    ///
+    /// Fails with `AllocationError::SizeOverflow` when the size in bytes
+    /// doesn't fit in a usize.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the caller must not use the slice after the arena is reset
+    pub unsafe fn allocate<T: Copy>(
+        &mut self,
+        count: usize,
+    ) -> Result<TransientSlice<T>, GraphicsError> {
+        let size = count
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(AllocationError::SizeOverflow {
+                count,
+                element_size: std::mem::size_of::<T>(),
+            })?
+            .max(1);
+        let alignment = self
+            .min_alignment
+            .max(std::mem::align_of::<T>() as vk::DeviceSize)
+            as usize;
+
+        while self.current_block < self.blocks.len() {
+            let block = &mut self.blocks[self.current_block];
+            let offset = (block.offset + alignment - 1) / alignment * alignment;
+            if size <= block.buffer.len().saturating_sub(offset) {
+                block.offset = offset + size;
+                return Ok(Self::slice(block, offset, count));
+            }
+            self.current_block += 1;
+        }
+
+        let block = self.create_block(size.max(Self::BLOCK_SIZE))?;
+        self.blocks.push(block);
+        self.current_block = self.blocks.len() - 1;
+        let block = &mut self.blocks[self.current_block];
+        block.offset = size;
+        Ok(Self::slice(block, 0, count))
+    }
+
+    /// Make this frame's writes visible to the device. Does nothing when the
+    /// memory is HOST_COHERENT.
+    pub fn flush(&self) -> Result<(), GraphicsError> {
+        for block in &self.blocks {
+            block.buffer.flush_range(0..block.offset)?;
+        }
+        Ok(())
+    }
+
+    /// Free every allocation at once.
+    ///
+    /// Blocks which were created for a single large allocation are freed,
+    /// so a one-off spike doesn't hold on to memory forever.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the device must be finished with every allocation
+    pub unsafe fn reset(&mut self) {
+        self.blocks
+            .retain(|block| block.buffer.len() <= Self::BLOCK_SIZE);
+        for block in &mut self.blocks {
+            block.offset = 0;
+        }
+        self.current_block = 0;
+    }
+}
+
+// Private API
+// -----------
+
+impl TransientArena {
+    /// A slice of `count` elements at `offset` bytes into the block.
+    fn slice<T: Copy>(
+        block: &mut Block,
+        offset: usize,
+        count: usize,
+    ) -> TransientSlice<T> {
+        debug_assert!(offset <= block.buffer.len());
+        // SAFE because the caller keeps `offset` inside the block.
+        let ptr = unsafe { block.buffer.as_mut_ptr().add(offset) } as *mut T;
+        debug_assert!(ptr.align_offset(std::mem::align_of::<T>()) == 0);
+        TransientSlice {
+            buffer: block.buffer.raw(),
+            offset: offset as vk::DeviceSize,
+            len: count,
+            ptr,
+        }
+    }
+
+    /// Create a block with room for at least `size` bytes.
+    unsafe fn create_block(
+        &mut self,
+        size: usize,
+    ) -> Result<Block, GraphicsError> {
+        let buffer = match self.memory_property_flags {
+            Some(memory_property_flags) => {
+                match MappedBuffer::new(
+                    self.render_device.clone(),
+                    size,
+                    Self::USAGE,
+                    memory_property_flags,
+                ) {
+                    Ok(buffer) => buffer,
+                    Err(error) if error.find::<AllocationError>().is_some() => {
+                        // Stop using host-visible device memory once it's
+                        // full rather than failing every frame.
+                        log::debug!(
+                            "Host-visible device-local memory is full, \
+                             transient memory falls back to host memory: {}",
+                            error
+                        );
+                        self.memory_property_flags = None;
+                        MappedBuffer::new_host_visible(
+                            self.render_device.clone(),
+                            size,
+                            Self::USAGE,
+                        )?
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            None => MappedBuffer::new_host_visible(
+                self.render_device.clone(),
+                size,
+                Self::USAGE,
+            )?,
+        };
+        buffer.set_debug_name(format!(
+            "Frame {} Transient Block {}",
+            self.index,
+            self.blocks.len()
+        ));
+        Ok(Block { buffer, offset: 0 })
+    }
+}
@@ -0,0 +1,164 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::{sync::Arc, time::Duration},
+};
+
+/// Measures how long the GPU spends executing each frame's main command
+/// buffer using timestamp queries.
+///
+/// Each frame, call `begin_frame` before recording any commands and
+/// `end_frame` after the last command. Like OcclusionQueries, every frame in
+/// flight has its own query pool and results are collected the next time the
+/// frame is used, so `last_frame_time` is `frame_count` frames old but never
+/// stalls.
+///
+/// Commands recorded into named command buffers are not timed.
+pub struct GpuTimer {
+    pools: Vec<raii::QueryPool>,
+    pool_has_results: Vec<bool>,
+    timestamp_period: f32,
+    last_frame_time: Option<Duration>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl GpuTimer {
+    /// Create query pools for every in-flight frame.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create the query pools
+    /// * `frame_count` - the number of frames in flight
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frame_count: usize,
+    ) -> Result<Self, GraphicsError> {
+        let mut pools = Vec::with_capacity(frame_count);
+        for index in 0..frame_count {
+            let create_info = vk::QueryPoolCreateInfo {
+                query_type: vk::QueryType::TIMESTAMP,
+                query_count: 2,
+                ..Default::default()
+            };
+            let pool =
+                raii::QueryPool::new(render_device.clone(), &create_info)?;
+            pool.set_debug_name(format!("Frame {index} GPU Timer"));
+            pools.push(pool);
+        }
+        let timestamp_period = render_device
+            .ash()
+            .get_physical_device_properties(render_device.physical_device())
+            .limits
+            .timestamp_period;
+        Ok(Self {
+            pools,
+            pool_has_results: vec![false; frame_count],
+            timestamp_period,
+            last_frame_time: None,
+            render_device,
+        })
+    }
+
+    /// Collect the time measured the last time this frame was used, then
+    /// write the frame's starting timestamp.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this must be called once per frame before any other commands are
+    ///     recorded into the frame's command buffer
+    pub unsafe fn begin_frame(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<(), GraphicsError> {
+        let index = frame.frame_index();
+        if self.pool_has_results[index] {
+            self.read_results(index)?;
+        }
+        let device = self.render_device.device();
+        device.cmd_reset_query_pool(
+            frame.command_buffer(),
+            self.pools[index].raw(),
+            0,
+            2,
+        );
+        device.cmd_write_timestamp2(
+            frame.command_buffer(),
+            vk::PipelineStageFlags2::TOP_OF_PIPE,
+            self.pools[index].raw(),
+            0,
+        );
+        Ok(())
+    }
+
+    /// Write the frame's ending timestamp.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - `begin_frame` must have been called for this frame
+    ///   - commands recorded after this call are not timed
+    pub unsafe fn end_frame(&mut self, frame: &Frame) {
+        let index = frame.frame_index();
+        self.render_device.device().cmd_write_timestamp2(
+            frame.command_buffer(),
+            vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+            self.pools[index].raw(),
+            1,
+        );
+        self.pool_has_results[index] = true;
+    }
+
+    /// The GPU time for the most recently collected frame.
+    ///
+    /// None until the first frame's results have been collected.
+    pub fn last_frame_time(&self) -> Option<Duration> {
+        self.last_frame_time
+    }
+}
+
+// Private API
+// -----------
+
+impl GpuTimer {
+    /// Read both timestamps from a frame's query pool.
+    unsafe fn read_results(
+        &mut self,
+        index: usize,
+    ) -> Result<(), GraphicsError> {
+        // Each timestamp is followed by an availability value.
+        let mut data = [[0u64; 2]; 2];
+        let result = self.render_device.device().get_query_pool_results(
+            self.pools[index].raw(),
+            0,
+            2,
+            &mut data,
+            vk::QueryResultFlags::TYPE_64
+                | vk::QueryResultFlags::WITH_AVAILABILITY,
+        );
+        match result {
+            Ok(()) | Err(vk::Result::NOT_READY) => (),
+            Err(err) => return Err(err.into()),
+        }
+        let [[start, start_available], [end, end_available]] = data;
+        if start_available != 0 && end_available != 0 {
+            let ticks = end.saturating_sub(start) as f64;
+            let nanos = ticks * self.timestamp_period as f64;
+            let elapsed = Duration::from_nanos(nanos as u64);
+            self.last_frame_time = Some(elapsed);
+            crate::bench::record_gpu_time(elapsed);
+        }
+        Ok(())
+    }
+}
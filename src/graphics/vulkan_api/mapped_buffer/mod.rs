@@ -0,0 +1,376 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        AllocationError, GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+    std::{ops::Range, sync::Arc},
+};
+
+/// A host-visible buffer which stays mapped for its whole life and is
+/// accessed as a typed slice.
+///
+/// The mapping is checked for alignment when the buffer is created, so
+/// `write` is safe. The slice accessors are unsafe because the contents are
+/// undefined until written and the device can write any bit pattern, which
+/// isn't a valid value of every `T: Copy`. The GPU doesn't participate in
+/// Rust's borrow rules either: the application must still make sure the GPU
+/// isn't reading or writing the buffer while the CPU modifies it, usually by
+/// keeping one MappedBuffer per frame in flight.
+///
+/// When the memory isn't HOST_COHERENT, writes must be made visible to the
+/// device with `flush` or `flush_range` before the commands which read them
+/// are submitted, and device writes must be made visible to the host with
+/// `invalidate` or `invalidate_range` after the commands which wrote them
+/// complete. Both are no-ops for coherent memory, so code which always
+/// calls them works with either kind.
+pub struct MappedBuffer<T: Copy> {
+    len: usize,
+    ptr: *mut T,
+    memory_property_flags: vk::MemoryPropertyFlags,
+    non_coherent_atom_size: vk::DeviceSize,
+    buffer: raii::Buffer,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl<T: Copy> MappedBuffer<T> {
+    /// Create a buffer with room for `len` elements and map it.
+    ///
+    /// The buffer's contents are undefined until they're written.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create the buffer
+    /// * `len` - the number of elements in the buffer
+    /// * `usage` - how the buffer will be used by the device
+    /// * `memory_property_flags` - must include HOST_VISIBLE
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must not be dropped while the GPU is using it
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        len: usize,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<Self, GraphicsError> {
+        if !memory_property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+        {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "A MappedBuffer needs HOST_VISIBLE memory, but got {:?}",
+                memory_property_flags
+            )));
+        }
+
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: (len.max(1) * std::mem::size_of::<T>()) as vk::DeviceSize,
+            usage,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )?;
+        let ptr = buffer.allocation().map(render_device.device())? as *mut T;
+        if ptr.align_offset(std::mem::align_of::<T>()) != 0 {
+            buffer.allocation().unmap(render_device.device())?;
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "The mapped pointer {:?} is not aligned for {}",
+                ptr,
+                std::any::type_name::<T>()
+            )));
+        }
+
+        let non_coherent_atom_size = render_device
+            .ash()
+            .get_physical_device_properties(render_device.physical_device())
+            .limits
+            .non_coherent_atom_size;
+
+        Ok(Self {
+            len,
+            ptr,
+            memory_property_flags,
+            non_coherent_atom_size,
+            buffer,
+            render_device,
+        })
+    }
+
+    /// Create a buffer in host-visible memory, preferring HOST_COHERENT
+    /// memory but falling back to non-coherent memory when the device has
+    /// no coherent memory type or it's out of space.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - see `new`
+    pub unsafe fn new_host_visible(
+        render_device: Arc<RenderDevice>,
+        len: usize,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<Self, GraphicsError> {
+        let coherent = vk::MemoryPropertyFlags::HOST_VISIBLE
+            | vk::MemoryPropertyFlags::HOST_COHERENT;
+        if Self::has_memory_type(&render_device, coherent) {
+            match Self::new(render_device.clone(), len, usage, coherent) {
+                Ok(buffer) => return Ok(buffer),
+                Err(error) if error.find::<AllocationError>().is_some() => {
+                    log::debug!(
+                        "Falling back to non-coherent memory for a \
+                         MappedBuffer: {}",
+                        error
+                    );
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        Self::new(
+            render_device,
+            len,
+            usage,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+    }
+
+    /// The number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true when the buffer has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The buffer's size in bytes.
+    pub fn size_in_bytes(&self) -> vk::DeviceSize {
+        (self.len * std::mem::size_of::<T>()) as vk::DeviceSize
+    }
+
+    /// The underlying buffer.
+    pub fn buffer(&self) -> &raii::Buffer {
+        &self.buffer
+    }
+
+    /// The raw Vulkan buffer handle.
+    pub fn raw(&self) -> vk::Buffer {
+        self.buffer.raw()
+    }
+
+    /// Set the name which shows up in Vulkan debug logs for this resource.
+    pub fn set_debug_name(&self, name: impl Into<String>) {
+        self.buffer.set_debug_name(name);
+    }
+
+    /// Returns true when writes are visible to the device without a flush.
+    pub fn is_coherent(&self) -> bool {
+        self.memory_property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+    }
+
+    /// The buffer's contents.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - every element must hold a valid `T`, written with `write` or by
+    ///     device commands which have finished executing and been
+    ///     invalidated
+    ///   - the device must not be writing the buffer
+    pub unsafe fn as_slice(&self) -> &[T] {
+        std::slice::from_raw_parts(self.ptr, self.len)
+    }
+
+    /// The buffer's contents, for writing.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - every element must hold a valid `T`, the same as `as_slice`
+    ///   - the device must not be reading or writing the buffer
+    pub unsafe fn as_slice_mut(&mut self) -> &mut [T] {
+        std::slice::from_raw_parts_mut(self.ptr, self.len)
+    }
+
+    /// A pointer to the first element of the mapped memory.
+    ///
+    /// The pointer is valid for `len()` elements while the buffer exists.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr
+    }
+
+    /// Copy `data` into the start of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` has more than `len()` elements.
+    pub fn write(&mut self, data: &[T]) {
+        assert!(
+            data.len() <= self.len,
+            "Cannot write {} elements to a MappedBuffer of {}",
+            data.len(),
+            self.len
+        );
+        // SAFE because the pointer is aligned and stays mapped for the life
+        // of the buffer, the range was checked above, and nothing is read
+        // from the possibly uninitialized memory.
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.ptr, data.len());
+        }
+    }
+
+    /// Make host writes to the buffer visible to the device. Does nothing
+    /// when the memory is HOST_COHERENT.
+    pub fn flush(&self) -> Result<(), GraphicsError> {
+        self.flush_range(0..self.len)
+    }
+
+    /// Make host writes to a range of elements visible to the device. Does
+    /// nothing when the memory is HOST_COHERENT.
+    ///
+    /// The range is widened to nonCoherentAtomSize boundaries, so
+    /// neighboring elements may be flushed too.
+    pub fn flush_range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<(), GraphicsError> {
+        if self.is_coherent() || range.is_empty() {
+            return Ok(());
+        }
+        let memory_range = self.mapped_memory_range(range);
+        unsafe {
+            self.render_device
+                .device()
+                .flush_mapped_memory_ranges(&[memory_range])?;
+        }
+        Ok(())
+    }
+
+    /// Make device writes to the buffer visible to the host. Does nothing
+    /// when the memory is HOST_COHERENT.
+    pub fn invalidate(&self) -> Result<(), GraphicsError> {
+        self.invalidate_range(0..self.len)
+    }
+
+    /// Make device writes to a range of elements visible to the host. Does
+    /// nothing when the memory is HOST_COHERENT.
+    ///
+    /// The range is widened to nonCoherentAtomSize boundaries, so
+    /// neighboring elements may be invalidated too. Unflushed host writes
+    /// to those elements are lost.
+    pub fn invalidate_range(
+        &self,
+        range: Range<usize>,
+    ) -> Result<(), GraphicsError> {
+        if self.is_coherent() || range.is_empty() {
+            return Ok(());
+        }
+        let memory_range = self.mapped_memory_range(range);
+        unsafe {
+            self.render_device
+                .device()
+                .invalidate_mapped_memory_ranges(&[memory_range])?;
+        }
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl<T: Copy> MappedBuffer<T> {
+    /// Returns true when the device has a memory type with all of the
+    /// requested properties.
+    unsafe fn has_memory_type(
+        render_device: &RenderDevice,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> bool {
+        let properties =
+            render_device.ash().get_physical_device_memory_properties(
+                render_device.physical_device(),
+            );
+        properties.memory_types[..properties.memory_type_count as usize]
+            .iter()
+            .any(|memory_type| {
+                memory_type.property_flags.contains(memory_property_flags)
+            })
+    }
+
+    /// The memory range which covers a range of elements, widened to
+    /// nonCoherentAtomSize boundaries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the buffer.
+    fn mapped_memory_range(
+        &self,
+        range: Range<usize>,
+    ) -> vk::MappedMemoryRange {
+        assert!(
+            range.end <= self.len,
+            "range {:?} is out of bounds for a MappedBuffer with {} elements",
+            range,
+            self.len
+        );
+        let allocation = self.buffer.allocation();
+        let element_size = std::mem::size_of::<T>() as vk::DeviceSize;
+        let start = allocation.offset_in_bytes()
+            + range.start as vk::DeviceSize * element_size;
+        let end = allocation.offset_in_bytes()
+            + range.end as vk::DeviceSize * element_size;
+
+        let atom = self.non_coherent_atom_size.max(1);
+        let aligned_start = start / atom * atom;
+        let aligned_end = (end + atom - 1) / atom * atom;
+
+        // The aligned end can land past the end of the allocation, and
+        // maybe the memory object. WHOLE_SIZE is always valid.
+        let allocation_end =
+            allocation.offset_in_bytes() + allocation.size_in_bytes();
+        let size = if aligned_end <= allocation_end {
+            aligned_end - aligned_start
+        } else {
+            vk::WHOLE_SIZE
+        };
+
+        vk::MappedMemoryRange {
+            memory: allocation.memory(),
+            offset: aligned_start,
+            size,
+            ..Default::default()
+        }
+    }
+}
+
+impl<T: Copy> Drop for MappedBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // Nothing can be done about a failure here, and the buffer's
+            // memory is freed right after.
+            let _ = self.buffer.allocation().unmap(self.render_device.device());
+        }
+    }
+}
+
+impl<T: Copy> std::fmt::Debug for MappedBuffer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedBuffer")
+            .field("len", &self.len)
+            .field("memory_property_flags", &self.memory_property_flags)
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
@@ -0,0 +1,98 @@
+use {
+    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Commands for drawing with mesh shader pipelines.
+///
+/// The version of Ash used by this project predates VK_EXT_mesh_shader, so
+/// mesh shading is provided by VK_NV_mesh_shader. Shaders should use the
+/// `GL_NV_mesh_shader` GLSL extension and pipelines are built with
+/// `GraphicsPipelineBuilder::mesh_shader` and
+/// `GraphicsPipelineBuilder::task_shader`.
+///
+/// The render device must be created with the extension named by
+/// `MeshShader::extension_name()`. Using mesh shader stages also requires the
+/// `meshShader` (and optionally `taskShader`) device feature from
+/// VkPhysicalDeviceMeshShaderFeaturesNV.
+pub struct MeshShader {
+    loader: ash::extensions::nv::MeshShader,
+    _render_device: Arc<RenderDevice>,
+}
+
+impl MeshShader {
+    /// The name of the device extension required for mesh shading.
+    pub fn extension_name() -> String {
+        ash::extensions::nv::MeshShader::name()
+            .to_owned()
+            .into_string()
+            .unwrap()
+    }
+
+    /// Load the mesh shader extension functions.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the MeshShader must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<Self, GraphicsError> {
+        if !render_device.is_device_extension_enabled(Self::extension_name()) {
+            return Err(GraphicsError::DeviceExtensionNotEnabled(
+                Self::extension_name(),
+            ));
+        }
+        let loader = ash::extensions::nv::MeshShader::new(
+            render_device.ash(),
+            render_device.device(),
+        );
+        Ok(Self {
+            loader,
+            _render_device: render_device,
+        })
+    }
+
+    /// Launch `task_count` task shader workgroups, or mesh shader workgroups
+    /// if the pipeline has no task shader.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - a mesh shader pipeline must be bound inside of a render pass
+    pub unsafe fn cmd_draw_mesh_tasks(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        task_count: u32,
+        first_task: u32,
+    ) {
+        self.loader
+            .cmd_draw_mesh_tasks(command_buffer, task_count, first_task);
+    }
+
+    /// Launch mesh tasks with parameters read from a buffer of
+    /// VkDrawMeshTasksIndirectCommandNV structs.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - a mesh shader pipeline must be bound inside of a render pass
+    ///   - the buffer must be created with INDIRECT_BUFFER usage and must
+    ///     hold at least `draw_count` commands
+    pub unsafe fn cmd_draw_mesh_tasks_indirect(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+    ) {
+        self.loader.cmd_draw_mesh_tasks_indirect(
+            command_buffer,
+            buffer,
+            offset,
+            draw_count,
+            std::mem::size_of::<vk::DrawMeshTasksIndirectCommandNV>() as u32,
+        );
+    }
+}
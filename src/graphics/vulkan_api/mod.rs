@@ -1,18 +1,65 @@
+mod barrier;
 mod bindless_triangles;
 mod command_buffer;
+mod conditional_rendering;
+mod descriptor_allocator;
+mod frame_recorder;
 mod frames_in_flight;
+mod gpu_timer;
+mod mapped_buffer;
+mod mesh_shader;
+mod occlusion_queries;
+mod pipeline_builder;
+mod push_descriptors;
+mod ray_tracing;
+mod readback_buffer;
 mod render_device;
 mod render_pass;
+mod smart_buffer;
+mod split_screen;
 mod swapchain;
 mod texture;
+mod tracked_image;
+mod viewport;
 
 pub mod raii;
+pub(crate) use self::frames_in_flight::FrameSync;
 pub use self::{
-    bindless_triangles::{BindlessTriangles, BindlessVertex},
-    command_buffer::OneTimeSubmitCommandBuffer,
-    frames_in_flight::{Frame, FrameStatus, FramesInFlight},
+    barrier::{Barrier, BarrierAccess, BarrierStage},
+    bindless_triangles::{BindlessObject, BindlessTriangles, BindlessVertex},
+    command_buffer::{
+        AsyncSubmitPool, OneTimeSubmitCommandBuffer, SubmissionTicket,
+    },
+    conditional_rendering::ConditionalRendering,
+    descriptor_allocator::GrowableDescriptorAllocator,
+    frame_recorder::FrameRecorder,
+    frames_in_flight::{
+        Frame, FrameStatus, FramesInFlight, PerFrame, PerSwapchainImage,
+        SwapchainChanges, SwapchainDependent, TransientSlice,
+    },
+    gpu_timer::GpuTimer,
+    mapped_buffer::MappedBuffer,
+    mesh_shader::MeshShader,
+    occlusion_queries::OcclusionQueries,
+    pipeline_builder::{DepthBias, GraphicsPipelineBuilder},
+    push_descriptors::PushDescriptors,
+    ray_tracing::{
+        AccelerationStructure, AccelerationStructureBuilder,
+        AccelerationStructureInstance, HitGroup, RayTracingPipeline,
+        RayTracingPipelineBuilder, ShaderBindingTable, TriangleGeometry,
+    },
+    readback_buffer::ReadbackBuffer,
     render_device::{Queue, RenderDevice},
-    render_pass::ColorPass,
+    render_pass::{
+        ColorAttachmentOps, ColorLoadOp, ColorPass, ColorSubpass, DepthPrePass,
+    },
+    smart_buffer::{HostVisibleDeviceMemory, SmartBuffer, SmartBufferPath},
+    split_screen::{SplitScreen, ViewportRegion},
     swapchain::{Swapchain, SwapchainStatus},
-    texture::{Texture2D, TextureLoader},
+    texture::{
+        read_image_to_rgba8, save_rgba8_png, ExternalImage,
+        ExternalImageHandle, Texture2D, TextureLoader, TextureUploadPath,
+    },
+    tracked_image::{TrackedImage, TrackedLayout},
+    viewport::Viewport,
 };
@@ -0,0 +1,201 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A set of occlusion queries with a separate query pool for every frame in
+/// flight.
+///
+/// Each frame, call `begin_frame` before the render pass starts, then wrap
+/// draws with `begin_query` and `end_query`. Results become available when
+/// the frame's fence signals, which FramesInFlight waits on before handing
+/// out the frame again. At that point `begin_frame` copies the results so
+/// they can be read with `samples_passed` without stalling.
+///
+/// Results are therefore `frame_count` frames old.
+pub struct OcclusionQueries {
+    query_count: u32,
+    pools: Vec<raii::QueryPool>,
+    pool_has_results: Vec<bool>,
+    samples_passed: Vec<Option<u64>>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl OcclusionQueries {
+    /// Create query pools for every in-flight frame.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create the query pools
+    /// * `frame_count` - the number of frames in flight
+    /// * `query_count` - the number of queries available each frame
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frame_count: usize,
+        query_count: u32,
+    ) -> Result<Self, GraphicsError> {
+        let mut pools = Vec::with_capacity(frame_count);
+        for index in 0..frame_count {
+            let create_info = vk::QueryPoolCreateInfo {
+                query_type: vk::QueryType::OCCLUSION,
+                query_count,
+                ..Default::default()
+            };
+            let pool =
+                raii::QueryPool::new(render_device.clone(), &create_info)?;
+            pool.set_debug_name(format!("Frame {index} Occlusion Queries"));
+            pools.push(pool);
+        }
+        Ok(Self {
+            query_count,
+            pools,
+            pool_has_results: vec![false; frame_count],
+            samples_passed: vec![None; query_count as usize],
+            render_device,
+        })
+    }
+
+    /// The number of queries available each frame.
+    pub fn query_count(&self) -> u32 {
+        self.query_count
+    }
+
+    /// Collect the results written the last time this frame's query pool was
+    /// used, then reset the pool for new queries.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this must be called once per frame before any queries are started
+    ///   - this must be called outside of a render pass
+    pub unsafe fn begin_frame(
+        &mut self,
+        frame: &Frame,
+    ) -> Result<(), GraphicsError> {
+        let index = frame.frame_index();
+        if self.pool_has_results[index] {
+            self.read_results(index)?;
+        }
+        self.render_device.device().cmd_reset_query_pool(
+            frame.command_buffer(),
+            self.pools[index].raw(),
+            0,
+            self.query_count,
+        );
+        self.pool_has_results[index] = true;
+        Ok(())
+    }
+
+    /// Begin an occlusion query.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `query` - the query index in the range [0, query_count)
+    /// * `precise` - when true, the query counts every sample which passes.
+    ///   Otherwise the result is only guaranteed to be zero or non-zero, which
+    ///   can be faster on some hardware.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - `begin_frame` must have been called for this frame
+    ///   - each query can only be used once per frame
+    ///   - precise queries require the `occlusionQueryPrecise` feature
+    pub unsafe fn begin_query(&self, frame: &Frame, query: u32, precise: bool) {
+        let flags = if precise {
+            vk::QueryControlFlags::PRECISE
+        } else {
+            vk::QueryControlFlags::empty()
+        };
+        self.render_device.device().cmd_begin_query(
+            frame.command_buffer(),
+            self.pools[frame.frame_index()].raw(),
+            query,
+            flags,
+        );
+    }
+
+    /// End an occlusion query started with `begin_query`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the query must be started in the same subpass
+    pub unsafe fn end_query(&self, frame: &Frame, query: u32) {
+        self.render_device.device().cmd_end_query(
+            frame.command_buffer(),
+            self.pools[frame.frame_index()].raw(),
+            query,
+        );
+    }
+
+    /// The number of samples which passed the depth and stencil tests the
+    /// last time the query's results were collected.
+    ///
+    /// None when the query has no result yet, e.g. in the first few frames
+    /// or if the query wasn't used.
+    pub fn samples_passed(&self, query: u32) -> Option<u64> {
+        self.samples_passed[query as usize]
+    }
+
+    /// Returns true when any samples passed for the query.
+    pub fn is_visible(&self, query: u32) -> Option<bool> {
+        self.samples_passed(query).map(|samples| samples > 0)
+    }
+
+    /// The raw query pool used by the given frame.
+    pub fn query_pool(&self, frame: &Frame) -> vk::QueryPool {
+        self.pools[frame.frame_index()].raw()
+    }
+}
+
+// Private API
+// -----------
+
+impl OcclusionQueries {
+    /// Read every available result from a frame's query pool.
+    ///
+    /// Queries which weren't used in the frame have no availability bit and
+    /// keep their previous results.
+    unsafe fn read_results(
+        &mut self,
+        index: usize,
+    ) -> Result<(), GraphicsError> {
+        // Each result is followed by an availability value.
+        let mut data = vec![[0u64; 2]; self.query_count as usize];
+        let result = self.render_device.device().get_query_pool_results(
+            self.pools[index].raw(),
+            0,
+            self.query_count,
+            &mut data,
+            vk::QueryResultFlags::TYPE_64
+                | vk::QueryResultFlags::WITH_AVAILABILITY,
+        );
+        match result {
+            // NOT_READY just means some queries were not used this frame.
+            Ok(()) | Err(vk::Result::NOT_READY) => (),
+            Err(err) => return Err(err.into()),
+        }
+        for (samples_passed, [value, available]) in
+            self.samples_passed.iter_mut().zip(data)
+        {
+            if available != 0 {
+                *samples_passed = Some(value);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::{ffi::CString, sync::Arc},
+};
+
+/// A utility for building graphics pipelines without repeating all of the
+/// Vulkan fixed-function boilerplate.
+///
+/// Viewport and scissor state is always dynamic, so pipelines built with this
+/// builder do not need to be rebuilt when the swapchain is resized.
+#[derive(Debug, Clone)]
+pub struct GraphicsPipelineBuilder<'a> {
+    stages: Vec<(vk::ShaderStageFlags, &'a [u8])>,
+    vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    topology: vk::PrimitiveTopology,
+    polygon_mode: vk::PolygonMode,
+    cull_mode: vk::CullModeFlags,
+    front_face: vk::FrontFace,
+    color_blend_attachments: Option<Vec<vk::PipelineColorBlendAttachmentState>>,
+    depth_test_enable: bool,
+    depth_write_enable: bool,
+    depth_compare_op: vk::CompareOp,
+    depth_bias: Option<DepthBias>,
+    samples: vk::SampleCountFlags,
+    subpass: u32,
+}
+
+/// Depth bias parameters applied by the rasterizer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DepthBias {
+    /// A constant depth value added to each fragment.
+    pub constant_factor: f32,
+
+    /// The maximum (or minimum) depth bias of a fragment.
+    pub clamp: f32,
+
+    /// A scalar applied to a fragment's slope in depth bias calculations.
+    pub slope_factor: f32,
+}
+
+// Public API
+// ----------
+
+impl<'a> GraphicsPipelineBuilder<'a> {
+    /// Create a new builder with defaults matching the pipelines used by the
+    /// examples: triangle lists, no culling, and a single alpha-blended color
+    /// attachment.
+    pub fn new() -> Self {
+        Self {
+            stages: vec![],
+            vertex_bindings: vec![],
+            vertex_attributes: vec![],
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::NONE,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            color_blend_attachments: None,
+            depth_test_enable: false,
+            depth_write_enable: false,
+            depth_compare_op: vk::CompareOp::LESS,
+            depth_bias: None,
+            samples: vk::SampleCountFlags::TYPE_1,
+            subpass: 0,
+        }
+    }
+
+    /// A color blend attachment state which does standard alpha blending.
+    pub fn alpha_blend_attachment() -> vk::PipelineColorBlendAttachmentState {
+        vk::PipelineColorBlendAttachmentState {
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+            blend_enable: vk::TRUE,
+            src_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+            dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE,
+            dst_alpha_blend_factor: vk::BlendFactor::ZERO,
+            alpha_blend_op: vk::BlendOp::ADD,
+        }
+    }
+
+    /// A color blend attachment state which overwrites the attachment.
+    pub fn opaque_attachment() -> vk::PipelineColorBlendAttachmentState {
+        vk::PipelineColorBlendAttachmentState {
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+            blend_enable: vk::FALSE,
+            ..Default::default()
+        }
+    }
+
+    /// A push constant range with room for `count` buffer device addresses,
+    /// starting at `offset` bytes.
+    ///
+    /// Use with `raii::PipelineLayout::cmd_push_device_addresses` to pass
+    /// buffers to shaders without descriptors.
+    pub fn device_address_push_constant_range(
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        count: u32,
+    ) -> vk::PushConstantRange {
+        vk::PushConstantRange {
+            stage_flags,
+            offset,
+            size: count * std::mem::size_of::<vk::DeviceAddress>() as u32,
+        }
+    }
+
+    /// Use the given SPIRV bytes for the vertex shader stage.
+    pub fn vertex_shader(self, source_bytes: &'a [u8]) -> Self {
+        self.shader_stage(vk::ShaderStageFlags::VERTEX, source_bytes)
+    }
+
+    /// Use the given SPIRV bytes for the fragment shader stage.
+    pub fn fragment_shader(self, source_bytes: &'a [u8]) -> Self {
+        self.shader_stage(vk::ShaderStageFlags::FRAGMENT, source_bytes)
+    }
+
+    /// Use the given SPIRV bytes for the task shader stage.
+    ///
+    /// Task shaders are only valid in mesh shader pipelines. See MeshShader.
+    pub fn task_shader(self, source_bytes: &'a [u8]) -> Self {
+        self.shader_stage(vk::ShaderStageFlags::TASK_NV, source_bytes)
+    }
+
+    /// Use the given SPIRV bytes for the mesh shader stage.
+    ///
+    /// Pipelines with a mesh shader have no vertex input or input assembly
+    /// state, so the topology is ignored. See MeshShader.
+    pub fn mesh_shader(self, source_bytes: &'a [u8]) -> Self {
+        self.shader_stage(vk::ShaderStageFlags::MESH_NV, source_bytes)
+    }
+
+    /// Use the given SPIRV bytes for an arbitrary shader stage. Every shader
+    /// must use `main` as the entrypoint.
+    pub fn shader_stage(
+        mut self,
+        stage: vk::ShaderStageFlags,
+        source_bytes: &'a [u8],
+    ) -> Self {
+        self.stages.push((stage, source_bytes));
+        self
+    }
+
+    /// Set the primitive topology used by the input assembler.
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Set the polygon mode used by the rasterizer.
+    pub fn polygon_mode(mut self, polygon_mode: vk::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    /// Set the cull mode and front face used by the rasterizer.
+    pub fn cull_mode(
+        mut self,
+        cull_mode: vk::CullModeFlags,
+        front_face: vk::FrontFace,
+    ) -> Self {
+        self.cull_mode = cull_mode;
+        self.front_face = front_face;
+        self
+    }
+
+    /// Add a color blend attachment state. There must be one blend attachment
+    /// for every color attachment in the targeted subpass.
+    ///
+    /// If no attachments are added then the pipeline uses a single
+    /// alpha-blended attachment.
+    pub fn color_blend_attachment(
+        mut self,
+        attachment: vk::PipelineColorBlendAttachmentState,
+    ) -> Self {
+        self.color_blend_attachments
+            .get_or_insert_with(Vec::new)
+            .push(attachment);
+        self
+    }
+
+    /// Configure the pipeline for a subpass with no color attachments, such
+    /// as a depth pre-pass. The fragment shader can be omitted entirely.
+    pub fn depth_only(mut self) -> Self {
+        self.color_blend_attachments = Some(vec![]);
+        self
+    }
+
+    /// Enable or disable depth testing and depth writes. Depth testing uses
+    /// `LESS` as the compare op unless changed with `depth_compare_op`.
+    pub fn depth_test(mut self, enabled: bool) -> Self {
+        self.depth_test_enable = enabled;
+        self.depth_write_enable = enabled;
+        self
+    }
+
+    /// Enable or disable depth writes independently of depth testing.
+    ///
+    /// Pipelines which render after a depth pre-pass typically keep depth
+    /// testing enabled but disable writes.
+    pub fn depth_write(mut self, enabled: bool) -> Self {
+        self.depth_write_enable = enabled;
+        self
+    }
+
+    /// Set the compare op used for depth testing.
+    ///
+    /// Pipelines which render after a depth pre-pass should use `EQUAL` or
+    /// `LESS_OR_EQUAL` so only the nearest fragments are shaded.
+    pub fn depth_compare_op(mut self, compare_op: vk::CompareOp) -> Self {
+        self.depth_compare_op = compare_op;
+        self
+    }
+
+    /// Enable depth bias in the rasterizer. Useful for decals and shadow maps
+    /// to avoid z-fighting.
+    pub fn depth_bias(mut self, depth_bias: DepthBias) -> Self {
+        self.depth_bias = Some(depth_bias);
+        self
+    }
+
+    /// Read vertices from vertex buffers with fixed-function vertex input.
+    ///
+    /// By default pipelines have no vertex input and shaders fetch vertices
+    /// from storage buffers.
+    pub fn vertex_input(
+        mut self,
+        bindings: &[vk::VertexInputBindingDescription],
+        attributes: &[vk::VertexInputAttributeDescription],
+    ) -> Self {
+        self.vertex_bindings = bindings.to_vec();
+        self.vertex_attributes = attributes.to_vec();
+        self
+    }
+
+    /// Set the subpass index the pipeline will be used with.
+    pub fn subpass(mut self, subpass: u32) -> Self {
+        self.subpass = subpass;
+        self
+    }
+
+    /// Set the rasterization sample count. It must match the sample count of
+    /// the targeted subpass's attachments. Defaults to a single sample.
+    pub fn samples(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Create the graphics pipeline.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create Vulkan resources
+    /// * `layout` - the pipeline layout used by the pipeline
+    /// * `render_pass` - the render pass the pipeline will be compatible with
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the pipeline must be destroyed before the render device
+    ///   - the pipeline layout must live at least as long as the pipeline
+    #[tracing::instrument(skip_all)]
+    pub unsafe fn build(
+        &self,
+        render_device: Arc<RenderDevice>,
+        layout: &raii::PipelineLayout,
+        render_pass: &raii::RenderPass,
+    ) -> Result<raii::Pipeline, GraphicsError> {
+        let mut shader_modules = Vec::with_capacity(self.stages.len());
+        for (_, source_bytes) in &self.stages {
+            shader_modules.push(raii::ShaderModule::new_from_bytes(
+                render_device.clone(),
+                source_bytes,
+            )?);
+        }
+
+        let shader_entry_name = CString::new("main").unwrap();
+        let stages = self
+            .stages
+            .iter()
+            .zip(shader_modules.iter())
+            .map(|((stage, _), module)| vk::PipelineShaderStageCreateInfo {
+                module: module.raw(),
+                stage: *stage,
+                p_name: shader_entry_name.as_ptr(),
+                ..Default::default()
+            })
+            .collect::<Vec<vk::PipelineShaderStageCreateInfo>>();
+
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo {
+            vertex_binding_description_count: self.vertex_bindings.len() as u32,
+            p_vertex_binding_descriptions: self.vertex_bindings.as_ptr(),
+            vertex_attribute_description_count: self.vertex_attributes.len()
+                as u32,
+            p_vertex_attribute_descriptions: self.vertex_attributes.as_ptr(),
+            ..Default::default()
+        };
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo {
+            topology: self.topology,
+            primitive_restart_enable: vk::FALSE,
+            ..Default::default()
+        };
+        let depth_bias = self.depth_bias.unwrap_or(DepthBias {
+            constant_factor: 0.0,
+            clamp: 0.0,
+            slope_factor: 0.0,
+        });
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo {
+            depth_clamp_enable: vk::FALSE,
+            rasterizer_discard_enable: vk::FALSE,
+            polygon_mode: self.polygon_mode,
+            line_width: 1.0,
+            cull_mode: self.cull_mode,
+            front_face: self.front_face,
+            depth_bias_enable: self.depth_bias.is_some() as vk::Bool32,
+            depth_bias_constant_factor: depth_bias.constant_factor,
+            depth_bias_clamp: depth_bias.clamp,
+            depth_bias_slope_factor: depth_bias.slope_factor,
+            ..Default::default()
+        };
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo {
+            sample_shading_enable: vk::FALSE,
+            rasterization_samples: self.samples,
+            ..Default::default()
+        };
+        let color_blend_attachment_states = self
+            .color_blend_attachments
+            .clone()
+            .unwrap_or_else(|| vec![Self::alpha_blend_attachment()]);
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
+            attachment_count: color_blend_attachment_states.len() as u32,
+            p_attachments: if color_blend_attachment_states.is_empty() {
+                std::ptr::null()
+            } else {
+                color_blend_attachment_states.as_ptr()
+            },
+            ..Default::default()
+        };
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo {
+            depth_test_enable: self.depth_test_enable as vk::Bool32,
+            depth_write_enable: self.depth_write_enable as vk::Bool32,
+            depth_compare_op: self.depth_compare_op,
+            depth_bounds_test_enable: vk::FALSE,
+            stencil_test_enable: vk::FALSE,
+            min_depth_bounds: 0.0,
+            max_depth_bounds: 1.0,
+            ..Default::default()
+        };
+        let viewports = [vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }];
+        let scissors = [vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+        }];
+        let viewport_state = vk::PipelineViewportStateCreateInfo {
+            viewport_count: viewports.len() as u32,
+            p_viewports: viewports.as_ptr(),
+            scissor_count: scissors.len() as u32,
+            p_scissors: scissors.as_ptr(),
+            ..Default::default()
+        };
+        let dynamic_states =
+            [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo {
+            dynamic_state_count: dynamic_states.len() as u32,
+            p_dynamic_states: dynamic_states.as_ptr(),
+            ..Default::default()
+        };
+        let is_mesh_pipeline = self
+            .stages
+            .iter()
+            .any(|(stage, _)| *stage == vk::ShaderStageFlags::MESH_NV);
+        let create_info = vk::GraphicsPipelineCreateInfo {
+            stage_count: stages.len() as u32,
+            p_stages: stages.as_ptr(),
+            p_vertex_input_state: if is_mesh_pipeline {
+                std::ptr::null()
+            } else {
+                &vertex_input_state
+            },
+            p_input_assembly_state: if is_mesh_pipeline {
+                std::ptr::null()
+            } else {
+                &input_assembly
+            },
+            p_dynamic_state: &dynamic_state,
+            p_rasterization_state: &rasterization_state,
+            p_multisample_state: &multisample_state,
+            p_color_blend_state: &color_blend_state,
+            p_tessellation_state: std::ptr::null(),
+            p_viewport_state: &viewport_state,
+            p_depth_stencil_state: &depth_stencil_state,
+            render_pass: render_pass.raw(),
+            layout: layout.raw(),
+            subpass: self.subpass,
+
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: 0,
+            ..Default::default()
+        };
+        raii::Pipeline::new_graphics_pipeline(render_device, create_info)
+    }
+}
+
+impl<'a> Default for GraphicsPipelineBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
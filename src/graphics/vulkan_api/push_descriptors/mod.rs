@@ -0,0 +1,181 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::{extensions::khr, vk},
+    std::sync::Arc,
+};
+
+/// Commands for VK_KHR_push_descriptor, with a fallback for devices which
+/// don't support it.
+///
+/// Push descriptors are written directly into the command buffer, so simple
+/// draws, like a single textured quad, don't need a descriptor pool or any
+/// descriptor set lifetime tracking.
+///
+/// When the render device was created without the extension named by
+/// `PushDescriptors::extension_name()`, the same calls allocate a transient
+/// descriptor set from the frame, write it, and bind it. Callers don't need
+/// to handle both paths.
+///
+/// Push descriptor set layouts are limited to `maxPushDescriptors`
+/// descriptors, which is at least 32.
+pub struct PushDescriptors {
+    loader: Option<khr::PushDescriptor>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl PushDescriptors {
+    /// The name of the device extension used for push descriptors.
+    pub fn extension_name() -> String {
+        khr::PushDescriptor::name()
+            .to_owned()
+            .into_string()
+            .unwrap()
+    }
+
+    /// Load the push descriptor extension functions if the extension is
+    /// enabled. Otherwise all commands use transient descriptor sets.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    pub unsafe fn new(render_device: Arc<RenderDevice>) -> Self {
+        let loader = if render_device
+            .is_device_extension_enabled(Self::extension_name())
+        {
+            Some(khr::PushDescriptor::new(
+                render_device.ash(),
+                render_device.device(),
+            ))
+        } else {
+            log::debug!(
+                "{} is not enabled, falling back to transient descriptor sets",
+                Self::extension_name()
+            );
+            None
+        };
+        Self {
+            loader,
+            render_device,
+        }
+    }
+
+    /// Returns true when descriptors are pushed directly into the command
+    /// buffer, false when transient descriptor sets are used instead.
+    pub fn is_push_supported(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    /// The descriptor set layout flags for sets written with
+    /// `cmd_push_descriptor_set`.
+    ///
+    /// PUSH_DESCRIPTOR_KHR when push descriptors are supported, otherwise
+    /// empty.
+    pub fn descriptor_set_layout_flags(
+        &self,
+    ) -> vk::DescriptorSetLayoutCreateFlags {
+        if self.is_push_supported() {
+            vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR
+        } else {
+            vk::DescriptorSetLayoutCreateFlags::empty()
+        }
+    }
+
+    /// Create a descriptor set layout which can be used with
+    /// `cmd_push_descriptor_set`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the layout must be dropped before the render device
+    pub unsafe fn create_descriptor_set_layout(
+        &self,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) -> Result<raii::DescriptorSetLayout, GraphicsError> {
+        raii::DescriptorSetLayout::new_with_bindings_and_flags(
+            self.render_device.clone(),
+            bindings,
+            self.descriptor_set_layout_flags(),
+        )
+    }
+
+    /// Write descriptors and bind them to a set index for subsequent draws or
+    /// dispatches in the frame's command buffer.
+    ///
+    /// The `dst_set` field of each write is ignored.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `bind_point` - the pipeline bind point, e.g. GRAPHICS or COMPUTE
+    /// * `pipeline_layout` - the layout of the pipeline which will use the
+    ///   descriptors
+    /// * `set` - the set index being written
+    /// * `set_layout` - the layout of the set, which must have been created
+    ///   with `create_descriptor_set_layout`
+    /// * `writes` - the descriptors to write
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - every resource referenced by the writes must stay alive until the
+    ///     frame's commands have finished executing
+    ///   - `set_layout` must be the layout at index `set` in the pipeline
+    ///     layout
+    pub unsafe fn cmd_push_descriptor_set(
+        &self,
+        frame: &mut Frame,
+        bind_point: vk::PipelineBindPoint,
+        pipeline_layout: &raii::PipelineLayout,
+        set: u32,
+        set_layout: &raii::DescriptorSetLayout,
+        writes: &[vk::WriteDescriptorSet],
+    ) -> Result<(), GraphicsError> {
+        if let Some(loader) = &self.loader {
+            loader.cmd_push_descriptor_set(
+                frame.command_buffer(),
+                bind_point,
+                pipeline_layout.raw(),
+                set,
+                writes,
+            );
+            return Ok(());
+        }
+
+        let descriptor_set =
+            frame.allocate_transient_descriptor_set(set_layout)?;
+        let writes: Vec<vk::WriteDescriptorSet> = writes
+            .iter()
+            .map(|write| vk::WriteDescriptorSet {
+                dst_set: descriptor_set,
+                ..*write
+            })
+            .collect();
+        self.render_device
+            .device()
+            .update_descriptor_sets(&writes, &[]);
+        self.render_device.device().cmd_bind_descriptor_sets(
+            frame.command_buffer(),
+            bind_point,
+            pipeline_layout.raw(),
+            set,
+            &[descriptor_set],
+            &[],
+        );
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for PushDescriptors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PushDescriptors")
+            .field("is_push_supported", &self.is_push_supported())
+            .finish()
+    }
+}
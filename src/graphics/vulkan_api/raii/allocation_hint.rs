@@ -0,0 +1,29 @@
+use {super::LinearArena, std::sync::Arc};
+
+/// Where a Buffer or Image gets its memory.
+///
+/// `Buffer::new` and `Image::new` always use Pooled. Use the `new_with_hint`
+/// constructors to pick something else.
+#[derive(Debug, Clone, Default)]
+pub enum AllocationHint {
+    /// Sub-allocate from the device allocator's pools. Best for long-lived
+    /// resources of any size.
+    #[default]
+    Pooled,
+
+    /// Give the resource its own VkDeviceMemory with
+    /// VkMemoryDedicatedAllocateInfo. Some drivers render faster to
+    /// dedicated render targets, and large resources don't fragment the
+    /// pools.
+    Dedicated,
+
+    /// The resource only lives within a frame or a render pass. Images are
+    /// created with TRANSIENT_ATTACHMENT usage in LAZILY_ALLOCATED memory
+    /// when the device has it, so tile-based GPUs may never back them with
+    /// real memory. Buffers have no transient memory, so they're pooled.
+    Transient,
+
+    /// Bind the buffer into a linear arena which is reset all at once, for
+    /// many small per-frame buffers. Only valid for buffers.
+    Linear(Arc<LinearArena>),
+}
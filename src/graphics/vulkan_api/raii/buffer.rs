@@ -1,14 +1,45 @@
 use {
-    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    super::{device_memory, AllocationHint, LinearArena},
+    crate::graphics::{
+        vulkan_api::RenderDevice, AllocationError, GraphicsError,
+    },
+    anyhow::anyhow,
     ash::vk,
     ccthw_ash_allocator::Allocation,
     std::sync::Arc,
 };
 
+/// The memory which backs a Buffer.
+///
+/// Provides the same accessors as the device allocator's Allocation no
+/// matter where the memory came from.
+pub struct BufferAllocation {
+    memory: BufferMemory,
+}
+
+enum BufferMemory {
+    /// Memory from the device allocator.
+    Pooled(Allocation),
+
+    /// A dedicated allocation. Mapped for its whole life when HOST_VISIBLE.
+    Dedicated {
+        memory: vk::DeviceMemory,
+        size_in_bytes: vk::DeviceSize,
+        mapped: *mut std::ffi::c_void,
+    },
+
+    /// A range of a linear arena.
+    Linear {
+        arena: Arc<LinearArena>,
+        offset_in_bytes: vk::DeviceSize,
+        size_in_bytes: vk::DeviceSize,
+    },
+}
+
 /// RAII Vulkan Buffer.
 pub struct Buffer {
     buffer: vk::Buffer,
-    allocation: Allocation,
+    allocation: BufferAllocation,
     render_device: Arc<RenderDevice>,
 }
 
@@ -27,15 +58,80 @@ impl Buffer {
         let (buffer, allocation) = unsafe {
             render_device
                 .memory()
-                .allocate_buffer(create_info, memory_property_flags)?
+                .allocate_buffer(create_info, memory_property_flags)
+                .map_err(|source| AllocationError::Buffer {
+                    size: create_info.size,
+                    usage: create_info.usage,
+                    memory_properties: memory_property_flags,
+                    source,
+                })?
         };
         Ok(Self {
             buffer,
-            allocation,
+            allocation: BufferAllocation {
+                memory: BufferMemory::Pooled(allocation),
+            },
             render_device,
         })
     }
 
+    /// Create a new Vulkan buffer with memory from the place suggested by
+    /// `hint`.
+    ///
+    /// Transient buffers are pooled because there is no transient buffer
+    /// memory. Linear buffers take their memory properties from the arena
+    /// and ignore `memory_property_flags`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - buffers must be destroyed before the Vulkan device is dropped.
+    ///   - linear buffers must not be used after their arena is reset
+    pub unsafe fn new_with_hint(
+        render_device: Arc<RenderDevice>,
+        create_info: &vk::BufferCreateInfo,
+        memory_property_flags: vk::MemoryPropertyFlags,
+        hint: AllocationHint,
+    ) -> Result<Self, GraphicsError> {
+        match hint {
+            AllocationHint::Pooled | AllocationHint::Transient => {
+                Self::new(render_device, create_info, memory_property_flags)
+            }
+            AllocationHint::Dedicated => Self::new_dedicated(
+                render_device,
+                create_info,
+                memory_property_flags,
+            ),
+            AllocationHint::Linear(arena) => {
+                Self::new_linear(render_device, create_info, arena)
+            }
+        }
+    }
+
+    /// Create a new Vulkan buffer which can be referenced by its device
+    /// address.
+    ///
+    /// SHADER_DEVICE_ADDRESS is added to the create info's usage flags.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - buffers must be destroyed before the Vulkan device is dropped.
+    ///   - the bufferDeviceAddress device feature must be enabled, see
+    ///     `RenderDevice::is_buffer_device_address_supported`
+    pub unsafe fn new_with_device_address(
+        render_device: Arc<RenderDevice>,
+        create_info: &vk::BufferCreateInfo,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<Self, GraphicsError> {
+        let create_info = vk::BufferCreateInfo {
+            usage: create_info.usage
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            ..*create_info
+        };
+        Self::new(render_device, &create_info, memory_property_flags)
+    }
+
     /// Set the name which shows up in Vulkan debug logs for this resource.
     pub fn set_debug_name(&self, name: impl Into<String>) {
         self.render_device.set_debug_name(
@@ -46,22 +142,259 @@ impl Buffer {
     }
 
     /// Get the backing memory allocation for the Buffer.
-    pub fn allocation(&self) -> &Allocation {
+    pub fn allocation(&self) -> &BufferAllocation {
         &self.allocation
     }
 
+    /// Get the buffer's device address.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the buffer must be created with SHADER_DEVICE_ADDRESS usage and the
+    ///     bufferDeviceAddress device feature must be enabled
+    pub unsafe fn device_address(&self) -> vk::DeviceAddress {
+        self.render_device.device().get_buffer_device_address(
+            &vk::BufferDeviceAddressInfo {
+                buffer: self.buffer,
+                ..Default::default()
+            },
+        )
+    }
+
     /// Get the raw Vulkan command pool handle.
     pub fn raw(&self) -> vk::Buffer {
         self.buffer
     }
 }
 
+// Private API
+// -----------
+
+impl Buffer {
+    /// Create a buffer with its own dedicated memory.
+    unsafe fn new_dedicated(
+        render_device: Arc<RenderDevice>,
+        create_info: &vk::BufferCreateInfo,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<Self, GraphicsError> {
+        let device = render_device.device();
+        let buffer = device.create_buffer(create_info, None)?;
+        let requirements = device.get_buffer_memory_requirements(buffer);
+        let memory = match device_memory::allocate_memory(
+            &render_device,
+            &requirements,
+            memory_property_flags,
+            device_memory::allocate_flags_for(create_info.usage),
+            Some(&vk::MemoryDedicatedAllocateInfo {
+                buffer,
+                ..Default::default()
+            }),
+        ) {
+            Ok(memory) => memory,
+            Err(error) => {
+                device.destroy_buffer(buffer, None);
+                return Err(error);
+            }
+        };
+        let mapped = device
+            .bind_buffer_memory(buffer, memory, 0)
+            .map_err(GraphicsError::from)
+            .and_then(|_| {
+                device_memory::map_if_host_visible(
+                    &render_device,
+                    memory,
+                    memory_property_flags,
+                )
+            });
+        let mapped = match mapped {
+            Ok(mapped) => mapped,
+            Err(error) => {
+                device.destroy_buffer(buffer, None);
+                device.free_memory(memory, None);
+                return Err(error);
+            }
+        };
+        Ok(Self {
+            buffer,
+            allocation: BufferAllocation {
+                memory: BufferMemory::Dedicated {
+                    memory,
+                    size_in_bytes: requirements.size,
+                    mapped,
+                },
+            },
+            render_device,
+        })
+    }
+
+    /// Create a buffer which is bound into a linear arena.
+    unsafe fn new_linear(
+        render_device: Arc<RenderDevice>,
+        create_info: &vk::BufferCreateInfo,
+        arena: Arc<LinearArena>,
+    ) -> Result<Self, GraphicsError> {
+        let device = render_device.device();
+        let buffer = device.create_buffer(create_info, None)?;
+        let offset_in_bytes = match arena.bind_buffer(buffer) {
+            Ok(offset_in_bytes) => offset_in_bytes,
+            Err(error) => {
+                device.destroy_buffer(buffer, None);
+                return Err(error);
+            }
+        };
+        Ok(Self {
+            buffer,
+            allocation: BufferAllocation {
+                memory: BufferMemory::Linear {
+                    arena,
+                    offset_in_bytes,
+                    size_in_bytes: create_info.size,
+                },
+            },
+            render_device,
+        })
+    }
+}
+
 impl Drop for Buffer {
     fn drop(&mut self) {
         unsafe {
-            self.render_device
-                .memory()
-                .free_buffer(self.buffer, self.allocation.clone());
+            match &self.allocation.memory {
+                BufferMemory::Pooled(allocation) => {
+                    self.render_device
+                        .memory()
+                        .free_buffer(self.buffer, allocation.clone());
+                }
+                BufferMemory::Dedicated { memory, .. } => {
+                    // Freeing the memory implicitly unmaps it.
+                    let device = self.render_device.device();
+                    device.destroy_buffer(self.buffer, None);
+                    device.free_memory(*memory, None);
+                }
+                BufferMemory::Linear { .. } => {
+                    // The arena owns the memory.
+                    self.render_device
+                        .device()
+                        .destroy_buffer(self.buffer, None);
+                }
+            }
+        }
+    }
+}
+
+impl BufferAllocation {
+    /// The device memory which backs the buffer.
+    pub fn memory(&self) -> vk::DeviceMemory {
+        match &self.memory {
+            BufferMemory::Pooled(allocation) => allocation.memory(),
+            BufferMemory::Dedicated { memory, .. } => *memory,
+            BufferMemory::Linear { arena, .. } => arena.memory(),
+        }
+    }
+
+    /// The buffer's offset into `memory()`.
+    pub fn offset_in_bytes(&self) -> vk::DeviceSize {
+        match &self.memory {
+            BufferMemory::Pooled(allocation) => allocation.offset_in_bytes(),
+            BufferMemory::Dedicated { .. } => 0,
+            BufferMemory::Linear {
+                offset_in_bytes, ..
+            } => *offset_in_bytes,
+        }
+    }
+
+    /// The size of the buffer's memory in bytes.
+    pub fn size_in_bytes(&self) -> vk::DeviceSize {
+        match &self.memory {
+            BufferMemory::Pooled(allocation) => allocation.size_in_bytes(),
+            BufferMemory::Dedicated { size_in_bytes, .. }
+            | BufferMemory::Linear { size_in_bytes, .. } => *size_in_bytes,
+        }
+    }
+
+    /// Returns the allocator's Allocation when the memory is pooled.
+    pub fn pooled(&self) -> Option<&Allocation> {
+        match &self.memory {
+            BufferMemory::Pooled(allocation) => Some(allocation),
+            _ => None,
+        }
+    }
+
+    /// Get a pointer to the start of the buffer's memory.
+    ///
+    /// Pooled memory must be unmapped once for each map. Dedicated and
+    /// linear memory stays mapped, so `unmap` does nothing.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the memory must be HOST_VISIBLE
+    pub unsafe fn map(
+        &self,
+        device: &ash::Device,
+    ) -> Result<*mut std::ffi::c_void, GraphicsError> {
+        let ptr = match &self.memory {
+            BufferMemory::Pooled(allocation) => {
+                allocation.map(device)? as *mut std::ffi::c_void
+            }
+            BufferMemory::Dedicated { mapped, .. } => *mapped,
+            BufferMemory::Linear {
+                arena,
+                offset_in_bytes,
+                ..
+            } => arena.mapped_ptr(*offset_in_bytes),
+        };
+        if ptr.is_null() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "Unable to map a buffer which isn't in HOST_VISIBLE memory"
+            )));
+        }
+        Ok(ptr)
+    }
+
+    /// Release a pointer returned by `map`.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - pointers returned by `map` must not be used after this call
+    pub unsafe fn unmap(
+        &self,
+        device: &ash::Device,
+    ) -> Result<(), GraphicsError> {
+        if let BufferMemory::Pooled(allocation) = &self.memory {
+            allocation.unmap(device)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for BufferAllocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.memory {
+            BufferMemory::Pooled(allocation) => {
+                f.debug_tuple("Pooled").field(allocation).finish()
+            }
+            BufferMemory::Dedicated {
+                memory,
+                size_in_bytes,
+                ..
+            } => f
+                .debug_struct("Dedicated")
+                .field("memory", memory)
+                .field("size_in_bytes", size_in_bytes)
+                .finish(),
+            BufferMemory::Linear {
+                arena,
+                offset_in_bytes,
+                size_in_bytes,
+            } => f
+                .debug_struct("Linear")
+                .field("memory", &arena.memory())
+                .field("offset_in_bytes", offset_in_bytes)
+                .field("size_in_bytes", size_in_bytes)
+                .finish(),
         }
     }
 }
@@ -1,7 +1,7 @@
 use {
     crate::graphics::{
         vulkan_api::{raii, RenderDevice},
-        GraphicsError,
+        AllocationError, GraphicsError,
     },
     ash::vk,
     std::sync::Arc,
@@ -97,13 +97,37 @@ impl DescriptorPool {
         let descriptor_sets = unsafe {
             self.render_device
                 .device()
-                .allocate_descriptor_sets(&create_info)?
+                .allocate_descriptor_sets(&create_info)
+                .map_err(AllocationError::from)?
         };
         let last = self.descriptor_sets.len();
         self.descriptor_sets.extend_from_slice(&descriptor_sets);
         Ok(last)
     }
 
+    /// The number of descriptor sets allocated from this pool since it was
+    /// created or last reset.
+    pub fn descriptor_set_count(&self) -> usize {
+        self.descriptor_sets.len()
+    }
+
+    /// Return every descriptor set to the pool.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - none of the pool's descriptor sets can be in use by the GPU
+    ///   - the caller must not keep any descriptor set handles from before
+    ///     the reset
+    pub unsafe fn reset(&mut self) -> Result<(), GraphicsError> {
+        self.render_device.device().reset_descriptor_pool(
+            self.descriptor_pool,
+            vk::DescriptorPoolResetFlags::empty(),
+        )?;
+        self.descriptor_sets.clear();
+        Ok(())
+    }
+
     /// Get the raw Vulkan command pool handle.
     pub fn raw(&self) -> vk::DescriptorPool {
         self.descriptor_pool
@@ -24,8 +24,30 @@ impl DescriptorSetLayout {
     pub unsafe fn new_with_bindings(
         render_device: Arc<RenderDevice>,
         bindings: &[vk::DescriptorSetLayoutBinding],
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_bindings_and_flags(
+            render_device,
+            bindings,
+            vk::DescriptorSetLayoutCreateFlags::empty(),
+        )
+    }
+
+    /// Create a new DescriptorSetLayout using the given bindings and create
+    /// flags, e.g. PUSH_DESCRIPTOR_KHR.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The DescriptorSetLayout must be dropped before the Vulkan device.
+    ///   - The application must synchronize usage of this resource.
+    ///   - Any extensions required by the flags must be enabled.
+    pub unsafe fn new_with_bindings_and_flags(
+        render_device: Arc<RenderDevice>,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+        flags: vk::DescriptorSetLayoutCreateFlags,
     ) -> Result<Self, GraphicsError> {
         let create_info = vk::DescriptorSetLayoutCreateInfo {
+            flags,
             binding_count: bindings.len() as u32,
             p_bindings: if bindings.is_empty() {
                 std::ptr::null()
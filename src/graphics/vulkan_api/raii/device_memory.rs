@@ -0,0 +1,127 @@
+use {
+    crate::graphics::{
+        vulkan_api::RenderDevice, AllocationError, GraphicsError,
+    },
+    anyhow::anyhow,
+    ash::vk,
+};
+
+/// Find the first memory type allowed by `memory_type_bits` which has every
+/// requested property.
+pub(super) fn find_memory_type_index(
+    render_device: &RenderDevice,
+    memory_type_bits: u32,
+    memory_property_flags: vk::MemoryPropertyFlags,
+) -> Result<u32, GraphicsError> {
+    let properties = unsafe {
+        render_device.ash().get_physical_device_memory_properties(
+            render_device.physical_device(),
+        )
+    };
+    properties.memory_types[..properties.memory_type_count as usize]
+        .iter()
+        .enumerate()
+        .find(|(index, memory_type)| {
+            memory_type_bits & (1 << index) != 0
+                && memory_type.property_flags.contains(memory_property_flags)
+        })
+        .map(|(index, _)| index as u32)
+        .ok_or_else(|| {
+            GraphicsError::RuntimeError(anyhow!(
+                "No memory type in {:#b} has properties {:?}",
+                memory_type_bits,
+                memory_property_flags
+            ))
+        })
+}
+
+/// Allocate a block of device memory directly from Vulkan, bypassing the
+/// device allocator.
+///
+/// # Params
+///
+/// * `render_device` - the device which owns the memory
+/// * `requirements` - the memory requirements of the resource(s) which will
+///   be bound to the memory
+/// * `memory_property_flags` - the properties the memory must have
+/// * `allocate_flags` - DEVICE_ADDRESS when buffers bound to the memory
+///   use SHADER_DEVICE_ADDRESS
+/// * `dedicated_info` - set when the memory is dedicated to one resource
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the memory must be freed before the device is dropped
+pub(super) unsafe fn allocate_memory(
+    render_device: &RenderDevice,
+    requirements: &vk::MemoryRequirements,
+    memory_property_flags: vk::MemoryPropertyFlags,
+    allocate_flags: vk::MemoryAllocateFlags,
+    dedicated_info: Option<&vk::MemoryDedicatedAllocateInfo>,
+) -> Result<vk::DeviceMemory, GraphicsError> {
+    let memory_type_index = find_memory_type_index(
+        render_device,
+        requirements.memory_type_bits,
+        memory_property_flags,
+    )?;
+    let dedicated_info_ptr = dedicated_info.map_or(std::ptr::null(), |info| {
+        info as *const vk::MemoryDedicatedAllocateInfo
+            as *const std::ffi::c_void
+    });
+    let flags_info = vk::MemoryAllocateFlagsInfo {
+        p_next: dedicated_info_ptr,
+        flags: allocate_flags,
+        ..Default::default()
+    };
+    let allocate_info = vk::MemoryAllocateInfo {
+        p_next: if allocate_flags.is_empty() {
+            dedicated_info_ptr
+        } else {
+            &flags_info as *const vk::MemoryAllocateFlagsInfo
+                as *const std::ffi::c_void
+        },
+        allocation_size: requirements.size,
+        memory_type_index,
+        ..Default::default()
+    };
+    let memory = render_device
+        .device()
+        .allocate_memory(&allocate_info, None)
+        .map_err(AllocationError::from)?;
+    Ok(memory)
+}
+
+/// Map the whole of a block of memory if it's HOST_VISIBLE, otherwise
+/// return a null pointer.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the memory must not already be mapped
+pub(super) unsafe fn map_if_host_visible(
+    render_device: &RenderDevice,
+    memory: vk::DeviceMemory,
+    memory_property_flags: vk::MemoryPropertyFlags,
+) -> Result<*mut std::ffi::c_void, GraphicsError> {
+    if !memory_property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+        return Ok(std::ptr::null_mut());
+    }
+    let ptr = render_device.device().map_memory(
+        memory,
+        0,
+        vk::WHOLE_SIZE,
+        vk::MemoryMapFlags::empty(),
+    )?;
+    Ok(ptr)
+}
+
+/// The allocate flags needed by buffers with the given usage.
+pub(super) fn allocate_flags_for(
+    usage: vk::BufferUsageFlags,
+) -> vk::MemoryAllocateFlags {
+    if usage.contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS) {
+        vk::MemoryAllocateFlags::DEVICE_ADDRESS
+    } else {
+        vk::MemoryAllocateFlags::empty()
+    }
+}
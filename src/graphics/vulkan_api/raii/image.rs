@@ -1,14 +1,30 @@
 use {
-    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    super::{device_memory, AllocationHint},
+    crate::graphics::{
+        vulkan_api::RenderDevice, AllocationError, GraphicsError,
+    },
+    anyhow::anyhow,
     ash::vk,
     ccthw_ash_allocator::Allocation,
     std::sync::Arc,
 };
 
+/// The memory which backs an Image.
+enum ImageMemory {
+    /// Memory from the device allocator.
+    Allocated(Allocation),
+
+    /// A dedicated allocation imported from an external handle.
+    Imported(vk::DeviceMemory),
+
+    /// A dedicated allocation made for this image.
+    Dedicated(vk::DeviceMemory),
+}
+
 /// RAII Vulkan Image.
 pub struct Image {
     image: vk::Image,
-    allocation: Allocation,
+    memory: ImageMemory,
     render_device: Arc<RenderDevice>,
 }
 
@@ -27,15 +43,89 @@ impl Image {
         let (image, allocation) = unsafe {
             render_device
                 .memory()
-                .allocate_image(create_info, memory_property_flags)?
+                .allocate_image(create_info, memory_property_flags)
+                .map_err(|source| AllocationError::Image {
+                    extent: create_info.extent,
+                    format: create_info.format,
+                    memory_properties: memory_property_flags,
+                    source,
+                })?
         };
         Ok(Self {
             image,
-            allocation,
+            memory: ImageMemory::Allocated(allocation),
             render_device,
         })
     }
 
+    /// Create a new Vulkan image with memory from the place suggested by
+    /// `hint`.
+    ///
+    /// Transient images get TRANSIENT_ATTACHMENT usage and LAZILY_ALLOCATED
+    /// memory when the device has it, so `create_info` must only have
+    /// attachment usages. Linear arenas only hold buffers, so the Linear
+    /// hint is an error.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - images must be destroyed before the Vulkan device is dropped.
+    pub unsafe fn new_with_hint(
+        render_device: Arc<RenderDevice>,
+        create_info: &vk::ImageCreateInfo,
+        memory_property_flags: vk::MemoryPropertyFlags,
+        hint: AllocationHint,
+    ) -> Result<Self, GraphicsError> {
+        match hint {
+            AllocationHint::Pooled => {
+                Self::new(render_device, create_info, memory_property_flags)
+            }
+            AllocationHint::Dedicated => Self::new_dedicated(
+                render_device,
+                create_info,
+                &[memory_property_flags],
+            ),
+            AllocationHint::Transient => Self::new_dedicated(
+                render_device,
+                &vk::ImageCreateInfo {
+                    usage: create_info.usage
+                        | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                    ..*create_info
+                },
+                &[
+                    memory_property_flags
+                        | vk::MemoryPropertyFlags::LAZILY_ALLOCATED,
+                    memory_property_flags,
+                ],
+            ),
+            AllocationHint::Linear(_) => Err(GraphicsError::RuntimeError(
+                anyhow!("Images can't be allocated from a linear arena"),
+            )),
+        }
+    }
+
+    /// Take ownership of an image which is bound to imported memory.
+    ///
+    /// Both the image and the memory are destroyed when the Image is dropped.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the image and memory must have been created by the render device
+    ///   - the memory must already be bound to the image
+    ///   - the Image must be dropped before the Vulkan device is dropped.
+    pub unsafe fn from_imported_memory(
+        render_device: Arc<RenderDevice>,
+        image: vk::Image,
+        memory: vk::DeviceMemory,
+    ) -> Self {
+        Self {
+            image,
+            memory: ImageMemory::Imported(memory),
+            render_device,
+        }
+    }
+
     /// Set the name which shows up in Vulkan debug logs for this resource.
     pub fn set_debug_name(&self, name: impl Into<String>) {
         self.render_device.set_debug_name(
@@ -46,8 +136,14 @@ impl Image {
     }
 
     /// Get the backing memory allocation for the Image.
-    pub fn allocation(&self) -> &Allocation {
-        &self.allocation
+    ///
+    /// Returns None when the Image is backed by imported or dedicated
+    /// memory.
+    pub fn allocation(&self) -> Option<&Allocation> {
+        match &self.memory {
+            ImageMemory::Allocated(allocation) => Some(allocation),
+            ImageMemory::Imported(_) | ImageMemory::Dedicated(_) => None,
+        }
     }
 
     /// Get the raw Vulkan command pool handle.
@@ -56,12 +152,77 @@ impl Image {
     }
 }
 
+// Private API
+// -----------
+
+impl Image {
+    /// Create an image with its own dedicated memory, using the first of
+    /// `memory_property_flags` which the device can provide.
+    unsafe fn new_dedicated(
+        render_device: Arc<RenderDevice>,
+        create_info: &vk::ImageCreateInfo,
+        memory_property_flags: &[vk::MemoryPropertyFlags],
+    ) -> Result<Self, GraphicsError> {
+        let device = render_device.device();
+        let image = device.create_image(create_info, None)?;
+        let requirements = device.get_image_memory_requirements(image);
+        let dedicated_info = vk::MemoryDedicatedAllocateInfo {
+            image,
+            ..Default::default()
+        };
+
+        let mut result = Err(GraphicsError::RuntimeError(anyhow!(
+            "No memory properties were requested"
+        )));
+        for &flags in memory_property_flags {
+            result = device_memory::allocate_memory(
+                &render_device,
+                &requirements,
+                flags,
+                vk::MemoryAllocateFlags::empty(),
+                Some(&dedicated_info),
+            );
+            if result.is_ok() {
+                break;
+            }
+        }
+        let memory = match result {
+            Ok(memory) => memory,
+            Err(error) => {
+                device.destroy_image(image, None);
+                return Err(error);
+            }
+        };
+        if let Err(error) = device.bind_image_memory(image, memory, 0) {
+            device.destroy_image(image, None);
+            device.free_memory(memory, None);
+            return Err(error.into());
+        }
+
+        Ok(Self {
+            image,
+            memory: ImageMemory::Dedicated(memory),
+            render_device,
+        })
+    }
+}
+
 impl Drop for Image {
     fn drop(&mut self) {
         unsafe {
-            self.render_device
-                .memory()
-                .free_image(self.image, self.allocation.clone());
+            match &self.memory {
+                ImageMemory::Allocated(allocation) => {
+                    self.render_device
+                        .memory()
+                        .free_image(self.image, allocation.clone());
+                }
+                ImageMemory::Imported(memory)
+                | ImageMemory::Dedicated(memory) => {
+                    let device = self.render_device.device();
+                    device.destroy_image(self.image, None);
+                    device.free_memory(*memory, None);
+                }
+            }
         }
     }
 }
@@ -70,7 +231,7 @@ impl std::fmt::Debug for Image {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Image")
             .field("image", &self.image)
-            .field("allocation", &self.allocation)
+            .field("allocation", &self.allocation())
             .finish()
     }
 }
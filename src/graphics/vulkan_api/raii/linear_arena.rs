@@ -0,0 +1,212 @@
+use {
+    super::device_memory,
+    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    anyhow::anyhow,
+    ash::vk,
+    std::{cell::Cell, sync::Arc},
+};
+
+/// A single block of device memory which buffers are bound into one after
+/// another.
+///
+/// Binding a buffer only bumps an offset, so creating many small buffers is
+/// cheap. Individual buffers are never freed, instead the whole arena is
+/// reset at once. Keep one arena per frame in flight and reset it when the
+/// frame is acquired to get per-frame buffers without fragmenting the device
+/// allocator.
+///
+/// Buffers keep the arena alive, so the memory is freed after the arena and
+/// every buffer bound to it are dropped.
+pub struct LinearArena {
+    memory: vk::DeviceMemory,
+    memory_type_bits: u32,
+    memory_property_flags: vk::MemoryPropertyFlags,
+    size_in_bytes: vk::DeviceSize,
+    offset: Cell<vk::DeviceSize>,
+    mapped: *mut std::ffi::c_void,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl LinearArena {
+    /// Allocate an arena which can hold buffers with the given usage.
+    ///
+    /// The memory is mapped for the arena's whole life when it's
+    /// HOST_VISIBLE.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to allocate memory
+    /// * `size_in_bytes` - the total size of every buffer bound between
+    ///   resets, including alignment padding
+    /// * `usage` - the usage of the buffers which will be bound to the arena
+    /// * `memory_property_flags` - the properties the memory must have
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        size_in_bytes: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<Arc<Self>, GraphicsError> {
+        // Memory requirements depend on the buffer's usage, so ask the
+        // driver about a buffer which covers the whole arena.
+        let device = render_device.device();
+        let probe = device.create_buffer(
+            &vk::BufferCreateInfo {
+                size: size_in_bytes,
+                usage,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                ..Default::default()
+            },
+            None,
+        )?;
+        let requirements = device.get_buffer_memory_requirements(probe);
+        device.destroy_buffer(probe, None);
+
+        let memory = device_memory::allocate_memory(
+            &render_device,
+            &requirements,
+            memory_property_flags,
+            device_memory::allocate_flags_for(usage),
+            None,
+        )?;
+        let mapped = match device_memory::map_if_host_visible(
+            &render_device,
+            memory,
+            memory_property_flags,
+        ) {
+            Ok(mapped) => mapped,
+            Err(error) => {
+                device.free_memory(memory, None);
+                return Err(error);
+            }
+        };
+
+        Ok(Arc::new(Self {
+            memory,
+            memory_type_bits: requirements.memory_type_bits,
+            memory_property_flags,
+            size_in_bytes: requirements.size,
+            offset: Cell::new(0),
+            mapped,
+            render_device,
+        }))
+    }
+
+    /// Set the name which shows up in Vulkan debug logs for this resource.
+    pub fn set_debug_name(&self, name: impl Into<String>) {
+        self.render_device.set_debug_name(
+            self.memory,
+            vk::ObjectType::DEVICE_MEMORY,
+            name,
+        );
+    }
+
+    /// The arena's total size in bytes.
+    pub fn size_in_bytes(&self) -> vk::DeviceSize {
+        self.size_in_bytes
+    }
+
+    /// The number of bytes bound since the last reset.
+    pub fn used_bytes(&self) -> vk::DeviceSize {
+        self.offset.get()
+    }
+
+    /// The memory's property flags.
+    pub fn memory_property_flags(&self) -> vk::MemoryPropertyFlags {
+        self.memory_property_flags
+    }
+
+    /// Make the whole arena available again.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - buffers bound since the last reset must not be used again, new
+    ///     buffers will alias their memory
+    ///   - the GPU must be finished with every buffer bound since the last
+    ///     reset
+    pub unsafe fn reset(&self) {
+        self.offset.set(0);
+    }
+}
+
+// Private API
+// -----------
+
+impl LinearArena {
+    /// The arena's memory.
+    pub(super) fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+
+    /// A pointer to the byte at `offset`, or null when the memory isn't
+    /// HOST_VISIBLE.
+    pub(super) fn mapped_ptr(
+        &self,
+        offset: vk::DeviceSize,
+    ) -> *mut std::ffi::c_void {
+        if self.mapped.is_null() {
+            return self.mapped;
+        }
+        unsafe { (self.mapped as *mut u8).add(offset as usize) as *mut _ }
+    }
+
+    /// Bind a buffer to the next free memory in the arena. Returns the
+    /// buffer's offset in the arena.
+    pub(super) unsafe fn bind_buffer(
+        &self,
+        buffer: vk::Buffer,
+    ) -> Result<vk::DeviceSize, GraphicsError> {
+        let device = self.render_device.device();
+        let requirements = device.get_buffer_memory_requirements(buffer);
+        if requirements.memory_type_bits & self.memory_type_bits == 0 {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "The buffer can't use the arena's memory type, create the \
+                 arena with the buffer's usage flags"
+            )));
+        }
+
+        let alignment = requirements.alignment.max(1);
+        let offset =
+            (self.offset.get() + alignment - 1) / alignment * alignment;
+        if offset + requirements.size > self.size_in_bytes {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "The {} byte arena can't fit a {} byte buffer after {} bytes",
+                self.size_in_bytes,
+                requirements.size,
+                self.offset.get()
+            )));
+        }
+
+        device.bind_buffer_memory(buffer, self.memory, offset)?;
+        self.offset.set(offset + requirements.size);
+        Ok(offset)
+    }
+}
+
+impl Drop for LinearArena {
+    fn drop(&mut self) {
+        unsafe {
+            // Freeing the memory implicitly unmaps it.
+            self.render_device.device().free_memory(self.memory, None);
+        }
+    }
+}
+
+impl std::fmt::Debug for LinearArena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LinearArena")
+            .field("memory", &self.memory)
+            .field("memory_property_flags", &self.memory_property_flags)
+            .field("size_in_bytes", &self.size_in_bytes)
+            .field("used_bytes", &self.offset.get())
+            .finish()
+    }
+}
@@ -1,8 +1,11 @@
+mod allocation_hint;
 mod buffer;
 mod command_pool;
 mod descriptor_pool;
 mod descriptor_set_layout;
+mod device_memory;
 mod image;
+mod linear_arena;
 mod pipeline;
 mod pipeline_layout;
 mod shader_module;
@@ -14,9 +17,15 @@ use {
 };
 
 pub use self::{
-    buffer::Buffer, command_pool::CommandPool, descriptor_pool::DescriptorPool,
-    descriptor_set_layout::DescriptorSetLayout, image::Image,
-    pipeline::Pipeline, pipeline_layout::PipelineLayout,
+    allocation_hint::AllocationHint,
+    buffer::{Buffer, BufferAllocation},
+    command_pool::CommandPool,
+    descriptor_pool::DescriptorPool,
+    descriptor_set_layout::DescriptorSetLayout,
+    image::Image,
+    linear_arena::LinearArena,
+    pipeline::Pipeline,
+    pipeline_layout::PipelineLayout,
     shader_module::ShaderModule,
 };
 
@@ -123,3 +132,10 @@ raii_wrapper!(
     create_sampler,
     destroy_sampler
 );
+raii_wrapper!(
+    QueryPool,
+    QueryPoolCreateInfo,
+    QUERY_POOL,
+    create_query_pool,
+    destroy_query_pool
+);
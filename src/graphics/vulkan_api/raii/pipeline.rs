@@ -1,8 +1,10 @@
 use {
-    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
-    anyhow::Context,
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError, PipelineError,
+    },
     ash::vk,
-    std::sync::Arc,
+    std::{ffi::CString, sync::Arc},
 };
 
 pub struct Pipeline {
@@ -37,6 +39,7 @@ impl Pipeline {
     /// Unsafe because:
     ///   - The application must not drop the resource while it is in use by the
     ///     GPU.
+    #[tracing::instrument(skip_all)]
     pub unsafe fn new_graphics_pipeline(
         render_device: Arc<RenderDevice>,
         create_info: vk::GraphicsPipelineCreateInfo,
@@ -49,13 +52,79 @@ impl Pipeline {
         let pipeline = match result {
             Ok(mut pipelines) => pipelines.pop().unwrap(),
             Err((_, result)) => {
-                return Err(GraphicsError::VulkanError(result))
-                    .context("Error creating graphics pipeline")?;
+                return Err(PipelineError::CreateFailed {
+                    kind: "graphics",
+                    result,
+                }
+                .into());
             }
         };
         Self::new(render_device, pipeline)
     }
 
+    /// Create a new compute pipeline Vulkan resource which is automatically
+    /// destroyed when dropped.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not drop the resource while it is in use by the
+    ///     GPU.
+    #[tracing::instrument(skip_all)]
+    pub unsafe fn new_compute_pipeline(
+        render_device: Arc<RenderDevice>,
+        create_info: vk::ComputePipelineCreateInfo,
+    ) -> Result<Self, GraphicsError> {
+        let result = render_device.device().create_compute_pipelines(
+            vk::PipelineCache::null(),
+            &[create_info],
+            None,
+        );
+        let pipeline = match result {
+            Ok(mut pipelines) => pipelines.pop().unwrap(),
+            Err((_, result)) => {
+                return Err(PipelineError::CreateFailed {
+                    kind: "compute",
+                    result,
+                }
+                .into());
+            }
+        };
+        Self::new(render_device, pipeline)
+    }
+
+    /// Create a compute pipeline from the SPIRV bytes for a compute shader
+    /// with a `main` entry point.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not drop the resource while it is in use by the
+    ///     GPU.
+    ///   - the pipeline layout must live at least as long as the pipeline
+    pub unsafe fn new_compute_pipeline_from_bytes(
+        render_device: Arc<RenderDevice>,
+        layout: &raii::PipelineLayout,
+        source_bytes: &[u8],
+    ) -> Result<Self, GraphicsError> {
+        let module = raii::ShaderModule::new_from_bytes(
+            render_device.clone(),
+            source_bytes,
+        )?;
+        let shader_entry_name = CString::new("main").unwrap();
+        let create_info = vk::ComputePipelineCreateInfo {
+            stage: vk::PipelineShaderStageCreateInfo {
+                module: module.raw(),
+                stage: vk::ShaderStageFlags::COMPUTE,
+                p_name: shader_entry_name.as_ptr(),
+                ..Default::default()
+            },
+            layout: layout.raw(),
+            ..Default::default()
+        };
+        Self::new_compute_pipeline(render_device, create_info)
+    }
+
     /// Set the debug name for how this resource appears in Vulkan logs.
     pub fn set_debug_name(&self, name: impl Into<String>) {
         self.render_device.set_debug_name(
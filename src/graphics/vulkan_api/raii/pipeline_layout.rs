@@ -1,6 +1,6 @@
 use {
     super::raii_wrapper,
-    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    crate::graphics::{vulkan_api::RenderDevice, GraphicsError, PipelineError},
     ash::vk,
     std::sync::Arc,
 };
@@ -34,6 +34,24 @@ impl PipelineLayout {
         descriptor_set_layouts: &[vk::DescriptorSetLayout],
         push_constant_ranges: &[vk::PushConstantRange],
     ) -> Result<Self, GraphicsError> {
+        let push_constants_size = push_constant_ranges
+            .iter()
+            .map(|range| range.offset + range.size)
+            .max()
+            .unwrap_or(0);
+        let max_push_constants_size = render_device
+            .ash()
+            .get_physical_device_properties(render_device.physical_device())
+            .limits
+            .max_push_constants_size;
+        if push_constants_size > max_push_constants_size {
+            return Err(PipelineError::PushConstantsTooLarge {
+                size: push_constants_size,
+                max: max_push_constants_size,
+            }
+            .into());
+        }
+
         let create_info = vk::PipelineLayoutCreateInfo {
             set_layout_count: descriptor_set_layouts.len() as u32,
             p_set_layouts: if descriptor_set_layouts.is_empty() {
@@ -51,4 +69,42 @@ impl PipelineLayout {
         };
         Self::new(render_device, &create_info)
     }
+
+    /// Push buffer device addresses as 64-bit push constants.
+    ///
+    /// Shaders can read the addresses with `GL_EXT_buffer_reference`, e.g.
+    /// `layout(push_constant) uniform Addresses { Vertices vertices; };`.
+    /// The layout's push constant range can be created with
+    /// `GraphicsPipelineBuilder::device_address_push_constant_range`.
+    ///
+    /// # Params
+    ///
+    /// * `command_buffer` - the command buffer being recorded
+    /// * `stage_flags` - the shader stages which read the addresses
+    /// * `offset` - the byte offset of the first address
+    /// * `addresses` - the device addresses to push
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the layout must have a push constant range which covers the
+    ///     addresses for the given stages
+    pub unsafe fn cmd_push_device_addresses(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        addresses: &[vk::DeviceAddress],
+    ) {
+        self.render_device.device().cmd_push_constants(
+            command_buffer,
+            self.raw,
+            stage_flags,
+            offset,
+            std::slice::from_raw_parts(
+                addresses.as_ptr() as *const u8,
+                std::mem::size_of_val(addresses),
+            ),
+        );
+    }
 }
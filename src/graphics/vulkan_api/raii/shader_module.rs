@@ -1,7 +1,6 @@
 use {
     super::raii_wrapper,
-    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
-    anyhow::{bail, Context, Result},
+    crate::graphics::{vulkan_api::RenderDevice, GraphicsError, ShaderError},
     ash::vk,
     std::sync::Arc,
 };
@@ -28,19 +27,23 @@ impl ShaderModule {
     ///  - the application must destroy the shader module before exit
     ///  - the shader module can be destroyed once the pipeline using it has
     ///    been created
+    #[tracing::instrument(skip_all)]
     pub unsafe fn new_from_bytes(
         render_device: Arc<RenderDevice>,
         source_bytes: &[u8],
     ) -> Result<Self, GraphicsError> {
-        let aligned_bytes = Self::copy_to_u32(source_bytes)
-            .context("Error transforming SPIRV [u8] bytes to [u32]")?;
+        let aligned_bytes = Self::copy_to_u32(source_bytes)?;
 
         let create_info = vk::ShaderModuleCreateInfo {
             p_code: aligned_bytes.as_ptr(),
             code_size: source_bytes.len(),
             ..Default::default()
         };
-        Self::new(render_device, &create_info)
+        let raw = render_device
+            .device()
+            .create_shader_module(&create_info, None)
+            .map_err(ShaderError::CreateFailed)?;
+        Ok(Self { raw, render_device })
     }
 
     /// Copy a byte slice into a properly-aligned u32 array.
@@ -53,26 +56,22 @@ impl ShaderModule {
     ///
     /// Assumes that data is little endian and will break on other
     /// architectures.
-    fn copy_to_u32(bytes: &[u8]) -> Result<Vec<u32>> {
+    fn copy_to_u32(bytes: &[u8]) -> Result<Vec<u32>, ShaderError> {
         use std::convert::TryInto;
         const U32_SIZE: usize = std::mem::size_of::<u32>();
+        const SPIRV_MAGIC_NUMBER: u32 = 0x07230203;
 
-        if bytes.len() % U32_SIZE != 0 {
-            bail!(
-                "Invalid size! Cannot evenly divide the buffer into u32 words!"
-            );
+        if bytes.is_empty() || bytes.len() % U32_SIZE != 0 {
+            return Err(ShaderError::InvalidLength(bytes.len()));
         }
 
-        let mut buffer: Vec<u32> = vec![];
-        let mut input: &[u8] = bytes;
-        while !input.is_empty() {
-            let (int_slice, rest) = input.split_at(U32_SIZE);
-            input = rest;
-            let word =
-                u32::from_le_bytes(int_slice.try_into().context(
-                    "Erro while copying a u32 word from the buffer",
-                )?);
-            buffer.push(word);
+        let buffer: Vec<u32> = bytes
+            .chunks_exact(U32_SIZE)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+
+        if buffer[0] != SPIRV_MAGIC_NUMBER {
+            return Err(ShaderError::InvalidMagicNumber(buffer[0]));
         }
 
         Ok(buffer)
@@ -0,0 +1,145 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::{extensions::khr, vk},
+    std::sync::Arc,
+};
+
+/// RAII Vulkan acceleration structure and the buffer which backs it.
+pub struct AccelerationStructure {
+    raw: vk::AccelerationStructureKHR,
+    device_address: vk::DeviceAddress,
+    _buffer: raii::Buffer,
+    loader: khr::AccelerationStructure,
+    render_device: Arc<RenderDevice>,
+}
+
+impl AccelerationStructure {
+    /// Create an acceleration structure with a device-local backing buffer.
+    ///
+    /// The acceleration structure is empty until it is built.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - The application must not drop the resource while it is in use by the
+    ///     GPU.
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        loader: khr::AccelerationStructure,
+        ty: vk::AccelerationStructureTypeKHR,
+        size: vk::DeviceSize,
+    ) -> Result<Self, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let buffer_create_info = vk::BufferCreateInfo {
+            size,
+            usage: vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &buffer_create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        let create_info = vk::AccelerationStructureCreateInfoKHR {
+            buffer: buffer.raw(),
+            offset: 0,
+            size,
+            ty,
+            ..Default::default()
+        };
+        let raw = loader.create_acceleration_structure(&create_info, None)?;
+        let device_address = loader.get_acceleration_structure_device_address(
+            &vk::AccelerationStructureDeviceAddressInfoKHR {
+                acceleration_structure: raw,
+                ..Default::default()
+            },
+        );
+
+        Ok(Self {
+            raw,
+            device_address,
+            _buffer: buffer,
+            loader,
+            render_device,
+        })
+    }
+
+    /// Set the debug name for how this resource appears in Vulkan logs.
+    pub fn set_debug_name(&self, name: impl Into<String>) {
+        self.render_device.set_debug_name(
+            self.raw,
+            vk::ObjectType::ACCELERATION_STRUCTURE_KHR,
+            name,
+        )
+    }
+
+    /// The acceleration structure's device address, used to reference a BLAS
+    /// from TLAS instances.
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        self.device_address
+    }
+
+    /// Write this acceleration structure into a descriptor set binding with
+    /// the ACCELERATION_STRUCTURE_KHR descriptor type.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the descriptor set must not be in use by the GPU when it is written
+    pub unsafe fn write_descriptor(
+        &self,
+        descriptor_set: vk::DescriptorSet,
+        binding: u32,
+    ) {
+        let acceleration_structure_info =
+            vk::WriteDescriptorSetAccelerationStructureKHR {
+                acceleration_structure_count: 1,
+                p_acceleration_structures: &self.raw,
+                ..Default::default()
+            };
+        let write = vk::WriteDescriptorSet {
+            p_next: &acceleration_structure_info
+                as *const vk::WriteDescriptorSetAccelerationStructureKHR
+                as *const std::ffi::c_void,
+            dst_set: descriptor_set,
+            dst_binding: binding,
+            dst_array_element: 0,
+            descriptor_type: vk::DescriptorType::ACCELERATION_STRUCTURE_KHR,
+            descriptor_count: 1,
+            ..Default::default()
+        };
+        self.render_device
+            .device()
+            .update_descriptor_sets(&[write], &[]);
+    }
+
+    /// Get the raw Vulkan acceleration structure handle.
+    pub fn raw(&self) -> vk::AccelerationStructureKHR {
+        self.raw
+    }
+}
+
+impl Drop for AccelerationStructure {
+    fn drop(&mut self) {
+        unsafe {
+            self.loader.destroy_acceleration_structure(self.raw, None);
+        }
+    }
+}
+
+impl std::fmt::Debug for AccelerationStructure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccelerationStructure")
+            .field("raw", &self.raw)
+            .field("device_address", &self.device_address)
+            .finish()
+    }
+}
@@ -0,0 +1,388 @@
+use {
+    super::AccelerationStructure,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    ash::{extensions::khr, vk},
+    std::sync::Arc,
+};
+
+/// A single instance of a bottom-level acceleration structure in a TLAS.
+#[derive(Debug, Copy, Clone)]
+pub struct AccelerationStructureInstance<'a> {
+    /// The bottom-level acceleration structure to instance.
+    pub blas: &'a AccelerationStructure,
+
+    /// The object-to-world transform. Only the top three rows are used.
+    pub transform: Mat4,
+
+    /// A 24-bit value available to shaders as the instance custom index.
+    pub custom_index: u32,
+
+    /// Rays only intersect this instance if `ray_mask & mask != 0`.
+    pub mask: u8,
+
+    /// The offset of this instance's hit group in the shader binding table.
+    /// Only used by ray tracing pipelines.
+    pub hit_group_offset: u32,
+}
+
+impl<'a> AccelerationStructureInstance<'a> {
+    /// An instance with the given transform which is visible to every ray.
+    pub fn new(blas: &'a AccelerationStructure, transform: Mat4) -> Self {
+        Self {
+            blas,
+            transform,
+            custom_index: 0,
+            mask: 0xFF,
+            hit_group_offset: 0,
+        }
+    }
+}
+
+/// Triangle geometry used to build a bottom-level acceleration structure.
+///
+/// Vertices are read as three tightly packed f32 positions at the start of
+/// each vertex. Indices are u32.
+#[derive(Debug, Copy, Clone)]
+pub struct TriangleGeometry<'a> {
+    /// The buffer which holds vertex data.
+    pub vertex_buffer: &'a raii::Buffer,
+
+    /// The number of vertices in the vertex buffer.
+    pub vertex_count: u32,
+
+    /// The size of a single vertex in bytes.
+    pub vertex_stride: vk::DeviceSize,
+
+    /// The buffer which holds u32 triangle indices.
+    pub index_buffer: &'a raii::Buffer,
+
+    /// The number of indices. Must be a multiple of 3.
+    pub index_count: u32,
+}
+
+/// Builds bottom and top-level acceleration structures for ray queries and
+/// ray tracing pipelines.
+///
+/// The render device must be created with every extension returned by
+/// `required_device_extensions`, and the `bufferDeviceAddress`,
+/// `accelerationStructure`, and `rayQuery` device features must be enabled.
+///
+/// Buffers used as build inputs must be created with
+/// ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR and SHADER_DEVICE_ADDRESS
+/// usage.
+///
+/// Builds are synchronous: each build call blocks until the GPU finishes.
+pub struct AccelerationStructureBuilder {
+    loader: khr::AccelerationStructure,
+    one_time_submit: OneTimeSubmitCommandBuffer,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl AccelerationStructureBuilder {
+    /// The device extensions required for building acceleration structures
+    /// and using ray queries in shaders.
+    pub fn required_device_extensions() -> Vec<String> {
+        [
+            khr::AccelerationStructure::name(),
+            khr::DeferredHostOperations::name(),
+            vk::KhrRayQueryFn::name(),
+        ]
+        .iter()
+        .map(|name| name.to_owned().into_string().unwrap())
+        .collect()
+    }
+
+    /// Create a new builder.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the builder must be dropped before the render device
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<Self, GraphicsError> {
+        let extension_name = khr::AccelerationStructure::name()
+            .to_owned()
+            .into_string()
+            .unwrap();
+        if !render_device.is_device_extension_enabled(&extension_name) {
+            return Err(GraphicsError::DeviceExtensionNotEnabled(
+                extension_name,
+            ));
+        }
+        let loader = khr::AccelerationStructure::new(
+            render_device.ash(),
+            render_device.device(),
+        );
+        let one_time_submit = OneTimeSubmitCommandBuffer::new(
+            render_device.clone(),
+            render_device.graphics_queue().clone(),
+        )?;
+        Ok(Self {
+            loader,
+            one_time_submit,
+            render_device,
+        })
+    }
+
+    /// The extension loader, used by other ray tracing utilities.
+    pub fn loader(&self) -> &khr::AccelerationStructure {
+        &self.loader
+    }
+
+    /// Build a bottom-level acceleration structure from triangle meshes.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the returned acceleration structure must be dropped before the
+    ///     render device
+    ///   - the geometry buffers must not be written while the build executes
+    pub unsafe fn build_blas(
+        &mut self,
+        geometry: &[TriangleGeometry],
+    ) -> Result<AccelerationStructure, GraphicsError> {
+        let geometries = geometry
+            .iter()
+            .map(|triangles| vk::AccelerationStructureGeometryKHR {
+                geometry_type: vk::GeometryTypeKHR::TRIANGLES,
+                geometry: vk::AccelerationStructureGeometryDataKHR {
+                    triangles:
+                        vk::AccelerationStructureGeometryTrianglesDataKHR {
+                            vertex_format: vk::Format::R32G32B32_SFLOAT,
+                            vertex_data: vk::DeviceOrHostAddressConstKHR {
+                                device_address: triangles
+                                    .vertex_buffer
+                                    .device_address(),
+                            },
+                            vertex_stride: triangles.vertex_stride,
+                            max_vertex: triangles
+                                .vertex_count
+                                .saturating_sub(1),
+                            index_type: vk::IndexType::UINT32,
+                            index_data: vk::DeviceOrHostAddressConstKHR {
+                                device_address: triangles
+                                    .index_buffer
+                                    .device_address(),
+                            },
+                            ..Default::default()
+                        },
+                },
+                flags: vk::GeometryFlagsKHR::OPAQUE,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let primitive_counts = geometry
+            .iter()
+            .map(|triangles| triangles.index_count / 3)
+            .collect::<Vec<u32>>();
+
+        let acceleration_structure = self.build(
+            vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+            &geometries,
+            &primitive_counts,
+        )?;
+        acceleration_structure.set_debug_name("BLAS");
+        Ok(acceleration_structure)
+    }
+
+    /// Build a top-level acceleration structure from BLAS instances.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the returned acceleration structure must be dropped before the
+    ///     render device
+    ///   - every referenced BLAS must outlive the returned TLAS
+    pub unsafe fn build_tlas(
+        &mut self,
+        instances: &[AccelerationStructureInstance],
+    ) -> Result<AccelerationStructure, GraphicsError> {
+        let instance_buffer = self.create_instance_buffer(instances)?;
+
+        let geometries = [vk::AccelerationStructureGeometryKHR {
+            geometry_type: vk::GeometryTypeKHR::INSTANCES,
+            geometry: vk::AccelerationStructureGeometryDataKHR {
+                instances: vk::AccelerationStructureGeometryInstancesDataKHR {
+                    array_of_pointers: vk::FALSE,
+                    data: vk::DeviceOrHostAddressConstKHR {
+                        device_address: instance_buffer.device_address(),
+                    },
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        }];
+
+        let acceleration_structure = self.build(
+            vk::AccelerationStructureTypeKHR::TOP_LEVEL,
+            &geometries,
+            &[instances.len() as u32],
+        )?;
+        acceleration_structure.set_debug_name("TLAS");
+        Ok(acceleration_structure)
+    }
+}
+
+// Private API
+// -----------
+
+impl AccelerationStructureBuilder {
+    /// Allocate and build an acceleration structure, then block until the
+    /// build completes.
+    unsafe fn build(
+        &mut self,
+        ty: vk::AccelerationStructureTypeKHR,
+        geometries: &[vk::AccelerationStructureGeometryKHR],
+        primitive_counts: &[u32],
+    ) -> Result<AccelerationStructure, GraphicsError> {
+        let mut build_info = vk::AccelerationStructureBuildGeometryInfoKHR {
+            ty,
+            flags: vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE,
+            mode: vk::BuildAccelerationStructureModeKHR::BUILD,
+            geometry_count: geometries.len() as u32,
+            p_geometries: geometries.as_ptr(),
+            ..Default::default()
+        };
+        let sizes = self.loader.get_acceleration_structure_build_sizes(
+            vk::AccelerationStructureBuildTypeKHR::DEVICE,
+            &build_info,
+            primitive_counts,
+        );
+
+        let acceleration_structure = AccelerationStructure::new(
+            self.render_device.clone(),
+            self.loader.clone(),
+            ty,
+            sizes.acceleration_structure_size,
+        )?;
+        let scratch_buffer = self.create_device_address_buffer(
+            sizes.build_scratch_size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+
+        build_info.dst_acceleration_structure = acceleration_structure.raw();
+        build_info.scratch_data = vk::DeviceOrHostAddressKHR {
+            device_address: scratch_buffer.device_address(),
+        };
+        let build_ranges = primitive_counts
+            .iter()
+            .map(|&primitive_count| {
+                vk::AccelerationStructureBuildRangeInfoKHR {
+                    primitive_count,
+                    primitive_offset: 0,
+                    first_vertex: 0,
+                    transform_offset: 0,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        self.loader.cmd_build_acceleration_structures(
+            self.one_time_submit.command_buffer(),
+            &[build_info],
+            &[&build_ranges],
+        );
+
+        // The scratch buffer and any instance buffers can be dropped as soon
+        // as the build finishes.
+        self.one_time_submit.sync_submit_and_reset()?;
+
+        Ok(acceleration_structure)
+    }
+
+    /// Create a host-visible buffer holding the Vulkan instance structs for a
+    /// TLAS build.
+    unsafe fn create_instance_buffer(
+        &self,
+        instances: &[AccelerationStructureInstance],
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let vk_instances = instances
+            .iter()
+            .map(|instance| {
+                let m = &instance.transform;
+                vk::AccelerationStructureInstanceKHR {
+                    transform: vk::TransformMatrixKHR {
+                        matrix: [
+                            m[(0, 0)],
+                            m[(0, 1)],
+                            m[(0, 2)],
+                            m[(0, 3)],
+                            m[(1, 0)],
+                            m[(1, 1)],
+                            m[(1, 2)],
+                            m[(1, 3)],
+                            m[(2, 0)],
+                            m[(2, 1)],
+                            m[(2, 2)],
+                            m[(2, 3)],
+                        ],
+                    },
+                    instance_custom_index_and_mask: vk::Packed24_8::new(
+                        instance.custom_index,
+                        instance.mask,
+                    ),
+                    instance_shader_binding_table_record_offset_and_flags:
+                        vk::Packed24_8::new(
+                            instance.hit_group_offset,
+                            vk::GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE
+                                .as_raw() as u8,
+                        ),
+                    acceleration_structure_reference:
+                        vk::AccelerationStructureReferenceKHR {
+                            device_handle: instance.blas.device_address(),
+                        },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let size = (std::mem::size_of::<vk::AccelerationStructureInstanceKHR>()
+            * vk_instances.len().max(1)) as vk::DeviceSize;
+        let buffer = self.create_device_address_buffer(
+            size,
+            vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        let ptr = buffer.allocation().map(self.render_device.device())?;
+        let data = std::slice::from_raw_parts_mut(
+            ptr as *mut vk::AccelerationStructureInstanceKHR,
+            vk_instances.len(),
+        );
+        data.copy_from_slice(&vk_instances);
+        Ok(buffer)
+    }
+
+    /// Create a buffer which can be referenced by its device address.
+    unsafe fn create_device_address_buffer(
+        &self,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memory_property_flags: vk::MemoryPropertyFlags,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index =
+            self.render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size,
+            usage: usage | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        raii::Buffer::new(
+            self.render_device.clone(),
+            &create_info,
+            memory_property_flags,
+        )
+    }
+}
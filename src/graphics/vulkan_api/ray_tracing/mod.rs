@@ -0,0 +1,33 @@
+//! Hardware ray tracing support built on VK_KHR_acceleration_structure.
+//!
+//! Acceleration structures built here can be bound to descriptor sets with
+//! the ACCELERATION_STRUCTURE_KHR descriptor type and traced with
+//! `rayQueryEXT` from fragment or compute shaders (GL_EXT_ray_query).
+//!
+//! The version of ccthw_ash_instance used by this project cannot chain the
+//! VkPhysicalDeviceAccelerationStructureFeaturesKHR and
+//! VkPhysicalDeviceRayQueryFeaturesKHR structs, so applications must make
+//! sure the `accelerationStructure` and `rayQuery` features are enabled by
+//! the driver. The same is true for `bufferDeviceAddress`, which can be
+//! checked with `RenderDevice::is_buffer_device_address_supported`.
+//!
+//! RayTracingPipeline and ShaderBindingTable add full ray tracing pipelines
+//! with raygen, miss, and hit shaders. These additionally require the
+//! `rayTracingPipeline` feature.
+
+mod acceleration_structure;
+mod builder;
+mod ray_tracing_pipeline;
+mod shader_binding_table;
+
+pub use self::{
+    acceleration_structure::AccelerationStructure,
+    builder::{
+        AccelerationStructureBuilder, AccelerationStructureInstance,
+        TriangleGeometry,
+    },
+    ray_tracing_pipeline::{
+        HitGroup, RayTracingPipeline, RayTracingPipelineBuilder,
+    },
+    shader_binding_table::ShaderBindingTable,
+};
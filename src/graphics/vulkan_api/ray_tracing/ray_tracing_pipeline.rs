@@ -0,0 +1,339 @@
+use {
+    super::ShaderBindingTable,
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError, PipelineError,
+    },
+    ash::{extensions::khr, vk},
+    std::{ffi::CString, sync::Arc},
+};
+
+/// The shaders used when a ray hits geometry.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HitGroup<'a> {
+    /// SPIRV bytes for the closest hit shader.
+    pub closest_hit: Option<&'a [u8]>,
+
+    /// SPIRV bytes for the any hit shader.
+    pub any_hit: Option<&'a [u8]>,
+}
+
+/// A utility for building ray tracing pipelines.
+///
+/// Shader groups are always laid out in the order: raygen, every miss shader,
+/// then every hit group. The ShaderBindingTable relies on this order.
+#[derive(Debug, Clone)]
+pub struct RayTracingPipelineBuilder<'a> {
+    raygen_shader: &'a [u8],
+    miss_shaders: Vec<&'a [u8]>,
+    hit_groups: Vec<HitGroup<'a>>,
+    max_recursion_depth: u32,
+}
+
+/// A ray tracing pipeline along with the information needed to build a
+/// shader binding table and trace rays.
+pub struct RayTracingPipeline {
+    miss_count: u32,
+    hit_group_count: u32,
+    properties: vk::PhysicalDeviceRayTracingPipelinePropertiesKHR,
+    loader: khr::RayTracingPipeline,
+    pipeline: raii::Pipeline,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl<'a> RayTracingPipelineBuilder<'a> {
+    /// Create a new builder with the given raygen shader and a max recursion
+    /// depth of 1.
+    pub fn new(raygen_shader: &'a [u8]) -> Self {
+        Self {
+            raygen_shader,
+            miss_shaders: vec![],
+            hit_groups: vec![],
+            max_recursion_depth: 1,
+        }
+    }
+
+    /// Add a miss shader. Miss shaders are indexed in the order they are
+    /// added.
+    pub fn miss_shader(mut self, source_bytes: &'a [u8]) -> Self {
+        self.miss_shaders.push(source_bytes);
+        self
+    }
+
+    /// Add a triangle hit group. Hit groups are indexed in the order they are
+    /// added.
+    pub fn hit_group(mut self, hit_group: HitGroup<'a>) -> Self {
+        self.hit_groups.push(hit_group);
+        self
+    }
+
+    /// Add a triangle hit group with only a closest hit shader.
+    pub fn closest_hit_shader(self, source_bytes: &'a [u8]) -> Self {
+        self.hit_group(HitGroup {
+            closest_hit: Some(source_bytes),
+            any_hit: None,
+        })
+    }
+
+    /// Set the maximum depth of recursive `traceRayEXT` calls.
+    ///
+    /// This is clamped to the device's maxRayRecursionDepth when the pipeline
+    /// is built.
+    pub fn max_recursion_depth(mut self, max_recursion_depth: u32) -> Self {
+        self.max_recursion_depth = max_recursion_depth;
+        self
+    }
+
+    /// Create the ray tracing pipeline.
+    ///
+    /// The render device must be created with every extension returned by
+    /// `RayTracingPipeline::required_device_extensions` and the
+    /// `rayTracingPipeline` device feature must be enabled.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the pipeline must be destroyed before the render device
+    ///   - the pipeline layout must live at least as long as the pipeline
+    #[tracing::instrument(skip_all)]
+    pub unsafe fn build(
+        &self,
+        render_device: Arc<RenderDevice>,
+        layout: &raii::PipelineLayout,
+    ) -> Result<RayTracingPipeline, GraphicsError> {
+        let extension_name = RayTracingPipeline::extension_name();
+        if !render_device.is_device_extension_enabled(&extension_name) {
+            return Err(GraphicsError::DeviceExtensionNotEnabled(
+                extension_name,
+            ));
+        }
+        let loader = khr::RayTracingPipeline::new(
+            render_device.ash(),
+            render_device.device(),
+        );
+        let properties = khr::RayTracingPipeline::get_properties(
+            render_device.ash(),
+            render_device.physical_device(),
+        );
+
+        // Collect every shader stage, then build groups which reference the
+        // stages by index.
+        let mut stages: Vec<(vk::ShaderStageFlags, &[u8])> =
+            vec![(vk::ShaderStageFlags::RAYGEN_KHR, self.raygen_shader)];
+        let mut groups = vec![Self::general_group(0)];
+        for miss_shader in &self.miss_shaders {
+            groups.push(Self::general_group(stages.len() as u32));
+            stages.push((vk::ShaderStageFlags::MISS_KHR, *miss_shader));
+        }
+        for hit_group in &self.hit_groups {
+            let mut group = vk::RayTracingShaderGroupCreateInfoKHR {
+                ty: vk::RayTracingShaderGroupTypeKHR::TRIANGLES_HIT_GROUP,
+                general_shader: vk::SHADER_UNUSED_KHR,
+                closest_hit_shader: vk::SHADER_UNUSED_KHR,
+                any_hit_shader: vk::SHADER_UNUSED_KHR,
+                intersection_shader: vk::SHADER_UNUSED_KHR,
+                ..Default::default()
+            };
+            if let Some(closest_hit) = hit_group.closest_hit {
+                group.closest_hit_shader = stages.len() as u32;
+                stages
+                    .push((vk::ShaderStageFlags::CLOSEST_HIT_KHR, closest_hit));
+            }
+            if let Some(any_hit) = hit_group.any_hit {
+                group.any_hit_shader = stages.len() as u32;
+                stages.push((vk::ShaderStageFlags::ANY_HIT_KHR, any_hit));
+            }
+            groups.push(group);
+        }
+
+        let mut shader_modules = Vec::with_capacity(stages.len());
+        for (_, source_bytes) in &stages {
+            shader_modules.push(raii::ShaderModule::new_from_bytes(
+                render_device.clone(),
+                source_bytes,
+            )?);
+        }
+        let shader_entry_name = CString::new("main").unwrap();
+        let stage_create_infos = stages
+            .iter()
+            .zip(shader_modules.iter())
+            .map(|((stage, _), module)| vk::PipelineShaderStageCreateInfo {
+                module: module.raw(),
+                stage: *stage,
+                p_name: shader_entry_name.as_ptr(),
+                ..Default::default()
+            })
+            .collect::<Vec<vk::PipelineShaderStageCreateInfo>>();
+
+        let create_info = vk::RayTracingPipelineCreateInfoKHR {
+            stage_count: stage_create_infos.len() as u32,
+            p_stages: stage_create_infos.as_ptr(),
+            group_count: groups.len() as u32,
+            p_groups: groups.as_ptr(),
+            max_pipeline_ray_recursion_depth: self
+                .max_recursion_depth
+                .min(properties.max_ray_recursion_depth),
+            layout: layout.raw(),
+            ..Default::default()
+        };
+        let raw = loader
+            .create_ray_tracing_pipelines(
+                vk::DeferredOperationKHR::null(),
+                vk::PipelineCache::null(),
+                &[create_info],
+                None,
+            )
+            .map_err(|result| PipelineError::CreateFailed {
+                kind: "ray tracing",
+                result,
+            })?
+            .pop()
+            .unwrap();
+        let pipeline = raii::Pipeline::new(render_device.clone(), raw)?;
+
+        Ok(RayTracingPipeline {
+            miss_count: self.miss_shaders.len() as u32,
+            hit_group_count: self.hit_groups.len() as u32,
+            properties,
+            loader,
+            pipeline,
+            render_device,
+        })
+    }
+}
+
+impl RayTracingPipeline {
+    /// The name of the device extension required for ray tracing pipelines.
+    pub fn extension_name() -> String {
+        khr::RayTracingPipeline::name()
+            .to_owned()
+            .into_string()
+            .unwrap()
+    }
+
+    /// The device extensions required for ray tracing pipelines, including
+    /// the extensions required to build acceleration structures.
+    pub fn required_device_extensions() -> Vec<String> {
+        let mut extensions =
+            super::AccelerationStructureBuilder::required_device_extensions();
+        extensions.push(Self::extension_name());
+        extensions
+    }
+
+    /// Set the debug name for how this resource appears in Vulkan logs.
+    pub fn set_debug_name(&self, name: impl Into<String>) {
+        self.pipeline.set_debug_name(name)
+    }
+
+    /// The device's ray tracing pipeline properties, including the shader
+    /// group handle size and alignment requirements.
+    pub fn properties(
+        &self,
+    ) -> &vk::PhysicalDeviceRayTracingPipelinePropertiesKHR {
+        &self.properties
+    }
+
+    /// The number of miss shader groups in the pipeline.
+    pub fn miss_count(&self) -> u32 {
+        self.miss_count
+    }
+
+    /// The number of hit groups in the pipeline.
+    pub fn hit_group_count(&self) -> u32 {
+        self.hit_group_count
+    }
+
+    /// The total number of shader groups in the pipeline.
+    pub fn group_count(&self) -> u32 {
+        1 + self.miss_count + self.hit_group_count
+    }
+
+    /// Get the opaque shader group handles for every group in the pipeline.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the pipeline must still be valid
+    pub unsafe fn shader_group_handles(
+        &self,
+    ) -> Result<Vec<u8>, GraphicsError> {
+        let data_size = self.group_count() as usize
+            * self.properties.shader_group_handle_size as usize;
+        let handles = self.loader.get_ray_tracing_shader_group_handles(
+            self.pipeline.raw(),
+            0,
+            self.group_count(),
+            data_size,
+        )?;
+        Ok(handles)
+    }
+
+    /// Bind the pipeline for ray tracing.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording
+    pub unsafe fn bind(&self, command_buffer: vk::CommandBuffer) {
+        self.render_device.device().cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::RAY_TRACING_KHR,
+            self.pipeline.raw(),
+        );
+    }
+
+    /// Trace one ray per invocation in a width x height x depth grid.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording
+    ///   - the pipeline must be bound along with every descriptor set it uses
+    ///   - the shader binding table must have been created for this pipeline
+    pub unsafe fn cmd_trace_rays(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        shader_binding_table: &ShaderBindingTable,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) {
+        self.loader.cmd_trace_rays(
+            command_buffer,
+            shader_binding_table.raygen_region(),
+            shader_binding_table.miss_region(),
+            shader_binding_table.hit_region(),
+            shader_binding_table.callable_region(),
+            width,
+            height,
+            depth,
+        );
+    }
+
+    /// Get the raw Vulkan pipeline handle.
+    pub fn raw(&self) -> vk::Pipeline {
+        self.pipeline.raw()
+    }
+}
+
+// Private API
+// -----------
+
+impl<'a> RayTracingPipelineBuilder<'a> {
+    /// A general shader group for raygen and miss shaders.
+    fn general_group(
+        stage_index: u32,
+    ) -> vk::RayTracingShaderGroupCreateInfoKHR {
+        vk::RayTracingShaderGroupCreateInfoKHR {
+            ty: vk::RayTracingShaderGroupTypeKHR::GENERAL,
+            general_shader: stage_index,
+            closest_hit_shader: vk::SHADER_UNUSED_KHR,
+            any_hit_shader: vk::SHADER_UNUSED_KHR,
+            intersection_shader: vk::SHADER_UNUSED_KHR,
+            ..Default::default()
+        }
+    }
+}
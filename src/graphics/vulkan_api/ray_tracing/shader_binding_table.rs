@@ -0,0 +1,179 @@
+use {
+    super::RayTracingPipeline,
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// The shader binding table for a RayTracingPipeline.
+///
+/// Records are stored in a single host-visible buffer with one region each
+/// for the raygen shader, miss shaders, and hit groups. Each record is
+/// padded to the device's shaderGroupHandleAlignment and each region starts
+/// on a shaderGroupBaseAlignment boundary.
+pub struct ShaderBindingTable {
+    raygen_region: vk::StridedDeviceAddressRegionKHR,
+    miss_region: vk::StridedDeviceAddressRegionKHR,
+    hit_region: vk::StridedDeviceAddressRegionKHR,
+    callable_region: vk::StridedDeviceAddressRegionKHR,
+    _buffer: raii::Buffer,
+}
+
+// Public API
+// ----------
+
+impl ShaderBindingTable {
+    /// Create a shader binding table with one record for every shader group
+    /// in the pipeline.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the table must be dropped before the render device
+    ///   - the bufferDeviceAddress device feature must be enabled
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        pipeline: &RayTracingPipeline,
+    ) -> Result<Self, GraphicsError> {
+        let properties = pipeline.properties();
+        let handle_size = properties.shader_group_handle_size as u64;
+        let handle_stride = align_up(
+            handle_size,
+            properties.shader_group_handle_alignment as u64,
+        );
+        let base_alignment = properties.shader_group_base_alignment as u64;
+
+        // The raygen region's size must equal its stride.
+        let raygen_size = align_up(handle_stride, base_alignment);
+        let miss_size = align_up(
+            handle_stride * pipeline.miss_count() as u64,
+            base_alignment,
+        );
+        let hit_size = align_up(
+            handle_stride * pipeline.hit_group_count() as u64,
+            base_alignment,
+        );
+        let miss_offset = raygen_size;
+        let hit_offset = miss_offset + miss_size;
+        let total_size = (hit_offset + hit_size).max(base_alignment);
+
+        let buffer =
+            Self::create_buffer(&render_device, total_size, base_alignment)?;
+
+        // Regions are addressed from the first aligned address in the
+        // buffer.
+        let buffer_address = buffer.device_address();
+        let address = align_up(buffer_address, base_alignment);
+        let base_offset = address - buffer_address;
+
+        // Copy each handle into its record.
+        let handles = pipeline.shader_group_handles()?;
+        let ptr = buffer.allocation().map(render_device.device())? as *mut u8;
+        let data = std::slice::from_raw_parts_mut(
+            ptr.add(base_offset as usize),
+            total_size as usize,
+        );
+        let record_offsets = std::iter::once(0)
+            .chain(
+                (0..pipeline.miss_count() as u64)
+                    .map(|i| miss_offset + i * handle_stride),
+            )
+            .chain(
+                (0..pipeline.hit_group_count() as u64)
+                    .map(|i| hit_offset + i * handle_stride),
+            );
+        for (handle, offset) in handles
+            .chunks_exact(handle_size as usize)
+            .zip(record_offsets)
+        {
+            let offset = offset as usize;
+            data[offset..offset + handle.len()].copy_from_slice(handle);
+        }
+
+        let region = |offset: u64, stride: u64, size: u64| {
+            if size == 0 {
+                vk::StridedDeviceAddressRegionKHR::default()
+            } else {
+                vk::StridedDeviceAddressRegionKHR {
+                    device_address: address + offset,
+                    stride,
+                    size,
+                }
+            }
+        };
+
+        Ok(Self {
+            raygen_region: region(0, raygen_size, raygen_size),
+            miss_region: region(miss_offset, handle_stride, miss_size),
+            hit_region: region(hit_offset, handle_stride, hit_size),
+            callable_region: vk::StridedDeviceAddressRegionKHR::default(),
+            _buffer: buffer,
+        })
+    }
+
+    /// The region containing the raygen shader record.
+    pub fn raygen_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.raygen_region
+    }
+
+    /// The region containing every miss shader record.
+    pub fn miss_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.miss_region
+    }
+
+    /// The region containing every hit group record.
+    pub fn hit_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.hit_region
+    }
+
+    /// The region containing callable shader records. Always empty.
+    pub fn callable_region(&self) -> &vk::StridedDeviceAddressRegionKHR {
+        &self.callable_region
+    }
+}
+
+// Private API
+// -----------
+
+impl ShaderBindingTable {
+    /// Create the host-visible buffer which holds every record.
+    ///
+    /// The buffer is over-allocated by one base alignment so the first
+    /// region can always be aligned regardless of where the allocator places
+    /// the buffer.
+    unsafe fn create_buffer(
+        render_device: &Arc<RenderDevice>,
+        size: vk::DeviceSize,
+        base_alignment: vk::DeviceSize,
+    ) -> Result<raii::Buffer, GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::BufferCreateInfo {
+            size: size + base_alignment,
+            usage: vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            ..Default::default()
+        };
+        let buffer = raii::Buffer::new(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        buffer.set_debug_name("Shader Binding Table");
+        Ok(buffer)
+    }
+}
+
+/// Round value up to the next multiple of alignment.
+fn align_up(value: u64, alignment: u64) -> u64 {
+    if alignment == 0 {
+        return value;
+    }
+    (value + alignment - 1) / alignment * alignment
+}
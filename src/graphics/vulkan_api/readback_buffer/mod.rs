@@ -0,0 +1,236 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, FramesInFlight, MappedBuffer, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Copies data from a device-local buffer back to the CPU without stalling.
+///
+/// There is one host-visible staging buffer for every frame in flight. Call
+/// `cmd_copy` at the point in a frame where the source buffer holds the
+/// desired data, then call `try_read` on later frames. `try_read` returns the
+/// newest data whose frame has finished executing, typically a few frames
+/// after the copy was recorded.
+///
+/// The staging buffers use HOST_COHERENT memory when the device has it, and
+/// are invalidated before they're read otherwise.
+pub struct ReadbackBuffer<T: Copy> {
+    count: usize,
+    staging: Vec<MappedBuffer<T>>,
+    pending_copies: Vec<Option<u64>>,
+    next_copy: u64,
+    latest_copy: Option<u64>,
+    latest: Vec<T>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl<T: Copy> ReadbackBuffer<T> {
+    /// Create staging buffers large enough for `count` elements.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create the staging buffers
+    /// * `frame_count` - the number of frames in flight
+    /// * `count` - the number of elements copied back each time
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the render device
+    ///   - this instance must not be dropped while a copy is in flight
+    ///   - every buffer copied from must hold valid `T` values, the bytes
+    ///     are read back as-is
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frame_count: usize,
+        count: usize,
+    ) -> Result<Self, GraphicsError> {
+        let mut staging = Vec::with_capacity(frame_count);
+        for index in 0..frame_count {
+            let buffer = MappedBuffer::new_host_visible(
+                render_device.clone(),
+                count,
+                vk::BufferUsageFlags::TRANSFER_DST,
+            )?;
+            buffer.set_debug_name(format!("Frame {index} Readback Buffer"));
+            staging.push(buffer);
+        }
+        Ok(Self {
+            count,
+            staging,
+            pending_copies: vec![None; frame_count],
+            next_copy: 0,
+            latest_copy: None,
+            latest: Vec::with_capacity(count),
+            render_device,
+        })
+    }
+
+    /// The number of elements copied back each time.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true when the readback buffer holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Record a copy from the source buffer into this frame's staging buffer.
+    ///
+    /// A barrier is recorded before the copy so all prior writes to the
+    /// source buffer are visible.
+    ///
+    /// # Params
+    ///
+    /// * `frame` - the frame being recorded
+    /// * `source` - a buffer created with TRANSFER_SRC usage
+    /// * `source_offset` - the byte offset of the first element in `source`
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - must be recorded outside of a render pass
+    ///   - the source buffer must hold at least `len()` elements after
+    ///     `source_offset`
+    pub unsafe fn cmd_copy(
+        &mut self,
+        frame: &Frame,
+        source: &raii::Buffer,
+        source_offset: vk::DeviceSize,
+    ) -> Result<(), GraphicsError> {
+        let index = frame.frame_index();
+
+        // FramesInFlight waits for the frame's previous submission before
+        // handing it out, so any pending copy into this slot is complete.
+        // Keep it before the staging buffer is overwritten.
+        if self.pending_copies[index].is_some() {
+            self.take_staging_data(index)?;
+        }
+
+        let device = self.render_device.device();
+        let before_copy = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+            src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::COPY,
+            dst_access_mask: vk::AccessFlags2::TRANSFER_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            frame.command_buffer(),
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &before_copy,
+                ..Default::default()
+            },
+        );
+        device.cmd_copy_buffer(
+            frame.command_buffer(),
+            source.raw(),
+            self.staging[index].raw(),
+            &[vk::BufferCopy {
+                src_offset: source_offset,
+                dst_offset: 0,
+                size: Self::size_in_bytes(self.count),
+            }],
+        );
+        let after_copy = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::COPY,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::HOST,
+            dst_access_mask: vk::AccessFlags2::HOST_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            frame.command_buffer(),
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &after_copy,
+                ..Default::default()
+            },
+        );
+
+        self.pending_copies[index] = Some(self.next_copy);
+        self.next_copy += 1;
+
+        Ok(())
+    }
+
+    /// Get the newest data which has finished copying.
+    ///
+    /// Never blocks. Returns None until the first copy completes, after that
+    /// the most recent completed data is returned until newer data is ready.
+    pub fn try_read(
+        &mut self,
+        frames_in_flight: &FramesInFlight,
+    ) -> Result<Option<&[T]>, GraphicsError> {
+        // Staging buffers are indexed by the frame which recorded the copy.
+        let mut newest_complete: Option<(u64, usize)> = None;
+        for (index, pending_copy) in self.pending_copies.iter().enumerate() {
+            if let Some(copy) = *pending_copy {
+                let is_newer = newest_complete
+                    .map(|(newest, _)| copy > newest)
+                    .unwrap_or(true);
+                if is_newer && frames_in_flight.is_frame_complete(index)? {
+                    newest_complete = Some((copy, index));
+                }
+            }
+        }
+        if let Some((_, index)) = newest_complete {
+            unsafe {
+                // SAFE because the frame which recorded the copy is complete.
+                self.take_staging_data(index)?;
+            }
+        }
+        if self.latest_copy.is_some() {
+            Ok(Some(&self.latest))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl<T: Copy> ReadbackBuffer<T> {
+    /// The size of `count` elements in bytes.
+    fn size_in_bytes(count: usize) -> vk::DeviceSize {
+        (count.max(1) * std::mem::size_of::<T>()) as vk::DeviceSize
+    }
+
+    /// Copy a staging buffer's contents into the latest data if they are
+    /// newer, then mark the staging buffer as idle.
+    ///
+    /// Unsafe because the copy into the staging buffer must be complete.
+    unsafe fn take_staging_data(
+        &mut self,
+        index: usize,
+    ) -> Result<(), GraphicsError> {
+        let copy = match self.pending_copies[index].take() {
+            Some(copy) => copy,
+            None => return Ok(()),
+        };
+        if self
+            .latest_copy
+            .map(|latest| latest > copy)
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+        let staging = &self.staging[index];
+        staging.invalidate()?;
+        self.latest.clear();
+        // SAFE because the copy is complete and invalidated, and `new`'s
+        // caller guarantees the copied data is made of valid T values.
+        self.latest.extend_from_slice(staging.as_slice());
+        self.latest_copy = Some(copy);
+        Ok(())
+    }
+}
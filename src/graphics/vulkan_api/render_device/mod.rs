@@ -5,7 +5,7 @@ use {
         LogicalDevice, PhysicalDevice, PhysicalDeviceFeatures, VulkanInstance,
     },
     indoc::indoc,
-    std::sync::Mutex,
+    std::sync::{Arc, Mutex},
 };
 
 mod queue;
@@ -25,10 +25,11 @@ pub use self::queue::Queue;
 pub struct RenderDevice {
     graphics_queue: Queue,
     presentation_queue: Queue,
-    window_surface: WindowSurface,
+    window_surface: Option<WindowSurface>,
     logical_device: LogicalDevice,
     instance: VulkanInstance,
     allocator: Mutex<MemoryAllocator>,
+    device_extensions: Vec<String>,
 }
 
 // Public Api
@@ -57,52 +58,116 @@ impl RenderDevice {
         features: PhysicalDeviceFeatures,
         surface: vk::SurfaceKHR,
     ) -> Result<Self, GraphicsError> {
-        let window_surface = WindowSurface::new(&instance, surface);
-        let physical_device =
-            Self::pick_physical_device(&instance, features, &window_surface)?;
-        let queue_finder = QueueFinder::new(&physical_device, &window_surface);
-        let logical_device = unsafe {
-            // SAFE because the RenderDevice takes ownership of the instance
-            // along with the LogicalDevice.
-            LogicalDevice::new(
-                &instance,
-                physical_device.clone(),
-                &[ash::extensions::khr::Swapchain::name()
-                    .to_owned()
-                    .into_string()
-                    .unwrap()],
-                &queue_finder.queue_family_infos(),
-            )?
-        };
-        let (graphics_queue, presentation_queue) =
-            queue_finder.get_queues_from_device(&logical_device);
+        Self::new_with_device_extensions(instance, features, surface, &[])
+    }
 
-        let allocator = ccthw_ash_allocator::create_system_allocator(
-            instance.ash(),
-            logical_device.raw().clone(),
-            *physical_device.raw(),
-        );
+    /// Create a new render device with additional device extensions.
+    ///
+    /// # Params
+    ///
+    /// * `instance` - the VulkanInstance used to create all application
+    ///   resources.
+    /// * `features` - the physical device features required by this
+    ///   application.
+    /// * `surface` - the surface this application will use for swapchain
+    ///   presentation.
+    /// * `device_extensions` - device extensions required by the application.
+    ///   Only physical devices which support every extension are considered.
+    ///   The swapchain extension is always enabled and does not need to be
+    ///   provided.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe for the same reasons as `RenderDevice::new`.
+    pub unsafe fn new_with_device_extensions(
+        instance: VulkanInstance,
+        features: PhysicalDeviceFeatures,
+        surface: vk::SurfaceKHR,
+        device_extensions: &[String],
+    ) -> Result<Self, GraphicsError> {
+        let mut all_device_extensions =
+            vec![ash::extensions::khr::Swapchain::name()
+                .to_owned()
+                .into_string()
+                .unwrap()];
+        for extension in device_extensions {
+            if !all_device_extensions.contains(extension) {
+                all_device_extensions.push(extension.clone());
+            }
+        }
 
-        let render_device = Self {
-            graphics_queue,
-            presentation_queue,
-            window_surface,
-            logical_device,
+        let window_surface = WindowSurface::new(&instance, surface);
+        Self::new_with_optional_surface(
             instance,
-            allocator: Mutex::new(allocator),
-        };
-        render_device.set_debug_name(
-            *render_device.presentation_queue().raw(),
-            vk::ObjectType::QUEUE,
-            "presentation queue",
-        );
-        render_device.set_debug_name(
-            *render_device.graphics_queue.raw(),
-            vk::ObjectType::QUEUE,
-            "graphics queue",
-        );
+            features,
+            Some(window_surface),
+            all_device_extensions,
+        )
+    }
 
-        Ok(render_device)
+    /// Create a render device without a window surface.
+    ///
+    /// Headless render devices have a single graphics queue, which also
+    /// supports compute and transfer commands, and can't create swapchains.
+    /// They are meant for integration tests and offline rendering on
+    /// machines without a window system, e.g. CI runners with lavapipe or
+    /// SwiftShader.
+    ///
+    /// The presentation queue is the same as the graphics queue.
+    ///
+    /// # Params
+    ///
+    /// * `instance` - the VulkanInstance used to create all application
+    ///   resources. No surface extensions are required.
+    /// * `features` - the physical device features required by this
+    ///   application.
+    /// * `device_extensions` - device extensions required by the application.
+    ///   Only physical devices which support every extension are considered.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe for the same reasons as `RenderDevice::new`.
+    pub unsafe fn new_headless(
+        instance: VulkanInstance,
+        features: PhysicalDeviceFeatures,
+        device_extensions: &[String],
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_optional_surface(
+            instance,
+            features,
+            None,
+            device_extensions.to_vec(),
+        )
+    }
+
+    /// Create a Vulkan instance and a headless render device.
+    ///
+    /// # Params
+    ///
+    /// * `instance_layers` - layers to enable on the instance, e.g. the
+    ///   Khronos validation layer when it is installed.
+    /// * `features` - the physical device features required by this
+    ///   application.
+    /// * `device_extensions` - device extensions required by the application.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe for the same reasons as `RenderDevice::new`.
+    pub unsafe fn create_headless(
+        instance_layers: &[String],
+        features: PhysicalDeviceFeatures,
+        device_extensions: &[String],
+    ) -> Result<Arc<Self>, GraphicsError> {
+        let instance = VulkanInstance::new(&[], instance_layers)?;
+        let device = Self::new_headless(instance, features, device_extensions)?;
+        log::debug!("{}", device);
+        Ok(Arc::new(device))
+    }
+
+    /// Returns true when the render device was created without a window
+    /// surface and so cannot present swapchain images.
+    pub fn is_headless(&self) -> bool {
+        self.window_surface.is_none()
     }
 
     /// Borrow the device memory allocator.
@@ -157,6 +222,64 @@ impl RenderDevice {
         // no-op on release builds
     }
 
+    /// Returns true when the named device extension was enabled when the
+    /// render device was created.
+    pub fn is_device_extension_enabled(&self, name: impl AsRef<str>) -> bool {
+        self.device_extensions
+            .iter()
+            .any(|extension| extension == name.as_ref())
+    }
+
+    /// Returns true when the physical device supports the
+    /// `bufferDeviceAddress` feature.
+    ///
+    /// PhysicalDeviceFeatures can't chain
+    /// VkPhysicalDeviceBufferDeviceAddressFeatures, so applications which need
+    /// buffer device addresses should check this after creating the render
+    /// device and fall back (or exit) when it returns false.
+    pub fn is_buffer_device_address_supported(&self) -> bool {
+        let mut buffer_device_address_features =
+            vk::PhysicalDeviceBufferDeviceAddressFeatures::default();
+        let mut features = vk::PhysicalDeviceFeatures2 {
+            p_next: &mut buffer_device_address_features
+                as *mut vk::PhysicalDeviceBufferDeviceAddressFeatures
+                as *mut std::ffi::c_void,
+            ..Default::default()
+        };
+        unsafe {
+            // SAFE because the physical device outlives the render device.
+            self.ash().get_physical_device_features2(
+                self.physical_device(),
+                &mut features,
+            );
+        }
+        buffer_device_address_features.buffer_device_address == vk::TRUE
+    }
+
+    /// Returns true when the physical device supports the `multiview`
+    /// feature.
+    ///
+    /// Multiview is core in Vulkan 1.1 but still optional, so it must be
+    /// enabled with `vulkan_11_features_mut` when the device is created.
+    pub fn is_multiview_supported(&self) -> bool {
+        let mut vulkan_11_features =
+            vk::PhysicalDeviceVulkan11Features::default();
+        let mut features = vk::PhysicalDeviceFeatures2 {
+            p_next: &mut vulkan_11_features
+                as *mut vk::PhysicalDeviceVulkan11Features
+                as *mut std::ffi::c_void,
+            ..Default::default()
+        };
+        unsafe {
+            // SAFE because the physical device outlives the render device.
+            self.ash().get_physical_device_features2(
+                self.physical_device(),
+                &mut features,
+            );
+        }
+        vulkan_11_features.multiview == vk::TRUE
+    }
+
     /// The queue this application uses for graphics operations.
     pub fn presentation_queue(&self) -> &Queue {
         &self.presentation_queue
@@ -188,14 +311,26 @@ impl RenderDevice {
         self.logical_device.raw()
     }
 
+    /// The raw Vulkan physical device backing the logical device.
+    ///
+    /// # Safety
+    ///
+    /// The caller must not keep copies of the physical device handle after
+    /// the render device is dropped.
+    pub unsafe fn physical_device(&self) -> vk::PhysicalDevice {
+        *self.logical_device.physical_device().raw()
+    }
+
     /// The KHR surface provided by the window system for rendering.
     ///
+    /// Fails when the render device is headless.
+    ///
     /// # Safety
     ///
     /// The caller must not keep copies of the device handle after the render
     /// device is dropped.
-    pub unsafe fn surface(&self) -> &vk::SurfaceKHR {
-        self.window_surface.raw()
+    pub unsafe fn surface(&self) -> Result<&vk::SurfaceKHR, GraphicsError> {
+        Ok(self.window_surface()?.raw())
     }
 
     /// Get all of the surface formats supported by this device.
@@ -205,7 +340,7 @@ impl RenderDevice {
         unsafe {
             // Safe because the physical device is checked for support when
             // the Render Device is constructed.
-            self.window_surface.get_physical_device_surface_formats(
+            self.window_surface()?.get_physical_device_surface_formats(
                 self.logical_device.physical_device(),
             )
         }
@@ -218,7 +353,7 @@ impl RenderDevice {
         unsafe {
             // Safe because the physical device is checked for support when
             // the Render Device is constructed.
-            self.window_surface
+            self.window_surface()?
                 .get_physical_device_surface_present_modes(
                     self.logical_device.physical_device(),
                 )
@@ -232,7 +367,7 @@ impl RenderDevice {
         unsafe {
             // Safe because the physical device is checked for support when
             // the Render Device is constructed.
-            self.window_surface
+            self.window_surface()?
                 .get_surface_capabilities(self.logical_device.physical_device())
         }
     }
@@ -275,6 +410,83 @@ impl std::fmt::Display for RenderDevice {
 // -----------
 
 impl RenderDevice {
+    /// The window surface, or an error when the render device is headless.
+    fn window_surface(&self) -> Result<&WindowSurface, GraphicsError> {
+        self.window_surface
+            .as_ref()
+            .ok_or(GraphicsError::NoWindowSurface)
+    }
+
+    /// Create the render device, with or without a window surface.
+    ///
+    /// # Params
+    ///
+    /// * `instance` - the VulkanInstance used to create all application
+    ///   resources.
+    /// * `features` - the physical device features required by this
+    ///   application.
+    /// * `window_surface` - the surface used for presentation, if any.
+    /// * `all_device_extensions` - every device extension to enable.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe for the same reasons as `RenderDevice::new`.
+    unsafe fn new_with_optional_surface(
+        instance: VulkanInstance,
+        features: PhysicalDeviceFeatures,
+        window_surface: Option<WindowSurface>,
+        all_device_extensions: Vec<String>,
+    ) -> Result<Self, GraphicsError> {
+        let physical_device = Self::pick_physical_device(
+            &instance,
+            features,
+            window_surface.as_ref(),
+            &all_device_extensions,
+        )?;
+        let queue_finder =
+            QueueFinder::new(&physical_device, window_surface.as_ref());
+        let logical_device = unsafe {
+            // SAFE because the RenderDevice takes ownership of the instance
+            // along with the LogicalDevice.
+            LogicalDevice::new(
+                &instance,
+                physical_device.clone(),
+                &all_device_extensions,
+                &queue_finder.queue_family_infos(),
+            )?
+        };
+        let (graphics_queue, presentation_queue) =
+            queue_finder.get_queues_from_device(&logical_device);
+
+        let allocator = ccthw_ash_allocator::create_system_allocator(
+            instance.ash(),
+            logical_device.raw().clone(),
+            *physical_device.raw(),
+        );
+
+        let render_device = Self {
+            graphics_queue,
+            presentation_queue,
+            window_surface,
+            logical_device,
+            instance,
+            allocator: Mutex::new(allocator),
+            device_extensions: all_device_extensions,
+        };
+        render_device.set_debug_name(
+            *render_device.presentation_queue().raw(),
+            vk::ObjectType::QUEUE,
+            "presentation queue",
+        );
+        render_device.set_debug_name(
+            *render_device.graphics_queue.raw(),
+            vk::ObjectType::QUEUE,
+            "graphics queue",
+        );
+
+        Ok(render_device)
+    }
+
     /// Pick a physical device which is suitable for this application.
     ///
     /// # Params
@@ -282,10 +494,15 @@ impl RenderDevice {
     /// * `instance` - the Vulkan instance used to access devices on this
     ///   platform.
     /// * `features` - all features required by this application.
+    /// * `window_surface` - the surface which must support presentation, if
+    ///   any.
+    /// * `device_extensions` - all device extensions required by this
+    ///   application.
     fn pick_physical_device(
         instance: &VulkanInstance,
         features: PhysicalDeviceFeatures,
-        window_surface: &WindowSurface,
+        window_surface: Option<&WindowSurface>,
+        device_extensions: &[String],
     ) -> Result<PhysicalDevice, GraphicsError> {
         let all_devices =
             PhysicalDevice::enumerate_supported_devices(instance, &features)?;
@@ -308,13 +525,10 @@ impl RenderDevice {
                 has_required_queues
             })
             .filter(|device| {
-                let has_extensions =
-                    device.available_extension_names().contains(
-                        &ash::extensions::khr::Swapchain::name()
-                            .to_owned()
-                            .into_string()
-                            .unwrap(),
-                    );
+                let available_extensions = device.available_extension_names();
+                let has_extensions = device_extensions
+                    .iter()
+                    .all(|extension| available_extensions.contains(extension));
                 log::trace!(
                     "{} has required extensions? {}",
                     device,
@@ -25,16 +25,22 @@ impl QueueFinder {
     ///
     /// * `device` - The physical device to consider.
     /// * `window_surface` - the window surface which will be used for
-    ///   presenting swapchain images.
+    ///   presenting swapchain images. Headless devices only need a graphics
+    ///   queue.
     pub fn device_has_required_queues(
         device: &PhysicalDevice,
-        window_surface: &WindowSurface,
+        window_surface: Option<&WindowSurface>,
     ) -> bool {
         let has_graphics_queue =
             Self::find_graphics_queue_family_index(device).is_some();
-        let has_present_queue =
-            Self::find_presentation_queue_family_index(window_surface, device)
-                .is_some();
+        let has_present_queue = match window_surface {
+            Some(window_surface) => Self::find_presentation_queue_family_index(
+                window_surface,
+                device,
+            )
+            .is_some(),
+            None => true,
+        };
 
         has_graphics_queue && has_present_queue
     }
@@ -45,9 +51,11 @@ impl QueueFinder {
     /// # Params
     ///
     /// * `device` - The physical device to use when finding queue indices.
+    /// * `window_surface` - the window surface used for presentation. When
+    ///   None, the graphics queue is also used as the presentation queue.
     pub fn new(
         device: &PhysicalDevice,
-        window_surface: &WindowSurface,
+        window_surface: Option<&WindowSurface>,
     ) -> Self {
         let mut families = HashMap::<usize, QueueFamilyInfo>::new();
 
@@ -58,9 +66,14 @@ impl QueueFinder {
             .or_insert_with_key(|&index| QueueFamilyInfo::new(index as u32))
             .add_queue_priority(1.0);
 
-        let presentation_queue_family_index =
-            Self::find_presentation_queue_family_index(window_surface, device)
-                .unwrap();
+        let presentation_queue_family_index = match window_surface {
+            Some(window_surface) => Self::find_presentation_queue_family_index(
+                window_surface,
+                device,
+            )
+            .unwrap(),
+            None => graphics_queue_family_index,
+        };
         if presentation_queue_family_index != graphics_queue_family_index {
             families
                 .entry(presentation_queue_family_index)
@@ -1,23 +1,111 @@
 use {
+    super::DepthPrePass,
     crate::graphics::{
         vulkan_api::{raii, Frame, RenderDevice, Swapchain},
         GraphicsError,
     },
+    anyhow::anyhow,
     ash::vk,
-    std::sync::Arc,
+    std::{cell::Cell, sync::Arc},
 };
 
+/// How the ColorPass's color attachment is loaded when the render pass
+/// begins.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorLoadOp {
+    /// Clear to the color given to `begin_render_pass_inline`.
+    Clear,
+
+    /// Preserve the image's previous contents. Useful for sketches which
+    /// accumulate or feed back into the previous frame.
+    ///
+    /// Each swapchain image keeps its own contents, so a frame loads
+    /// whatever was last rendered to the same swapchain image, not
+    /// necessarily the previous frame. The first time each image is used,
+    /// it is cleared instead because it has no previous contents.
+    ///
+    /// The image must be in `ColorAttachmentOps::load_layout` when the
+    /// render pass begins.
+    Load,
+
+    /// The previous contents are undefined. Useful when every pixel is
+    /// overwritten anyway.
+    DontCare,
+}
+
+/// The load and store ops for the ColorPass's color attachment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorAttachmentOps {
+    /// How the color attachment is loaded when the render pass begins.
+    pub load: ColorLoadOp,
+
+    /// How the color attachment is stored when the render pass ends. Only
+    /// use DONT_CARE for transient targets whose results are never read.
+    pub store_op: vk::AttachmentStoreOp,
+
+    /// The image's layout when a render pass with `ColorLoadOp::Load`
+    /// begins. PRESENT_SRC_KHR matches an image which was last presented or
+    /// written by another ColorPass. A pass which follows some other
+    /// render pass must use that pass's final layout instead.
+    ///
+    /// Ignored by the other load ops, and the first time each image is
+    /// used, because the previous contents are discarded.
+    pub load_layout: vk::ImageLayout,
+}
+
+impl Default for ColorAttachmentOps {
+    /// Clear and store, the same as every ColorPass before load and store
+    /// ops were configurable.
+    fn default() -> Self {
+        Self {
+            load: ColorLoadOp::Clear,
+            store_op: vk::AttachmentStoreOp::STORE,
+            load_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        }
+    }
+}
+
+/// An extra subpass which runs after the ColorPass's first subpass.
+///
+/// Every subpass writes the color attachment and, when the ColorPass has
+/// one, is depth tested against the depth attachment. Extra subpasses can
+/// also read the attachments as input attachments, in the order color then
+/// depth. For example, deferred decals read depth and blend into color,
+/// while programmable blending reads and writes color.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ColorSubpass {
+    /// Read the color attachment as an input attachment. The color
+    /// attachment uses the GENERAL layout in this subpass, and draws which
+    /// read pixels written earlier in the same subpass need a
+    /// `cmd_pipeline_barrier` (a by-region self-dependency is declared).
+    /// Requires swapchain images with INPUT_ATTACHMENT usage.
+    pub read_color: bool,
+
+    /// Read the depth attachment as an input attachment. Depth is read-only
+    /// in this subpass, so pipelines must disable depth writes. Requires a
+    /// DepthPrePass.
+    pub read_depth: bool,
+}
+
 /// A utility for managing a render pass and framebuffers which target a given
 /// set of images.
 ///
-/// The color pass is single-sampled and does not have a depth/stencil buffer.
+/// The color pass is single-sampled. By default it does not have a
+/// depth/stencil buffer, but it can load the depth buffer written by a
+/// DepthPrePass. It always has one subpass and can declare extra subpasses
+/// which read the attachments as input attachments.
 #[derive(Debug)]
 pub struct ColorPass {
     extent: vk::Extent2D,
     format: vk::Format,
     render_pass: raii::RenderPass,
+    first_use_render_pass: Option<raii::RenderPass>,
+    images_used: Vec<Cell<bool>>,
+    attachment_ops: ColorAttachmentOps,
     framebuffers: Vec<raii::Framebuffer>,
-    _image_views: Vec<raii::ImageView>,
+    image_views: Vec<raii::ImageView>,
+    depth_view: Option<vk::ImageView>,
+    subpasses: Vec<ColorSubpass>,
     render_device: Arc<RenderDevice>,
 }
 
@@ -39,16 +127,143 @@ impl ColorPass {
     ///
     /// Unsafe because:
     ///  - the framebuffers are only valid while the swapchain exists
-    ///  - if the swapchain is rebuilt, the framebuffers should be destroyed and
-    ///    rebuilt too
+    ///  - if the swapchain is rebuilt, the framebuffers must be rebuilt with
+    ///    `rebuild_for_swapchain`
     ///  - the targeted images MUST outlive the ColorPass.
     pub unsafe fn new(
         render_device: Arc<RenderDevice>,
         swapchain: &Swapchain,
     ) -> Result<Self, GraphicsError> {
-        let render_pass = Self::create_render_pass(
+        Self::new_with_subpasses(render_device, swapchain, None, &[])
+    }
+
+    /// Create a render pass with a single color attachment and the depth
+    /// buffer from a DepthPrePass.
+    ///
+    /// The depth buffer is loaded, not cleared, so draws in this pass are
+    /// tested against the depth written by the pre-pass. Pipelines should
+    /// typically disable depth writes and use `EQUAL` or `LESS_OR_EQUAL` as
+    /// the depth compare op.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `swapchain` - the swapchain images targeted by this render pass
+    /// * `depth_pre_pass` - the depth pre-pass which owns the depth buffer.
+    ///   Its extent must match the swapchain extent.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the framebuffers are only valid while the swapchain exists
+    ///  - if the swapchain is rebuilt, the framebuffers must be rebuilt with
+    ///    `rebuild_for_swapchain`
+    ///  - the depth pre-pass MUST outlive the ColorPass.
+    pub unsafe fn new_with_depth_pre_pass(
+        render_device: Arc<RenderDevice>,
+        swapchain: &Swapchain,
+        depth_pre_pass: &DepthPrePass,
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_subpasses(
+            render_device,
+            swapchain,
+            Some(depth_pre_pass),
+            &[],
+        )
+    }
+
+    /// Create a render pass with the first subpass followed by extra
+    /// subpasses.
+    ///
+    /// Subpass 0 is the same as the subpass created by `new` or
+    /// `new_with_depth_pre_pass`. Subpass `i + 1` is described by
+    /// `subpasses[i]`. Pipelines for extra subpasses must be built with
+    /// `GraphicsPipelineBuilder::subpass`, and each subpass is started with
+    /// `cmd_next_subpass`.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `swapchain` - the swapchain images targeted by this render pass
+    /// * `depth_pre_pass` - an optional depth pre-pass which owns the depth
+    ///   buffer, see `new_with_depth_pre_pass`
+    /// * `subpasses` - the extra subpasses
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the framebuffers are only valid while the swapchain exists
+    ///  - if the swapchain is rebuilt, the framebuffers must be rebuilt with
+    ///    `rebuild_for_swapchain`
+    ///  - the depth pre-pass MUST outlive the ColorPass.
+    pub unsafe fn new_with_subpasses(
+        render_device: Arc<RenderDevice>,
+        swapchain: &Swapchain,
+        depth_pre_pass: Option<&DepthPrePass>,
+        subpasses: &[ColorSubpass],
+    ) -> Result<Self, GraphicsError> {
+        Self::new_with_attachment_ops(
+            render_device,
+            swapchain,
+            depth_pre_pass,
+            subpasses,
+            ColorAttachmentOps::default(),
+        )
+    }
+
+    /// Create a render pass with extra subpasses and custom load and store
+    /// ops for the color attachment.
+    ///
+    /// The other constructors always clear and store the color attachment.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `swapchain` - the swapchain images targeted by this render pass
+    /// * `depth_pre_pass` - an optional depth pre-pass which owns the depth
+    ///   buffer, see `new_with_depth_pre_pass`
+    /// * `subpasses` - the extra subpasses, see `new_with_subpasses`
+    /// * `attachment_ops` - the color attachment's load and store ops
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the framebuffers are only valid while the swapchain exists
+    ///  - if the swapchain is rebuilt, the framebuffers must be rebuilt with
+    ///    `rebuild_for_swapchain`
+    ///  - the depth pre-pass MUST outlive the ColorPass.
+    pub unsafe fn new_with_attachment_ops(
+        render_device: Arc<RenderDevice>,
+        swapchain: &Swapchain,
+        depth_pre_pass: Option<&DepthPrePass>,
+        subpasses: &[ColorSubpass],
+        attachment_ops: ColorAttachmentOps,
+    ) -> Result<Self, GraphicsError> {
+        let depth_view = depth_pre_pass
+            .map(|depth_pre_pass| depth_pre_pass.depth_view().raw());
+        if depth_view.is_none()
+            && subpasses.iter().any(|subpass| subpass.read_depth)
+        {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "A subpass can only read depth when there is a DepthPrePass"
+            )));
+        }
+        if subpasses.iter().any(|subpass| subpass.read_color)
+            && !swapchain
+                .image_usage()
+                .contains(vk::ImageUsageFlags::INPUT_ATTACHMENT)
+        {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "The swapchain images can't be used as input attachments"
+            )));
+        }
+
+        let (render_pass, first_use_render_pass) = Self::create_render_passes(
             render_device.clone(),
             swapchain.image_format(),
+            attachment_ops,
+            depth_view.is_some(),
+            subpasses,
         )?;
         let image_views = Self::create_image_views(
             render_device.clone(),
@@ -61,18 +276,93 @@ impl ColorPass {
             render_pass.raw(),
             swapchain.extent(),
             &image_views,
+            depth_view,
         )?;
 
         Ok(Self {
             extent: swapchain.extent(),
             format: swapchain.image_format(),
             render_pass,
+            first_use_render_pass,
+            images_used: image_views.iter().map(|_| Cell::new(false)).collect(),
+            attachment_ops,
             framebuffers,
-            _image_views: image_views,
+            image_views,
+            depth_view,
+            subpasses: subpasses.to_vec(),
             render_device,
         })
     }
 
+    /// Rebuild the image views and framebuffers for a rebuilt swapchain.
+    ///
+    /// The render pass is only replaced when the swapchain's image format
+    /// changed, so pipelines built against `render_pass` stay valid unless
+    /// `SwapchainChanges::format_changed` is true.
+    ///
+    /// # Params
+    ///
+    /// * `swapchain` - the rebuilt swapchain
+    /// * `depth_pre_pass` - the depth pre-pass, rebuilt to match the new
+    ///   extent. Required when, and only when, the ColorPass was created
+    ///   with one.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - every frame which used the old framebuffers must have finished
+    ///    executing
+    ///  - the depth pre-pass MUST outlive the ColorPass.
+    pub unsafe fn rebuild_for_swapchain(
+        &mut self,
+        swapchain: &Swapchain,
+        depth_pre_pass: Option<&DepthPrePass>,
+    ) -> Result<(), GraphicsError> {
+        let depth_view = depth_pre_pass
+            .map(|depth_pre_pass| depth_pre_pass.depth_view().raw());
+        if depth_view.is_some() != self.depth_view.is_some() {
+            return Err(GraphicsError::RuntimeError(anyhow!(
+                "A ColorPass must be rebuilt with a DepthPrePass if and only \
+                 if it was created with one"
+            )));
+        }
+
+        if swapchain.image_format() != self.format {
+            let (render_pass, first_use_render_pass) =
+                Self::create_render_passes(
+                    self.render_device.clone(),
+                    swapchain.image_format(),
+                    self.attachment_ops,
+                    depth_view.is_some(),
+                    &self.subpasses,
+                )?;
+            self.render_pass = render_pass;
+            self.first_use_render_pass = first_use_render_pass;
+            self.format = swapchain.image_format();
+        }
+
+        // Drop the old framebuffers before the views they reference.
+        self.framebuffers.clear();
+        self.image_views = Self::create_image_views(
+            self.render_device.clone(),
+            self.format,
+            swapchain.images(),
+        )?;
+        self.framebuffers = Self::create_framebuffers(
+            self.render_device.clone(),
+            self.render_pass.raw(),
+            swapchain.extent(),
+            &self.image_views,
+            depth_view,
+        )?;
+        self.images_used =
+            self.image_views.iter().map(|_| Cell::new(false)).collect();
+        self.extent = swapchain.extent();
+        self.depth_view = depth_view;
+
+        Ok(())
+    }
+
     /// The current extent.
     pub fn extent(&self) -> vk::Extent2D {
         self.extent
@@ -88,8 +378,60 @@ impl ColorPass {
         &self.render_pass
     }
 
+    /// The color attachment's load and store ops.
+    pub fn attachment_ops(&self) -> ColorAttachmentOps {
+        self.attachment_ops
+    }
+
+    /// The total number of subpasses, including the first subpass.
+    pub fn subpass_count(&self) -> u32 {
+        self.subpasses.len() as u32 + 1
+    }
+
+    /// Descriptor infos for a subpass's input attachments, in input
+    /// attachment index order, for the framebuffer targeting the given
+    /// swapchain image.
+    ///
+    /// Descriptor sets with input attachments must be rewritten for each
+    /// swapchain image, so this is typically called once per swapchain image
+    /// after the ColorPass is created. The first subpass has no input
+    /// attachments.
+    pub fn input_attachment_image_infos(
+        &self,
+        subpass: u32,
+        swapchain_image_index: usize,
+    ) -> Vec<vk::DescriptorImageInfo> {
+        let subpass = match subpass
+            .checked_sub(1)
+            .and_then(|index| self.subpasses.get(index as usize))
+        {
+            Some(subpass) => subpass,
+            None => return vec![],
+        };
+        let mut infos = vec![];
+        if subpass.read_color {
+            infos.push(vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: self.image_views[swapchain_image_index].raw(),
+                image_layout: vk::ImageLayout::GENERAL,
+            });
+        }
+        if let (true, Some(depth_view)) = (subpass.read_depth, self.depth_view)
+        {
+            infos.push(vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: depth_view,
+                image_layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            });
+        }
+        infos
+    }
+
     /// Begin a render pass for the given image index.
     ///
+    /// `clear_color` is used when the color attachment is cleared. It is
+    /// ignored when the color attachment is loaded or DONT_CARE.
+    ///
     /// # Safety
     ///
     /// Unsafe because:
@@ -101,19 +443,106 @@ impl ColorPass {
         &self,
         frame: &Frame,
         clear_color: [f32; 4],
+    ) {
+        self.begin_render_pass(frame, clear_color, self.full_render_area());
+    }
+
+    /// Begin a render pass which only renders to part of the framebuffer.
+    ///
+    /// The render area is clamped to the framebuffer and the dynamic scissor
+    /// is set to match it, so draws outside of the area are discarded. The
+    /// viewport is left alone so geometry stays in the same place on screen.
+    ///
+    /// Pixels outside of the render area are not modified, so this is
+    /// typically used with `ColorLoadOp::Load` to redraw only a dirty region.
+    /// With `ColorLoadOp::Clear` only the render area is cleared.
+    ///
+    /// Returns false, without beginning the render pass, when the render
+    /// area lies entirely outside of the framebuffer. The caller must skip
+    /// the pass's draw commands and must not end the render pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the ColorPass must not be destroyed until the command buffer
+    ///     finishes executing or is discarded.
+    ///   - pipelines which set their own scissor, like BindlessTriangles,
+    ///     replace the scissor set here. Draws are still limited to the
+    ///     render area, but pixels outside of it become undefined.
+    #[must_use]
+    pub unsafe fn begin_render_pass_inline_with_render_area(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+        render_area: vk::Rect2D,
+    ) -> bool {
+        let render_area = match self.clamp_render_area(render_area) {
+            Some(render_area) => render_area,
+            None => return false,
+        };
+        self.begin_render_pass(frame, clear_color, render_area);
+        self.render_device.device().cmd_set_scissor(
+            frame.command_buffer(),
+            0,
+            &[render_area],
+        );
+        true
+    }
+
+    /// A render area which covers the entire framebuffer.
+    pub fn full_render_area(&self) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: self.extent(),
+        }
+    }
+
+    /// Move on to the next subpass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the render pass must already be started and must not already be in
+    ///     its last subpass
+    pub unsafe fn cmd_next_subpass(&self, frame: &Frame) {
+        self.render_device.device().cmd_next_subpass(
+            frame.command_buffer(),
+            vk::SubpassContents::INLINE,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl ColorPass {
+    /// Begin the render pass for the frame's swapchain image.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the render area must be inside of the framebuffer
+    unsafe fn begin_render_pass(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+        render_area: vk::Rect2D,
     ) {
         let clear_values = [vk::ClearValue {
             color: vk::ClearColorValue {
                 float32: clear_color,
             },
         }];
+        let index = frame.swapchain_image_index();
+        let first_use = !self.images_used[index].replace(true);
+        let render_pass = match &self.first_use_render_pass {
+            Some(first_use_render_pass) if first_use => first_use_render_pass,
+            _ => &self.render_pass,
+        };
         let begin_info = vk::RenderPassBeginInfo {
-            render_pass: self.render_pass.raw(),
-            framebuffer: self.framebuffers[frame.swapchain_image_index()].raw(),
-            render_area: vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: self.extent(),
-            },
+            render_pass: render_pass.raw(),
+            framebuffer: self.framebuffers[index].raw(),
+            render_area,
             clear_value_count: clear_values.len() as u32,
             p_clear_values: clear_values.as_ptr(),
             ..Default::default()
@@ -124,12 +553,89 @@ impl ColorPass {
             vk::SubpassContents::INLINE,
         );
     }
-}
 
-// Private API
-// -----------
+    /// The part of a render area which lies inside of the framebuffer, or
+    /// None when it's entirely outside. Vulkan requires a non-empty render
+    /// area.
+    fn clamp_render_area(&self, render_area: vk::Rect2D) -> Option<vk::Rect2D> {
+        let vk::Extent2D { width, height } = self.extent;
+        let vk::Offset2D { x, y } = render_area.offset;
+        let left = (x as i64).clamp(0, width as i64);
+        let top = (y as i64).clamp(0, height as i64);
+        let right =
+            (x as i64 + render_area.extent.width as i64).clamp(0, width as i64);
+        let bottom = (y as i64 + render_area.extent.height as i64)
+            .clamp(0, height as i64);
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(vk::Rect2D {
+            offset: vk::Offset2D {
+                x: left as i32,
+                y: top as i32,
+            },
+            extent: vk::Extent2D {
+                width: (right - left) as u32,
+                height: (bottom - top) as u32,
+            },
+        })
+    }
+
+    /// Create the render pass, and the render pass used the first time
+    /// each image is loaded when the color attachment is loaded.
+    ///
+    /// Swapchain images start out with undefined contents, so each image is
+    /// cleared the first time it's used instead of loaded. The render passes
+    /// only differ by load op and layout so they are compatible and share
+    /// framebuffers and pipelines.
+    unsafe fn create_render_passes(
+        render_device: Arc<RenderDevice>,
+        format: vk::Format,
+        attachment_ops: ColorAttachmentOps,
+        has_depth: bool,
+        subpasses: &[ColorSubpass],
+    ) -> Result<(raii::RenderPass, Option<raii::RenderPass>), GraphicsError>
+    {
+        let load_op = match attachment_ops.load {
+            ColorLoadOp::Clear => vk::AttachmentLoadOp::CLEAR,
+            ColorLoadOp::Load => vk::AttachmentLoadOp::LOAD,
+            ColorLoadOp::DontCare => vk::AttachmentLoadOp::DONT_CARE,
+        };
+        let initial_layout = if load_op == vk::AttachmentLoadOp::LOAD {
+            if attachment_ops.load_layout == vk::ImageLayout::UNDEFINED {
+                return Err(GraphicsError::RuntimeError(anyhow!(
+                    "ColorLoadOp::Load requires a defined load_layout"
+                )));
+            }
+            attachment_ops.load_layout
+        } else {
+            vk::ImageLayout::UNDEFINED
+        };
+        let render_pass = Self::create_render_pass(
+            render_device.clone(),
+            format,
+            load_op,
+            initial_layout,
+            attachment_ops.store_op,
+            has_depth,
+            subpasses,
+        )?;
+        let first_use_render_pass = if load_op == vk::AttachmentLoadOp::LOAD {
+            Some(Self::create_render_pass(
+                render_device,
+                format,
+                vk::AttachmentLoadOp::CLEAR,
+                vk::ImageLayout::UNDEFINED,
+                attachment_ops.store_op,
+                has_depth,
+                subpasses,
+            )?)
+        } else {
+            None
+        };
+        Ok((render_pass, first_use_render_pass))
+    }
 
-impl ColorPass {
     /// Create image views for each image.
     ///
     /// # Params
@@ -185,6 +691,8 @@ impl ColorPass {
     /// * `extent` - the size of the targeted images
     /// * `image_views` - the image views to use as framebuffer color
     ///   attachments
+    /// * `depth_view` - an optional depth attachment shared by every
+    ///   framebuffer
     ///
     /// # Safety
     ///
@@ -198,16 +706,18 @@ impl ColorPass {
         render_pass: vk::RenderPass,
         extent: vk::Extent2D,
         image_views: &[raii::ImageView],
+        depth_view: Option<vk::ImageView>,
     ) -> Result<Vec<raii::Framebuffer>, GraphicsError> {
         let mut framebuffers = vec![];
         let vk::Extent2D { width, height } = extent;
         for image_view in image_views {
-            let raw_image_view = image_view.raw();
+            let mut attachments = vec![image_view.raw()];
+            attachments.extend(depth_view);
             let framebuffer = {
                 let create_info = vk::FramebufferCreateInfo {
                     render_pass,
-                    attachment_count: 1,
-                    p_attachments: &raw_image_view,
+                    attachment_count: attachments.len() as u32,
+                    p_attachments: attachments.as_ptr(),
                     width,
                     height,
                     layers: 1,
@@ -221,13 +731,20 @@ impl ColorPass {
         Ok(framebuffers)
     }
 
-    /// Create a render pass with a single subpass with external dependencies
-    /// for input and output.
+    /// Create a render pass with the first subpass, any extra subpasses, and
+    /// external dependencies for input and output.
     ///
     /// # Params
     ///
     /// * `device` - the Vulkan device used to create all resources
     /// * `format` - the targeted image format
+    /// * `load_op` - the color attachment's load op
+    /// * `initial_layout` - the color attachment's layout when the render
+    ///   pass begins. UNDEFINED unless the attachment is loaded.
+    /// * `store_op` - the color attachment's store op
+    /// * `has_depth` - when true, the render pass loads a depth attachment
+    ///   written by a DepthPrePass
+    /// * `extra_subpasses` - the subpasses which follow the first subpass
     ///
     /// # Safety
     ///
@@ -238,46 +755,127 @@ impl ColorPass {
     unsafe fn create_render_pass(
         render_device: Arc<RenderDevice>,
         format: vk::Format,
+        load_op: vk::AttachmentLoadOp,
+        initial_layout: vk::ImageLayout,
+        store_op: vk::AttachmentStoreOp,
+        has_depth: bool,
+        extra_subpasses: &[ColorSubpass],
     ) -> Result<raii::RenderPass, GraphicsError> {
-        let attachments = [
+        let mut attachments = vec![
             // The color attachment
             vk::AttachmentDescription {
                 format,
                 samples: vk::SampleCountFlags::TYPE_1,
-                load_op: vk::AttachmentLoadOp::CLEAR,
-                store_op: vk::AttachmentStoreOp::STORE,
+                load_op,
+                store_op,
                 stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
                 stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-                initial_layout: vk::ImageLayout::UNDEFINED,
+                initial_layout,
                 final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
                 flags: vk::AttachmentDescriptionFlags::empty(),
             },
         ];
+        if has_depth {
+            // The depth attachment is written by the DepthPrePass
+            attachments.push(vk::AttachmentDescription {
+                format: DepthPrePass::DEPTH_FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::LOAD,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout:
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            });
+        }
+        let subpass0_depth_attachment = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
         let subpass0_color_attachments = [vk::AttachmentReference {
             attachment: 0,
             layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
         }];
-        let subpasses = [vk::SubpassDescription {
+        let mut subpasses = vec![vk::SubpassDescription {
             flags: vk::SubpassDescriptionFlags::empty(),
             pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
             color_attachment_count: subpass0_color_attachments.len() as u32,
             p_color_attachments: subpass0_color_attachments.as_ptr(),
+            p_depth_stencil_attachment: if has_depth {
+                &subpass0_depth_attachment
+            } else {
+                std::ptr::null()
+            },
             ..Default::default()
         }];
+
+        // The attachment references for extra subpasses must stay alive until
+        // the render pass is created, so they are all built before any
+        // descriptions point at them.
+        let extra_references = extra_subpasses
+            .iter()
+            .map(|subpass| {
+                let color_layout = if subpass.read_color {
+                    vk::ImageLayout::GENERAL
+                } else {
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                };
+                let depth_layout = if subpass.read_depth {
+                    vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL
+                } else {
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+                };
+                let color = vk::AttachmentReference {
+                    attachment: 0,
+                    layout: color_layout,
+                };
+                let depth = vk::AttachmentReference {
+                    attachment: 1,
+                    layout: depth_layout,
+                };
+                let mut inputs = vec![];
+                if subpass.read_color {
+                    inputs.push(color);
+                }
+                if subpass.read_depth {
+                    inputs.push(depth);
+                }
+                (color, depth, inputs)
+            })
+            .collect::<Vec<_>>();
+        for (color, depth, inputs) in &extra_references {
+            subpasses.push(vk::SubpassDescription {
+                flags: vk::SubpassDescriptionFlags::empty(),
+                pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+                color_attachment_count: 1,
+                p_color_attachments: color,
+                input_attachment_count: inputs.len() as u32,
+                p_input_attachments: inputs.as_ptr(),
+                p_depth_stencil_attachment: if has_depth {
+                    depth
+                } else {
+                    std::ptr::null()
+                },
+                ..Default::default()
+            });
+        }
         // External dependenciesensure that the image layout transitions at the
         // right time because we're using synchronization2 for graphics command
         // submission and the semaphore wait+signal operations both occur at
         // COLOR_ATTACHMENT_OUTPUT to minimize their scope. The subpass
         // dependencies are adjusted to match these signal operations.
-        let dependencies = [
-            // input dependency
+        let mut dependencies = vec![
+            // input dependency, loaded attachments are also read
             vk::SubpassDependency {
                 src_subpass: vk::SUBPASS_EXTERNAL,
                 dst_subpass: 0,
                 src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
                 dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
                 src_access_mask: vk::AccessFlags::NONE,
-                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
                 dependency_flags: vk::DependencyFlags::empty(),
             },
             // output dependency
@@ -291,6 +889,62 @@ impl ColorPass {
                 dependency_flags: vk::DependencyFlags::empty(),
             },
         ];
+        if has_depth {
+            // depth reads must wait for the DepthPrePass's depth writes,
+            // which happen in either fragment test stage
+            dependencies.push(vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            });
+        }
+        for (index, subpass) in extra_subpasses.iter().enumerate() {
+            let dst_subpass = index as u32 + 1;
+
+            // each subpass reads and blends over the previous subpass's
+            // output at the same pixel
+            dependencies.push(vk::SubpassDependency {
+                src_subpass: dst_subpass - 1,
+                dst_subpass,
+                src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::INPUT_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::BY_REGION,
+            });
+
+            if subpass.read_color {
+                // lets draws read pixels written by earlier draws in the same
+                // subpass after a pipeline barrier
+                dependencies.push(vk::SubpassDependency {
+                    src_subpass: dst_subpass,
+                    dst_subpass,
+                    src_stage_mask:
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    dst_access_mask: vk::AccessFlags::INPUT_ATTACHMENT_READ,
+                    dependency_flags: vk::DependencyFlags::BY_REGION,
+                });
+            }
+        }
         let create_info = vk::RenderPassCreateInfo {
             attachment_count: attachments.len() as u32,
             p_attachments: attachments.as_ptr(),
@@ -0,0 +1,265 @@
+use {
+    crate::graphics::{
+        vulkan_api::{raii, Frame, RenderDevice},
+        GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// A depth-only render pass which fills a depth buffer before the main color
+/// pass.
+///
+/// Rendering opaque geometry into the depth buffer first means the color pass
+/// can use an `EQUAL` or `LESS_OR_EQUAL` depth test with writes disabled, so
+/// expensive fragment shaders only run once per visible pixel.
+///
+/// The depth buffer is shared by every frame in flight and can be attached to
+/// a ColorPass with `ColorPass::new_with_depth_pre_pass`.
+#[derive(Debug)]
+pub struct DepthPrePass {
+    extent: vk::Extent2D,
+    render_pass: raii::RenderPass,
+    framebuffer: raii::Framebuffer,
+    depth_view: raii::ImageView,
+    depth_image: raii::Image,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl DepthPrePass {
+    /// The format of the shared depth buffer.
+    pub const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+    /// Create the depth buffer and depth-only render pass.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the render device used to create Vulkan resources
+    /// * `extent` - the size of the depth buffer. This should match the
+    ///   swapchain extent when used with a ColorPass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///  - the depth pre-pass must be dropped before the render device
+    ///  - the depth pre-pass must be rebuilt when the swapchain is rebuilt
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<Self, GraphicsError> {
+        let (depth_image, depth_view) =
+            Self::create_depth_buffer(render_device.clone(), extent)?;
+        let render_pass = Self::create_render_pass(render_device.clone())?;
+
+        let raw_depth_view = depth_view.raw();
+        let framebuffer = {
+            let create_info = vk::FramebufferCreateInfo {
+                render_pass: render_pass.raw(),
+                attachment_count: 1,
+                p_attachments: &raw_depth_view,
+                width: extent.width,
+                height: extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            raii::Framebuffer::new(render_device.clone(), &create_info)?
+        };
+
+        Ok(Self {
+            extent,
+            render_pass,
+            framebuffer,
+            depth_view,
+            depth_image,
+            render_device,
+        })
+    }
+
+    /// The size of the depth buffer.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// The depth-only render pass.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// The shared depth buffer. It's in DEPTH_STENCIL_ATTACHMENT_OPTIMAL after
+    /// the render pass.
+    pub fn depth_image(&self) -> &raii::Image {
+        &self.depth_image
+    }
+
+    /// The image view for the shared depth buffer.
+    pub fn depth_view(&self) -> &raii::ImageView {
+        &self.depth_view
+    }
+
+    /// Begin the depth-only render pass. The depth buffer is cleared to 1.0.
+    ///
+    /// Pipelines used inside this render pass should be built with
+    /// `GraphicsPipelineBuilder::depth_only` and depth testing enabled.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the DepthPrePass must not be destroyed until the command buffer
+    ///     finishes executing or is discarded.
+    pub unsafe fn begin_render_pass_inline(&self, frame: &Frame) {
+        let clear_values = [vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        }];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffer.raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl DepthPrePass {
+    /// Create the device-local depth image and a view for it.
+    unsafe fn create_depth_buffer(
+        render_device: Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            format: Self::DEPTH_FORMAT,
+            mip_levels: 1,
+            array_layers: 1,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            tiling: vk::ImageTiling::OPTIMAL,
+            usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::INPUT_ATTACHMENT,
+            flags: vk::ImageCreateFlags::empty(),
+            extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            ..vk::ImageCreateInfo::default()
+        };
+        let image = raii::Image::new_with_hint(
+            render_device.clone(),
+            &create_info,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            raii::AllocationHint::Dedicated,
+        )?;
+        image.set_debug_name("Depth Pre-Pass Depth Buffer");
+
+        let view_create_info = vk::ImageViewCreateInfo {
+            image: image.raw(),
+            view_type: vk::ImageViewType::TYPE_2D,
+            format: Self::DEPTH_FORMAT,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        let view = raii::ImageView::new(render_device, &view_create_info)?;
+
+        Ok((image, view))
+    }
+
+    /// Create a render pass with a single depth-only subpass.
+    ///
+    /// The depth buffer is left in DEPTH_STENCIL_ATTACHMENT_OPTIMAL so the
+    /// color pass can load it without a layout transition.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let attachments = [vk::AttachmentDescription {
+            format: Self::DEPTH_FORMAT,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            flags: vk::AttachmentDescriptionFlags::empty(),
+        }];
+        let depth_attachment = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let subpasses = [vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            p_depth_stencil_attachment: &depth_attachment,
+            ..Default::default()
+        }];
+        let dependencies = [
+            // The depth buffer is shared between frames in flight, so wait
+            // for the previous frame's depth tests to finish before clearing.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+            // Make depth writes visible to the color pass's depth tests.
+            vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: vk::SUBPASS_EXTERNAL,
+                src_stage_mask: vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                dst_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+        let create_info = vk::RenderPassCreateInfo {
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
@@ -1,3 +1,7 @@
 mod color_pass;
+mod depth_pre_pass;
 
-pub use self::color_pass::ColorPass;
+pub use self::{
+    color_pass::{ColorAttachmentOps, ColorLoadOp, ColorPass, ColorSubpass},
+    depth_pre_pass::DepthPrePass,
+};
@@ -0,0 +1,361 @@
+use {
+    crate::graphics::{
+        vulkan_api::{
+            raii, Frame, FramesInFlight, MappedBuffer, PerFrame, RenderDevice,
+        },
+        AllocationError, GraphicsError,
+    },
+    ash::vk,
+    std::sync::Arc,
+};
+
+/// Without resizable BAR, device-local host-visible memory is a small
+/// window, typically 256 MiB, which is shared with the driver.
+const SMALL_BAR_HEAP_SIZE: vk::DeviceSize = 256 * 1024 * 1024;
+
+/// A memory type which is both DEVICE_LOCAL and HOST_VISIBLE, so the CPU can
+/// write directly into memory which the GPU reads at full speed.
+///
+/// Every discrete GPU has a small window of this memory. With resizable BAR
+/// the whole of video memory is host-visible.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HostVisibleDeviceMemory {
+    /// The memory type's property flags. Always includes DEVICE_LOCAL and
+    /// HOST_VISIBLE.
+    pub memory_property_flags: vk::MemoryPropertyFlags,
+
+    /// The size of the heap which the memory type allocates from.
+    pub heap_size: vk::DeviceSize,
+}
+
+/// How a SmartBuffer gets its data to the GPU.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SmartBufferPath {
+    /// Data is written directly into device-local host-visible memory.
+    DirectWrite,
+
+    /// Data is written to a host-visible staging buffer and copied to a
+    /// device-local buffer by the frame's command buffer.
+    StagingCopy,
+}
+
+/// A device-local buffer for each frame in flight, for data which the CPU
+/// rewrites every frame, like uniforms and instance data.
+///
+/// When the device has host-visible device-local memory with room to spare,
+/// `write` copies straight into it. Otherwise `write` fills a staging buffer
+/// and records a copy into the frame's command buffer. `path` reports which
+/// was chosen, so the application can check what the hardware provides.
+///
+/// Either way, shaders read the buffer returned by `buffer(frame)`.
+pub struct SmartBuffer<T: Copy> {
+    len: usize,
+    path: SmartBufferPath,
+    mapped: PerFrame<MappedBuffer<T>>,
+    device_buffers: Option<PerFrame<raii::Buffer>>,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl HostVisibleDeviceMemory {
+    /// Find the device's largest DEVICE_LOCAL and HOST_VISIBLE memory type,
+    /// preferring HOST_COHERENT types. Returns None when the device has no
+    /// such memory, like most integrated GPUs where all memory is host
+    /// memory anyway.
+    pub fn find(render_device: &RenderDevice) -> Option<Self> {
+        let properties = unsafe {
+            render_device.ash().get_physical_device_memory_properties(
+                render_device.physical_device(),
+            )
+        };
+        let required = vk::MemoryPropertyFlags::DEVICE_LOCAL
+            | vk::MemoryPropertyFlags::HOST_VISIBLE;
+        properties.memory_types[..properties.memory_type_count as usize]
+            .iter()
+            .filter(|memory_type| memory_type.property_flags.contains(required))
+            .map(|memory_type| Self {
+                memory_property_flags: memory_type.property_flags,
+                heap_size: properties.memory_heaps
+                    [memory_type.heap_index as usize]
+                    .size,
+            })
+            .max_by_key(|memory| {
+                (
+                    memory
+                        .memory_property_flags
+                        .contains(vk::MemoryPropertyFlags::HOST_COHERENT),
+                    memory.heap_size,
+                )
+            })
+    }
+
+    /// Returns true when the heap is larger than the small BAR window,
+    /// which means resizable BAR (or an equivalent) is enabled.
+    pub fn is_resizable_bar(&self) -> bool {
+        self.heap_size > SMALL_BAR_HEAP_SIZE
+    }
+}
+
+impl<T: Copy> SmartBuffer<T> {
+    /// Create one buffer with room for `len` elements for each frame in
+    /// flight.
+    ///
+    /// # Params
+    ///
+    /// * `render_device` - the device used to create the buffers
+    /// * `frames_in_flight` - the frames which will write the buffer
+    /// * `len` - the number of elements in each frame's buffer
+    /// * `usage` - how shaders use the buffer, like UNIFORM_BUFFER
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - this instance must be dropped before the RenderDevice is destroyed
+    ///   - this instance must not be dropped while a frame which uses it is
+    ///     in flight
+    pub unsafe fn new(
+        render_device: Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        len: usize,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<Self, GraphicsError> {
+        if let Some(mapped) =
+            Self::create_direct(&render_device, frames_in_flight, len, usage)?
+        {
+            return Ok(Self {
+                len,
+                path: SmartBufferPath::DirectWrite,
+                mapped,
+                device_buffers: None,
+                render_device,
+            });
+        }
+
+        let mapped = frames_in_flight.create_per_frame(|index| {
+            let buffer = MappedBuffer::new_host_visible(
+                render_device.clone(),
+                len,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+            )?;
+            buffer.set_debug_name(format!("Frame {index} SmartBuffer Staging"));
+            Ok(buffer)
+        })?;
+        let device_buffers = frames_in_flight.create_per_frame(|index| {
+            let queue_family_index =
+                render_device.graphics_queue().family_index();
+            let create_info = vk::BufferCreateInfo {
+                size: (len.max(1) * std::mem::size_of::<T>()) as vk::DeviceSize,
+                usage: usage | vk::BufferUsageFlags::TRANSFER_DST,
+                queue_family_index_count: 1,
+                p_queue_family_indices: &queue_family_index,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                ..Default::default()
+            };
+            let buffer = raii::Buffer::new(
+                render_device.clone(),
+                &create_info,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+            buffer.set_debug_name(format!("Frame {index} SmartBuffer"));
+            Ok(buffer)
+        })?;
+        log::debug!(
+            "SmartBuffer<{}> with {} elements uses {:?}",
+            std::any::type_name::<T>(),
+            len,
+            SmartBufferPath::StagingCopy
+        );
+
+        Ok(Self {
+            len,
+            path: SmartBufferPath::StagingCopy,
+            mapped,
+            device_buffers: Some(device_buffers),
+            render_device,
+        })
+    }
+
+    /// The number of elements in each frame's buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true when the buffers have no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How data gets to the GPU on this device.
+    pub fn path(&self) -> SmartBufferPath {
+        self.path
+    }
+
+    /// The buffer which shaders read for the given frame.
+    pub fn buffer(&self, frame: &Frame) -> &raii::Buffer {
+        match &self.device_buffers {
+            Some(device_buffers) => &device_buffers[frame],
+            None => self.mapped[frame].buffer(),
+        }
+    }
+
+    /// Every frame's buffer, ordered by frame index. Useful for writing one
+    /// descriptor set per frame.
+    pub fn buffers(&self) -> Vec<&raii::Buffer> {
+        match &self.device_buffers {
+            Some(device_buffers) => device_buffers.iter().collect(),
+            None => self.mapped.iter().map(MappedBuffer::buffer).collect(),
+        }
+    }
+
+    /// Copy `data` into the start of the frame's buffer.
+    ///
+    /// On the staging path this records a copy and a barrier, so the data is
+    /// visible to every command recorded afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` has more than `len()` elements.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the frame's command buffer must be recording and outside of a
+    ///     render pass
+    ///   - the frame's buffer must not be read by commands recorded before
+    ///     this call
+    pub unsafe fn write(
+        &mut self,
+        frame: &Frame,
+        data: &[T],
+    ) -> Result<(), GraphicsError> {
+        let mapped = &mut self.mapped[frame];
+        mapped.write(data);
+        mapped.flush_range(0..data.len())?;
+
+        let device_buffers = match &self.device_buffers {
+            Some(device_buffers) => device_buffers,
+
+            // Host writes made before the frame is submitted are visible to
+            // the frame's commands without a barrier.
+            None => return Ok(()),
+        };
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let device = self.render_device.device();
+        let command_buffer = frame.command_buffer();
+        device.cmd_copy_buffer(
+            command_buffer,
+            mapped.raw(),
+            device_buffers[frame].raw(),
+            &[vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size: (data.len() * std::mem::size_of::<T>()) as vk::DeviceSize,
+            }],
+        );
+        let memory_barrier = vk::MemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::ALL_TRANSFER,
+            src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+            dst_access_mask: vk::AccessFlags2::MEMORY_READ,
+            ..Default::default()
+        };
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                memory_barrier_count: 1,
+                p_memory_barriers: &memory_barrier,
+                ..Default::default()
+            },
+        );
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl<T: Copy> SmartBuffer<T> {
+    /// Create mapped buffers in host-visible device-local memory, or return
+    /// None when the device doesn't have enough of it.
+    unsafe fn create_direct(
+        render_device: &Arc<RenderDevice>,
+        frames_in_flight: &FramesInFlight,
+        len: usize,
+        usage: vk::BufferUsageFlags,
+    ) -> Result<Option<PerFrame<MappedBuffer<T>>>, GraphicsError> {
+        let memory = match HostVisibleDeviceMemory::find(render_device) {
+            Some(memory) => memory,
+            None => {
+                log::debug!("No host-visible device-local memory");
+                return Ok(None);
+            }
+        };
+
+        // Leave most of a small BAR window for the driver and other
+        // resources. With resizable BAR there's plenty of room.
+        let size_in_bytes = (len.max(1)
+            * std::mem::size_of::<T>()
+            * frames_in_flight.frame_count())
+            as vk::DeviceSize;
+        if size_in_bytes > memory.heap_size / 4 {
+            log::debug!(
+                "SmartBuffer<{}> needs {} bytes, which is too much for a {} \
+                 byte host-visible device-local heap",
+                std::any::type_name::<T>(),
+                size_in_bytes,
+                memory.heap_size
+            );
+            return Ok(None);
+        }
+
+        let memory_property_flags = vk::MemoryPropertyFlags::DEVICE_LOCAL
+            | vk::MemoryPropertyFlags::HOST_VISIBLE
+            | (memory.memory_property_flags
+                & vk::MemoryPropertyFlags::HOST_COHERENT);
+        let mapped = frames_in_flight.create_per_frame(|index| {
+            let buffer = MappedBuffer::new(
+                render_device.clone(),
+                len,
+                usage,
+                memory_property_flags,
+            )?;
+            buffer.set_debug_name(format!("Frame {index} SmartBuffer"));
+            Ok(buffer)
+        });
+        match mapped {
+            Ok(mapped) => {
+                log::debug!(
+                    "SmartBuffer<{}> with {} elements uses {:?}, resizable \
+                     BAR: {}",
+                    std::any::type_name::<T>(),
+                    len,
+                    SmartBufferPath::DirectWrite,
+                    memory.is_resizable_bar()
+                );
+                Ok(Some(mapped))
+            }
+            Err(error) if error.find::<AllocationError>().is_some() => {
+                log::debug!(
+                    "Host-visible device-local memory is full: {}",
+                    error
+                );
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl<T: Copy> std::fmt::Debug for SmartBuffer<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmartBuffer")
+            .field("len", &self.len)
+            .field("path", &self.path)
+            .finish()
+    }
+}
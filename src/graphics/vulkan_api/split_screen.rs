@@ -0,0 +1,136 @@
+use ash::vk;
+
+/// One viewport rectangle in a SplitScreen.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ViewportRegion {
+    /// The region's index in the SplitScreen.
+    pub index: usize,
+
+    /// The region's viewport, in framebuffer pixels.
+    pub viewport: vk::Viewport,
+
+    /// A scissor rect covering exactly the viewport.
+    pub scissor: vk::Rect2D,
+}
+
+impl ViewportRegion {
+    /// The region's width divided by its height, for building per-region
+    /// projection matrices.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.viewport.width / self.viewport.height.max(1.0)
+    }
+}
+
+/// Divides a framebuffer into viewport rectangles, e.g. for split-screen
+/// views of a scene with a different camera in each.
+///
+/// Regions are described in normalized coordinates, [0, 1] across the
+/// framebuffer with the origin at the top left, so they can be refit when
+/// the swapchain is rebuilt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitScreen {
+    normalized_rects: Vec<[f32; 4]>,
+    regions: Vec<ViewportRegion>,
+}
+
+// Public API
+// ----------
+
+impl SplitScreen {
+    /// Create viewports from normalized rects.
+    ///
+    /// # Params
+    ///
+    /// * `normalized_rects` - each region as [x, y, width, height], in the
+    ///   range [0, 1] across the framebuffer
+    /// * `extent` - the framebuffer size, typically the swapchain extent
+    pub fn new(normalized_rects: &[[f32; 4]], extent: vk::Extent2D) -> Self {
+        let mut split_screen = Self {
+            normalized_rects: normalized_rects.to_vec(),
+            regions: vec![],
+        };
+        split_screen.resize(extent);
+        split_screen
+    }
+
+    /// Split the framebuffer into equal columns, left to right.
+    pub fn side_by_side(count: usize, extent: vk::Extent2D) -> Self {
+        Self::grid(count, 1, extent)
+    }
+
+    /// Split the framebuffer into equal rows, top to bottom.
+    pub fn stacked(count: usize, extent: vk::Extent2D) -> Self {
+        Self::grid(1, count, extent)
+    }
+
+    /// Split the framebuffer into an equal grid of regions, ordered left to
+    /// right and then top to bottom.
+    pub fn grid(columns: usize, rows: usize, extent: vk::Extent2D) -> Self {
+        let width = 1.0 / columns.max(1) as f32;
+        let height = 1.0 / rows.max(1) as f32;
+        let rects = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| {
+                    [column as f32 * width, row as f32 * height, width, height]
+                })
+            })
+            .collect::<Vec<_>>();
+        Self::new(&rects, extent)
+    }
+
+    /// Refit every region to a new framebuffer size, e.g. after the
+    /// swapchain is rebuilt.
+    pub fn resize(&mut self, extent: vk::Extent2D) {
+        let framebuffer_width = extent.width as f32;
+        let framebuffer_height = extent.height as f32;
+        self.regions = self
+            .normalized_rects
+            .iter()
+            .enumerate()
+            .map(|(index, &[x, y, width, height])| {
+                // Round the edges rather than the sizes so neighboring
+                // regions share edges without gaps or overlap.
+                let left = (x * framebuffer_width).round();
+                let top = (y * framebuffer_height).round();
+                let right = ((x + width) * framebuffer_width).round();
+                let bottom = ((y + height) * framebuffer_height).round();
+                ViewportRegion {
+                    index,
+                    viewport: vk::Viewport {
+                        x: left,
+                        y: top,
+                        width: right - left,
+                        height: bottom - top,
+                        min_depth: 0.0,
+                        max_depth: 1.0,
+                    },
+                    scissor: vk::Rect2D {
+                        offset: vk::Offset2D {
+                            x: left as i32,
+                            y: top as i32,
+                        },
+                        extent: vk::Extent2D {
+                            width: (right - left) as u32,
+                            height: (bottom - top) as u32,
+                        },
+                    },
+                }
+            })
+            .collect();
+    }
+
+    /// Every region, in the order they were given.
+    pub fn regions(&self) -> &[ViewportRegion] {
+        &self.regions
+    }
+
+    /// The number of regions.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// True when there are no regions.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
@@ -4,7 +4,9 @@
 //! exposed on the public api - because it ends up being so verbose.
 
 use {
-    super::Swapchain, crate::graphics::GraphicsError, anyhow::Context, ash::vk,
+    super::Swapchain,
+    crate::graphics::{GraphicsError, SwapchainError},
+    ash::vk,
     ccthw_ash_instance::VulkanHandle,
 };
 
@@ -15,6 +17,12 @@ pub enum SwapchainStatus {
     /// Completed the operation with the given swapchain index.
     Index(usize),
 
+    /// Acquired the given swapchain index, but the swapchain no longer
+    /// matches the surface exactly. The acquire semaphore will still be
+    /// signaled, so the image should be rendered and presented as usual
+    /// before the swapchain is rebuilt. Only returned when acquiring.
+    Suboptimal(usize),
+
     /// Indicates that the swapchain needs to be rebuilt.
     NeedsRebuild,
 }
@@ -33,7 +41,9 @@ impl Swapchain {
     /// # Safety
     ///
     /// The application must correctly handle a swapchain acquisition failure
-    /// and rebuild the swapchain on demand.
+    /// and rebuild the swapchain on demand. A Suboptimal image has been
+    /// acquired and must be presented, otherwise the semaphore is left with
+    /// a pending signal.
     pub unsafe fn acquire_swapchain_image(
         &self,
         semaphore: vk::Semaphore,
@@ -50,11 +60,11 @@ impl Swapchain {
             Ok((index, false)) => Ok(SwapchainStatus::Index(index as usize)),
 
             // index acquired but the swapchain is suboptimal for the surface
-            Ok((_, true)) => {
+            Ok((index, true)) => {
                 log::debug!(
                     "Acquire Image: Swapchain suboptimal, needs rebuild."
                 );
-                Ok(SwapchainStatus::NeedsRebuild)
+                Ok(SwapchainStatus::Suboptimal(index as usize))
             }
 
             // the swapchain is lost and needs to be rebuilt
@@ -63,14 +73,11 @@ impl Swapchain {
                 Ok(SwapchainStatus::NeedsRebuild)
             }
 
-            Err(_) => Err(GraphicsError::RuntimeError(
-                result
-                    .context(
-                        "Unexpected error while acquiring swapchain image!",
-                    )
-                    .err()
-                    .unwrap(),
-            )),
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                Err(SwapchainError::SurfaceLost.into())
+            }
+
+            Err(result) => Err(SwapchainError::AcquireFailed(result).into()),
         }
     }
 
@@ -119,14 +126,13 @@ impl Swapchain {
                 Ok(SwapchainStatus::NeedsRebuild)
             }
 
-            Err(_) => Err(GraphicsError::RuntimeError(
-                result
-                    .context(
-                        "Unexpected error while presenting swapchain image!",
-                    )
-                    .err()
-                    .unwrap(),
-            )),
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                Err(SwapchainError::SurfaceLost.into())
+            }
+
+            Err(result) => {
+                Err(SwapchainError::PresentFailed { index, result }.into())
+            }
         }
     }
 }
@@ -1,5 +1,7 @@
 use {
-    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    crate::graphics::{
+        vulkan_api::RenderDevice, GraphicsError, SwapchainError,
+    },
     anyhow::Context,
     ash::{extensions, vk},
     ccthw_ash_instance::VulkanHandle,
@@ -20,6 +22,7 @@ pub struct Swapchain {
     images: Vec<vk::Image>,
     extent: vk::Extent2D,
     format: vk::SurfaceFormatKHR,
+    image_usage: vk::ImageUsageFlags,
     present_mode: vk::PresentModeKHR,
     swapchain: vk::SwapchainKHR,
     swapchain_loader: extensions::khr::Swapchain,
@@ -64,8 +67,16 @@ impl Swapchain {
             Self::choose_swapchain_extent(capabilities, framebuffer_size);
         let min_image_count = Self::choose_image_count(capabilities);
 
+        // TRANSFER_SRC allows frames to be copied back to the CPU, e.g. by
+        // the FrameRecorder, and INPUT_ATTACHMENT allows ColorPass subpasses
+        // to read the color attachment, but not every surface supports them.
+        let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | (capabilities.supported_usage_flags
+                & (vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::INPUT_ATTACHMENT));
+
         let mut create_info = vk::SwapchainCreateInfoKHR {
-            surface: *render_device.surface(),
+            surface: *render_device.surface()?,
 
             // image settings
             min_image_count,
@@ -73,7 +84,7 @@ impl Swapchain {
             image_color_space: format.color_space,
             image_extent: extent,
             image_array_layers: 1,
-            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            image_usage,
 
             // window system settings
             present_mode,
@@ -112,7 +123,12 @@ impl Swapchain {
         let swapchain = unsafe {
             swapchain_loader
                 .create_swapchain(&create_info, None)
-                .context("Error creating the swapchain!")?
+                .map_err(|result| SwapchainError::CreateFailed {
+                    extent,
+                    format: format.format,
+                    present_mode,
+                    result,
+                })?
         };
 
         let images = unsafe {
@@ -125,6 +141,7 @@ impl Swapchain {
             images,
             extent,
             format,
+            image_usage,
             present_mode,
             swapchain,
             swapchain_loader,
@@ -145,6 +162,11 @@ impl Swapchain {
         &self.images
     }
 
+    /// The number of images in the swapchain.
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
     /// The format used by images in the swapchain.
     pub fn image_format(&self) -> vk::Format {
         self.format.format
@@ -155,6 +177,14 @@ impl Swapchain {
         self.extent
     }
 
+    /// The usage flags the swapchain images were created with.
+    ///
+    /// Always includes COLOR_ATTACHMENT. Includes TRANSFER_SRC when the
+    /// surface supports it.
+    pub fn image_usage(&self) -> vk::ImageUsageFlags {
+        self.image_usage
+    }
+
     /// The presentation mode used by this swapchain.
     pub fn present_mode(&self) -> vk::PresentModeKHR {
         self.present_mode
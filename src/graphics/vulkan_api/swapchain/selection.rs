@@ -1,7 +1,9 @@
 //! Private Swapchain Selection API
 
 use {
-    super::Swapchain, crate::graphics::GraphicsError, anyhow::Context, ash::vk,
+    super::Swapchain,
+    crate::graphics::{GraphicsError, SwapchainError},
+    ash::vk,
 };
 
 impl Swapchain {
@@ -28,7 +30,7 @@ impl Swapchain {
 
         let backup_format = available_formats
             .first()
-            .context("No swapchain formats available!")?;
+            .ok_or(SwapchainError::NoSurfaceFormats)?;
 
         log::trace!("Fall back to swapchain format {:#?}", backup_format);
 
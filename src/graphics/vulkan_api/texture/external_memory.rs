@@ -0,0 +1,430 @@
+use {
+    super::{Texture2D, TextureLoader},
+    crate::graphics::{
+        vulkan_api::{raii, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::{anyhow, Context},
+    ash::{extensions::khr, vk},
+    std::sync::Arc,
+};
+
+/// A handle to image memory which was allocated outside of this application,
+/// or by another Vulkan device.
+///
+/// A successful import transfers ownership of file descriptors to the Vulkan
+/// driver, so `dup` the descriptor first if it is still needed. Windows
+/// handles are not consumed by the import.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExternalImageHandle {
+    /// A Linux DMA-BUF, e.g. from a video decoder, V4L2, or a Wayland client.
+    ///
+    /// Only single-plane formats are supported.
+    #[cfg(unix)]
+    DmaBuf {
+        /// The DMA-BUF file descriptor.
+        fd: std::os::unix::io::RawFd,
+
+        /// The DRM format modifier which describes the buffer's tiling.
+        /// Linear buffers use 0 (DRM_FORMAT_MOD_LINEAR).
+        drm_format_modifier: u64,
+
+        /// The offset of the first pixel in the buffer, in bytes.
+        offset: u64,
+
+        /// The number of bytes between the start of each row.
+        row_pitch: u64,
+    },
+
+    /// An opaque file descriptor exported by another Vulkan or OpenGL
+    /// device with `vkGetMemoryFdKHR`.
+    #[cfg(unix)]
+    OpaqueFd(std::os::unix::io::RawFd),
+
+    /// The shared handle of a D3D11 texture.
+    #[cfg(windows)]
+    D3D11Texture(vk::HANDLE),
+
+    /// An opaque NT handle exported by another Vulkan or OpenGL device with
+    /// `vkGetMemoryWin32HandleKHR`.
+    #[cfg(windows)]
+    OpaqueWin32(vk::HANDLE),
+}
+
+/// Describes an externally allocated image to import as a Texture2D.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExternalImage {
+    /// The handle to the image's memory.
+    pub handle: ExternalImageHandle,
+
+    /// The image's format, which must match how the producer wrote it.
+    pub format: vk::Format,
+
+    /// The image's size in pixels.
+    pub extent: vk::Extent2D,
+}
+
+impl ExternalImage {
+    /// The device extensions required to import external images on this
+    /// platform. External memory itself is part of Vulkan 1.1.
+    pub fn required_device_extensions() -> Vec<String> {
+        #[cfg(unix)]
+        let names = [
+            khr::ExternalMemoryFd::name(),
+            vk::ExtExternalMemoryDmaBufFn::name(),
+            vk::ExtImageDrmFormatModifierFn::name(),
+        ];
+        #[cfg(windows)]
+        let names = [khr::ExternalMemoryWin32::name()];
+
+        names
+            .iter()
+            .map(|name| name.to_owned().into_string().unwrap())
+            .collect()
+    }
+}
+
+impl ExternalImageHandle {
+    /// The Vulkan handle type used to import this handle.
+    pub fn handle_type(&self) -> vk::ExternalMemoryHandleTypeFlags {
+        match self {
+            #[cfg(unix)]
+            Self::DmaBuf { .. } => {
+                vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT
+            }
+            #[cfg(unix)]
+            Self::OpaqueFd(_) => vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD,
+            #[cfg(windows)]
+            Self::D3D11Texture(_) => {
+                vk::ExternalMemoryHandleTypeFlags::D3D11_TEXTURE_KHR
+            }
+            #[cfg(windows)]
+            Self::OpaqueWin32(_) => {
+                vk::ExternalMemoryHandleTypeFlags::OPAQUE_WIN32
+            }
+        }
+    }
+
+    /// The queue family which owns the image before it is imported.
+    ///
+    /// DMA-BUFs may be written by devices outside of Vulkan entirely, so they
+    /// are acquired from the foreign queue family.
+    fn source_queue_family(&self) -> u32 {
+        match self {
+            #[cfg(unix)]
+            Self::DmaBuf { .. } => vk::QUEUE_FAMILY_FOREIGN_EXT,
+            _ => vk::QUEUE_FAMILY_EXTERNAL,
+        }
+    }
+}
+
+impl TextureLoader {
+    /// Import an externally allocated image as a Texture2D without copying
+    /// it.
+    ///
+    /// The render device must be created with
+    /// `ExternalImage::required_device_extensions()`. The image is acquired
+    /// from its producer and left in SHADER_READ_ONLY_OPTIMAL. Blocks until
+    /// the acquire completes.
+    ///
+    /// # Params
+    ///
+    /// * `external_image` - the handle, format, and size of the image
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the caller is responsible for destroying the returned texture
+    ///     before the render device is dropped
+    ///   - the producer must finish writing the image before it is imported,
+    ///     and must not write it while the texture is in use
+    pub unsafe fn import_texture_2d(
+        &mut self,
+        external_image: &ExternalImage,
+    ) -> Result<Texture2D, GraphicsError> {
+        for extension in ExternalImage::required_device_extensions() {
+            if !self.render_device.is_device_extension_enabled(&extension) {
+                return Err(GraphicsError::DeviceExtensionNotEnabled(
+                    extension,
+                ));
+            }
+        }
+
+        let image = create_external_image(&self.render_device, external_image)?;
+        let memory =
+            match import_memory(&self.render_device, external_image, image) {
+                Ok(memory) => memory,
+                Err(error) => {
+                    self.render_device.device().destroy_image(image, None);
+                    return Err(error);
+                }
+            };
+        let image = raii::Image::from_imported_memory(
+            self.render_device.clone(),
+            image,
+            memory,
+        );
+        image.set_debug_name("Imported External Image");
+
+        let image_view = {
+            let create_info = vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D,
+                format: external_image.format,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    level_count: 1,
+                    layer_count: 1,
+                    base_array_layer: 0,
+                    base_mip_level: 0,
+                },
+                ..Default::default()
+            };
+            raii::ImageView::new(self.render_device.clone(), &create_info)?
+        };
+
+        // Acquire ownership from the producer. GENERAL is the layout used
+        // for sharing images across APIs.
+        let acquire_barrier = vk::ImageMemoryBarrier2 {
+            src_stage_mask: vk::PipelineStageFlags2::NONE,
+            src_access_mask: vk::AccessFlags2::NONE,
+            dst_stage_mask: vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
+            old_layout: vk::ImageLayout::GENERAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            src_queue_family_index: external_image.handle.source_queue_family(),
+            dst_queue_family_index: self
+                .render_device
+                .graphics_queue()
+                .family_index(),
+            image: image.raw(),
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        self.render_device.device().cmd_pipeline_barrier2(
+            self.one_time_submit.command_buffer(),
+            &vk::DependencyInfo {
+                image_memory_barrier_count: 1,
+                p_image_memory_barriers: &acquire_barrier,
+                ..Default::default()
+            },
+        );
+        self.one_time_submit.sync_submit_and_reset()?;
+
+        Ok(Texture2D {
+            image,
+            image_view,
+            format: external_image.format,
+            extent: external_image.extent,
+        })
+    }
+}
+
+/// Create an image which can be bound to memory from the external handle.
+unsafe fn create_external_image(
+    render_device: &Arc<RenderDevice>,
+    external_image: &ExternalImage,
+) -> Result<vk::Image, GraphicsError> {
+    // DMA-BUFs describe their own memory layout with a DRM format modifier.
+    #[cfg(unix)]
+    let drm_format_modifier = match external_image.handle {
+        ExternalImageHandle::DmaBuf {
+            drm_format_modifier,
+            offset,
+            row_pitch,
+            ..
+        } => Some((
+            drm_format_modifier,
+            vk::SubresourceLayout {
+                offset,
+                row_pitch,
+                ..Default::default()
+            },
+        )),
+        _ => None,
+    };
+    #[cfg(windows)]
+    let drm_format_modifier: Option<(u64, vk::SubresourceLayout)> = None;
+
+    let drm_format_modifier_info =
+        drm_format_modifier
+            .as_ref()
+            .map(|(modifier, plane_layout)| {
+                vk::ImageDrmFormatModifierExplicitCreateInfoEXT {
+                    drm_format_modifier: *modifier,
+                    drm_format_modifier_plane_count: 1,
+                    p_plane_layouts: plane_layout,
+                    ..Default::default()
+                }
+            });
+    let external_memory_info = vk::ExternalMemoryImageCreateInfo {
+        p_next: drm_format_modifier_info.as_ref().map_or(
+            std::ptr::null(),
+            |info| {
+                info as *const vk::ImageDrmFormatModifierExplicitCreateInfoEXT
+                    as *const std::ffi::c_void
+            },
+        ),
+        handle_types: external_image.handle.handle_type(),
+        ..Default::default()
+    };
+    let tiling = if drm_format_modifier.is_some() {
+        vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT
+    } else {
+        vk::ImageTiling::OPTIMAL
+    };
+
+    let queue_family_index = render_device.graphics_queue().family_index();
+    let create_info = vk::ImageCreateInfo {
+        p_next: &external_memory_info
+            as *const vk::ExternalMemoryImageCreateInfo
+            as *const std::ffi::c_void,
+        image_type: vk::ImageType::TYPE_2D,
+        format: external_image.format,
+        mip_levels: 1,
+        array_layers: 1,
+        initial_layout: vk::ImageLayout::UNDEFINED,
+        samples: vk::SampleCountFlags::TYPE_1,
+        sharing_mode: vk::SharingMode::EXCLUSIVE,
+        queue_family_index_count: 1,
+        p_queue_family_indices: &queue_family_index,
+        tiling,
+        usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC,
+        flags: vk::ImageCreateFlags::empty(),
+        extent: vk::Extent3D {
+            width: external_image.extent.width,
+            height: external_image.extent.height,
+            depth: 1,
+        },
+        ..vk::ImageCreateInfo::default()
+    };
+    let image = render_device
+        .device()
+        .create_image(&create_info, None)
+        .with_context(|| {
+            format!(
+                "Unable to create an image for external memory {:?}",
+                external_image
+            )
+        })?;
+    Ok(image)
+}
+
+/// Import the external handle as a dedicated allocation and bind it to the
+/// image.
+unsafe fn import_memory(
+    render_device: &Arc<RenderDevice>,
+    external_image: &ExternalImage,
+    image: vk::Image,
+) -> Result<vk::DeviceMemory, GraphicsError> {
+    let device = render_device.device();
+    let handle_type = external_image.handle.handle_type();
+    let requirements = device.get_image_memory_requirements(image);
+
+    let dedicated_info = vk::MemoryDedicatedAllocateInfo {
+        image,
+        ..Default::default()
+    };
+    let dedicated_info_ptr = &dedicated_info
+        as *const vk::MemoryDedicatedAllocateInfo
+        as *const std::ffi::c_void;
+
+    let memory = match external_image.handle {
+        #[cfg(unix)]
+        ExternalImageHandle::DmaBuf { fd, .. }
+        | ExternalImageHandle::OpaqueFd(fd) => {
+            let loader =
+                khr::ExternalMemoryFd::new(render_device.ash(), device);
+            let memory_type_bits = if matches!(
+                external_image.handle,
+                ExternalImageHandle::DmaBuf { .. }
+            ) {
+                loader
+                    .get_memory_fd_properties(handle_type, fd)
+                    .context("Unable to query DMA-BUF memory properties")?
+                    .memory_type_bits
+            } else {
+                // Opaque fds must use the memory types reported by the
+                // image's requirements.
+                u32::MAX
+            };
+            let import_info = vk::ImportMemoryFdInfoKHR {
+                p_next: dedicated_info_ptr,
+                handle_type,
+                fd,
+                ..Default::default()
+            };
+            allocate(
+                render_device,
+                &requirements,
+                memory_type_bits,
+                &import_info as *const vk::ImportMemoryFdInfoKHR
+                    as *const std::ffi::c_void,
+            )?
+        }
+        #[cfg(windows)]
+        ExternalImageHandle::D3D11Texture(handle)
+        | ExternalImageHandle::OpaqueWin32(handle) => {
+            let loader =
+                khr::ExternalMemoryWin32::new(render_device.ash(), device);
+            let memory_type_bits = loader
+                .get_memory_win32_handle_properties(handle_type, handle)
+                .context("Unable to query Win32 handle memory properties")?
+                .memory_type_bits;
+            let import_info = vk::ImportMemoryWin32HandleInfoKHR {
+                p_next: dedicated_info_ptr,
+                handle_type,
+                handle,
+                ..Default::default()
+            };
+            allocate(
+                render_device,
+                &requirements,
+                memory_type_bits,
+                &import_info as *const vk::ImportMemoryWin32HandleInfoKHR
+                    as *const std::ffi::c_void,
+            )?
+        }
+    };
+
+    if let Err(error) = device.bind_image_memory(image, memory, 0) {
+        device.free_memory(memory, None);
+        return Err(error.into());
+    }
+    Ok(memory)
+}
+
+/// Allocate memory with the first memory type supported by both the image
+/// and the external handle.
+unsafe fn allocate(
+    render_device: &Arc<RenderDevice>,
+    requirements: &vk::MemoryRequirements,
+    handle_memory_type_bits: u32,
+    p_next: *const std::ffi::c_void,
+) -> Result<vk::DeviceMemory, GraphicsError> {
+    let memory_type_bits =
+        requirements.memory_type_bits & handle_memory_type_bits;
+    if memory_type_bits == 0 {
+        return Err(anyhow!(
+            "No memory type can hold both the image and the external handle"
+        )
+        .into());
+    }
+    let allocate_info = vk::MemoryAllocateInfo {
+        p_next,
+        allocation_size: requirements.size,
+        memory_type_index: memory_type_bits.trailing_zeros(),
+        ..Default::default()
+    };
+    let memory = render_device
+        .device()
+        .allocate_memory(&allocate_info, None)
+        .context("Unable to import external memory")?;
+    Ok(memory)
+}
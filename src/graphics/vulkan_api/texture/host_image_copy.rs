@@ -0,0 +1,341 @@
+use {
+    crate::graphics::{vulkan_api::RenderDevice, GraphicsError},
+    anyhow::Context,
+    ash::vk,
+    std::{ffi::c_void, os::raw::c_char},
+};
+
+// The version of Ash used by this project predates VK_EXT_host_image_copy,
+// so the structures and entry points used by TextureLoader are declared
+// here.
+
+const EXTENSION_NAME: &str = "VK_EXT_host_image_copy";
+
+const PHYSICAL_DEVICE_HOST_IMAGE_COPY_FEATURES: i32 = 1_000_270_000;
+const PHYSICAL_DEVICE_HOST_IMAGE_COPY_PROPERTIES: i32 = 1_000_270_001;
+const MEMORY_TO_IMAGE_COPY: i32 = 1_000_270_002;
+const COPY_MEMORY_TO_IMAGE_INFO: i32 = 1_000_270_005;
+const HOST_IMAGE_LAYOUT_TRANSITION_INFO: i32 = 1_000_270_006;
+
+/// VK_IMAGE_USAGE_HOST_TRANSFER_BIT_EXT
+const IMAGE_USAGE_HOST_TRANSFER: u32 = 0x0040_0000;
+
+/// VK_FORMAT_FEATURE_2_HOST_IMAGE_TRANSFER_BIT_EXT
+const FORMAT_FEATURE_HOST_IMAGE_TRANSFER: u64 = 0x4000_0000_0000;
+
+#[repr(C)]
+struct PhysicalDeviceHostImageCopyFeatures {
+    s_type: vk::StructureType,
+    p_next: *mut c_void,
+    host_image_copy: vk::Bool32,
+}
+
+#[repr(C)]
+struct PhysicalDeviceHostImageCopyProperties {
+    s_type: vk::StructureType,
+    p_next: *mut c_void,
+    copy_src_layout_count: u32,
+    p_copy_src_layouts: *mut vk::ImageLayout,
+    copy_dst_layout_count: u32,
+    p_copy_dst_layouts: *mut vk::ImageLayout,
+    optimal_tiling_layout_uuid: [u8; vk::UUID_SIZE],
+    identical_memory_type_requirements: vk::Bool32,
+}
+
+#[repr(C)]
+struct MemoryToImageCopy {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    p_host_pointer: *const c_void,
+    memory_row_length: u32,
+    memory_image_height: u32,
+    image_subresource: vk::ImageSubresourceLayers,
+    image_offset: vk::Offset3D,
+    image_extent: vk::Extent3D,
+}
+
+#[repr(C)]
+struct CopyMemoryToImageInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: vk::Flags,
+    dst_image: vk::Image,
+    dst_image_layout: vk::ImageLayout,
+    region_count: u32,
+    p_regions: *const MemoryToImageCopy,
+}
+
+#[repr(C)]
+struct HostImageLayoutTransitionInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    subresource_range: vk::ImageSubresourceRange,
+}
+
+type CopyMemoryToImageFn = unsafe extern "system" fn(
+    vk::Device,
+    *const CopyMemoryToImageInfo,
+) -> vk::Result;
+
+type TransitionImageLayoutFn = unsafe extern "system" fn(
+    vk::Device,
+    u32,
+    *const HostImageLayoutTransitionInfo,
+) -> vk::Result;
+
+/// How TextureLoader copied pixels into a texture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextureUploadPath {
+    /// Pixels were written to a staging buffer and copied to the image by
+    /// the graphics queue.
+    StagingBuffer,
+
+    /// Pixels were copied directly from host memory to the image with
+    /// VK_EXT_host_image_copy.
+    HostImageCopy,
+}
+
+/// The VK_EXT_host_image_copy entry points.
+pub(super) struct HostImageCopy {
+    copy_memory_to_image: CopyMemoryToImageFn,
+    transition_image_layout: TransitionImageLayoutFn,
+}
+
+impl HostImageCopy {
+    /// The name of the device extension for host image copies.
+    pub(super) fn extension_name() -> String {
+        EXTENSION_NAME.to_owned()
+    }
+
+    /// The image usage flag required for host copies.
+    pub(super) fn usage() -> vk::ImageUsageFlags {
+        vk::ImageUsageFlags::from_raw(IMAGE_USAGE_HOST_TRANSFER)
+    }
+
+    /// Load the extension's entry points.
+    ///
+    /// Returns None when the extension isn't enabled, the device doesn't
+    /// support the `hostImageCopy` feature, or the device can't copy directly
+    /// into SHADER_READ_ONLY_OPTIMAL images.
+    pub(super) fn load(render_device: &RenderDevice) -> Option<Self> {
+        if !render_device.is_device_extension_enabled(EXTENSION_NAME) {
+            return None;
+        }
+        if !Self::is_feature_supported(render_device)
+            || !Self::can_copy_to_shader_read_only(render_device)
+        {
+            log::warn!(
+                "{} is enabled but can't copy to sampled images",
+                EXTENSION_NAME
+            );
+            return None;
+        }
+
+        let device = unsafe { render_device.device() };
+        let get_device_proc_addr =
+            render_device.ash().fp_v1_0().get_device_proc_addr;
+        let load = |name: &[u8]| unsafe {
+            get_device_proc_addr(
+                device.handle(),
+                name.as_ptr() as *const c_char,
+            )
+        };
+        let copy_memory_to_image = load(b"vkCopyMemoryToImageEXT\0")?;
+        let transition_image_layout = load(b"vkTransitionImageLayoutEXT\0")?;
+        unsafe {
+            // SAFE because the pointers were loaded by name for the
+            // matching entry points.
+            Some(Self {
+                copy_memory_to_image: std::mem::transmute::<
+                    unsafe extern "system" fn(),
+                    CopyMemoryToImageFn,
+                >(copy_memory_to_image),
+                transition_image_layout: std::mem::transmute::<
+                    unsafe extern "system" fn(),
+                    TransitionImageLayoutFn,
+                >(
+                    transition_image_layout
+                ),
+            })
+        }
+    }
+
+    /// Returns true when the format can be the destination of a host copy.
+    pub(super) fn is_format_supported(
+        render_device: &RenderDevice,
+        format: vk::Format,
+    ) -> bool {
+        let mut format_properties_3 = vk::FormatProperties3::default();
+        let mut format_properties = vk::FormatProperties2 {
+            p_next: &mut format_properties_3 as *mut vk::FormatProperties3
+                as *mut c_void,
+            ..Default::default()
+        };
+        unsafe {
+            // SAFE because the physical device outlives the render device.
+            render_device.ash().get_physical_device_format_properties2(
+                render_device.physical_device(),
+                format,
+                &mut format_properties,
+            );
+        }
+        format_properties_3.optimal_tiling_features.as_raw()
+            & FORMAT_FEATURE_HOST_IMAGE_TRANSFER
+            != 0
+    }
+
+    /// Copy tightly-packed pixels into the first mip level and layer of a
+    /// color image, leaving it in SHADER_READ_ONLY_OPTIMAL.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the image must have been created with `HostImageCopy::usage()`
+    ///     and must not be in use by the GPU
+    ///   - `pixels` must hold the entire extent in the image's format
+    pub(super) unsafe fn copy_to_image(
+        &self,
+        render_device: &RenderDevice,
+        image: vk::Image,
+        extent: vk::Extent2D,
+        pixels: &[u8],
+    ) -> Result<(), GraphicsError> {
+        let device = render_device.device().handle();
+        let transition = HostImageLayoutTransitionInfo {
+            s_type: vk::StructureType::from_raw(
+                HOST_IMAGE_LAYOUT_TRANSITION_INFO,
+            ),
+            p_next: std::ptr::null(),
+            image,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+        };
+        (self.transition_image_layout)(device, 1, &transition)
+            .result()
+            .context("Unable to transition the image layout from the host")?;
+
+        let region = MemoryToImageCopy {
+            s_type: vk::StructureType::from_raw(MEMORY_TO_IMAGE_COPY),
+            p_next: std::ptr::null(),
+            p_host_pointer: pixels.as_ptr() as *const c_void,
+            memory_row_length: 0,
+            memory_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+        };
+        let copy_info = CopyMemoryToImageInfo {
+            s_type: vk::StructureType::from_raw(COPY_MEMORY_TO_IMAGE_INFO),
+            p_next: std::ptr::null(),
+            flags: 0,
+            dst_image: image,
+            dst_image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            region_count: 1,
+            p_regions: &region,
+        };
+        (self.copy_memory_to_image)(device, &copy_info)
+            .result()
+            .context("Unable to copy pixels to the image from the host")?;
+        Ok(())
+    }
+}
+
+impl HostImageCopy {
+    /// Returns true when the physical device supports the `hostImageCopy`
+    /// feature.
+    fn is_feature_supported(render_device: &RenderDevice) -> bool {
+        let mut host_image_copy_features =
+            PhysicalDeviceHostImageCopyFeatures {
+                s_type: vk::StructureType::from_raw(
+                    PHYSICAL_DEVICE_HOST_IMAGE_COPY_FEATURES,
+                ),
+                p_next: std::ptr::null_mut(),
+                host_image_copy: vk::FALSE,
+            };
+        let mut features = vk::PhysicalDeviceFeatures2 {
+            p_next: &mut host_image_copy_features
+                as *mut PhysicalDeviceHostImageCopyFeatures
+                as *mut c_void,
+            ..Default::default()
+        };
+        unsafe {
+            // SAFE because the physical device outlives the render device.
+            render_device.ash().get_physical_device_features2(
+                render_device.physical_device(),
+                &mut features,
+            );
+        }
+        host_image_copy_features.host_image_copy == vk::TRUE
+    }
+
+    /// Returns true when host copies can write directly to images in
+    /// SHADER_READ_ONLY_OPTIMAL.
+    fn can_copy_to_shader_read_only(render_device: &RenderDevice) -> bool {
+        let query = |properties: &mut PhysicalDeviceHostImageCopyProperties| {
+            let mut properties_2 = vk::PhysicalDeviceProperties2 {
+                p_next: properties as *mut PhysicalDeviceHostImageCopyProperties
+                    as *mut c_void,
+                ..Default::default()
+            };
+            unsafe {
+                // SAFE because the physical device outlives the render
+                // device.
+                render_device.ash().get_physical_device_properties2(
+                    render_device.physical_device(),
+                    &mut properties_2,
+                );
+            }
+        };
+        let mut properties = PhysicalDeviceHostImageCopyProperties {
+            s_type: vk::StructureType::from_raw(
+                PHYSICAL_DEVICE_HOST_IMAGE_COPY_PROPERTIES,
+            ),
+            p_next: std::ptr::null_mut(),
+            copy_src_layout_count: 0,
+            p_copy_src_layouts: std::ptr::null_mut(),
+            copy_dst_layout_count: 0,
+            p_copy_dst_layouts: std::ptr::null_mut(),
+            optimal_tiling_layout_uuid: [0; vk::UUID_SIZE],
+            identical_memory_type_requirements: vk::FALSE,
+        };
+
+        // The first query gets the number of layouts, the second fills them.
+        query(&mut properties);
+        let mut copy_dst_layouts = vec![
+            vk::ImageLayout::UNDEFINED;
+            properties.copy_dst_layout_count
+                as usize
+        ];
+        properties.copy_src_layout_count = 0;
+        properties.p_copy_dst_layouts = copy_dst_layouts.as_mut_ptr();
+        query(&mut properties);
+        copy_dst_layouts.truncate(properties.copy_dst_layout_count as usize);
+
+        copy_dst_layouts.contains(&vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    }
+}
+
+impl std::fmt::Debug for HostImageCopy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostImageCopy").finish()
+    }
+}
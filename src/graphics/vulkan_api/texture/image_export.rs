@@ -0,0 +1,281 @@
+use {
+    super::Texture2D,
+    crate::graphics::{
+        vulkan_api::{raii, OneTimeSubmitCommandBuffer, RenderDevice},
+        GraphicsError,
+    },
+    anyhow::{anyhow, Context},
+    ash::vk,
+    std::{path::Path, sync::Arc},
+};
+
+impl Texture2D {
+    /// Copy the texture back to the CPU.
+    ///
+    /// Blocks until the GPU finishes the copy. The texture must be in the
+    /// SHADER_READ_ONLY_OPTIMAL layout and is returned to that layout.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the texture must not be in use by any in-flight commands
+    pub unsafe fn read_back_to_image(
+        &self,
+        render_device: Arc<RenderDevice>,
+    ) -> Result<image::RgbaImage, GraphicsError> {
+        read_image_to_rgba8(
+            render_device,
+            &self.image,
+            self.format,
+            self.extent,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )
+    }
+
+    /// Copy the texture back to the CPU and save it as a PNG.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the texture must not be in use by any in-flight commands
+    pub unsafe fn save_png(
+        &self,
+        render_device: Arc<RenderDevice>,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GraphicsError> {
+        save_rgba8_png(&self.read_back_to_image(render_device)?, path)
+    }
+}
+
+/// Copy a 2D color image back to the CPU and convert it to RGBA8.
+///
+/// This handles the layout transitions, the copy into a host-visible staging
+/// buffer, and conversion from the image's format. Supported formats are the
+/// 8-bit RGBA and BGRA formats, R16G16B16A16_SFLOAT, and R32G32B32A32_SFLOAT.
+/// Float values are clamped to [0, 1].
+///
+/// Blocks until the GPU finishes the copy.
+///
+/// # Params
+///
+/// * `render_device` - the device which owns the image
+/// * `image` - the image to read. It must have been created with
+///   TRANSFER_SRC usage.
+/// * `format` - the image's format
+/// * `extent` - the image's size in pixels
+/// * `layout` - the image's current layout. The image is returned to this
+///   layout after the copy.
+///
+/// # Safety
+///
+/// Unsafe because:
+///   - the image must not be in use by any in-flight commands
+pub unsafe fn read_image_to_rgba8(
+    render_device: Arc<RenderDevice>,
+    image: &raii::Image,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layout: vk::ImageLayout,
+) -> Result<image::RgbaImage, GraphicsError> {
+    let bytes_per_pixel = bytes_per_pixel(format)?;
+    let pixel_count = (extent.width * extent.height) as usize;
+    let size = (pixel_count * bytes_per_pixel) as u64;
+
+    let queue_family_index = render_device.graphics_queue().family_index();
+    let staging_buffer = raii::Buffer::new(
+        render_device.clone(),
+        &vk::BufferCreateInfo {
+            size,
+            usage: vk::BufferUsageFlags::TRANSFER_DST,
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_index_count: 1,
+            p_queue_family_indices: &queue_family_index,
+            ..Default::default()
+        },
+        vk::MemoryPropertyFlags::HOST_VISIBLE
+            | vk::MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+    staging_buffer.set_debug_name("Image Readback Staging Buffer");
+
+    let mut one_time_submit = OneTimeSubmitCommandBuffer::new(
+        render_device.clone(),
+        render_device.graphics_queue().clone(),
+    )?;
+    let command_buffer = one_time_submit.command_buffer();
+    let device = render_device.device();
+    let subresource_range = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    let barrier_before = vk::ImageMemoryBarrier2 {
+        src_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+        src_access_mask: vk::AccessFlags2::MEMORY_WRITE,
+        dst_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+        dst_access_mask: vk::AccessFlags2::TRANSFER_READ,
+        old_layout: layout,
+        new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        image: image.raw(),
+        subresource_range,
+        ..Default::default()
+    };
+    device.cmd_pipeline_barrier2(
+        command_buffer,
+        &vk::DependencyInfo {
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &barrier_before,
+            ..Default::default()
+        },
+    );
+
+    let region = vk::BufferImageCopy2 {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        image_offset: vk::Offset3D::default(),
+        image_extent: vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+        ..Default::default()
+    };
+    device.cmd_copy_image_to_buffer2(
+        command_buffer,
+        &vk::CopyImageToBufferInfo2 {
+            src_image: image.raw(),
+            src_image_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            dst_buffer: staging_buffer.raw(),
+            region_count: 1,
+            p_regions: &region,
+            ..Default::default()
+        },
+    );
+
+    let barrier_after = vk::ImageMemoryBarrier2 {
+        src_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+        src_access_mask: vk::AccessFlags2::NONE,
+        dst_stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+        dst_access_mask: vk::AccessFlags2::MEMORY_READ
+            | vk::AccessFlags2::MEMORY_WRITE,
+        old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        new_layout: layout,
+        image: image.raw(),
+        subresource_range,
+        ..Default::default()
+    };
+    let buffer_barrier = vk::BufferMemoryBarrier2 {
+        src_stage_mask: vk::PipelineStageFlags2::TRANSFER,
+        src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        dst_stage_mask: vk::PipelineStageFlags2::HOST,
+        dst_access_mask: vk::AccessFlags2::HOST_READ,
+        buffer: staging_buffer.raw(),
+        offset: 0,
+        size: vk::WHOLE_SIZE,
+        ..Default::default()
+    };
+    device.cmd_pipeline_barrier2(
+        command_buffer,
+        &vk::DependencyInfo {
+            buffer_memory_barrier_count: 1,
+            p_buffer_memory_barriers: &buffer_barrier,
+            image_memory_barrier_count: 1,
+            p_image_memory_barriers: &barrier_after,
+            ..Default::default()
+        },
+    );
+
+    one_time_submit.sync_submit_and_reset()?;
+
+    let ptr = staging_buffer.allocation().map(device)?;
+    let data = std::slice::from_raw_parts(ptr as *const u8, size as usize);
+    let rgba = convert_to_rgba8(format, data);
+    staging_buffer.allocation().unmap(device)?;
+
+    let image = image::RgbaImage::from_raw(extent.width, extent.height, rgba)
+        .context("Readback data does not match the image size")?;
+    Ok(image)
+}
+
+/// Save RGBA8 pixels as a PNG file.
+pub fn save_rgba8_png(
+    image: &image::RgbaImage,
+    path: impl AsRef<Path>,
+) -> Result<(), GraphicsError> {
+    image
+        .save_with_format(&path, image::ImageFormat::Png)
+        .with_context(|| {
+            format!("Unable to save png to {:?}", path.as_ref())
+        })?;
+    Ok(())
+}
+
+/// The size of one pixel for each supported readback format.
+pub(crate) fn bytes_per_pixel(
+    format: vk::Format,
+) -> Result<usize, GraphicsError> {
+    let size = match format {
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB => 4,
+        vk::Format::R16G16B16A16_SFLOAT => 8,
+        vk::Format::R32G32B32A32_SFLOAT => 16,
+        _ => {
+            return Err(anyhow!(
+                "Unsupported format for image readback: {:?}",
+                format
+            )
+            .into())
+        }
+    };
+    Ok(size)
+}
+
+/// Convert tightly-packed pixels in a supported format to RGBA8.
+pub(crate) fn convert_to_rgba8(format: vk::Format, data: &[u8]) -> Vec<u8> {
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match format {
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => data
+            .chunks_exact(4)
+            .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+            .collect(),
+        vk::Format::R16G16B16A16_SFLOAT => data
+            .chunks_exact(2)
+            .map(|half| {
+                to_u8(f16_to_f32(u16::from_le_bytes([half[0], half[1]])))
+            })
+            .collect(),
+        vk::Format::R32G32B32A32_SFLOAT => data
+            .chunks_exact(4)
+            .map(|float| {
+                to_u8(f32::from_le_bytes([
+                    float[0], float[1], float[2], float[3],
+                ]))
+            })
+            .collect(),
+        _ => data.to_vec(),
+    }
+}
+
+/// Convert an IEEE 754 half-precision float to f32.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2.0f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    }
+}
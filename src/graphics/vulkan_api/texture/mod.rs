@@ -8,15 +8,33 @@ use {
     std::{path::Path, sync::Arc},
 };
 
+mod external_memory;
+mod host_image_copy;
+mod image_export;
+
+use self::host_image_copy::HostImageCopy;
+
+pub use self::{
+    external_memory::{ExternalImage, ExternalImageHandle},
+    host_image_copy::TextureUploadPath,
+    image_export::{read_image_to_rgba8, save_rgba8_png},
+};
+
+pub(crate) use self::image_export::{bytes_per_pixel, convert_to_rgba8};
+
 /// Represents a 2D rgba texture which can be used by shaders.
 pub struct Texture2D {
     pub image_view: raii::ImageView,
     pub image: raii::Image,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
 }
 
 pub struct TextureLoader {
     staging_buffer: raii::Buffer,
     one_time_submit: OneTimeSubmitCommandBuffer,
+    host_image_copy: Option<HostImageCopy>,
+    last_upload_path: Option<TextureUploadPath>,
     render_device: Arc<RenderDevice>,
 }
 
@@ -24,6 +42,10 @@ impl TextureLoader {
     /// Create a new Texture Loader which can build textures from images on the
     /// harddrive.
     ///
+    /// When the render device was created with
+    /// `TextureLoader::host_image_copy_extension_name()`, textures are copied
+    /// directly from host memory instead of through a staging buffer.
+    ///
     /// # Safety
     ///
     /// Unsafe because:
@@ -42,13 +64,42 @@ impl TextureLoader {
             render_device.graphics_queue().clone(),
         )?;
 
+        let host_image_copy = HostImageCopy::load(&render_device);
+        log::debug!(
+            "TextureLoader host image copies available: {}",
+            host_image_copy.is_some()
+        );
+
         Ok(Self {
             staging_buffer,
             one_time_submit,
+            host_image_copy,
+            last_upload_path: None,
             render_device,
         })
     }
 
+    /// The optional device extension which lets the loader skip staging
+    /// buffers.
+    ///
+    /// The device must also support the `hostImageCopy` feature. The feature
+    /// can't be chained by PhysicalDeviceFeatures, so it is checked here
+    /// rather than required at device creation.
+    pub fn host_image_copy_extension_name() -> String {
+        HostImageCopy::extension_name()
+    }
+
+    /// Returns true when textures can be copied directly from host memory.
+    pub fn is_host_image_copy_available(&self) -> bool {
+        self.host_image_copy.is_some()
+    }
+
+    /// How the most recent texture's pixels were uploaded, or None if no
+    /// texture has been created yet.
+    pub fn last_upload_path(&self) -> Option<TextureUploadPath> {
+        self.last_upload_path
+    }
+
     /// Read image data from a file on disk and create a 2D texture.
     ///
     /// # Safety
@@ -60,6 +111,11 @@ impl TextureLoader {
         &mut self,
         texture_path: impl AsRef<Path>,
     ) -> Result<Texture2D, GraphicsError> {
+        let _span = tracing::info_span!(
+            "load_texture_2d",
+            path = %texture_path.as_ref().display()
+        )
+        .entered();
         let img = image::io::Reader::open(&texture_path)
             .with_context(|| {
                 format!(
@@ -76,23 +132,45 @@ impl TextureLoader {
             })?
             .into_rgba8();
 
-        self.resize_staging_buffer(
-            self.render_device.clone(),
-            (img.as_raw().len() * std::mem::size_of::<u8>()) as u64,
-        )?;
+        self.create_texture_2d_from_rgba8(
+            img.width(),
+            img.height(),
+            img.as_raw(),
+        )
+    }
 
-        // Write image data into the staging buffer
-        unsafe {
-            let ptr = self
-                .staging_buffer
-                .allocation()
-                .map(self.render_device.device())?;
-            assert!(ptr as usize % std::mem::align_of::<u8>() == 0);
-            let data = std::slice::from_raw_parts_mut(
-                ptr as *mut u8,
-                img.as_raw().len(),
-            );
-            data.copy_from_slice(img.as_raw());
+    /// Create a 2D texture from tightly-packed RGBA8 pixel data.
+    ///
+    /// # Params
+    ///
+    /// * `width` - the width of the texture in pixels
+    /// * `height` - the height of the texture in pixels
+    /// * `pixels` - `width * height * 4` bytes of pixel data, row by row
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    /// - the caller is responsible for destroying the returned texture before
+    ///   render device is dropped
+    #[tracing::instrument(skip(self, pixels))]
+    pub unsafe fn create_texture_2d_from_rgba8(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Texture2D, GraphicsError> {
+        debug_assert!(pixels.len() == (width * height * 4) as usize);
+
+        let host_image_copy = self.host_image_copy.as_ref().filter(|_| {
+            HostImageCopy::is_format_supported(
+                &self.render_device,
+                vk::Format::R8G8B8A8_UNORM,
+            )
+        });
+        let host_image_copy_usage = if host_image_copy.is_some() {
+            HostImageCopy::usage()
+        } else {
+            vk::ImageUsageFlags::empty()
         };
 
         let image = unsafe {
@@ -110,11 +188,13 @@ impl TextureLoader {
                 p_queue_family_indices: &queue_family_index,
                 tiling: vk::ImageTiling::OPTIMAL,
                 usage: vk::ImageUsageFlags::TRANSFER_DST
-                    | vk::ImageUsageFlags::SAMPLED,
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::SAMPLED
+                    | host_image_copy_usage,
                 flags: vk::ImageCreateFlags::empty(),
                 extent: vk::Extent3D {
-                    width: img.width(),
-                    height: img.height(),
+                    width,
+                    height,
                     depth: 1,
                 },
                 ..vk::ImageCreateInfo::default()
@@ -143,6 +223,41 @@ impl TextureLoader {
             raii::ImageView::new(self.render_device.clone(), &create_info)?
         };
 
+        let texture = Texture2D {
+            image,
+            image_view,
+            format: vk::Format::R8G8B8A8_UNORM,
+            extent: vk::Extent2D { width, height },
+        };
+
+        if let Some(host_image_copy) = host_image_copy {
+            host_image_copy.copy_to_image(
+                &self.render_device,
+                texture.image.raw(),
+                texture.extent,
+                pixels,
+            )?;
+            self.last_upload_path = Some(TextureUploadPath::HostImageCopy);
+            return Ok(texture);
+        }
+
+        self.resize_staging_buffer(
+            self.render_device.clone(),
+            (pixels.len() * std::mem::size_of::<u8>()) as u64,
+        )?;
+
+        // Write image data into the staging buffer
+        unsafe {
+            let ptr = self
+                .staging_buffer
+                .allocation()
+                .map(self.render_device.device())?;
+            assert!(ptr as usize % std::mem::align_of::<u8>() == 0);
+            let data =
+                std::slice::from_raw_parts_mut(ptr as *mut u8, pixels.len());
+            data.copy_from_slice(pixels);
+        };
+
         unsafe {
             let image_memory_barrier_before = vk::ImageMemoryBarrier2 {
                 src_stage_mask: vk::PipelineStageFlags2::TOP_OF_PIPE,
@@ -151,7 +266,7 @@ impl TextureLoader {
                 dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
                 old_layout: vk::ImageLayout::UNDEFINED,
                 new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                image: image.raw(),
+                image: texture.image.raw(),
                 subresource_range: vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::COLOR,
                     base_mip_level: 0,
@@ -186,15 +301,15 @@ impl TextureLoader {
                 },
                 image_offset: vk::Offset3D::default(),
                 image_extent: vk::Extent3D {
-                    width: img.width(),
-                    height: img.height(),
+                    width,
+                    height,
                     depth: 1,
                 },
                 ..Default::default()
             };
             let copy_buffer_to_image_info2 = vk::CopyBufferToImageInfo2 {
                 src_buffer: self.staging_buffer.raw(),
-                dst_image: image.raw(),
+                dst_image: texture.image.raw(),
                 dst_image_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
                 region_count: 1,
                 p_regions: &regions,
@@ -212,7 +327,7 @@ impl TextureLoader {
                 dst_access_mask: vk::AccessFlags2::SHADER_SAMPLED_READ,
                 old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
                 new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
-                image: image.raw(),
+                image: texture.image.raw(),
                 subresource_range: vk::ImageSubresourceRange {
                     aspect_mask: vk::ImageAspectFlags::COLOR,
                     base_mip_level: 0,
@@ -239,7 +354,8 @@ impl TextureLoader {
         // Queue Submission
         self.one_time_submit.sync_submit_and_reset()?;
 
-        Ok(Texture2D { image, image_view })
+        self.last_upload_path = Some(TextureUploadPath::StagingBuffer);
+        Ok(texture)
     }
 }
 
@@ -0,0 +1,210 @@
+mod tracked_layout;
+
+use ash::vk;
+
+pub use self::tracked_layout::TrackedLayout;
+
+/// An image handle which remembers how each of its subresources was last
+/// used, so transitions can be recorded without writing barriers by hand.
+///
+/// The TrackedImage does not own the image. It only tracks commands recorded
+/// through it, so any layout change made elsewhere, like a render pass's
+/// final layout, must be reported with `assume_layout`.
+///
+/// Tracking happens while commands are recorded, so the commands must be
+/// submitted in the same order they were recorded.
+#[derive(Debug, Clone)]
+pub struct TrackedImage {
+    image: vk::Image,
+    aspect_mask: vk::ImageAspectFlags,
+    mip_levels: u32,
+    array_layers: u32,
+    layouts: Vec<TrackedLayout>,
+}
+
+// Public API
+// ----------
+
+impl TrackedImage {
+    /// Start tracking an image.
+    ///
+    /// # Params
+    ///
+    /// * `image` - the image to track. Swapchain images can be used directly.
+    /// * `aspect_mask` - the aspects included in every barrier
+    /// * `mip_levels` - the number of mip levels in the image
+    /// * `array_layers` - the number of array layers in the image
+    /// * `layout` - the current usage of every subresource, typically
+    ///   `Undefined` for a new image
+    pub fn new(
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+        mip_levels: u32,
+        array_layers: u32,
+        layout: TrackedLayout,
+    ) -> Self {
+        Self {
+            image,
+            aspect_mask,
+            mip_levels,
+            array_layers,
+            layouts: vec![layout; (mip_levels * array_layers) as usize],
+        }
+    }
+
+    /// The raw image handle.
+    pub fn raw(&self) -> vk::Image {
+        self.image
+    }
+
+    /// The tracked usage of a single subresource.
+    pub fn layout(&self, mip_level: u32, array_layer: u32) -> TrackedLayout {
+        self.layouts[self.index(mip_level, array_layer)]
+    }
+
+    /// Record that every subresource is now in the given usage without
+    /// recording a barrier, e.g. after a render pass transitions the image.
+    pub fn assume_layout(&mut self, layout: TrackedLayout) {
+        for tracked in &mut self.layouts {
+            *tracked = layout;
+        }
+    }
+
+    /// Transition every subresource to the given usage.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording
+    ///   - the image must outlive the command buffer's execution
+    pub unsafe fn transition_to(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        layout: TrackedLayout,
+    ) {
+        self.transition_range_to(
+            device,
+            command_buffer,
+            0..self.mip_levels,
+            0..self.array_layers,
+            layout,
+        );
+    }
+
+    /// Transition some of the image's subresources to the given usage.
+    ///
+    /// Only the barriers which are needed are recorded. Subresources which
+    /// are already in the target usage are skipped when the usage is
+    /// read-only, and adjacent array layers with the same previous usage
+    /// share a single barrier.
+    ///
+    /// # Params
+    ///
+    /// * `mip_levels` - the mip levels to transition
+    /// * `array_layers` - the array layers to transition
+    /// * `layout` - the new usage
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the command buffer must be recording
+    ///   - the image must outlive the command buffer's execution
+    ///   - the ranges must be inside of the image
+    pub unsafe fn transition_range_to(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        mip_levels: std::ops::Range<u32>,
+        array_layers: std::ops::Range<u32>,
+        layout: TrackedLayout,
+    ) {
+        let mut barriers = vec![];
+        for mip_level in mip_levels {
+            let mut run: Option<(TrackedLayout, u32, u32)> = None;
+            for array_layer in array_layers.clone() {
+                let index = self.index(mip_level, array_layer);
+                let previous = self.layouts[index];
+                self.layouts[index] = layout;
+
+                let needs_barrier =
+                    !(previous == layout && layout.is_read_only());
+                run = match (run, needs_barrier) {
+                    (Some((run_layout, base, count)), true)
+                        if run_layout == previous =>
+                    {
+                        Some((run_layout, base, count + 1))
+                    }
+                    (run, needs_barrier) => {
+                        if let Some((run_layout, base, count)) = run {
+                            barriers.push(self.barrier(
+                                run_layout, layout, mip_level, base, count,
+                            ));
+                        }
+                        if needs_barrier {
+                            Some((previous, array_layer, 1))
+                        } else {
+                            None
+                        }
+                    }
+                };
+            }
+            if let Some((run_layout, base, count)) = run {
+                barriers.push(
+                    self.barrier(run_layout, layout, mip_level, base, count),
+                );
+            }
+        }
+
+        if barriers.is_empty() {
+            return;
+        }
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo {
+                image_memory_barrier_count: barriers.len() as u32,
+                p_image_memory_barriers: barriers.as_ptr(),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+// Private API
+// -----------
+
+impl TrackedImage {
+    fn index(&self, mip_level: u32, array_layer: u32) -> usize {
+        debug_assert!(mip_level < self.mip_levels);
+        debug_assert!(array_layer < self.array_layers);
+        (mip_level * self.array_layers + array_layer) as usize
+    }
+
+    /// Build a barrier for a run of array layers in one mip level.
+    fn barrier(
+        &self,
+        from: TrackedLayout,
+        to: TrackedLayout,
+        mip_level: u32,
+        base_array_layer: u32,
+        layer_count: u32,
+    ) -> vk::ImageMemoryBarrier2 {
+        vk::ImageMemoryBarrier2 {
+            src_stage_mask: from.stage_mask(),
+            src_access_mask: from.access_mask(),
+            dst_stage_mask: to.stage_mask(),
+            dst_access_mask: to.access_mask(),
+            old_layout: from.image_layout(),
+            new_layout: to.image_layout(),
+            image: self.image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: self.aspect_mask,
+                base_mip_level: mip_level,
+                level_count: 1,
+                base_array_layer,
+                layer_count,
+            },
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use ash::vk;
+
+/// How a subresource of a TrackedImage is being used.
+///
+/// Each variant implies the image layout along with the pipeline stages and
+/// memory accesses which use the image, so barriers can be generated without
+/// spelling them out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrackedLayout {
+    /// The contents are undefined, e.g. a newly created image.
+    Undefined,
+
+    /// Sampled or read-only in fragment and compute shaders.
+    ShaderRead,
+
+    /// Read and written as a storage image in fragment and compute shaders.
+    ShaderReadWrite,
+
+    /// Written as a color attachment.
+    ColorAttachment,
+
+    /// Tested and written as a depth/stencil attachment.
+    DepthAttachment,
+
+    /// The source of a copy or blit.
+    TransferSrc,
+
+    /// The destination of a copy, blit, or clear.
+    TransferDst,
+
+    /// Ready for presentation. Only valid for swapchain images.
+    Present,
+}
+
+impl TrackedLayout {
+    /// The Vulkan image layout for this usage.
+    pub fn image_layout(self) -> vk::ImageLayout {
+        match self {
+            Self::Undefined => vk::ImageLayout::UNDEFINED,
+            Self::ShaderRead => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            Self::ShaderReadWrite => vk::ImageLayout::GENERAL,
+            Self::ColorAttachment => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            Self::DepthAttachment => {
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+            }
+            Self::TransferSrc => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            Self::TransferDst => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            Self::Present => vk::ImageLayout::PRESENT_SRC_KHR,
+        }
+    }
+
+    /// The pipeline stages which access the image with this usage.
+    pub fn stage_mask(self) -> vk::PipelineStageFlags2 {
+        match self {
+            Self::Undefined | Self::Present => vk::PipelineStageFlags2::NONE,
+            Self::ShaderRead | Self::ShaderReadWrite => {
+                vk::PipelineStageFlags2::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags2::COMPUTE_SHADER
+            }
+            Self::ColorAttachment => {
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+            }
+            Self::DepthAttachment => {
+                vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS
+            }
+            Self::TransferSrc | Self::TransferDst => {
+                vk::PipelineStageFlags2::TRANSFER
+            }
+        }
+    }
+
+    /// The memory accesses made to the image with this usage.
+    pub fn access_mask(self) -> vk::AccessFlags2 {
+        match self {
+            Self::Undefined | Self::Present => vk::AccessFlags2::NONE,
+            Self::ShaderRead => vk::AccessFlags2::SHADER_SAMPLED_READ,
+            Self::ShaderReadWrite => {
+                vk::AccessFlags2::SHADER_STORAGE_READ
+                    | vk::AccessFlags2::SHADER_STORAGE_WRITE
+            }
+            Self::ColorAttachment => {
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ
+                    | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+            }
+            Self::DepthAttachment => {
+                vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE
+            }
+            Self::TransferSrc => vk::AccessFlags2::TRANSFER_READ,
+            Self::TransferDst => vk::AccessFlags2::TRANSFER_WRITE,
+        }
+    }
+
+    /// True when this usage only reads the image.
+    pub fn is_read_only(self) -> bool {
+        matches!(self, Self::ShaderRead | Self::TransferSrc)
+    }
+}
@@ -0,0 +1,177 @@
+use {crate::math::Vec2, ash::vk};
+
+/// Maps a fixed virtual resolution onto a framebuffer of any size.
+///
+/// Sketches can be designed at a single resolution, like 1920x1080, and
+/// still fill screens with any shape. The virtual area is scaled uniformly
+/// to fit the framebuffer and centered, leaving letterbox bars on the sides
+/// or top and bottom.
+///
+/// Virtual coordinates start at the top left of the virtual area, with +y
+/// down, to match framebuffer and window coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    virtual_extent: vk::Extent2D,
+    framebuffer_extent: vk::Extent2D,
+
+    /// The letterboxed area in framebuffer pixels: [x, y, width, height].
+    area: [f32; 4],
+}
+
+// Public API
+// ----------
+
+impl Viewport {
+    /// Create a viewport which fits the virtual resolution into the
+    /// framebuffer.
+    ///
+    /// # Params
+    ///
+    /// * `virtual_extent` - the resolution the sketch is designed for
+    /// * `framebuffer_extent` - the size of the render target, typically the
+    ///   swapchain extent
+    pub fn new(
+        virtual_extent: vk::Extent2D,
+        framebuffer_extent: vk::Extent2D,
+    ) -> Self {
+        let mut viewport = Self {
+            virtual_extent,
+            framebuffer_extent,
+            area: [0.0; 4],
+        };
+        viewport.resize(framebuffer_extent);
+        viewport
+    }
+
+    /// Refit the virtual area, e.g. after the swapchain is rebuilt.
+    pub fn resize(&mut self, framebuffer_extent: vk::Extent2D) {
+        self.framebuffer_extent = framebuffer_extent;
+        let framebuffer_width = framebuffer_extent.width as f32;
+        let framebuffer_height = framebuffer_extent.height as f32;
+        let scale = (framebuffer_width
+            / self.virtual_extent.width.max(1) as f32)
+            .min(framebuffer_height / self.virtual_extent.height.max(1) as f32);
+
+        // Round to whole pixels so the scissor and viewport always agree.
+        let width = (self.virtual_extent.width as f32 * scale).round();
+        let height = (self.virtual_extent.height as f32 * scale).round();
+        let x = ((framebuffer_width - width) * 0.5).floor();
+        let y = ((framebuffer_height - height) * 0.5).floor();
+        self.area = [x, y, width, height];
+    }
+
+    /// The resolution the sketch is designed for.
+    pub fn virtual_extent(&self) -> vk::Extent2D {
+        self.virtual_extent
+    }
+
+    /// The size of the render target.
+    pub fn framebuffer_extent(&self) -> vk::Extent2D {
+        self.framebuffer_extent
+    }
+
+    /// Framebuffer pixels per virtual pixel.
+    pub fn scale(&self) -> f32 {
+        self.area[2] / self.virtual_extent.width.max(1) as f32
+    }
+
+    /// The letterboxed viewport for `cmd_set_viewport`.
+    pub fn vk_viewport(&self) -> vk::Viewport {
+        let [x, y, width, height] = self.area;
+        vk::Viewport {
+            x,
+            y,
+            width,
+            height,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+
+    /// A scissor rect which matches the letterboxed viewport, so nothing is
+    /// drawn into the bars.
+    pub fn scissor(&self) -> vk::Rect2D {
+        let [x, y, width, height] = self.area;
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: x as i32,
+                y: y as i32,
+            },
+            extent: vk::Extent2D {
+                width: width as u32,
+                height: height as u32,
+            },
+        }
+    }
+
+    /// Set the dynamic viewport and scissor state to the letterboxed area.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the bound pipeline must use dynamic viewport and scissor state
+    pub unsafe fn cmd_set_viewport_and_scissor(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        device.cmd_set_viewport(command_buffer, 0, &[self.vk_viewport()]);
+        device.cmd_set_scissor(command_buffer, 0, &[self.scissor()]);
+    }
+
+    /// Convert a point in framebuffer pixels to virtual coordinates.
+    ///
+    /// Points in the letterbox bars map outside of the virtual area, see
+    /// `contains`.
+    pub fn framebuffer_to_virtual(&self, point: Vec2) -> Vec2 {
+        let [x, y, _, _] = self.area;
+        (point - Vec2::new(x, y)) / self.scale()
+    }
+
+    /// Convert a point in virtual coordinates to framebuffer pixels.
+    pub fn virtual_to_framebuffer(&self, point: Vec2) -> Vec2 {
+        let [x, y, _, _] = self.area;
+        point * self.scale() + Vec2::new(x, y)
+    }
+
+    /// Convert a cursor position to virtual coordinates.
+    ///
+    /// Windows report cursor positions in window coordinates, which differ
+    /// from framebuffer pixels on high-DPI displays, so the window size is
+    /// needed too. Returns None when the cursor is over a letterbox bar.
+    ///
+    /// # Params
+    ///
+    /// * `cursor` - the cursor position from a `WindowEvent::CursorPos`
+    ///   event
+    /// * `window_size` - the window size from
+    ///   `WindowBackend::get_window_size`
+    pub fn cursor_to_virtual(
+        &self,
+        cursor: (f64, f64),
+        window_size: (i32, i32),
+    ) -> Option<Vec2> {
+        let window_width = window_size.0.max(1) as f32;
+        let window_height = window_size.1.max(1) as f32;
+        let framebuffer_point = Vec2::new(
+            cursor.0 as f32 * self.framebuffer_extent.width as f32
+                / window_width,
+            cursor.1 as f32 * self.framebuffer_extent.height as f32
+                / window_height,
+        );
+        let point = self.framebuffer_to_virtual(framebuffer_point);
+        if self.contains(point) {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    /// True when a point in virtual coordinates is inside the virtual area.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= 0.0
+            && point.y >= 0.0
+            && point.x <= self.virtual_extent.width as f32
+            && point.y <= self.virtual_extent.height as f32
+    }
+}
@@ -0,0 +1,207 @@
+use {
+    crate::{
+        application::WindowBackend,
+        graphics::{vulkan_api::RenderDevice, GraphicsError},
+    },
+    anyhow::{anyhow, Context},
+    ash::vk::{self, Handle},
+    ccthw_ash_instance::PhysicalDeviceFeatures,
+    openxr as xr,
+    std::sync::Arc,
+};
+
+/// The OpenXR instance and the headset system it renders to.
+///
+/// The context is created before the RenderDevice because OpenXR decides
+/// which Vulkan extensions and which physical device the application must
+/// use.
+pub struct XrContext {
+    system: xr::SystemId,
+    blend_mode: xr::EnvironmentBlendMode,
+    eye_extent: vk::Extent2D,
+    instance: xr::Instance,
+    _entry: xr::Entry,
+}
+
+// Public API
+// ----------
+
+impl XrContext {
+    /// Load the OpenXR runtime and find a head-mounted display.
+    ///
+    /// # Params
+    ///
+    /// * `application_name` - the name reported to the OpenXR runtime
+    pub fn new(application_name: &str) -> Result<Self, GraphicsError> {
+        let entry = unsafe {
+            // SAFE because the entry is kept alive by the context.
+            xr::Entry::load().context("Unable to load the OpenXR loader")?
+        };
+        let available_extensions = entry
+            .enumerate_extensions()
+            .context("Unable to enumerate OpenXR extensions")?;
+        if !available_extensions.khr_vulkan_enable {
+            return Err(anyhow!(
+                "The OpenXR runtime does not support XR_KHR_vulkan_enable"
+            )
+            .into());
+        }
+        let mut enabled_extensions = xr::ExtensionSet::default();
+        enabled_extensions.khr_vulkan_enable = true;
+
+        let instance = entry
+            .create_instance(
+                &xr::ApplicationInfo {
+                    application_name,
+                    application_version: 0,
+                    engine_name: "ccthw",
+                    engine_version: 0,
+                    api_version: xr::Version::new(1, 0, 0),
+                },
+                &enabled_extensions,
+                &[],
+            )
+            .context("Unable to create the OpenXR instance")?;
+        let system = instance
+            .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+            .context("No head-mounted display is available")?;
+
+        let blend_mode = instance
+            .enumerate_environment_blend_modes(
+                system,
+                xr::ViewConfigurationType::PRIMARY_STEREO,
+            )
+            .context("Unable to enumerate environment blend modes")?
+            .first()
+            .copied()
+            .unwrap_or(xr::EnvironmentBlendMode::OPAQUE);
+
+        let views = instance
+            .enumerate_view_configuration_views(
+                system,
+                xr::ViewConfigurationType::PRIMARY_STEREO,
+            )
+            .context("Unable to enumerate the stereo views")?;
+        let first_view = views
+            .first()
+            .context("The headset does not have any views")?;
+        let eye_extent = vk::Extent2D {
+            width: first_view.recommended_image_rect_width,
+            height: first_view.recommended_image_rect_height,
+        };
+
+        let requirements = instance
+            .graphics_requirements::<xr::Vulkan>(system)
+            .context("Unable to get the OpenXR Vulkan requirements")?;
+        log::info!(
+            "OpenXR runtime {} requires Vulkan {} to {}",
+            instance
+                .properties()
+                .map(|properties| properties.runtime_name)
+                .unwrap_or_default(),
+            requirements.min_api_version_supported,
+            requirements.max_api_version_supported,
+        );
+
+        Ok(Self {
+            system,
+            blend_mode,
+            eye_extent,
+            instance,
+            _entry: entry,
+        })
+    }
+
+    /// Vulkan instance extensions required by the OpenXR runtime.
+    pub fn required_instance_extensions(
+        &self,
+    ) -> Result<Vec<String>, GraphicsError> {
+        let extensions = self
+            .instance
+            .vulkan_legacy_instance_extensions(self.system)
+            .context("Unable to get OpenXR Vulkan instance extensions")?;
+        Ok(split_extensions(&extensions))
+    }
+
+    /// Vulkan device extensions required by the OpenXR runtime.
+    pub fn required_device_extensions(
+        &self,
+    ) -> Result<Vec<String>, GraphicsError> {
+        let extensions = self
+            .instance
+            .vulkan_legacy_device_extensions(self.system)
+            .context("Unable to get OpenXR Vulkan device extensions")?;
+        Ok(split_extensions(&extensions))
+    }
+
+    /// Create a render device which meets the OpenXR runtime's requirements.
+    ///
+    /// The window is still used for the device's presentation surface, so
+    /// sketches can mirror the headset view to the desktop. Multiview is
+    /// always enabled because XrSession renders both eyes in one pass.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the application must destroy the render device before exit
+    pub unsafe fn create_render_device(
+        &self,
+        window: &dyn WindowBackend,
+        mut features: PhysicalDeviceFeatures,
+    ) -> Result<Arc<RenderDevice>, GraphicsError> {
+        features.vulkan_11_features_mut().multiview = vk::TRUE;
+        let render_device = window
+            .create_render_device_with_device_extensions(
+                &self.required_instance_extensions()?,
+                &[],
+                &self.required_device_extensions()?,
+                features,
+            )
+            .map_err(GraphicsError::RuntimeError)?;
+
+        let xr_physical_device = self
+            .instance
+            .vulkan_graphics_device(
+                self.system,
+                render_device.ash().handle().as_raw() as _,
+            )
+            .context("Unable to get the OpenXR physical device")?;
+        if xr_physical_device as u64 != render_device.physical_device().as_raw()
+        {
+            return Err(anyhow!(
+                "The render device does not use the headset's GPU"
+            )
+            .into());
+        }
+        Ok(render_device)
+    }
+
+    /// The recommended size of the image for each eye.
+    pub fn eye_extent(&self) -> vk::Extent2D {
+        self.eye_extent
+    }
+
+    /// The OpenXR instance.
+    pub fn instance(&self) -> &xr::Instance {
+        &self.instance
+    }
+
+    /// The headset system.
+    pub fn system(&self) -> xr::SystemId {
+        self.system
+    }
+
+    /// How rendered frames are blended with the real world.
+    pub fn blend_mode(&self) -> xr::EnvironmentBlendMode {
+        self.blend_mode
+    }
+}
+
+/// Split OpenXR's space-separated extension list.
+fn split_extensions(extensions: &str) -> Vec<String> {
+    extensions
+        .split(' ')
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
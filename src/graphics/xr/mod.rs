@@ -0,0 +1,13 @@
+//! OpenXR headset rendering, enabled with the `xr` feature.
+//!
+//! Create an XrContext first, use it to create the RenderDevice, then render
+//! frames through an XrSession instead of FramesInFlight. The GLFW window is
+//! still created and can be used to show a mirror of the headset view.
+
+mod context;
+mod session;
+
+pub use self::{
+    context::XrContext,
+    session::{XrEye, XrFrame, XrSession, XrSessionStatus},
+};
@@ -0,0 +1,696 @@
+use {
+    super::XrContext,
+    crate::{
+        graphics::{
+            vulkan_api::{raii, DepthPrePass, Frame, FrameSync, RenderDevice},
+            GraphicsError,
+        },
+        math::Mat4,
+    },
+    anyhow::{anyhow, Context},
+    ash::vk::{self, Handle},
+    openxr as xr,
+    std::sync::Arc,
+};
+
+/// What the application should do after polling XrSession events.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum XrSessionStatus {
+    /// The headset is showing the application, so frames should be
+    /// rendered.
+    Running,
+
+    /// The session is waiting for the runtime, e.g. the headset is not being
+    /// worn. Poll again later without rendering.
+    Idle,
+
+    /// The runtime asked the application to exit.
+    Exit,
+}
+
+/// The camera for one eye in an XrFrame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct XrEye {
+    /// Transforms stage space to the eye's view space.
+    pub view: Mat4,
+
+    /// The eye's asymmetric projection, following the same conventions as
+    /// `math::perspective`.
+    pub projection: Mat4,
+}
+
+/// A frame acquired from an XrSession.
+///
+/// The frame's swapchain image index refers to the XR swapchain, so
+/// `XrSession::begin_render_pass_inline` must be used instead of a
+/// ColorPass.
+pub struct XrFrame {
+    /// The frame's command buffer and index, usable with any renderer which
+    /// records into a Frame.
+    pub frame: Frame,
+
+    /// The camera for each eye, indexed by multiview view index.
+    pub eyes: [XrEye; 2],
+
+    /// When the frame will be shown, for animating in sync with the headset.
+    pub predicted_display_time: xr::Time,
+
+    views: Vec<xr::View>,
+}
+
+/// A running OpenXR session which renders into the headset's swapchain.
+///
+/// XrSession replaces FramesInFlight when rendering to a headset: frames
+/// are paced by xrWaitFrame, xrBeginFrame, and xrEndFrame instead of the
+/// window's swapchain. Both eyes are rendered in a single multiview render
+/// pass, so shaders should select each eye's camera with `gl_ViewIndex`.
+pub struct XrSession {
+    session_running: bool,
+    current_frame: usize,
+    frames: Vec<Option<FrameSync>>,
+    eye_extent: vk::Extent2D,
+    blend_mode: xr::EnvironmentBlendMode,
+
+    framebuffers: Vec<raii::Framebuffer>,
+    _image_views: Vec<raii::ImageView>,
+    _depth_view: raii::ImageView,
+    _depth_image: raii::Image,
+    render_pass: raii::RenderPass,
+
+    swapchain: xr::Swapchain<xr::Vulkan>,
+    stage: xr::Space,
+    frame_stream: xr::FrameStream<xr::Vulkan>,
+    frame_waiter: xr::FrameWaiter,
+    session: xr::Session<xr::Vulkan>,
+    instance: xr::Instance,
+    render_device: Arc<RenderDevice>,
+}
+
+// Public API
+// ----------
+
+impl XrSession {
+    /// The format of the headset swapchain images.
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+    /// Create a session, the headset swapchain, and per-frame resources.
+    ///
+    /// # Params
+    ///
+    /// * `context` - the OpenXR instance and headset
+    /// * `render_device` - a render device created by
+    ///   `XrContext::create_render_device`
+    /// * `frame_count` - the number of in-flight frames to support
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the session must be dropped before the RenderDevice is destroyed
+    ///   - pipelines must be built against this session's render pass
+    pub unsafe fn new(
+        context: &XrContext,
+        render_device: Arc<RenderDevice>,
+        frame_count: usize,
+    ) -> Result<Self, GraphicsError> {
+        let instance = context.instance().clone();
+        let graphics_queue = render_device.graphics_queue();
+        let (session, frame_waiter, frame_stream) = instance
+            .create_session::<xr::Vulkan>(
+                context.system(),
+                &xr::vulkan::SessionCreateInfo {
+                    instance: render_device.ash().handle().as_raw() as _,
+                    physical_device: render_device.physical_device().as_raw()
+                        as _,
+                    device: render_device.device().handle().as_raw() as _,
+                    queue_family_index: graphics_queue.family_index(),
+                    queue_index: graphics_queue.index(),
+                },
+            )
+            .context("Unable to create the OpenXR session")?;
+        let stage = session
+            .create_reference_space(
+                xr::ReferenceSpaceType::STAGE,
+                xr::Posef::IDENTITY,
+            )
+            .or_else(|_| {
+                session.create_reference_space(
+                    xr::ReferenceSpaceType::LOCAL,
+                    xr::Posef::IDENTITY,
+                )
+            })
+            .context("Unable to create a reference space")?;
+
+        let eye_extent = context.eye_extent();
+        let swapchain = session
+            .create_swapchain(&xr::SwapchainCreateInfo {
+                create_flags: xr::SwapchainCreateFlags::EMPTY,
+                usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
+                    | xr::SwapchainUsageFlags::SAMPLED,
+                format: Self::FORMAT.as_raw() as _,
+                sample_count: 1,
+                width: eye_extent.width,
+                height: eye_extent.height,
+                face_count: 1,
+                array_size: 2,
+                mip_count: 1,
+            })
+            .context("Unable to create the OpenXR swapchain")?;
+        let images = swapchain
+            .enumerate_images()
+            .context("Unable to get the OpenXR swapchain images")?;
+
+        let render_pass = Self::create_render_pass(render_device.clone())?;
+        let (depth_image, depth_view) =
+            Self::create_depth_buffer(&render_device, eye_extent)?;
+        let mut image_views = vec![];
+        let mut framebuffers = vec![];
+        for image in images {
+            let image_view = raii::ImageView::new(
+                render_device.clone(),
+                &vk::ImageViewCreateInfo {
+                    image: vk::Image::from_raw(image),
+                    view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+                    format: Self::FORMAT,
+                    subresource_range: vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 2,
+                    },
+                    ..Default::default()
+                },
+            )?;
+            let attachments = [image_view.raw(), depth_view.raw()];
+            let framebuffer = raii::Framebuffer::new(
+                render_device.clone(),
+                &vk::FramebufferCreateInfo {
+                    render_pass: render_pass.raw(),
+                    attachment_count: attachments.len() as u32,
+                    p_attachments: attachments.as_ptr(),
+                    width: eye_extent.width,
+                    height: eye_extent.height,
+                    layers: 1,
+                    ..Default::default()
+                },
+            )?;
+            image_views.push(image_view);
+            framebuffers.push(framebuffer);
+        }
+
+        let mut frames = vec![];
+        for i in 0..frame_count {
+            frames.push(Some(FrameSync::new(render_device.clone(), i)?));
+        }
+
+        Ok(Self {
+            session_running: false,
+            current_frame: 0,
+            frames,
+            eye_extent,
+            blend_mode: context.blend_mode(),
+            framebuffers,
+            _image_views: image_views,
+            _depth_view: depth_view,
+            _depth_image: depth_image,
+            render_pass,
+            swapchain,
+            stage,
+            frame_stream,
+            frame_waiter,
+            session,
+            instance,
+            render_device,
+        })
+    }
+
+    /// The size of the image for each eye.
+    pub fn eye_extent(&self) -> vk::Extent2D {
+        self.eye_extent
+    }
+
+    /// The multiview render pass which targets the headset swapchain.
+    pub fn render_pass(&self) -> &raii::RenderPass {
+        &self.render_pass
+    }
+
+    /// Handle OpenXR events, starting and stopping the session as the
+    /// runtime requests. Call this once per application update, before
+    /// acquiring a frame.
+    pub fn poll_events(&mut self) -> Result<XrSessionStatus, GraphicsError> {
+        let mut buffer = xr::EventDataBuffer::new();
+        while let Some(event) = self
+            .instance
+            .poll_event(&mut buffer)
+            .context("Unable to poll OpenXR events")?
+        {
+            match event {
+                xr::Event::SessionStateChanged(state_change) => {
+                    match state_change.state() {
+                        xr::SessionState::READY => {
+                            self.session
+                                .begin(
+                                    xr::ViewConfigurationType::PRIMARY_STEREO,
+                                )
+                                .context(
+                                    "Unable to begin the OpenXR session",
+                                )?;
+                            self.session_running = true;
+                        }
+                        xr::SessionState::STOPPING => {
+                            self.wait_for_all_frames_to_complete()?;
+                            self.session
+                                .end()
+                                .context("Unable to end the OpenXR session")?;
+                            self.session_running = false;
+                        }
+                        xr::SessionState::EXITING
+                        | xr::SessionState::LOSS_PENDING => {
+                            return Ok(XrSessionStatus::Exit);
+                        }
+                        _ => (),
+                    }
+                }
+                xr::Event::InstanceLossPending(_) => {
+                    return Ok(XrSessionStatus::Exit);
+                }
+                _ => (),
+            }
+        }
+
+        if self.session_running {
+            Ok(XrSessionStatus::Running)
+        } else {
+            Ok(XrSessionStatus::Idle)
+        }
+    }
+
+    /// Wait for the runtime to be ready for the next frame and acquire a
+    /// swapchain image.
+    ///
+    /// Returns None when the runtime doesn't want this frame rendered, e.g.
+    /// while the application is hidden. The frame is ended automatically in
+    /// that case.
+    ///
+    /// # Params
+    ///
+    /// * `near` - the distance to the near clipping plane for both eyes
+    /// * `far` - the distance to the far clipping plane for both eyes
+    pub fn acquire_frame(
+        &mut self,
+        near: f32,
+        far: f32,
+    ) -> Result<Option<XrFrame>, GraphicsError> {
+        if !self.session_running {
+            return Ok(None);
+        }
+
+        let frame_state = self
+            .frame_waiter
+            .wait()
+            .context("Error while waiting for the next OpenXR frame")?;
+        self.frame_stream
+            .begin()
+            .context("Unable to begin the OpenXR frame")?;
+        if !frame_state.should_render {
+            self.frame_stream
+                .end(frame_state.predicted_display_time, self.blend_mode, &[])
+                .context("Unable to end the OpenXR frame")?;
+            return Ok(None);
+        }
+
+        let (_, views) = self
+            .session
+            .locate_views(
+                xr::ViewConfigurationType::PRIMARY_STEREO,
+                frame_state.predicted_display_time,
+                &self.stage,
+            )
+            .context("Unable to locate the headset views")?;
+        if views.len() < 2 {
+            return Err(
+                anyhow!("The headset reported fewer than two views").into()
+            );
+        }
+        let eye = |view: &xr::View| XrEye {
+            view: view_matrix(&view.pose),
+            projection: fov_projection(&view.fov, near, far),
+        };
+        let eyes = [eye(&views[0]), eye(&views[1])];
+
+        let image_index = self
+            .swapchain
+            .acquire_image()
+            .context("Unable to acquire an OpenXR swapchain image")?;
+        self.swapchain
+            .wait_image(xr::Duration::INFINITE)
+            .context("Error while waiting for an OpenXR swapchain image")?;
+
+        // advance the frame counter and restart the frame's command buffer
+        self.current_frame = (self.current_frame + 1) % self.frames.len();
+        let frame_sync =
+            self.frames[self.current_frame].take().with_context(|| {
+                format!("Unable to acquire frame {}", self.current_frame)
+            })?;
+        frame_sync.wait_and_restart_command_buffer()?;
+
+        Ok(Some(XrFrame {
+            frame: Frame::new(frame_sync, image_index as usize),
+            eyes,
+            predicted_display_time: frame_state.predicted_display_time,
+            views,
+        }))
+    }
+
+    /// Begin the multiview render pass for the frame's swapchain image. Color
+    /// is cleared to the clear color and depth is cleared to 1.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe because:
+    ///   - the XrSession must not be destroyed until the command buffer
+    ///     finishes executing or is discarded
+    pub unsafe fn begin_render_pass_inline(
+        &self,
+        frame: &Frame,
+        clear_color: [f32; 4],
+    ) {
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+        let begin_info = vk::RenderPassBeginInfo {
+            render_pass: self.render_pass.raw(),
+            framebuffer: self.framebuffers[frame.swapchain_image_index()].raw(),
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.eye_extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.render_device.device().cmd_begin_render_pass(
+            frame.command_buffer(),
+            &begin_info,
+            vk::SubpassContents::INLINE,
+        );
+    }
+
+    /// Submit a frame's commands, release the swapchain image, and hand the
+    /// frame to the OpenXR compositor.
+    pub fn present_frame(
+        &mut self,
+        xr_frame: XrFrame,
+    ) -> Result<(), GraphicsError> {
+        let XrFrame {
+            frame,
+            predicted_display_time,
+            views,
+            ..
+        } = xr_frame;
+        debug_assert!(frame.frame_index() == self.current_frame);
+
+        let frame_index = frame.frame_index();
+        self.frames[frame_index] = Some(frame.take_sync());
+        let sync = self.frames[frame_index].as_ref().unwrap();
+        let command_buffer = sync.command_pool.primary_command_buffer(0);
+
+        // The runtime waits for the image to be released, so there are no
+        // semaphores. The fence paces reuse of the frame's command buffer.
+        unsafe {
+            self.render_device
+                .device()
+                .end_command_buffer(command_buffer)
+                .with_context(|| {
+                    format!(
+                        "Error ending graphics command buffer for frame {}",
+                        frame_index
+                    )
+                })?;
+            let command_buffer_infos = [vk::CommandBufferSubmitInfo {
+                command_buffer,
+                ..Default::default()
+            }];
+            let submit_info = vk::SubmitInfo2 {
+                p_command_buffer_infos: command_buffer_infos.as_ptr(),
+                command_buffer_info_count: command_buffer_infos.len() as u32,
+                ..Default::default()
+            };
+            self.render_device.device().queue_submit2(
+                *self.render_device.graphics_queue().raw(),
+                &[submit_info],
+                sync.graphics_commands_completed_fence.raw(),
+            )?;
+        }
+
+        self.swapchain
+            .release_image()
+            .context("Unable to release the OpenXR swapchain image")?;
+
+        let rect = xr::Rect2Di {
+            offset: xr::Offset2Di { x: 0, y: 0 },
+            extent: xr::Extent2Di {
+                width: self.eye_extent.width as i32,
+                height: self.eye_extent.height as i32,
+            },
+        };
+        let projection_views = [0, 1].map(|eye| {
+            xr::CompositionLayerProjectionView::new()
+                .pose(views[eye].pose)
+                .fov(views[eye].fov)
+                .sub_image(
+                    xr::SwapchainSubImage::new()
+                        .swapchain(&self.swapchain)
+                        .image_array_index(eye as u32)
+                        .image_rect(rect),
+                )
+        });
+        self.frame_stream
+            .end(
+                predicted_display_time,
+                self.blend_mode,
+                &[&xr::CompositionLayerProjection::new()
+                    .space(&self.stage)
+                    .views(&projection_views)],
+            )
+            .context("Unable to end the OpenXR frame")?;
+        Ok(())
+    }
+
+    /// Stall until every submitted frame has finished executing on the GPU.
+    pub fn wait_for_all_frames_to_complete(&self) -> Result<(), GraphicsError> {
+        for frame_sync in self.frames.iter().flatten() {
+            frame_sync.wait_for_graphics_commands_to_complete()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for XrSession {
+    fn drop(&mut self) {
+        // Frames may still reference the framebuffers and depth buffer.
+        if let Err(error) = self.wait_for_all_frames_to_complete() {
+            log::error!("Error while dropping the XrSession: {}", error);
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl XrSession {
+    /// Create the depth buffer shared by every frame, with a layer per eye.
+    unsafe fn create_depth_buffer(
+        render_device: &Arc<RenderDevice>,
+        extent: vk::Extent2D,
+    ) -> Result<(raii::Image, raii::ImageView), GraphicsError> {
+        let queue_family_index = render_device.graphics_queue().family_index();
+        let image = raii::Image::new(
+            render_device.clone(),
+            &vk::ImageCreateInfo {
+                image_type: vk::ImageType::TYPE_2D,
+                format: DepthPrePass::DEPTH_FORMAT,
+                mip_levels: 1,
+                array_layers: 2,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                samples: vk::SampleCountFlags::TYPE_1,
+                sharing_mode: vk::SharingMode::EXCLUSIVE,
+                queue_family_index_count: 1,
+                p_queue_family_indices: &queue_family_index,
+                tiling: vk::ImageTiling::OPTIMAL,
+                usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                flags: vk::ImageCreateFlags::empty(),
+                extent: vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                ..vk::ImageCreateInfo::default()
+            },
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        image.set_debug_name("XR Depth Buffer");
+        let view = raii::ImageView::new(
+            render_device.clone(),
+            &vk::ImageViewCreateInfo {
+                image: image.raw(),
+                view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+                format: DepthPrePass::DEPTH_FORMAT,
+                subresource_range: vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::DEPTH,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 2,
+                },
+                ..Default::default()
+            },
+        )?;
+        Ok((image, view))
+    }
+
+    /// Create a multiview render pass which renders both eyes in a single
+    /// subpass and leaves the swapchain image in COLOR_ATTACHMENT_OPTIMAL,
+    /// as OpenXR expects.
+    unsafe fn create_render_pass(
+        render_device: Arc<RenderDevice>,
+    ) -> Result<raii::RenderPass, GraphicsError> {
+        let attachments = [
+            vk::AttachmentDescription {
+                format: Self::FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+            vk::AttachmentDescription {
+                format: DepthPrePass::DEPTH_FORMAT,
+                samples: vk::SampleCountFlags::TYPE_1,
+                load_op: vk::AttachmentLoadOp::CLEAR,
+                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                flags: vk::AttachmentDescriptionFlags::empty(),
+            },
+        ];
+        let color_attachment = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+        let depth_attachment = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        let subpasses = [vk::SubpassDescription {
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment,
+            p_depth_stencil_attachment: &depth_attachment,
+            ..Default::default()
+        }];
+        let dependencies = [
+            // The depth buffer is shared between frames in flight, so wait
+            // for the previous frame's depth tests to finish.
+            vk::SubpassDependency {
+                src_subpass: vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask:
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dependency_flags: vk::DependencyFlags::empty(),
+            },
+        ];
+        let view_mask: u32 = 0b11;
+        let multiview_info = vk::RenderPassMultiviewCreateInfo {
+            subpass_count: 1,
+            p_view_masks: &view_mask,
+            correlation_mask_count: 1,
+            p_correlation_masks: &view_mask,
+            ..Default::default()
+        };
+        let create_info = vk::RenderPassCreateInfo {
+            p_next: &multiview_info as *const vk::RenderPassMultiviewCreateInfo
+                as *const std::ffi::c_void,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: dependencies.len() as u32,
+            p_dependencies: dependencies.as_ptr(),
+            flags: vk::RenderPassCreateFlags::empty(),
+            ..Default::default()
+        };
+        raii::RenderPass::new(render_device, &create_info)
+    }
+}
+
+/// The view matrix for an eye: the inverse of its pose in stage space.
+fn view_matrix(pose: &xr::Posef) -> Mat4 {
+    use nalgebra::{Isometry3, Quaternion, Translation3, UnitQuaternion};
+    let orientation = UnitQuaternion::from_quaternion(Quaternion::new(
+        pose.orientation.w,
+        pose.orientation.x,
+        pose.orientation.y,
+        pose.orientation.z,
+    ));
+    let position =
+        Translation3::new(pose.position.x, pose.position.y, pose.position.z);
+    Isometry3::from_parts(position, orientation)
+        .inverse()
+        .to_homogeneous()
+}
+
+/// An asymmetric perspective projection for an OpenXR field of view.
+///
+/// Matches `math::perspective`: clip space Y points down and depth goes from
+/// 0 at the near plane to 1 at the far plane.
+fn fov_projection(fov: &xr::Fovf, near: f32, far: f32) -> Mat4 {
+    let left = fov.angle_left.tan();
+    let right = fov.angle_right.tan();
+    let up = fov.angle_up.tan();
+    let down = fov.angle_down.tan();
+    let width = right - left;
+    let height = up - down;
+    let depth = near - far;
+    Mat4::new(
+        2.0 / width,
+        0.0,
+        (right + left) / width,
+        0.0,
+        0.0,
+        -2.0 / height,
+        -(up + down) / height,
+        0.0,
+        0.0,
+        0.0,
+        far / depth,
+        near * far / depth,
+        0.0,
+        0.0,
+        -1.0,
+        0.0,
+    )
+}
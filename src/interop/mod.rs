@@ -0,0 +1,3 @@
+//! Integrations with other creative coding tools.
+
+pub mod osc;
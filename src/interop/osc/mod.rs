@@ -0,0 +1,254 @@
+//! Control sketch parameters over OSC (Open Sound Control).
+//!
+//! An OscListener receives OSC messages on a UDP socket in a background
+//! thread and maps their addresses to named parameters. Sketches call
+//! `OscListener::params` in `State::update` to get a consistent snapshot, so
+//! tools like TouchOSC, Max, or SuperCollider can drive the sketch live.
+
+mod packet;
+
+use {
+    anyhow::{Context, Result},
+    std::{
+        collections::HashMap,
+        net::{ToSocketAddrs, UdpSocket},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread::JoinHandle,
+        time::Duration,
+    },
+};
+
+pub use self::packet::{decode_packet, OscArg, OscMessage};
+
+/// Maps an OSC address to a named parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscParam {
+    pub name: String,
+    pub address: String,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+
+    /// When true, incoming values are treated as normalized [0, 1] values
+    /// (like TouchOSC faders) and mapped to [min, max]. Otherwise incoming
+    /// values are clamped to [min, max].
+    pub normalized: bool,
+}
+
+impl OscParam {
+    /// Map normalized values received on `address` to `[min, max]`.
+    /// The parameter starts at `min`.
+    pub fn new(
+        name: impl Into<String>,
+        address: impl Into<String>,
+        min: f32,
+        max: f32,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            address: address.into(),
+            min,
+            max,
+            default: min,
+            normalized: true,
+        }
+    }
+
+    /// Set the parameter's value before any messages are received.
+    pub fn with_default(mut self, default: f32) -> Self {
+        self.default = default;
+        self
+    }
+
+    /// Treat incoming values as already being in `[min, max]`.
+    pub fn raw(mut self) -> Self {
+        self.normalized = false;
+        self
+    }
+
+    /// Convert an incoming value into the parameter's range.
+    pub fn map_value(&self, value: f32) -> f32 {
+        let (low, high) = (self.min.min(self.max), self.min.max(self.max));
+        if self.normalized {
+            self.min + value.clamp(0.0, 1.0) * (self.max - self.min)
+        } else {
+            value.clamp(low, high)
+        }
+    }
+}
+
+/// A snapshot of parameter values by name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params {
+    values: HashMap<String, f32>,
+}
+
+impl Params {
+    /// Get a parameter's value.
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.values.get(name).copied()
+    }
+
+    /// Get a parameter's value, or `default` if there is no such parameter.
+    pub fn get_or(&self, name: &str, default: f32) -> f32 {
+        self.get(name).unwrap_or(default)
+    }
+
+    /// Set a parameter's value.
+    pub fn set(&mut self, name: impl Into<String>, value: f32) {
+        self.values.insert(name.into(), value);
+    }
+
+    /// Iterate over every parameter's name and value.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f32)> + '_ {
+        self.values
+            .iter()
+            .map(|(name, &value)| (name.as_str(), value))
+    }
+}
+
+/// Listens for OSC messages and updates parameters in a background thread.
+pub struct OscListener {
+    params: Arc<Mutex<Params>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+// Public API
+// ----------
+
+impl OscListener {
+    /// The largest OSC packet which can be received.
+    const MAX_PACKET_SIZE: usize = 64 * 1024;
+
+    /// Bind a UDP socket and start listening for messages.
+    ///
+    /// # Params
+    ///
+    /// * `address` - the local address to listen on, e.g. "0.0.0.0:9000"
+    /// * `mappings` - the parameters which can be controlled. Messages for
+    ///   any other address are logged and ignored.
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        mappings: Vec<OscParam>,
+    ) -> Result<Self> {
+        let socket =
+            UdpSocket::bind(address).context("Unable to bind OSC socket")?;
+        socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .context("Unable to set the OSC socket's read timeout")?;
+        log::info!("Listening for OSC messages on {:?}", socket.local_addr());
+
+        let mut params = Params::default();
+        for mapping in &mappings {
+            params.set(mapping.name.clone(), mapping.default);
+        }
+        let params = Arc::new(Mutex::new(params));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let worker_params = params.clone();
+        let worker_stop = stop.clone();
+        let worker = std::thread::Builder::new()
+            .name("osc-listener".to_owned())
+            .spawn(move || {
+                Self::listen(socket, mappings, worker_params, worker_stop)
+            })
+            .context("Unable to spawn the OSC listener thread")?;
+
+        Ok(Self {
+            params,
+            stop,
+            worker: Some(worker),
+        })
+    }
+
+    /// A snapshot of every parameter's current value.
+    pub fn params(&self) -> Params {
+        match self.params.lock() {
+            Ok(params) => params.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl Drop for OscListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::warn!("The OSC listener thread panicked");
+            }
+        }
+    }
+}
+
+// Private API
+// -----------
+
+impl OscListener {
+    /// The listener thread's main loop.
+    fn listen(
+        socket: UdpSocket,
+        mappings: Vec<OscParam>,
+        params: Arc<Mutex<Params>>,
+        stop: Arc<AtomicBool>,
+    ) {
+        let mut buffer = vec![0; Self::MAX_PACKET_SIZE];
+        while !stop.load(Ordering::Relaxed) {
+            let size = match socket.recv(&mut buffer) {
+                Ok(size) => size,
+                Err(error)
+                    if error.kind() == std::io::ErrorKind::WouldBlock
+                        || error.kind() == std::io::ErrorKind::TimedOut =>
+                {
+                    continue;
+                }
+                Err(error) => {
+                    log::error!("Error receiving OSC packet: {}", error);
+                    continue;
+                }
+            };
+            let messages = match decode_packet(&buffer[..size]) {
+                Ok(messages) => messages,
+                Err(error) => {
+                    log::warn!("Ignoring invalid OSC packet: {:?}", error);
+                    continue;
+                }
+            };
+            for message in messages {
+                Self::apply_message(&mappings, &params, &message);
+            }
+        }
+    }
+
+    /// Update every parameter which is mapped to the message's address.
+    fn apply_message(
+        mappings: &[OscParam],
+        params: &Arc<Mutex<Params>>,
+        message: &OscMessage,
+    ) {
+        let value = match message.args.first().and_then(OscArg::as_f32) {
+            Some(value) => value,
+            None => {
+                log::trace!("Ignoring OSC message {:?}", message);
+                return;
+            }
+        };
+        let mut matched = false;
+        for mapping in mappings {
+            if mapping.address != message.address {
+                continue;
+            }
+            matched = true;
+            if let Ok(mut params) = params.lock() {
+                params.set(mapping.name.clone(), mapping.map_value(value));
+            }
+        }
+        if !matched {
+            log::debug!("No parameter for OSC address {}", message.address);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{bail, Context, Result};
+
+/// A single argument in an OSC message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    String(String),
+    Blob(Vec<u8>),
+    Nil,
+    Impulse,
+}
+
+impl OscArg {
+    /// The argument as an f32, if it is numeric or boolean.
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            OscArg::Int(value) => Some(value as f32),
+            OscArg::Long(value) => Some(value as f32),
+            OscArg::Float(value) => Some(value),
+            OscArg::Double(value) => Some(value as f32),
+            OscArg::Bool(value) => Some(if value { 1.0 } else { 0.0 }),
+            OscArg::Impulse => Some(1.0),
+            _ => None,
+        }
+    }
+}
+
+/// An OSC message: an address pattern and its arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+/// Decode every message in an OSC packet. Bundles are flattened and their
+/// time tags are ignored.
+pub fn decode_packet(packet: &[u8]) -> Result<Vec<OscMessage>> {
+    let mut messages = vec![];
+    decode_into(packet, &mut messages)?;
+    Ok(messages)
+}
+
+fn decode_into(packet: &[u8], messages: &mut Vec<OscMessage>) -> Result<()> {
+    const BUNDLE_TAG: &[u8] = b"#bundle\0";
+    if !packet.starts_with(BUNDLE_TAG) {
+        messages.push(decode_message(packet)?);
+        return Ok(());
+    }
+
+    // skip the bundle tag and the 8-byte time tag
+    let mut reader = Reader::new(packet);
+    reader.take(BUNDLE_TAG.len() + 8)?;
+    while !reader.is_empty() {
+        let size = reader.read_i32()?;
+        if size < 0 {
+            bail!("Invalid OSC bundle element size {}", size);
+        }
+        decode_into(reader.take(size as usize)?, messages)?;
+    }
+    Ok(())
+}
+
+fn decode_message(packet: &[u8]) -> Result<OscMessage> {
+    let mut reader = Reader::new(packet);
+    let address = reader.read_string().context("Invalid OSC address")?;
+    if !address.starts_with('/') {
+        bail!("Invalid OSC address {:?}", address);
+    }
+
+    // Very old implementations omit the type tag string entirely.
+    if reader.is_empty() {
+        return Ok(OscMessage {
+            address,
+            args: vec![],
+        });
+    }
+    let type_tags = reader.read_string().context("Invalid OSC type tags")?;
+    let type_tags = type_tags
+        .strip_prefix(',')
+        .with_context(|| format!("Invalid OSC type tags {:?}", type_tags))?;
+
+    let mut args = Vec::with_capacity(type_tags.len());
+    for tag in type_tags.chars() {
+        let arg = match tag {
+            'i' => OscArg::Int(reader.read_i32()?),
+            'h' => OscArg::Long(i64::from_be_bytes(reader.read_array()?)),
+            'f' => OscArg::Float(f32::from_be_bytes(reader.read_array()?)),
+            'd' => OscArg::Double(f64::from_be_bytes(reader.read_array()?)),
+            's' | 'S' => OscArg::String(reader.read_string()?),
+            'b' => {
+                let size = reader.read_i32()?.max(0) as usize;
+                let blob = reader.take(size)?.to_vec();
+                reader.skip_padding(size)?;
+                OscArg::Blob(blob)
+            }
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'N' => OscArg::Nil,
+            'I' => OscArg::Impulse,
+            // 32-bit types which are uncommon for parameter control
+            'c' | 'r' | 'm' => OscArg::Int(reader.read_i32()?),
+            't' => OscArg::Long(i64::from_be_bytes(reader.read_array()?)),
+            '[' | ']' => continue,
+            other => bail!("Unsupported OSC type tag {:?}", other),
+        };
+        args.push(arg);
+    }
+    Ok(OscMessage { address, args })
+}
+
+/// Reads big-endian, 4-byte aligned OSC data.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.offset + count;
+        if end > self.data.len() {
+            bail!("Truncated OSC packet");
+        }
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.read_array()?))
+    }
+
+    /// Skip the padding after `size` bytes of data.
+    fn skip_padding(&mut self, size: usize) -> Result<()> {
+        self.take((4 - size % 4) % 4)?;
+        Ok(())
+    }
+
+    /// Read a null-terminated string and its padding.
+    fn read_string(&mut self) -> Result<String> {
+        let remaining = &self.data[self.offset..];
+        let length = remaining
+            .iter()
+            .position(|&byte| byte == 0)
+            .context("Unterminated OSC string")?;
+        let string = String::from_utf8_lossy(&remaining[..length]).into_owned();
+        // the terminator is always followed by padding to a 4 byte boundary
+        self.take((length / 4 + 1) * 4)?;
+        Ok(string)
+    }
+}
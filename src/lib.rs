@@ -2,5 +2,11 @@
 //! Ash library.
 
 pub mod application;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod bench;
 pub mod graphics;
+pub mod interop;
 pub mod math;
+pub mod params;
+pub mod timing;
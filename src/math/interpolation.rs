@@ -0,0 +1,52 @@
+use std::ops::{Add, Mul, Sub};
+
+/// Values which can be linearly interpolated, like f32, Vec2, and Vec3.
+pub trait Lerp:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+}
+
+impl<T> Lerp for T where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>
+{
+}
+
+/// Linearly interpolate from `a` to `b`. `t` is not clamped, so values
+/// outside of [0, 1] extrapolate.
+pub fn lerp<T: Lerp>(a: T, b: T, t: f32) -> T {
+    a + (b - a) * t
+}
+
+/// The inverse of lerp: where `value` falls between `a` and `b`, where `a`
+/// maps to 0 and `b` maps to 1.
+pub fn inverse_lerp(a: f32, b: f32, value: f32) -> f32 {
+    if a == b {
+        0.0
+    } else {
+        (value - a) / (b - a)
+    }
+}
+
+/// Map `value` from the range [in_min, in_max] to [out_min, out_max].
+pub fn remap<T: Lerp>(
+    value: f32,
+    in_min: f32,
+    in_max: f32,
+    out_min: T,
+    out_max: T,
+) -> T {
+    lerp(out_min, out_max, inverse_lerp(in_min, in_max, value))
+}
+
+/// Hermite interpolation between 0 and 1 as `x` goes from `edge0` to
+/// `edge1`. Matches GLSL's smoothstep.
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = inverse_lerp(edge0, edge1, x).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Like smoothstep but with zero first and second derivatives at the edges.
+pub fn smootherstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = inverse_lerp(edge0, edge1, x).clamp(0.0, 1.0);
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
@@ -0,0 +1,67 @@
+use super::Vec2;
+
+/// The 2D cross product (the z component of the 3D cross product).
+pub fn cross_2d(a: &Vec2, b: &Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Returns true when `point` is inside of `polygon`.
+///
+/// The polygon may be convex or concave, and may be wound in either
+/// direction. Self-intersecting polygons use the even-odd rule.
+pub fn point_in_polygon(point: &Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    let mut previous = match polygon.last() {
+        Some(last) => last,
+        None => return false,
+    };
+    for current in polygon {
+        let crosses = (current.y > point.y) != (previous.y > point.y);
+        if crosses {
+            let x = current.x
+                + (point.y - current.y) * (previous.x - current.x)
+                    / (previous.y - current.y);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
+/// Find where the segments `a0 -> a1` and `b0 -> b1` intersect.
+///
+/// Returns None when the segments don't touch or are parallel.
+pub fn segment_intersection(
+    a0: &Vec2,
+    a1: &Vec2,
+    b0: &Vec2,
+    b1: &Vec2,
+) -> Option<Vec2> {
+    let a = a1 - a0;
+    let b = b1 - b0;
+    let denominator = cross_2d(&a, &b);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let offset = b0 - a0;
+    let t = cross_2d(&offset, &b) / denominator;
+    let u = cross_2d(&offset, &a) / denominator;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        Some(a0 + a * t)
+    } else {
+        None
+    }
+}
+
+/// The point on the segment `a -> b` which is closest to `point`.
+pub fn closest_point_on_segment(point: &Vec2, a: &Vec2, b: &Vec2) -> Vec2 {
+    let ab = b - a;
+    let length_squared = ab.norm_squared();
+    if length_squared <= f32::EPSILON {
+        return *a;
+    }
+    let t = ((point - a).dot(&ab) / length_squared).clamp(0.0, 1.0);
+    a + ab * t
+}
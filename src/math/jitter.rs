@@ -0,0 +1,54 @@
+use super::{Mat4, Vec2};
+
+// Sub-pixel camera jitter for temporal anti-aliasing. Each frame the
+// projection is offset by a different fraction of a pixel so that, over
+// several frames, every pixel is sampled at many positions.
+
+/// The number of jitter positions before the sequence repeats.
+pub const JITTER_SEQUENCE_LENGTH: u32 = 8;
+
+/// The `index`th value of the Halton sequence with the given base, in
+/// [0, 1).
+pub fn halton(index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    let mut index = index;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// The jitter for a frame, in pixels, in [-0.5, 0.5).
+///
+/// Uses the Halton(2, 3) sequence, starting at index 1 because index 0 is
+/// always the origin.
+pub fn taa_jitter(frame_index: u32) -> Vec2 {
+    let index = (frame_index % JITTER_SEQUENCE_LENGTH) + 1;
+    Vec2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+}
+
+/// Offset a projection by a jitter in pixels.
+///
+/// The offset is applied in clip space, so it works for both perspective and
+/// orthographic projections.
+///
+/// # Params
+///
+/// * `projection` - the projection to jitter
+/// * `jitter` - the offset in pixels, usually from `taa_jitter`
+/// * `width` - the width of the render target in pixels
+/// * `height` - the height of the render target in pixels
+pub fn jitter_projection(
+    projection: &Mat4,
+    jitter: &Vec2,
+    width: u32,
+    height: u32,
+) -> Mat4 {
+    let mut offset = Mat4::identity();
+    offset[(0, 3)] = 2.0 * jitter.x / width as f32;
+    offset[(1, 3)] = 2.0 * jitter.y / height as f32;
+    offset * projection
+}
@@ -0,0 +1,116 @@
+use super::{look_at, DVec3, Mat4, Vec3};
+
+// Helpers for scenes whose coordinates are too large for f32. Positions are
+// kept as f64 on the CPU, and only differences from a nearby point, usually
+// the camera, are sent to the GPU.
+
+/// Split an f64 into a high and low f32 whose sum approximates the value
+/// with roughly 48 bits of precision.
+pub fn split_f64(value: f64) -> (f32, f32) {
+    let high = value as f32;
+    let low = (value - high as f64) as f32;
+    (high, low)
+}
+
+/// An f64 position split into two f32 vectors.
+///
+/// Shaders can subtract two split positions, high parts first, to get a
+/// small and precise f32 offset even when both positions are far from the
+/// origin. Must match SplitVec3 in `graphics/scene/shaders/large_world.glsl`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[repr(C)]
+pub struct SplitVec3 {
+    /// The position rounded to f32. w is always 0.
+    pub high: [f32; 4],
+
+    /// The rounding error from `high`. w is always 0.
+    pub low: [f32; 4],
+}
+
+impl SplitVec3 {
+    /// Split a position.
+    pub fn new(position: &DVec3) -> Self {
+        let (x_high, x_low) = split_f64(position.x);
+        let (y_high, y_low) = split_f64(position.y);
+        let (z_high, z_low) = split_f64(position.z);
+        Self {
+            high: [x_high, y_high, z_high, 0.0],
+            low: [x_low, y_low, z_low, 0.0],
+        }
+    }
+
+    /// Recombine the position.
+    pub fn to_f64(&self) -> DVec3 {
+        DVec3::new(
+            self.high[0] as f64 + self.low[0] as f64,
+            self.high[1] as f64 + self.low[1] as f64,
+            self.high[2] as f64 + self.low[2] as f64,
+        )
+    }
+}
+
+/// The offset from `camera` to `position`, computed in f64 before rounding.
+pub fn camera_relative(position: &DVec3, camera: &DVec3) -> Vec3 {
+    (position - camera).map(|value| value as f32)
+}
+
+/// A view matrix for a camera at the origin, for use with positions made by
+/// `camera_relative`.
+///
+/// The view has no translation, so precision doesn't depend on how far the
+/// camera is from the world's origin.
+pub fn look_at_camera_relative(eye: &DVec3, target: &DVec3, up: &Vec3) -> Mat4 {
+    look_at(&Vec3::zeros(), &camera_relative(target, eye), up)
+}
+
+/// A floating origin which follows the camera.
+///
+/// Scenes store f32 positions relative to the origin. When the camera wanders
+/// more than `rebase_distance` away, `update` moves the origin to the camera
+/// and returns the shift, which the caller subtracts from everything stored
+/// relative to the old origin, e.g. with `SceneGraph::shift_origin`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WorldOrigin {
+    origin: DVec3,
+
+    /// How far the camera can get from the origin before it is rebased.
+    pub rebase_distance: f64,
+}
+
+impl WorldOrigin {
+    /// Create an origin at the world's origin.
+    pub fn new(rebase_distance: f64) -> Self {
+        Self {
+            origin: DVec3::zeros(),
+            rebase_distance,
+        }
+    }
+
+    /// The origin's position in world coordinates.
+    pub fn origin(&self) -> DVec3 {
+        self.origin
+    }
+
+    /// Convert a world position to f32 coordinates relative to the origin.
+    pub fn to_local(&self, position: &DVec3) -> Vec3 {
+        camera_relative(position, &self.origin)
+    }
+
+    /// Convert coordinates relative to the origin back to a world position.
+    pub fn to_world(&self, local: &Vec3) -> DVec3 {
+        self.origin + local.map(|value| value as f64)
+    }
+
+    /// Move the origin to the camera if the camera is too far from it.
+    ///
+    /// Returns the distance the origin moved, in the old local coordinates,
+    /// or None when the origin didn't move.
+    pub fn update(&mut self, camera_position: &DVec3) -> Option<Vec3> {
+        let offset = camera_position - self.origin;
+        if offset.norm() <= self.rebase_distance {
+            return None;
+        }
+        self.origin = *camera_position;
+        Some(offset.map(|value| value as f32))
+    }
+}
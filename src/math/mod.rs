@@ -1,8 +1,37 @@
 //! Mathematical primitives and operations.
 
+pub mod easing;
+mod interpolation;
+mod intersection;
+mod jitter;
+mod large_world;
+mod projection;
+mod spring;
+
 use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
 
+pub use self::{
+    interpolation::{
+        inverse_lerp, lerp, remap, smootherstep, smoothstep, Lerp,
+    },
+    intersection::{
+        closest_point_on_segment, cross_2d, point_in_polygon,
+        segment_intersection,
+    },
+    jitter::{halton, jitter_projection, taa_jitter, JITTER_SEQUENCE_LENGTH},
+    large_world::{
+        camera_relative, look_at_camera_relative, split_f64, SplitVec3,
+        WorldOrigin,
+    },
+    projection::{
+        inverse, look_at, normal_matrix, ortho_projection, perspective,
+        transpose,
+    },
+    spring::Spring,
+};
+
 pub type Mat4 = Matrix4<f32>;
 pub type Vec2 = Vector2<f32>;
 pub type Vec3 = Vector3<f32>;
 pub type Vec4 = Vector4<f32>;
+pub type DVec3 = Vector3<f64>;
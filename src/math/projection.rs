@@ -0,0 +1,116 @@
+use super::{Mat4, Vec3};
+
+// All projections follow Vulkan's conventions: view space is right-handed
+// with the camera looking down -Z, clip space Y points down, and depth goes
+// from 0 at the near plane to 1 at the far plane.
+
+/// An orthographic projection which maps the given view volume to clip
+/// space.
+pub fn ortho_projection(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> Mat4 {
+    let width = right - left;
+    let height = top - bottom;
+    let depth = far - near;
+    Mat4::new(
+        2.0 / width,
+        0.0,
+        0.0,
+        -(right + left) / width,
+        0.0,
+        -2.0 / height,
+        0.0,
+        (top + bottom) / height,
+        0.0,
+        0.0,
+        -1.0 / depth,
+        -near / depth,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    )
+}
+
+/// A perspective projection.
+///
+/// # Params
+///
+/// * `fov_y` - the vertical field of view in radians
+/// * `aspect` - the viewport's width divided by its height
+/// * `near` - the distance to the near clipping plane, must be > 0
+/// * `far` - the distance to the far clipping plane
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let focal_length = 1.0 / (fov_y / 2.0).tan();
+    let depth = near - far;
+    Mat4::new(
+        focal_length / aspect,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        -focal_length,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        far / depth,
+        near * far / depth,
+        0.0,
+        0.0,
+        -1.0,
+        0.0,
+    )
+}
+
+/// A view matrix for a camera at `eye` looking toward `target`.
+///
+/// `up` is the approximate up direction and must not be parallel to the
+/// view direction.
+pub fn look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Mat4 {
+    let forward = (target - eye).normalize();
+    let right = forward.cross(up).normalize();
+    let up = right.cross(&forward);
+    Mat4::new(
+        right.x,
+        right.y,
+        right.z,
+        -right.dot(eye),
+        up.x,
+        up.y,
+        up.z,
+        -up.dot(eye),
+        -forward.x,
+        -forward.y,
+        -forward.z,
+        forward.dot(eye),
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    )
+}
+
+/// The inverse of a matrix, or None if the matrix is not invertible.
+pub fn inverse(matrix: &Mat4) -> Option<Mat4> {
+    matrix.try_inverse()
+}
+
+/// The transpose of a matrix.
+pub fn transpose(matrix: &Mat4) -> Mat4 {
+    matrix.transpose()
+}
+
+/// The matrix for transforming normals by `model`: the inverse transpose.
+///
+/// Falls back to the identity when `model` is not invertible.
+pub fn normal_matrix(model: &Mat4) -> Mat4 {
+    inverse(model)
+        .map(|inverse| inverse.transpose())
+        .unwrap_or_else(Mat4::identity)
+}
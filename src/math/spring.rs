@@ -0,0 +1,75 @@
+use {super::Lerp, std::f32::consts::PI};
+
+/// A damped spring which pulls a value toward a target.
+///
+/// Springs are a frame-rate independent way to smooth out motion, e.g. to
+/// make a point follow the mouse. Works with f32, Vec2, Vec3, or any other
+/// type which can be lerped.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Spring<T: Lerp> {
+    pub position: T,
+    pub velocity: T,
+    pub target: T,
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+impl<T: Lerp> Spring<T> {
+    /// Create a spring at rest at `position`.
+    ///
+    /// # Params
+    ///
+    /// * `position` - the initial position and target
+    /// * `stiffness` - how strongly the spring pulls toward the target
+    /// * `damping` - how strongly velocity is resisted
+    pub fn new(position: T, stiffness: f32, damping: f32) -> Self {
+        Self {
+            position,
+            velocity: position * 0.0,
+            target: position,
+            stiffness,
+            damping,
+        }
+    }
+
+    /// Create a spring from more intuitive parameters.
+    ///
+    /// # Params
+    ///
+    /// * `position` - the initial position and target
+    /// * `frequency` - the spring's natural oscillation frequency in hertz
+    /// * `damping_ratio` - 1.0 is critically damped and settles as quickly as
+    ///   possible without overshooting. Values below 1.0 bounce and values
+    ///   above 1.0 settle slowly.
+    pub fn with_frequency(
+        position: T,
+        frequency: f32,
+        damping_ratio: f32,
+    ) -> Self {
+        let omega = 2.0 * PI * frequency;
+        Self::new(position, omega * omega, 2.0 * damping_ratio * omega)
+    }
+
+    /// Advance the simulation by `dt` seconds with semi-implicit Euler
+    /// integration.
+    ///
+    /// Large time steps are split into smaller ones to keep stiff springs
+    /// stable. Time steps longer than a quarter second, like the one after
+    /// the window was dragged or minimized, are shortened so a single call
+    /// never runs more than a few dozen substeps. Negative and NaN time
+    /// steps do nothing.
+    pub fn step(&mut self, dt: f32) -> T {
+        const MAX_STEP: f32 = 1.0 / 120.0;
+        const MAX_DT: f32 = 0.25;
+        let mut remaining = dt.max(0.0).min(MAX_DT);
+        while remaining > 0.0 {
+            let step = remaining.min(MAX_STEP);
+            let acceleration = (self.target - self.position) * self.stiffness
+                - self.velocity * self.damping;
+            self.velocity = self.velocity + acceleration * step;
+            self.position = self.position + self.velocity * step;
+            remaining -= step;
+        }
+        self.position
+    }
+}
@@ -0,0 +1,15 @@
+//! Tunable sketch parameters.
+//!
+//! Sketches declare parameters (float ranges, colors, bools, and choices) in
+//! `State::new` with a ParamStore. The store can map every parameter to an
+//! OSC address for live control and persist values to a TOML file next to
+//! the sketch so a good configuration survives restarts.
+
+mod param;
+mod persistence;
+mod store;
+
+pub use self::{
+    param::{Param, ParamKind, ParamValue},
+    store::ParamStore,
+};
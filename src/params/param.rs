@@ -0,0 +1,58 @@
+/// The kind of a tunable parameter and its constraints.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamKind {
+    /// A float in `[min, max]`.
+    Float { min: f32, max: f32 },
+
+    /// An RGBA color with components in `[0, 1]`.
+    Color,
+
+    /// An on/off toggle.
+    Bool,
+
+    /// One of a fixed list of named options.
+    Choice { options: Vec<String> },
+}
+
+/// The current value of a parameter.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParamValue {
+    Float(f32),
+    Color([f32; 4]),
+    Bool(bool),
+
+    /// The index of the selected option.
+    Choice(usize),
+}
+
+/// A named, tunable parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamKind,
+    pub value: ParamValue,
+    pub default: ParamValue,
+}
+
+impl Param {
+    /// Coerce a value to fit this parameter's kind and constraints.
+    ///
+    /// Returns None when the value is the wrong type for the parameter.
+    pub fn constrain(&self, value: ParamValue) -> Option<ParamValue> {
+        match (&self.kind, value) {
+            (ParamKind::Float { min, max }, ParamValue::Float(value)) => {
+                Some(ParamValue::Float(value.clamp(*min, *max)))
+            }
+            (ParamKind::Color, ParamValue::Color(color)) => {
+                Some(ParamValue::Color(color.map(|c| c.clamp(0.0, 1.0))))
+            }
+            (ParamKind::Bool, ParamValue::Bool(value)) => {
+                Some(ParamValue::Bool(value))
+            }
+            (ParamKind::Choice { options }, ParamValue::Choice(index)) => Some(
+                ParamValue::Choice(index.min(options.len().saturating_sub(1))),
+            ),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use {
+    super::{ParamKind, ParamStore, ParamValue},
+    anyhow::{Context, Result},
+    std::path::{Path, PathBuf},
+};
+
+// Public API
+// ----------
+
+impl ParamStore {
+    /// Use `path` for `save` and `load`.
+    ///
+    /// Sketches typically keep the file next to their source, e.g.
+    /// `examples/my_sketch.params.toml`.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// The file used by `save` and `load`, if any.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Save every parameter's value to the store's file.
+    pub fn save(&self) -> Result<()> {
+        let path = self
+            .file
+            .as_ref()
+            .context("The parameter store has no file to save to")?;
+        self.save_toml(path)
+    }
+
+    /// Load parameter values from the store's file.
+    ///
+    /// # Returns
+    ///
+    /// Returns false without changing any values when there is no file yet.
+    pub fn load(&mut self) -> Result<bool> {
+        let path = self
+            .file
+            .clone()
+            .context("The parameter store has no file to load from")?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        self.load_toml(path)?;
+        Ok(true)
+    }
+
+    /// Save every parameter's value to a TOML file.
+    ///
+    /// Floats and bools are stored as-is, colors are stored as 4-element
+    /// arrays, and choices are stored by option name so the file stays
+    /// readable when options are reordered.
+    pub fn save_toml(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_toml()?).with_context(|| {
+            format!("Unable to write parameters to {:?}", path)
+        })?;
+        log::info!("Saved parameters to {:?}", path);
+        Ok(())
+    }
+
+    /// Load parameter values from a TOML file written by `save_toml`.
+    ///
+    /// Values for undeclared parameters, or with the wrong type, are logged
+    /// and skipped so a stale file never prevents a sketch from starting.
+    pub fn load_toml(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|| {
+            format!("Unable to read parameters from {:?}", path)
+        })?;
+        self.apply_toml(&text)
+            .with_context(|| format!("Invalid parameter file {:?}", path))?;
+        log::info!("Loaded parameters from {:?}", path);
+        Ok(())
+    }
+}
+
+// Private API
+// -----------
+
+impl ParamStore {
+    /// Serialize every parameter's value as a TOML table.
+    fn to_toml(&self) -> Result<String> {
+        let mut table = toml::Table::new();
+        for param in self.iter() {
+            let value = match (&param.kind, param.value) {
+                (_, ParamValue::Float(value)) => {
+                    toml::Value::Float(value as f64)
+                }
+                (_, ParamValue::Bool(value)) => toml::Value::Boolean(value),
+                (_, ParamValue::Color(color)) => toml::Value::Array(
+                    color
+                        .iter()
+                        .map(|&c| toml::Value::Float(c as f64))
+                        .collect(),
+                ),
+                (ParamKind::Choice { options }, ParamValue::Choice(index)) => {
+                    toml::Value::String(options[index].clone())
+                }
+                (_, ParamValue::Choice(index)) => {
+                    toml::Value::Integer(index as i64)
+                }
+            };
+            table.insert(param.name.clone(), value);
+        }
+        toml::to_string(&table).context("Unable to serialize parameters")
+    }
+
+    /// Parse a TOML table and apply each value to its parameter.
+    fn apply_toml(&mut self, text: &str) -> Result<()> {
+        let table: toml::Table = text.parse()?;
+        for (name, value) in &table {
+            let param_value = match self.get(name) {
+                Some(param) => Self::parse_toml_value(&param.kind, value),
+                None => {
+                    log::warn!("Ignoring undeclared parameter {}", name);
+                    continue;
+                }
+            };
+            match param_value {
+                Some(param_value) => {
+                    self.set(name, param_value);
+                }
+                None => {
+                    log::warn!("Ignoring invalid value for {}: {}", name, value)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert a TOML value to a parameter value of the given kind.
+    fn parse_toml_value(
+        kind: &ParamKind,
+        value: &toml::Value,
+    ) -> Option<ParamValue> {
+        let as_f32 = |value: &toml::Value| match value {
+            toml::Value::Float(value) => Some(*value as f32),
+            toml::Value::Integer(value) => Some(*value as f32),
+            _ => None,
+        };
+        match kind {
+            ParamKind::Float { .. } => as_f32(value).map(ParamValue::Float),
+            ParamKind::Bool => value.as_bool().map(ParamValue::Bool),
+            ParamKind::Color => {
+                let components = value.as_array()?;
+                if components.len() != 4 {
+                    return None;
+                }
+                let mut color = [0.0; 4];
+                for (c, component) in color.iter_mut().zip(components) {
+                    *c = as_f32(component)?;
+                }
+                Some(ParamValue::Color(color))
+            }
+            ParamKind::Choice { options } => match value {
+                toml::Value::String(name) => options
+                    .iter()
+                    .position(|option| option == name)
+                    .map(ParamValue::Choice),
+                toml::Value::Integer(index) if *index >= 0 => {
+                    Some(ParamValue::Choice(*index as usize))
+                }
+                _ => None,
+            },
+        }
+    }
+}
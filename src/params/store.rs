@@ -0,0 +1,334 @@
+use {
+    super::{Param, ParamKind, ParamValue},
+    crate::interop::osc::{OscParam, Params},
+    std::{collections::HashMap, path::PathBuf},
+};
+
+/// A collection of named, tunable parameters.
+///
+/// Declare parameters in `State::new`, then read them each frame. Values can
+/// be driven over OSC with `osc_mappings` and `apply_osc`, and saved to or
+/// loaded from a TOML file with `with_file`, `save`, and `load`.
+#[derive(Debug, Clone, Default)]
+pub struct ParamStore {
+    params: Vec<Param>,
+    indices: HashMap<String, usize>,
+    last_osc: Params,
+    pub(super) file: Option<PathBuf>,
+}
+
+// Public API
+// ----------
+
+impl ParamStore {
+    /// Create an empty parameter store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a float parameter in `[min, max]`.
+    pub fn add_float(
+        &mut self,
+        name: impl Into<String>,
+        min: f32,
+        max: f32,
+        default: f32,
+    ) -> &mut Self {
+        let (min, max) = (min.min(max), min.max(max));
+        self.add(
+            name.into(),
+            ParamKind::Float { min, max },
+            ParamValue::Float(default.clamp(min, max)),
+        )
+    }
+
+    /// Declare an RGBA color parameter.
+    pub fn add_color(
+        &mut self,
+        name: impl Into<String>,
+        default: [f32; 4],
+    ) -> &mut Self {
+        self.add(name.into(), ParamKind::Color, ParamValue::Color(default))
+    }
+
+    /// Declare an on/off parameter.
+    pub fn add_bool(
+        &mut self,
+        name: impl Into<String>,
+        default: bool,
+    ) -> &mut Self {
+        self.add(name.into(), ParamKind::Bool, ParamValue::Bool(default))
+    }
+
+    /// Declare a parameter which selects one of several named options.
+    ///
+    /// A choice needs at least one option. Declarations without any are
+    /// logged and skipped.
+    pub fn add_choice(
+        &mut self,
+        name: impl Into<String>,
+        options: &[&str],
+        default: usize,
+    ) -> &mut Self {
+        let name = name.into();
+        if options.is_empty() {
+            log::warn!("Ignoring choice parameter {} without options", name);
+            return self;
+        }
+        let options = options.iter().map(|&option| option.to_owned()).collect();
+        self.add(
+            name,
+            ParamKind::Choice { options },
+            ParamValue::Choice(default),
+        )
+    }
+
+    /// Every declared parameter, in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = &Param> + '_ {
+        self.params.iter()
+    }
+
+    /// Get a parameter.
+    pub fn get(&self, name: &str) -> Option<&Param> {
+        self.indices.get(name).map(|&index| &self.params[index])
+    }
+
+    /// Set a parameter's value. The value is clamped to the parameter's
+    /// range.
+    ///
+    /// Returns false if there is no such parameter or the value is the wrong
+    /// type.
+    pub fn set(&mut self, name: &str, value: ParamValue) -> bool {
+        let index = match self.indices.get(name) {
+            Some(&index) => index,
+            None => return false,
+        };
+        let param = &mut self.params[index];
+        match param.constrain(value) {
+            Some(value) => {
+                param.value = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reset every parameter to its default value.
+    pub fn reset(&mut self) {
+        for param in &mut self.params {
+            param.value = param.default;
+        }
+    }
+
+    /// Get a float parameter's value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no float parameter with this name was declared.
+    pub fn float(&self, name: &str) -> f32 {
+        match self.value(name) {
+            ParamValue::Float(value) => value,
+            other => panic!("Parameter {} is not a float: {:?}", name, other),
+        }
+    }
+
+    /// Get a color parameter's value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no color parameter with this name was declared.
+    pub fn color(&self, name: &str) -> [f32; 4] {
+        match self.value(name) {
+            ParamValue::Color(value) => value,
+            other => panic!("Parameter {} is not a color: {:?}", name, other),
+        }
+    }
+
+    /// Get a bool parameter's value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no bool parameter with this name was declared.
+    pub fn bool(&self, name: &str) -> bool {
+        match self.value(name) {
+            ParamValue::Bool(value) => value,
+            other => panic!("Parameter {} is not a bool: {:?}", name, other),
+        }
+    }
+
+    /// Get the index of a choice parameter's selected option.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no choice parameter with this name was declared.
+    pub fn choice(&self, name: &str) -> usize {
+        match self.value(name) {
+            ParamValue::Choice(index) => index,
+            other => panic!("Parameter {} is not a choice: {:?}", name, other),
+        }
+    }
+
+    /// Get the name of a choice parameter's selected option.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no choice parameter with this name was declared.
+    pub fn choice_name(&self, name: &str) -> &str {
+        let index = self.choice(name);
+        match &self.get(name).unwrap().kind {
+            ParamKind::Choice { options } => &options[index],
+            _ => unreachable!(),
+        }
+    }
+
+    /// OSC mappings for every parameter, for use with OscListener::bind.
+    ///
+    /// Each parameter is controlled by `{prefix}/{name}`. Floats accept
+    /// normalized [0, 1] values, bools accept 0 or 1, and choices accept the
+    /// option index. Colors are split into `{prefix}/{name}/r`, `/g`, `/b`,
+    /// and `/a`. The mappings start at the parameters' current values.
+    pub fn osc_mappings(&self, prefix: &str) -> Vec<OscParam> {
+        let mut mappings = vec![];
+        for param in &self.params {
+            let address = format!("{}/{}", prefix, param.name);
+            match (&param.kind, param.value) {
+                (ParamKind::Float { min, max }, ParamValue::Float(value)) => {
+                    mappings.push(
+                        OscParam::new(&param.name, address, *min, *max)
+                            .with_default(value),
+                    );
+                }
+                (ParamKind::Bool, ParamValue::Bool(value)) => {
+                    mappings.push(
+                        OscParam::new(&param.name, address, 0.0, 1.0)
+                            .with_default(value as u32 as f32)
+                            .raw(),
+                    );
+                }
+                (ParamKind::Choice { options }, ParamValue::Choice(index)) => {
+                    let max = options.len().saturating_sub(1) as f32;
+                    mappings.push(
+                        OscParam::new(&param.name, address, 0.0, max)
+                            .with_default(index as f32)
+                            .raw(),
+                    );
+                }
+                (ParamKind::Color, ParamValue::Color(color)) => {
+                    for (channel, value) in
+                        Self::COLOR_CHANNELS.iter().zip(color)
+                    {
+                        mappings.push(
+                            OscParam::new(
+                                format!("{}.{}", param.name, channel),
+                                format!("{}/{}", address, channel),
+                                0.0,
+                                1.0,
+                            )
+                            .with_default(value),
+                        );
+                    }
+                }
+                _ => (),
+            }
+        }
+        mappings
+    }
+
+    /// Apply values received over OSC.
+    ///
+    /// Only values which changed since the previous call are applied, so
+    /// parameters set by code or loaded from a file aren't overwritten by
+    /// stale OSC values every frame.
+    pub fn apply_osc(&mut self, osc: &Params) {
+        for index in 0..self.params.len() {
+            let name = self.params[index].name.clone();
+            let value =
+                match (&self.params[index].kind, self.params[index].value) {
+                    (ParamKind::Float { .. }, _) => {
+                        self.osc_changed(osc, &name).map(ParamValue::Float)
+                    }
+                    (ParamKind::Bool, _) => self
+                        .osc_changed(osc, &name)
+                        .map(|value| ParamValue::Bool(value >= 0.5)),
+                    (ParamKind::Choice { .. }, _) => {
+                        self.osc_changed(osc, &name).map(|value| {
+                            ParamValue::Choice(value.round() as usize)
+                        })
+                    }
+                    (ParamKind::Color, ParamValue::Color(mut color)) => {
+                        let mut changed = false;
+                        for (channel, component) in
+                            Self::COLOR_CHANNELS.iter().zip(color.iter_mut())
+                        {
+                            let channel_name = format!("{}.{}", name, channel);
+                            if let Some(value) =
+                                self.osc_changed(osc, &channel_name)
+                            {
+                                *component = value;
+                                changed = true;
+                            }
+                        }
+                        changed.then(|| ParamValue::Color(color))
+                    }
+                    _ => None,
+                };
+            if let Some(value) = value {
+                self.set(&name, value);
+            }
+        }
+        self.last_osc = osc.clone();
+    }
+}
+
+// Private API
+// -----------
+
+impl ParamStore {
+    const COLOR_CHANNELS: [&'static str; 4] = ["r", "g", "b", "a"];
+
+    fn add(
+        &mut self,
+        name: String,
+        kind: ParamKind,
+        default: ParamValue,
+    ) -> &mut Self {
+        let mut param = Param {
+            name: name.clone(),
+            kind,
+            value: default,
+            default,
+        };
+        if let Some(value) = param.constrain(default) {
+            param.value = value;
+            param.default = value;
+        }
+        match self.indices.get(&name) {
+            Some(&index) => {
+                log::warn!("Parameter {} was declared twice", name);
+                self.params[index] = param;
+            }
+            None => {
+                self.indices.insert(name, self.params.len());
+                self.params.push(param);
+            }
+        }
+        self
+    }
+
+    fn value(&self, name: &str) -> ParamValue {
+        self.get(name)
+            .unwrap_or_else(|| panic!("Parameter {} was never declared", name))
+            .value
+    }
+
+    /// The OSC value for `name` if it changed since the last call to
+    /// `apply_osc`.
+    fn osc_changed(&self, osc: &Params, name: &str) -> Option<f32> {
+        let value = osc.get(name)?;
+        if self.last_osc.get(name) == Some(value) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
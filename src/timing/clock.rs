@@ -0,0 +1,170 @@
+use std::time::Instant;
+
+/// A controllable source of animation time.
+///
+/// A real-time clock advances by the wall-clock time between ticks. A
+/// fixed-step clock advances by exactly one step per tick, no matter how
+/// long each frame takes. Either way, the clock can be paused, stepped one
+/// frame at a time, scaled, or moved to any time.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    time: f64,
+    delta_time: f32,
+    frame: u64,
+    time_scale: f32,
+    paused: bool,
+    pending_steps: u32,
+    scrub_offset: f32,
+    fixed_step: Option<f32>,
+    last_tick: Option<Instant>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Public API
+// ----------
+
+impl Clock {
+    /// The step used by `step` on a real-time clock.
+    pub const DEFAULT_STEP: f32 = 1.0 / 60.0;
+
+    /// Create a clock which follows the wall clock.
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            delta_time: 0.0,
+            frame: 0,
+            time_scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+            scrub_offset: 0.0,
+            fixed_step: None,
+            last_tick: None,
+        }
+    }
+
+    /// Create a clock which advances by exactly `step` seconds every tick.
+    pub fn fixed_step(step: f32) -> Self {
+        Self {
+            fixed_step: Some(step),
+            ..Self::new()
+        }
+    }
+
+    /// The animation time in seconds.
+    pub fn time(&self) -> f32 {
+        self.time as f32
+    }
+
+    /// The animation time, in seconds, which passed during the last tick.
+    ///
+    /// Zero while paused. Negative if the clock was scrubbed backwards.
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    /// The number of ticks since the clock was created, including ticks
+    /// while paused.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns true when the clock is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stop advancing time.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resume advancing time.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    /// Pause if running, resume if paused.
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    /// Advance a paused clock by one step on the next tick.
+    ///
+    /// The step is the fixed step, or DEFAULT_STEP for a real-time clock,
+    /// multiplied by the time scale. Does nothing while running.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// The multiplier applied to elapsed time.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Set the multiplier applied to elapsed time, e.g. 0.25 for slow motion.
+    /// Negative values run time backwards.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale;
+    }
+
+    /// Jump to a specific time.
+    ///
+    /// The jump is reported as the next tick's delta time.
+    pub fn seek(&mut self, time: f32) {
+        self.scrub(time - self.time());
+    }
+
+    /// Move time forward or backward by `offset` seconds.
+    ///
+    /// The offset is reported as part of the next tick's delta time.
+    pub fn scrub(&mut self, offset: f32) {
+        self.time += offset as f64;
+        self.scrub_offset += offset;
+    }
+}
+
+// Private API
+// -----------
+
+impl Clock {
+    /// Advance the clock for a new frame.
+    ///
+    /// Called by the Application before every `State::update`.
+    pub(crate) fn tick(&mut self) {
+        let now = Instant::now();
+        // The first tick starts at time zero for both kinds of clock.
+        let elapsed = match (self.fixed_step, self.last_tick) {
+            (_, None) => 0.0,
+            (Some(step), Some(_)) => step,
+            (None, Some(last_tick)) => (now - last_tick).as_secs_f32(),
+        };
+        self.last_tick = Some(now);
+
+        let advance = if !self.paused {
+            elapsed * self.time_scale
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            self.fixed_step.unwrap_or(Self::DEFAULT_STEP) * self.time_scale
+        } else {
+            0.0
+        };
+        self.time += advance as f64;
+        // Scrubbing since the last tick is already applied to time but is
+        // still reported as part of this tick's delta.
+        self.delta_time = self.scrub_offset + advance;
+        self.scrub_offset = 0.0;
+        self.frame += 1;
+    }
+}
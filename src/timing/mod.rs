@@ -0,0 +1,10 @@
+//! Virtual time for animation.
+//!
+//! The Application owns a Clock and passes it to `State::update`. Sketches
+//! animate with `Clock::time` and `Clock::delta_time` instead of reading the
+//! wall clock, so animation can be paused, single-stepped, slowed down, or
+//! scrubbed while debugging, and rendered at a fixed timestep in batch mode.
+
+mod clock;
+
+pub use self::clock::Clock;